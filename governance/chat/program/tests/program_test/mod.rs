@@ -121,6 +121,7 @@ impl GovernanceChatProgramTest {
             &governing_token_mint_keypair.pubkey(),
             &self.bench.payer.pubkey(),
             None,
+            &spl_token::id(),
             Some(community_token_config_args),
             None,
             name.clone(),
@@ -161,6 +162,7 @@ impl GovernanceChatProgramTest {
                 &self.bench.payer.pubkey(),
                 amount,
                 &governing_token_mint_keypair.pubkey(),
+                &spl_token::id(),
             );
 
             self.bench
@@ -278,6 +280,9 @@ impl GovernanceChatProgramTest {
             options,
             use_deny_option,
             &proposal_seed,
+            None,
+            None,
+            None,
         );
 
         self.bench
@@ -335,6 +340,7 @@ impl GovernanceChatProgramTest {
             &self.bench.payer.pubkey(),
             deposit_amount,
             &proposal_cookie.governing_token_mint,
+            &spl_token::id(),
         );
 
         self.bench
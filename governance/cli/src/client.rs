@@ -0,0 +1,259 @@
+use {
+    crate::Error,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    },
+    solana_sdk::compute_budget::ComputeBudgetInstruction,
+    spl_governance::{
+        instruction::execute_transaction,
+        state::{
+            native_treasury::get_native_treasury_address,
+            proposal_transaction::{estimate_compute_units, ProposalTransactionV2},
+        },
+    },
+};
+
+/// Rebuilds the instructions of a ProposalTransaction the way ExecuteTransaction
+/// invokes them, with the accounts resolved from the stored InstructionData
+///
+/// ExecuteTransaction signs with the governance PDA, and with its native
+/// treasury PDA when referenced, so those are the only signers the instructions
+/// can require. They can't sign a transaction sent by a client, which is why
+/// the instructions must be simulated without signature verification
+pub fn simulate_proposal_execution(
+    program_id: &Pubkey,
+    governance: &Pubkey,
+    proposal_transaction: &ProposalTransactionV2,
+) -> Result<Vec<Instruction>, Error> {
+    let native_treasury = get_native_treasury_address(program_id, governance);
+
+    proposal_transaction
+        .instructions
+        .iter()
+        .map(|instruction_data| {
+            let instruction = Instruction::from(instruction_data);
+            if let Some(signer) = instruction.accounts.iter().find(|account| {
+                account.is_signer
+                    && account.pubkey != *governance
+                    && account.pubkey != native_treasury
+            }) {
+                return Err(format!(
+                    "Instruction for program {} requires signer {}, which the governance can't sign for",
+                    instruction.program_id, signer.pubkey
+                )
+                .into());
+            }
+            Ok(instruction)
+        })
+        .collect()
+}
+
+/// Composes the instructions to execute a ProposalTransaction, preceded by a
+/// compute unit limit for the estimated compute units of the transaction
+///
+/// ExecuteTransaction invokes all the instructions of the transaction and
+/// passes each of them all the accounts, so the program id and accounts of
+/// every instruction follow the ExecuteTransaction accounts. The governance
+/// PDAs sign when the instructions are invoked, not the transaction
+/// Note: Transactions inserted before the estimate was stored are estimated by
+/// the client
+pub fn compose_execute_transaction(
+    program_id: &Pubkey,
+    governance: &Pubkey,
+    proposal: &Pubkey,
+    proposal_transaction_address: &Pubkey,
+    proposal_transaction: &ProposalTransactionV2,
+) -> Result<Vec<Instruction>, Error> {
+    let first_instruction = proposal_transaction
+        .instructions
+        .first()
+        .ok_or("Proposal transaction has no instructions")?;
+
+    let mut instruction_accounts = vec![];
+    for (index, instruction_data) in proposal_transaction.instructions.iter().enumerate() {
+        // The program id of the first instruction is passed by execute_transaction
+        if index > 0 {
+            instruction_accounts.push(AccountMeta::new_readonly(
+                instruction_data.program_id,
+                false,
+            ));
+        }
+        instruction_accounts.extend(instruction_data.accounts.iter().map(|account| AccountMeta {
+            pubkey: account.pubkey,
+            is_signer: false,
+            is_writable: account.is_writable,
+        }));
+    }
+
+    let compute_units = match proposal_transaction.estimated_compute_units {
+        0 => estimate_compute_units(&proposal_transaction.instructions),
+        estimated_compute_units => estimated_compute_units,
+    };
+
+    Ok(vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_units),
+        execute_transaction(
+            program_id,
+            governance,
+            proposal,
+            proposal_transaction_address,
+            &first_instruction.program_id,
+            &instruction_accounts,
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{account::Account, account_info::IntoAccountInfo, system_instruction},
+        spl_governance::state::{
+            enums::GovernanceAccountType,
+            proposal_transaction::{get_proposal_transaction_data, AccountMetaData},
+        },
+    };
+
+    // ProposalTransactionV2 holding a single SPL Token transfer of 1_000_000
+    // from [3; 32] to [4; 32], authorised by the governance [1; 32]
+    const TRANSFER_PROPOSAL_TRANSACTION: &[u8] =
+        include_bytes!("../tests/fixtures/proposal_transaction_transfer.bin");
+
+    fn load_proposal_transaction(program_id: &Pubkey, data: &[u8]) -> ProposalTransactionV2 {
+        let address = Pubkey::new_unique();
+        let mut account = Account {
+            lamports: 1,
+            data: data.to_vec(),
+            owner: *program_id,
+            ..Account::default()
+        };
+        let account_info = (&address, &mut account).into_account_info();
+        get_proposal_transaction_data(program_id, &account_info).unwrap()
+    }
+
+    #[test]
+    fn test_simulate_recorded_proposal_transaction() {
+        let program_id = Pubkey::new_unique();
+        let governance = Pubkey::new_from_array([1; 32]);
+        let proposal_transaction =
+            load_proposal_transaction(&program_id, TRANSFER_PROPOSAL_TRANSACTION);
+        assert_eq!(
+            proposal_transaction.account_type,
+            GovernanceAccountType::ProposalTransactionV2
+        );
+        assert_eq!(
+            proposal_transaction.proposal,
+            Pubkey::new_from_array([2; 32])
+        );
+
+        let instructions =
+            simulate_proposal_execution(&program_id, &governance, &proposal_transaction).unwrap();
+
+        let transfer = spl_token::instruction::transfer(
+            &spl_token::id(),
+            &Pubkey::new_from_array([3; 32]),
+            &Pubkey::new_from_array([4; 32]),
+            &governance,
+            &[],
+            1_000_000,
+        )
+        .unwrap();
+        assert_eq!(instructions, vec![transfer]);
+    }
+
+    #[test]
+    fn test_compose_execute_transaction() {
+        let program_id = Pubkey::new_unique();
+        let governance = Pubkey::new_from_array([1; 32]);
+        let proposal = Pubkey::new_from_array([2; 32]);
+        let proposal_transaction_address = Pubkey::new_unique();
+        let mut proposal_transaction =
+            load_proposal_transaction(&program_id, TRANSFER_PROPOSAL_TRANSACTION);
+        // recorded before the estimate was stored
+        assert_eq!(proposal_transaction.estimated_compute_units, 0);
+
+        let instructions = compose_execute_transaction(
+            &program_id,
+            &governance,
+            &proposal,
+            &proposal_transaction_address,
+            &proposal_transaction,
+        )
+        .unwrap();
+
+        let execute_transaction_instruction = execute_transaction(
+            &program_id,
+            &governance,
+            &proposal,
+            &proposal_transaction_address,
+            &spl_token::id(),
+            &[
+                AccountMeta::new(Pubkey::new_from_array([3; 32]), false),
+                AccountMeta::new(Pubkey::new_from_array([4; 32]), false),
+                AccountMeta::new_readonly(governance, false),
+            ],
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(estimate_compute_units(
+                    &proposal_transaction.instructions
+                )),
+                execute_transaction_instruction.clone(),
+            ]
+        );
+
+        // the stored estimate is used once recorded
+        proposal_transaction.estimated_compute_units = 300_000;
+        let instructions = compose_execute_transaction(
+            &program_id,
+            &governance,
+            &proposal,
+            &proposal_transaction_address,
+            &proposal_transaction,
+        )
+        .unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                execute_transaction_instruction,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simulate_native_treasury_signer() {
+        let program_id = Pubkey::new_unique();
+        let governance = Pubkey::new_unique();
+        let native_treasury = get_native_treasury_address(&program_id, &governance);
+        let mut proposal_transaction =
+            load_proposal_transaction(&program_id, TRANSFER_PROPOSAL_TRANSACTION);
+        let instruction = system_instruction::transfer(&native_treasury, &Pubkey::new_unique(), 1);
+        proposal_transaction.instructions = vec![instruction.clone().into()];
+
+        let instructions =
+            simulate_proposal_execution(&program_id, &governance, &proposal_transaction).unwrap();
+
+        assert_eq!(instructions, vec![instruction]);
+    }
+
+    #[test]
+    fn test_simulate_foreign_signer_error() {
+        let program_id = Pubkey::new_unique();
+        let governance = Pubkey::new_from_array([1; 32]);
+        let mut proposal_transaction =
+            load_proposal_transaction(&program_id, TRANSFER_PROPOSAL_TRANSACTION);
+        // the transfer is authorised by an account other than the governance
+        proposal_transaction.instructions[0].accounts[2] = AccountMetaData {
+            pubkey: Pubkey::new_unique(),
+            is_signer: true,
+            is_writable: false,
+        };
+
+        assert!(
+            simulate_proposal_execution(&program_id, &governance, &proposal_transaction).is_err()
+        );
+    }
+}
@@ -0,0 +1,1174 @@
+#![allow(clippy::arithmetic_side_effects)]
+mod client;
+mod output;
+
+use {
+    crate::{
+        client::{compose_execute_transaction, simulate_proposal_execution},
+        output::{CliGovernanceCommand, CliProposal, CliProposals},
+    },
+    clap::{
+        crate_description, crate_name, crate_version, value_t, App, AppSettings, Arg, ArgMatches,
+        SubCommand,
+    },
+    solana_clap_utils::{
+        fee_payer::fee_payer_arg,
+        input_parsers::{pubkey_of, value_of, values_of},
+        input_validators::{is_amount, is_parsable, is_pubkey, is_url, is_valid_signer},
+        keypair::signer_from_path,
+    },
+    solana_cli_output::OutputFormat,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+        rpc_request::MAX_MULTIPLE_ACCOUNTS,
+    },
+    solana_program::{native_token::lamports_to_sol, program_pack::Pack, pubkey::Pubkey},
+    solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_sdk::{
+        account_info::IntoAccountInfo,
+        commitment_config::CommitmentConfig,
+        instruction::Instruction,
+        message::Message,
+        signature::{Keypair, Signer},
+        signer::unique_signers,
+        transaction::Transaction,
+    },
+    spl_governance::{
+        instruction::{
+            cancel_proposal, cast_vote, create_proposal, create_realm, deposit_governing_tokens,
+            sign_off_proposal,
+        },
+        state::{
+            enums::{GovernanceAccountType, MintMaxVoterWeightSource},
+            governance::{get_governance_data, GovernanceV2},
+            proposal::{get_proposal_address, get_proposal_data, ProposalV2, VoteType},
+            proposal_category_record::{
+                get_proposal_category_record_data, PROPOSAL_CATEGORY_RECORD_CATEGORY_OFFSET,
+                PROPOSAL_CATEGORY_RECORD_GOVERNANCE_OFFSET,
+            },
+            proposal_transaction::{
+                get_proposal_transaction_address, get_proposal_transaction_data,
+            },
+            realm::get_realm_address,
+            token_owner_record::get_token_owner_record_address,
+            vote_record::{Vote, VoteChoice},
+        },
+    },
+    spl_token::{state::Mint, ui_amount_to_amount},
+    std::{process::exit, rc::Rc},
+};
+
+struct Config {
+    rpc_client: RpcClient,
+    fee_payer: Box<dyn Signer>,
+    governance_program_id: Pubkey,
+    verbose: bool,
+    dry_run: bool,
+    output_format: OutputFormat,
+}
+
+type Error = Box<dyn std::error::Error>;
+type CommandResult = Result<(), Error>;
+
+const GOVERNANCE_PROGRAM_ID: &str = "GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw";
+
+fn main() {
+    solana_logger::setup_with_default("solana=info");
+
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg({
+            let arg = Arg::with_name("config_file")
+                .short("C")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Configuration file to use");
+            if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
+                arg.default_value(config_file)
+            } else {
+                arg
+            }
+        })
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(is_url)
+                .help("JSON RPC URL for the cluster.  Default from the configuration file."),
+        )
+        .arg(
+            fee_payer_arg()
+                .short("p")
+                .global(true)
+        )
+        .arg(
+            Arg::with_name("governance_program_id")
+                .long("program")
+                .validator(is_pubkey)
+                .value_name("PUBKEY")
+                .takes_value(true)
+                .required(true)
+                .default_value(GOVERNANCE_PROGRAM_ID)
+                .help("Governance program ID"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .takes_value(false)
+                .global(true)
+                .help("Show additional information"),
+        )
+        .arg(
+            Arg::with_name("output_format")
+                .long("output")
+                .value_name("FORMAT")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["json", "json-compact"])
+                .help("Return information in specified output format"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .takes_value(false)
+                .global(true)
+                .help("Simulate transaction instead of executing"),
+        )
+        .subcommand(
+            SubCommand::with_name("create-realm")
+                .about("Create a new realm")
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Name of the realm, used to derive the realm address"),
+                )
+                .arg(
+                    Arg::with_name("community_mint")
+                        .long("community-mint")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Community governing token mint"),
+                )
+                .arg(
+                    Arg::with_name("council_mint")
+                        .long("council-mint")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Optional council governing token mint"),
+                )
+                .arg(
+                    Arg::with_name("realm_authority")
+                        .long("realm-authority")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Realm authority. Defaults to the fee payer"),
+                )
+                .arg(
+                    Arg::with_name("min_community_weight_to_create_governance")
+                        .long("min-community-weight-to-create-governance")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("1")
+                        .help("Min community token weight required to create a governance"),
+                )
+                .arg(
+                    Arg::with_name("community_mint_max_voter_weight_percentage")
+                        .long("community-mint-max-voter-weight-percentage")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("100")
+                        .help("Percentage of the community mint supply used as max voter weight: (0, 100]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("deposit-governing-tokens")
+                .about("Deposit governing tokens into a realm")
+                .arg(
+                    Arg::with_name("realm")
+                        .long("realm")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Realm address"),
+                )
+                .arg(
+                    Arg::with_name("governing_token_mint")
+                        .long("mint")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Governing token mint (community or council)"),
+                )
+                .arg(
+                    Arg::with_name("governing_token_source")
+                        .long("source")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to deposit governing tokens from"),
+                )
+                .arg(
+                    Arg::with_name("governing_token_owner")
+                        .long("owner")
+                        .validator(is_valid_signer)
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .help("Owner of the source account and of the token owner record. Defaults to the fee payer"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .validator(is_amount)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of governing tokens to deposit"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("create-proposal")
+                .about("Create a new proposal for a governance")
+                .arg(
+                    Arg::with_name("governance")
+                        .long("governance")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Governance address"),
+                )
+                .arg(
+                    Arg::with_name("governing_token_mint")
+                        .long("mint")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Governing token mint the proposal is voted on with"),
+                )
+                .arg(
+                    Arg::with_name("proposal_owner")
+                        .long("owner")
+                        .validator(is_valid_signer)
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .help("Owner of the token owner record creating the proposal. Defaults to the fee payer"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Name of the proposal"),
+                )
+                .arg(
+                    Arg::with_name("description_link")
+                        .long("description")
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .default_value("")
+                        .help("Link to a gist document describing the proposal"),
+                )
+                .arg(
+                    Arg::with_name("options")
+                        .long("option")
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .default_value("Approve")
+                        .help("Proposal option, can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("category")
+                        .long("category")
+                        .validator(is_valid_category)
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .help("Category tag of the proposal for off-chain filtering, at most 8 bytes"),
+                )
+                .arg(
+                    Arg::with_name("sign_off")
+                        .long("sign-off")
+                        .takes_value(false)
+                        .help("Sign off the proposal and start voting, the owner must be the sole signatory"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-proposals")
+                .about("List the proposals of a governance")
+                .arg(
+                    Arg::with_name("governance")
+                        .long("governance")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Governance address"),
+                )
+                .arg(
+                    Arg::with_name("category")
+                        .long("category")
+                        .validator(is_valid_category)
+                        .value_name("STRING")
+                        .takes_value(true)
+                        .help("Only list proposals tagged with this category"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sign-off-proposal")
+                .about("Sign off a proposal")
+                .arg(
+                    Arg::with_name("proposal")
+                        .long("proposal")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposal address"),
+                )
+                .arg(
+                    Arg::with_name("signatory")
+                        .long("signatory")
+                        .validator(is_valid_signer)
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .help("Signatory, or the proposal owner if no signatories were added. Defaults to the fee payer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cast-vote")
+                .about("Cast a vote on a proposal")
+                .arg(
+                    Arg::with_name("proposal")
+                        .long("proposal")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposal address"),
+                )
+                .arg(
+                    Arg::with_name("vote")
+                        .long("vote")
+                        .value_name("VOTE")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(&["yes", "no"])
+                        .help("Approve (yes) or deny (no) the proposal"),
+                )
+                .arg(
+                    Arg::with_name("voter")
+                        .long("voter")
+                        .validator(is_valid_signer)
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .help("Owner of the voting token owner record. Defaults to the fee payer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cancel-proposal")
+                .about("Cancel a proposal")
+                .arg(
+                    Arg::with_name("proposal")
+                        .long("proposal")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposal address"),
+                )
+                .arg(
+                    Arg::with_name("proposal_owner")
+                        .long("owner")
+                        .validator(is_valid_signer)
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .help("Owner of the proposal token owner record. Defaults to the fee payer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("simulate-proposal")
+                .about("Simulate the execution of the transactions of a proposal")
+                .arg(
+                    Arg::with_name("proposal")
+                        .long("proposal")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposal address"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("execute-proposal")
+                .about("Execute the transactions of a proposal which aren't executed yet")
+                .arg(
+                    Arg::with_name("proposal")
+                        .long("proposal")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposal address"),
+                ),
+        )
+        .get_matches();
+
+    let mut wallet_manager = None;
+    let config = {
+        let cli_config = if let Some(config_file) = matches.value_of("config_file") {
+            solana_cli_config::Config::load(config_file).unwrap_or_default()
+        } else {
+            solana_cli_config::Config::default()
+        };
+        let json_rpc_url = value_t!(matches, "json_rpc_url", String)
+            .unwrap_or_else(|_| cli_config.json_rpc_url.clone());
+
+        let fee_payer = signer_from_path(
+            &matches,
+            matches
+                .value_of("fee_payer")
+                .unwrap_or(&cli_config.keypair_path),
+            "fee_payer",
+            &mut wallet_manager,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
+
+        let governance_program_id = pubkey_of(&matches, "governance_program_id").unwrap();
+        let verbose = matches.is_present("verbose");
+        let output_format = matches
+            .value_of("output_format")
+            .map(|value| match value {
+                "json" => OutputFormat::Json,
+                "json-compact" => OutputFormat::JsonCompact,
+                _ => unreachable!(),
+            })
+            .unwrap_or(if verbose {
+                OutputFormat::DisplayVerbose
+            } else {
+                OutputFormat::Display
+            });
+        let dry_run = matches.is_present("dry_run");
+
+        Config {
+            rpc_client: RpcClient::new_with_commitment(json_rpc_url, CommitmentConfig::confirmed()),
+            fee_payer,
+            governance_program_id,
+            verbose,
+            dry_run,
+            output_format,
+        }
+    };
+
+    let _ = match matches.subcommand() {
+        ("create-realm", Some(arg_matches)) => {
+            let name = value_t!(arg_matches, "name", String).unwrap();
+            let community_mint = pubkey_of(arg_matches, "community_mint").unwrap();
+            let council_mint = pubkey_of(arg_matches, "council_mint");
+            let realm_authority = pubkey_of(arg_matches, "realm_authority")
+                .unwrap_or_else(|| config.fee_payer.pubkey());
+            let min_community_weight_to_create_governance =
+                value_of(arg_matches, "min_community_weight_to_create_governance").unwrap();
+            let max_voter_weight_percentage: u8 =
+                value_of(arg_matches, "community_mint_max_voter_weight_percentage").unwrap();
+
+            command_create_realm(
+                &config,
+                name,
+                community_mint,
+                council_mint,
+                realm_authority,
+                min_community_weight_to_create_governance,
+                MintMaxVoterWeightSource::SupplyFraction(
+                    MintMaxVoterWeightSource::SUPPLY_FRACTION_BASE / 100
+                        * max_voter_weight_percentage as u64,
+                ),
+            )
+        }
+        ("deposit-governing-tokens", Some(arg_matches)) => {
+            let realm = pubkey_of(arg_matches, "realm").unwrap();
+            let governing_token_mint = pubkey_of(arg_matches, "governing_token_mint").unwrap();
+            let governing_token_source = pubkey_of(arg_matches, "governing_token_source").unwrap();
+            let governing_token_owner =
+                signer_of_or_exit(arg_matches, "governing_token_owner", &mut wallet_manager);
+            let ui_amount = value_of(arg_matches, "amount").unwrap();
+
+            command_deposit_governing_tokens(
+                &config,
+                realm,
+                governing_token_mint,
+                governing_token_source,
+                governing_token_owner,
+                ui_amount,
+            )
+        }
+        ("create-proposal", Some(arg_matches)) => {
+            let governance = pubkey_of(arg_matches, "governance").unwrap();
+            let governing_token_mint = pubkey_of(arg_matches, "governing_token_mint").unwrap();
+            let proposal_owner =
+                signer_of_or_exit(arg_matches, "proposal_owner", &mut wallet_manager);
+            let name = value_t!(arg_matches, "name", String).unwrap();
+            let description_link = value_t!(arg_matches, "description_link", String).unwrap();
+            let options = values_of(arg_matches, "options").unwrap();
+            let category = category_of(arg_matches, "category");
+            let sign_off = arg_matches.is_present("sign_off");
+
+            command_create_proposal(
+                &config,
+                governance,
+                governing_token_mint,
+                proposal_owner,
+                name,
+                description_link,
+                options,
+                category,
+                sign_off,
+            )
+        }
+        ("list-proposals", Some(arg_matches)) => {
+            let governance = pubkey_of(arg_matches, "governance").unwrap();
+            let category = category_of(arg_matches, "category");
+
+            command_list_proposals(&config, governance, category)
+        }
+        ("sign-off-proposal", Some(arg_matches)) => {
+            let proposal = pubkey_of(arg_matches, "proposal").unwrap();
+            let signatory = signer_of_or_exit(arg_matches, "signatory", &mut wallet_manager);
+
+            command_sign_off_proposal(&config, proposal, signatory)
+        }
+        ("cast-vote", Some(arg_matches)) => {
+            let proposal = pubkey_of(arg_matches, "proposal").unwrap();
+            let vote = match arg_matches.value_of("vote").unwrap() {
+                "yes" => Vote::Approve(vec![VoteChoice {
+                    rank: 0,
+                    weight_percentage: 100,
+                }]),
+                "no" => Vote::Deny,
+                _ => unreachable!(),
+            };
+            let voter = signer_of_or_exit(arg_matches, "voter", &mut wallet_manager);
+
+            command_cast_vote(&config, proposal, vote, voter)
+        }
+        ("cancel-proposal", Some(arg_matches)) => {
+            let proposal = pubkey_of(arg_matches, "proposal").unwrap();
+            let proposal_owner =
+                signer_of_or_exit(arg_matches, "proposal_owner", &mut wallet_manager);
+
+            command_cancel_proposal(&config, proposal, proposal_owner)
+        }
+        ("simulate-proposal", Some(arg_matches)) => {
+            let proposal = pubkey_of(arg_matches, "proposal").unwrap();
+
+            command_simulate_proposal(&config, proposal)
+        }
+        ("execute-proposal", Some(arg_matches)) => {
+            let proposal = pubkey_of(arg_matches, "proposal").unwrap();
+
+            command_execute_proposal(&config, proposal)
+        }
+        _ => unreachable!(),
+    }
+    .map_err(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+}
+
+// COMMANDS
+
+fn command_create_realm(
+    config: &Config,
+    name: String,
+    community_mint: Pubkey,
+    council_mint: Option<Pubkey>,
+    realm_authority: Pubkey,
+    min_community_weight_to_create_governance: u64,
+    community_mint_max_voter_weight_source: MintMaxVoterWeightSource,
+) -> CommandResult {
+    let realm_address = get_realm_address(&config.governance_program_id, &name);
+    let mut output = CliGovernanceCommand::new("create-realm", Some(realm_address.to_string()));
+
+    // The holding accounts are created by the token program owning the mints
+    let token_program = config.rpc_client.get_account(&community_mint)?.owner;
+
+    let instruction = create_realm(
+        &config.governance_program_id,
+        &realm_authority,
+        &community_mint,
+        &config.fee_payer.pubkey(),
+        council_mint,
+        &token_program,
+        None,
+        None,
+        name,
+        min_community_weight_to_create_governance,
+        community_mint_max_voter_weight_source,
+    );
+
+    send_instructions(config, &[instruction], vec![], &mut output)?;
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_deposit_governing_tokens(
+    config: &Config,
+    realm: Pubkey,
+    governing_token_mint: Pubkey,
+    governing_token_source: Pubkey,
+    governing_token_owner: Option<Box<dyn Signer>>,
+    ui_amount: f64,
+) -> CommandResult {
+    let governing_token_owner = governing_token_owner
+        .as_deref()
+        .unwrap_or(config.fee_payer.as_ref());
+
+    let governing_token_mint_account = config.rpc_client.get_account(&governing_token_mint)?;
+    let governing_token_mint_data = Mint::unpack_from_slice(&governing_token_mint_account.data)?;
+    let amount = ui_amount_to_amount(ui_amount, governing_token_mint_data.decimals);
+
+    let token_owner_record_address = get_token_owner_record_address(
+        &config.governance_program_id,
+        &realm,
+        &governing_token_mint,
+        &governing_token_owner.pubkey(),
+    );
+    let mut output = CliGovernanceCommand::new(
+        "deposit-governing-tokens",
+        Some(token_owner_record_address.to_string()),
+    );
+
+    let instruction = deposit_governing_tokens(
+        &config.governance_program_id,
+        &realm,
+        &governing_token_source,
+        &governing_token_owner.pubkey(),
+        &governing_token_owner.pubkey(),
+        &config.fee_payer.pubkey(),
+        amount,
+        &governing_token_mint,
+        &governing_token_mint_account.owner,
+    );
+
+    send_instructions(
+        config,
+        &[instruction],
+        vec![governing_token_owner],
+        &mut output,
+    )?;
+    print_output(config, &output);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_create_proposal(
+    config: &Config,
+    governance: Pubkey,
+    governing_token_mint: Pubkey,
+    proposal_owner: Option<Box<dyn Signer>>,
+    name: String,
+    description_link: String,
+    options: Vec<String>,
+    category: Option<[u8; 8]>,
+    sign_off: bool,
+) -> CommandResult {
+    let proposal_owner = proposal_owner
+        .as_deref()
+        .unwrap_or(config.fee_payer.as_ref());
+
+    let governance_data = get_governance(config, &governance)?;
+    let realm = governance_data.realm;
+
+    let proposal_owner_record = get_token_owner_record_address(
+        &config.governance_program_id,
+        &realm,
+        &governing_token_mint,
+        &proposal_owner.pubkey(),
+    );
+
+    let proposal_seed = Keypair::new().pubkey();
+    let proposal_address = get_proposal_address(
+        &config.governance_program_id,
+        &governance,
+        &governing_token_mint,
+        &proposal_seed,
+    );
+    let mut output =
+        CliGovernanceCommand::new("create-proposal", Some(proposal_address.to_string()));
+
+    let instruction = create_proposal(
+        &config.governance_program_id,
+        &governance,
+        &proposal_owner_record,
+        &proposal_owner.pubkey(),
+        &config.fee_payer.pubkey(),
+        None,
+        &realm,
+        name,
+        description_link,
+        &governing_token_mint,
+        VoteType::SingleChoice,
+        options,
+        true,
+        &proposal_seed,
+        category,
+        sign_off.then(|| proposal_owner.pubkey()).as_ref(),
+        None,
+    );
+
+    send_instructions(config, &[instruction], vec![proposal_owner], &mut output)?;
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_list_proposals(
+    config: &Config,
+    governance: Pubkey,
+    category: Option<[u8; 8]>,
+) -> CommandResult {
+    let proposals = get_proposals_by_category(config, &governance, category)?
+        .into_iter()
+        .map(|(address, proposal_data)| CliProposal {
+            address: address.to_string(),
+            name: proposal_data.name,
+            state: format!("{:?}", proposal_data.state),
+            category: String::from_utf8_lossy(&proposal_data.category)
+                .trim_end_matches('\0')
+                .to_string(),
+        })
+        .collect();
+
+    println!(
+        "{}",
+        config
+            .output_format
+            .formatted_string(&CliProposals { proposals })
+    );
+    Ok(())
+}
+
+fn command_sign_off_proposal(
+    config: &Config,
+    proposal: Pubkey,
+    signatory: Option<Box<dyn Signer>>,
+) -> CommandResult {
+    let signatory = signatory.as_deref().unwrap_or(config.fee_payer.as_ref());
+
+    let proposal_data = get_proposal(config, &proposal)?;
+    let governance_data = get_governance(config, &proposal_data.governance)?;
+
+    // Without signatories the proposal owner signs off using its token owner record
+    let proposal_owner_record = if proposal_data.signatories_count == 0 {
+        Some(&proposal_data.token_owner_record)
+    } else {
+        None
+    };
+
+    let mut output = CliGovernanceCommand::new("sign-off-proposal", Some(proposal.to_string()));
+
+    let instruction = sign_off_proposal(
+        &config.governance_program_id,
+        &governance_data.realm,
+        &proposal_data.governance,
+        &proposal,
+        &signatory.pubkey(),
+        proposal_owner_record,
+        &proposal_data.governing_token_mint,
+        None,
+    );
+
+    send_instructions(config, &[instruction], vec![signatory], &mut output)?;
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_cast_vote(
+    config: &Config,
+    proposal: Pubkey,
+    vote: Vote,
+    voter: Option<Box<dyn Signer>>,
+) -> CommandResult {
+    let voter = voter.as_deref().unwrap_or(config.fee_payer.as_ref());
+
+    let proposal_data = get_proposal(config, &proposal)?;
+    let governance_data = get_governance(config, &proposal_data.governance)?;
+
+    let voter_token_owner_record = get_token_owner_record_address(
+        &config.governance_program_id,
+        &governance_data.realm,
+        &proposal_data.governing_token_mint,
+        &voter.pubkey(),
+    );
+    if config.verbose {
+        println!(
+            "Voting with token owner record {}",
+            voter_token_owner_record
+        );
+    }
+
+    let mut output = CliGovernanceCommand::new("cast-vote", Some(proposal.to_string()));
+
+    let instruction = cast_vote(
+        &config.governance_program_id,
+        &governance_data.realm,
+        &proposal_data.governance,
+        &proposal,
+        &proposal_data.token_owner_record,
+        &voter_token_owner_record,
+        &voter.pubkey(),
+        &proposal_data.governing_token_mint,
+        &config.fee_payer.pubkey(),
+        None,
+        None,
+        vote,
+    );
+
+    send_instructions(config, &[instruction], vec![voter], &mut output)?;
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_cancel_proposal(
+    config: &Config,
+    proposal: Pubkey,
+    proposal_owner: Option<Box<dyn Signer>>,
+) -> CommandResult {
+    let proposal_owner = proposal_owner
+        .as_deref()
+        .unwrap_or(config.fee_payer.as_ref());
+
+    let proposal_data = get_proposal(config, &proposal)?;
+    let governance_data = get_governance(config, &proposal_data.governance)?;
+
+    let mut output = CliGovernanceCommand::new("cancel-proposal", Some(proposal.to_string()));
+
+    let instruction = cancel_proposal(
+        &config.governance_program_id,
+        &governance_data.realm,
+        &proposal_data.governance,
+        &proposal,
+        &proposal_data.token_owner_record,
+        &proposal_owner.pubkey(),
+    );
+
+    send_instructions(config, &[instruction], vec![proposal_owner], &mut output)?;
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_simulate_proposal(config: &Config, proposal: Pubkey) -> CommandResult {
+    let proposal_data = get_proposal(config, &proposal)?;
+
+    let mut output = CliGovernanceCommand::new("simulate-proposal", Some(proposal.to_string()));
+
+    for (option_index, option) in proposal_data.options.iter().enumerate() {
+        for transaction_index in 0..option.transactions_next_index {
+            let proposal_transaction = get_proposal_transaction_address(
+                &config.governance_program_id,
+                &proposal,
+                &(option_index as u8).to_le_bytes(),
+                &transaction_index.to_le_bytes(),
+            );
+
+            // Removed transactions leave gaps in the transaction indexes
+            let mut account = match config
+                .rpc_client
+                .get_account_with_commitment(&proposal_transaction, config.rpc_client.commitment())?
+                .value
+            {
+                Some(account) => account,
+                None => continue,
+            };
+            let account_info = (&proposal_transaction, &mut account).into_account_info();
+            let proposal_transaction_data =
+                get_proposal_transaction_data(&config.governance_program_id, &account_info)?;
+            if proposal_transaction_data.executed_at.is_some() {
+                continue;
+            }
+
+            let instructions = simulate_proposal_execution(
+                &config.governance_program_id,
+                &proposal_data.governance,
+                &proposal_transaction_data,
+            )?;
+            let message = Message::new(&instructions, Some(&config.fee_payer.pubkey()));
+
+            // The governance PDA signatures only exist when the program invokes
+            // the instructions, so signatures can't be verified
+            let result = config.rpc_client.simulate_transaction_with_config(
+                &Transaction::new_unsigned(message),
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )?;
+            output
+                .simulations
+                .push(format!("{} {:?}", proposal_transaction, result.value));
+        }
+    }
+
+    print_output(config, &output);
+    Ok(())
+}
+
+fn command_execute_proposal(config: &Config, proposal: Pubkey) -> CommandResult {
+    let proposal_data = get_proposal(config, &proposal)?;
+
+    let mut output = CliGovernanceCommand::new("execute-proposal", Some(proposal.to_string()));
+
+    // Dependencies can only point to options with a lower index, so executing
+    // the options in order executes the dependencies first
+    for (option_index, option) in proposal_data.options.iter().enumerate() {
+        for transaction_index in 0..option.transactions_next_index {
+            let proposal_transaction = get_proposal_transaction_address(
+                &config.governance_program_id,
+                &proposal,
+                &(option_index as u8).to_le_bytes(),
+                &transaction_index.to_le_bytes(),
+            );
+
+            // Removed transactions leave gaps in the transaction indexes
+            let mut account = match config
+                .rpc_client
+                .get_account_with_commitment(&proposal_transaction, config.rpc_client.commitment())?
+                .value
+            {
+                Some(account) => account,
+                None => continue,
+            };
+            let account_info = (&proposal_transaction, &mut account).into_account_info();
+            let proposal_transaction_data =
+                get_proposal_transaction_data(&config.governance_program_id, &account_info)?;
+            if proposal_transaction_data.executed_at.is_some() {
+                continue;
+            }
+
+            let instructions = compose_execute_transaction(
+                &config.governance_program_id,
+                &proposal_data.governance,
+                &proposal,
+                &proposal_transaction,
+                &proposal_transaction_data,
+            )?;
+            send_instructions(config, &instructions, vec![], &mut output)?;
+        }
+    }
+
+    print_output(config, &output);
+    Ok(())
+}
+
+// HELPERS
+
+fn signer_of_or_exit(
+    matches: &ArgMatches<'_>,
+    name: &str,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Option<Box<dyn Signer>> {
+    matches.value_of(name).map(|path| {
+        signer_from_path(matches, path, name, wallet_manager).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        })
+    })
+}
+
+fn is_valid_category(category: String) -> Result<(), String> {
+    if category.len() > 8 {
+        Err(format!("Category {} is longer than 8 bytes", category))
+    } else {
+        Ok(())
+    }
+}
+
+fn category_of(matches: &ArgMatches<'_>, name: &str) -> Option<[u8; 8]> {
+    matches.value_of(name).map(|category| {
+        let mut bytes = [0; 8];
+        bytes[..category.len()].copy_from_slice(category.as_bytes());
+        bytes
+    })
+}
+
+/// Returns the Proposals of the Governance, limited to the given category if
+/// provided
+///
+/// The category is matched by the RPC node on the ProposalCategoryRecord
+/// accounts, which store it at a fixed offset, and the Proposals they point to
+/// are fetched afterwards
+/// Note: ProposalV1 accounts can't have a category and aren't returned
+fn get_proposals_by_category(
+    config: &Config,
+    governance: &Pubkey,
+    category: Option<[u8; 8]>,
+) -> Result<Vec<(Pubkey, ProposalV2)>, Error> {
+    let category = match category {
+        Some(category) => category,
+        None => return get_proposals(config, governance),
+    };
+
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            &[GovernanceAccountType::ProposalCategoryRecord as u8],
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            PROPOSAL_CATEGORY_RECORD_GOVERNANCE_OFFSET,
+            governance.as_ref(),
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            PROPOSAL_CATEGORY_RECORD_CATEGORY_OFFSET,
+            &category,
+        )),
+    ];
+    let accounts = config.rpc_client.get_program_accounts_with_config(
+        &config.governance_program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(filters),
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+
+    let mut proposal_addresses = vec![];
+    for (address, mut account) in accounts {
+        let account_info = (&address, &mut account).into_account_info();
+        let proposal_category_record_data =
+            get_proposal_category_record_data(&config.governance_program_id, &account_info)?;
+        proposal_addresses.push(proposal_category_record_data.proposal);
+    }
+
+    let mut proposals = vec![];
+    for addresses in proposal_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = config.rpc_client.get_multiple_accounts(addresses)?;
+        for (address, account) in addresses.iter().zip(accounts) {
+            if let Some(mut account) = account {
+                let account_info = (address, &mut account).into_account_info();
+                let proposal_data =
+                    get_proposal_data(&config.governance_program_id, &account_info)?;
+                proposals.push((*address, proposal_data));
+            }
+        }
+    }
+    Ok(proposals)
+}
+
+/// Returns all the ProposalV2 accounts of the Governance
+fn get_proposals(config: &Config, governance: &Pubkey) -> Result<Vec<(Pubkey, ProposalV2)>, Error> {
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            &[GovernanceAccountType::ProposalV2 as u8],
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(1, governance.as_ref())),
+    ];
+    let accounts = config.rpc_client.get_program_accounts_with_config(
+        &config.governance_program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(filters),
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+
+    let mut proposals = vec![];
+    for (address, mut account) in accounts {
+        let account_info = (&address, &mut account).into_account_info();
+        let proposal_data = get_proposal_data(&config.governance_program_id, &account_info)?;
+        proposals.push((address, proposal_data));
+    }
+    Ok(proposals)
+}
+
+fn get_proposal(config: &Config, proposal: &Pubkey) -> Result<ProposalV2, Error> {
+    let mut account = config.rpc_client.get_account(proposal)?;
+    let account_info = (proposal, &mut account).into_account_info();
+    Ok(get_proposal_data(
+        &config.governance_program_id,
+        &account_info,
+    )?)
+}
+
+fn get_governance(config: &Config, governance: &Pubkey) -> Result<GovernanceV2, Error> {
+    let mut account = config.rpc_client.get_account(governance)?;
+    let account_info = (governance, &mut account).into_account_info();
+    Ok(get_governance_data(
+        &config.governance_program_id,
+        &account_info,
+    )?)
+}
+
+fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
+    let balance = config.rpc_client.get_balance(&config.fee_payer.pubkey())?;
+    if balance < required_balance {
+        Err(format!(
+            "Fee payer, {}, has insufficient balance: {} required, {} available",
+            config.fee_payer.pubkey(),
+            lamports_to_sol(required_balance),
+            lamports_to_sol(balance)
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+fn send_instructions<'a>(
+    config: &'a Config,
+    instructions: &[Instruction],
+    mut signers: Vec<&'a dyn Signer>,
+    output: &mut CliGovernanceCommand,
+) -> CommandResult {
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+
+    signers.push(config.fee_payer.as_ref());
+    let transaction = Transaction::new(&unique_signers(signers), message, recent_blockhash);
+
+    if config.dry_run {
+        let result = config.rpc_client.simulate_transaction(&transaction)?;
+        output.simulations.push(format!("{:?}", result.value));
+    } else {
+        let signature = config
+            .rpc_client
+            .send_and_confirm_transaction_with_spinner(&transaction)?;
+        output.signatures.push(signature.to_string());
+    }
+    Ok(())
+}
+
+fn print_output(config: &Config, output: &CliGovernanceCommand) {
+    println!("{}", config.output_format.formatted_string(output));
+}
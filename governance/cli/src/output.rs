@@ -0,0 +1,74 @@
+use {
+    serde::{Deserialize, Serialize},
+    solana_cli_output::{QuietDisplay, VerboseDisplay},
+    std::fmt::{Display, Formatter, Result},
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliGovernanceCommand {
+    pub command: String,
+    pub address: Option<String>,
+    pub signatures: Vec<String>,
+    pub simulations: Vec<String>,
+}
+
+impl CliGovernanceCommand {
+    pub fn new(command: &str, address: Option<String>) -> Self {
+        Self {
+            command: command.to_string(),
+            address,
+            signatures: vec![],
+            simulations: vec![],
+        }
+    }
+}
+
+impl Display for CliGovernanceCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(address) = &self.address {
+            writeln!(f, "Address: {}", address)?;
+        }
+        for simulation in &self.simulations {
+            writeln!(f, "Simulate result: {}", simulation)?;
+        }
+        for signature in &self.signatures {
+            writeln!(f, "Signature: {}", signature)?;
+        }
+        Ok(())
+    }
+}
+
+impl QuietDisplay for CliGovernanceCommand {}
+impl VerboseDisplay for CliGovernanceCommand {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliProposal {
+    pub address: String,
+    pub name: String,
+    pub state: String,
+    pub category: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliProposals {
+    pub proposals: Vec<CliProposal>,
+}
+
+impl Display for CliProposals {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for proposal in &self.proposals {
+            writeln!(
+                f,
+                "{} {} [{}] {}",
+                proposal.address, proposal.state, proposal.category, proposal.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl QuietDisplay for CliProposals {}
+impl VerboseDisplay for CliProposals {}
@@ -548,6 +548,92 @@ pub enum GovernanceError {
     /// TokenOwnerRecordLockAuthority not found
     #[error("TokenOwnerRecordLockAuthority not found")]
     TokenOwnerRecordLockAuthorityNotFound, // 631
+
+    /// Insufficient weight to create community proposal
+    #[error("Insufficient weight to create community proposal")]
+    InsufficientProposalCreationWeight, // 632
+
+    /// Council membership required to create community proposal
+    #[error("Council membership required to create community proposal")]
+    CouncilMembershipRequiredToCreateProposal, // 633
+
+    /// Proposal hold up time below Governance min transaction hold up time
+    #[error("Proposal hold up time below Governance min transaction hold up time")]
+    InvalidHoldUpTime, // 634
+
+    /// Transaction dependency option has not been executed
+    #[error("Transaction dependency option has not been executed")]
+    TransactionDependencyNotExecuted, // 635
+
+    /// Invalid transaction dependency
+    #[error("Invalid transaction dependency")]
+    InvalidTransactionDependency, // 636
+
+    /// Transaction instruction program id doesn't match the governed program
+    #[error("Transaction instruction program id doesn't match the governed program")]
+    InvalidTransactionProgramId, // 637
+
+    /// Signer doesn't match the Realm pending authority
+    #[error("Signer doesn't match the Realm pending authority")]
+    PendingAuthorityMismatch, // 638
+
+    /// Governing token deposits are not tracked for the Realm
+    #[error("Governing token deposits are not tracked for the Realm")]
+    GoverningTokenDepositsNotTracked, // 639
+
+    /// Governing token holding account has no surplus above the deposits
+    #[error("Governing token holding account has no surplus above the deposits")]
+    GoverningTokenHoldingHasNoSurplus, // 640
+
+    /// Invalid treasury account for the governing token surplus
+    #[error("Invalid treasury account for the governing token surplus")]
+    InvalidGoverningTokenTreasury, // 641
+
+    /// Proposal description link is too long
+    #[error("Proposal description link is too long")]
+    DescriptionTooLong, // 642
+
+    /// Proposal owner must be the sole signatory to sign off at creation
+    #[error("Proposal owner must be the sole signatory to sign off at creation")]
+    ProposalOwnerNotSoleSignatory, // 643
+
+    /// Membership tokens can only be deposited by the mint authority or the
+    /// Realm authority
+    #[error(
+        "Membership tokens can only be deposited by the mint authority or the Realm authority"
+    )]
+    MembershipDepositNotAuthorized, // 644
+
+    /// Realm name must be non-empty and at most MAX_REALM_NAME_LEN bytes
+    #[error("Realm name must be non-empty and at most MAX_REALM_NAME_LEN bytes")]
+    RealmNameInvalid, // 645
+
+    /// Invalid governing token program for the Realm
+    #[error("Invalid governing token program for the Realm")]
+    InvalidGoverningTokenProgram, // 646
+
+    /// Governing token mint extension is not allowed for the Realm
+    #[error("Governing token mint extension is not allowed for the Realm")]
+    GoverningTokenMintExtensionNotAllowed, // 647
+
+    /// Vote can only be changed to a different vote of the same kind
+    #[error("Vote can only be changed to a different vote of the same kind")]
+    InvalidVoteChange, // 648
+
+    /// Transaction has more accounts than its instructions can be invoked with
+    #[error("Transaction has more accounts than its instructions can be invoked with")]
+    TooManyTransactionAccounts, // 649
+
+    /// RealmConfig account must be writable when the Realm tracks the
+    /// governing token deposits
+    #[error(
+        "RealmConfig account must be writable when the Realm tracks the governing token deposits"
+    )]
+    RealmConfigMustBeWritable, // 650
+
+    /// Governing token deposits can't exceed the holding account balance
+    #[error("Governing token deposits can't exceed the holding account balance")]
+    InvalidGoverningTokenDeposits, // 651
 }
 
 impl PrintProgramError for GovernanceError {
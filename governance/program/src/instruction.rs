@@ -11,6 +11,7 @@ use {
             native_treasury::get_native_treasury_address,
             program_metadata::get_program_metadata_address,
             proposal::{get_proposal_address, VoteType},
+            proposal_category_record::get_proposal_category_record_address,
             proposal_deposit::get_proposal_deposit_address,
             proposal_transaction::{get_proposal_transaction_address, InstructionData},
             realm::{
@@ -52,7 +53,8 @@ pub enum GovernanceInstruction {
     ///     The account will be created with the Realm PDA as its owner
     /// 4. `[signer]` Payer
     /// 5. `[]` System
-    /// 6. `[]` SPL Token
+    /// 6. `[]` SPL Token or Token-2022 program owning the Community and
+    ///    Council Token Mints
     /// 7. `[]` Sysvar Rent
     /// 8. `[]` Council Token Mint - optional
     /// 9. `[writable]` Council Token Holding account - optional unless council
@@ -67,7 +69,8 @@ pub enum GovernanceInstruction {
     /// 14. `[]` Optional Max Council Voter Weight Addin Program Id
     CreateRealm {
         #[allow(dead_code)]
-        /// UTF-8 encoded Governance Realm name
+        /// UTF-8 encoded Governance Realm name, non-empty and at most
+        /// MAX_REALM_NAME_LEN bytes
         name: String,
 
         #[allow(dead_code)]
@@ -90,15 +93,26 @@ pub enum GovernanceInstruction {
     ///     minted to the Holding account
     ///  3. `[signer]` Governing Token Owner account
     ///  4. `[signer]` Governing Token Source account authority It should be
-    ///     owner for TokenAccount and mint_authority for MintAccount
+    ///     owner for TokenAccount and mint_authority for MintAccount. For
+    ///     Membership tokens it must be the mint_authority or, for a
+    ///     TokenAccount, the Realm authority
     ///  5. `[writable]` TokenOwnerRecord account.
     ///     * PDA seeds: ['governance',realm, governing_token_mint,
     ///       governing_token_owner]
     ///  6. `[signer]` Payer
     ///  7. `[]` System
-    ///  8. `[]` SPL Token program
+    ///  8. `[]` SPL Token or Token-2022 program of the Realm
     ///  9. `[]` RealmConfig account.
     ///     * PDA seeds: ['realm-config', realm]
+    ///     * Must be writable when the Realm tracks the governing token
+    ///       deposits, see SetRealmConfigItemArgs::GoverningTokenDepositTracking
+    ///  10. `[]` Governing Token Mint - required for Token-2022 governing
+    ///     tokens
+    ///
+    /// Note: Token-2022 mints with the transfer fee or non-transferable
+    /// extension can only be deposited when allowed in RealmConfig, and only
+    /// the amount received by the Holding account net of the transfer fee is
+    /// deposited
     DepositGoverningTokens {
         /// The amount to deposit into the realm
         #[allow(dead_code)]
@@ -121,9 +135,13 @@ pub enum GovernanceInstruction {
     ///  4. `[writable]` TokenOwnerRecord account.
     ///     * PDA seeds: ['governance',realm, governing_token_mint,
     ///       governing_token_owner]
-    ///  5. `[]` SPL Token program
+    ///  5. `[]` SPL Token or Token-2022 program of the Realm
     ///  6. `[]` RealmConfig account.
     ///     * PDA seeds: ['realm-config', realm]
+    ///     * Must be writable when the Realm tracks the governing token
+    ///       deposits, see SetRealmConfigItemArgs::GoverningTokenDepositTracking
+    ///  7. `[]` Governing Token Mint - required for Token-2022 governing
+    ///     tokens
     WithdrawGoverningTokens {},
 
     /// Sets Governance Delegate for the given Realm and Governing Token Mint
@@ -221,13 +239,28 @@ pub enum GovernanceInstruction {
     ///     The deposit is paid by the Payer of the transaction and can be
     ///     reclaimed using RefundProposalDeposit once the Proposal is no
     ///     longer active.
+    ///     It must always be passed when category or sign_off is set
+    ///   11.`[writable]` ProposalCategoryRecord account, required when
+    ///      category is set
+    ///     * PDA seeds: ['proposal-category', proposal]
+    ///   12.`[writable]` SignatoryRecord account of the Proposal owner,
+    ///      required when sign_off is set
+    ///     * PDA seeds: ['governance', proposal, governing_token_owner]
+    ///   13.`[]` RequiredSignatory account of the Proposal owner, required
+    ///      when sign_off is set
+    ///     * PDA seeds: ['required-signatory', governance,
+    ///       governing_token_owner]
+    ///     Only validated when the Governance has a required signatory
+    ///   14.`[]` Optional MaxVoterWeightRecord, used when sign_off is set
+    ///      and the RealmConfig snapshots the max voter weight
     CreateProposal {
         #[allow(dead_code)]
         /// UTF-8 encoded name of the proposal
         name: String,
 
         #[allow(dead_code)]
-        /// Link to a gist explaining the proposal
+        /// UTF-8 encoded link to a gist explaining the proposal, at most
+        /// MAX_DESCRIPTION_LINK_LEN bytes
         description_link: String,
 
         #[allow(dead_code)]
@@ -248,6 +281,17 @@ pub enum GovernanceInstruction {
         #[allow(dead_code)]
         /// Unique seed for the Proposal PDA
         proposal_seed: Pubkey,
+
+        #[allow(dead_code)]
+        /// Optional category tag for off-chain filtering, zero padded
+        /// The category is also stored at a fixed offset in the
+        /// ProposalCategoryRecord account
+        category: Option<[u8; 8]>,
+
+        #[allow(dead_code)]
+        /// Signs off the Proposal on behalf of its owner and starts voting
+        /// Only allowed when the Proposal owner is the sole signatory
+        sign_off: bool,
     },
 
     /// Adds a signatory to the Proposal which means this Proposal can't leave
@@ -278,6 +322,9 @@ pub enum GovernanceInstruction {
     /// the range indicated by Proposal transactions_next_index
     /// If a Transaction replaces an existing Transaction at a given index then
     /// the old one must be removed using RemoveTransaction first
+    /// The instructions can declare at most MAX_TRANSACTION_ACCOUNTS accounts,
+    /// including their program ids, and the compute units estimated to
+    /// execute them are stored with the Transaction
 
     ///   0. `[]` Governance account
     ///   1. `[writable]` Proposal account
@@ -304,6 +351,12 @@ pub enum GovernanceInstruction {
         #[allow(dead_code)]
         /// Instructions Data
         instructions: Vec<InstructionData>,
+
+        #[allow(dead_code)]
+        /// The option whose transactions must all be executed before the
+        /// transaction can be executed
+        /// Only options with a lower index can be depended on
+        depends_on_option_index: Option<u8>,
     },
 
     /// Removes Transaction from the Proposal
@@ -342,6 +395,12 @@ pub enum GovernanceInstruction {
     ///   4. `[]` TokenOwnerRecord for the Proposal owner, required when the
     ///      owner signs off the Proposal Or `[writable]` SignatoryRecord
     ///      account, required when non owner sings off the Proposal
+    ///   5. `[]` RealmConfig account. PDA seeds: ['realm-config', realm]
+    ///   6. `[]` The Governing Token Mint of the Proposal
+    ///   7. `[]` Optional Max Voter Weight Record
+    ///
+    /// Accounts 5-7 are only used by the sign off which starts voting, and
+    /// 6-7 only when the Realm snapshots the max voter weight at voting start
     SignOffProposal,
 
     ///  Uses your voter weight (deposited Community or Council tokens) to cast
@@ -518,14 +577,16 @@ pub enum GovernanceInstruction {
     FlagTransactionError,
 
     /// Sets new Realm authority
+    /// SetUnchecked only proposes the new authority which must accept it with
+    /// AcceptRealmAuthority
     ///
     ///   0. `[writable]` Realm account
     ///   1. `[signer]` Current Realm authority
     ///   2. `[]` New realm authority. Must be one of the realm governances when
-    ///      set
+    ///      set with SetChecked
     SetRealmAuthority {
         #[allow(dead_code)]
-        /// Set action ( SetUnchecked, SetChecked, Remove)
+        /// Set action ( SetUnchecked, SetChecked, Remove, CancelPending)
         action: SetRealmAuthorityAction,
     },
 
@@ -612,6 +673,8 @@ pub enum GovernanceInstruction {
     ///                   membership
     ///  5. `[]` RealmConfig account.
     ///     * PDA seeds: ['realm-config', realm]
+    ///     * Must be writable when the Realm tracks the governing token
+    ///       deposits, see SetRealmConfigItemArgs::GoverningTokenDepositTracking
     ///  6. `[]` SPL Token program
     RevokeGoverningTokens {
         /// The amount to revoke
@@ -714,11 +777,86 @@ pub enum GovernanceInstruction {
     ///   2. `[signer]`  Realm authority
     ///   3. `[signer]` Payer
     ///   4. `[]` System
+    ///   5. `[]` Governing Token Holding account - required for
+    ///      GoverningTokenDepositTracking
+    ///     * PDA seeds: ['governance',realm, governing_token_mint]
     SetRealmConfigItem {
         #[allow(dead_code)]
         /// Config args
         args: SetRealmConfigItemArgs,
     },
+
+    /// Sets the min hold up time applied to all the Proposal's transactions
+    /// It can only be set in Draft state and can't be lower than the parent
+    /// Governance min_transaction_hold_up_time
+    ///
+    ///   0. `[]` Governance account
+    ///   1. `[writable]` Proposal account
+    ///   2. `[]` TokenOwnerRecord account of the Proposal owner
+    ///   3. `[signer]` Governance Authority (Token Owner or Governance
+    ///      Delegate)
+    SetProposalHoldUpTime {
+        #[allow(dead_code)]
+        /// Min hold up time in seconds
+        hold_up_time: u32,
+    },
+
+    /// Accepts the Realm authority proposed with SetRealmAuthority and makes
+    /// the pending authority the Realm authority
+    ///
+    ///   0. `[writable]` Realm account
+    ///   1. `[signer]` Pending Realm authority
+    AcceptRealmAuthority {},
+
+    /// Sweeps governing tokens which were transferred directly into the
+    /// Governing Token Holding account, and are not deposited by any
+    /// TokenOwnerRecord, to the given Governance treasury
+    /// The surplus is the holding balance above the total deposits tracked in
+    /// RealmConfig and the instruction is permissionless
+    /// Note: The deposits are only tracked for Realms which opted in with
+    /// SetRealmConfigItemArgs::GoverningTokenDepositTracking
+    ///
+    ///   0. `[]` Realm account
+    ///   1. `[writable]` Governing Token Holding account.
+    ///     * PDA seeds: ['governance',realm, governing_token_mint]
+    ///   2. `[]` RealmConfig account.
+    ///     * PDA seeds: ['realm-config', realm]
+    ///   3. `[]` Governance account of the Realm
+    ///   4. `[writable]` Governing Token Treasury account owned by the
+    ///      Governance or its native treasury
+    ///   5. `[]` SPL Token program
+    ReconcileGoverningTokenHolding {},
+
+    /// Changes the vote of the voter on a Proposal which is still being voted
+    /// on. The voter weight recorded in the VoteRecord is moved from the
+    /// previous vote to the new vote in a single instruction
+    /// Note: The vote can only be changed to a different vote of the same kind
+    /// and Approve votes are not accepted within the voting cool off time
+    ///
+    ///   0. `[]` Realm account
+    ///   1. `[writable]` Governance account
+    ///   2. `[writable]` Proposal account
+    ///   3. `[writable]` TokenOwnerRecord of the Proposal owner
+    ///   4. `[writable]` TokenOwnerRecord of the voter.
+    ///     * PDA seeds: ['governance',realm, vote_governing_token_mint,
+    ///       governing_token_owner]
+    ///   5. `[signer]` Governance Authority (Token Owner or Governance
+    ///      Delegate)
+    ///   6. `[writable]` Proposal VoteRecord account.
+    ///     * PDA seeds: ['governance',proposal,token_owner_record]
+    ///   7. `[]` The Governing Token Mint which was used to cast the vote
+    ///      (vote_governing_token_mint)
+    ///   8. `[signer]` Payer, tops up the VoteRecord rent when the new vote
+    ///      doesn't fit the account
+    ///   9. `[]` System program
+    ///   10. `[]` RealmConfig account.
+    ///     * PDA seeds: ['realm-config', realm]
+    ///   11. `[]` Optional Max Voter Weight Record
+    ChangeVote {
+        #[allow(dead_code)]
+        /// User's new vote
+        vote: Vote,
+    },
 }
 
 /// Creates CreateRealm instruction
@@ -730,6 +868,7 @@ pub fn create_realm(
     community_token_mint: &Pubkey,
     payer: &Pubkey,
     council_token_mint: Option<Pubkey>,
+    token_program: &Pubkey,
     // Accounts Args
     community_token_config_args: Option<GoverningTokenConfigAccountArgs>,
     council_token_config_args: Option<GoverningTokenConfigAccountArgs>,
@@ -749,7 +888,7 @@ pub fn create_realm(
         AccountMeta::new(community_token_holding_address, false),
         AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*token_program, false),
         AccountMeta::new_readonly(sysvar::rent::id(), false),
     ];
 
@@ -804,6 +943,7 @@ pub fn deposit_governing_tokens(
     // Args
     amount: u64,
     governing_token_mint: &Pubkey,
+    token_program: &Pubkey,
 ) -> Instruction {
     let token_owner_record_address = get_token_owner_record_address(
         program_id,
@@ -817,7 +957,7 @@ pub fn deposit_governing_tokens(
 
     let realm_config_address = get_realm_config_address(program_id, realm);
 
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new_readonly(*realm, false),
         AccountMeta::new(governing_token_holding_address, false),
         AccountMeta::new(*governing_token_source, false),
@@ -826,10 +966,14 @@ pub fn deposit_governing_tokens(
         AccountMeta::new(token_owner_record_address, false),
         AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*token_program, false),
         AccountMeta::new_readonly(realm_config_address, false),
     ];
 
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*governing_token_mint, false));
+    }
+
     let instruction = GovernanceInstruction::DepositGoverningTokens { amount };
 
     Instruction {
@@ -848,6 +992,7 @@ pub fn withdraw_governing_tokens(
     governing_token_owner: &Pubkey,
     // Args
     governing_token_mint: &Pubkey,
+    token_program: &Pubkey,
 ) -> Instruction {
     let token_owner_record_address = get_token_owner_record_address(
         program_id,
@@ -861,16 +1006,20 @@ pub fn withdraw_governing_tokens(
 
     let realm_config_address = get_realm_config_address(program_id, realm);
 
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new_readonly(*realm, false),
         AccountMeta::new(governing_token_holding_address, false),
         AccountMeta::new(*governing_token_destination, false),
         AccountMeta::new_readonly(*governing_token_owner, true),
         AccountMeta::new(token_owner_record_address, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*token_program, false),
         AccountMeta::new_readonly(realm_config_address, false),
     ];
 
+    if *token_program == spl_token_2022::id() {
+        accounts.push(AccountMeta::new_readonly(*governing_token_mint, false));
+    }
+
     let instruction = GovernanceInstruction::WithdrawGoverningTokens {};
 
     Instruction {
@@ -880,6 +1029,27 @@ pub fn withdraw_governing_tokens(
     }
 }
 
+/// Marks the RealmConfig account of a DepositGoverningTokens,
+/// WithdrawGoverningTokens or RevokeGoverningTokens instruction writable,
+/// which is required while the Realm tracks the governing token deposits
+pub fn with_writable_realm_config(
+    program_id: &Pubkey,
+    realm: &Pubkey,
+    mut instruction: Instruction,
+) -> Instruction {
+    let realm_config_address = get_realm_config_address(program_id, realm);
+
+    for account in instruction
+        .accounts
+        .iter_mut()
+        .filter(|account| account.pubkey == realm_config_address)
+    {
+        account.is_writable = true;
+    }
+
+    instruction
+}
+
 /// Creates SetGovernanceDelegate instruction
 pub fn set_governance_delegate(
     program_id: &Pubkey,
@@ -1113,6 +1283,10 @@ pub fn create_proposal(
     options: Vec<String>,
     use_deny_option: bool,
     proposal_seed: &Pubkey,
+    category: Option<[u8; 8]>,
+    // Sign off
+    sign_off_signatory: Option<&Pubkey>,
+    max_voter_weight_record: Option<Pubkey>,
 ) -> Instruction {
     let proposal_address =
         get_proposal_address(program_id, governance, governing_token_mint, proposal_seed);
@@ -1137,6 +1311,26 @@ pub fn create_proposal(
         get_proposal_deposit_address(program_id, &proposal_address, payer);
     accounts.push(AccountMeta::new(proposal_deposit_address, false));
 
+    if category.is_some() {
+        let proposal_category_record_address =
+            get_proposal_category_record_address(program_id, &proposal_address);
+        accounts.push(AccountMeta::new(proposal_category_record_address, false));
+    }
+
+    if let Some(sign_off_signatory) = sign_off_signatory {
+        let signatory_record_address =
+            get_signatory_record_address(program_id, &proposal_address, sign_off_signatory);
+        accounts.push(AccountMeta::new(signatory_record_address, false));
+
+        let required_signatory_address =
+            get_required_signatory_address(program_id, governance, sign_off_signatory);
+        accounts.push(AccountMeta::new_readonly(required_signatory_address, false));
+
+        if let Some(max_voter_weight_record) = max_voter_weight_record {
+            accounts.push(AccountMeta::new_readonly(max_voter_weight_record, false));
+        }
+    }
+
     let instruction = GovernanceInstruction::CreateProposal {
         name,
         description_link,
@@ -1144,6 +1338,8 @@ pub fn create_proposal(
         options,
         use_deny_option,
         proposal_seed: *proposal_seed,
+        category,
+        sign_off: sign_off_signatory.is_some(),
     };
 
     Instruction {
@@ -1218,6 +1414,7 @@ pub enum AddSignatoryAuthority {
 }
 
 /// Creates SignOffProposal instruction
+#[allow(clippy::too_many_arguments)]
 pub fn sign_off_proposal(
     program_id: &Pubkey,
     // Accounts
@@ -1226,6 +1423,8 @@ pub fn sign_off_proposal(
     proposal: &Pubkey,
     signatory: &Pubkey,
     proposal_owner_record: Option<&Pubkey>,
+    governing_token_mint: &Pubkey,
+    max_voter_weight_record: Option<Pubkey>,
 ) -> Instruction {
     let mut accounts = vec![
         AccountMeta::new_readonly(*realm, false),
@@ -1242,6 +1441,14 @@ pub fn sign_off_proposal(
         accounts.push(AccountMeta::new(signatory_record_address, false));
     }
 
+    let realm_config_address = get_realm_config_address(program_id, realm);
+    accounts.push(AccountMeta::new_readonly(realm_config_address, false));
+    accounts.push(AccountMeta::new_readonly(*governing_token_mint, false));
+
+    if let Some(max_voter_weight_record) = max_voter_weight_record {
+        accounts.push(AccountMeta::new_readonly(max_voter_weight_record, false));
+    }
+
     let instruction = GovernanceInstruction::SignOffProposal;
 
     Instruction {
@@ -1302,6 +1509,56 @@ pub fn cast_vote(
     }
 }
 
+/// Creates ChangeVote instruction
+#[allow(clippy::too_many_arguments)]
+pub fn change_vote(
+    program_id: &Pubkey,
+    // Accounts
+    realm: &Pubkey,
+    governance: &Pubkey,
+    proposal: &Pubkey,
+    proposal_owner_record: &Pubkey,
+    voter_token_owner_record: &Pubkey,
+    governance_authority: &Pubkey,
+    vote_governing_token_mint: &Pubkey,
+    payer: &Pubkey,
+    max_voter_weight_record: Option<Pubkey>,
+    // Args
+    vote: Vote,
+) -> Instruction {
+    let vote_record_address =
+        get_vote_record_address(program_id, proposal, voter_token_owner_record);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*realm, false),
+        AccountMeta::new(*governance, false),
+        AccountMeta::new(*proposal, false),
+        AccountMeta::new(*proposal_owner_record, false),
+        AccountMeta::new(*voter_token_owner_record, false),
+        AccountMeta::new_readonly(*governance_authority, true),
+        AccountMeta::new(vote_record_address, false),
+        AccountMeta::new_readonly(*vote_governing_token_mint, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    with_realm_config_accounts(
+        program_id,
+        &mut accounts,
+        realm,
+        None,
+        max_voter_weight_record,
+    );
+
+    let instruction = GovernanceInstruction::ChangeVote { vote };
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&instruction).unwrap(),
+    }
+}
+
 /// Creates FinalizeVote instruction
 pub fn finalize_vote(
     program_id: &Pubkey,
@@ -1418,6 +1675,7 @@ pub fn insert_transaction(
     index: u16,
     hold_up_time: u32,
     instructions: Vec<InstructionData>,
+    depends_on_option_index: Option<u8>,
 ) -> Instruction {
     let proposal_transaction_address = get_proposal_transaction_address(
         program_id,
@@ -1442,6 +1700,7 @@ pub fn insert_transaction(
         index,
         hold_up_time,
         instructions,
+        depends_on_option_index,
     };
 
     Instruction {
@@ -1572,7 +1831,7 @@ pub fn set_realm_authority(
                 false,
             ));
         }
-        SetRealmAuthorityAction::Remove => {}
+        SetRealmAuthorityAction::Remove | SetRealmAuthorityAction::CancelPending => {}
     }
 
     let instruction = GovernanceInstruction::SetRealmAuthority { action };
@@ -1584,6 +1843,27 @@ pub fn set_realm_authority(
     }
 }
 
+/// Creates AcceptRealmAuthority instruction
+pub fn accept_realm_authority(
+    program_id: &Pubkey,
+    // Accounts
+    realm: &Pubkey,
+    pending_realm_authority: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*realm, false),
+        AccountMeta::new_readonly(*pending_realm_authority, true),
+    ];
+
+    let instruction = GovernanceInstruction::AcceptRealmAuthority {};
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&instruction).unwrap(),
+    }
+}
+
 /// Creates SetRealmConfig instruction
 #[allow(clippy::too_many_arguments)]
 pub fn set_realm_config(
@@ -1940,6 +2220,33 @@ pub fn complete_proposal(
     }
 }
 
+/// Creates SetProposalHoldUpTime instruction
+pub fn set_proposal_hold_up_time(
+    program_id: &Pubkey,
+    // Accounts
+    governance: &Pubkey,
+    proposal: &Pubkey,
+    token_owner_record: &Pubkey,
+    governance_authority: &Pubkey,
+    // Args
+    hold_up_time: u32,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*governance, false),
+        AccountMeta::new(*proposal, false),
+        AccountMeta::new_readonly(*token_owner_record, false),
+        AccountMeta::new_readonly(*governance_authority, true),
+    ];
+
+    let instruction = GovernanceInstruction::SetProposalHoldUpTime { hold_up_time };
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&instruction).unwrap(),
+    }
+}
+
 /// Creates SetTokenOwnerRecordLock instruction to issue TokenOwnerRecord lock
 pub fn set_token_owner_record_lock(
     program_id: &Pubkey,
@@ -2019,7 +2326,7 @@ pub fn set_realm_config_item(
 ) -> Instruction {
     let realm_config_address = get_realm_config_address(program_id, realm);
 
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new(*realm, false),
         AccountMeta::new(realm_config_address, false),
         AccountMeta::new_readonly(*realm_authority, true),
@@ -2027,6 +2334,19 @@ pub fn set_realm_config_item(
         AccountMeta::new_readonly(system_program::id(), false),
     ];
 
+    if let SetRealmConfigItemArgs::GoverningTokenDepositTracking {
+        governing_token_mint,
+        ..
+    } = &args
+    {
+        let governing_token_holding_address =
+            get_governing_token_holding_address(program_id, realm, governing_token_mint);
+        accounts.push(AccountMeta::new_readonly(
+            governing_token_holding_address,
+            false,
+        ));
+    }
+
     let instruction = GovernanceInstruction::SetRealmConfigItem { args };
 
     Instruction {
@@ -2035,3 +2355,36 @@ pub fn set_realm_config_item(
         data: borsh::to_vec(&instruction).unwrap(),
     }
 }
+
+/// Creates ReconcileGoverningTokenHolding instruction
+pub fn reconcile_governing_token_holding(
+    program_id: &Pubkey,
+    // Accounts
+    realm: &Pubkey,
+    governance: &Pubkey,
+    governing_token_treasury: &Pubkey,
+    // Args
+    governing_token_mint: &Pubkey,
+) -> Instruction {
+    let governing_token_holding_address =
+        get_governing_token_holding_address(program_id, realm, governing_token_mint);
+
+    let realm_config_address = get_realm_config_address(program_id, realm);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*realm, false),
+        AccountMeta::new(governing_token_holding_address, false),
+        AccountMeta::new_readonly(realm_config_address, false),
+        AccountMeta::new_readonly(*governance, false),
+        AccountMeta::new(*governing_token_treasury, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let instruction = GovernanceInstruction::ReconcileGoverningTokenHolding {};
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&instruction).unwrap(),
+    }
+}
@@ -1,9 +1,11 @@
 //! Program processor
 
+mod process_accept_realm_authority;
 mod process_add_required_signatory;
 mod process_add_signatory;
 mod process_cancel_proposal;
 mod process_cast_vote;
+mod process_change_vote;
 mod process_complete_proposal;
 mod process_create_governance;
 mod process_create_mint_governance;
@@ -18,6 +20,7 @@ mod process_execute_transaction;
 mod process_finalize_vote;
 mod process_flag_transaction_error;
 mod process_insert_transaction;
+mod process_reconcile_governing_token_holding;
 mod process_refund_proposal_deposit;
 mod process_relinquish_token_owner_record_locks;
 mod process_relinquish_vote;
@@ -26,6 +29,7 @@ mod process_remove_transaction;
 mod process_revoke_governing_tokens;
 mod process_set_governance_config;
 mod process_set_governance_delegate;
+mod process_set_proposal_hold_up_time;
 mod process_set_realm_authority;
 mod process_set_realm_config;
 mod process_set_realm_config_item;
@@ -36,10 +40,12 @@ mod process_withdraw_governing_tokens;
 
 use {
     crate::{error::GovernanceError, instruction::GovernanceInstruction},
+    process_accept_realm_authority::*,
     process_add_required_signatory::*,
     process_add_signatory::*,
     process_cancel_proposal::*,
     process_cast_vote::*,
+    process_change_vote::*,
     process_complete_proposal::*,
     process_create_governance::*,
     process_create_mint_governance::*,
@@ -54,6 +60,7 @@ use {
     process_finalize_vote::*,
     process_flag_transaction_error::*,
     process_insert_transaction::*,
+    process_reconcile_governing_token_holding::*,
     process_refund_proposal_deposit::*,
     process_relinquish_token_owner_record_locks::*,
     process_relinquish_vote::*,
@@ -62,6 +69,7 @@ use {
     process_revoke_governing_tokens::*,
     process_set_governance_config::*,
     process_set_governance_delegate::*,
+    process_set_proposal_hold_up_time::*,
     process_set_realm_authority::*,
     process_set_realm_config::*,
     process_set_realm_config_item::*,
@@ -92,14 +100,16 @@ pub fn process_instruction(
         index,
         hold_up_time,
         instructions: _,
+        depends_on_option_index,
     } = instruction
     {
         // Do not dump instruction data into logs
         msg!(
-            "GOVERNANCE-INSTRUCTION: InsertInstruction {{option_index: {:?}, index: {:?}, hold_up_time: {:?} }}",
+            "GOVERNANCE-INSTRUCTION: InsertInstruction {{option_index: {:?}, index: {:?}, hold_up_time: {:?}, depends_on_option_index: {:?} }}",
             option_index,
             index,
-            hold_up_time
+            hold_up_time,
+            depends_on_option_index
         );
     } else {
         msg!("GOVERNANCE-INSTRUCTION: {:?}", instruction);
@@ -160,6 +170,8 @@ pub fn process_instruction(
             options,
             use_deny_option,
             proposal_seed,
+            category,
+            sign_off,
         } => process_create_proposal(
             program_id,
             accounts,
@@ -169,6 +181,8 @@ pub fn process_instruction(
             options,
             use_deny_option,
             proposal_seed,
+            category,
+            sign_off,
         ),
         GovernanceInstruction::AddSignatory { signatory } => {
             process_add_signatory(program_id, accounts, signatory)
@@ -192,6 +206,7 @@ pub fn process_instruction(
             index,
             hold_up_time,
             instructions,
+            depends_on_option_index,
         } => process_insert_transaction(
             program_id,
             accounts,
@@ -199,6 +214,7 @@ pub fn process_instruction(
             index,
             hold_up_time,
             instructions,
+            depends_on_option_index,
         ),
 
         GovernanceInstruction::RemoveTransaction {} => {
@@ -261,5 +277,21 @@ pub fn process_instruction(
         GovernanceInstruction::SetRealmConfigItem { args } => {
             process_set_realm_config_item(program_id, accounts, args)
         }
+
+        GovernanceInstruction::SetProposalHoldUpTime { hold_up_time } => {
+            process_set_proposal_hold_up_time(program_id, accounts, hold_up_time)
+        }
+
+        GovernanceInstruction::AcceptRealmAuthority {} => {
+            process_accept_realm_authority(program_id, accounts)
+        }
+
+        GovernanceInstruction::ReconcileGoverningTokenHolding {} => {
+            process_reconcile_governing_token_holding(program_id, accounts)
+        }
+
+        GovernanceInstruction::ChangeVote { vote } => {
+            process_change_vote(program_id, accounts, vote)
+        }
     }
 }
@@ -0,0 +1,37 @@
+//! Program state processor
+
+use {
+    crate::{error::GovernanceError, state::realm::get_realm_data},
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        pubkey::Pubkey,
+    },
+};
+
+/// Processes AcceptRealmAuthority instruction
+pub fn process_accept_realm_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let realm_info = next_account_info(account_info_iter)?; // 0
+    let pending_realm_authority_info = next_account_info(account_info_iter)?; // 1
+
+    let mut realm_data = get_realm_data(program_id, realm_info)?;
+
+    if realm_data.pending_authority != Some(*pending_realm_authority_info.key) {
+        return Err(GovernanceError::PendingAuthorityMismatch.into());
+    }
+
+    if !pending_realm_authority_info.is_signer {
+        return Err(GovernanceError::RealmAuthorityMustSign.into());
+    }
+
+    realm_data.authority = realm_data.pending_authority.take();
+
+    realm_data.serialize(&mut realm_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
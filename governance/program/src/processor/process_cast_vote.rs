@@ -117,34 +117,7 @@ pub fn process_cast_vote(
     proposal_data.assert_valid_vote(&vote)?;
 
     // Calculate Proposal voting weights
-    match &vote {
-        Vote::Approve(choices) => {
-            for (option, choice) in proposal_data.options.iter_mut().zip(choices) {
-                option.vote_weight = option
-                    .vote_weight
-                    .checked_add(choice.get_choice_weight(voter_weight)?)
-                    .unwrap();
-            }
-        }
-        Vote::Deny => {
-            proposal_data.deny_vote_weight = Some(
-                proposal_data
-                    .deny_vote_weight
-                    .unwrap()
-                    .checked_add(voter_weight)
-                    .unwrap(),
-            )
-        }
-        Vote::Veto => {
-            proposal_data.veto_vote_weight = proposal_data
-                .veto_vote_weight
-                .checked_add(voter_weight)
-                .unwrap();
-        }
-        Vote::Abstain => {
-            return Err(GovernanceError::NotSupportedVoteType.into());
-        }
-    }
+    proposal_data.add_vote_weight(&vote, voter_weight)?;
 
     let max_voter_weight = proposal_data.resolve_max_voter_weight(
         account_info_iter, // max_voter_weight_record  11
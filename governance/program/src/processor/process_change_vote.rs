@@ -0,0 +1,162 @@
+//! Program state processor
+
+use {
+    crate::state::{
+        governance::get_governance_data_for_realm,
+        proposal::get_proposal_data_for_governance,
+        realm::get_realm_data_for_governing_token_mint,
+        realm_config::get_realm_config_data_for_realm,
+        token_owner_record::{
+            get_token_owner_record_data_for_proposal_owner,
+            get_token_owner_record_data_for_realm_and_governing_mint,
+        },
+        vote_record::{
+            get_vote_kind, get_vote_record_data_for_proposal_and_token_owner_record, Vote,
+        },
+    },
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        clock::Clock,
+        entrypoint::ProgramResult,
+        msg,
+        pubkey::Pubkey,
+        rent::Rent,
+        sysvar::Sysvar,
+    },
+};
+
+/// Processes ChangeVote instruction
+pub fn process_change_vote(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vote: Vote,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let realm_info = next_account_info(account_info_iter)?; // 0
+    let governance_info = next_account_info(account_info_iter)?; // 1
+
+    let proposal_info = next_account_info(account_info_iter)?; // 2
+    let proposal_owner_record_info = next_account_info(account_info_iter)?; // 3
+
+    let voter_token_owner_record_info = next_account_info(account_info_iter)?; // 4
+    let governance_authority_info = next_account_info(account_info_iter)?; // 5
+
+    let vote_record_info = next_account_info(account_info_iter)?; // 6
+    let vote_governing_token_mint_info = next_account_info(account_info_iter)?; // 7
+
+    let payer_info = next_account_info(account_info_iter)?; // 8
+    let system_info = next_account_info(account_info_iter)?; // 9
+
+    let rent = Rent::get()?;
+    let clock = Clock::get()?;
+
+    let realm_data = get_realm_data_for_governing_token_mint(
+        program_id,
+        realm_info,
+        vote_governing_token_mint_info.key,
+    )?;
+
+    let mut governance_data =
+        get_governance_data_for_realm(program_id, governance_info, realm_info.key)?;
+
+    let mut proposal_data =
+        get_proposal_data_for_governance(program_id, proposal_info, governance_info.key)?;
+    proposal_data.assert_can_cast_vote(&governance_data.config, &vote, clock.unix_timestamp)?;
+
+    let mut voter_token_owner_record_data =
+        get_token_owner_record_data_for_realm_and_governing_mint(
+            program_id,
+            voter_token_owner_record_info,
+            &governance_data.realm,
+            vote_governing_token_mint_info.key,
+        )?;
+    voter_token_owner_record_data
+        .assert_token_owner_or_delegate_is_signer(governance_authority_info)?;
+
+    let mut vote_record_data = get_vote_record_data_for_proposal_and_token_owner_record(
+        program_id,
+        vote_record_info,
+        &realm_data,
+        proposal_info.key,
+        &proposal_data,
+        &voter_token_owner_record_data,
+    )?;
+    vote_record_data.assert_can_change_vote(&vote)?;
+
+    proposal_data.assert_valid_vote(&vote)?;
+
+    // Move the recorded voter weight from the old vote to the new one within the
+    // same instruction so it's never counted twice
+    proposal_data.remove_vote_weight(&vote_record_data.vote, vote_record_data.voter_weight)?;
+    proposal_data.add_vote_weight(&vote, vote_record_data.voter_weight)?;
+
+    msg!(
+        "GOVERNANCE-VOTE-CHANGED: {{old_vote: {:?}, new_vote: {:?}, voter_weight: {:?} }}",
+        vote_record_data.vote,
+        vote,
+        vote_record_data.voter_weight
+    );
+
+    let realm_config_info = next_account_info(account_info_iter)?; // 10
+    let realm_config_data =
+        get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
+
+    let vote_kind = get_vote_kind(&vote);
+
+    let max_voter_weight = proposal_data.resolve_max_voter_weight(
+        account_info_iter, // max_voter_weight_record  11
+        realm_info.key,
+        &realm_data,
+        &realm_config_data,
+        vote_governing_token_mint_info,
+        &vote_kind,
+    )?;
+
+    let vote_threshold = governance_data.resolve_vote_threshold(
+        &realm_data,
+        vote_governing_token_mint_info.key,
+        &vote_kind,
+    )?;
+
+    if proposal_data.try_tip_vote(
+        max_voter_weight,
+        governance_data.get_vote_tipping(&realm_data, vote_governing_token_mint_info.key)?,
+        clock.unix_timestamp,
+        &vote_threshold,
+        &vote_kind,
+    )? {
+        // Deserialize proposal owner and validate it's the actual owner of the proposal
+        let mut proposal_owner_record_data = get_token_owner_record_data_for_proposal_owner(
+            program_id,
+            proposal_owner_record_info,
+            &proposal_data.token_owner_record,
+        )?;
+
+        // If the voter is also the proposal owner then update the voter record which is
+        // serialized for the voter later on
+        if proposal_owner_record_info.key == voter_token_owner_record_info.key {
+            voter_token_owner_record_data.decrease_outstanding_proposal_count();
+        } else {
+            proposal_owner_record_data.decrease_outstanding_proposal_count();
+            proposal_owner_record_data
+                .serialize(&mut proposal_owner_record_info.data.borrow_mut()[..])?;
+        };
+
+        // If the proposal is tipped decrease Governance active_proposal_count
+        governance_data.active_proposal_count =
+            governance_data.active_proposal_count.saturating_sub(1);
+        governance_data.serialize(&mut governance_info.data.borrow_mut()[..])?;
+    }
+
+    voter_token_owner_record_data
+        .serialize(&mut voter_token_owner_record_info.data.borrow_mut()[..])?;
+
+    proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
+
+    vote_record_data.vote = vote;
+    // The new vote can be bigger than the old one, e.g. Deny changed to Approve
+    vote_record_data.serialize_with_resize(vote_record_info, payer_info, system_info, &rent)?;
+
+    Ok(())
+}
@@ -7,12 +7,17 @@ use {
             enums::{GovernanceAccountType, InstructionExecutionFlags, ProposalState},
             governance::get_governance_data_for_realm,
             proposal::{
-                assert_valid_proposal_options, get_proposal_address_seeds, OptionVoteResult,
-                ProposalOption, ProposalV2, VoteType,
+                assert_valid_proposal_description_link, assert_valid_proposal_options,
+                get_proposal_address_seeds, OptionVoteResult, ProposalOption, ProposalV2, VoteType,
+            },
+            proposal_category_record::{
+                get_proposal_category_record_address_seeds, ProposalCategoryRecord,
             },
             proposal_deposit::{get_proposal_deposit_address_seeds, ProposalDeposit},
             realm::get_realm_data_for_governing_token_mint,
             realm_config::get_realm_config_data_for_realm,
+            required_signatory::get_required_signatory_data_for_governance,
+            signatory_record::{get_signatory_record_address_seeds, SignatoryRecordV2},
             token_owner_record::get_token_owner_record_data_for_realm,
             vote_record::VoteKind,
         },
@@ -40,6 +45,8 @@ pub fn process_create_proposal(
     options: Vec<String>,
     use_deny_option: bool,
     proposal_seed: Pubkey,
+    category: Option<[u8; 8]>,
+    sign_off: bool,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -61,6 +68,8 @@ pub fn process_create_proposal(
         return Err(GovernanceError::ProposalAlreadyExists.into());
     }
 
+    assert_valid_proposal_description_link(&description_link)?;
+
     let realm_data = get_realm_data_for_governing_token_mint(
         program_id,
         realm_info,
@@ -107,10 +116,20 @@ pub fn process_create_proposal(
         voter_weight,
     )?;
 
+    // Ensure the Realm proposal creation policy allows the proposal owner to create
+    // community proposals
+    realm_config_data.assert_can_create_proposal(
+        &realm_data,
+        governing_token_mint_info.key,
+        &proposal_owner_record_data.governing_token_mint,
+        voter_weight,
+    )?;
+
     proposal_owner_record_data.outstanding_proposal_count = proposal_owner_record_data
         .outstanding_proposal_count
         .checked_add(1)
         .unwrap();
+    let proposal_owner = proposal_owner_record_data.governing_token_owner;
     proposal_owner_record_data.serialize(&mut proposal_owner_record_info.data.borrow_mut()[..])?;
 
     assert_valid_proposal_options(&options, &vote_type)?;
@@ -129,7 +148,7 @@ pub fn process_create_proposal(
 
     let deny_vote_weight = if use_deny_option { Some(0) } else { None };
 
-    let proposal_data = ProposalV2 {
+    let mut proposal_data = ProposalV2 {
         account_type: GovernanceAccountType::ProposalV2,
         governance: *governance_info.key,
         governing_token_mint: *governing_token_mint_info.key,
@@ -163,8 +182,11 @@ pub fn process_create_proposal(
         max_vote_weight: None,
         max_voting_time: None,
         vote_threshold: None,
+        hold_up_time: 0,
+        category: category.unwrap_or_default(),
 
-        reserved: [0; 64],
+        max_voting_weight_snapshot: 0,
+        reserved: [0; 44],
         reserved1: 0,
     };
 
@@ -209,6 +231,103 @@ pub fn process_create_proposal(
             &rent,
             proposal_deposit_amount,
         )?;
+    } else if category.is_some() || sign_off {
+        // The deposit account is always passed with a category or when signing
+        // off to keep the positions of the following accounts fixed
+        next_account_info(account_info_iter)?; // *10
+    }
+
+    // Record the category at a fixed offset so Proposals can be filtered by it
+    if let Some(category) = category {
+        let proposal_category_record_info = next_account_info(account_info_iter)?; // *11
+        let proposal_category_record_data = ProposalCategoryRecord {
+            account_type: GovernanceAccountType::ProposalCategoryRecord,
+            governance: *governance_info.key,
+            category,
+            proposal: *proposal_info.key,
+            reserved: [0; 32],
+        };
+
+        create_and_serialize_account_signed::<ProposalCategoryRecord>(
+            payer_info,
+            proposal_category_record_info,
+            &proposal_category_record_data,
+            &get_proposal_category_record_address_seeds(proposal_info.key),
+            program_id,
+            system_info,
+            &rent,
+            0,
+        )?;
+    }
+
+    // Sign off the Proposal on behalf of its owner who must be the only
+    // signatory and start voting
+    if sign_off {
+        let signatory_record_info = next_account_info(account_info_iter)?; // *12
+        let required_signatory_info = next_account_info(account_info_iter)?; // *13
+
+        let signatory = proposal_owner;
+
+        // The Governance can require at most one signatory who must be the
+        // Proposal owner
+        let is_sole_signatory = match governance_data.required_signatories_count {
+            0 => true,
+            1 => {
+                !required_signatory_info.data_is_empty()
+                    && get_required_signatory_data_for_governance(
+                        program_id,
+                        required_signatory_info,
+                        governance_info.key,
+                    )?
+                    .signatory
+                        == signatory
+            }
+            _ => false,
+        };
+
+        if !is_sole_signatory {
+            return Err(GovernanceError::ProposalOwnerNotSoleSignatory.into());
+        }
+
+        let signatory_record_data = SignatoryRecordV2 {
+            account_type: GovernanceAccountType::SignatoryRecordV2,
+            proposal: *proposal_info.key,
+            signatory,
+            signed_off: true,
+            reserved_v2: [0; 8],
+        };
+
+        create_and_serialize_account_signed::<SignatoryRecordV2>(
+            payer_info,
+            signatory_record_info,
+            &signatory_record_data,
+            &get_signatory_record_address_seeds(proposal_info.key, &signatory),
+            program_id,
+            system_info,
+            &rent,
+            0,
+        )?;
+
+        proposal_data.signatories_count = 1;
+        proposal_data.signatories_signed_off_count = 1;
+
+        proposal_data.signing_off_at = Some(clock.unix_timestamp);
+        proposal_data.voting_at = Some(clock.unix_timestamp);
+        proposal_data.voting_at_slot = Some(clock.slot);
+        proposal_data.state = ProposalState::Voting;
+
+        if realm_config_data.snapshot_max_voter_weight {
+            proposal_data.max_voting_weight_snapshot = proposal_data.resolve_max_voter_weight(
+                account_info_iter, // max_voter_weight_record  *14
+                realm_info.key,
+                &realm_data,
+                &realm_config_data,
+                governing_token_mint_info,
+                &VoteKind::Electorate,
+            )?;
+        }
+
+        proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
     }
 
     // Serialize the governance account update to GovernanceV2 if needed
@@ -6,14 +6,16 @@ use {
         state::{
             enums::GovernanceAccountType,
             realm::{
-                assert_valid_realm_config_args, get_governing_token_holding_address_seeds,
-                get_realm_address_seeds, RealmConfig, RealmConfigArgs, RealmV2,
+                assert_valid_realm_config_args, assert_valid_realm_name,
+                get_governing_token_holding_address_seeds, get_realm_address_seeds, RealmConfig,
+                RealmConfigArgs, RealmV2,
             },
             realm_config::{
-                get_realm_config_address_seeds, resolve_governing_token_config, RealmConfigAccount,
+                get_realm_config_address_seeds, resolve_governing_token_config,
+                ProposalCreationAuthority, RealmConfigAccount,
             },
         },
-        tools::{spl_token::create_spl_token_account_signed, structs::Reserved110},
+        tools::{spl_token::create_spl_token_account_signed, structs::Reserved49},
     },
     solana_program::{
         account_info::{next_account_info, AccountInfo},
@@ -45,12 +47,23 @@ pub fn process_create_realm(
     let rent_sysvar_info = next_account_info(account_info_iter)?; // 7
     let rent = &Rent::from_account_info(rent_sysvar_info)?;
 
+    // The name is the Realm PDA seed and must be validated before the Realm
+    // address can be derived from it
+    assert_valid_realm_name(&name)?;
+
+    // The Realm address is unique for the name
     if !realm_info.data_is_empty() {
         return Err(GovernanceError::RealmAlreadyExists.into());
     }
 
     assert_valid_realm_config_args(&realm_config_args)?;
 
+    // The community and council holding accounts are created by the same token
+    // program which must own both mints
+    if *spl_token_info.key != spl_token::id() && *spl_token_info.key != spl_token_2022::id() {
+        return Err(GovernanceError::InvalidGoverningTokenProgram.into());
+    }
+
     // Create Community token holding account
     create_spl_token_account_signed(
         payer_info,
@@ -110,7 +123,13 @@ pub fn process_create_realm(
         realm: *realm_info.key,
         community_token_config,
         council_token_config,
-        reserved: Reserved110::default(),
+        community_proposal_creation: ProposalCreationAuthority::default(),
+        snapshot_max_voter_weight: false,
+        // Deposit tracking is opt-in, see SetRealmConfigItemArgs::GoverningTokenDepositTracking
+        community_token_deposits: None,
+        council_token_deposits: None,
+        governing_token_program: Some(*spl_token_info.key),
+        reserved: Reserved49::default(),
     };
 
     create_and_serialize_account_signed::<RealmConfigAccount>(
@@ -143,7 +162,8 @@ pub fn process_create_realm(
             legacy2: 0,
         },
         legacy1: 0,
-        reserved_v2: [0; 128],
+        pending_authority: None,
+        reserved_v2: [0; 95],
     };
 
     create_and_serialize_account_signed::<RealmV2>(
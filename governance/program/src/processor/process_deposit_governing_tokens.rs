@@ -6,15 +6,15 @@ use {
         state::{
             enums::GovernanceAccountType,
             realm::get_realm_data,
-            realm_config::get_realm_config_data_for_realm,
+            realm_config::{get_realm_config_data_for_realm, GoverningTokenType},
             token_owner_record::{
                 get_token_owner_record_address_seeds, get_token_owner_record_data_for_seeds,
                 TokenOwnerRecordV2, TOKEN_OWNER_RECORD_LAYOUT_VERSION,
             },
         },
         tools::spl_token::{
-            get_spl_token_mint, is_spl_token_account, is_spl_token_mint, mint_spl_tokens_to,
-            transfer_spl_tokens,
+            get_spl_token_amount, get_spl_token_mint, is_spl_token_account, is_spl_token_mint,
+            mint_spl_tokens_to, transfer_spl_tokens, transfer_spl_tokens_checked,
         },
     },
     solana_program::{
@@ -58,20 +58,66 @@ pub fn process_deposit_governing_tokens(
         governing_token_holding_info.key,
     )?;
 
-    let realm_config_data =
+    let mut realm_config_data =
         get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
 
     realm_config_data.assert_can_deposit_governing_token(&realm_data, &governing_token_mint)?;
+    realm_config_data.assert_is_valid_governing_token_program(spl_token_info.key)?;
+
+    // Token-2022 transfers are checked against the governing token mint
+    let governing_token_mint_info = if *spl_token_info.key == spl_token_2022::id() {
+        let governing_token_mint_info = next_account_info(account_info_iter)?; // 10
+
+        if *governing_token_mint_info.key != governing_token_mint {
+            return Err(GovernanceError::InvalidGoverningTokenMint.into());
+        }
+
+        realm_config_data.assert_governing_token_mint_extensions_allowed(
+            &realm_data,
+            governing_token_mint_info,
+        )?;
+
+        Some(governing_token_mint_info)
+    } else {
+        None
+    };
+
+    // Membership tokens held by members could be traded, so new membership
+    // weight can only be minted by the mint authority or handed out by the
+    // Realm authority
+    if realm_config_data
+        .get_token_config(&realm_data, &governing_token_mint)?
+        .token_type
+        == GoverningTokenType::Membership
+        && !is_spl_token_mint(governing_token_source_info)
+        && !(governing_token_source_authority_info.is_signer
+            && realm_data.authority == Some(*governing_token_source_authority_info.key))
+    {
+        return Err(GovernanceError::MembershipDepositNotAuthorized.into());
+    }
+
+    let holding_amount = get_spl_token_amount(governing_token_holding_info)?;
 
     if is_spl_token_account(governing_token_source_info) {
         // If the source is spl-token token account then transfer tokens from it
-        transfer_spl_tokens(
-            governing_token_source_info,
-            governing_token_holding_info,
-            governing_token_source_authority_info,
-            amount,
-            spl_token_info,
-        )?;
+        if let Some(governing_token_mint_info) = governing_token_mint_info {
+            transfer_spl_tokens_checked(
+                governing_token_source_info,
+                governing_token_mint_info,
+                governing_token_holding_info,
+                governing_token_source_authority_info,
+                amount,
+                spl_token_info,
+            )?;
+        } else {
+            transfer_spl_tokens(
+                governing_token_source_info,
+                governing_token_holding_info,
+                governing_token_source_authority_info,
+                amount,
+                spl_token_info,
+            )?;
+        }
     } else if is_spl_token_mint(governing_token_source_info) {
         // If it's a mint then mint the tokens
         mint_spl_tokens_to(
@@ -85,6 +131,12 @@ pub fn process_deposit_governing_tokens(
         return Err(GovernanceError::InvalidGoverningTokenSource.into());
     }
 
+    // Only the amount received by the holding account is deposited, net of any
+    // Token-2022 transfer fee withheld from the transfer
+    let amount = get_spl_token_amount(governing_token_holding_info)?
+        .checked_sub(holding_amount)
+        .unwrap();
+
     let token_owner_record_address_seeds = get_token_owner_record_address_seeds(
         realm_info.key,
         &governing_token_mint,
@@ -138,5 +190,12 @@ pub fn process_deposit_governing_tokens(
         token_owner_record_data.serialize(&mut token_owner_record_info.data.borrow_mut()[..])?;
     }
 
+    realm_config_data.add_governing_token_deposits(
+        &realm_data,
+        &governing_token_mint,
+        amount,
+        realm_config_info,
+    )?;
+
     Ok(())
 }
@@ -13,7 +13,7 @@ use {
         clock::Clock,
         entrypoint::ProgramResult,
         instruction::Instruction,
-        program::invoke_signed,
+        program::{get_return_data, invoke_signed},
         pubkey::Pubkey,
         sysvar::Sysvar,
     },
@@ -82,8 +82,18 @@ pub fn process_execute_transaction(program_id: &Pubkey, accounts: &[AccountInfo]
         signers_seeds.push(&treasury_seeds[..]);
     }
 
+    // Capture the return data of the last instruction which set it. Data left
+    // by a program nested deeper in the invocation is ignored
+    let mut return_data = None;
+
     for instruction in instructions {
         invoke_signed(&instruction, instruction_account_infos, &signers_seeds[..])?;
+
+        if let Some((program_id, data)) = get_return_data() {
+            if program_id == instruction.program_id {
+                return_data = Some((program_id, data));
+            }
+        }
     }
 
     // Update proposal and instruction accounts
@@ -115,6 +125,15 @@ pub fn process_execute_transaction(program_id: &Pubkey, accounts: &[AccountInfo]
 
     proposal_transaction_data.executed_at = Some(clock.unix_timestamp);
     proposal_transaction_data.execution_status = TransactionExecutionStatus::Success;
+
+    if let Some((program_id, data)) = return_data {
+        proposal_transaction_data.set_return_data(
+            program_id,
+            data,
+            proposal_transaction_info.data_len(),
+        );
+    }
+
     proposal_transaction_data.serialize(&mut proposal_transaction_info.data.borrow_mut()[..])?;
 
     Ok(())
@@ -8,6 +8,7 @@ use {
             governance::get_governance_data,
             proposal::get_proposal_data_for_governance,
             proposal_transaction::{
+                assert_instructions_accounts_count, estimate_compute_units,
                 get_proposal_transaction_address_seeds, InstructionData, ProposalTransactionV2,
             },
             token_owner_record::get_token_owner_record_data_for_proposal_owner,
@@ -32,6 +33,7 @@ pub fn process_insert_transaction(
     instruction_index: u16,
     hold_up_time: u32,
     instructions: Vec<InstructionData>,
+    depends_on_option_index: Option<u8>,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -70,6 +72,16 @@ pub fn process_insert_transaction(
 
     token_owner_record_data.assert_token_owner_or_delegate_is_signer(governance_authority_info)?;
 
+    // Dependencies can only point to a lower option index which keeps the
+    // dependency graph acyclic
+    if let Some(depends_on_option_index) = depends_on_option_index {
+        if depends_on_option_index >= option_index {
+            return Err(GovernanceError::InvalidTransactionDependency.into());
+        }
+    }
+
+    assert_instructions_accounts_count(&instructions)?;
+
     let option = &mut proposal_data.options[option_index as usize];
 
     match instruction_index.cmp(&option.transactions_next_index) {
@@ -86,6 +98,8 @@ pub fn process_insert_transaction(
     option.transactions_count = option.transactions_count.checked_add(1).unwrap();
     proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
 
+    let estimated_compute_units = estimate_compute_units(&instructions);
+
     let proposal_transaction_data = ProposalTransactionV2 {
         account_type: GovernanceAccountType::ProposalTransactionV2,
         option_index,
@@ -95,7 +109,10 @@ pub fn process_insert_transaction(
         executed_at: None,
         execution_status: TransactionExecutionStatus::None,
         proposal: *proposal_info.key,
-        reserved_v2: [0; 8],
+        depends_on_option_index,
+        return_data: None,
+        estimated_compute_units,
+        reserved_v2: [0; 1],
     };
 
     create_and_serialize_account_signed::<ProposalTransactionV2>(
@@ -0,0 +1,92 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::GovernanceError,
+        state::{
+            governance::get_governance_data_for_realm,
+            native_treasury::get_native_treasury_address,
+            realm::{get_realm_address_seeds, get_realm_data},
+            realm_config::get_realm_config_data_for_realm,
+        },
+        tools::spl_token::{
+            get_spl_token_amount, get_spl_token_mint, get_spl_token_owner,
+            transfer_spl_tokens_signed,
+        },
+    },
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        pubkey::Pubkey,
+    },
+};
+
+/// Processes ReconcileGoverningTokenHolding instruction
+pub fn process_reconcile_governing_token_holding(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let realm_info = next_account_info(account_info_iter)?; // 0
+    let governing_token_holding_info = next_account_info(account_info_iter)?; // 1
+    let realm_config_info = next_account_info(account_info_iter)?; // 2
+    let governance_info = next_account_info(account_info_iter)?; // 3
+    let governing_token_treasury_info = next_account_info(account_info_iter)?; // 4
+    let spl_token_info = next_account_info(account_info_iter)?; // 5
+
+    let realm_data = get_realm_data(program_id, realm_info)?;
+    let governing_token_mint = get_spl_token_mint(governing_token_holding_info)?;
+
+    realm_data.assert_is_valid_governing_token_mint_and_holding(
+        program_id,
+        realm_info.key,
+        &governing_token_mint,
+        governing_token_holding_info.key,
+    )?;
+
+    let realm_config_data =
+        get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
+    realm_config_data.assert_is_valid_governing_token_program(spl_token_info.key)?;
+
+    // The tracked total is the sum of all TokenOwnerRecord deposits and only the
+    // balance above it can be swept, so no deposit can be reduced by the reconciliation
+    let governing_token_deposits = realm_config_data
+        .get_governing_token_deposits(&realm_data, &governing_token_mint)?
+        .ok_or(GovernanceError::GoverningTokenDepositsNotTracked)?;
+
+    let surplus_amount = get_spl_token_amount(governing_token_holding_info)?
+        .saturating_sub(governing_token_deposits);
+
+    if surplus_amount == 0 {
+        return Err(GovernanceError::GoverningTokenHoldingHasNoSurplus.into());
+    }
+
+    // The surplus can only be swept to a treasury of the Realm's governance
+    get_governance_data_for_realm(program_id, governance_info, realm_info.key)?;
+
+    if get_spl_token_mint(governing_token_treasury_info)? != governing_token_mint {
+        return Err(GovernanceError::InvalidGoverningTokenTreasury.into());
+    }
+
+    let governing_token_treasury_owner = get_spl_token_owner(governing_token_treasury_info)?;
+
+    if governing_token_treasury_owner != *governance_info.key
+        && governing_token_treasury_owner
+            != get_native_treasury_address(program_id, governance_info.key)
+    {
+        return Err(GovernanceError::InvalidGoverningTokenTreasury.into());
+    }
+
+    transfer_spl_tokens_signed(
+        governing_token_holding_info,
+        governing_token_treasury_info,
+        realm_info,
+        &get_realm_address_seeds(&realm_data.name),
+        program_id,
+        surplus_amount,
+        spl_token_info,
+    )?;
+
+    Ok(())
+}
@@ -4,12 +4,11 @@ use {
     crate::{
         error::GovernanceError,
         state::{
-            enums::ProposalState,
-            governance::get_governance_data_for_realm,
+            enums::ProposalState, governance::get_governance_data_for_realm,
             proposal::get_proposal_data_for_governance,
             realm::get_realm_data_for_governing_token_mint,
             token_owner_record::get_token_owner_record_data_for_realm_and_governing_mint,
-            vote_record::{get_vote_record_data_for_proposal_and_token_owner_record, Vote},
+            vote_record::get_vote_record_data_for_proposal_and_token_owner_record,
         },
     },
     solana_program::{
@@ -83,34 +82,7 @@ pub fn process_relinquish_vote(program_id: &Pubkey, accounts: &[AccountInfo]) ->
         token_owner_record_data
             .assert_token_owner_or_delegate_is_signer(governance_authority_info)?;
 
-        match vote_record_data.vote {
-            Vote::Approve(choices) => {
-                for (option, choice) in proposal_data.options.iter_mut().zip(choices) {
-                    option.vote_weight = option
-                        .vote_weight
-                        .checked_sub(choice.get_choice_weight(vote_record_data.voter_weight)?)
-                        .unwrap();
-                }
-            }
-            Vote::Deny => {
-                proposal_data.deny_vote_weight = Some(
-                    proposal_data
-                        .deny_vote_weight
-                        .unwrap()
-                        .checked_sub(vote_record_data.voter_weight)
-                        .unwrap(),
-                )
-            }
-            Vote::Veto => {
-                proposal_data.veto_vote_weight = proposal_data
-                    .veto_vote_weight
-                    .checked_sub(vote_record_data.voter_weight)
-                    .unwrap();
-            }
-            Vote::Abstain => {
-                return Err(GovernanceError::NotSupportedVoteType.into());
-            }
-        }
+        proposal_data.remove_vote_weight(&vote_record_data.vote, vote_record_data.voter_weight)?;
 
         proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
 
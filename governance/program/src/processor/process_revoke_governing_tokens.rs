@@ -45,11 +45,12 @@ pub fn process_revoke_governing_tokens(
         governing_token_holding_info.key,
     )?;
 
-    let realm_config_data =
+    let mut realm_config_data =
         get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
 
     realm_config_data
         .assert_can_revoke_governing_token(&realm_data, governing_token_mint_info.key)?;
+    realm_config_data.assert_is_valid_governing_token_program(spl_token_info.key)?;
 
     let mut token_owner_record_data = get_token_owner_record_data_for_realm_and_governing_mint(
         program_id,
@@ -80,6 +81,13 @@ pub fn process_revoke_governing_tokens(
 
     token_owner_record_data.serialize(&mut token_owner_record_info.data.borrow_mut()[..])?;
 
+    realm_config_data.sub_governing_token_deposits(
+        &realm_data,
+        governing_token_mint_info.key,
+        amount,
+        realm_config_info,
+    )?;
+
     burn_spl_tokens_signed(
         governing_token_holding_info,
         governing_token_mint_info,
@@ -0,0 +1,53 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::GovernanceError,
+        state::{
+            governance::get_governance_data, proposal::get_proposal_data_for_governance,
+            token_owner_record::get_token_owner_record_data_for_proposal_owner,
+        },
+    },
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        pubkey::Pubkey,
+    },
+};
+
+/// Processes SetProposalHoldUpTime instruction
+pub fn process_set_proposal_hold_up_time(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    hold_up_time: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let governance_info = next_account_info(account_info_iter)?; // 0
+    let proposal_info = next_account_info(account_info_iter)?; // 1
+    let token_owner_record_info = next_account_info(account_info_iter)?; // 2
+    let governance_authority_info = next_account_info(account_info_iter)?; // 3
+
+    let governance_data = get_governance_data(program_id, governance_info)?;
+
+    if hold_up_time < governance_data.config.min_transaction_hold_up_time {
+        return Err(GovernanceError::InvalidHoldUpTime.into());
+    }
+
+    let mut proposal_data =
+        get_proposal_data_for_governance(program_id, proposal_info, governance_info.key)?;
+    proposal_data.assert_can_edit_instructions()?;
+
+    let token_owner_record_data = get_token_owner_record_data_for_proposal_owner(
+        program_id,
+        token_owner_record_info,
+        &proposal_data.token_owner_record,
+    )?;
+
+    token_owner_record_data.assert_token_owner_or_delegate_is_signer(governance_authority_info)?;
+
+    proposal_data.hold_up_time = hold_up_time;
+    proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
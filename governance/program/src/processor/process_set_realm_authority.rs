@@ -4,6 +4,7 @@ use {
     crate::{
         error::GovernanceError,
         state::{
+            enums::GovernanceAccountType,
             governance::assert_governance_for_realm,
             realm::{get_realm_data_for_authority, SetRealmAuthorityAction},
         },
@@ -11,6 +12,7 @@ use {
     solana_program::{
         account_info::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
+        program_error::ProgramError,
         pubkey::Pubkey,
     },
 };
@@ -33,21 +35,36 @@ pub fn process_set_realm_authority(
         return Err(GovernanceError::RealmAuthorityMustSign.into());
     }
 
-    let new_realm_authority = match action {
-        SetRealmAuthorityAction::SetUnchecked | SetRealmAuthorityAction::SetChecked => {
+    match action {
+        SetRealmAuthorityAction::SetUnchecked => {
             let new_realm_authority_info = next_account_info(account_info_iter)?; // 2
 
-            if action == SetRealmAuthorityAction::SetChecked {
-                // Ensure the new realm authority is one of the governances from the realm
-                assert_governance_for_realm(program_id, new_realm_authority_info, realm_info.key)?;
+            // RealmV1 accounts can't be resized and have no space for the pending authority
+            if realm_data.account_type == GovernanceAccountType::RealmV1 {
+                return Err(ProgramError::InvalidAccountData);
             }
 
-            Some(*new_realm_authority_info.key)
+            // The new authority could be a wrong or uncontrolled account, so it only becomes
+            // the authority once it accepts it with AcceptRealmAuthority
+            realm_data.pending_authority = Some(*new_realm_authority_info.key);
         }
-        SetRealmAuthorityAction::Remove => None,
-    };
+        SetRealmAuthorityAction::SetChecked => {
+            let new_realm_authority_info = next_account_info(account_info_iter)?; // 2
+
+            // Ensure the new realm authority is one of the governances from the realm
+            assert_governance_for_realm(program_id, new_realm_authority_info, realm_info.key)?;
 
-    realm_data.authority = new_realm_authority;
+            realm_data.authority = Some(*new_realm_authority_info.key);
+            realm_data.pending_authority = None;
+        }
+        SetRealmAuthorityAction::Remove => {
+            realm_data.authority = None;
+            realm_data.pending_authority = None;
+        }
+        SetRealmAuthorityAction::CancelPending => {
+            realm_data.pending_authority = None;
+        }
+    }
 
     realm_data.serialize(&mut realm_info.data.borrow_mut()[..])?;
 
@@ -5,9 +5,9 @@ use {
         error::GovernanceError,
         state::{
             realm::{get_realm_data_for_authority, SetRealmConfigItemArgs},
-            realm_config::get_realm_config_data_for_realm,
+            realm_config::{get_realm_config_data_for_realm, ProposalCreationAuthority},
         },
-        tools::structs::SetConfigItemActionType,
+        tools::{spl_token::get_spl_token_amount, structs::SetConfigItemActionType},
     },
     solana_program::{
         account_info::{next_account_info, AccountInfo},
@@ -76,6 +76,57 @@ pub fn process_set_realm_config_item(
                 }
             }
         }
+        SetRealmConfigItemArgs::CommunityProposalCreation { authority } => {
+            // CouncilOnly would lock out community proposals for Realms without council
+            if authority == ProposalCreationAuthority::CouncilOnly
+                && realm_data.config.council_mint.is_none()
+            {
+                return Err(GovernanceError::InvalidGoverningTokenMint.into());
+            }
+
+            realm_config_data.community_proposal_creation = authority;
+        }
+        SetRealmConfigItemArgs::MaxVoterWeightSnapshot { enabled } => {
+            realm_config_data.snapshot_max_voter_weight = enabled;
+        }
+        SetRealmConfigItemArgs::GoverningTokenRestrictedExtensions {
+            governing_token_mint,
+            allowed,
+        } => {
+            realm_config_data
+                .get_token_config_mut(&realm_data, &governing_token_mint)?
+                .allow_restricted_extensions = allowed;
+        }
+        SetRealmConfigItemArgs::GoverningTokenDepositTracking {
+            governing_token_mint,
+            enabled,
+            deposits,
+        } => {
+            let governing_token_holding_info = next_account_info(account_info_iter)?; // 5
+
+            realm_data.assert_is_valid_governing_token_mint_and_holding(
+                program_id,
+                realm_info.key,
+                &governing_token_mint,
+                governing_token_holding_info.key,
+            )?;
+
+            let governing_token_deposits = realm_config_data
+                .get_governing_token_deposits_mut(&realm_data, &governing_token_mint)?;
+
+            if !enabled {
+                *governing_token_deposits = None;
+            } else if governing_token_deposits.is_none() {
+                // The deposits of the existing TokenOwnerRecords can't be summed up on-chain
+                // and are given by the Realm authority instead
+                // Any holding balance above them is surplus which can be swept
+                if deposits > get_spl_token_amount(governing_token_holding_info)? {
+                    return Err(GovernanceError::InvalidGoverningTokenDeposits.into());
+                }
+
+                *governing_token_deposits = Some(deposits);
+            }
+        }
     }
 
     realm_config_data.serialize(
@@ -4,10 +4,14 @@ use {
     crate::{
         error::GovernanceError,
         state::{
-            enums::ProposalState, governance::get_governance_data_for_realm,
-            proposal::get_proposal_data_for_governance, realm::assert_is_valid_realm,
+            enums::{GovernanceAccountType, ProposalState},
+            governance::get_governance_data_for_realm,
+            proposal::get_proposal_data_for_governance,
+            realm::{assert_is_valid_realm, get_realm_data_for_governing_token_mint},
+            realm_config::get_realm_config_data_for_realm,
             signatory_record::get_signatory_record_data_for_seeds,
             token_owner_record::get_token_owner_record_data_for_proposal_owner,
+            vote_record::VoteKind,
         },
     },
     solana_program::{
@@ -94,6 +98,37 @@ pub fn process_sign_off_proposal(program_id: &Pubkey, accounts: &[AccountInfo])
         proposal_data.voting_at = Some(clock.unix_timestamp);
         proposal_data.voting_at_slot = Some(clock.slot);
         proposal_data.state = ProposalState::Voting;
+
+        let realm_config_info = next_account_info(account_info_iter)?; // 5
+        let realm_config_data =
+            get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
+
+        // ProposalV1 accounts have no space for the snapshot and keep using the
+        // current max voter weight
+        if realm_config_data.snapshot_max_voter_weight
+            && proposal_data.account_type == GovernanceAccountType::ProposalV2
+        {
+            let governing_token_mint_info = next_account_info(account_info_iter)?; // 6
+
+            if proposal_data.governing_token_mint != *governing_token_mint_info.key {
+                return Err(GovernanceError::InvalidGoverningMintForProposal.into());
+            }
+
+            let realm_data = get_realm_data_for_governing_token_mint(
+                program_id,
+                realm_info,
+                governing_token_mint_info.key,
+            )?;
+
+            proposal_data.max_voting_weight_snapshot = proposal_data.resolve_max_voter_weight(
+                account_info_iter, // max_voter_weight_record  7
+                realm_info.key,
+                &realm_data,
+                &realm_config_data,
+                governing_token_mint_info,
+                &VoteKind::Electorate,
+            )?;
+        }
     }
 
     proposal_data.serialize(&mut proposal_info.data.borrow_mut()[..])?;
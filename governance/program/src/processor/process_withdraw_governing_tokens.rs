@@ -10,7 +10,9 @@ use {
                 get_token_owner_record_address_seeds, get_token_owner_record_data_for_seeds,
             },
         },
-        tools::spl_token::{get_spl_token_mint, transfer_spl_tokens_signed},
+        tools::spl_token::{
+            get_spl_token_mint, transfer_spl_tokens_checked_signed, transfer_spl_tokens_signed,
+        },
     },
     solana_program::{
         account_info::{next_account_info, AccountInfo},
@@ -51,10 +53,11 @@ pub fn process_withdraw_governing_tokens(
         governing_token_holding_info.key,
     )?;
 
-    let realm_config_data =
+    let mut realm_config_data =
         get_realm_config_data_for_realm(program_id, realm_config_info, realm_info.key)?;
 
     realm_config_data.assert_can_withdraw_governing_token(&realm_data, &governing_token_mint)?;
+    realm_config_data.assert_is_valid_governing_token_program(spl_token_info.key)?;
 
     let token_owner_record_address_seeds = get_token_owner_record_address_seeds(
         realm_info.key,
@@ -70,14 +73,41 @@ pub fn process_withdraw_governing_tokens(
 
     token_owner_record_data.assert_can_withdraw_governing_tokens(clock.unix_timestamp)?;
 
-    transfer_spl_tokens_signed(
-        governing_token_holding_info,
-        governing_token_destination_info,
-        realm_info,
-        &get_realm_address_seeds(&realm_data.name),
-        program_id,
+    // Token-2022 transfers are checked against the governing token mint
+    if *spl_token_info.key == spl_token_2022::id() {
+        let governing_token_mint_info = next_account_info(account_info_iter)?; // 7
+
+        if *governing_token_mint_info.key != governing_token_mint {
+            return Err(GovernanceError::InvalidGoverningTokenMint.into());
+        }
+
+        transfer_spl_tokens_checked_signed(
+            governing_token_holding_info,
+            governing_token_mint_info,
+            governing_token_destination_info,
+            realm_info,
+            &get_realm_address_seeds(&realm_data.name),
+            program_id,
+            token_owner_record_data.governing_token_deposit_amount,
+            spl_token_info,
+        )?;
+    } else {
+        transfer_spl_tokens_signed(
+            governing_token_holding_info,
+            governing_token_destination_info,
+            realm_info,
+            &get_realm_address_seeds(&realm_data.name),
+            program_id,
+            token_owner_record_data.governing_token_deposit_amount,
+            spl_token_info,
+        )?;
+    }
+
+    realm_config_data.sub_governing_token_deposits(
+        &realm_data,
+        &governing_token_mint,
         token_owner_record_data.governing_token_deposit_amount,
-        spl_token_info,
+        realm_config_info,
     )?;
 
     token_owner_record_data.governing_token_deposit_amount = 0;
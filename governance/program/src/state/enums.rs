@@ -102,6 +102,9 @@ pub enum GovernanceAccountType {
 
     /// Required signatory account
     RequiredSignatory,
+
+    /// Proposal category record account
+    ProposalCategoryRecord,
 }
 
 /// What state a Proposal is in
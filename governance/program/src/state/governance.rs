@@ -164,7 +164,8 @@ pub fn is_governance_v2_account_type(account_type: &GovernanceAccountType) -> bo
         | GovernanceAccountType::VoteRecordV2
         | GovernanceAccountType::ProgramMetadata
         | GovernanceAccountType::ProposalDeposit
-        | GovernanceAccountType::RequiredSignatory => false,
+        | GovernanceAccountType::RequiredSignatory
+        | GovernanceAccountType::ProposalCategoryRecord => false,
     }
 }
 
@@ -200,7 +201,8 @@ pub fn try_get_governance_v2_type_for_v1(
         | GovernanceAccountType::VoteRecordV2
         | GovernanceAccountType::ProgramMetadata
         | GovernanceAccountType::ProposalDeposit
-        | GovernanceAccountType::RequiredSignatory => None,
+        | GovernanceAccountType::RequiredSignatory
+        | GovernanceAccountType::ProposalCategoryRecord => None,
     }
 }
 
@@ -249,7 +251,8 @@ impl GovernanceV2 {
             | GovernanceAccountType::RealmV2
             | GovernanceAccountType::TokenOwnerRecordV2
             | GovernanceAccountType::SignatoryRecordV2
-            | GovernanceAccountType::RequiredSignatory => {
+            | GovernanceAccountType::RequiredSignatory
+            | GovernanceAccountType::ProposalCategoryRecord => {
                 return Err(GovernanceToolsError::InvalidAccountType.into())
             }
         };
@@ -163,7 +163,8 @@ pub fn is_governance_v1_account_type(account_type: &GovernanceAccountType) -> bo
         | GovernanceAccountType::VoteRecordV2
         | GovernanceAccountType::ProgramMetadata
         | GovernanceAccountType::ProposalDeposit
-        | GovernanceAccountType::RequiredSignatory => false,
+        | GovernanceAccountType::RequiredSignatory
+        | GovernanceAccountType::ProposalCategoryRecord => false,
     }
 }
 
@@ -236,13 +236,29 @@ pub struct ProposalV2 {
     /// (only higher value possible)
     pub vote_threshold: Option<VoteThreshold>,
 
+    /// Min hold up time (in seconds) applied to all the Proposal's transactions
+    /// when higher than their own hold up time (only higher value than the
+    /// parent Governance min_transaction_hold_up_time possible). 0 if not set
+    pub hold_up_time: u32,
+
+    /// Optional category tag used to organise proposals off-chain, zero padded
+    /// and all zeros if not set
+    /// It's metadata only and isn't used by the program
+    pub category: [u8; 8],
+
+    /// The max voter weight captured when voting started, used instead of the
+    /// current max voter weight to resolve Electorate votes. 0 if the Realm
+    /// doesn't snapshot the max voter weight
+    pub max_voting_weight_snapshot: u64,
+
     /// Reserved space for future versions
-    pub reserved: [u8; 64],
+    pub reserved: [u8; 44],
 
     /// Proposal name
     pub name: String,
 
-    /// Link to proposal's description
+    /// UTF-8 link to proposal's description, at most MAX_DESCRIPTION_LINK_LEN
+    /// bytes for Proposals created after the limit was introduced
     pub description_link: String,
 
     /// The total weight of Veto votes
@@ -445,6 +461,48 @@ impl ProposalV2 {
         Ok(())
     }
 
+    /// Returns true if the weight cast in the completed vote reached the vote
+    /// threshold captured when the vote ended, regardless of the outcome
+    /// It tells apart Defeated proposals which nobody voted on from those
+    /// which had enough participation but lost
+    ///
+    /// Note: For MultiChoice votes a voter's weight can count towards several
+    /// options and only the best option's weight is counted
+    pub fn participation_met(&self) -> bool {
+        // Vetoed proposals capture the veto vote params instead
+        if self.state == ProposalState::Vetoed {
+            return false;
+        }
+
+        let min_vote_threshold_weight = match (self.max_vote_weight, &self.vote_threshold) {
+            (Some(max_vote_weight), Some(vote_threshold)) => {
+                match get_min_vote_threshold_weight(vote_threshold, max_vote_weight) {
+                    Ok(min_vote_threshold_weight) => min_vote_threshold_weight,
+                    Err(_) => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        let options_vote_weight = match self.vote_type {
+            VoteType::SingleChoice => self.options.iter().fold(0u64, |weight, option| {
+                weight.saturating_add(option.vote_weight)
+            }),
+            VoteType::MultiChoice { .. } => self
+                .options
+                .iter()
+                .map(|option| option.vote_weight)
+                .max()
+                .unwrap_or(0),
+        };
+
+        let cast_vote_weight = options_vote_weight
+            .saturating_add(self.deny_vote_weight.unwrap_or(0))
+            .saturating_add(self.abstain_vote_weight.unwrap_or(0));
+
+        cast_vote_weight >= min_vote_threshold_weight
+    }
+
     /// Resolves final proposal state after vote ends
     /// It inspects all proposals options and resolves their final vote results
     fn resolve_final_vote_state(
@@ -613,9 +671,23 @@ impl ProposalV2 {
         vote_governing_token_mint_info: &AccountInfo,
         vote_kind: &VoteKind,
     ) -> Result<u64, ProgramError> {
+        // If the max voter weight was captured when voting started then Electorate
+        // votes are resolved against it and changes to the supply or the max voter
+        // weight record during the vote have no effect
+        if *vote_kind == VoteKind::Electorate && self.max_voting_weight_snapshot > 0 {
+            return Ok(self.coerce_max_voter_weight(self.max_voting_weight_snapshot, vote_kind));
+        }
+
         // if the Realm is configured to use max voter weight for the given voting
         // governing_token_mint then use the externally provided max_voter_weight
         // instead of the supply based max
+        //
+        // Note: The supply based max is only used when the Realm has no max voter
+        // weight addin. If the addin is configured then a record for a different
+        // realm or governing_token_mint, or an expired record, is rejected instead
+        // of falling back to the supply. The supply of a mint used with an addin
+        // can be arbitrary and the fallback would let anyone skew the quorum by
+        // omitting the record
         if let Some(max_voter_weight_addin) = realm_config_data
             .get_token_config(realm_data, vote_governing_token_mint_info.key)?
             .max_voter_weight_addin
@@ -863,10 +935,28 @@ impl ProposalV2 {
             return Err(GovernanceError::CannotExecuteDefeatedOption.into());
         }
 
+        if let Some(depends_on_option_index) = proposal_transaction_data.depends_on_option_index {
+            let depends_on_option = self
+                .options
+                .get(depends_on_option_index as usize)
+                .ok_or(GovernanceError::InvalidTransactionDependency)?;
+
+            if depends_on_option.vote_result != OptionVoteResult::Succeeded
+                || depends_on_option.transactions_executed_count
+                    < depends_on_option.transactions_count
+            {
+                return Err(GovernanceError::TransactionDependencyNotExecuted.into());
+            }
+        }
+
+        let hold_up_time = proposal_transaction_data
+            .hold_up_time
+            .max(self.hold_up_time);
+
         if self
             .voting_completed_at
             .unwrap()
-            .checked_add(proposal_transaction_data.hold_up_time as i64)
+            .checked_add(hold_up_time as i64)
             .unwrap()
             >= current_unix_timestamp
         {
@@ -1002,6 +1092,71 @@ impl ProposalV2 {
         Ok(())
     }
 
+    /// Adds the voter weight of the given vote to the Proposal vote weights
+    pub fn add_vote_weight(&mut self, vote: &Vote, voter_weight: u64) -> Result<(), ProgramError> {
+        match vote {
+            Vote::Approve(choices) => {
+                for (option, choice) in self.options.iter_mut().zip(choices) {
+                    option.vote_weight = option
+                        .vote_weight
+                        .checked_add(choice.get_choice_weight(voter_weight)?)
+                        .unwrap();
+                }
+            }
+            Vote::Deny => {
+                self.deny_vote_weight = Some(
+                    self.deny_vote_weight
+                        .unwrap()
+                        .checked_add(voter_weight)
+                        .unwrap(),
+                )
+            }
+            Vote::Veto => {
+                self.veto_vote_weight = self.veto_vote_weight.checked_add(voter_weight).unwrap();
+            }
+            Vote::Abstain => {
+                return Err(GovernanceError::NotSupportedVoteType.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the voter weight of the given vote from the Proposal vote
+    /// weights
+    pub fn remove_vote_weight(
+        &mut self,
+        vote: &Vote,
+        voter_weight: u64,
+    ) -> Result<(), ProgramError> {
+        match vote {
+            Vote::Approve(choices) => {
+                for (option, choice) in self.options.iter_mut().zip(choices) {
+                    option.vote_weight = option
+                        .vote_weight
+                        .checked_sub(choice.get_choice_weight(voter_weight)?)
+                        .unwrap();
+                }
+            }
+            Vote::Deny => {
+                self.deny_vote_weight = Some(
+                    self.deny_vote_weight
+                        .unwrap()
+                        .checked_sub(voter_weight)
+                        .unwrap(),
+                )
+            }
+            Vote::Veto => {
+                self.veto_vote_weight = self.veto_vote_weight.checked_sub(voter_weight).unwrap();
+            }
+            Vote::Abstain => {
+                return Err(GovernanceError::NotSupportedVoteType.into());
+            }
+        }
+
+        Ok(())
+    }
+
     /// Serializes account into the target buffer
     pub fn serialize<W: Write>(self, writer: W) -> Result<(), ProgramError> {
         if self.account_type == GovernanceAccountType::ProposalV2 {
@@ -1026,6 +1181,18 @@ impl ProposalV2 {
                 panic!("ProposalV1 doesn't support max voting time")
             }
 
+            if self.hold_up_time > 0 {
+                panic!("ProposalV1 doesn't support hold up time")
+            }
+
+            if self.category != [0; 8] {
+                panic!("ProposalV1 doesn't support category")
+            }
+
+            if self.max_voting_weight_snapshot > 0 {
+                panic!("ProposalV1 doesn't support max voting weight snapshot")
+            }
+
             if self.options.len() != 1 {
                 panic!("ProposalV1 doesn't support multiple options")
             }
@@ -1149,7 +1316,10 @@ pub fn get_proposal_data(
             vote_threshold: proposal_data_v1.vote_threshold,
             name: proposal_data_v1.name,
             description_link: proposal_data_v1.description_link,
-            reserved: [0; 64],
+            hold_up_time: 0,
+            category: [0; 8],
+            max_voting_weight_snapshot: 0,
+            reserved: [0; 44],
             reserved1: 0,
         });
     }
@@ -1217,6 +1387,19 @@ pub fn get_proposal_address<'a>(
     .0
 }
 
+/// Max length in bytes of the Proposal description link
+pub const MAX_DESCRIPTION_LINK_LEN: usize = 512;
+
+/// Asserts the Proposal description link is within MAX_DESCRIPTION_LINK_LEN.
+/// The link is valid UTF-8 once deserialized as a String
+pub fn assert_valid_proposal_description_link(description_link: &str) -> Result<(), ProgramError> {
+    if description_link.len() > MAX_DESCRIPTION_LINK_LEN {
+        return Err(GovernanceError::DescriptionTooLong.into());
+    }
+
+    Ok(())
+}
+
 /// Assert options to create proposal are valid for the Proposal vote_type
 pub fn assert_valid_proposal_options(
     options: &[String],
@@ -1308,7 +1491,10 @@ mod test {
             max_voting_time: Some(0),
             vote_threshold: Some(VoteThreshold::YesVotePercentage(100)),
 
-            reserved: [0; 64],
+            hold_up_time: 0,
+            category: [0; 8],
+            max_voting_weight_snapshot: 0,
+            reserved: [0; 44],
             reserved1: 0,
         }
     }
@@ -1364,7 +1550,8 @@ mod test {
                 min_community_weight_to_create_governance: 10,
             },
             legacy1: 0,
-            reserved_v2: [0; 128],
+            pending_authority: None,
+            reserved_v2: [0; 95],
         }
     }
 
@@ -1400,6 +1587,90 @@ mod test {
         assert_eq!(proposal.get_max_size(), Some(size));
     }
 
+    #[test]
+    fn test_description_link_serialisation_roundtrip() {
+        for description_link in ["".to_string(), "x".repeat(MAX_DESCRIPTION_LINK_LEN)] {
+            // Arrange
+            let mut proposal = create_test_proposal();
+            proposal.description_link = description_link;
+
+            // Act
+            let data = borsh::to_vec(&proposal).unwrap();
+
+            // Assert
+            assert_eq!(
+                assert_valid_proposal_description_link(&proposal.description_link),
+                Ok(())
+            );
+            assert_eq!(ProposalV2::try_from_slice(&data).unwrap(), proposal);
+            assert!(data.len() <= proposal.get_max_size().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_description_link_too_long_error() {
+        // Arrange
+        let description_link = "x".repeat(MAX_DESCRIPTION_LINK_LEN + 1);
+
+        // Act
+        let err = assert_valid_proposal_description_link(&description_link)
+            .err()
+            .unwrap();
+
+        // Assert
+        assert_eq!(err, GovernanceError::DescriptionTooLong.into());
+    }
+
+    #[test]
+    fn test_description_link_length_is_counted_in_bytes() {
+        // Arrange
+        // Each character takes 2 bytes in UTF-8
+        let description_link = "é".repeat(MAX_DESCRIPTION_LINK_LEN / 2 + 1);
+
+        // Act
+        let err = assert_valid_proposal_description_link(&description_link)
+            .err()
+            .unwrap();
+
+        // Assert
+        assert_eq!(err, GovernanceError::DescriptionTooLong.into());
+    }
+
+    #[test]
+    fn test_category_serialisation_roundtrip() {
+        // Arrange
+        let proposal = create_test_proposal();
+
+        let mut categorised_proposal = proposal.clone();
+        categorised_proposal.category = *b"treasury";
+
+        // Act
+        let data = borsh::to_vec(&proposal).unwrap();
+        let categorised_data = borsh::to_vec(&categorised_proposal).unwrap();
+
+        // Assert
+        assert_eq!(
+            ProposalV2::try_from_slice(&categorised_data).unwrap(),
+            categorised_proposal
+        );
+        assert_eq!(categorised_data.len(), data.len());
+
+        // Only the category bytes change, the other fields keep their offsets
+        let category_offset = data.len()
+            - (8 + 8 + 44 + 4 + proposal.name.len() + 4 + proposal.description_link.len() + 8);
+        let changed_offsets: Vec<usize> = (0..data.len())
+            .filter(|i| data[*i] != categorised_data[*i])
+            .collect();
+        assert_eq!(
+            changed_offsets,
+            (category_offset..category_offset + 8).collect::<Vec<usize>>()
+        );
+        assert_eq!(
+            &categorised_data[category_offset..category_offset + 8],
+            b"treasury"
+        );
+    }
+
     #[test]
     fn test_multi_option_proposal_max_size() {
         let mut proposal = create_test_multi_option_proposal();
@@ -2302,6 +2573,93 @@ mod test {
         assert_eq!(proposal.max_vote_weight, Some(100));
     }
 
+    #[test]
+    fn test_participation_met_for_defeated_vote_with_high_participation() {
+        // Arrange
+        let mut proposal = create_test_proposal();
+
+        proposal.options[0].vote_weight = 30;
+        proposal.deny_vote_weight = Some(60);
+
+        proposal.state = ProposalState::Voting;
+
+        let governance_config = create_test_governance_config();
+        let vote_threshold = VoteThreshold::YesVotePercentage(60);
+
+        // Act
+        proposal
+            .finalize_vote(100, &governance_config, 16_i64, &vote_threshold)
+            .unwrap();
+
+        // Assert
+        assert_eq!(proposal.state, ProposalState::Defeated);
+        assert!(proposal.participation_met());
+    }
+
+    #[test]
+    fn test_participation_not_met_for_defeated_vote_without_votes() {
+        // Arrange
+        let mut proposal = create_test_proposal();
+
+        proposal.options[0].vote_weight = 0;
+        proposal.deny_vote_weight = Some(0);
+
+        proposal.state = ProposalState::Voting;
+
+        let governance_config = create_test_governance_config();
+        let vote_threshold = VoteThreshold::YesVotePercentage(60);
+
+        // Act
+        proposal
+            .finalize_vote(100, &governance_config, 16_i64, &vote_threshold)
+            .unwrap();
+
+        // Assert
+        assert_eq!(proposal.state, ProposalState::Defeated);
+        assert!(!proposal.participation_met());
+    }
+
+    #[test]
+    fn test_participation_met_counts_best_multi_choice_option() {
+        // Arrange
+        let mut proposal = create_test_multi_option_proposal();
+        proposal.vote_type = VoteType::MultiChoice {
+            choice_type: MultiChoiceType::FullWeight,
+            min_voter_options: 1,
+            max_voter_options: 3,
+            max_winning_options: 3,
+        };
+        proposal.deny_vote_weight = None;
+        proposal.max_vote_weight = Some(100);
+        proposal.vote_threshold = Some(VoteThreshold::YesVotePercentage(60));
+        proposal.state = ProposalState::Completed;
+
+        proposal.options[0].vote_weight = 40;
+        proposal.options[1].vote_weight = 40;
+
+        // Act + Assert
+        assert!(!proposal.participation_met());
+
+        proposal.options[2].vote_weight = 60;
+        assert!(proposal.participation_met());
+    }
+
+    #[test]
+    fn test_participation_not_met_before_vote_completed() {
+        // Arrange
+        let mut proposal = create_test_proposal();
+        proposal.options[0].vote_weight = 100;
+        proposal.max_vote_weight = None;
+        proposal.vote_threshold = None;
+        proposal.state = ProposalState::Voting;
+
+        // Act
+        let participation_met = proposal.participation_met();
+
+        // Assert
+        assert!(!participation_met);
+    }
+
     #[test]
     fn test_finalize_vote_with_reduced_community_mint_max_vote_weight_and_vote_overflow() {
         // Arrange
@@ -0,0 +1,123 @@
+//! Proposal category record account
+
+use {
+    crate::state::enums::GovernanceAccountType,
+    borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
+    solana_program::{
+        account_info::AccountInfo, program_error::ProgramError, program_pack::IsInitialized,
+        pubkey::Pubkey,
+    },
+    spl_governance_tools::account::{get_account_data, AccountMaxSize},
+};
+
+/// Offset of the Governance in ProposalCategoryRecord account data
+pub const PROPOSAL_CATEGORY_RECORD_GOVERNANCE_OFFSET: usize = 1;
+
+/// Offset of the category in ProposalCategoryRecord account data
+pub const PROPOSAL_CATEGORY_RECORD_CATEGORY_OFFSET: usize = 1 + 32;
+
+/// Proposal category record account
+/// The account stores the category of a Proposal at a fixed offset so
+/// Proposals can be filtered by category with memcmp
+#[derive(Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct ProposalCategoryRecord {
+    /// Governance account type
+    pub account_type: GovernanceAccountType,
+
+    /// Governance the Proposal belongs to
+    pub governance: Pubkey,
+
+    /// Category of the Proposal, zero padded
+    pub category: [u8; 8],
+
+    /// The Proposal the category belongs to
+    pub proposal: Pubkey,
+
+    /// Reserved
+    pub reserved: [u8; 32],
+}
+
+impl AccountMaxSize for ProposalCategoryRecord {
+    fn get_max_size(&self) -> Option<usize> {
+        Some(1 + 32 + 8 + 32 + 32)
+    }
+}
+
+impl IsInitialized for ProposalCategoryRecord {
+    fn is_initialized(&self) -> bool {
+        self.account_type == GovernanceAccountType::ProposalCategoryRecord
+    }
+}
+
+/// Returns ProposalCategoryRecord PDA seeds
+pub fn get_proposal_category_record_address_seeds(proposal: &Pubkey) -> [&[u8]; 2] {
+    [b"proposal-category", proposal.as_ref()]
+}
+
+/// Returns ProposalCategoryRecord PDA address
+pub fn get_proposal_category_record_address(program_id: &Pubkey, proposal: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &get_proposal_category_record_address_seeds(proposal),
+        program_id,
+    )
+    .0
+}
+
+/// Deserializes ProposalCategoryRecord account and checks owner program and
+/// account type
+pub fn get_proposal_category_record_data(
+    program_id: &Pubkey,
+    proposal_category_record_info: &AccountInfo,
+) -> Result<ProposalCategoryRecord, ProgramError> {
+    get_account_data::<ProposalCategoryRecord>(program_id, proposal_category_record_info)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn create_test_proposal_category_record() -> ProposalCategoryRecord {
+        ProposalCategoryRecord {
+            account_type: GovernanceAccountType::ProposalCategoryRecord,
+            governance: Pubkey::new_unique(),
+            category: *b"treasury",
+            proposal: Pubkey::new_unique(),
+            reserved: [0; 32],
+        }
+    }
+
+    #[test]
+    fn test_max_size() {
+        // Arrange
+        let proposal_category_record_data = create_test_proposal_category_record();
+
+        // Act
+        let size = borsh::to_vec(&proposal_category_record_data).unwrap().len();
+
+        // Assert
+        assert_eq!(proposal_category_record_data.get_max_size(), Some(size));
+    }
+
+    #[test]
+    fn test_filter_offsets() {
+        // Arrange
+        let proposal_category_record_data = create_test_proposal_category_record();
+
+        // Act
+        let data = borsh::to_vec(&proposal_category_record_data).unwrap();
+
+        // Assert
+        assert_eq!(data[0], GovernanceAccountType::ProposalCategoryRecord as u8);
+        assert_eq!(
+            &data[PROPOSAL_CATEGORY_RECORD_GOVERNANCE_OFFSET
+                ..PROPOSAL_CATEGORY_RECORD_GOVERNANCE_OFFSET + 32],
+            proposal_category_record_data.governance.as_ref()
+        );
+        assert_eq!(
+            &data[PROPOSAL_CATEGORY_RECORD_CATEGORY_OFFSET
+                ..PROPOSAL_CATEGORY_RECORD_CATEGORY_OFFSET + 8],
+            &proposal_category_record_data.category
+        );
+    }
+}
@@ -82,6 +82,36 @@ impl From<&InstructionData> for Instruction {
     }
 }
 
+/// Maximum number of accounts, including the program ids, declared by the
+/// instructions of a ProposalTransaction
+/// ExecuteTransaction passes all of them to each instruction it invokes and the
+/// account infos of a single CPI can't exceed the transaction account lock
+/// limit
+pub const MAX_TRANSACTION_ACCOUNTS: usize = 64;
+
+/// Compute units ExecuteTransaction uses itself, besides the instructions it
+/// invokes
+pub const EXECUTE_TRANSACTION_COMPUTE_UNITS: u32 = 50_000;
+
+/// Compute units budgeted for each invoked instruction, which is the budget
+/// the instruction would get as a top level instruction of a transaction
+pub const TRANSACTION_INSTRUCTION_COMPUTE_UNITS: u32 = 200_000;
+
+/// Maximum compute units a transaction can request
+pub const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// Maximum length of the return data stored on an executed ProposalTransaction
+pub const MAX_TRANSACTION_RETURN_DATA_LEN: usize = 128;
+
+/// Return data set by an instruction executed for a ProposalTransaction
+#[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct TransactionReturnData {
+    /// Program which set the return data
+    pub program_id: Pubkey,
+    /// Return data truncated to MAX_TRANSACTION_RETURN_DATA_LEN bytes
+    pub data: Vec<u8>,
+}
+
 /// Account for an instruction to be executed for Proposal
 #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub struct ProposalTransactionV2 {
@@ -114,20 +144,29 @@ pub struct ProposalTransactionV2 {
     /// Instruction execution status
     pub execution_status: TransactionExecutionStatus,
 
+    /// The option whose transactions must all be executed before this
+    /// transaction can be executed
+    pub depends_on_option_index: Option<u8>,
+
+    /// Return data of the last executed instruction which set it, recorded
+    /// together with executed_at once the transaction is executed
+    pub return_data: Option<TransactionReturnData>,
+
+    /// Compute units estimated to execute the transaction, recorded when the
+    /// transaction is inserted
+    /// Note: Transactions inserted before the estimate was recorded have 0
+    pub estimated_compute_units: u32,
+
     /// Reserved space for versions v2 and onwards
     /// Note: V1 accounts must be resized before using this space
-    pub reserved_v2: [u8; 8],
+    pub reserved_v2: [u8; 1],
 }
 
 impl AccountMaxSize for ProposalTransactionV2 {
     fn get_max_size(&self) -> Option<usize> {
-        let instructions_size = self
-            .instructions
-            .iter()
-            .map(|i| i.accounts.len() * 34 + i.data.len() + 40)
-            .sum::<usize>();
-
-        Some(instructions_size + 62)
+        // Space for the return data is allocated upfront because the account
+        // can't be resized when the transaction is executed
+        Some(self.get_size_without_return_data() + 36 + MAX_TRANSACTION_RETURN_DATA_LEN)
     }
 }
 
@@ -138,6 +177,33 @@ impl IsInitialized for ProposalTransactionV2 {
 }
 
 impl ProposalTransactionV2 {
+    fn get_size_without_return_data(&self) -> usize {
+        let instructions_size = self
+            .instructions
+            .iter()
+            .map(|i| i.accounts.len() * 34 + i.data.len() + 40)
+            .sum::<usize>();
+
+        instructions_size + 62
+    }
+
+    /// Stores the return data of the executed transaction truncated to
+    /// MAX_TRANSACTION_RETURN_DATA_LEN and to the space left in the account
+    /// Note: Accounts created before the return data was captured have no
+    /// space for it and the return data is not stored
+    pub fn set_return_data(&mut self, program_id: Pubkey, mut data: Vec<u8>, account_size: usize) {
+        if self.account_type != GovernanceAccountType::ProposalTransactionV2 {
+            return;
+        }
+
+        if let Some(available_len) =
+            account_size.checked_sub(self.get_size_without_return_data() + 36)
+        {
+            data.truncate(available_len.min(MAX_TRANSACTION_RETURN_DATA_LEN));
+            self.return_data = Some(TransactionReturnData { program_id, data });
+        }
+    }
+
     /// Serializes account into the target buffer
     pub fn serialize<W: Write>(self, writer: W) -> Result<(), ProgramError> {
         if self.account_type == GovernanceAccountType::ProposalTransactionV2 {
@@ -152,7 +218,11 @@ impl ProposalTransactionV2 {
 
             // If reserved_v2 is used it must be individually asses for v1 backward
             // compatibility impact
-            if self.reserved_v2 != [0; 8] {
+            if self.reserved_v2 != [0; 1]
+                || self.depends_on_option_index.is_some()
+                || self.return_data.is_some()
+                || self.estimated_compute_units != 0
+            {
                 panic!("Extended data not supported by ProposalInstructionV1")
             }
 
@@ -227,7 +297,10 @@ pub fn get_proposal_transaction_data(
             instructions: vec![proposal_transaction_data_v1.instruction],
             executed_at: proposal_transaction_data_v1.executed_at,
             execution_status: proposal_transaction_data_v1.execution_status,
-            reserved_v2: [0; 8],
+            depends_on_option_index: None,
+            return_data: None,
+            estimated_compute_units: 0,
+            reserved_v2: [0; 1],
         });
     }
 
@@ -251,6 +324,53 @@ pub fn get_proposal_transaction_data_for_proposal(
     Ok(proposal_transaction_data)
 }
 
+/// Asserts all the given instructions are executed by the governed program
+/// Integrations which let a governance control a single program, e.g. reserve
+/// config proposals for a lending market, use it to reject any other
+/// instruction before it's inserted into a proposal
+pub fn assert_instructions_program_id(
+    instructions: &[InstructionData],
+    governed_program_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    if instructions
+        .iter()
+        .any(|instruction| instruction.program_id != *governed_program_id)
+    {
+        return Err(GovernanceError::InvalidTransactionProgramId.into());
+    }
+
+    Ok(())
+}
+
+/// Asserts the given instructions declare no more accounts than a single CPI
+/// can be invoked with
+pub fn assert_instructions_accounts_count(
+    instructions: &[InstructionData],
+) -> Result<(), ProgramError> {
+    let accounts_count = instructions
+        .iter()
+        .map(|instruction| instruction.accounts.len() + 1)
+        .sum::<usize>();
+
+    if accounts_count > MAX_TRANSACTION_ACCOUNTS {
+        return Err(GovernanceError::TooManyTransactionAccounts.into());
+    }
+
+    Ok(())
+}
+
+/// Estimates the compute units needed to execute the given instructions with
+/// ExecuteTransaction
+/// All the instructions are invoked within ExecuteTransaction and share its
+/// compute budget, so clients should request at least the estimate instead
+/// of relying on the default budget of a single instruction
+pub fn estimate_compute_units(instructions: &[InstructionData]) -> u32 {
+    (instructions.len() as u32)
+        .saturating_mul(TRANSACTION_INSTRUCTION_COMPUTE_UNITS)
+        .saturating_add(EXECUTE_TRANSACTION_COMPUTE_UNITS)
+        .min(MAX_TRANSACTION_COMPUTE_UNITS)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -291,7 +411,13 @@ mod test {
             instructions: create_test_instruction_data(),
             executed_at: Some(100),
             execution_status: TransactionExecutionStatus::Success,
-            reserved_v2: [0; 8],
+            depends_on_option_index: Some(0),
+            return_data: Some(TransactionReturnData {
+                program_id: Pubkey::new_unique(),
+                data: vec![1; MAX_TRANSACTION_RETURN_DATA_LEN],
+            }),
+            estimated_compute_units: 400_000,
+            reserved_v2: [0; 1],
         }
     }
 
@@ -326,6 +452,132 @@ mod test {
         assert_eq!(proposal_transaction.get_max_size(), Some(size));
     }
 
+    #[test]
+    fn test_set_return_data_truncated_to_max_len() {
+        // Arrange
+        let mut proposal_transaction = create_test_proposal_transaction();
+        proposal_transaction.return_data = None;
+        let account_size = proposal_transaction.get_max_size().unwrap();
+        let program_id = Pubkey::new_unique();
+
+        // Act
+        proposal_transaction.set_return_data(
+            program_id,
+            vec![2; MAX_TRANSACTION_RETURN_DATA_LEN + 1],
+            account_size,
+        );
+
+        // Assert
+        assert_eq!(
+            proposal_transaction.return_data,
+            Some(TransactionReturnData {
+                program_id,
+                data: vec![2; MAX_TRANSACTION_RETURN_DATA_LEN],
+            })
+        );
+        assert_eq!(
+            borsh::to_vec(&proposal_transaction).unwrap().len(),
+            account_size
+        );
+    }
+
+    #[test]
+    fn test_set_return_data_without_account_space() {
+        // Arrange
+        let mut proposal_transaction = create_test_proposal_transaction();
+        proposal_transaction.return_data = None;
+        let account_size = borsh::to_vec(&proposal_transaction).unwrap().len();
+
+        // Act
+        proposal_transaction.set_return_data(Pubkey::new_unique(), vec![2; 8], account_size);
+
+        // Assert
+        assert_eq!(proposal_transaction.return_data, None);
+    }
+
+    #[test]
+    fn test_assert_instructions_program_id() {
+        // Arrange
+        let instructions = create_test_instruction_data();
+        let governed_program_id = instructions[0].program_id;
+
+        // Act
+        let result = assert_instructions_program_id(&instructions, &governed_program_id);
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_assert_instructions_program_id_with_other_program_error() {
+        // Arrange
+        let mut instructions = create_test_instruction_data();
+        let governed_program_id = instructions[0].program_id;
+
+        instructions.push(create_test_instruction_data()[0].clone());
+
+        // Act
+        let err = assert_instructions_program_id(&instructions, &governed_program_id)
+            .err()
+            .unwrap();
+
+        // Assert
+        assert_eq!(err, GovernanceError::InvalidTransactionProgramId.into());
+    }
+
+    #[test]
+    fn test_estimate_compute_units() {
+        // Arrange
+        let mut instructions = create_test_instruction_data();
+
+        // Act, Assert
+        assert_eq!(
+            estimate_compute_units(&instructions),
+            EXECUTE_TRANSACTION_COMPUTE_UNITS + TRANSACTION_INSTRUCTION_COMPUTE_UNITS
+        );
+
+        instructions = vec![instructions[0].clone(); 10];
+        assert_eq!(
+            estimate_compute_units(&instructions),
+            MAX_TRANSACTION_COMPUTE_UNITS
+        );
+    }
+
+    #[test]
+    fn test_assert_instructions_accounts_count_at_max() {
+        // Arrange
+        let mut instructions = create_test_instruction_data();
+        instructions.push(create_test_instruction_data()[0].clone());
+
+        // Both program ids count towards the max
+        instructions[1].accounts =
+            vec![create_test_account_meta_data(); MAX_TRANSACTION_ACCOUNTS - 5];
+
+        // Act
+        let result = assert_instructions_accounts_count(&instructions);
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_assert_instructions_accounts_count_above_max_error() {
+        // Arrange
+        let mut instructions = create_test_instruction_data();
+        instructions.push(create_test_instruction_data()[0].clone());
+
+        instructions[1].accounts =
+            vec![create_test_account_meta_data(); MAX_TRANSACTION_ACCOUNTS - 4];
+
+        // Act
+        let err = assert_instructions_accounts_count(&instructions)
+            .err()
+            .unwrap();
+
+        // Assert
+        assert_eq!(err, GovernanceError::TooManyTransactionAccounts.into());
+    }
+
     #[test]
     fn test_upgrade_instruction_serialization() {
         // Arrange
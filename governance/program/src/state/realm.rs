@@ -6,7 +6,9 @@ use {
         state::{
             enums::{GovernanceAccountType, MintMaxVoterWeightSource},
             legacy::RealmV1,
-            realm_config::{get_realm_config_data_for_realm, GoverningTokenType},
+            realm_config::{
+                get_realm_config_data_for_realm, GoverningTokenType, ProposalCreationAuthority,
+            },
             token_owner_record::get_token_owner_record_data_for_realm,
             vote_record::VoteKind,
         },
@@ -18,7 +20,7 @@ use {
         account_info::{next_account_info, AccountInfo},
         program_error::ProgramError,
         program_pack::IsInitialized,
-        pubkey::Pubkey,
+        pubkey::{Pubkey, MAX_SEED_LEN},
     },
     spl_governance_addin_api::voter_weight::VoterWeightAction,
     spl_governance_tools::account::{
@@ -28,9 +30,10 @@ use {
 };
 
 /// SetRealmConfigItem instruction arguments to set a single Realm config item
-/// Note: In the current version only TokenOwnerRecordLockAuthority is supported
-/// Eventually all Realm config items should be supported for single config item
-/// change
+/// Note: In the current version only TokenOwnerRecordLockAuthority,
+/// CommunityProposalCreation and MaxVoterWeightSnapshot are supported
+/// Eventually all Realm config items should be supported for single config
+/// item change
 #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub enum SetRealmConfigItemArgs {
     /// Set TokenOwnerRecord lock authority
@@ -45,6 +48,55 @@ pub enum SetRealmConfigItemArgs {
         #[allow(dead_code)]
         authority: Pubkey,
     },
+
+    /// Set the authority allowed to create community proposals
+    CommunityProposalCreation {
+        /// Authority allowed to create community proposals
+        #[allow(dead_code)]
+        authority: ProposalCreationAuthority,
+    },
+
+    /// Set whether Proposals snapshot the max voter weight when voting starts
+    MaxVoterWeightSnapshot {
+        /// Indicates whether the snapshot is taken
+        #[allow(dead_code)]
+        enabled: bool,
+    },
+
+    /// Set whether a Token-2022 governing token mint with the transfer fee or
+    /// non-transferable extension can be deposited
+    GoverningTokenRestrictedExtensions {
+        /// Mint of the governing token the extensions are allowed for
+        #[allow(dead_code)]
+        governing_token_mint: Pubkey,
+        /// Indicates whether the extensions are allowed
+        #[allow(dead_code)]
+        allowed: bool,
+    },
+
+    /// Set whether the Realm tracks the total deposits of a governing token,
+    /// which ReconcileGoverningTokenHolding needs to sweep tokens sent to the
+    /// holding account outside of deposits
+    /// When enabled the total starts at the given deposits, which can't exceed
+    /// the holding account balance
+    /// Note: While enabled DepositGoverningTokens, WithdrawGoverningTokens
+    /// and RevokeGoverningTokens for the token require the RealmConfig
+    /// account to be writable
+    GoverningTokenDepositTracking {
+        /// Mint of the governing token the deposits are tracked for
+        #[allow(dead_code)]
+        governing_token_mint: Pubkey,
+        /// Indicates whether the deposits are tracked
+        #[allow(dead_code)]
+        enabled: bool,
+        /// Sum of the governing token deposits of the Realm's
+        /// TokenOwnerRecords the tracked total starts from
+        /// It's only used when the tracking is enabled and must not understate
+        /// the deposits, otherwise ReconcileGoverningTokenHolding sweeps
+        /// deposited tokens
+        #[allow(dead_code)]
+        deposits: u64,
+    },
 }
 
 /// Realm Config instruction args
@@ -102,9 +154,10 @@ pub struct GoverningTokenConfigAccountArgs {
 /// SetRealmAuthority instruction action
 #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub enum SetRealmAuthorityAction {
-    /// Sets realm authority without any checks
+    /// Proposes a new realm authority without any checks
     /// Uncheck option allows to set the realm authority to non governance
-    /// accounts
+    /// accounts. The new authority becomes pending and must accept it with
+    /// AcceptRealmAuthority, until then the current authority keeps control
     SetUnchecked,
 
     /// Sets realm authority and checks the new new authority is one of the
@@ -117,6 +170,9 @@ pub enum SetRealmAuthorityAction {
 
     /// Removes realm authority
     Remove,
+
+    /// Cancels the pending realm authority proposed with SetUnchecked
+    CancelPending,
 }
 
 /// Realm Config defining Realm parameters.
@@ -177,9 +233,13 @@ pub struct RealmV2 {
     /// Governance Realm name
     pub name: String,
 
+    /// Realm authority proposed by the current authority which becomes the
+    /// authority once it accepts it
+    pub pending_authority: Option<Pubkey>,
+
     /// Reserved space for versions v2 and onwards
     /// Note: V1 accounts must be resized before using this space
-    pub reserved_v2: [u8; 128],
+    pub reserved_v2: [u8; 95],
 }
 
 impl AccountMaxSize for RealmV2 {
@@ -221,7 +281,8 @@ pub fn is_realm_account_type(account_type: &GovernanceAccountType) -> bool {
         | GovernanceAccountType::VoteRecordV2
         | GovernanceAccountType::ProgramMetadata
         | GovernanceAccountType::ProposalDeposit
-        | GovernanceAccountType::RequiredSignatory => false,
+        | GovernanceAccountType::RequiredSignatory
+        | GovernanceAccountType::ProposalCategoryRecord => false,
     }
 }
 
@@ -351,7 +412,7 @@ impl RealmV2 {
 
             // If reserved_v2 is used it must be individually asses for v1 backward
             // compatibility impact
-            if self.reserved_v2 != [0; 128] {
+            if self.reserved_v2 != [0; 95] || self.pending_authority.is_some() {
                 panic!("Extended data not supported by RealmV1")
             }
 
@@ -400,8 +461,9 @@ pub fn get_realm_data(
             legacy1: 0,
             authority: realm_data_v1.authority,
             name: realm_data_v1.name,
+            pending_authority: None,
             // Add the extra reserved_v2 padding
-            reserved_v2: [0; 128],
+            reserved_v2: [0; 95],
         });
     }
 
@@ -476,6 +538,19 @@ pub fn get_governing_token_holding_address(
     .0
 }
 
+/// Max length in bytes of the Realm name, bounded by the max length of the
+/// Realm PDA name seed
+pub const MAX_REALM_NAME_LEN: usize = MAX_SEED_LEN;
+
+/// Asserts the Realm name is non-empty and within MAX_REALM_NAME_LEN
+pub fn assert_valid_realm_name(name: &str) -> Result<(), ProgramError> {
+    if name.is_empty() || name.len() > MAX_REALM_NAME_LEN {
+        return Err(GovernanceError::RealmNameInvalid.into());
+    }
+
+    Ok(())
+}
+
 /// Asserts given realm config args are correct
 pub fn assert_valid_realm_config_args(
     realm_config_args: &RealmConfigArgs,
@@ -513,6 +588,7 @@ mod test {
 
             authority: Some(Pubkey::new_unique()),
             name: "test-realm".to_string(),
+            pending_authority: Some(Pubkey::new_unique()),
             config: RealmConfig {
                 council_mint: Some(Pubkey::new_unique()),
                 legacy1: 0,
@@ -523,7 +599,7 @@ mod test {
             },
 
             legacy1: 0,
-            reserved_v2: [0; 128],
+            reserved_v2: [0; 95],
         };
 
         let size = borsh::to_vec(&realm).unwrap().len();
@@ -606,4 +682,39 @@ mod test {
             panic!("Can't deserialize v1 CreateRealm instruction from v2");
         }
     }
+
+    #[test]
+    fn test_assert_valid_realm_name() {
+        for name in ["r".to_string(), "r".repeat(MAX_REALM_NAME_LEN)] {
+            // Act
+            let result = assert_valid_realm_name(&name);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_assert_valid_realm_name_with_invalid_name_error() {
+        for name in ["".to_string(), "r".repeat(MAX_REALM_NAME_LEN + 1)] {
+            // Act
+            let err = assert_valid_realm_name(&name).err().unwrap();
+
+            // Assert
+            assert_eq!(err, GovernanceError::RealmNameInvalid.into());
+        }
+    }
+
+    #[test]
+    fn test_realm_name_length_is_counted_in_bytes() {
+        // Arrange
+        // 'é' takes 2 bytes in UTF-8
+        let name = "é".repeat(MAX_REALM_NAME_LEN / 2 + 1);
+
+        // Act
+        let err = assert_valid_realm_name(&name).err().unwrap();
+
+        // Assert
+        assert_eq!(err, GovernanceError::RealmNameInvalid.into());
+    }
 }
@@ -6,7 +6,7 @@ use {
             enums::GovernanceAccountType,
             realm::{GoverningTokenConfigArgs, RealmConfigArgs, RealmV2},
         },
-        tools::structs::Reserved110,
+        tools::{spl_token::get_spl_token_mint_extension_types, structs::Reserved49},
     },
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
@@ -19,6 +19,7 @@ use {
     spl_governance_tools::account::{
         create_and_serialize_account_signed, extend_account_size, get_account_data, AccountMaxSize,
     },
+    spl_token_2022::extension::ExtensionType,
     std::slice::Iter,
 };
 
@@ -36,9 +37,9 @@ pub enum GoverningTokenType {
 
     /// Membership token is a token controlled by Realm authority
     /// Deposit - Yes, membership tokens can be deposited to gain governance
-    /// power.
-    /// The membership tokens are conventionally minted into the holding
-    /// account to keep them out of members possession.
+    /// power, but only when minted into the holding account by the mint
+    /// authority or transferred by the Realm authority. Membership tokens
+    /// acquired by members otherwise can't be deposited.
     /// Withdraw - No, after membership tokens are deposited they are no longer
     /// transferable and can't be withdrawn.
     /// Revoke - Yes, Realm authority can Revoke (burn) membership tokens.
@@ -85,13 +86,42 @@ pub struct GoverningTokenConfig {
     /// Governing token type
     pub token_type: GoverningTokenType,
 
+    /// Indicates whether a Token-2022 governing token mint with the transfer
+    /// fee or non-transferable extension can be deposited
+    pub allow_restricted_extensions: bool,
+
     /// Reserved space for future versions
-    pub reserved: [u8; 4],
+    pub reserved: [u8; 3],
 
     /// Lock authorities for TokenOwnerRecords
     pub lock_authorities: Vec<Pubkey>,
 }
 
+/// Defines who can create proposals voted on by the community
+#[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub enum ProposalCreationAuthority {
+    /// Any token owner with enough weight for the Governance can create
+    /// proposals
+    Anyone,
+
+    /// Only council members can create community proposals
+    CouncilOnly,
+
+    /// Community token owners must have at least the given voter weight to
+    /// create community proposals
+    /// Note: The weight is checked in addition to the Governance
+    /// min_community_weight_to_create_proposal and doesn't apply to council
+    /// members
+    MinTokenWeight(u64),
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ProposalCreationAuthority {
+    fn default() -> Self {
+        ProposalCreationAuthority::Anyone
+    }
+}
+
 /// RealmConfig account
 /// The account is an optional extension to RealmConfig stored on Realm account
 #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
@@ -108,8 +138,36 @@ pub struct RealmConfigAccount {
     /// Council token config
     pub council_token_config: GoverningTokenConfig,
 
+    /// Authority allowed to create community proposals
+    pub community_proposal_creation: ProposalCreationAuthority,
+
+    /// Indicates whether Proposals capture the max voter weight when voting
+    /// starts and compute their vote thresholds against it for the rest of
+    /// the vote
+    /// Note: The snapshot only applies to Electorate votes, Veto votes are
+    /// always measured against the current max voter weight
+    pub snapshot_max_voter_weight: bool,
+
+    /// The total amount of community tokens deposited into the Realm by all
+    /// TokenOwnerRecords
+    /// Note: The total is None unless the Realm opted in to tracking the
+    /// deposits with SetRealmConfigItem
+    pub community_token_deposits: Option<u64>,
+
+    /// The total amount of council tokens deposited into the Realm by all
+    /// TokenOwnerRecords
+    /// Note: The total is None unless the Realm opted in to tracking the
+    /// deposits with SetRealmConfigItem
+    pub council_token_deposits: Option<u64>,
+
+    /// The token program of the community and council mints, spl-token or
+    /// Token-2022
+    /// Note: The program is None for Realms created before Token-2022 mints
+    /// were supported and they use spl-token
+    pub governing_token_program: Option<Pubkey>,
+
     /// Reserved
-    pub reserved: Reserved110,
+    pub reserved: Reserved49,
 }
 
 impl AccountMaxSize for RealmConfigAccount {
@@ -117,7 +175,11 @@ impl AccountMaxSize for RealmConfigAccount {
         Some(
             1 + 32
                 + 75 * 2
-                + 110
+                + 9
+                + 1
+                + 9 * 2
+                + 33
+                + 49
                 + self.community_token_config.lock_authorities.len() * 32
                 + self.council_token_config.lock_authorities.len() * 32,
         )
@@ -165,6 +227,145 @@ impl RealmConfigAccount {
         Ok(token_config)
     }
 
+    /// Returns the total deposits tracked for the given governing_token_mint
+    pub fn get_governing_token_deposits(
+        &self,
+        realm_data: &RealmV2,
+        governing_token_mint: &Pubkey,
+    ) -> Result<Option<u64>, ProgramError> {
+        let governing_token_deposits = if *governing_token_mint == realm_data.community_mint {
+            self.community_token_deposits
+        } else if Some(*governing_token_mint) == realm_data.config.council_mint {
+            self.council_token_deposits
+        } else {
+            return Err(GovernanceError::InvalidGoverningTokenMint.into());
+        };
+
+        Ok(governing_token_deposits)
+    }
+
+    /// Returns mutable total deposits tracked for the given
+    /// governing_token_mint
+    pub fn get_governing_token_deposits_mut(
+        &mut self,
+        realm_data: &RealmV2,
+        governing_token_mint: &Pubkey,
+    ) -> Result<&mut Option<u64>, ProgramError> {
+        let governing_token_deposits = if *governing_token_mint == realm_data.community_mint {
+            &mut self.community_token_deposits
+        } else if Some(*governing_token_mint) == realm_data.config.council_mint {
+            &mut self.council_token_deposits
+        } else {
+            return Err(GovernanceError::InvalidGoverningTokenMint.into());
+        };
+
+        Ok(governing_token_deposits)
+    }
+
+    /// Adds the deposited amount to the total tracked for the given
+    /// governing_token_mint and writes it to the RealmConfig account
+    /// Note: Nothing is written when the Realm doesn't track the deposits and
+    /// then the RealmConfig account can be read only
+    pub fn add_governing_token_deposits(
+        &mut self,
+        realm_data: &RealmV2,
+        governing_token_mint: &Pubkey,
+        amount: u64,
+        realm_config_info: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if let Some(governing_token_deposits) =
+            self.get_governing_token_deposits_mut(realm_data, governing_token_mint)?
+        {
+            *governing_token_deposits = governing_token_deposits.checked_add(amount).unwrap();
+            self.write_governing_token_deposits(realm_config_info)?;
+        }
+
+        Ok(())
+    }
+
+    /// Subtracts the withdrawn or revoked amount from the total tracked for
+    /// the given governing_token_mint and writes it to the RealmConfig account
+    /// Note: Nothing is written when the Realm doesn't track the deposits and
+    /// then the RealmConfig account can be read only
+    pub fn sub_governing_token_deposits(
+        &mut self,
+        realm_data: &RealmV2,
+        governing_token_mint: &Pubkey,
+        amount: u64,
+        realm_config_info: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if let Some(governing_token_deposits) =
+            self.get_governing_token_deposits_mut(realm_data, governing_token_mint)?
+        {
+            *governing_token_deposits = governing_token_deposits.checked_sub(amount).unwrap();
+            self.write_governing_token_deposits(realm_config_info)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_governing_token_deposits(
+        &self,
+        realm_config_info: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        // Skipping the update for a read only account would understate the
+        // deposits and let ReconcileGoverningTokenHolding sweep deposited tokens
+        if !realm_config_info.is_writable {
+            return Err(GovernanceError::RealmConfigMustBeWritable.into());
+        }
+
+        borsh::to_writer(&mut realm_config_info.data.borrow_mut()[..], self)?;
+
+        Ok(())
+    }
+
+    /// Returns the token program of the Realm governing tokens
+    pub fn get_governing_token_program(&self) -> Pubkey {
+        self.governing_token_program.unwrap_or(spl_token::id())
+    }
+
+    /// Asserts the given token program is the governing token program of the
+    /// Realm
+    pub fn assert_is_valid_governing_token_program(
+        &self,
+        token_program: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if *token_program != self.get_governing_token_program() {
+            return Err(GovernanceError::InvalidGoverningTokenProgram.into());
+        }
+
+        Ok(())
+    }
+
+    /// Asserts the given governing token mint has no Token-2022 extensions
+    /// restricting its transfers unless they are allowed for the mint
+    pub fn assert_governing_token_mint_extensions_allowed(
+        &self,
+        realm_data: &RealmV2,
+        governing_token_mint_info: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if self
+            .get_token_config(realm_data, governing_token_mint_info.key)?
+            .allow_restricted_extensions
+        {
+            return Ok(());
+        }
+
+        if get_spl_token_mint_extension_types(governing_token_mint_info)?
+            .iter()
+            .any(|extension_type| {
+                matches!(
+                    extension_type,
+                    ExtensionType::TransferFeeConfig | ExtensionType::NonTransferable
+                )
+            })
+        {
+            return Err(GovernanceError::GoverningTokenMintExtensionNotAllowed.into());
+        }
+
+        Ok(())
+    }
+
     /// Asserts the given governing token can be revoked
     pub fn assert_can_revoke_governing_token(
         &self,
@@ -220,6 +421,40 @@ impl RealmConfigAccount {
         }
     }
 
+    /// Asserts the community proposal creation policy allows the owner of the
+    /// given governing_token_mint deposit to create a proposal for
+    /// proposal_governing_token_mint
+    /// Note: Council proposals are not affected by the policy
+    pub fn assert_can_create_proposal(
+        &self,
+        realm_data: &RealmV2,
+        proposal_governing_token_mint: &Pubkey,
+        owner_governing_token_mint: &Pubkey,
+        voter_weight: u64,
+    ) -> Result<(), ProgramError> {
+        if *proposal_governing_token_mint != realm_data.community_mint {
+            return Ok(());
+        }
+
+        let is_council_member = Some(*owner_governing_token_mint) == realm_data.config.council_mint;
+
+        match self.community_proposal_creation {
+            ProposalCreationAuthority::Anyone => Ok(()),
+            ProposalCreationAuthority::CouncilOnly => {
+                if !is_council_member {
+                    return Err(GovernanceError::CouncilMembershipRequiredToCreateProposal.into());
+                }
+                Ok(())
+            }
+            ProposalCreationAuthority::MinTokenWeight(min_weight) => {
+                if !is_council_member && voter_weight < min_weight {
+                    return Err(GovernanceError::InsufficientProposalCreationWeight.into());
+                }
+                Ok(())
+            }
+        }
+    }
+
     /// Asserts the given RealmConfigArgs represent a valid Realm configuration
     /// change
     pub fn assert_can_change_config(
@@ -319,7 +554,12 @@ pub fn get_realm_config_data_for_realm(
             realm: *realm,
             community_token_config: GoverningTokenConfig::default(),
             council_token_config: GoverningTokenConfig::default(),
-            reserved: Reserved110::default(),
+            community_proposal_creation: ProposalCreationAuthority::default(),
+            snapshot_max_voter_weight: false,
+            community_token_deposits: None,
+            council_token_deposits: None,
+            governing_token_program: None,
+            reserved: Reserved49::default(),
         }
     } else {
         let realm_config_data = get_realm_config_data(program_id, realm_config_info)?;
@@ -364,18 +604,22 @@ pub fn resolve_governing_token_config(
         None
     };
 
-    let lock_authorities =
+    let (allow_restricted_extensions, lock_authorities) =
         if let Some(existing_governing_token_config) = existing_governing_token_config {
-            existing_governing_token_config.lock_authorities
+            (
+                existing_governing_token_config.allow_restricted_extensions,
+                existing_governing_token_config.lock_authorities,
+            )
         } else {
-            vec![]
+            (false, vec![])
         };
 
     Ok(GoverningTokenConfig {
         voter_weight_addin,
         max_voter_weight_addin,
         token_type: governing_token_config_args.token_type.clone(),
-        reserved: [0; 4],
+        allow_restricted_extensions,
+        reserved: [0; 3],
         lock_authorities,
     })
 }
@@ -396,17 +640,24 @@ mod test {
                 voter_weight_addin: Some(Pubkey::new_unique()),
                 max_voter_weight_addin: Some(Pubkey::new_unique()),
                 token_type: GoverningTokenType::Liquid,
-                reserved: [0; 4],
+                allow_restricted_extensions: false,
+                reserved: [0; 3],
                 lock_authorities: vec![],
             },
             council_token_config: GoverningTokenConfig {
                 voter_weight_addin: Some(Pubkey::new_unique()),
                 max_voter_weight_addin: Some(Pubkey::new_unique()),
                 token_type: GoverningTokenType::Liquid,
-                reserved: [0; 4],
+                allow_restricted_extensions: false,
+                reserved: [0; 3],
                 lock_authorities: vec![],
             },
-            reserved: Reserved110::default(),
+            community_proposal_creation: ProposalCreationAuthority::MinTokenWeight(100),
+            snapshot_max_voter_weight: true,
+            community_token_deposits: Some(100),
+            council_token_deposits: Some(10),
+            governing_token_program: Some(spl_token_2022::id()),
+            reserved: Reserved49::default(),
         };
 
         let size = borsh::to_vec(&realm_config).unwrap().len();
@@ -423,17 +674,24 @@ mod test {
                 voter_weight_addin: Some(Pubkey::new_unique()),
                 max_voter_weight_addin: Some(Pubkey::new_unique()),
                 token_type: GoverningTokenType::Liquid,
-                reserved: [0; 4],
+                allow_restricted_extensions: false,
+                reserved: [0; 3],
                 lock_authorities: vec![Pubkey::new_unique()],
             },
             council_token_config: GoverningTokenConfig {
                 voter_weight_addin: Some(Pubkey::new_unique()),
                 max_voter_weight_addin: Some(Pubkey::new_unique()),
                 token_type: GoverningTokenType::Liquid,
-                reserved: [0; 4],
+                allow_restricted_extensions: false,
+                reserved: [0; 3],
                 lock_authorities: vec![Pubkey::new_unique(), Pubkey::new_unique()],
             },
-            reserved: Reserved110::default(),
+            community_proposal_creation: ProposalCreationAuthority::MinTokenWeight(100),
+            snapshot_max_voter_weight: true,
+            community_token_deposits: Some(100),
+            council_token_deposits: Some(10),
+            governing_token_program: Some(spl_token_2022::id()),
+            reserved: Reserved49::default(),
         };
 
         let size = borsh::to_vec(&realm_config).unwrap().len();
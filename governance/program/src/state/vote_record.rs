@@ -15,9 +15,11 @@ use {
     borsh::{io::Write, BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
         account_info::AccountInfo, program_error::ProgramError, program_pack::IsInitialized,
-        pubkey::Pubkey,
+        pubkey::Pubkey, rent::Rent,
+    },
+    spl_governance_tools::account::{
+        extend_account_size, get_account_data, get_account_type, AccountMaxSize,
     },
-    spl_governance_tools::account::{get_account_data, get_account_type, AccountMaxSize},
 };
 
 /// Voter choice for a proposal option
@@ -136,6 +138,47 @@ impl VoteRecordV2 {
         Ok(())
     }
 
+    /// Checks the vote can be changed to the given vote
+    pub fn assert_can_change_vote(&self, vote: &Vote) -> Result<(), ProgramError> {
+        if self.is_relinquished {
+            return Err(GovernanceError::VoteAlreadyRelinquished.into());
+        }
+
+        // The vote kind determines the Proposal voting population the voter weight
+        // is counted for and it can't be changed
+        if self.vote == *vote || get_vote_kind(&self.vote) != get_vote_kind(vote) {
+            return Err(GovernanceError::InvalidVoteChange.into());
+        }
+
+        Ok(())
+    }
+
+    /// Serializes VoteRecord and resizes it if the vote doesn't fit the
+    /// account
+    /// Note: VoteRecordV1 accounts have fixed size and are never resized
+    pub fn serialize_with_resize<'a>(
+        self,
+        vote_record_info: &AccountInfo<'a>,
+        payer_info: &AccountInfo<'a>,
+        system_info: &AccountInfo<'a>,
+        rent: &Rent,
+    ) -> Result<(), ProgramError> {
+        if self.account_type == GovernanceAccountType::VoteRecordV2 {
+            let vote_record_data_size = borsh::to_vec(&self)?.len();
+            if vote_record_info.data_len() < vote_record_data_size {
+                extend_account_size(
+                    vote_record_info,
+                    payer_info,
+                    vote_record_data_size,
+                    rent,
+                    system_info,
+                )?;
+            }
+        }
+
+        self.serialize(&mut vote_record_info.data.borrow_mut()[..])
+    }
+
     /// Serializes account into the target buffer
     pub fn serialize<W: Write>(self, writer: W) -> Result<(), ProgramError> {
         if self.account_type == GovernanceAccountType::VoteRecordV2 {
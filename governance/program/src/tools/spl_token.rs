@@ -19,10 +19,28 @@ use {
         instruction::{set_authority, AuthorityType},
         state::{Account, Mint},
     },
+    spl_token_2022::extension::{
+        AccountType, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
 };
 
+/// Returns the size of a token account of the given mint including the
+/// Token-2022 account extensions required by the mint
+fn get_spl_token_account_len(token_mint_info: &AccountInfo) -> Result<usize, ProgramError> {
+    if token_mint_info.owner == &spl_token_2022::id() {
+        let mint_extension_types = get_spl_token_mint_extension_types(token_mint_info)?;
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(
+            &ExtensionType::get_required_init_account_extensions(&mint_extension_types),
+        )
+    } else {
+        Ok(Account::LEN)
+    }
+}
+
 /// Creates and initializes SPL token account with PDA using the provided PDA
 /// seeds
+/// The account is owned by the given spl_token_info program, spl-token or
+/// Token-2022
 #[allow(clippy::too_many_arguments)]
 pub fn create_spl_token_account_signed<'a>(
     payer_info: &AccountInfo<'a>,
@@ -36,12 +54,14 @@ pub fn create_spl_token_account_signed<'a>(
     rent_sysvar_info: &AccountInfo<'a>,
     rent: &Rent,
 ) -> Result<(), ProgramError> {
+    let token_account_len = get_spl_token_account_len(token_mint_info)?;
+
     let create_account_instruction = system_instruction::create_account(
         payer_info.key,
         token_account_info.key,
-        1.max(rent.minimum_balance(spl_token::state::Account::get_packed_len())),
-        spl_token::state::Account::get_packed_len() as u64,
-        &spl_token::id(),
+        1.max(rent.minimum_balance(token_account_len)),
+        token_account_len as u64,
+        spl_token_info.key,
     );
 
     let (account_address, bump_seed) =
@@ -70,8 +90,8 @@ pub fn create_spl_token_account_signed<'a>(
         &[&signers_seeds[..]],
     )?;
 
-    let initialize_account_instruction = spl_token::instruction::initialize_account(
-        &spl_token::id(),
+    let initialize_account_instruction = spl_token_2022::instruction::initialize_account(
+        spl_token_info.key,
         token_account_info.key,
         token_mint_info.key,
         token_account_owner_info.key,
@@ -100,8 +120,9 @@ pub fn transfer_spl_tokens<'a>(
     amount: u64,
     spl_token_info: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let transfer_instruction = spl_token::instruction::transfer(
-        &spl_token::id(),
+    #[allow(deprecated)]
+    let transfer_instruction = spl_token_2022::instruction::transfer(
+        spl_token_info.key,
         source_info.key,
         destination_info.key,
         authority_info.key,
@@ -131,8 +152,8 @@ pub fn mint_spl_tokens_to<'a>(
     amount: u64,
     spl_token_info: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let mint_to_ix = spl_token::instruction::mint_to(
-        &spl_token::id(),
+    let mint_to_ix = spl_token_2022::instruction::mint_to(
+        spl_token_info.key,
         mint_info.key,
         destination_info.key,
         mint_authority_info.key,
@@ -154,6 +175,42 @@ pub fn mint_spl_tokens_to<'a>(
     Ok(())
 }
 
+/// Transfers SPL Tokens using TransferChecked
+/// Note: Token-2022 requires the mint for transfers of tokens with the
+/// transfer fee extension
+pub fn transfer_spl_tokens_checked<'a>(
+    source_info: &AccountInfo<'a>,
+    mint_info: &AccountInfo<'a>,
+    destination_info: &AccountInfo<'a>,
+    authority_info: &AccountInfo<'a>,
+    amount: u64,
+    spl_token_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    let transfer_instruction = spl_token_2022::instruction::transfer_checked(
+        spl_token_info.key,
+        source_info.key,
+        mint_info.key,
+        destination_info.key,
+        authority_info.key,
+        &[],
+        amount,
+        get_spl_token_mint_decimals(mint_info)?,
+    )?;
+
+    invoke(
+        &transfer_instruction,
+        &[
+            spl_token_info.clone(),
+            authority_info.clone(),
+            source_info.clone(),
+            mint_info.clone(),
+            destination_info.clone(),
+        ],
+    )?;
+
+    Ok(())
+}
+
 /// Transfers SPL Tokens from a token account owned by the provided PDA
 /// authority with seeds
 pub fn transfer_spl_tokens_signed<'a>(
@@ -176,8 +233,9 @@ pub fn transfer_spl_tokens_signed<'a>(
         return Err(ProgramError::InvalidSeeds);
     }
 
-    let transfer_instruction = spl_token::instruction::transfer(
-        &spl_token::id(),
+    #[allow(deprecated)]
+    let transfer_instruction = spl_token_2022::instruction::transfer(
+        spl_token_info.key,
         source_info.key,
         destination_info.key,
         authority_info.key,
@@ -204,6 +262,60 @@ pub fn transfer_spl_tokens_signed<'a>(
     Ok(())
 }
 
+/// Transfers SPL Tokens using TransferChecked from a token account owned by the
+/// provided PDA authority with seeds
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_spl_tokens_checked_signed<'a>(
+    source_info: &AccountInfo<'a>,
+    mint_info: &AccountInfo<'a>,
+    destination_info: &AccountInfo<'a>,
+    authority_info: &AccountInfo<'a>,
+    authority_seeds: &[&[u8]],
+    program_id: &Pubkey,
+    amount: u64,
+    spl_token_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    let (authority_address, bump_seed) = Pubkey::find_program_address(authority_seeds, program_id);
+
+    if authority_address != *authority_info.key {
+        msg!(
+            "Transfer SPL Token with Authority PDA: {:?} was requested while PDA: {:?} was expected",
+            authority_info.key,
+            authority_address
+        );
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let transfer_instruction = spl_token_2022::instruction::transfer_checked(
+        spl_token_info.key,
+        source_info.key,
+        mint_info.key,
+        destination_info.key,
+        authority_info.key,
+        &[],
+        amount,
+        get_spl_token_mint_decimals(mint_info)?,
+    )?;
+
+    let mut signers_seeds = authority_seeds.to_vec();
+    let bump = &[bump_seed];
+    signers_seeds.push(bump);
+
+    invoke_signed(
+        &transfer_instruction,
+        &[
+            spl_token_info.clone(),
+            authority_info.clone(),
+            source_info.clone(),
+            mint_info.clone(),
+            destination_info.clone(),
+        ],
+        &[&signers_seeds[..]],
+    )?;
+
+    Ok(())
+}
+
 /// Burns SPL Tokens from a token account owned by the provided PDA authority
 /// with seeds
 pub fn burn_spl_tokens_signed<'a>(
@@ -226,8 +338,8 @@ pub fn burn_spl_tokens_signed<'a>(
         return Err(ProgramError::InvalidSeeds);
     }
 
-    let burn_ix = spl_token::instruction::burn(
-        &spl_token::id(),
+    let burn_ix = spl_token_2022::instruction::burn(
+        spl_token_info.key,
         token_account_info.key,
         token_mint_info.key,
         authority_info.key,
@@ -254,18 +366,32 @@ pub fn burn_spl_tokens_signed<'a>(
     Ok(())
 }
 
+/// Returns true if the given data of a Token-2022 account is extended with the
+/// given account type
+fn is_extended_spl_token_2022_data(data: &[u8], account_type: AccountType) -> bool {
+    // Extended Token-2022 accounts, Mints included, are padded to the token account
+    // size followed by the account type
+    data.len() > Account::LEN && data[Account::LEN] == account_type as u8
+}
+
 /// Asserts the given account_info represents a valid SPL Token account which is
-/// initialized and belongs to spl_token program
+/// initialized and belongs to spl_token or Token-2022 program
 pub fn assert_is_valid_spl_token_account(account_info: &AccountInfo) -> Result<(), ProgramError> {
     if account_info.data_is_empty() {
         return Err(GovernanceError::SplTokenAccountDoesNotExist.into());
     }
 
-    if account_info.owner != &spl_token::id() {
+    if account_info.owner != &spl_token::id() && account_info.owner != &spl_token_2022::id() {
         return Err(GovernanceError::SplTokenAccountWithInvalidOwner.into());
     }
 
-    if account_info.data_len() != Account::LEN {
+    if account_info.data_len() != Account::LEN
+        && !(account_info.owner == &spl_token_2022::id()
+            && is_extended_spl_token_2022_data(
+                &account_info.try_borrow_data()?,
+                AccountType::Account,
+            ))
+    {
         return Err(GovernanceError::SplTokenInvalidTokenAccountData.into());
     }
 
@@ -292,17 +418,20 @@ pub fn is_spl_token_account(account_info: &AccountInfo) -> bool {
 }
 
 /// Asserts the given mint_info represents a valid SPL Token Mint account  which
-/// is initialized and belongs to spl_token program
+/// is initialized and belongs to spl_token or Token-2022 program
 pub fn assert_is_valid_spl_token_mint(mint_info: &AccountInfo) -> Result<(), ProgramError> {
     if mint_info.data_is_empty() {
         return Err(GovernanceError::SplTokenMintDoesNotExist.into());
     }
 
-    if mint_info.owner != &spl_token::id() {
+    if mint_info.owner != &spl_token::id() && mint_info.owner != &spl_token_2022::id() {
         return Err(GovernanceError::SplTokenMintWithInvalidOwner.into());
     }
 
-    if mint_info.data_len() != Mint::LEN {
+    if mint_info.data_len() != Mint::LEN
+        && !(mint_info.owner == &spl_token_2022::id()
+            && is_extended_spl_token_2022_data(&mint_info.try_borrow_data()?, AccountType::Mint))
+    {
         return Err(GovernanceError::SplTokenInvalidMintAccountData.into());
     }
 
@@ -344,6 +473,17 @@ pub fn get_spl_token_owner(token_account_info: &AccountInfo) -> Result<Pubkey, P
     Ok(Pubkey::new_from_array(*owner_data))
 }
 
+/// Computationally cheap method to get amount from a token account
+/// It reads amount without deserializing full account data
+pub fn get_spl_token_amount(token_account_info: &AccountInfo) -> Result<u64, ProgramError> {
+    assert_is_valid_spl_token_account(token_account_info)?;
+
+    // TokeAccount layout:   mint(32), owner(32), amount(8)
+    let data = token_account_info.try_borrow_data()?;
+    let amount = array_ref![data, 64, 8];
+    Ok(u64::from_le_bytes(*amount))
+}
+
 /// Computationally cheap method to just get supply from a mint without
 /// unpacking the whole object
 pub fn get_spl_token_mint_supply(mint_info: &AccountInfo) -> Result<u64, ProgramError> {
@@ -356,6 +496,30 @@ pub fn get_spl_token_mint_supply(mint_info: &AccountInfo) -> Result<u64, Program
     Ok(u64::from_le_bytes(*bytes))
 }
 
+/// Computationally cheap method to just get decimals from a mint without
+/// unpacking the whole object
+pub fn get_spl_token_mint_decimals(mint_info: &AccountInfo) -> Result<u8, ProgramError> {
+    assert_is_valid_spl_token_mint(mint_info)?;
+    // In token program, 36, 8, 1, 1 is the layout, where the first 1 is decimals.
+    let data = mint_info.try_borrow_data()?;
+    Ok(data[44])
+}
+
+/// Returns Token-2022 extension types of the given mint
+/// Note: spl-token mints have no extensions
+pub fn get_spl_token_mint_extension_types(
+    mint_info: &AccountInfo,
+) -> Result<Vec<ExtensionType>, ProgramError> {
+    assert_is_valid_spl_token_mint(mint_info)?;
+
+    if mint_info.owner != &spl_token_2022::id() {
+        return Ok(vec![]);
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?.get_extension_types()
+}
+
 /// Computationally cheap method to just get authority from a mint without
 /// unpacking the whole object
 pub fn get_spl_token_mint_authority(
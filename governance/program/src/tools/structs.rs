@@ -23,6 +23,75 @@ impl Default for Reserved110 {
     }
 }
 
+/// Reserved 101 bytes
+#[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct Reserved101 {
+    /// Reserved 64 bytes
+    pub reserved64: [u8; 64],
+    /// Reserved 32 bytes
+    pub reserved32: [u8; 32],
+    /// Reserved 5 bytes
+    pub reserved5: [u8; 5],
+}
+
+impl Default for Reserved101 {
+    fn default() -> Self {
+        Self {
+            reserved64: [0; 64],
+            reserved32: [0; 32],
+            reserved5: [0; 5],
+        }
+    }
+}
+
+/// Reserved 100 bytes
+#[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct Reserved100 {
+    /// Reserved 64 bytes
+    pub reserved64: [u8; 64],
+    /// Reserved 32 bytes
+    pub reserved32: [u8; 32],
+    /// Reserved 4 bytes
+    pub reserved4: [u8; 4],
+}
+
+impl Default for Reserved100 {
+    fn default() -> Self {
+        Self {
+            reserved64: [0; 64],
+            reserved32: [0; 32],
+            reserved4: [0; 4],
+        }
+    }
+}
+
+/// Reserved 82 bytes
+#[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct Reserved82 {
+    /// Reserved 64 bytes
+    pub reserved64: [u8; 64],
+    /// Reserved 18 bytes
+    pub reserved18: [u8; 18],
+}
+
+impl Default for Reserved82 {
+    fn default() -> Self {
+        Self {
+            reserved64: [0; 64],
+            reserved18: [0; 18],
+        }
+    }
+}
+
+/// Reserved 49 bytes
+#[derive(Clone, Debug, Default, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct Reserved49 {
+    /// Reserved 32 bytes
+    pub reserved32: [u8; 32],
+    /// Reserved 17 bytes
+    pub reserved17: [u8; 17],
+}
+
 /// Reserved 119 bytes
 #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub struct Reserved119 {
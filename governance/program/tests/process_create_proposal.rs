@@ -12,7 +12,15 @@ use {
     solana_sdk::signature::Keypair,
     spl_governance::{
         error::GovernanceError,
-        state::{enums::VoteThreshold, governance::SECURITY_DEPOSIT_BASE_LAMPORTS},
+        instruction::GovernanceInstruction,
+        state::{
+            enums::{GovernanceAccountType, ProposalState, VoteThreshold},
+            governance::SECURITY_DEPOSIT_BASE_LAMPORTS,
+            proposal::MAX_DESCRIPTION_LINK_LEN,
+            proposal_category_record::get_proposal_category_record_address,
+            realm_config::ProposalCreationAuthority,
+            signatory_record::get_signatory_record_address,
+        },
     },
     spl_governance_tools::account::AccountMaxSize,
 };
@@ -781,3 +789,443 @@ async fn test_create_multiple_proposals_with_security_deposits() {
 
     assert_eq!(expected_lamports, proposal_deposit_account_info3.lamports);
 }
+
+#[tokio::test]
+async fn test_create_community_proposal_with_anyone_proposal_creation() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(&realm_cookie, ProposalCreationAuthority::Anyone)
+        .await
+        .unwrap();
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(proposal_cookie.account, proposal_account);
+}
+
+#[tokio::test]
+async fn test_create_community_proposal_with_council_only_proposal_creation_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(&realm_cookie, ProposalCreationAuthority::CouncilOnly)
+        .await
+        .unwrap();
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::CouncilMembershipRequiredToCreateProposal.into()
+    );
+}
+
+#[tokio::test]
+async fn test_create_community_proposal_with_council_only_proposal_creation_using_council_tokens() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(&realm_cookie, ProposalCreationAuthority::CouncilOnly)
+        .await
+        .unwrap();
+
+    let mut community_token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &community_token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let council_token_owner_record_cookie = governance_test
+        .with_council_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    // Change the proposal owner to council token owner
+    community_token_owner_record_cookie.address = council_token_owner_record_cookie.address;
+    community_token_owner_record_cookie.token_owner = council_token_owner_record_cookie.token_owner;
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal(&community_token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(
+        realm_cookie.account.community_mint,
+        proposal_account.governing_token_mint
+    );
+}
+
+#[tokio::test]
+async fn test_create_council_proposal_with_council_only_proposal_creation() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(&realm_cookie, ProposalCreationAuthority::CouncilOnly)
+        .await
+        .unwrap();
+
+    let token_owner_record_cookie = governance_test
+        .with_council_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(proposal_cookie.account, proposal_account);
+}
+
+#[tokio::test]
+async fn test_create_community_proposal_with_min_token_weight_proposal_creation() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(
+            &realm_cookie,
+            ProposalCreationAuthority::MinTokenWeight(200),
+        )
+        .await
+        .unwrap();
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit_amount(&realm_cookie, 200)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(proposal_cookie.account, proposal_account);
+}
+
+#[tokio::test]
+async fn test_create_community_proposal_with_min_token_weight_proposal_creation_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    governance_test
+        .with_community_proposal_creation(
+            &realm_cookie,
+            ProposalCreationAuthority::MinTokenWeight(200),
+        )
+        .await
+        .unwrap();
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit_amount(&realm_cookie, 199)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::InsufficientProposalCreationWeight.into()
+    );
+}
+
+#[tokio::test]
+async fn test_create_proposal_with_description_link_too_long_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_proposal_using_instruction(&token_owner_record_cookie, &mut governance_cookie, |i| {
+            let mut instruction = borsh::from_slice::<GovernanceInstruction>(&i.data).unwrap();
+            if let GovernanceInstruction::CreateProposal {
+                description_link, ..
+            } = &mut instruction
+            {
+                *description_link = "x".repeat(MAX_DESCRIPTION_LINK_LEN + 1);
+            }
+            i.data = borsh::to_vec(&instruction).unwrap();
+        })
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::DescriptionTooLong.into());
+}
+
+#[tokio::test]
+async fn test_create_proposal_signed_off_by_owner() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal_signed_off_at_creation(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(proposal_cookie.account, proposal_account);
+    assert_eq!(ProposalState::Voting, proposal_account.state);
+
+    let signatory_record_account = governance_test
+        .get_signatory_record_account(&get_signatory_record_address(
+            &governance_test.program_id,
+            &proposal_cookie.address,
+            &token_owner_record_cookie.account.governing_token_owner,
+        ))
+        .await;
+
+    assert!(signatory_record_account.signed_off);
+
+    // The Proposal can be voted on straight away
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_create_proposal_signed_off_by_owner_with_required_signatory_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let (token_owner_record_cookie, mut governance_cookie, _, _) = governance_test
+        .with_governance_with_required_signatory()
+        .await;
+
+    // Act
+    let err = governance_test
+        .with_proposal_signed_off_at_creation(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::ProposalOwnerNotSoleSignatory.into());
+}
+
+#[tokio::test]
+async fn test_create_proposal_with_category() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let proposal_cookie = governance_test
+        .with_proposal_with_category(
+            &token_owner_record_cookie,
+            &mut governance_cookie,
+            *b"treasury",
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(proposal_cookie.account, proposal_account);
+
+    let proposal_category_record_account = governance_test
+        .get_proposal_category_record_account(&get_proposal_category_record_address(
+            &governance_test.program_id,
+            &proposal_cookie.address,
+        ))
+        .await;
+
+    assert_eq!(
+        GovernanceAccountType::ProposalCategoryRecord,
+        proposal_category_record_account.account_type
+    );
+    assert_eq!(
+        governance_cookie.address,
+        proposal_category_record_account.governance
+    );
+    assert_eq!(*b"treasury", proposal_category_record_account.category);
+    assert_eq!(
+        proposal_cookie.address,
+        proposal_category_record_account.proposal
+    );
+}
@@ -1,15 +1,35 @@
 #![cfg(feature = "test-sbf")]
 
-use solana_program_test::*;
+use {borsh::BorshDeserialize, solana_program::instruction::Instruction, solana_program_test::*};
 
 mod program_test;
 
 use {
     crate::program_test::args::RealmSetupArgs,
     program_test::*,
-    spl_governance::state::{enums::MintMaxVoterWeightSource, realm::get_realm_address},
+    spl_governance::{
+        error::GovernanceError,
+        instruction::GovernanceInstruction,
+        state::{
+            enums::MintMaxVoterWeightSource,
+            realm::{get_realm_address, MAX_REALM_NAME_LEN},
+        },
+    },
+    spl_governance_test_sdk::tools::NopOverride,
 };
 
+// Replaces the name in the CreateRealm instruction data, keeping the Realm
+// address derived from the original name
+fn set_realm_name(create_realm_ix: &mut Instruction, realm_name: &str) {
+    let mut instruction = GovernanceInstruction::try_from_slice(&create_realm_ix.data).unwrap();
+
+    if let GovernanceInstruction::CreateRealm { name, .. } = &mut instruction {
+        *name = realm_name.to_string();
+    }
+
+    create_realm_ix.data = borsh::to_vec(&instruction).unwrap();
+}
+
 #[tokio::test]
 async fn test_create_realm() {
     // Arrange
@@ -106,3 +126,60 @@ async fn test_create_realm_for_existing_pda() {
 
     assert_eq!(realm_cookie.account, realm_account);
 }
+
+#[tokio::test]
+async fn test_create_realm_with_empty_name_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    // Act
+    let err = governance_test
+        .with_realm_using_instruction(&RealmSetupArgs::default(), |i| set_realm_name(i, ""))
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::RealmNameInvalid.into());
+}
+
+#[tokio::test]
+async fn test_create_realm_with_name_too_long_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_name = "r".repeat(MAX_REALM_NAME_LEN + 1);
+
+    // Act
+    let err = governance_test
+        .with_realm_using_instruction(&RealmSetupArgs::default(), |i| {
+            set_realm_name(i, &realm_name)
+        })
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::RealmNameInvalid.into());
+}
+
+#[tokio::test]
+async fn test_create_realm_with_duplicate_name_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    governance_test.with_realm().await;
+
+    // Reuse the name of the existing Realm
+    governance_test.next_realm_id -= 1;
+
+    // Act
+    let err = governance_test
+        .with_realm_using_instruction(&RealmSetupArgs::default(), NopOverride)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::RealmAlreadyExists.into());
+}
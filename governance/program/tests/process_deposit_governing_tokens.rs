@@ -12,9 +12,13 @@ use {
         error::GovernanceError,
         instruction::deposit_governing_tokens,
         state::{
-            realm_config::GoverningTokenType, token_owner_record::TOKEN_OWNER_RECORD_LAYOUT_VERSION,
+            realm_config::GoverningTokenType,
+            token_owner_record::{
+                get_token_owner_record_address, TOKEN_OWNER_RECORD_LAYOUT_VERSION,
+            },
         },
     },
+    spl_token_2022::extension::ExtensionType,
 };
 
 #[tokio::test]
@@ -233,6 +237,7 @@ async fn test_deposit_initial_community_tokens_with_owner_must_sign_error() {
         &governance_test.bench.context.payer.pubkey(),
         amount,
         &realm_cookie.account.community_mint,
+        &spl_token::id(),
     );
 
     deposit_ix.accounts[3] = AccountMeta::new_readonly(token_owner.pubkey(), false);
@@ -282,6 +287,7 @@ async fn test_deposit_community_tokens_with_malicious_holding_account_error() {
         &governance_test.bench.context.payer.pubkey(),
         amount,
         &realm_cookie.account.community_mint,
+        &spl_token::id(),
     );
 
     // Try to maliciously deposit to the source
@@ -364,3 +370,328 @@ async fn test_deposit_comunity_tokens_with_cannot_deposit_dormant_tokens_error()
     // Assert
     assert_eq!(err, GovernanceError::CannotDepositDormantTokens.into());
 }
+
+#[tokio::test]
+async fn test_deposit_membership_council_tokens() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let mut realm_config_args = RealmSetupArgs::default();
+    realm_config_args.council_token_config_args.token_type = GoverningTokenType::Membership;
+
+    let realm_cookie = governance_test
+        .with_realm_using_args(&realm_config_args)
+        .await;
+
+    let council_mint = realm_cookie.account.config.council_mint.unwrap();
+    let realm_authority = realm_cookie.realm_authority.as_ref().unwrap();
+    let token_owner = Keypair::new();
+    let token_source = Keypair::new();
+
+    // Realm authority hands out membership tokens it holds
+    governance_test
+        .bench
+        .create_token_account_with_transfer_authority(
+            &token_source,
+            &council_mint,
+            realm_cookie.council_mint_authority.as_ref().unwrap(),
+            10,
+            realm_authority,
+            &realm_authority.pubkey(),
+        )
+        .await;
+
+    let deposit_ix = deposit_governing_tokens(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &token_source.pubkey(),
+        &token_owner.pubkey(),
+        &realm_authority.pubkey(),
+        &governance_test.bench.context.payer.pubkey(),
+        10,
+        &council_mint,
+        &spl_token::id(),
+    );
+
+    // Act
+
+    // Membership tokens minted by the mint authority
+    let token_owner_record_cookie = governance_test
+        .with_council_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .bench
+        .process_transaction(&[deposit_ix], Some(&[&token_owner, realm_authority]))
+        .await
+        .unwrap();
+
+    // Assert
+
+    let token_owner_record = governance_test
+        .get_token_owner_record_account(&token_owner_record_cookie.address)
+        .await;
+
+    assert_eq!(token_owner_record_cookie.account, token_owner_record);
+
+    let holding_account = governance_test
+        .get_token_account(&realm_cookie.council_token_holding_account.unwrap())
+        .await;
+
+    assert_eq!(
+        token_owner_record.governing_token_deposit_amount + 10,
+        holding_account.amount
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_membership_council_tokens_with_not_authorized_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let mut realm_config_args = RealmSetupArgs::default();
+    realm_config_args.council_token_config_args.token_type = GoverningTokenType::Membership;
+
+    let realm_cookie = governance_test
+        .with_realm_using_args(&realm_config_args)
+        .await;
+
+    // Act
+
+    // Membership tokens acquired by the member
+    let err = governance_test
+        .with_initial_governing_token_deposit(
+            &realm_cookie.address,
+            &realm_cookie.account.config.council_mint.unwrap(),
+            realm_cookie.council_mint_authority.as_ref().unwrap(),
+            100,
+            None,
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::MembershipDepositNotAuthorized.into());
+}
+
+#[tokio::test]
+async fn test_deposit_token_2022_community_tokens() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_token_2022_realm(&[]).await;
+
+    let token_owner = Keypair::new();
+    let token_source = governance_test
+        .with_token_2022_account(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &token_owner.pubkey(),
+            100,
+        )
+        .await;
+
+    // Act
+    governance_test
+        .deposit_token_2022_community_tokens(&realm_cookie, &token_source, &token_owner, 100)
+        .await
+        .unwrap();
+
+    // Assert
+    let token_owner_record = governance_test
+        .get_token_owner_record_account(&get_token_owner_record_address(
+            &governance_test.program_id,
+            &realm_cookie.address,
+            &realm_cookie.account.community_mint,
+            &token_owner.pubkey(),
+        ))
+        .await;
+
+    assert_eq!(100, token_owner_record.governing_token_deposit_amount);
+
+    let holding_account = governance_test
+        .get_token_2022_account(&realm_cookie.community_token_holding_account)
+        .await;
+
+    assert_eq!(100, holding_account.amount);
+
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(
+        Some(spl_token_2022::id()),
+        realm_config_account.governing_token_program
+    );
+    // Deposit tracking is opt-in and wasn't enabled for the Realm
+    assert_eq!(None, realm_config_account.community_token_deposits);
+}
+
+#[tokio::test]
+async fn test_deposit_token_2022_community_tokens_with_invalid_token_program_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_token_2022_realm(&[]).await;
+
+    let token_owner = Keypair::new();
+    let token_source = governance_test
+        .with_token_2022_account(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &token_owner.pubkey(),
+            100,
+        )
+        .await;
+
+    let deposit_governing_tokens_ix = deposit_governing_tokens(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &token_source,
+        &token_owner.pubkey(),
+        &token_owner.pubkey(),
+        &governance_test.bench.payer.pubkey(),
+        100,
+        &realm_cookie.account.community_mint,
+        &spl_token::id(),
+    );
+
+    // Act
+    let err = governance_test
+        .bench
+        .process_transaction(&[deposit_governing_tokens_ix], Some(&[&token_owner]))
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::InvalidGoverningTokenProgram.into());
+}
+
+#[tokio::test]
+async fn test_deposit_token_2022_community_tokens_with_transfer_fee_not_allowed_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test
+        .with_token_2022_realm(&[ExtensionType::TransferFeeConfig])
+        .await;
+
+    let token_owner = Keypair::new();
+    let token_source = governance_test
+        .with_token_2022_account(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &token_owner.pubkey(),
+            100,
+        )
+        .await;
+
+    // Act
+    let err = governance_test
+        .deposit_token_2022_community_tokens(&realm_cookie, &token_source, &token_owner, 100)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::GoverningTokenMintExtensionNotAllowed.into()
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_token_2022_community_tokens_with_non_transferable_not_allowed_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test
+        .with_token_2022_realm(&[ExtensionType::NonTransferable])
+        .await;
+
+    let token_owner = Keypair::new();
+
+    // Non-transferable tokens can only be deposited by minting them
+    let deposit_governing_tokens_ix = deposit_governing_tokens(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &realm_cookie.account.community_mint,
+        &token_owner.pubkey(),
+        &realm_cookie.community_mint_authority.pubkey(),
+        &governance_test.bench.payer.pubkey(),
+        100,
+        &realm_cookie.account.community_mint,
+        &spl_token_2022::id(),
+    );
+
+    // Act
+    let err = governance_test
+        .bench
+        .process_transaction(
+            &[deposit_governing_tokens_ix],
+            Some(&[&token_owner, &realm_cookie.community_mint_authority]),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::GoverningTokenMintExtensionNotAllowed.into()
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_token_2022_community_tokens_with_allowed_transfer_fee() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test
+        .with_token_2022_realm(&[ExtensionType::TransferFeeConfig])
+        .await;
+
+    governance_test
+        .with_community_restricted_extensions(&realm_cookie, true)
+        .await
+        .unwrap();
+
+    let token_owner = Keypair::new();
+    let token_source = governance_test
+        .with_token_2022_account(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &token_owner.pubkey(),
+            100,
+        )
+        .await;
+
+    // Act
+    governance_test
+        .deposit_token_2022_community_tokens(&realm_cookie, &token_source, &token_owner, 100)
+        .await
+        .unwrap();
+
+    // Assert
+
+    // Only the amount received net of the transfer fee is deposited
+    let deposit_amount = 100 - 100 * TOKEN_2022_TRANSFER_FEE_BASIS_POINTS as u64 / 10_000;
+
+    let token_owner_record = governance_test
+        .get_token_owner_record_account(&get_token_owner_record_address(
+            &governance_test.program_id,
+            &realm_cookie.address,
+            &realm_cookie.account.community_mint,
+            &token_owner.pubkey(),
+        ))
+        .await;
+
+    assert_eq!(
+        deposit_amount,
+        token_owner_record.governing_token_deposit_amount
+    );
+
+    let holding_account = governance_test
+        .get_token_2022_account(&realm_cookie.community_token_holding_account)
+        .await;
+
+    assert_eq!(deposit_amount, holding_account.amount);
+}
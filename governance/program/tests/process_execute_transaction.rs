@@ -7,13 +7,18 @@ use {
     solana_program::{
         instruction::{AccountMeta, Instruction},
         program_error::ProgramError,
+        program_pack::Pack,
         sysvar::clock,
     },
     solana_program_test::tokio,
     spl_governance::{
         error::GovernanceError,
-        state::enums::{ProposalState, TransactionExecutionStatus},
+        state::{
+            enums::{ProposalState, TransactionExecutionStatus},
+            proposal_transaction::TransactionReturnData,
+        },
     },
+    spl_token::{instruction::get_account_data_size, state::Account},
 };
 
 #[tokio::test]
@@ -784,3 +789,200 @@ async fn test_execute_transaction_with_create_proposal_and_execute_in_single_slo
         GovernanceError::CannotExecuteTransactionWithinHoldUpTime.into()
     );
 }
+
+#[tokio::test]
+async fn test_execute_transaction_within_proposal_hold_up_time_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_mint_cookie = governance_test.with_governed_mint().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut mint_governance_cookie = governance_test
+        .with_mint_governance(
+            &realm_cookie,
+            &governed_mint_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut mint_governance_cookie)
+        .await
+        .unwrap();
+
+    let signatory_record_cookie = governance_test
+        .with_signatory(
+            &proposal_cookie,
+            &mint_governance_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_transaction_cookie = governance_test
+        .with_mint_tokens_transaction(
+            &governed_mint_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            None,
+            Some(15),
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .set_proposal_hold_up_time(&mut proposal_cookie, &token_owner_record_cookie, 100)
+        .await
+        .unwrap();
+
+    governance_test
+        .sign_off_proposal(&proposal_cookie, &signatory_record_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Advance timestamp past the transaction hold_up_time but not past the
+    // proposal hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_transaction_cookie.account.hold_up_time as u64)
+        .await;
+
+    // Act
+    let err = governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::CannotExecuteTransactionWithinHoldUpTime.into()
+    );
+
+    // Advance timestamp past the proposal hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_cookie.account.hold_up_time as u64)
+        .await;
+
+    // Act
+    governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_transaction_account = governance_test
+        .get_proposal_transaction_account(&proposal_transaction_cookie.address)
+        .await;
+
+    assert_eq!(
+        TransactionExecutionStatus::Success,
+        proposal_transaction_account.execution_status
+    );
+}
+
+#[tokio::test]
+async fn test_execute_transaction_with_return_data() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_mint_cookie = governance_test.with_governed_mint().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut mint_governance_cookie = governance_test
+        .with_mint_governance(
+            &realm_cookie,
+            &governed_mint_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut mint_governance_cookie)
+        .await
+        .unwrap();
+
+    let signatory_record_cookie = governance_test
+        .with_signatory(
+            &proposal_cookie,
+            &mint_governance_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // GetAccountDataSize returns the token account size as return data
+    let mut instruction =
+        get_account_data_size(&spl_token::id(), &governed_mint_cookie.address).unwrap();
+
+    let proposal_transaction_cookie = governance_test
+        .with_proposal_transaction(
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            None,
+            &mut instruction,
+            None,
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .sign_off_proposal(&proposal_cookie, &signatory_record_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Advance timestamp past hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_transaction_cookie.account.hold_up_time as u64)
+        .await;
+
+    let clock = governance_test.bench.get_clock().await;
+
+    // Act
+    governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_transaction_account = governance_test
+        .get_proposal_transaction_account(&proposal_transaction_cookie.address)
+        .await;
+
+    assert_eq!(
+        Some(clock.unix_timestamp),
+        proposal_transaction_account.executed_at
+    );
+    assert_eq!(
+        Some(TransactionReturnData {
+            program_id: spl_token::id(),
+            data: (Account::LEN as u64).to_le_bytes().to_vec(),
+        }),
+        proposal_transaction_account.return_data
+    );
+}
@@ -2,7 +2,18 @@
 
 mod program_test;
 
-use {program_test::*, solana_program_test::tokio, spl_governance::error::GovernanceError};
+use {
+    program_test::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    },
+    solana_program_test::tokio,
+    spl_governance::{
+        error::GovernanceError,
+        state::proposal_transaction::{estimate_compute_units, MAX_TRANSACTION_ACCOUNTS},
+    },
+};
 
 #[tokio::test]
 async fn test_insert_transaction() {
@@ -385,3 +396,118 @@ async fn test_insert_transaction_with_invalid_governance_for_proposal_error() {
     // Assert
     assert_eq!(err, GovernanceError::InvalidGovernanceForProposal.into());
 }
+
+// The instruction program id counts towards the transaction accounts too
+fn create_instruction_with_accounts(accounts_count: usize) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: (0..accounts_count)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect(),
+        data: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_insert_transaction_with_max_accounts() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    let mut instruction = create_instruction_with_accounts(MAX_TRANSACTION_ACCOUNTS - 1);
+
+    // Act
+    let proposal_transaction_cookie = governance_test
+        .with_proposal_transaction(
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            None,
+            &mut instruction,
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_transaction_account = governance_test
+        .get_proposal_transaction_account(&proposal_transaction_cookie.address)
+        .await;
+
+    assert_eq!(
+        proposal_transaction_account.instructions[0].accounts.len(),
+        MAX_TRANSACTION_ACCOUNTS - 1
+    );
+    assert_eq!(
+        proposal_transaction_account.estimated_compute_units,
+        estimate_compute_units(&proposal_transaction_account.instructions)
+    );
+}
+
+#[tokio::test]
+async fn test_insert_transaction_with_too_many_accounts_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    let mut instruction = create_instruction_with_accounts(MAX_TRANSACTION_ACCOUNTS);
+
+    // Act
+    let err = governance_test
+        .with_proposal_transaction(
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            None,
+            &mut instruction,
+            None,
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::TooManyTransactionAccounts.into());
+}
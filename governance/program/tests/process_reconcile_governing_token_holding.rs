@@ -0,0 +1,638 @@
+#![cfg(feature = "test-sbf")]
+
+use {solana_program::pubkey::Pubkey, solana_program_test::*};
+
+mod program_test;
+
+use {
+    program_test::*,
+    solana_sdk::signature::Signer,
+    spl_governance::{
+        error::GovernanceError,
+        instruction::{
+            reconcile_governing_token_holding, with_writable_realm_config,
+            withdraw_governing_tokens,
+        },
+        state::native_treasury::get_native_treasury_address,
+    },
+};
+
+#[tokio::test]
+async fn test_reconcile_community_token_holding() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let treasury_cookie = governance_test
+        .bench
+        .with_token_account(
+            &realm_cookie.account.community_mint,
+            &governance_cookie.address,
+            &realm_cookie.community_mint_authority,
+            0,
+        )
+        .await;
+
+    // Tokens sent directly to the holding account without a deposit
+    governance_test
+        .bench
+        .mint_tokens(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &realm_cookie.community_token_holding_account,
+            50,
+        )
+        .await;
+
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &treasury_cookie.address,
+        &realm_cookie.account.community_mint,
+    );
+
+    // Act
+    governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .unwrap();
+
+    // Assert
+    let treasury_account = governance_test
+        .get_token_account(&treasury_cookie.address)
+        .await;
+
+    assert_eq!(50, treasury_account.amount);
+
+    let holding_account = governance_test
+        .get_token_account(&realm_cookie.community_token_holding_account)
+        .await;
+
+    assert_eq!(
+        token_owner_record_cookie
+            .account
+            .governing_token_deposit_amount,
+        holding_account.amount
+    );
+
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(
+        Some(
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount
+        ),
+        realm_config_account.community_token_deposits
+    );
+
+    // The depositor can only withdraw the deposited amount
+    let withdraw_ix = with_writable_realm_config(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        withdraw_governing_tokens(
+            &governance_test.program_id,
+            &realm_cookie.address,
+            &token_owner_record_cookie.token_source,
+            &token_owner_record_cookie.token_owner.pubkey(),
+            &realm_cookie.account.community_mint,
+            &spl_token::id(),
+        ),
+    );
+
+    governance_test
+        .bench
+        .process_transaction(
+            &[withdraw_ix],
+            Some(&[&token_owner_record_cookie.token_owner]),
+        )
+        .await
+        .unwrap();
+
+    let source_account = governance_test
+        .get_token_account(&token_owner_record_cookie.token_source)
+        .await;
+
+    assert_eq!(
+        token_owner_record_cookie.token_source_amount,
+        source_account.amount
+    );
+
+    let holding_account = governance_test
+        .get_token_account(&realm_cookie.community_token_holding_account)
+        .await;
+
+    assert_eq!(0, holding_account.amount);
+}
+
+#[tokio::test]
+async fn test_reconcile_council_token_holding_to_native_treasury() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_council_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let council_mint = realm_cookie.account.config.council_mint.unwrap();
+
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &council_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let council_mint_authority = realm_cookie.council_mint_authority.as_ref().unwrap();
+
+    let treasury_cookie = governance_test
+        .bench
+        .with_token_account(
+            &council_mint,
+            &get_native_treasury_address(&governance_test.program_id, &governance_cookie.address),
+            council_mint_authority,
+            0,
+        )
+        .await;
+
+    governance_test
+        .bench
+        .mint_tokens(
+            &council_mint,
+            council_mint_authority,
+            &realm_cookie.council_token_holding_account.unwrap(),
+            10,
+        )
+        .await;
+
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &treasury_cookie.address,
+        &council_mint,
+    );
+
+    // Act
+    governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .unwrap();
+
+    // Assert
+    let treasury_account = governance_test
+        .get_token_account(&treasury_cookie.address)
+        .await;
+
+    assert_eq!(10, treasury_account.amount);
+
+    let holding_account = governance_test
+        .get_token_account(&realm_cookie.council_token_holding_account.unwrap())
+        .await;
+
+    assert_eq!(
+        token_owner_record_cookie
+            .account
+            .governing_token_deposit_amount,
+        holding_account.amount
+    );
+}
+
+#[tokio::test]
+async fn test_reconcile_community_token_holding_with_no_surplus_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let treasury_cookie = governance_test
+        .bench
+        .with_token_account(
+            &realm_cookie.account.community_mint,
+            &governance_cookie.address,
+            &realm_cookie.community_mint_authority,
+            0,
+        )
+        .await;
+
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &treasury_cookie.address,
+        &realm_cookie.account.community_mint,
+    );
+
+    // Act
+    let err = governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::GoverningTokenHoldingHasNoSurplus.into()
+    );
+}
+
+#[tokio::test]
+async fn test_reconcile_community_token_holding_with_invalid_treasury_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Token account not owned by the Governance
+    let hacker_token_account_cookie = governance_test
+        .bench
+        .with_token_account(
+            &realm_cookie.account.community_mint,
+            &Pubkey::new_unique(),
+            &realm_cookie.community_mint_authority,
+            0,
+        )
+        .await;
+
+    governance_test
+        .bench
+        .mint_tokens(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &realm_cookie.community_token_holding_account,
+            50,
+        )
+        .await;
+
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &hacker_token_account_cookie.address,
+        &realm_cookie.account.community_mint,
+    );
+
+    // Act
+    let err = governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::InvalidGoverningTokenTreasury.into());
+}
+
+#[tokio::test]
+async fn test_reconcile_community_token_holding_with_untracked_deposits_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let treasury_cookie = governance_test
+        .bench
+        .with_token_account(
+            &realm_cookie.account.community_mint,
+            &governance_cookie.address,
+            &realm_cookie.community_mint_authority,
+            0,
+        )
+        .await;
+
+    governance_test
+        .bench
+        .mint_tokens(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &realm_cookie.community_token_holding_account,
+            50,
+        )
+        .await;
+
+    // Deposit tracking is opt-in and wasn't enabled for the Realm
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &treasury_cookie.address,
+        &realm_cookie.account.community_mint,
+    );
+
+    // Act
+    let err = governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::GoverningTokenDepositsNotTracked.into()
+    );
+}
+
+#[tokio::test]
+async fn test_enable_deposit_tracking_with_earlier_surplus() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let treasury_cookie = governance_test
+        .bench
+        .with_token_account(
+            &realm_cookie.account.community_mint,
+            &governance_cookie.address,
+            &realm_cookie.community_mint_authority,
+            0,
+        )
+        .await;
+
+    // Tokens sent to the holding account before the deposits are tracked
+    governance_test
+        .bench
+        .mint_tokens(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &realm_cookie.community_token_holding_account,
+            50,
+        )
+        .await;
+
+    // Act
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    // Assert
+
+    // The tracked total starts at the given deposits
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(
+        Some(
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount
+        ),
+        realm_config_account.community_token_deposits
+    );
+
+    // The surplus sent before the tracking was enabled can be swept
+    let reconcile_ix = reconcile_governing_token_holding(
+        &governance_test.program_id,
+        &realm_cookie.address,
+        &governance_cookie.address,
+        &treasury_cookie.address,
+        &realm_cookie.account.community_mint,
+    );
+
+    governance_test
+        .bench
+        .process_transaction(&[reconcile_ix], None)
+        .await
+        .unwrap();
+
+    let treasury_account = governance_test
+        .get_token_account(&treasury_cookie.address)
+        .await;
+
+    assert_eq!(50, treasury_account.amount);
+}
+
+#[tokio::test]
+async fn test_enable_deposit_tracking_with_deposits_above_holding_balance_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount
+                + 1,
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::InvalidGoverningTokenDeposits.into());
+
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(None, realm_config_account.community_token_deposits);
+}
+
+#[tokio::test]
+async fn test_withdraw_tracked_deposits_with_read_only_realm_config_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_realm().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            true,
+            token_owner_record_cookie
+                .account
+                .governing_token_deposit_amount,
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .withdraw_community_tokens(&realm_cookie, &token_owner_record_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::RealmConfigMustBeWritable.into());
+
+    // Clients passing a read only RealmConfig work again once the tracking is disabled
+    governance_test
+        .with_governing_token_deposit_tracking(
+            &realm_cookie,
+            &realm_cookie.account.community_mint,
+            false,
+            0,
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .withdraw_community_tokens(&realm_cookie, &token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(None, realm_config_account.community_token_deposits);
+}
@@ -1,6 +1,10 @@
 #![cfg(feature = "test-sbf")]
 
-use {solana_program::pubkey::Pubkey, solana_program_test::*};
+use {
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::signature::{Keypair, Signer},
+};
 
 mod program_test;
 
@@ -109,7 +113,110 @@ async fn test_set_realm_authority_unchecked() {
         .get_realm_account(&realm_cookie.address)
         .await;
 
-    assert_eq!(realm_account.authority, Some(new_realm_authority));
+    assert_eq!(
+        realm_account.authority,
+        Some(realm_cookie.realm_authority.as_ref().unwrap().pubkey())
+    );
+    assert_eq!(realm_account.pending_authority, Some(new_realm_authority));
+}
+
+#[tokio::test]
+async fn test_accept_realm_authority() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+
+    let new_realm_authority = Keypair::new();
+
+    governance_test
+        .set_realm_authority_impl(&realm_cookie, Some(&new_realm_authority.pubkey()), false)
+        .await
+        .unwrap();
+
+    // Act
+    governance_test
+        .accept_realm_authority(&realm_cookie, &new_realm_authority)
+        .await
+        .unwrap();
+
+    // Assert
+    let realm_account = governance_test
+        .get_realm_account(&realm_cookie.address)
+        .await;
+
+    assert_eq!(realm_account.authority, Some(new_realm_authority.pubkey()));
+    assert_eq!(realm_account.pending_authority, None);
+}
+
+#[tokio::test]
+async fn test_cancel_pending_realm_authority() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+
+    let new_realm_authority = Keypair::new();
+
+    governance_test
+        .set_realm_authority_impl(&realm_cookie, Some(&new_realm_authority.pubkey()), false)
+        .await
+        .unwrap();
+
+    // Act
+    governance_test
+        .cancel_pending_realm_authority(&realm_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let realm_account = governance_test
+        .get_realm_account(&realm_cookie.address)
+        .await;
+
+    assert_eq!(realm_account.pending_authority, None);
+
+    let err = governance_test
+        .accept_realm_authority(&realm_cookie, &new_realm_authority)
+        .await
+        .err()
+        .unwrap();
+
+    assert_eq!(err, GovernanceError::PendingAuthorityMismatch.into());
+}
+
+#[tokio::test]
+async fn test_accept_realm_authority_with_wrong_signer_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+
+    let new_realm_authority = Keypair::new();
+
+    governance_test
+        .set_realm_authority_impl(&realm_cookie, Some(&new_realm_authority.pubkey()), false)
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .accept_realm_authority(&realm_cookie, &Keypair::new())
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::PendingAuthorityMismatch.into());
+
+    let realm_account = governance_test
+        .get_realm_account(&realm_cookie.address)
+        .await;
+
+    assert_eq!(
+        realm_account.authority,
+        Some(realm_cookie.realm_authority.as_ref().unwrap().pubkey())
+    );
 }
 
 #[tokio::test]
@@ -3,6 +3,7 @@
 mod program_test;
 
 use {
+    crate::program_test::args::RealmSetupArgs,
     program_test::*,
     solana_program::pubkey::Pubkey,
     solana_program_test::tokio,
@@ -12,9 +13,9 @@ use {
         state::{
             enums::GovernanceAccountType,
             realm::SetRealmConfigItemArgs,
-            realm_config::{GoverningTokenConfig, RealmConfigAccount},
+            realm_config::{GoverningTokenConfig, ProposalCreationAuthority, RealmConfigAccount},
         },
-        tools::structs::{Reserved110, SetConfigItemActionType},
+        tools::structs::{Reserved49, SetConfigItemActionType},
     },
     spl_governance_tools::account::AccountMaxSize,
 };
@@ -336,7 +337,12 @@ async fn test_set_realm_config_item_with_extended_account_size() {
         realm: realm_cookie.address,
         community_token_config: GoverningTokenConfig::default(),
         council_token_config: GoverningTokenConfig::default(),
-        reserved: Reserved110::default(),
+        community_proposal_creation: ProposalCreationAuthority::default(),
+        snapshot_max_voter_weight: false,
+        community_token_deposits: None,
+        council_token_deposits: None,
+        governing_token_program: None,
+        reserved: Reserved49::default(),
     };
 
     assert_eq!(
@@ -384,3 +390,76 @@ async fn test_remove_token_owner_record_lock_authority_with_authority_not_found_
         GovernanceError::TokenOwnerRecordLockAuthorityNotFound.into()
     );
 }
+
+#[tokio::test]
+async fn test_set_community_proposal_creation() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+
+    // Act
+    governance_test
+        .with_community_proposal_creation(
+            &realm_cookie,
+            ProposalCreationAuthority::MinTokenWeight(200),
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert_eq!(
+        ProposalCreationAuthority::MinTokenWeight(200),
+        realm_config_account.community_proposal_creation
+    );
+}
+
+#[tokio::test]
+async fn test_set_council_only_proposal_creation_without_council_mint_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_setup_args = RealmSetupArgs {
+        use_council_mint: false,
+        ..Default::default()
+    };
+
+    let realm_cookie = governance_test
+        .with_realm_using_args(&realm_setup_args)
+        .await;
+
+    // Act
+    let err = governance_test
+        .with_community_proposal_creation(&realm_cookie, ProposalCreationAuthority::CouncilOnly)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::InvalidGoverningTokenMint.into());
+}
+
+#[tokio::test]
+async fn test_set_max_voter_weight_snapshot() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+
+    // Act
+    governance_test
+        .with_max_voter_weight_snapshot(&realm_cookie, true)
+        .await
+        .unwrap();
+
+    // Assert
+    let realm_config_account = governance_test
+        .get_realm_config_account(&realm_cookie.realm_config.address)
+        .await;
+
+    assert!(realm_config_account.snapshot_max_voter_weight);
+}
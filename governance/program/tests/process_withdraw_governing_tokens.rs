@@ -10,7 +10,7 @@ mod program_test;
 use {
     crate::program_test::args::RealmSetupArgs,
     program_test::*,
-    solana_sdk::signature::Signer,
+    solana_sdk::signature::{Keypair, Signer},
     spl_governance::{
         error::GovernanceError,
         instruction::withdraw_governing_tokens,
@@ -119,6 +119,7 @@ async fn test_withdraw_community_tokens_with_owner_must_sign_error() {
         &hacker_token_destination,
         &token_owner_record_cookie.token_owner.pubkey(),
         &realm_cookie.account.community_mint,
+        &spl_token::id(),
     );
 
     withdraw_ix.accounts[3] =
@@ -166,6 +167,7 @@ async fn test_withdraw_community_tokens_with_token_owner_record_address_mismatch
         &hacker_record_cookie.token_source,
         &hacker_record_cookie.token_owner.pubkey(),
         &realm_cookie.account.community_mint,
+        &spl_token::id(),
     );
 
     withdraw_ix.accounts[4] = AccountMeta::new(vote_record_address, false);
@@ -315,6 +317,7 @@ async fn test_withdraw_tokens_with_malicious_holding_account_error() {
         &token_owner_record_cookie.token_source,
         &token_owner_record_cookie.token_owner.pubkey(),
         &realm_cookie.account.community_mint,
+        &spl_token::id(),
     );
 
     withdraw_ix.accounts[1].pubkey = realm_token_account_cookie.address;
@@ -525,3 +528,53 @@ async fn test_withdraw_governing_tokens_with_token_owner_record_lock_error() {
     // Assert
     assert_eq!(err, GovernanceError::TokenOwnerRecordLocked.into());
 }
+
+#[tokio::test]
+async fn test_withdraw_token_2022_community_tokens() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+    let realm_cookie = governance_test.with_token_2022_realm(&[]).await;
+
+    let token_owner = Keypair::new();
+    let token_source = governance_test
+        .with_token_2022_account(
+            &realm_cookie.account.community_mint,
+            &realm_cookie.community_mint_authority,
+            &token_owner.pubkey(),
+            100,
+        )
+        .await;
+
+    governance_test
+        .deposit_token_2022_community_tokens(&realm_cookie, &token_source, &token_owner, 100)
+        .await
+        .unwrap();
+
+    // Act
+    governance_test
+        .withdraw_token_2022_community_tokens(&realm_cookie, &token_source, &token_owner)
+        .await
+        .unwrap();
+
+    // Assert
+    let token_owner_record = governance_test
+        .get_token_owner_record_account(&get_token_owner_record_address(
+            &governance_test.program_id,
+            &realm_cookie.address,
+            &realm_cookie.account.community_mint,
+            &token_owner.pubkey(),
+        ))
+        .await;
+
+    assert_eq!(0, token_owner_record.governing_token_deposit_amount);
+
+    let holding_account = governance_test
+        .get_token_2022_account(&realm_cookie.community_token_holding_account)
+        .await;
+
+    assert_eq!(0, holding_account.amount);
+
+    let source_account = governance_test.get_token_2022_account(&token_source).await;
+
+    assert_eq!(100, source_account.amount);
+}
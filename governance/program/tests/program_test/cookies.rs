@@ -12,6 +12,7 @@ use {
         max_voter_weight::MaxVoterWeightRecord, voter_weight::VoterWeightRecord,
     },
     spl_governance_test_sdk::tools::clone_keypair,
+    spl_token_lending::state::ReserveConfig,
 };
 
 pub trait AccountCookie {
@@ -130,6 +131,19 @@ impl AccountCookie for GovernedAccountCookie {
     }
 }
 
+#[derive(Debug)]
+pub struct GovernedLendingReserveCookie {
+    pub address: Pubkey,
+    pub lending_market: Pubkey,
+    pub config: ReserveConfig,
+}
+
+impl AccountCookie for GovernedLendingReserveCookie {
+    fn get_address(&self) -> Pubkey {
+        self.address
+    }
+}
+
 #[derive(Debug)]
 pub struct GovernanceCookie {
     pub address: Pubkey,
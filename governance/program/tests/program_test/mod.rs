@@ -16,16 +16,17 @@ use {
     solana_sdk::signature::{Keypair, Signer},
     spl_governance::{
         instruction::{
-            add_required_signatory, add_signatory, cancel_proposal, cast_vote, complete_proposal,
-            create_governance, create_mint_governance, create_native_treasury,
-            create_program_governance, create_proposal, create_realm, create_token_governance,
-            create_token_owner_record, deposit_governing_tokens, execute_transaction,
-            finalize_vote, flag_transaction_error, insert_transaction, refund_proposal_deposit,
-            relinquish_token_owner_record_locks, relinquish_vote, remove_required_signatory,
-            remove_transaction, revoke_governing_tokens, set_governance_config,
-            set_governance_delegate, set_realm_authority, set_realm_config, set_realm_config_item,
+            accept_realm_authority, add_required_signatory, add_signatory, cancel_proposal,
+            cast_vote, change_vote, complete_proposal, create_governance, create_mint_governance,
+            create_native_treasury, create_program_governance, create_proposal, create_realm,
+            create_token_governance, create_token_owner_record, deposit_governing_tokens,
+            execute_transaction, finalize_vote, flag_transaction_error, insert_transaction,
+            refund_proposal_deposit, relinquish_token_owner_record_locks, relinquish_vote,
+            remove_required_signatory, remove_transaction, revoke_governing_tokens,
+            set_governance_config, set_governance_delegate, set_proposal_hold_up_time,
+            set_realm_authority, set_realm_config, set_realm_config_item,
             set_token_owner_record_lock, sign_off_proposal, upgrade_program_metadata,
-            withdraw_governing_tokens, AddSignatoryAuthority,
+            withdraw_governing_tokens, AddSignatoryAuthority, GovernanceInstruction,
         },
         processor::process_instruction,
         state::{
@@ -43,8 +44,12 @@ use {
             proposal::{
                 get_proposal_address, OptionVoteResult, ProposalOption, ProposalV2, VoteType,
             },
+            proposal_category_record::{
+                get_proposal_category_record_address, ProposalCategoryRecord,
+            },
             proposal_deposit::{get_proposal_deposit_address, ProposalDeposit},
             proposal_transaction::{
+                assert_instructions_program_id, estimate_compute_units,
                 get_proposal_transaction_address, InstructionData, ProposalTransactionV2,
             },
             realm::{
@@ -52,8 +57,11 @@ use {
                 GoverningTokenConfigAccountArgs, RealmConfig, RealmV2, SetRealmAuthorityAction,
                 SetRealmConfigItemArgs,
             },
-            realm_config::{get_realm_config_address, GoverningTokenConfig, RealmConfigAccount},
-            required_signatory::RequiredSignatory,
+            realm_config::{
+                get_realm_config_address, GoverningTokenConfig, GoverningTokenType,
+                ProposalCreationAuthority, RealmConfigAccount,
+            },
+            required_signatory::{get_required_signatory_address, RequiredSignatory},
             signatory_record::{get_signatory_record_address, SignatoryRecordV2},
             token_owner_record::{
                 get_token_owner_record_address, TokenOwnerRecordV2,
@@ -63,7 +71,7 @@ use {
         },
         tools::{
             bpf_loader_upgradeable::get_program_data_address,
-            structs::{Reserved110, Reserved119, SetConfigItemActionType},
+            structs::{Reserved119, Reserved49, SetConfigItemActionType},
         },
     },
     spl_governance_addin_api::{
@@ -73,9 +81,24 @@ use {
     spl_governance_addin_mock::instruction::{
         setup_max_voter_weight_record, setup_voter_weight_record,
     },
+    spl_token_2022::{
+        extension::{
+            transfer_fee::instruction::initialize_transfer_fee_config, BaseStateWithExtensions,
+            ExtensionType, StateWithExtensions,
+        },
+        instruction::initialize_non_transferable_mint,
+    },
+    spl_token_lending::{
+        instruction::modify_reserve_config,
+        state::{InitLendingMarketParams, LendingMarket, Reserve, ReserveConfig, PROGRAM_VERSION},
+    },
     std::str::FromStr,
 };
 
+/// Transfer fee of Token-2022 governing token mints with the transfer fee
+/// extension
+pub const TOKEN_2022_TRANSFER_FEE_BASIS_POINTS: u16 = 100;
+
 pub mod args;
 pub mod cookies;
 pub mod legacy;
@@ -84,18 +107,17 @@ use {
     crate::{
         args::{PluginSetupArgs, RealmSetupArgs},
         cookies::{
-            GovernanceCookie, GovernedAccountCookie, GovernedMintCookie, GovernedProgramCookie,
-            GovernedTokenCookie, MaxVoterWeightRecordCookie, NativeTreasuryCookie,
-            ProgramMetadataCookie, ProposalCookie, ProposalDepositCookie,
-            ProposalTransactionCookie, RealmCookie, TokenOwnerRecordCookie,
-            TokenOwnerRecordLockCookie, VoteRecordCookie,
+            GovernanceCookie, GovernedAccountCookie, GovernedLendingReserveCookie,
+            GovernedMintCookie, GovernedProgramCookie, GovernedTokenCookie,
+            MaxVoterWeightRecordCookie, NativeTreasuryCookie, ProgramMetadataCookie,
+            ProposalCookie, ProposalDepositCookie, ProposalTransactionCookie, RealmCookie,
+            TokenOwnerRecordCookie, TokenOwnerRecordLockCookie, VoteRecordCookie,
         },
         program_test::cookies::{
             RealmConfigCookie, SignatoryRecordCookie, VoterWeightRecordCookie,
         },
     },
     spl_governance_test_sdk::{
-        addins::ensure_addin_mock_is_built,
         cookies::WalletCookie,
         tools::{clone_keypair, NopOverride},
         ProgramTestBench,
@@ -146,15 +168,6 @@ impl GovernanceProgramTest {
         use_voter_weight_addin: bool,
         use_max_voter_weight_addin: bool,
     ) -> Self {
-        // We only ensure the addin mock program is built but it doesn't detect
-        // changes.
-        // If the addin is changed then it needs to be manually rebuilt.
-        // Note: The crate of the mock is built when spl-governance is built
-        // but we also need spl_governance_addin_mock.so.
-        // And we can't use build.rs script because cargo build-sbf hangs when
-        // executed from the script.
-        ensure_addin_mock_is_built();
-
         Self::start_impl(use_voter_weight_addin, use_max_voter_weight_addin).await
     }
 
@@ -169,10 +182,21 @@ impl GovernanceProgramTest {
             processor!(process_instruction),
         );
 
+        // Lending program governed through reserve config proposals
+        program_test.add_program(
+            "spl_token_lending",
+            spl_token_lending::id(),
+            processor!(spl_token_lending::processor::process_instruction),
+        );
+
         let voter_weight_addin_id = if use_voter_weight_addin {
             let addin_mock_id =
                 Pubkey::from_str("VoterWeightAddin111111111111111111111111111").unwrap();
-            program_test.add_program("spl_governance_addin_mock", addin_mock_id, None);
+            program_test.add_program(
+                "spl_governance_addin_mock",
+                addin_mock_id,
+                processor!(spl_governance_addin_mock::processor::process_instruction),
+            );
 
             Some(addin_mock_id)
         } else {
@@ -182,7 +206,11 @@ impl GovernanceProgramTest {
         let max_voter_weight_addin_id = if use_max_voter_weight_addin {
             let addin_mock_id =
                 Pubkey::from_str("MaxVoterWeightAddin111111111111111111111111").unwrap();
-            program_test.add_program("spl_governance_addin_mock", addin_mock_id, None);
+            program_test.add_program(
+                "spl_governance_addin_mock",
+                addin_mock_id,
+                processor!(spl_governance_addin_mock::processor::process_instruction),
+            );
 
             Some(addin_mock_id)
         } else {
@@ -245,6 +273,17 @@ impl GovernanceProgramTest {
         &mut self,
         realm_setup_args: &RealmSetupArgs,
     ) -> RealmCookie {
+        self.with_realm_using_instruction(realm_setup_args, NopOverride)
+            .await
+            .unwrap()
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_realm_using_instruction<F: Fn(&mut Instruction)>(
+        &mut self,
+        realm_setup_args: &RealmSetupArgs,
+        instruction_override: F,
+    ) -> Result<RealmCookie, ProgramError> {
         let name = format!("Realm #{}", self.next_realm_id).to_string();
         self.next_realm_id += 1;
 
@@ -326,12 +365,13 @@ impl GovernanceProgramTest {
                 .clone(),
         };
 
-        let create_realm_ix = create_realm(
+        let mut create_realm_ix = create_realm(
             &self.program_id,
             &realm_authority.pubkey(),
             &community_token_mint_keypair.pubkey(),
             &self.bench.payer.pubkey(),
             council_token_mint_pubkey,
+            &spl_token::id(),
             Some(community_token_args),
             Some(council_token_args),
             name.clone(),
@@ -341,10 +381,11 @@ impl GovernanceProgramTest {
                 .clone(),
         );
 
+        instruction_override(&mut create_realm_ix);
+
         self.bench
             .process_transaction(&[create_realm_ix], None)
-            .await
-            .unwrap();
+            .await?;
 
         let account = RealmV2 {
             account_type: GovernanceAccountType::RealmV2,
@@ -366,7 +407,8 @@ impl GovernanceProgramTest {
                 legacy2: 0,
             },
             legacy1: 0,
-            reserved_v2: [0; 128],
+            pending_authority: None,
+            reserved_v2: [0; 95],
         };
 
         let realm_config_cookie = RealmConfigCookie {
@@ -374,7 +416,12 @@ impl GovernanceProgramTest {
             account: RealmConfigAccount {
                 account_type: GovernanceAccountType::RealmConfig,
                 realm: realm_address,
-                reserved: Reserved110::default(),
+                community_proposal_creation: ProposalCreationAuthority::default(),
+                snapshot_max_voter_weight: false,
+                community_token_deposits: None,
+                council_token_deposits: None,
+                governing_token_program: Some(spl_token::id()),
+                reserved: Reserved49::default(),
                 community_token_config: GoverningTokenConfig {
                     voter_weight_addin: realm_setup_args
                         .community_token_config_args
@@ -386,7 +433,8 @@ impl GovernanceProgramTest {
                         .community_token_config_args
                         .token_type
                         .clone(),
-                    reserved: [0; 4],
+                    allow_restricted_extensions: false,
+                    reserved: [0; 3],
                     lock_authorities: vec![],
                 },
                 council_token_config: GoverningTokenConfig {
@@ -400,13 +448,14 @@ impl GovernanceProgramTest {
                         .council_token_config_args
                         .token_type
                         .clone(),
-                    reserved: [0; 4],
+                    allow_restricted_extensions: false,
+                    reserved: [0; 3],
                     lock_authorities: vec![],
                 },
             },
         };
 
-        RealmCookie {
+        Ok(RealmCookie {
             address: realm_address,
             account,
 
@@ -417,7 +466,7 @@ impl GovernanceProgramTest {
             council_mint_authority: council_token_mint_authority,
             realm_authority: Some(realm_authority),
             realm_config: realm_config_cookie,
-        }
+        })
     }
 
     #[allow(dead_code)]
@@ -439,6 +488,7 @@ impl GovernanceProgramTest {
             &realm_cookie.account.community_mint,
             &self.bench.context.payer.pubkey(),
             Some(council_mint),
+            &spl_token::id(),
             None,
             None,
             name.clone(),
@@ -469,7 +519,8 @@ impl GovernanceProgramTest {
                 legacy2: 0,
             },
             legacy1: 0,
-            reserved_v2: [0; 128],
+            pending_authority: None,
+            reserved_v2: [0; 95],
         };
 
         let community_token_holding_address = get_governing_token_holding_address(
@@ -487,7 +538,12 @@ impl GovernanceProgramTest {
                 account_type: GovernanceAccountType::RealmConfig,
                 realm: realm_address,
                 council_token_config: GoverningTokenConfig::default(),
-                reserved: Reserved110::default(),
+                community_proposal_creation: ProposalCreationAuthority::default(),
+                snapshot_max_voter_weight: false,
+                community_token_deposits: None,
+                council_token_deposits: None,
+                governing_token_program: Some(spl_token::id()),
+                reserved: Reserved49::default(),
                 community_token_config: GoverningTokenConfig::default(),
             },
         };
@@ -508,6 +564,268 @@ impl GovernanceProgramTest {
         }
     }
 
+    // Creates Realm without council using Token-2022 community mint with the given
+    // mint extensions
+    #[allow(dead_code)]
+    pub async fn with_token_2022_realm(
+        &mut self,
+        mint_extension_types: &[ExtensionType],
+    ) -> RealmCookie {
+        let name = format!("Realm #{}", self.next_realm_id).to_string();
+        self.next_realm_id += 1;
+
+        let realm_address = get_realm_address(&self.program_id, &name);
+
+        let community_token_mint_keypair = Keypair::new();
+        let community_token_mint_authority = Keypair::new();
+
+        self.create_token_2022_mint(
+            &community_token_mint_keypair,
+            &community_token_mint_authority.pubkey(),
+            mint_extension_types,
+        )
+        .await;
+
+        let realm_authority = Keypair::new();
+
+        let create_realm_ix = create_realm(
+            &self.program_id,
+            &realm_authority.pubkey(),
+            &community_token_mint_keypair.pubkey(),
+            &self.bench.payer.pubkey(),
+            None,
+            &spl_token_2022::id(),
+            None,
+            None,
+            name,
+            10,
+            MintMaxVoterWeightSource::FULL_SUPPLY_FRACTION,
+        );
+
+        self.bench
+            .process_transaction(&[create_realm_ix], None)
+            .await
+            .unwrap();
+
+        let account = self.get_realm_account(&realm_address).await;
+
+        let realm_config_address = get_realm_config_address(&self.program_id, &realm_address);
+        let realm_config_cookie = RealmConfigCookie {
+            address: realm_config_address,
+            account: self.get_realm_config_account(&realm_config_address).await,
+        };
+
+        RealmCookie {
+            address: realm_address,
+            account,
+
+            community_mint_authority: community_token_mint_authority,
+            community_token_holding_account: get_governing_token_holding_address(
+                &self.program_id,
+                &realm_address,
+                &community_token_mint_keypair.pubkey(),
+            ),
+
+            council_token_holding_account: None,
+            council_mint_authority: None,
+            realm_authority: Some(realm_authority),
+            realm_config: realm_config_cookie,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn create_token_2022_mint(
+        &mut self,
+        mint_keypair: &Keypair,
+        mint_authority: &Pubkey,
+        mint_extension_types: &[ExtensionType],
+    ) {
+        let mint_len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(
+            mint_extension_types,
+        )
+        .unwrap();
+
+        let mut instructions = vec![system_instruction::create_account(
+            &self.bench.payer.pubkey(),
+            &mint_keypair.pubkey(),
+            self.bench.rent.minimum_balance(mint_len),
+            mint_len as u64,
+            &spl_token_2022::id(),
+        )];
+
+        for extension_type in mint_extension_types {
+            instructions.push(match extension_type {
+                ExtensionType::TransferFeeConfig => initialize_transfer_fee_config(
+                    &spl_token_2022::id(),
+                    &mint_keypair.pubkey(),
+                    None,
+                    None,
+                    TOKEN_2022_TRANSFER_FEE_BASIS_POINTS,
+                    u64::MAX,
+                )
+                .unwrap(),
+                ExtensionType::NonTransferable => {
+                    initialize_non_transferable_mint(&spl_token_2022::id(), &mint_keypair.pubkey())
+                        .unwrap()
+                }
+                _ => panic!("Unsupported mint extension {:?}", extension_type),
+            });
+        }
+
+        instructions.push(
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::id(),
+                &mint_keypair.pubkey(),
+                mint_authority,
+                None,
+                0,
+            )
+            .unwrap(),
+        );
+
+        self.bench
+            .process_transaction(&instructions, Some(&[mint_keypair]))
+            .await
+            .unwrap();
+    }
+
+    // Creates Token-2022 account of the given mint which holds the given amount
+    #[allow(dead_code)]
+    pub async fn with_token_2022_account(
+        &mut self,
+        mint: &Pubkey,
+        mint_authority: &Keypair,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let token_account_keypair = Keypair::new();
+
+        let mint_data = self.bench.get_account(mint).await.unwrap().data;
+        let mint_extension_types =
+            StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)
+                .unwrap()
+                .get_extension_types()
+                .unwrap();
+        let token_account_len =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(
+                &ExtensionType::get_required_init_account_extensions(&mint_extension_types),
+            )
+            .unwrap();
+
+        let instructions = [
+            system_instruction::create_account(
+                &self.bench.payer.pubkey(),
+                &token_account_keypair.pubkey(),
+                self.bench.rent.minimum_balance(token_account_len),
+                token_account_len as u64,
+                &spl_token_2022::id(),
+            ),
+            spl_token_2022::instruction::initialize_account3(
+                &spl_token_2022::id(),
+                &token_account_keypair.pubkey(),
+                mint,
+                owner,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::mint_to(
+                &spl_token_2022::id(),
+                mint,
+                &token_account_keypair.pubkey(),
+                &mint_authority.pubkey(),
+                &[],
+                amount,
+            )
+            .unwrap(),
+        ];
+
+        self.bench
+            .process_transaction(
+                &instructions,
+                Some(&[&token_account_keypair, mint_authority]),
+            )
+            .await
+            .unwrap();
+
+        token_account_keypair.pubkey()
+    }
+
+    #[allow(dead_code)]
+    pub async fn deposit_token_2022_community_tokens(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        token_source: &Pubkey,
+        token_owner: &Keypair,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        let deposit_governing_tokens_ix = deposit_governing_tokens(
+            &self.program_id,
+            &realm_cookie.address,
+            token_source,
+            &token_owner.pubkey(),
+            &token_owner.pubkey(),
+            &self.bench.payer.pubkey(),
+            amount,
+            &realm_cookie.account.community_mint,
+            &spl_token_2022::id(),
+        );
+
+        self.bench
+            .process_transaction(&[deposit_governing_tokens_ix], Some(&[token_owner]))
+            .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn withdraw_token_2022_community_tokens(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        token_destination: &Pubkey,
+        token_owner: &Keypair,
+    ) -> Result<(), ProgramError> {
+        let withdraw_governing_tokens_ix = withdraw_governing_tokens(
+            &self.program_id,
+            &realm_cookie.address,
+            token_destination,
+            &token_owner.pubkey(),
+            &realm_cookie.account.community_mint,
+            &spl_token_2022::id(),
+        );
+
+        self.bench
+            .process_transaction(&[withdraw_governing_tokens_ix], Some(&[token_owner]))
+            .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_community_restricted_extensions(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        allowed: bool,
+    ) -> Result<(), ProgramError> {
+        let args = SetRealmConfigItemArgs::GoverningTokenRestrictedExtensions {
+            governing_token_mint: realm_cookie.account.community_mint,
+            allowed,
+        };
+
+        self.set_realm_config_item(realm_cookie, args).await
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_governing_token_deposit_tracking(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        governing_token_mint: &Pubkey,
+        enabled: bool,
+        deposits: u64,
+    ) -> Result<(), ProgramError> {
+        let args = SetRealmConfigItemArgs::GoverningTokenDepositTracking {
+            governing_token_mint: *governing_token_mint,
+            enabled,
+            deposits,
+        };
+
+        self.set_realm_config_item(realm_cookie, args).await
+    }
+
     // Creates TokenOwner which owns 100 community tokens and deposits them into the
     // given Realm
     #[allow(dead_code)]
@@ -515,6 +833,25 @@ impl GovernanceProgramTest {
         &mut self,
         realm_cookie: &RealmCookie,
     ) -> Result<TokenOwnerRecordCookie, ProgramError> {
+        // Membership tokens can only be deposited by minting them
+        if realm_cookie
+            .realm_config
+            .account
+            .community_token_config
+            .token_type
+            == GoverningTokenType::Membership
+        {
+            return self
+                .with_initial_governing_token_deposit_using_mint(
+                    &realm_cookie.address,
+                    &realm_cookie.account.community_mint,
+                    &realm_cookie.community_mint_authority,
+                    100,
+                    None,
+                )
+                .await;
+        }
+
         self.with_initial_governing_token_deposit(
             &realm_cookie.address,
             &realm_cookie.account.community_mint,
@@ -732,6 +1069,25 @@ impl GovernanceProgramTest {
         &mut self,
         realm_cookie: &RealmCookie,
     ) -> Result<TokenOwnerRecordCookie, ProgramError> {
+        // Membership tokens can only be deposited by minting them
+        if realm_cookie
+            .realm_config
+            .account
+            .council_token_config
+            .token_type
+            == GoverningTokenType::Membership
+        {
+            return self
+                .with_initial_governing_token_deposit_using_mint(
+                    &realm_cookie.address,
+                    &realm_cookie.account.config.council_mint.unwrap(),
+                    realm_cookie.council_mint_authority.as_ref().unwrap(),
+                    100,
+                    None,
+                )
+                .await;
+        }
+
         self.with_initial_governing_token_deposit(
             &realm_cookie.address,
             &realm_cookie.account.config.council_mint.unwrap(),
@@ -793,6 +1149,7 @@ impl GovernanceProgramTest {
             &self.bench.payer.pubkey(),
             amount,
             governing_mint,
+            &spl_token::id(),
         );
 
         self.bench
@@ -858,6 +1215,7 @@ impl GovernanceProgramTest {
             &self.bench.payer.pubkey(),
             amount,
             governing_mint,
+            &spl_token::id(),
         );
 
         self.bench
@@ -977,6 +1335,7 @@ impl GovernanceProgramTest {
             &self.bench.payer.pubkey(),
             amount,
             governing_token_mint,
+            &spl_token::id(),
         );
 
         self.bench
@@ -1134,6 +1493,47 @@ impl GovernanceProgramTest {
             .await
     }
 
+    #[allow(dead_code)]
+    pub async fn cancel_pending_realm_authority(
+        &mut self,
+        realm_cookie: &RealmCookie,
+    ) -> Result<(), ProgramError> {
+        let cancel_pending_ix = set_realm_authority(
+            &self.program_id,
+            &realm_cookie.address,
+            &realm_cookie.realm_authority.as_ref().unwrap().pubkey(),
+            None,
+            SetRealmAuthorityAction::CancelPending,
+        );
+
+        self.bench
+            .process_transaction(
+                &[cancel_pending_ix],
+                Some(&[realm_cookie.realm_authority.as_ref().unwrap()]),
+            )
+            .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn accept_realm_authority(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        pending_realm_authority: &Keypair,
+    ) -> Result<(), ProgramError> {
+        let accept_realm_authority_ix = accept_realm_authority(
+            &self.program_id,
+            &realm_cookie.address,
+            &pending_realm_authority.pubkey(),
+        );
+
+        self.bench
+            .process_transaction(
+                &[accept_realm_authority_ix],
+                Some(&[pending_realm_authority]),
+            )
+            .await
+    }
+
     #[allow(dead_code)]
     pub async fn set_realm_config(
         &mut self,
@@ -1190,7 +1590,15 @@ impl GovernanceProgramTest {
             account: RealmConfigAccount {
                 account_type: GovernanceAccountType::RealmConfig,
                 realm: realm_cookie.address,
-                reserved: Reserved110::default(),
+                community_proposal_creation: ProposalCreationAuthority::default(),
+                snapshot_max_voter_weight: false,
+                community_token_deposits: realm_cookie
+                    .realm_config
+                    .account
+                    .community_token_deposits,
+                council_token_deposits: realm_cookie.realm_config.account.council_token_deposits,
+                governing_token_program: realm_cookie.realm_config.account.governing_token_program,
+                reserved: Reserved49::default(),
                 community_token_config: GoverningTokenConfig {
                     voter_weight_addin: realm_setup_args
                         .community_token_config_args
@@ -1202,7 +1610,8 @@ impl GovernanceProgramTest {
                         .community_token_config_args
                         .token_type
                         .clone(),
-                    reserved: [0; 4],
+                    allow_restricted_extensions: false,
+                    reserved: [0; 3],
                     lock_authorities: vec![],
                 },
                 council_token_config: GoverningTokenConfig {
@@ -1216,7 +1625,8 @@ impl GovernanceProgramTest {
                         .council_token_config_args
                         .token_type
                         .clone(),
-                    reserved: [0; 4],
+                    allow_restricted_extensions: false,
+                    reserved: [0; 3],
                     lock_authorities: vec![],
                 },
             },
@@ -1271,6 +1681,7 @@ impl GovernanceProgramTest {
             &token_owner_record_cookie.token_source,
             &governing_token_owner.pubkey(),
             governing_token_mint,
+            &spl_token::id(),
         );
 
         self.bench
@@ -1430,6 +1841,78 @@ impl GovernanceProgramTest {
         }
     }
 
+    /// Creates a lending market owned by the given governance and a reserve in
+    /// the market, so proposals of the governance can modify the reserve
+    #[allow(dead_code)]
+    pub async fn with_governed_lending_reserve(
+        &mut self,
+        governance_cookie: &GovernanceCookie,
+    ) -> GovernedLendingReserveCookie {
+        let lending_market_address = Pubkey::new_unique();
+        let (_, bump_seed) = Pubkey::find_program_address(
+            &[lending_market_address.as_ref()],
+            &spl_token_lending::id(),
+        );
+
+        let lending_market = LendingMarket::new(InitLendingMarketParams {
+            bump_seed,
+            owner: governance_cookie.address,
+            quote_currency: [0u8; 32],
+            token_program_id: spl_token::id(),
+            oracle_program_id: Pubkey::new_unique(),
+        });
+
+        // Fund the accounts from the payer before their data is overridden, so the
+        // rent lamports are accounted for in the bank capitalization when the
+        // clock is advanced
+        self.bench
+            .transfer_sol(
+                &lending_market_address,
+                self.bench.rent.minimum_balance(LendingMarket::LEN),
+            )
+            .await;
+
+        self.bench.set_packed_account(
+            &spl_token_lending::id(),
+            &lending_market_address,
+            lending_market,
+        );
+
+        let reserve_address = Pubkey::new_unique();
+        let config = ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 50,
+            collateral_weight: 100,
+            liquidation_threshold: 55,
+            max_borrow_rate: 30,
+            liquidation_close_factor: 50,
+            ..ReserveConfig::default()
+        };
+
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            lending_market: lending_market_address,
+            config,
+            ..Reserve::default()
+        };
+
+        self.bench
+            .transfer_sol(
+                &reserve_address,
+                self.bench.rent.minimum_balance(Reserve::LEN),
+            )
+            .await;
+
+        self.bench
+            .set_packed_account(&spl_token_lending::id(), &reserve_address, reserve);
+
+        GovernedLendingReserveCookie {
+            address: reserve_address,
+            lending_market: lending_market_address,
+            config,
+        }
+    }
+
     pub fn get_default_governance_config(&mut self) -> GovernanceConfig {
         GovernanceConfig {
             community_vote_threshold: VoteThreshold::YesVotePercentage(60),
@@ -1969,6 +2452,82 @@ impl GovernanceProgramTest {
         Ok(proposal_cookie)
     }
 
+    #[allow(dead_code)]
+    pub async fn with_proposal_signed_off_at_creation(
+        &mut self,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        governance_cookie: &mut GovernanceCookie,
+    ) -> Result<ProposalCookie, ProgramError> {
+        let program_id = self.program_id;
+        let governance = governance_cookie.address;
+        let signatory = token_owner_record_cookie.account.governing_token_owner;
+
+        let mut proposal_cookie = self
+            .with_proposal_using_instruction(token_owner_record_cookie, governance_cookie, |i| {
+                let mut instruction = borsh::from_slice::<GovernanceInstruction>(&i.data).unwrap();
+                if let GovernanceInstruction::CreateProposal { sign_off, .. } = &mut instruction {
+                    *sign_off = true;
+                }
+                i.data = borsh::to_vec(&instruction).unwrap();
+
+                let proposal = i.accounts[1].pubkey;
+                i.accounts.push(AccountMeta::new(
+                    get_signatory_record_address(&program_id, &proposal, &signatory),
+                    false,
+                ));
+                i.accounts.push(AccountMeta::new_readonly(
+                    get_required_signatory_address(&program_id, &governance, &signatory),
+                    false,
+                ));
+            })
+            .await?;
+
+        let clock = self.bench.get_clock().await;
+
+        proposal_cookie.account.state = ProposalState::Voting;
+        proposal_cookie.account.signatories_count = 1;
+        proposal_cookie.account.signatories_signed_off_count = 1;
+        proposal_cookie.account.signing_off_at = Some(clock.unix_timestamp);
+        proposal_cookie.account.voting_at = Some(clock.unix_timestamp);
+        proposal_cookie.account.voting_at_slot = Some(clock.slot);
+
+        Ok(proposal_cookie)
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_proposal_with_category(
+        &mut self,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        governance_cookie: &mut GovernanceCookie,
+        category: [u8; 8],
+    ) -> Result<ProposalCookie, ProgramError> {
+        let program_id = self.program_id;
+
+        let mut proposal_cookie = self
+            .with_proposal_using_instruction(token_owner_record_cookie, governance_cookie, |i| {
+                let mut instruction = borsh::from_slice::<GovernanceInstruction>(&i.data).unwrap();
+                if let GovernanceInstruction::CreateProposal {
+                    category: instruction_category,
+                    ..
+                } = &mut instruction
+                {
+                    *instruction_category = Some(category);
+                }
+                i.data = borsh::to_vec(&instruction).unwrap();
+
+                let proposal = i.accounts[1].pubkey;
+                i.accounts.push(AccountMeta::new(
+                    get_proposal_category_record_address(&program_id, &proposal),
+                    false,
+                ));
+            })
+            .await?;
+
+        proposal_cookie.account.category = category;
+
+        Ok(proposal_cookie)
+    }
+
     #[allow(dead_code)]
     pub async fn with_proposal_using_instruction<F: Fn(&mut Instruction)>(
         &mut self,
@@ -2030,6 +2589,9 @@ impl GovernanceProgramTest {
             options.clone(),
             use_deny_option,
             &proposal_seed,
+            None,
+            None,
+            None,
         );
 
         instruction_override(&mut create_proposal_transaction);
@@ -2090,8 +2652,11 @@ impl GovernanceProgramTest {
             max_vote_weight: None,
             max_voting_time: None,
             vote_threshold: None,
+            hold_up_time: 0,
+            category: [0; 8],
+            max_voting_weight_snapshot: 0,
 
-            reserved: [0; 64],
+            reserved: [0; 44],
 
             reserved1: 0,
         };
@@ -2210,6 +2775,8 @@ impl GovernanceProgramTest {
             &proposal_cookie.address,
             &token_owner_record_cookie.account.governing_token_owner,
             Some(&token_owner_record_cookie.address),
+            &proposal_cookie.account.governing_token_mint,
+            None,
         );
 
         instruction_override(&mut sign_off_proposal_ix);
@@ -2254,6 +2821,8 @@ impl GovernanceProgramTest {
             &proposal_cookie.address,
             &signatory_record_cookie.signatory.as_ref().unwrap().pubkey(),
             None,
+            &proposal_cookie.account.governing_token_mint,
+            None,
         );
 
         instruction_override(&mut sign_off_proposal_ix);
@@ -2498,6 +3067,61 @@ impl GovernanceProgramTest {
         Ok(vote_record_cookie)
     }
 
+    #[allow(dead_code)]
+    pub async fn change_yes_no_vote(
+        &mut self,
+        proposal_cookie: &ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        yes_no_vote: YesNoVote,
+    ) -> Result<(), ProgramError> {
+        let vote = match yes_no_vote {
+            YesNoVote::Yes => Vote::Approve(vec![VoteChoice {
+                rank: 0,
+                weight_percentage: 100,
+            }]),
+            YesNoVote::No => Vote::Deny,
+        };
+
+        self.change_vote(proposal_cookie, token_owner_record_cookie, vote)
+            .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn change_vote(
+        &mut self,
+        proposal_cookie: &ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        vote: Vote,
+    ) -> Result<(), ProgramError> {
+        let max_voter_weight_record = token_owner_record_cookie
+            .max_voter_weight_record
+            .as_ref()
+            .map(|max_voter_weight_record| max_voter_weight_record.address);
+
+        let change_vote_ix = change_vote(
+            &self.program_id,
+            &token_owner_record_cookie.account.realm,
+            &proposal_cookie.account.governance,
+            &proposal_cookie.address,
+            &proposal_cookie.account.token_owner_record,
+            &token_owner_record_cookie.address,
+            &token_owner_record_cookie.token_owner.pubkey(),
+            &token_owner_record_cookie.account.governing_token_mint,
+            &self.bench.payer.pubkey(),
+            max_voter_weight_record,
+            vote,
+        );
+
+        self.bench
+            .process_transaction(
+                &[change_vote_ix],
+                Some(&[&token_owner_record_cookie.token_owner]),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn with_set_governance_config_transaction(
         &mut self,
@@ -2531,6 +3155,30 @@ impl GovernanceProgramTest {
         option_index: u8,
         index: Option<u16>,
         hold_up_time: Option<u32>,
+    ) -> Result<ProposalTransactionCookie, ProgramError> {
+        self.with_mint_tokens_transaction_depending_on(
+            governed_mint_cookie,
+            proposal_cookie,
+            token_owner_record_cookie,
+            option_index,
+            index,
+            hold_up_time,
+            None,
+        )
+        .await
+    }
+
+    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn with_mint_tokens_transaction_depending_on(
+        &mut self,
+        governed_mint_cookie: &GovernedMintCookie,
+        proposal_cookie: &mut ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        option_index: u8,
+        index: Option<u16>,
+        hold_up_time: Option<u32>,
+        depends_on_option_index: Option<u8>,
     ) -> Result<ProposalTransactionCookie, ProgramError> {
         let token_account_keypair = Keypair::new();
         self.bench
@@ -2551,13 +3199,48 @@ impl GovernanceProgramTest {
         )
         .unwrap();
 
-        self.with_proposal_transaction(
+        self.with_proposal_transaction_depending_on(
             proposal_cookie,
             token_owner_record_cookie,
             option_index,
             index,
             &mut instruction,
             hold_up_time,
+            depends_on_option_index,
+        )
+        .await
+    }
+
+    /// Inserts a transaction which modifies the reserve config with the
+    /// governance signing as the lending market owner
+    #[allow(dead_code)]
+    pub async fn with_modify_reserve_config_transaction(
+        &mut self,
+        governed_lending_reserve_cookie: &GovernedLendingReserveCookie,
+        proposal_cookie: &mut ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        config: ReserveConfig,
+    ) -> Result<ProposalTransactionCookie, ProgramError> {
+        let mut instruction = modify_reserve_config(
+            spl_token_lending::id(),
+            config,
+            governed_lending_reserve_cookie.address,
+            governed_lending_reserve_cookie.lending_market,
+            proposal_cookie.account.governance,
+        );
+
+        assert_instructions_program_id(
+            &[InstructionData::from(instruction.clone())],
+            &spl_token_lending::id(),
+        )?;
+
+        self.with_proposal_transaction(
+            proposal_cookie,
+            token_owner_record_cookie,
+            0,
+            None,
+            &mut instruction,
+            None,
         )
         .await
     }
@@ -2741,10 +3424,35 @@ impl GovernanceProgramTest {
         index: Option<u16>,
         instruction: &mut Instruction,
         hold_up_time: Option<u32>,
+    ) -> Result<ProposalTransactionCookie, ProgramError> {
+        self.with_proposal_transaction_depending_on(
+            proposal_cookie,
+            token_owner_record_cookie,
+            option_index,
+            index,
+            instruction,
+            hold_up_time,
+            None,
+        )
+        .await
+    }
+
+    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn with_proposal_transaction_depending_on(
+        &mut self,
+        proposal_cookie: &mut ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        option_index: u8,
+        index: Option<u16>,
+        instruction: &mut Instruction,
+        hold_up_time: Option<u32>,
+        depends_on_option_index: Option<u8>,
     ) -> Result<ProposalTransactionCookie, ProgramError> {
         let hold_up_time = hold_up_time.unwrap_or(15);
 
-        let instruction_data: InstructionData = instruction.clone().into();
+        let proposal_transaction_instructions: Vec<InstructionData> =
+            vec![instruction.clone().into()];
         let yes_option = &mut proposal_cookie.account.options[0];
 
         let transaction_index = index.unwrap_or(yes_option.transactions_next_index);
@@ -2761,7 +3469,8 @@ impl GovernanceProgramTest {
             option_index,
             transaction_index,
             hold_up_time,
-            vec![instruction_data.clone()],
+            proposal_transaction_instructions.clone(),
+            depends_on_option_index,
         );
 
         self.bench
@@ -2783,11 +3492,14 @@ impl GovernanceProgramTest {
             option_index,
             transaction_index,
             hold_up_time,
-            instructions: vec![instruction_data],
+            instructions: proposal_transaction_instructions.clone(),
             executed_at: None,
             execution_status: TransactionExecutionStatus::None,
             proposal: proposal_cookie.address,
-            reserved_v2: [0; 8],
+            depends_on_option_index,
+            return_data: None,
+            estimated_compute_units: estimate_compute_units(&proposal_transaction_instructions),
+            reserved_v2: [0; 1],
         };
 
         instruction.accounts = instruction
@@ -2879,6 +3591,8 @@ impl GovernanceProgramTest {
             &proposal_cookie.address,
             &signatory.pubkey(),
             None,
+            &proposal_cookie.account.governing_token_mint,
+            None,
         );
 
         self.bench
@@ -3092,6 +3806,16 @@ impl GovernanceProgramTest {
             .await
     }
 
+    #[allow(dead_code)]
+    pub async fn get_proposal_category_record_account(
+        &mut self,
+        address: &Pubkey,
+    ) -> ProposalCategoryRecord {
+        self.bench
+            .get_borsh_account::<ProposalCategoryRecord>(address)
+            .await
+    }
+
     #[allow(dead_code)]
     pub async fn get_proposal_deposit_account(&mut self, address: &Pubkey) -> ProposalDeposit {
         self.bench
@@ -3240,11 +3964,27 @@ impl GovernanceProgramTest {
         self.get_packed_account(address).await
     }
 
+    #[allow(dead_code)]
+    pub async fn get_token_2022_account(
+        &mut self,
+        address: &Pubkey,
+    ) -> spl_token_2022::state::Account {
+        let account_data = self.bench.get_account(address).await.unwrap().data;
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account_data)
+            .unwrap()
+            .base
+    }
+
     #[allow(dead_code)]
     pub async fn get_mint_account(&mut self, address: &Pubkey) -> spl_token::state::Mint {
         self.get_packed_account(address).await
     }
 
+    #[allow(dead_code)]
+    pub async fn get_lending_reserve_account(&mut self, address: &Pubkey) -> Reserve {
+        self.get_packed_account(address).await
+    }
+
     /// ----------- VoterWeight Addin -----------------------------
 
     #[allow(dead_code)]
@@ -3385,6 +4125,36 @@ impl GovernanceProgramTest {
         Ok(())
     }
 
+    #[allow(dead_code)]
+    pub async fn set_proposal_hold_up_time(
+        &mut self,
+        proposal_cookie: &mut ProposalCookie,
+        token_owner_record_cookie: &TokenOwnerRecordCookie,
+        hold_up_time: u32,
+    ) -> Result<(), ProgramError> {
+        let governance_authority = token_owner_record_cookie.get_governance_authority();
+
+        let set_proposal_hold_up_time_ix = set_proposal_hold_up_time(
+            &self.program_id,
+            &proposal_cookie.account.governance,
+            &proposal_cookie.address,
+            &proposal_cookie.account.token_owner_record,
+            &governance_authority.pubkey(),
+            hold_up_time,
+        );
+
+        self.bench
+            .process_transaction(
+                &[set_proposal_hold_up_time_ix],
+                Some(&[governance_authority]),
+            )
+            .await?;
+
+        proposal_cookie.account.hold_up_time = hold_up_time;
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn with_token_owner_record_lock(
         &mut self,
@@ -3588,4 +4358,26 @@ impl GovernanceProgramTest {
             authority: token_owner_record_lock_authority,
         })
     }
+
+    #[allow(dead_code)]
+    pub async fn with_community_proposal_creation(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        authority: ProposalCreationAuthority,
+    ) -> Result<(), ProgramError> {
+        let args = SetRealmConfigItemArgs::CommunityProposalCreation { authority };
+
+        self.set_realm_config_item(realm_cookie, args).await
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_max_voter_weight_snapshot(
+        &mut self,
+        realm_cookie: &RealmCookie,
+        enabled: bool,
+    ) -> Result<(), ProgramError> {
+        let args = SetRealmConfigItemArgs::MaxVoterWeightSnapshot { enabled };
+
+        self.set_realm_config_item(realm_cookie, args).await
+    }
 }
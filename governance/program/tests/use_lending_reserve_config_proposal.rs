@@ -0,0 +1,206 @@
+#![cfg(feature = "test-sbf")]
+
+mod program_test;
+
+use {
+    program_test::*,
+    solana_program::program_error::ProgramError,
+    solana_program_test::tokio,
+    spl_governance::state::enums::{ProposalState, TransactionExecutionStatus},
+    spl_token_lending::{error::LendingError, state::ReserveConfig},
+};
+
+#[tokio::test]
+async fn test_execute_modify_reserve_config_transaction() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let governed_lending_reserve_cookie = governance_test
+        .with_governed_lending_reserve(&governance_cookie)
+        .await;
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    let signatory_record_cookie = governance_test
+        .with_signatory(
+            &proposal_cookie,
+            &governance_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let new_config = ReserveConfig {
+        loan_to_value_ratio: 60,
+        liquidation_threshold: 65,
+        ..governed_lending_reserve_cookie.config
+    };
+
+    let proposal_transaction_cookie = governance_test
+        .with_modify_reserve_config_transaction(
+            &governed_lending_reserve_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            new_config,
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .sign_off_proposal(&proposal_cookie, &signatory_record_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Advance timestamp past hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_transaction_cookie.account.hold_up_time as u64)
+        .await;
+
+    // Act
+    governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(ProposalState::Completed, proposal_account.state);
+
+    let proposal_transaction_account = governance_test
+        .get_proposal_transaction_account(&proposal_transaction_cookie.address)
+        .await;
+
+    assert_eq!(
+        TransactionExecutionStatus::Success,
+        proposal_transaction_account.execution_status
+    );
+
+    let reserve_account = governance_test
+        .get_lending_reserve_account(&governed_lending_reserve_cookie.address)
+        .await;
+
+    assert_eq!(new_config, reserve_account.config);
+}
+
+#[tokio::test]
+async fn test_execute_modify_reserve_config_transaction_with_invalid_config_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let governed_lending_reserve_cookie = governance_test
+        .with_governed_lending_reserve(&governance_cookie)
+        .await;
+
+    let mut proposal_cookie = governance_test
+        .with_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    let signatory_record_cookie = governance_test
+        .with_signatory(
+            &proposal_cookie,
+            &governance_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    // Liquidation threshold must be above the loan to value ratio
+    let invalid_config = ReserveConfig {
+        loan_to_value_ratio: 60,
+        liquidation_threshold: 60,
+        ..governed_lending_reserve_cookie.config
+    };
+
+    let proposal_transaction_cookie = governance_test
+        .with_modify_reserve_config_transaction(
+            &governed_lending_reserve_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            invalid_config,
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .sign_off_proposal(&proposal_cookie, &signatory_record_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Advance timestamp past hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_transaction_cookie.account.hold_up_time as u64)
+        .await;
+
+    // Act
+    let err = governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        ProgramError::Custom(LendingError::InvalidLiquidationThreshold as u32)
+    );
+
+    let reserve_account = governance_test
+        .get_lending_reserve_account(&governed_lending_reserve_cookie.address)
+        .await;
+
+    assert_eq!(
+        governed_lending_reserve_cookie.config,
+        reserve_account.config
+    );
+}
@@ -1785,3 +1785,235 @@ async fn test_vote_multi_weighted_choice_proposal_executable_with_full_deny() {
         GovernanceError::InvalidStateCannotExecuteTransaction.into()
     );
 }
+
+#[tokio::test]
+async fn test_execute_proposal_transaction_with_option_dependency() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_mint_cookie = governance_test.with_governed_mint().await;
+
+    // 100 tokens
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_config = governance_test.get_default_governance_config();
+    governance_config.community_vote_threshold = VoteThreshold::YesVotePercentage(30);
+
+    let mut governance_cookie = governance_test
+        .with_mint_governance_using_config(
+            &realm_cookie,
+            &governed_mint_cookie,
+            &token_owner_record_cookie,
+            &governance_config,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_multi_option_proposal(
+            &token_owner_record_cookie,
+            &mut governance_cookie,
+            vec!["option 1".to_string(), "option 2".to_string()],
+            true,
+            VoteType::MultiChoice {
+                choice_type: MultiChoiceType::FullWeight,
+                min_voter_options: 1,
+                max_winning_options: 2,
+                max_voter_options: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+    let proposal_transaction_cookie1 = governance_test
+        .with_mint_tokens_transaction(
+            &governed_mint_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            Some(0),
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Option 2 transaction can only be executed once option 1 is executed
+    let proposal_transaction_cookie2 = governance_test
+        .with_mint_tokens_transaction_depending_on(
+            &governed_mint_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            1,
+            Some(0),
+            None,
+            Some(0),
+        )
+        .await
+        .unwrap();
+
+    let signatory_record_cookie = governance_test
+        .with_signatory(
+            &proposal_cookie,
+            &governance_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    governance_test
+        .sign_off_proposal(&proposal_cookie, &signatory_record_cookie)
+        .await
+        .unwrap();
+
+    let vote = Vote::Approve(vec![
+        VoteChoice {
+            rank: 0,
+            weight_percentage: 100,
+        },
+        VoteChoice {
+            rank: 0,
+            weight_percentage: 100,
+        },
+    ]);
+
+    governance_test
+        .with_cast_vote(&proposal_cookie, &token_owner_record_cookie, vote)
+        .await
+        .unwrap();
+
+    // Advance timestamp past voting_base_time
+    governance_test
+        .advance_clock_by_min_timespan(governance_cookie.account.config.voting_base_time as u64)
+        .await;
+
+    governance_test
+        .finalize_vote(&realm_cookie, &proposal_cookie, None)
+        .await
+        .unwrap();
+
+    // Advance timestamp past hold_up_time
+    governance_test
+        .advance_clock_by_min_timespan(proposal_transaction_cookie1.account.hold_up_time as u64)
+        .await;
+
+    let dependency_err = governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie2)
+        .await
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        dependency_err,
+        GovernanceError::TransactionDependencyNotExecuted.into()
+    );
+
+    governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie1)
+        .await
+        .unwrap();
+
+    // Act
+    governance_test
+        .execute_proposal_transaction(&proposal_cookie, &proposal_transaction_cookie2)
+        .await
+        .unwrap();
+
+    // Assert
+    let proposal_account = governance_test
+        .get_proposal_account(&proposal_cookie.address)
+        .await;
+
+    assert_eq!(ProposalState::Completed, proposal_account.state);
+
+    let proposal_transaction_account = governance_test
+        .get_proposal_transaction_account(&proposal_transaction_cookie2.address)
+        .await;
+
+    assert_eq!(
+        Some(0),
+        proposal_transaction_account.depends_on_option_index
+    );
+    assert!(proposal_transaction_account.executed_at.is_some());
+}
+
+#[tokio::test]
+async fn test_insert_transaction_with_invalid_option_dependency_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_new().await;
+
+    let realm_cookie = governance_test.with_realm().await;
+    let governed_mint_cookie = governance_test.with_governed_mint().await;
+
+    let token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_mint_governance(
+            &realm_cookie,
+            &governed_mint_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let mut proposal_cookie = governance_test
+        .with_multi_option_proposal(
+            &token_owner_record_cookie,
+            &mut governance_cookie,
+            vec!["option 1".to_string(), "option 2".to_string()],
+            true,
+            VoteType::MultiChoice {
+                choice_type: MultiChoiceType::FullWeight,
+                min_voter_options: 1,
+                max_winning_options: 2,
+                max_voter_options: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let forward_dependency_err = governance_test
+        .with_mint_tokens_transaction_depending_on(
+            &governed_mint_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            0,
+            Some(0),
+            None,
+            Some(1),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    let self_dependency_err = governance_test
+        .with_mint_tokens_transaction_depending_on(
+            &governed_mint_cookie,
+            &mut proposal_cookie,
+            &token_owner_record_cookie,
+            1,
+            Some(0),
+            None,
+            Some(1),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        forward_dependency_err,
+        GovernanceError::InvalidTransactionDependency.into()
+    );
+    assert_eq!(
+        self_dependency_err,
+        GovernanceError::InvalidTransactionDependency.into()
+    );
+}
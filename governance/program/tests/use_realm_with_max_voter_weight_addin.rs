@@ -6,6 +6,7 @@ mod program_test;
 
 use {
     program_test::{args::*, *},
+    solana_program::pubkey::Pubkey,
     spl_governance::{error::GovernanceError, state::enums::ProposalState},
 };
 
@@ -411,3 +412,180 @@ async fn test_cast_vote_with_max_voter_weight_addin_and_expired_record_error() {
     // Assert
     assert_eq!(err, GovernanceError::MaxVoterWeightRecordExpired.into());
 }
+
+#[tokio::test]
+async fn test_finalize_vote_with_max_voter_weight_addin_and_expired_record_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_max_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_MAX_VOTER_WEIGHT)
+        .await;
+
+    // TokenOwnerRecord with voting power of 100
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    // Bump MaxVoterWeight to 200
+    governance_test
+        .with_max_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Lower max to 120 with a record which expires before the vote is finalized
+    let max_voter_weight_record_cookie = governance_test
+        .with_max_voter_weight_addin_record_impl(&mut token_owner_record_cookie, 120, Some(1))
+        .await
+        .unwrap();
+
+    // Advance timestamp past max_voting_time
+    governance_test
+        .advance_clock_past_voting_time(&governance_cookie)
+        .await;
+
+    // Act
+    let err = governance_test
+        .finalize_vote(
+            &realm_cookie,
+            &proposal_cookie,
+            Some(max_voter_weight_record_cookie),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(err, GovernanceError::MaxVoterWeightRecordExpired.into());
+}
+
+#[tokio::test]
+async fn test_cast_vote_with_max_voter_weight_addin_and_record_for_other_realm_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_max_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_MAX_VOTER_WEIGHT)
+        .await;
+
+    // TokenOwnerRecord with voting power of 100
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Setup MaxVoterWeightRecord for another realm
+    let realm = token_owner_record_cookie.account.realm;
+    token_owner_record_cookie.account.realm = Pubkey::new_unique();
+
+    governance_test
+        .with_max_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    token_owner_record_cookie.account.realm = realm;
+
+    // Act
+    let err = governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::InvalidMaxVoterWeightRecordForRealm.into()
+    );
+}
+
+#[tokio::test]
+async fn test_cast_vote_with_max_voter_weight_addin_and_record_for_other_mint_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_max_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_MAX_VOTER_WEIGHT)
+        .await;
+
+    // TokenOwnerRecord with voting power of 100
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_deposit(&realm_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    // Setup MaxVoterWeightRecord for another governing token mint
+    let governing_token_mint = token_owner_record_cookie.account.governing_token_mint;
+    token_owner_record_cookie.account.governing_token_mint = Pubkey::new_unique();
+
+    governance_test
+        .with_max_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    token_owner_record_cookie.account.governing_token_mint = governing_token_mint;
+
+    // Act
+    let err = governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .err()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        err,
+        GovernanceError::InvalidMaxVoterWeightRecordForGoverningTokenMint.into()
+    );
+}
@@ -444,6 +444,187 @@ async fn test_cast_vote_with_voter_weight_action_error() {
     assert_eq!(err, GovernanceError::VoterWeightRecordInvalidAction.into());
 }
 
+#[tokio::test]
+async fn test_cast_vote_with_voter_weight_record_for_proposal() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_VOTER_WEIGHT)
+        .await;
+
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_owner_record(&realm_cookie)
+        .await;
+
+    governance_test
+        .with_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    governance_test.advance_clock().await;
+    let clock = governance_test.bench.get_clock().await;
+
+    // Revised in the current slot for casting a vote on the proposal
+    governance_test
+        .with_voter_weight_addin_record_impl(
+            &mut token_owner_record_cookie,
+            150,
+            Some(clock.slot),
+            Some(VoterWeightAction::CastVote),
+            Some(proposal_cookie.address),
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let vote_record_cookie = governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .unwrap();
+
+    // Assert
+    let vote_record_account = governance_test
+        .get_vote_record_account(&vote_record_cookie.address)
+        .await;
+
+    assert_eq!(150, vote_record_account.voter_weight);
+}
+
+#[tokio::test]
+async fn test_cast_vote_with_voter_weight_expiry_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_VOTER_WEIGHT)
+        .await;
+
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_owner_record(&realm_cookie)
+        .await;
+
+    governance_test
+        .with_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    governance_test.advance_clock().await;
+    let clock = governance_test.bench.get_clock().await;
+
+    governance_test
+        .with_voter_weight_addin_record_impl(
+            &mut token_owner_record_cookie,
+            150,
+            Some(clock.slot - 1), // Past slot
+            Some(VoterWeightAction::CastVote),
+            Some(proposal_cookie.address),
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .err()
+        .unwrap();
+
+    //  Assert
+    assert_eq!(err, GovernanceError::VoterWeightRecordExpired.into());
+}
+
+#[tokio::test]
+async fn test_cast_vote_with_voter_weight_action_target_error() {
+    // Arrange
+    let mut governance_test = GovernanceProgramTest::start_with_voter_weight_addin().await;
+    let governed_account_cookie = governance_test.with_governed_account().await;
+
+    let realm_cookie = governance_test
+        .with_realm_using_addins(PluginSetupArgs::COMMUNITY_VOTER_WEIGHT)
+        .await;
+
+    let mut token_owner_record_cookie = governance_test
+        .with_community_token_owner_record(&realm_cookie)
+        .await;
+
+    governance_test
+        .with_voter_weight_addin_record(&mut token_owner_record_cookie)
+        .await
+        .unwrap();
+
+    let mut governance_cookie = governance_test
+        .with_governance(
+            &realm_cookie,
+            &governed_account_cookie,
+            &token_owner_record_cookie,
+        )
+        .await
+        .unwrap();
+
+    let proposal_cookie = governance_test
+        .with_signed_off_proposal(&token_owner_record_cookie, &mut governance_cookie)
+        .await
+        .unwrap();
+
+    governance_test.advance_clock().await;
+    let clock = governance_test.bench.get_clock().await;
+
+    governance_test
+        .with_voter_weight_addin_record_impl(
+            &mut token_owner_record_cookie,
+            150,
+            Some(clock.slot),
+            Some(VoterWeightAction::CastVote),
+            Some(governance_cookie.address), // Not the proposal voted on
+        )
+        .await
+        .unwrap();
+
+    // Act
+    let err = governance_test
+        .with_cast_yes_no_vote(&proposal_cookie, &token_owner_record_cookie, YesNoVote::Yes)
+        .await
+        .err()
+        .unwrap();
+
+    //  Assert
+    assert_eq!(
+        err,
+        GovernanceError::VoterWeightRecordInvalidActionTarget.into()
+    );
+}
+
 #[tokio::test]
 async fn test_create_governance_with_voter_weight_action_target_error() {
     // Arrange
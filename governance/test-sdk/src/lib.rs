@@ -370,6 +370,28 @@ impl ProgramTestBench {
         self.context.set_account(address, &data);
     }
 
+    /// Overrides or creates Pack serialized account with arbitrary account
+    /// data subverting normal runtime checks
+    pub fn set_packed_account<T: Pack>(
+        &mut self,
+        program_id: &Pubkey,
+        address: &Pubkey,
+        account: T,
+    ) {
+        let mut account_data = vec![0; T::LEN];
+        T::pack(account, &mut account_data).unwrap();
+
+        let data = AccountSharedData::create(
+            self.rent.minimum_balance(account_data.len()),
+            account_data,
+            *program_id,
+            false,
+            Epoch::default(),
+        );
+
+        self.context.set_account(address, &data);
+    }
+
     /// Removes an account by setting its data to empty and owner to system
     /// subverting normal runtime checks
     pub fn remove_account(&mut self, address: &Pubkey) {
@@ -0,0 +1,168 @@
+//! Human readable explanations of lending program errors
+
+use {
+    num_traits::FromPrimitive,
+    solana_program::{instruction::InstructionError, pubkey::Pubkey},
+    solana_sdk::{message::Message, transaction::TransactionError},
+    spl_token_lending::error::LendingError,
+};
+
+/// Explains a transaction error if it was raised by an instruction of the
+/// lending program
+pub fn explain_transaction_error(
+    lending_program_id: &Pubkey,
+    message: &Message,
+    err: &TransactionError,
+) -> Option<String> {
+    let TransactionError::InstructionError(index, InstructionError::Custom(code)) = err else {
+        return None;
+    };
+    if message.program_id(*index as usize)? != lending_program_id {
+        return None;
+    }
+    Some(explain_lending_error(*code))
+}
+
+/// Variant name, description and a suggested fix for a lending error code
+pub fn explain_lending_error(code: u32) -> String {
+    let Some(error) = LendingError::from_u32(code) else {
+        return format!("Unknown lending program error {} (0x{:x})", code, code);
+    };
+    let mut explanation = format!(
+        "Lending program error {:?} (0x{:x}): {}",
+        error, code, error
+    );
+    if let Some(fix) = suggested_fix(&error) {
+        explanation.push_str(&format!("\nSuggested fix: {}", fix));
+    }
+    explanation
+}
+
+fn suggested_fix(error: &LendingError) -> Option<&'static str> {
+    Some(match error {
+        LendingError::AlreadyInitialized => "use a new account address",
+        LendingError::NotRentExempt => {
+            "fund the new account with enough lamports to be rent exempt"
+        }
+        LendingError::InvalidMarketOwner | LendingError::InvalidObligationOwner => {
+            "check the owner keypair passed to the command"
+        }
+        LendingError::InvalidAmount => "use an amount greater than zero",
+        LendingError::InvalidConfig => {
+            "check the reserve config values against the ranges in `add-reserve --help`"
+        }
+        LendingError::InvalidLtvRatio => "use a loan to value ratio below 100",
+        LendingError::InvalidLiquidationThreshold => {
+            "use a liquidation threshold above the loan to value ratio and at most 100"
+        }
+        LendingError::InvalidLiquidationBonus => {
+            "use a liquidation bonus of at most 100, and a max liquidation bonus between the liquidation bonus and 100"
+        }
+        LendingError::InvalidBorrowRateCurve => {
+            "borrow rates must increase from min to optimal to max to super max, and utilization rates from optimal to max"
+        }
+        LendingError::InvalidFeeConfig => {
+            "use borrow and flash loan fees below 100% and a host fee percentage of at most 100"
+        }
+        LendingError::InvalidSigner => "pass the missing signer to the command",
+        LendingError::InsufficientLiquidity => {
+            "use a smaller amount, the reserve does not have enough liquidity available"
+        }
+        LendingError::ReserveCollateralDisabled => {
+            "deposit collateral from a reserve with a non-zero loan to value ratio"
+        }
+        LendingError::ReserveStale => {
+            "refresh the reserve in the same slot, before this instruction"
+        }
+        LendingError::ReservePriceStale => {
+            "wait for the oracle to publish a new price, then refresh the reserve before this instruction"
+        }
+        LendingError::WithdrawTooSmall => "withdraw a larger amount",
+        LendingError::WithdrawTooLarge => {
+            "withdraw a smaller amount, it is capped by borrows and reserve utilization"
+        }
+        LendingError::BorrowTooSmall => "borrow a larger amount to cover the borrow fee and the market minimum borrow value",
+        LendingError::BorrowTooLarge => "deposit more collateral or borrow a smaller amount",
+        LendingError::RepayTooSmall => "repay a larger amount",
+        LendingError::LiquidationTooSmall => "liquidate a larger amount",
+        LendingError::ObligationHealthy => {
+            "only obligations borrowing more than their unhealthy borrow value can be liquidated"
+        }
+        LendingError::ObligationStale => {
+            "refresh every obligation reserve and then the obligation, before this instruction"
+        }
+        LendingError::ObligationReserveLimit => {
+            "fully repay or withdraw from an obligation reserve, or resize the obligation, before using a new one"
+        }
+        LendingError::ObligationDepositsEmpty => "deposit collateral into the obligation first",
+        LendingError::ObligationBorrowsEmpty => "the obligation has no borrows to repay",
+        LendingError::InvalidOracleConfig => {
+            "check the reserve Pyth product and price accounts and that the price is current"
+        }
+        LendingError::ExceededSlippage => "lower the slippage limit or retry",
+        LendingError::ExchangeRateSlippageExceeded => {
+            "lower the minimum amount or retry, the exchange rate moves as interest accrues"
+        }
+        LendingError::BorrowLimitExceeded => {
+            "borrow a smaller amount, the reserve is close to its borrow limit"
+        }
+        LendingError::DepositLimitExceeded => {
+            "deposit a smaller amount, the reserve is close to its deposit limit"
+        }
+        LendingError::ActionPaused => {
+            "the lending market owner has paused this action on the reserve, retry later"
+        }
+        LendingError::ReserveNotEmpty => {
+            "repay every borrow, redeem all collateral and empty the fee receiver first"
+        }
+        LendingError::MarketFrozen => {
+            "the lending market is frozen, only repays and liquidations are allowed until it is unfrozen"
+        }
+        LendingError::PriceConfidenceTooWide => {
+            "the oracle is uncertain about the price, retry once it settles or raise the reserve max confidence interval"
+        }
+        LendingError::ReserveDeprecated => {
+            "the reserve is being wound down, move deposits and borrows to another reserve"
+        }
+        LendingError::OutflowRateLimitExceeded => {
+            "the reserve outflow limit is reached, wait for the rate limit window to advance or withdraw less"
+        }
+        LendingError::BorrowerNotAllowed => {
+            "the market only allows listed borrowers, ask the market owner to add the obligation owner to the borrow allowlist"
+        }
+        LendingError::IsolatedCollateral => {
+            "isolated collateral must be the only collateral of an obligation, use a separate obligation or borrow from a reserve allowed against isolated collateral"
+        }
+        LendingError::MarketMetadataTooLong => {
+            "shorten the market name to 64 bytes and the description URI to 200 bytes"
+        }
+        LendingError::NoFlashRepayFound => {
+            "add a flash repay for the same reserve and amount after the flash borrow, pointing back to it"
+        }
+        LendingError::InvalidFlashRepay => {
+            "repay the same reserve and amount as the flash borrow, with a single flash repay per borrow"
+        }
+        LendingError::FlashLoanInvokedViaCpi => {
+            "send the flash borrow and repay as top level instructions of the transaction"
+        }
+        LendingError::ObligationAlreadyMigrated => {
+            "the obligation is already at the current version, no migration is needed"
+        }
+        LendingError::AccountAlreadyMigrated => {
+            "the lending market or reserve is already in the current layout, no migration is needed"
+        }
+        LendingError::AccountNotMigrated => {
+            "migrate the reserve to the current layout with `migrate-accounts` first"
+        }
+        LendingError::RiskAuthorityCannotLoosen => {
+            "the risk authority can only lower ratios, bonuses and limits, raise borrow rates and set pause flags, sign as the lending market owner for other changes"
+        }
+        LendingError::DepositTooSmall => {
+            "deposit at least the reserve minimum, the first deposit must mint 1000 collateral"
+        }
+        LendingError::RedemptionsQueued => {
+            "the reserve is short of liquidity and queues redemptions, request a queued redemption instead"
+        }
+        _ => return None,
+    })
+}
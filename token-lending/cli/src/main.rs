@@ -1,23 +1,63 @@
 #![allow(clippy::arithmetic_side_effects)]
+mod lending_error;
+mod refresh;
+mod registry;
+mod reserve_config;
+mod rpc;
+mod snapshot;
+mod verify;
+
 use {
+    crate::{
+        lending_error::explain_transaction_error,
+        refresh::{batch_refresh_instructions, is_oracle_stale},
+        registry::{is_address_or_name, is_registry_name, resolve_address, NameRegistry},
+        reserve_config::{reserve_config_args, reserve_config_of},
+        rpc::CliRpcClient,
+        snapshot::{
+            diff_snapshots, LendingMarketState, MarketSnapshot, ObligationState, ReserveState,
+        },
+        verify::{verify_reserve, MarketVerification, ReserveAccounts},
+    },
     clap::{
         crate_description, crate_name, crate_version, value_t, App, AppSettings, Arg, ArgMatches,
         SubCommand,
     },
+    serde_derive::Deserialize,
+    solana_account_decoder::{UiAccountData, UiAccountEncoding},
     solana_clap_utils::{
         fee_payer::fee_payer_arg,
         input_parsers::{keypair_of, pubkey_of, value_of},
-        input_validators::{is_amount, is_keypair, is_parsable, is_pubkey, is_url},
+        input_validators::{
+            is_amount, is_amount_or_all, is_keypair, is_parsable, is_pubkey, is_url,
+            is_valid_pubkey, is_within_range,
+        },
         keypair::signer_from_path,
+        offline::{blockhash_arg, dump_transaction_message, SIGN_ONLY_ARG},
+    },
+    solana_cli_output::{return_signers_with_config, OutputFormat, ReturnSignersConfig},
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{
+            RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionAccountsConfig,
+            RpcSimulateTransactionConfig,
+        },
+        rpc_filter::{Memcmp, RpcFilterType},
+    },
+    solana_program::{
+        native_token::{lamports_to_sol, Sol},
+        program_pack::Pack,
+        pubkey::Pubkey,
     },
-    solana_client::rpc_client::RpcClient,
-    solana_program::{native_token::lamports_to_sol, program_pack::Pack, pubkey::Pubkey},
+    solana_remote_wallet::remote_wallet::RemoteWalletManager,
     solana_sdk::{
         commitment_config::CommitmentConfig,
+        hash::Hash,
+        instruction::Instruction,
         message::Message,
         signature::{Keypair, Signer},
         system_instruction,
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
     },
     spl_token::{
         instruction::{approve, revoke},
@@ -26,27 +66,184 @@ use {
     },
     spl_token_lending::{
         self,
-        instruction::{init_lending_market, init_reserve},
+        instruction::{
+            deposit_obligation_collateral, deposit_obligation_collateral_and_borrow,
+            deposit_reserve_liquidity, init_lending_market, init_reserve, migrate_account,
+            migrate_obligation, modify_reserve_config, redeem_reserve_collateral,
+            refresh_obligation, refresh_reserve, set_reserve_status,
+            withdraw_obligation_collateral,
+        },
         math::WAD,
-        state::{LendingMarket, Reserve, ReserveConfig, ReserveFees},
+        state::{
+            find_borrow_allowlist_entry_address, CollateralExchangeRate, LendingMarket, Obligation,
+            Reserve, ReserveConfig, ReserveFees, ReserveStatus, LENDING_MARKET_V1_LEN,
+            PROGRAM_VERSION, RESERVE_V1_LEN,
+        },
+    },
+    std::{
+        borrow::Borrow, cell::Cell, collections::HashMap, fs::File, process::exit, rc::Rc,
+        str::FromStr,
     },
-    std::{borrow::Borrow, process::exit, str::FromStr},
     system_instruction::create_account,
 };
 
 struct Config {
-    rpc_client: RpcClient,
+    rpc_client: CliRpcClient,
     fee_payer: Box<dyn Signer>,
     lending_program_id: Pubkey,
     verbose: bool,
     dry_run: bool,
+    estimate_only: bool,
+    priority_fee: Option<u64>,
+    estimated_fee: Cell<u64>,
+    sign_only: bool,
+    dump_transaction_message: bool,
+    blockhash: Option<Hash>,
 }
 
 type Error = Box<dyn std::error::Error>;
 type CommandResult = Result<(), Error>;
 
+/// Lending market owner, either a bare pubkey for cold-storage owners or a
+/// signer that can sign owner-gated instructions in the same session
+enum MarketOwner {
+    Pubkey(Pubkey),
+    Signer(Box<dyn Signer>),
+}
+
+impl MarketOwner {
+    fn pubkey(&self) -> Pubkey {
+        match self {
+            Self::Pubkey(pubkey) => *pubkey,
+            Self::Signer(signer) => signer.pubkey(),
+        }
+    }
+
+    fn signer(&self) -> Option<&dyn Signer> {
+        match self {
+            Self::Pubkey(_) => None,
+            Self::Signer(signer) => Some(signer.as_ref()),
+        }
+    }
+}
+
+/// Named cluster settings, selected with `--cluster`
+#[derive(Deserialize)]
+struct ClusterProfile {
+    json_rpc_url: String,
+    websocket_url: Option<String>,
+    commitment: Option<String>,
+    keypair_path: Option<String>,
+}
+
+/// Lending CLI settings stored alongside the Solana CLI configuration
+#[derive(Deserialize)]
+struct LendingCliConfig {
+    #[serde(default)]
+    clusters: HashMap<String, ClusterProfile>,
+}
+
+/// Reserve entry of an `--add-reserves-file`, config values default to the
+/// `add-reserve` defaults
+#[derive(Deserialize)]
+struct ReserveFileEntry {
+    source: String,
+    amount: f64,
+    pyth_product: String,
+    pyth_price: String,
+    optimal_utilization_rate: Option<u8>,
+    loan_to_value_ratio: Option<u8>,
+    collateral_weight: Option<u8>,
+    liquidation_bonus: Option<u8>,
+    min_liquidation_bonus: Option<u8>,
+    max_liquidation_bonus: Option<u8>,
+    liquidation_threshold: Option<u8>,
+    stressed_liquidation_threshold: Option<u8>,
+    stress_utilization_rate: Option<u8>,
+    min_borrow_rate: Option<u8>,
+    optimal_borrow_rate: Option<u8>,
+    max_borrow_rate: Option<u8>,
+    max_utilization_rate: Option<u8>,
+    super_max_borrow_rate: Option<u8>,
+    max_withdraw_utilization: Option<u8>,
+    borrow_rate_rebate: Option<u8>,
+    borrow_rate_floor: Option<u8>,
+    borrow_limit: Option<u64>,
+    deposit_limit: Option<u64>,
+    max_oracle_age_slots: Option<u64>,
+    borrow_weight: Option<f64>,
+    protocol_liquidation_fee: Option<u8>,
+    liquidation_close_factor: Option<u8>,
+    liquidation_dust_threshold: Option<u64>,
+    min_deposit_amount: Option<u64>,
+    max_confidence_interval_bps: Option<u16>,
+    outflow_window_duration_slots: Option<u64>,
+    max_outflow: Option<u64>,
+    max_accrual_slots_per_refresh: Option<u64>,
+    rate_history_interval_slots: Option<u64>,
+    isolated: Option<bool>,
+    borrowable_in_isolation: Option<bool>,
+    borrow_fee: Option<f64>,
+    flash_loan_fee: Option<f64>,
+    host_fee_percentage: Option<u8>,
+}
+
+impl ReserveFileEntry {
+    fn reserve_config(&self) -> ReserveConfig {
+        ReserveConfig {
+            optimal_utilization_rate: self.optimal_utilization_rate.unwrap_or(80),
+            loan_to_value_ratio: self.loan_to_value_ratio.unwrap_or(50),
+            collateral_weight: self.collateral_weight.unwrap_or(100),
+            liquidation_bonus: self.liquidation_bonus.unwrap_or(5),
+            min_liquidation_bonus: self.min_liquidation_bonus.unwrap_or(0),
+            max_liquidation_bonus: self.max_liquidation_bonus.unwrap_or(5),
+            liquidation_threshold: self.liquidation_threshold.unwrap_or(55),
+            stressed_liquidation_threshold: self.stressed_liquidation_threshold.unwrap_or(0),
+            stress_utilization_rate: self.stress_utilization_rate.unwrap_or(0),
+            min_borrow_rate: self.min_borrow_rate.unwrap_or(0),
+            optimal_borrow_rate: self.optimal_borrow_rate.unwrap_or(4),
+            max_borrow_rate: self.max_borrow_rate.unwrap_or(30),
+            max_utilization_rate: self.max_utilization_rate.unwrap_or(0),
+            super_max_borrow_rate: self.super_max_borrow_rate.unwrap_or(0),
+            max_withdraw_utilization: self.max_withdraw_utilization.unwrap_or(100),
+            borrow_rate_rebate: self.borrow_rate_rebate.unwrap_or(0),
+            borrow_rate_floor: self.borrow_rate_floor.unwrap_or(0),
+            borrow_limit: self.borrow_limit.unwrap_or(u64::MAX),
+            deposit_limit: self.deposit_limit.unwrap_or(u64::MAX),
+            max_oracle_age_slots: self.max_oracle_age_slots.unwrap_or(0),
+            borrow_weight_wad: (self.borrow_weight.unwrap_or(1.0) * WAD as f64) as u64,
+            protocol_liquidation_fee: self.protocol_liquidation_fee.unwrap_or(0),
+            liquidation_close_factor: self.liquidation_close_factor.unwrap_or(50),
+            liquidation_dust_threshold: self.liquidation_dust_threshold.unwrap_or(0),
+            min_deposit_amount: self.min_deposit_amount.unwrap_or(0),
+            max_confidence_interval_bps: self.max_confidence_interval_bps.unwrap_or(0),
+            outflow_window_duration_slots: self.outflow_window_duration_slots.unwrap_or(0),
+            max_outflow: self.max_outflow.unwrap_or(0),
+            max_accrual_slots_per_refresh: self.max_accrual_slots_per_refresh.unwrap_or(0),
+            rate_history_interval_slots: self.rate_history_interval_slots.unwrap_or(0),
+            isolated: self.isolated.unwrap_or(false),
+            borrowable_in_isolation: self.borrowable_in_isolation.unwrap_or(false),
+            fees: ReserveFees {
+                borrow_fee_wad: (self.borrow_fee.unwrap_or(0.00001) * WAD as f64) as u64,
+                flash_loan_fee_wad: (self.flash_loan_fee.unwrap_or(0.3) * WAD as f64) as u64,
+                host_fee_percentage: self.host_fee_percentage.unwrap_or(20),
+            },
+        }
+    }
+}
+
 const PYTH_PROGRAM_ID: &str = "gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s";
 
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// MigrateObligation instructions sent per transaction by `migrate-obligations`,
+/// each adds one obligation account to the transaction
+const MIGRATE_OBLIGATIONS_PER_TRANSACTION: usize = 20;
+
+/// MigrateAccount instructions sent per transaction by `migrate-accounts`, each
+/// adds one lending market or reserve account to the transaction
+const MIGRATE_ACCOUNTS_PER_TRANSACTION: usize = 20;
+
 fn main() {
     solana_logger::setup_with_default("solana=info");
 
@@ -70,6 +267,14 @@ fn main() {
                 arg
             }
         })
+        .arg(
+            Arg::with_name("cluster")
+                .long("cluster")
+                .value_name("NAME")
+                .takes_value(true)
+                .global(true)
+                .help("Cluster profile from the `clusters` table of the configuration file"),
+        )
         .arg(
             Arg::with_name("json_rpc_url")
                 .long("url")
@@ -106,7 +311,53 @@ fn main() {
                 .long("dry-run")
                 .takes_value(false)
                 .global(true)
-                .help("Simulate transaction instead of executing"),
+                .help("Simulate transaction instead of executing, with signature verification"),
+        )
+        .arg(
+            Arg::with_name("estimate_only")
+                .long("estimate-only")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with("dry_run")
+                .help("Print the total fee of the transactions without signing or sending them"),
+        )
+        .arg(
+            Arg::with_name("priority_fee")
+                .long("priority-fee")
+                .validator(is_parsable::<u64>)
+                .value_name("MICRO_LAMPORTS")
+                .takes_value(true)
+                .global(true)
+                .requires("estimate_only")
+                .help("Compute unit price added to the estimate, priced from simulated compute units"),
+        )
+        .arg(
+            Arg::with_name(SIGN_ONLY_ARG.name)
+                .long(SIGN_ONLY_ARG.long)
+                .takes_value(false)
+                .global(true)
+                .conflicts_with_all(&["dry_run", "estimate_only"])
+                .help("Print the signers of the transactions instead of sending them, for offline signing"),
+        )
+        .arg(dump_transaction_message().global(true))
+        .arg(blockhash_arg().global(true))
+        .arg(
+            Arg::with_name("registry")
+                .long("registry")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("JSON file mapping names to reserve and lending market addresses, \
+                       so names can be passed instead of addresses"),
+        )
+        .arg(
+            Arg::with_name("max_rps")
+                .long("max-rps")
+                .validator(|value| is_within_range::<usize, _>(value, 1..))
+                .value_name("COUNT")
+                .takes_value(true)
+                .global(true)
+                .help("Maximum number of RPC requests sent per second"),
         )
         .subcommand(
             SubCommand::with_name("create-market")
@@ -114,11 +365,19 @@ fn main() {
                 .arg(
                     Arg::with_name("lending_market_owner")
                         .long("market-owner")
-                        .validator(is_pubkey)
-                        .value_name("PUBKEY")
+                        .validator(is_valid_pubkey)
+                        .value_name("PUBKEY_OR_SIGNER")
                         .takes_value(true)
                         .required(true)
-                        .help("Owner that can add reserves to the market"),
+                        .help("Owner that can add reserves to the market. \
+                               A signer is only required with --add-reserves-file"),
+                )
+                .arg(
+                    Arg::with_name("add_reserves_file")
+                        .long("add-reserves-file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help("JSON file listing reserves to add to the new market, signed by the market owner"),
                 )
                 .arg(
                     Arg::with_name("oracle_program_id")
@@ -138,6 +397,14 @@ fn main() {
                         .required(true)
                         .default_value("USD")
                         .help("Currency market prices are quoted in"),
+                )
+                .arg(
+                    Arg::with_name("register_as")
+                        .long("register-as")
+                        .validator(is_registry_name)
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .help("Name to register the new lending market as in the --registry file"),
                 ),
         )
         .subcommand(
@@ -166,11 +433,11 @@ fn main() {
                 .arg(
                     Arg::with_name("lending_market")
                         .long("market")
-                        .validator(is_pubkey)
-                        .value_name("PUBKEY")
+                        .validator(is_address_or_name)
+                        .value_name("PUBKEY_OR_NAME")
                         .takes_value(true)
                         .required(true)
-                        .help("Lending market address"),
+                        .help("Lending market address or registered name"),
                 )
                 .arg(
                     Arg::with_name("source_liquidity")
@@ -198,7 +465,7 @@ fn main() {
                         .value_name("PUBKEY")
                         .takes_value(true)
                         .required(true)
-                        .help("Pyth product account: https://pyth.network/developers/consumers/accounts"),
+                        .help("Pyth product account: https://pyth.network/developers/consumers/accounts, or the Switchboard aggregator account"),
                 )
                 .arg(
                     Arg::with_name("pyth_price")
@@ -207,107 +474,370 @@ fn main() {
                         .value_name("PUBKEY")
                         .takes_value(true)
                         .required(true)
-                        .help("Pyth price account: https://pyth.network/developers/consumers/accounts"),
+                        .help("Pyth price account: https://pyth.network/developers/consumers/accounts, or the Switchboard aggregator account"),
+                )
+                .args(&reserve_config_args())
+                .arg(
+                    Arg::with_name("register_as")
+                        .long("register-as")
+                        .validator(is_registry_name)
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .help("Name to register the new reserve as in the --registry file"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("deposit-and-collateralize")
+                .about("Deposit liquidity into a reserve and the minted collateral into an obligation")
+                .arg(
+                    Arg::with_name("obligation")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("OBLIGATION")
+                        .required(true)
+                        .help("Obligation address"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .index(2)
+                        .validator(is_address_or_name)
+                        .value_name("RESERVE")
+                        .required(true)
+                        .help("Reserve address or registered name"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .index(3)
+                        .validator(is_amount)
+                        .value_name("DECIMAL_AMOUNT")
+                        .required(true)
+                        .help("Amount of liquidity to deposit"),
+                )
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation and of the source liquidity and collateral accounts"),
+                )
+                .arg(
+                    Arg::with_name("source_liquidity")
+                        .long("source")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to deposit liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("user_collateral")
+                        .long("collateral")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account receiving the reserve collateral before it is deposited"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-and-redeem")
+                .about("Withdraw collateral from an obligation and redeem it for reserve liquidity")
+                .arg(
+                    Arg::with_name("obligation")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("OBLIGATION")
+                        .required(true)
+                        .help("Obligation address"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .index(2)
+                        .validator(is_address_or_name)
+                        .value_name("RESERVE")
+                        .required(true)
+                        .help("Reserve address or registered name"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .index(3)
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .required(true)
+                        .help("Amount of liquidity to receive, or ALL to withdraw all deposited collateral"),
+                )
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation and of the collateral account"),
+                )
+                .arg(
+                    Arg::with_name("user_collateral")
+                        .long("collateral")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account receiving the withdrawn collateral before it is redeemed"),
                 )
                 .arg(
-                    Arg::with_name("optimal_utilization_rate")
-                        .long("optimal-utilization-rate")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("destination_liquidity")
+                        .long("destination")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
                         .takes_value(true)
                         .required(true)
-                        .default_value("80")
-                        .help("Optimal utilization rate: [0, 100]"),
+                        .help("SPL Token account to receive the redeemed liquidity"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("deposit-and-borrow")
+                .about("Deposit collateral into an obligation and borrow liquidity against it")
+                .arg(
+                    Arg::with_name("obligation")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("OBLIGATION")
+                        .required(true)
+                        .help("Obligation address"),
+                )
+                .arg(
+                    Arg::with_name("deposit_reserve")
+                        .index(2)
+                        .validator(is_address_or_name)
+                        .value_name("DEPOSIT_RESERVE")
+                        .required(true)
+                        .help("Reserve of the deposited collateral, address or registered name"),
+                )
+                .arg(
+                    Arg::with_name("collateral_amount")
+                        .index(3)
+                        .validator(is_amount)
+                        .value_name("DECIMAL_AMOUNT")
+                        .required(true)
+                        .help("Amount of collateral to deposit"),
                 )
                 .arg(
-                    Arg::with_name("loan_to_value_ratio")
-                        .long("loan-to-value-ratio")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("borrow_reserve")
+                        .index(4)
+                        .validator(is_address_or_name)
+                        .value_name("BORROW_RESERVE")
+                        .required(true)
+                        .help("Reserve to borrow liquidity from, address or registered name"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .index(5)
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .required(true)
+                        .help("Amount of liquidity to borrow, or ALL to borrow up to the borrowing power"),
+                )
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
                         .takes_value(true)
                         .required(true)
-                        .default_value("50")
-                        .help("Target ratio of the value of borrows to deposits: [0, 100)"),
+                        .help("Owner of the obligation and of the collateral account"),
                 )
                 .arg(
-                    Arg::with_name("liquidation_bonus")
-                        .long("liquidation-bonus")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("user_collateral")
+                        .long("collateral")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
                         .takes_value(true)
                         .required(true)
-                        .default_value("5")
-                        .help("Bonus a liquidator gets when repaying part of an unhealthy obligation: [0, 100]"),
+                        .help("SPL Token account to deposit collateral from"),
                 )
                 .arg(
-                    Arg::with_name("liquidation_threshold")
-                        .long("liquidation-threshold")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("destination_liquidity")
+                        .long("destination")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
                         .takes_value(true)
                         .required(true)
-                        .default_value("55")
-                        .help("Loan to value ratio at which an obligation can be liquidated: (LTV, 100]"),
+                        .help("SPL Token account to receive the borrowed liquidity"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("dump-state")
+                .about("Write a lending market and all of its reserves to a JSON snapshot")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
                 )
                 .arg(
-                    Arg::with_name("min_borrow_rate")
-                        .long("min-borrow-rate")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("out")
+                        .long("out")
+                        .value_name("PATH")
                         .takes_value(true)
                         .required(true)
-                        .default_value("0")
-                        .help("Min borrow APY: min <= optimal <= max"),
+                        .help("File to write the snapshot to"),
+                )
+                .arg(
+                    Arg::with_name("include_obligations")
+                        .long("include-obligations")
+                        .takes_value(false)
+                        .help("Also write every obligation of the lending market"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("diff-state")
+                .about("Compare a lending market and all of its reserves against a JSON snapshot")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
                 )
                 .arg(
-                    Arg::with_name("optimal_borrow_rate")
-                        .long("optimal-borrow-rate")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("against")
+                        .long("against")
+                        .value_name("PATH")
                         .takes_value(true)
                         .required(true)
-                        .default_value("4")
-                        .help("Optimal (utilization) borrow APY: min <= optimal <= max"),
+                        .help("Snapshot written by dump-state"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("verify-market")
+                .about("Check that a lending market's reserves are consistent with their token and oracle accounts")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
                 )
                 .arg(
-                    Arg::with_name("max_borrow_rate")
-                        .long("max-borrow-rate")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("output")
+                        .long("output")
+                        .value_name("FORMAT")
                         .takes_value(true)
+                        .possible_values(&["display", "json"])
+                        .default_value("display")
+                        .help("Report format"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("refresh-reserves")
+                .about("Refresh every reserve of a lending market, batching as many refreshes as fit into each transaction")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-obligation")
+                .about("Migrate an obligation of an older program version to the current version")
+                .arg(
+                    Arg::with_name("obligation")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("OBLIGATION")
+                        .required(true)
+                        .help("Obligation address"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-obligations")
+                .about("Migrate every obligation of a lending market that is at an older program version")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-accounts")
+                .about("Migrate a lending market and its reserves that are still in the V1 account layout")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("MARKET")
+                        .required(true)
+                        .help("Lending market address or registered name"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("update-reserve-config")
+                .about("Update the config of a reserve, keeping the values of the flags not passed")
+                .arg(
+                    Arg::with_name("reserve")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("RESERVE")
                         .required(true)
-                        .default_value("30")
-                        .help("Max borrow APY: min <= optimal <= max"),
+                        .help("Reserve address or registered name"),
                 )
+                // @TODO: use is_valid_signer
                 .arg(
-                    Arg::with_name("borrow_fee")
-                        .long("borrow-fee")
-                        .validator(is_parsable::<f64>)
-                        .value_name("DECIMAL_PERCENT")
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
                         .takes_value(true)
                         .required(true)
-                        .default_value("0.00001")
-                        .help("Fee assessed on borrow, expressed as a percentage: [0, 1)"),
+                        .help("Owner of the lending market, or its risk authority to tighten the config"),
+                )
+                .args(&reserve_config_args())
+        )
+        .subcommand(
+            SubCommand::with_name("set-reserve-status")
+                .about("Set the status of a reserve, deprecating it to wind it down")
+                .arg(
+                    Arg::with_name("reserve")
+                        .index(1)
+                        .validator(is_address_or_name)
+                        .value_name("RESERVE")
+                        .required(true)
+                        .help("Reserve address or registered name"),
                 )
+                // @TODO: use is_valid_signer
                 .arg(
-                    Arg::with_name("flash_loan_fee")
-                        .long("flash-loan-fee")
-                        .validator(is_parsable::<f64>)
-                        .value_name("DECIMAL_PERCENT")
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
                         .takes_value(true)
                         .required(true)
-                        .default_value(".3")
-                        .help("Fee assessed for flash loans, expressed as a percentage: [0, 1)"),
+                        .help("Owner of the lending market"),
                 )
                 .arg(
-                    Arg::with_name("host_fee_percentage")
-                        .long("host-fee-percentage")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                    Arg::with_name("status")
+                        .long("status")
+                        .value_name("STATUS")
                         .takes_value(true)
+                        .possible_values(&["active", "deprecated", "obsolete"])
                         .required(true)
-                        .default_value("20")
-                        .help("Amount of fee going to host account: [0, 100]"),
+                        .help("Deprecated reserves reject new deposits and borrows, \
+                               obsolete reserves reject redemptions as well"),
                 )
         )
         .get_matches();
@@ -319,13 +849,37 @@ fn main() {
         } else {
             solana_cli_config::Config::default()
         };
-        let json_rpc_url = value_t!(matches, "json_rpc_url", String)
-            .unwrap_or_else(|_| cli_config.json_rpc_url.clone());
+        let cluster_profile = cluster_profile_of(&matches).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
+
+        // Explicit flags take precedence over the cluster profile, which takes
+        // precedence over the Solana CLI configuration
+        let json_rpc_url = value_t!(matches, "json_rpc_url", String).unwrap_or_else(|_| {
+            cluster_profile
+                .as_ref()
+                .map(|(_, profile)| profile.json_rpc_url.clone())
+                .unwrap_or_else(|| cli_config.json_rpc_url.clone())
+        });
+        let commitment = cluster_profile
+            .as_ref()
+            .and_then(|(_, profile)| profile.commitment.as_ref())
+            .unwrap_or(&cli_config.commitment);
+        let commitment = CommitmentConfig::from_str(commitment).unwrap_or_else(|_| {
+            eprintln!("error: invalid commitment {}", commitment);
+            exit(1);
+        });
 
         let fee_payer = signer_from_path(
             &matches,
             matches
                 .value_of("fee_payer")
+                .or_else(|| {
+                    cluster_profile
+                        .as_ref()
+                        .and_then(|(_, profile)| profile.keypair_path.as_deref())
+                })
                 .unwrap_or(&cli_config.keypair_path),
             "fee_payer",
             &mut wallet_manager,
@@ -338,94 +892,304 @@ fn main() {
         let lending_program_id = pubkey_of(&matches, "lending_program_id").unwrap();
         let verbose = matches.is_present("verbose");
         let dry_run = matches.is_present("dry_run");
+        let estimate_only = matches.is_present("estimate_only");
+        let priority_fee = value_of(&matches, "priority_fee");
+        let sign_only = matches.is_present(SIGN_ONLY_ARG.name);
+        let dump_transaction_message = matches.is_present("dump_transaction_message");
+        let blockhash = value_of(&matches, "blockhash");
+        let max_rps = value_of(&matches, "max_rps");
+
+        if verbose {
+            if let Some((cluster, profile)) = &cluster_profile {
+                println!("Cluster profile: {}", cluster);
+                if let Some(websocket_url) = &profile.websocket_url {
+                    println!("Websocket URL: {}", websocket_url);
+                }
+            }
+            println!("JSON RPC URL: {}", json_rpc_url);
+        }
 
         Config {
-            rpc_client: RpcClient::new_with_commitment(json_rpc_url, CommitmentConfig::confirmed()),
+            rpc_client: CliRpcClient::new(
+                RpcClient::new_with_commitment(json_rpc_url, commitment),
+                max_rps,
+            ),
             fee_payer,
             lending_program_id,
             verbose,
             dry_run,
+            estimate_only,
+            priority_fee,
+            estimated_fee: Cell::new(0),
+            sign_only,
+            dump_transaction_message,
+            blockhash,
         }
     };
 
+    let mut registry = matches
+        .value_of("registry")
+        .map(NameRegistry::load)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
+    let address_of = |matches: &ArgMatches<'_>, name: &str| {
+        resolve_address(registry.as_ref(), matches.value_of(name).unwrap()).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        })
+    };
+
     let _ = match matches.subcommand() {
         ("create-market", Some(arg_matches)) => {
-            let lending_market_owner = pubkey_of(arg_matches, "lending_market_owner").unwrap();
+            let lending_market_owner =
+                market_owner_of(arg_matches, "lending_market_owner", &mut wallet_manager)
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: {}", e);
+                        exit(1);
+                    });
             let quote_currency = quote_currency_of(arg_matches, "quote_currency").unwrap();
             let oracle_program_id = pubkey_of(arg_matches, "oracle_program_id").unwrap();
+            let register_as = arg_matches.value_of("register_as");
+            check_register_as(registry.as_ref(), register_as).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
+            let reserves = arg_matches
+                .value_of("add_reserves_file")
+                .map(reserves_file_of)
+                .transpose()
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                })
+                .unwrap_or_default();
             command_create_lending_market(
                 &config,
-                lending_market_owner,
+                &lending_market_owner,
                 quote_currency,
                 oracle_program_id,
+                reserves,
             )
+            .and_then(|lending_market_pubkey| {
+                register_address(
+                    &config,
+                    registry.as_mut(),
+                    register_as,
+                    &lending_market_pubkey,
+                )
+            })
         }
         ("add-reserve", Some(arg_matches)) => {
             let lending_market_owner_keypair =
                 keypair_of(arg_matches, "lending_market_owner").unwrap();
             let source_liquidity_owner_keypair =
                 keypair_of(arg_matches, "source_liquidity_owner").unwrap();
-            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
             let source_liquidity_pubkey = pubkey_of(arg_matches, "source_liquidity").unwrap();
             let ui_amount = value_of(arg_matches, "liquidity_amount").unwrap();
             let pyth_product_pubkey = pubkey_of(arg_matches, "pyth_product").unwrap();
             let pyth_price_pubkey = pubkey_of(arg_matches, "pyth_price").unwrap();
-            let optimal_utilization_rate =
-                value_of(arg_matches, "optimal_utilization_rate").unwrap();
-            let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio").unwrap();
-            let liquidation_bonus = value_of(arg_matches, "liquidation_bonus").unwrap();
-            let liquidation_threshold = value_of(arg_matches, "liquidation_threshold").unwrap();
-            let min_borrow_rate = value_of(arg_matches, "min_borrow_rate").unwrap();
-            let optimal_borrow_rate = value_of(arg_matches, "optimal_borrow_rate").unwrap();
-            let max_borrow_rate = value_of(arg_matches, "max_borrow_rate").unwrap();
-            let borrow_fee = value_of::<f64>(arg_matches, "borrow_fee").unwrap();
-            let flash_loan_fee = value_of::<f64>(arg_matches, "flash_loan_fee").unwrap();
-            let host_fee_percentage = value_of(arg_matches, "host_fee_percentage").unwrap();
-
-            let borrow_fee_wad = (borrow_fee * WAD as f64) as u64;
-            let flash_loan_fee_wad = (flash_loan_fee * WAD as f64) as u64;
+            let reserve_config = reserve_config_of(arg_matches, None).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
+            let register_as = arg_matches.value_of("register_as");
+            check_register_as(registry.as_ref(), register_as).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
 
             command_add_reserve(
                 &config,
                 ui_amount,
-                ReserveConfig {
-                    optimal_utilization_rate,
-                    loan_to_value_ratio,
-                    liquidation_bonus,
-                    liquidation_threshold,
-                    min_borrow_rate,
-                    optimal_borrow_rate,
-                    max_borrow_rate,
-                    fees: ReserveFees {
-                        borrow_fee_wad,
-                        flash_loan_fee_wad,
-                        host_fee_percentage,
-                    },
-                },
+                reserve_config,
                 source_liquidity_pubkey,
-                source_liquidity_owner_keypair,
+                &source_liquidity_owner_keypair,
                 lending_market_pubkey,
-                lending_market_owner_keypair,
+                &lending_market_owner_keypair,
                 pyth_product_pubkey,
                 pyth_price_pubkey,
             )
+            .and_then(|reserve_pubkey| {
+                register_address(&config, registry.as_mut(), register_as, &reserve_pubkey)
+            })
         }
-        _ => unreachable!(),
-    }
-    .map_err(|err| {
-        eprintln!("{}", err);
-        exit(1);
-    });
-}
+        ("deposit-and-collateralize", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let reserve_pubkey = address_of(arg_matches, "reserve");
+            let ui_amount = value_of(arg_matches, "liquidity_amount").unwrap();
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let source_liquidity_pubkey = pubkey_of(arg_matches, "source_liquidity").unwrap();
+            let user_collateral_pubkey = pubkey_of(arg_matches, "user_collateral").unwrap();
 
-// COMMANDS
+            command_deposit_and_collateralize(
+                &config,
+                obligation_pubkey,
+                reserve_pubkey,
+                ui_amount,
+                obligation_owner_keypair,
+                source_liquidity_pubkey,
+                user_collateral_pubkey,
+            )
+        }
+        ("withdraw-and-redeem", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let reserve_pubkey = address_of(arg_matches, "reserve");
+            let ui_amount = match arg_matches.value_of("liquidity_amount").unwrap() {
+                "ALL" => None,
+                _ => Some(value_of::<f64>(arg_matches, "liquidity_amount").unwrap()),
+            };
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let user_collateral_pubkey = pubkey_of(arg_matches, "user_collateral").unwrap();
+            let destination_liquidity_pubkey =
+                pubkey_of(arg_matches, "destination_liquidity").unwrap();
+
+            command_withdraw_and_redeem(
+                &config,
+                obligation_pubkey,
+                reserve_pubkey,
+                ui_amount,
+                obligation_owner_keypair,
+                user_collateral_pubkey,
+                destination_liquidity_pubkey,
+            )
+        }
+        ("deposit-and-borrow", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let deposit_reserve_pubkey = address_of(arg_matches, "deposit_reserve");
+            let collateral_ui_amount = value_of(arg_matches, "collateral_amount").unwrap();
+            let borrow_reserve_pubkey = address_of(arg_matches, "borrow_reserve");
+            let liquidity_ui_amount = match arg_matches.value_of("liquidity_amount").unwrap() {
+                "ALL" => None,
+                _ => Some(value_of::<f64>(arg_matches, "liquidity_amount").unwrap()),
+            };
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let user_collateral_pubkey = pubkey_of(arg_matches, "user_collateral").unwrap();
+            let destination_liquidity_pubkey =
+                pubkey_of(arg_matches, "destination_liquidity").unwrap();
+
+            command_deposit_and_borrow(
+                &config,
+                obligation_pubkey,
+                deposit_reserve_pubkey,
+                collateral_ui_amount,
+                borrow_reserve_pubkey,
+                liquidity_ui_amount,
+                obligation_owner_keypair,
+                user_collateral_pubkey,
+                destination_liquidity_pubkey,
+            )
+        }
+        ("dump-state", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+            let out_path = arg_matches.value_of("out").unwrap();
+            let include_obligations = arg_matches.is_present("include_obligations");
+
+            command_dump_state(
+                &config,
+                lending_market_pubkey,
+                out_path,
+                include_obligations,
+            )
+        }
+        ("diff-state", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+            let against_path = arg_matches.value_of("against").unwrap();
+
+            command_diff_state(&config, lending_market_pubkey, against_path)
+        }
+        ("verify-market", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+            let json = arg_matches.value_of("output") == Some("json");
+
+            command_verify_market(&config, lending_market_pubkey, json)
+        }
+        ("refresh-reserves", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+
+            command_refresh_reserves(&config, lending_market_pubkey)
+        }
+        ("migrate-obligation", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+
+            command_migrate_obligation(&config, obligation_pubkey)
+        }
+        ("migrate-obligations", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+
+            command_migrate_obligations(&config, lending_market_pubkey)
+        }
+        ("migrate-accounts", Some(arg_matches)) => {
+            let lending_market_pubkey = address_of(arg_matches, "lending_market");
+
+            command_migrate_accounts(&config, lending_market_pubkey)
+        }
+        ("update-reserve-config", Some(arg_matches)) => {
+            let reserve_pubkey = address_of(arg_matches, "reserve");
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+
+            get_reserve(&config, &reserve_pubkey).and_then(|reserve| {
+                let reserve_config = reserve_config_of(arg_matches, Some(reserve.config))?;
+                command_update_reserve_config(
+                    &config,
+                    reserve_pubkey,
+                    &reserve,
+                    &lending_market_owner_keypair,
+                    reserve_config,
+                )
+            })
+        }
+        ("set-reserve-status", Some(arg_matches)) => {
+            let reserve_pubkey = address_of(arg_matches, "reserve");
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let status = match arg_matches.value_of("status").unwrap() {
+                "active" => ReserveStatus::Active,
+                "deprecated" => ReserveStatus::Deprecated,
+                "obsolete" => ReserveStatus::Obsolete,
+                _ => unreachable!(),
+            };
+
+            command_set_reserve_status(
+                &config,
+                reserve_pubkey,
+                &lending_market_owner_keypair,
+                status,
+            )
+        }
+        _ => unreachable!(),
+    }
+    .map_err(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+
+    if config.estimate_only {
+        println!("Estimated fee: {}", Sol(config.estimated_fee.get()));
+    }
+    if config.verbose {
+        println!("RPC requests: {}", config.rpc_client.request_count());
+    }
+}
+
+// COMMANDS
 
 fn command_create_lending_market(
     config: &Config,
-    lending_market_owner: Pubkey,
+    lending_market_owner: &MarketOwner,
     quote_currency: [u8; 32],
     oracle_program_id: Pubkey,
-) -> CommandResult {
+    reserves: Vec<ReserveFileEntry>,
+) -> Result<Pubkey, Error> {
+    let lending_market_owner_signer = lending_market_owner.signer();
+    if !reserves.is_empty() && lending_market_owner_signer.is_none() {
+        return Err("Adding reserves requires the market owner to be a signer".into());
+    }
+
     let lending_market_keypair = Keypair::new();
     println!(
         "Creating lending market {}",
@@ -436,7 +1200,7 @@ fn command_create_lending_market(
         .rpc_client
         .get_minimum_balance_for_rent_exemption(LendingMarket::LEN)?;
 
-    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let recent_blockhash = get_blockhash(config)?;
     let message = Message::new_with_blockhash(
         &[
             // Account for the lending market
@@ -450,7 +1214,7 @@ fn command_create_lending_market(
             // Initialize lending market account
             init_lending_market(
                 config.lending_program_id,
-                lending_market_owner,
+                lending_market_owner.pubkey(),
                 quote_currency,
                 lending_market_keypair.pubkey(),
                 oracle_program_id,
@@ -460,17 +1224,32 @@ fn command_create_lending_market(
         &recent_blockhash,
     );
 
-    check_fee_payer_balance(
+    check_fee_payer_balance(config, lending_market_balance, &[&message])?;
+    send_transaction(
         config,
-        lending_market_balance + config.rpc_client.get_fee_for_message(&message)?,
-    )?;
-    let transaction = Transaction::new(
-        &vec![config.fee_payer.as_ref(), &lending_market_keypair],
         message,
-        recent_blockhash,
-    );
-    send_transaction(config, transaction)?;
-    Ok(())
+        &[config.fee_payer.as_ref(), &lending_market_keypair],
+    )?;
+
+    if let Some(lending_market_owner_signer) = lending_market_owner_signer {
+        for reserve in reserves {
+            let source_liquidity_pubkey = Pubkey::from_str(&reserve.source)?;
+            let pyth_product_pubkey = Pubkey::from_str(&reserve.pyth_product)?;
+            let pyth_price_pubkey = Pubkey::from_str(&reserve.pyth_price)?;
+            command_add_reserve(
+                config,
+                reserve.amount,
+                reserve.reserve_config(),
+                source_liquidity_pubkey,
+                lending_market_owner_signer,
+                lending_market_keypair.pubkey(),
+                lending_market_owner_signer,
+                pyth_product_pubkey,
+                pyth_price_pubkey,
+            )?;
+        }
+    }
+    Ok(lending_market_keypair.pubkey())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -479,12 +1258,12 @@ fn command_add_reserve(
     ui_amount: f64,
     reserve_config: ReserveConfig,
     source_liquidity_pubkey: Pubkey,
-    source_liquidity_owner_keypair: Keypair,
+    source_liquidity_owner_keypair: &dyn Signer,
     lending_market_pubkey: Pubkey,
-    lending_market_owner_keypair: Keypair,
+    lending_market_owner_keypair: &dyn Signer,
     pyth_product_pubkey: Pubkey,
     pyth_price_pubkey: Pubkey,
-) -> CommandResult {
+) -> Result<Pubkey, Error> {
     let source_liquidity_account = config.rpc_client.get_account(&source_liquidity_pubkey)?;
     let source_liquidity = Token::unpack_from_slice(source_liquidity_account.data.borrow())?;
 
@@ -550,7 +1329,7 @@ fn command_add_reserve(
         + liquidity_supply_balance
         + liquidity_fee_receiver_balance;
 
-    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let recent_blockhash = get_blockhash(config)?;
     let message_1 = Message::new_with_blockhash(
         &[
             create_account(
@@ -647,53 +1426,892 @@ fn command_add_reserve(
         Some(&config.fee_payer.pubkey()),
         &recent_blockhash,
     );
-    check_fee_payer_balance(
-        config,
-        total_balance
-            + config.rpc_client.get_fee_for_message(&message_1)?
-            + config.rpc_client.get_fee_for_message(&message_2)?
-            + config.rpc_client.get_fee_for_message(&message_3)?,
-    )?;
+    check_fee_payer_balance(config, total_balance, &[&message_1, &message_2, &message_3])?;
 
-    let transaction_1 = Transaction::new(
-        &vec![
+    send_transaction(
+        config,
+        message_1,
+        &[
             config.fee_payer.as_ref(),
             &reserve_keypair,
             &collateral_mint_keypair,
             &collateral_supply_keypair,
             &user_collateral_keypair,
         ],
-        message_1,
-        recent_blockhash,
-    );
-    send_transaction(config, transaction_1)?;
-    let transaction_2 = Transaction::new(
-        &vec![
+    )?;
+    send_transaction(
+        config,
+        message_2,
+        &[
             config.fee_payer.as_ref(),
             &liquidity_supply_keypair,
             &liquidity_fee_receiver_keypair,
         ],
-        message_2,
-        recent_blockhash,
-    );
-    send_transaction(config, transaction_2)?;
-    let transaction_3 = Transaction::new(
-        &vec![
+    )?;
+    send_transaction(
+        config,
+        message_3,
+        &[
             config.fee_payer.as_ref(),
-            &source_liquidity_owner_keypair,
-            &lending_market_owner_keypair,
+            source_liquidity_owner_keypair,
+            lending_market_owner_keypair,
             &user_transfer_authority_keypair,
         ],
-        message_3,
-        recent_blockhash,
+    )?;
+    Ok(reserve_keypair.pubkey())
+}
+
+fn command_deposit_and_collateralize(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    ui_amount: f64,
+    obligation_owner_keypair: Keypair,
+    source_liquidity_pubkey: Pubkey,
+    user_collateral_pubkey: Pubkey,
+) -> CommandResult {
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    let obligation_account = config.rpc_client.get_account(&obligation_pubkey)?;
+    let obligation = Obligation::unpack_account(&obligation_account.data)?;
+    let liquidity_amount = ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals);
+    let collateral_amount = current_collateral_exchange_rate(config, reserve.clone())?
+        .liquidity_to_collateral(liquidity_amount)?;
+
+    println!(
+        "Depositing {} liquidity as {} collateral into obligation {}",
+        liquidity_amount, collateral_amount, obligation_pubkey
+    );
+
+    // Depositing liquidity marks the reserve stale, so it is refreshed again
+    // before the collateral is deposited into the obligation
+    let instructions = [
+        refresh_reserve(
+            config.lending_program_id,
+            reserve_pubkey,
+            reserve.liquidity.oracle_pubkey,
+        ),
+        deposit_reserve_liquidity(
+            config.lending_program_id,
+            liquidity_amount,
+            None,
+            source_liquidity_pubkey,
+            user_collateral_pubkey,
+            reserve_pubkey,
+            reserve.liquidity.supply_pubkey,
+            reserve.collateral.mint_pubkey,
+            reserve.lending_market,
+            obligation_owner_keypair.pubkey(),
+        ),
+        refresh_reserve(
+            config.lending_program_id,
+            reserve_pubkey,
+            reserve.liquidity.oracle_pubkey,
+        ),
+        deposit_obligation_collateral(
+            config.lending_program_id,
+            collateral_amount,
+            user_collateral_pubkey,
+            reserve.collateral.supply_pubkey,
+            reserve_pubkey,
+            obligation_pubkey,
+            reserve.lending_market,
+            obligation_owner_keypair.pubkey(),
+            obligation_owner_keypair.pubkey(),
+            obligation
+                .deposits
+                .iter()
+                .map(|collateral| collateral.deposit_reserve)
+                .collect(),
+        ),
+    ];
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, 0, &[&message])?;
+
+    send_transaction(
+        config,
+        message,
+        &[config.fee_payer.as_ref(), &obligation_owner_keypair],
+    )?;
+    Ok(())
+}
+
+fn command_withdraw_and_redeem(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    ui_amount: Option<f64>,
+    obligation_owner_keypair: Keypair,
+    user_collateral_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+) -> CommandResult {
+    let obligation_account = config.rpc_client.get_account(&obligation_pubkey)?;
+    let obligation = Obligation::unpack_account(&obligation_account.data)?;
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+
+    let (collateral, _) = obligation.find_collateral_in_deposits(reserve_pubkey)?;
+    let collateral_amount = if let Some(ui_amount) = ui_amount {
+        let liquidity_amount = ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals);
+        current_collateral_exchange_rate(config, reserve.clone())?
+            .liquidity_to_collateral(liquidity_amount)?
+    } else if obligation.borrows.is_empty() {
+        collateral.deposited_amount
+    } else {
+        return Err(format!(
+            "Obligation {} has outstanding borrows, an amount must be provided instead of ALL",
+            obligation_pubkey
+        )
+        .into());
+    };
+    if collateral_amount > collateral.deposited_amount {
+        return Err(format!(
+            "Obligation {} has {} collateral deposited, {} required",
+            obligation_pubkey, collateral.deposited_amount, collateral_amount
+        )
+        .into());
+    }
+
+    println!(
+        "Withdrawing and redeeming {} collateral from obligation {}",
+        collateral_amount, obligation_pubkey
+    );
+
+    let mut instructions =
+        refresh_obligation_instructions(config, obligation_pubkey, &obligation, &[])?;
+    instructions.push(withdraw_obligation_collateral(
+        config.lending_program_id,
+        collateral_amount,
+        reserve.collateral.supply_pubkey,
+        user_collateral_pubkey,
+        reserve_pubkey,
+        obligation_pubkey,
+        reserve.lending_market,
+        obligation_owner_keypair.pubkey(),
+    ));
+    instructions.push(redeem_reserve_collateral(
+        config.lending_program_id,
+        collateral_amount,
+        None,
+        user_collateral_pubkey,
+        destination_liquidity_pubkey,
+        reserve_pubkey,
+        reserve.collateral.mint_pubkey,
+        reserve.liquidity.supply_pubkey,
+        reserve.lending_market,
+        obligation_owner_keypair.pubkey(),
+    ));
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, 0, &[&message])?;
+
+    send_transaction(
+        config,
+        message,
+        &[config.fee_payer.as_ref(), &obligation_owner_keypair],
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_deposit_and_borrow(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    deposit_reserve_pubkey: Pubkey,
+    collateral_ui_amount: f64,
+    borrow_reserve_pubkey: Pubkey,
+    liquidity_ui_amount: Option<f64>,
+    obligation_owner_keypair: Keypair,
+    user_collateral_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+) -> CommandResult {
+    let obligation_account = config.rpc_client.get_account(&obligation_pubkey)?;
+    let obligation = Obligation::unpack_account(&obligation_account.data)?;
+    let deposit_reserve = get_reserve(config, &deposit_reserve_pubkey)?;
+    let borrow_reserve = get_reserve(config, &borrow_reserve_pubkey)?;
+    let lending_market_account = config.rpc_client.get_account(&obligation.lending_market)?;
+    let lending_market = LendingMarket::unpack_account(lending_market_account.data.borrow())?;
+    let borrow_allowlist_entry_pubkey = lending_market.permissioned_borrowing.then(|| {
+        find_borrow_allowlist_entry_address(
+            &config.lending_program_id,
+            &obligation.lending_market,
+            &obligation_owner_keypair.pubkey(),
+        )
+        .0
+    });
+
+    // Collateral mints use the decimals of their reserve liquidity mint
+    let collateral_amount = ui_amount_to_amount(
+        collateral_ui_amount,
+        deposit_reserve.liquidity.mint_decimals,
+    );
+    let liquidity_amount = match liquidity_ui_amount {
+        Some(ui_amount) => ui_amount_to_amount(ui_amount, borrow_reserve.liquidity.mint_decimals),
+        None => u64::MAX,
+    };
+
+    println!(
+        "Depositing {} collateral into obligation {} and borrowing from reserve {}",
+        collateral_amount, obligation_pubkey, borrow_reserve_pubkey
+    );
+
+    let mut instructions = refresh_obligation_instructions(
+        config,
+        obligation_pubkey,
+        &obligation,
+        &[deposit_reserve_pubkey, borrow_reserve_pubkey],
+    )?;
+    instructions.push(deposit_obligation_collateral_and_borrow(
+        config.lending_program_id,
+        collateral_amount,
+        liquidity_amount,
+        None,
+        user_collateral_pubkey,
+        deposit_reserve.collateral.supply_pubkey,
+        deposit_reserve_pubkey,
+        borrow_reserve.liquidity.supply_pubkey,
+        destination_liquidity_pubkey,
+        borrow_reserve_pubkey,
+        borrow_reserve.liquidity.fee_receiver,
+        obligation_pubkey,
+        obligation.lending_market,
+        obligation_owner_keypair.pubkey(),
+        obligation_owner_keypair.pubkey(),
+        obligation
+            .deposits
+            .iter()
+            .map(|collateral| collateral.deposit_reserve)
+            .collect(),
+        borrow_allowlist_entry_pubkey,
+        None,
+    ));
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, 0, &[&message])?;
+
+    send_transaction(
+        config,
+        message,
+        &[config.fee_payer.as_ref(), &obligation_owner_keypair],
+    )?;
+    Ok(())
+}
+
+fn command_dump_state(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    out_path: &str,
+    include_obligations: bool,
+) -> CommandResult {
+    let snapshot = get_market_snapshot(config, &lending_market_pubkey, include_obligations)?;
+    let file = File::create(out_path)?;
+    serde_json::to_writer_pretty(file, &snapshot)?;
+    let obligations = snapshot
+        .obligations
+        .as_ref()
+        .map(|obligations| format!(", {} obligations", obligations.len()))
+        .unwrap_or_default();
+    println!(
+        "Wrote lending market {}, {} reserves{} at slot {} to {}",
+        lending_market_pubkey,
+        snapshot.reserves.len(),
+        obligations,
+        snapshot.slot,
+        out_path
+    );
+    println!("Checksum: {}", snapshot.checksum);
+    Ok(())
+}
+
+fn command_diff_state(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    against_path: &str,
+) -> CommandResult {
+    let file = File::open(against_path)?;
+    let before: MarketSnapshot = serde_json::from_reader(file)?;
+    before
+        .verify_checksum()
+        .map_err(|err| format!("{}: {}", against_path, err))?;
+    if before.lending_market.address != lending_market_pubkey.to_string() {
+        return Err(format!(
+            "Snapshot {} is of lending market {}, not {}",
+            against_path, before.lending_market.address, lending_market_pubkey
+        )
+        .into());
+    }
+
+    let after = get_market_snapshot(config, &lending_market_pubkey, before.obligations.is_some())?;
+    let diffs = diff_snapshots(&before, &after);
+    if diffs.is_empty() {
+        println!("No changes since slot {}", before.slot);
+    } else {
+        println!("Changes from slot {} to slot {}:", before.slot, after.slot);
+        for diff in diffs {
+            println!("{}", diff);
+        }
+    }
+    Ok(())
+}
+
+fn command_verify_market(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    json: bool,
+) -> CommandResult {
+    let slot = config.rpc_client.get_slot()?;
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_account(lending_market_account.data.borrow())?;
+    let reserves = get_market_reserves(config, &lending_market_pubkey)?;
+
+    // Collateral mints, liquidity supplies and oracles of every reserve, in
+    // that order
+    let account_pubkeys: Vec<Pubkey> = reserves
+        .iter()
+        .flat_map(|(_, reserve)| {
+            [
+                reserve.collateral.mint_pubkey,
+                reserve.liquidity.supply_pubkey,
+                reserve.liquidity.oracle_pubkey,
+            ]
+        })
+        .collect();
+    let accounts = config.rpc_client.get_multiple_accounts(&account_pubkeys)?;
+
+    let mut checks = vec![];
+    for ((reserve_pubkey, reserve), reserve_accounts) in reserves.iter().zip(accounts.chunks(3)) {
+        let reserve_accounts = ReserveAccounts {
+            collateral_mint: reserve_accounts[0]
+                .as_ref()
+                .and_then(|account| Mint::unpack(account.data.borrow()).ok()),
+            liquidity_supply: reserve_accounts[1]
+                .as_ref()
+                .and_then(|account| Token::unpack(account.data.borrow()).ok()),
+            oracle_owner: reserve_accounts[2].as_ref().map(|account| account.owner),
+        };
+        checks.extend(verify_reserve(
+            &lending_market,
+            reserve_pubkey,
+            reserve,
+            &reserve_accounts,
+        ));
+    }
+
+    let verification = MarketVerification {
+        lending_market: lending_market_pubkey.to_string(),
+        slot,
+        checks,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&verification)?);
+    } else {
+        println!(
+            "Verifying lending market {} and {} reserves at slot {}",
+            lending_market_pubkey,
+            reserves.len(),
+            slot
+        );
+        for check in &verification.checks {
+            println!("{}", check);
+        }
+    }
+
+    let failed_count = verification.failed_count();
+    if failed_count > 0 {
+        return Err(format!(
+            "{} of {} checks failed",
+            failed_count,
+            verification.checks.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn command_refresh_reserves(config: &Config, lending_market_pubkey: Pubkey) -> CommandResult {
+    let slot = config.rpc_client.get_slot()?;
+    let mut reserves = get_market_reserves(config, &lending_market_pubkey)?;
+    // Reserves sharing an oracle end up next to each other, and so mostly in
+    // the same transaction
+    reserves.sort_by_key(|(_, reserve)| reserve.liquidity.oracle_pubkey);
+
+    let oracle_pubkeys: Vec<Pubkey> = reserves
+        .iter()
+        .map(|(_, reserve)| reserve.liquidity.oracle_pubkey)
+        .collect();
+    let oracle_accounts = config.rpc_client.get_multiple_accounts(&oracle_pubkeys)?;
+
+    let mut refreshed = vec![];
+    let mut skipped = vec![];
+    for ((reserve_pubkey, reserve), oracle_account) in reserves.iter().zip(&oracle_accounts) {
+        let oracle_data = oracle_account
+            .as_ref()
+            .map(|account| account.data.as_slice());
+        if is_oracle_stale(reserve, oracle_data, slot) {
+            skipped.push((*reserve_pubkey, reserve.liquidity.oracle_pubkey));
+        } else {
+            refreshed.push((*reserve_pubkey, reserve.liquidity.oracle_pubkey));
+        }
+    }
+
+    let batches = batch_refresh_instructions(
+        config.lending_program_id,
+        &refreshed,
+        &config.fee_payer.pubkey(),
+    );
+    for instructions in &batches {
+        let recent_blockhash = get_blockhash(config)?;
+        let message = Message::new_with_blockhash(
+            instructions,
+            Some(&config.fee_payer.pubkey()),
+            &recent_blockhash,
+        );
+        check_fee_payer_balance(config, 0, &[&message])?;
+        send_transaction(config, message, &[config.fee_payer.as_ref()])?;
+    }
+
+    for (reserve_pubkey, oracle_pubkey) in &skipped {
+        println!(
+            "Skipped reserve {}, oracle {} is stale",
+            reserve_pubkey, oracle_pubkey
+        );
+    }
+    println!(
+        "Refreshed {} reserves in {} transactions, skipped {} with stale oracles",
+        refreshed.len(),
+        batches.len(),
+        skipped.len()
+    );
+    Ok(())
+}
+
+fn command_update_reserve_config(
+    config: &Config,
+    reserve_pubkey: Pubkey,
+    reserve: &Reserve,
+    lending_market_owner_keypair: &Keypair,
+    reserve_config: ReserveConfig,
+) -> CommandResult {
+    if reserve.config == reserve_config {
+        println!("Reserve {} config is already up to date", reserve_pubkey);
+        return Ok(());
+    }
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &[modify_reserve_config(
+            config.lending_program_id,
+            reserve_config,
+            reserve_pubkey,
+            reserve.lending_market,
+            lending_market_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, 0, &[&message])?;
+    send_transaction(
+        config,
+        message,
+        &[config.fee_payer.as_ref(), lending_market_owner_keypair],
+    )?;
+
+    println!("Updated reserve {} config", reserve_pubkey);
+    if config.verbose {
+        println!("{:#?}", reserve_config);
+    }
+    Ok(())
+}
+
+fn command_set_reserve_status(
+    config: &Config,
+    reserve_pubkey: Pubkey,
+    lending_market_owner_keypair: &Keypair,
+    status: ReserveStatus,
+) -> CommandResult {
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    if reserve.status == status {
+        println!("Reserve {} is already {:?}", reserve_pubkey, status);
+        return Ok(());
+    }
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &[set_reserve_status(
+            config.lending_program_id,
+            status,
+            reserve_pubkey,
+            reserve.lending_market,
+            lending_market_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, 0, &[&message])?;
+    send_transaction(
+        config,
+        message,
+        &[config.fee_payer.as_ref(), lending_market_owner_keypair],
+    )?;
+
+    println!(
+        "Set reserve {} status from {:?} to {:?}",
+        reserve_pubkey, reserve.status, status
     );
-    send_transaction(config, transaction_3)?;
     Ok(())
 }
 
 // HELPERS
 
-fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
+fn get_reserve(config: &Config, reserve_pubkey: &Pubkey) -> Result<Reserve, Error> {
+    let reserve_account = config.rpc_client.get_account(reserve_pubkey)?;
+    Ok(Reserve::unpack_account(reserve_account.data.borrow())?)
+}
+
+/// Fetches the reserves in as few RPC requests as possible
+fn get_reserves(config: &Config, reserve_pubkeys: &[Pubkey]) -> Result<Vec<Reserve>, Error> {
+    config
+        .rpc_client
+        .get_multiple_accounts(reserve_pubkeys)?
+        .into_iter()
+        .zip(reserve_pubkeys)
+        .map(|(reserve_account, reserve_pubkey)| {
+            let reserve_account =
+                reserve_account.ok_or_else(|| format!("Reserve {} not found", reserve_pubkey))?;
+            Ok(Reserve::unpack_account(reserve_account.data.borrow())?)
+        })
+        .collect()
+}
+
+/// Refreshes every reserve used by the obligation and any additional reserves,
+/// followed by the obligation itself
+fn refresh_obligation_instructions(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    obligation: &Obligation,
+    additional_reserve_pubkeys: &[Pubkey],
+) -> Result<Vec<Instruction>, Error> {
+    // Every reserve used by the obligation must be refreshed before the
+    // obligation itself, deposits first and then borrows
+    let obligation_reserve_pubkeys: Vec<Pubkey> = obligation
+        .deposits
+        .iter()
+        .map(|collateral| collateral.deposit_reserve)
+        .chain(
+            obligation
+                .borrows
+                .iter()
+                .map(|liquidity| liquidity.borrow_reserve),
+        )
+        .collect();
+    let mut refreshed_reserve_pubkeys: Vec<Pubkey> = vec![];
+    for reserve_pubkey in obligation_reserve_pubkeys
+        .iter()
+        .chain(additional_reserve_pubkeys)
+    {
+        if !refreshed_reserve_pubkeys.contains(reserve_pubkey) {
+            refreshed_reserve_pubkeys.push(*reserve_pubkey);
+        }
+    }
+    let mut instructions: Vec<Instruction> = vec![];
+    for (refreshed_reserve_pubkey, refreshed_reserve) in refreshed_reserve_pubkeys
+        .iter()
+        .zip(get_reserves(config, &refreshed_reserve_pubkeys)?)
+    {
+        instructions.push(refresh_reserve(
+            config.lending_program_id,
+            *refreshed_reserve_pubkey,
+            refreshed_reserve.liquidity.oracle_pubkey,
+        ));
+    }
+    instructions.push(refresh_obligation(
+        config.lending_program_id,
+        obligation_pubkey,
+        obligation_reserve_pubkeys,
+    ));
+    Ok(instructions)
+}
+
+/// Fetches the lending market and every reserve that belongs to it
+fn get_market_snapshot(
+    config: &Config,
+    lending_market_pubkey: &Pubkey,
+    include_obligations: bool,
+) -> Result<MarketSnapshot, Error> {
+    let slot = config.rpc_client.get_slot()?;
+    let lending_market_account = config.rpc_client.get_account(lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_account(lending_market_account.data.borrow())?;
+    let reserves = get_market_reserves(config, lending_market_pubkey)?
+        .iter()
+        .map(|(reserve_pubkey, reserve)| (reserve_pubkey.to_string(), ReserveState::from(reserve)))
+        .collect();
+    let obligations = if include_obligations {
+        Some(
+            get_market_obligations(config, lending_market_pubkey)?
+                .iter()
+                .map(|(obligation_pubkey, obligation)| {
+                    (
+                        obligation_pubkey.to_string(),
+                        ObligationState::from(obligation),
+                    )
+                })
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    Ok(MarketSnapshot::new(
+        slot,
+        LendingMarketState::new(lending_market_pubkey, &lending_market),
+        reserves,
+        obligations,
+    ))
+}
+
+/// Fetches every reserve that belongs to the lending market
+fn get_market_reserves(
+    config: &Config,
+    lending_market_pubkey: &Pubkey,
+) -> Result<Vec<(Pubkey, Reserve)>, Error> {
+    // Reserves store their lending market after the version and last update,
+    // in both the V1 and the V2 layout
+    let mut reserve_accounts = vec![];
+    for reserve_len in [RESERVE_V1_LEN, Reserve::LEN] {
+        reserve_accounts.extend(config.rpc_client.get_program_accounts_with_config(
+            &config.lending_program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(reserve_len as u64),
+                    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        10,
+                        lending_market_pubkey.as_ref(),
+                    )),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?);
+    }
+    reserve_accounts
+        .into_iter()
+        .map(|(reserve_pubkey, reserve_account)| {
+            Ok((
+                reserve_pubkey,
+                Reserve::unpack_account(reserve_account.data.borrow())?,
+            ))
+        })
+        .collect()
+}
+
+fn command_migrate_obligation(config: &Config, obligation_pubkey: Pubkey) -> CommandResult {
+    let obligation_account = config.rpc_client.get_account(&obligation_pubkey)?;
+    let obligation = Obligation::unpack_account(&obligation_account.data)?;
+    if obligation.version >= PROGRAM_VERSION {
+        return Err(format!(
+            "Obligation {} is already at version {}",
+            obligation_pubkey, obligation.version
+        )
+        .into());
+    }
+
+    let recent_blockhash = get_blockhash(config)?;
+    let message = Message::new_with_blockhash(
+        &[migrate_obligation(
+            config.lending_program_id,
+            obligation_pubkey,
+            obligation.lending_market,
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, 0, &[&message])?;
+    send_transaction(config, message, &[config.fee_payer.as_ref()])
+}
+
+fn command_migrate_obligations(config: &Config, lending_market_pubkey: Pubkey) -> CommandResult {
+    let instructions: Vec<Instruction> = get_market_obligations(config, &lending_market_pubkey)?
+        .into_iter()
+        .filter(|(_, obligation)| obligation.version < PROGRAM_VERSION)
+        .map(|(obligation_pubkey, _)| {
+            migrate_obligation(
+                config.lending_program_id,
+                obligation_pubkey,
+                lending_market_pubkey,
+            )
+        })
+        .collect();
+
+    let batches: Vec<&[Instruction]> = instructions
+        .chunks(MIGRATE_OBLIGATIONS_PER_TRANSACTION)
+        .collect();
+    for instructions in &batches {
+        let recent_blockhash = get_blockhash(config)?;
+        let message = Message::new_with_blockhash(
+            instructions,
+            Some(&config.fee_payer.pubkey()),
+            &recent_blockhash,
+        );
+        check_fee_payer_balance(config, 0, &[&message])?;
+        send_transaction(config, message, &[config.fee_payer.as_ref()])?;
+    }
+
+    println!(
+        "Migrated {} obligations in {} transactions",
+        instructions.len(),
+        batches.len()
+    );
+    Ok(())
+}
+
+fn command_migrate_accounts(config: &Config, lending_market_pubkey: Pubkey) -> CommandResult {
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let mut legacy_pubkeys = vec![];
+    if lending_market_account.data.len() == LENDING_MARKET_V1_LEN {
+        legacy_pubkeys.push(lending_market_pubkey);
+    }
+    let lending_market_count = legacy_pubkeys.len();
+    legacy_pubkeys.extend(
+        get_market_reserves(config, &lending_market_pubkey)?
+            .into_iter()
+            .filter(|(_, reserve)| Reserve::account_len(reserve.version) == RESERVE_V1_LEN)
+            .map(|(reserve_pubkey, _)| reserve_pubkey),
+    );
+    let reserve_count = legacy_pubkeys.len() - lending_market_count;
+
+    // the fee payer funds the rent of the larger accounts
+    let rent = |len| {
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(len)
+    };
+    let lending_market_rent = rent(LendingMarket::LEN)? - rent(LENDING_MARKET_V1_LEN)?;
+    let reserve_rent = rent(Reserve::LEN)? - rent(RESERVE_V1_LEN)?;
+    let additional_rent =
+        lending_market_count as u64 * lending_market_rent + reserve_count as u64 * reserve_rent;
+
+    let instructions: Vec<Instruction> = legacy_pubkeys
+        .into_iter()
+        .map(|account_pubkey| {
+            migrate_account(
+                config.lending_program_id,
+                account_pubkey,
+                config.fee_payer.pubkey(),
+            )
+        })
+        .collect();
+    let batches: Vec<&[Instruction]> = instructions
+        .chunks(MIGRATE_ACCOUNTS_PER_TRANSACTION)
+        .collect();
+    let mut messages = vec![];
+    for instructions in &batches {
+        let recent_blockhash = get_blockhash(config)?;
+        messages.push(Message::new_with_blockhash(
+            instructions,
+            Some(&config.fee_payer.pubkey()),
+            &recent_blockhash,
+        ));
+    }
+    check_fee_payer_balance(
+        config,
+        additional_rent,
+        &messages.iter().collect::<Vec<_>>(),
+    )?;
+    for message in messages {
+        send_transaction(config, message, &[config.fee_payer.as_ref()])?;
+    }
+
+    println!(
+        "Migrated {} lending market and {} reserves in {} transactions",
+        lending_market_count,
+        reserve_count,
+        batches.len()
+    );
+    Ok(())
+}
+
+/// Fetches every obligation that belongs to the lending market, including
+/// resized obligations
+fn get_market_obligations(
+    config: &Config,
+    lending_market_pubkey: &Pubkey,
+) -> Result<Vec<(Pubkey, Obligation)>, Error> {
+    // Obligations store their lending market at the same offset as reserves,
+    // they are told apart by their account size
+    let accounts = config.rpc_client.get_program_accounts_with_config(
+        &config.lending_program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                10,
+                lending_market_pubkey.as_ref(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+    accounts
+        .into_iter()
+        .filter(|(_, account)| {
+            let account_len = account.data.len();
+            account_len >= Obligation::LEN
+                && Obligation::account_len(Obligation::max_reserves(account_len)) == account_len
+        })
+        .map(|(obligation_pubkey, obligation_account)| {
+            Ok((
+                obligation_pubkey,
+                Obligation::unpack_account(&obligation_account.data)?,
+            ))
+        })
+        .collect()
+}
+
+/// Exchange rate the reserve would use if refreshed in the current slot
+fn current_collateral_exchange_rate(
+    config: &Config,
+    mut reserve: Reserve,
+) -> Result<CollateralExchangeRate, Error> {
+    let current_slot = config.rpc_client.get_slot()?;
+    reserve.accrue_interest(current_slot)?;
+    Ok(reserve.collateral_exchange_rate()?)
+}
+
+/// Checks that the fee payer can cover the given balance plus the fees of the
+/// messages. Skipped when not sending, the fee payer may not be funded yet when
+/// signing offline.
+fn check_fee_payer_balance(
+    config: &Config,
+    balance_without_fees: u64,
+    messages: &[&Message],
+) -> Result<(), Error> {
+    if config.estimate_only || config.sign_only {
+        return Ok(());
+    }
+    let mut required_balance = balance_without_fees;
+    for message in messages {
+        required_balance += config.rpc_client.get_fee_for_message(message)?;
+    }
     let balance = config.rpc_client.get_balance(&config.fee_payer.pubkey())?;
     if balance < required_balance {
         Err(format!(
@@ -708,22 +2326,245 @@ fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(),
     }
 }
 
-fn send_transaction(
-    config: &Config,
-    transaction: Transaction,
-) -> solana_client::client_error::Result<()> {
+/// Blockhash for new transactions, the `--blockhash` value if given
+fn get_blockhash(config: &Config) -> Result<Hash, Error> {
+    match config.blockhash {
+        Some(blockhash) => Ok(blockhash),
+        None => Ok(config.rpc_client.get_latest_blockhash()?),
+    }
+}
+
+fn send_transaction(config: &Config, message: Message, signers: &[&dyn Signer]) -> CommandResult {
+    if config.estimate_only {
+        return estimate_fee(config, message);
+    }
+    if config.sign_only {
+        return print_sign_only(config, message, signers);
+    }
+
+    let recent_blockhash = message.recent_blockhash;
+    let transaction = Transaction::new(signers, message, recent_blockhash);
     if config.dry_run {
-        let result = config.rpc_client.simulate_transaction(&transaction)?;
-        println!("Simulate result: {:?}", result);
+        simulate_transaction(config, &transaction)?;
     } else {
         let signature = config
             .rpc_client
-            .send_and_confirm_transaction_with_spinner(&transaction)?;
+            .send_and_confirm_transaction_with_spinner(&transaction)
+            .map_err(|err| match err.get_transaction_error() {
+                Some(transaction_error) => explain_error(
+                    config,
+                    &transaction.message,
+                    &transaction_error,
+                    err.to_string(),
+                ),
+                None => err.into(),
+            })?;
         println!("Signature: {}", signature);
     }
     Ok(())
 }
 
+/// Signs the transaction with the signers available locally and prints the
+/// signatures, missing signers and, with `--dump-transaction-message`, the
+/// base64 encoded message, as the Solana CLI does in sign-only mode
+fn print_sign_only(config: &Config, message: Message, signers: &[&dyn Signer]) -> CommandResult {
+    let recent_blockhash = message.recent_blockhash;
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.try_partial_sign(signers, recent_blockhash)?;
+    println!(
+        "{}",
+        return_signers_with_config(
+            &transaction,
+            &OutputFormat::Display,
+            &ReturnSignersConfig {
+                dump_transaction_message: config.dump_transaction_message,
+            },
+        )?
+    );
+    Ok(())
+}
+
+/// Simulates the signed transaction as the real send would execute it. The
+/// blockhash is not replaced, the RPC doesn't allow it together with signature
+/// verification, so a stale blockhash fails the simulation too.
+fn simulate_transaction(config: &Config, transaction: &Transaction) -> CommandResult {
+    let message = &transaction.message;
+    let writable_addresses = message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| message.is_writable(*i))
+        .map(|(_, address)| address.to_string())
+        .collect::<Vec<_>>();
+
+    let result = config
+        .rpc_client
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: true,
+                accounts: config
+                    .verbose
+                    .then(|| RpcSimulateTransactionAccountsConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        addresses: writable_addresses.clone(),
+                    }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+
+    match &result.err {
+        Some(err) => println!("Simulate result: {}", err),
+        None => println!("Simulate result: success"),
+    }
+    if let Some(units_consumed) = result.units_consumed {
+        println!("Compute units consumed: {}", units_consumed);
+    }
+    for log in result.logs.unwrap_or_default() {
+        println!("  {}", log);
+    }
+    if let Some(accounts) = result.accounts {
+        for (address, account) in writable_addresses.iter().zip(accounts) {
+            match account {
+                Some(account) => {
+                    let data = match account.data {
+                        UiAccountData::Binary(data, _) => data,
+                        data => format!("{:?}", data),
+                    };
+                    println!(
+                        "Account {}: {} lamports, owner {}, data {}",
+                        address, account.lamports, account.owner, data
+                    );
+                }
+                None => println!("Account {}: closed", address),
+            }
+        }
+    }
+
+    match result.err {
+        Some(err) => Err(explain_error(config, message, &err, err.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Appends the variant name, description and suggested fix to errors raised by
+/// the lending program, other errors are returned unchanged
+fn explain_error(
+    config: &Config,
+    message: &Message,
+    transaction_error: &TransactionError,
+    description: String,
+) -> Error {
+    match explain_transaction_error(&config.lending_program_id, message, transaction_error) {
+        Some(explanation) => format!("{}\n{}", description, explanation).into(),
+        None => description.into(),
+    }
+}
+
+/// Adds the fee of the transaction to the running estimate without signing
+/// it. The priority fee is priced from the compute units consumed in
+/// simulation.
+fn estimate_fee(config: &Config, message: Message) -> CommandResult {
+    let mut fee = config.rpc_client.get_fee_for_message(&message)?;
+    if let Some(compute_unit_price) = config.priority_fee {
+        let transaction = Transaction::new_unsigned(message);
+        let result = config.rpc_client.simulate_transaction(&transaction)?.value;
+        if let Some(err) = result.err {
+            println!(
+                "Simulation failed: {}, the priority fee only covers the compute units consumed before the failure",
+                err
+            );
+            if let Some(explanation) =
+                explain_transaction_error(&config.lending_program_id, &transaction.message, &err)
+            {
+                println!("{}", explanation);
+            }
+        }
+        let units_consumed = result.units_consumed.unwrap_or_default();
+        let priority_fee = (units_consumed as u128 * compute_unit_price as u128)
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+        fee += u64::try_from(priority_fee)?;
+    }
+
+    if config.verbose {
+        println!("Transaction fee: {}", Sol(fee));
+    }
+    config.estimated_fee.set(config.estimated_fee.get() + fee);
+    Ok(())
+}
+
+fn market_owner_of(
+    matches: &ArgMatches<'_>,
+    name: &str,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Result<MarketOwner, Error> {
+    let value = matches.value_of(name).unwrap();
+    if let Ok(pubkey) = Pubkey::from_str(value) {
+        Ok(MarketOwner::Pubkey(pubkey))
+    } else {
+        Ok(MarketOwner::Signer(signer_from_path(
+            matches,
+            value,
+            name,
+            wallet_manager,
+        )?))
+    }
+}
+
+fn cluster_profile_of(matches: &ArgMatches<'_>) -> Result<Option<(String, ClusterProfile)>, Error> {
+    let cluster = match matches.value_of("cluster") {
+        Some(cluster) => cluster,
+        None => return Ok(None),
+    };
+    let config_file = matches
+        .value_of("config_file")
+        .ok_or("A configuration file is required to select a cluster profile")?;
+    let mut lending_cli_config: LendingCliConfig =
+        solana_cli_config::load_config_file(config_file)?;
+    let profile = lending_cli_config
+        .clusters
+        .remove(cluster)
+        .ok_or_else(|| format!("Cluster profile {} not found in {}", cluster, config_file))?;
+    Ok(Some((cluster.to_string(), profile)))
+}
+
+/// Checks `--register-as` before any transaction is sent
+fn check_register_as(registry: Option<&NameRegistry>, name: Option<&str>) -> CommandResult {
+    match (registry, name) {
+        (None, Some(_)) => Err("--register-as requires a --registry file".into()),
+        (Some(registry), Some(name)) => match registry.get(name)? {
+            Some(address) => {
+                Err(format!("Name {} is already registered to {}", name, address).into())
+            }
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Registers the created account, unless its transactions were only
+/// simulated, estimated or signed
+fn register_address(
+    config: &Config,
+    registry: Option<&mut NameRegistry>,
+    name: Option<&str>,
+    address: &Pubkey,
+) -> CommandResult {
+    if config.dry_run || config.estimate_only || config.sign_only {
+        return Ok(());
+    }
+    match (registry, name) {
+        (Some(registry), Some(name)) => registry.register(name, address),
+        _ => Ok(()),
+    }
+}
+
+fn reserves_file_of(path: &str) -> Result<Vec<ReserveFileEntry>, Error> {
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
 fn quote_currency_of(matches: &ArgMatches<'_>, name: &str) -> Option<[u8; 32]> {
     if let Some(value) = matches.value_of(name) {
         if value == "USD" {
@@ -0,0 +1,121 @@
+//! Reserve refresh batching used by `refresh-reserves`
+
+use {
+    solana_program::pubkey::Pubkey,
+    solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::Message,
+        packet::PACKET_DATA_SIZE,
+    },
+    spl_token_lending::{
+        instruction::refresh_reserve,
+        pyth,
+        state::{OracleKind, Reserve},
+        switchboard,
+    },
+    std::mem::size_of,
+};
+
+/// Compute units requested for each RefreshReserve instruction, enough for
+/// interest accrual and either oracle
+pub const REFRESH_RESERVE_COMPUTE_UNITS: u32 = 30_000;
+
+/// Most compute units a single transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Slot the oracle's current price was published in, `None` when the account
+/// isn't a price of the reserve's oracle kind
+fn oracle_price_slot(oracle_kind: OracleKind, oracle_data: &[u8]) -> Option<u64> {
+    match oracle_kind {
+        OracleKind::Pyth => {
+            if oracle_data.len() < size_of::<pyth::Price>() {
+                return None;
+            }
+            let price = pyth::load::<pyth::Price>(oracle_data).ok()?;
+            Some(price.valid_slot)
+        }
+        OracleKind::Switchboard => {
+            if oracle_data.len() < size_of::<switchboard::Aggregator>() {
+                return None;
+            }
+            let aggregator = switchboard::load::<switchboard::Aggregator>(oracle_data).ok()?;
+            if aggregator.discriminator != switchboard::AGGREGATOR_DISCRIMINATOR
+                || aggregator.latest_confirmed_round.num_success == 0
+            {
+                return None;
+            }
+            Some(aggregator.latest_confirmed_round.round_open_slot)
+        }
+    }
+}
+
+/// Whether RefreshReserve would reject the reserve's oracle price as stale at
+/// the given slot. Missing or unreadable oracle accounts count as stale.
+pub fn is_oracle_stale(reserve: &Reserve, oracle_data: Option<&[u8]>, slot: u64) -> bool {
+    let price_slot =
+        match oracle_data.and_then(|data| oracle_price_slot(reserve.liquidity.oracle_kind, data)) {
+            Some(price_slot) => price_slot,
+            None => return true,
+        };
+    let stale_after_slots_elapsed = match (
+        reserve.config.max_oracle_age_slots,
+        reserve.liquidity.oracle_kind,
+    ) {
+        (0, OracleKind::Pyth) => pyth::STALE_AFTER_SLOTS_ELAPSED,
+        (0, OracleKind::Switchboard) => switchboard::STALE_AFTER_SLOTS_ELAPSED,
+        (max_oracle_age_slots, _) => max_oracle_age_slots,
+    };
+    slot.saturating_sub(price_slot) >= stale_after_slots_elapsed
+}
+
+/// Prepends a compute unit limit covering the refreshes, well under the
+/// default of 200k units per instruction, which lets more refreshes share a
+/// block with other transactions
+fn with_compute_unit_limit(refresh_instructions: Vec<Instruction>) -> Vec<Instruction> {
+    let compute_units = refresh_instructions.len() as u32 * REFRESH_RESERVE_COMPUTE_UNITS;
+    let mut instructions = Vec::with_capacity(refresh_instructions.len() + 1);
+    instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_units,
+    ));
+    instructions.extend(refresh_instructions);
+    instructions
+}
+
+/// Whether a transaction of the instructions, signed by the fee payer alone,
+/// fits in a single packet
+fn fits_in_packet(instructions: &[Instruction], fee_payer: &Pubkey) -> bool {
+    let message = Message::new(instructions, Some(fee_payer));
+    // compact-u16 signature count followed by the signatures
+    let signatures_len = 1 + 64 * message.header.num_required_signatures as usize;
+    signatures_len + message.serialize().len() <= PACKET_DATA_SIZE
+}
+
+/// Splits RefreshReserve instructions for the `(reserve, oracle)` pairs into as
+/// few transactions as fit the packet size and compute unit limit, keeping the
+/// given order
+pub fn batch_refresh_instructions(
+    program_id: Pubkey,
+    reserves: &[(Pubkey, Pubkey)],
+    fee_payer: &Pubkey,
+) -> Vec<Vec<Instruction>> {
+    let max_refreshes_per_transaction = MAX_COMPUTE_UNIT_LIMIT / REFRESH_RESERVE_COMPUTE_UNITS;
+
+    let mut batches = vec![];
+    let mut batch: Vec<Instruction> = vec![];
+    for (reserve_pubkey, oracle_pubkey) in reserves {
+        let instruction = refresh_reserve(program_id, *reserve_pubkey, *oracle_pubkey);
+        if !batch.is_empty() {
+            let mut candidate = batch.clone();
+            candidate.push(instruction.clone());
+            if candidate.len() as u32 > max_refreshes_per_transaction
+                || !fits_in_packet(&with_compute_unit_limit(candidate), fee_payer)
+            {
+                batches.push(with_compute_unit_limit(std::mem::take(&mut batch)));
+            }
+        }
+        batch.push(instruction);
+    }
+    if !batch.is_empty() {
+        batches.push(with_compute_unit_limit(batch));
+    }
+    batches
+}
@@ -0,0 +1,207 @@
+//! Local name registry used to pass reserves and markets by name, e.g. `usdc`
+
+use {
+    crate::Error,
+    solana_program::pubkey::Pubkey,
+    std::{
+        collections::BTreeMap,
+        fs::{self, File},
+        io::ErrorKind,
+        path::PathBuf,
+        str::FromStr,
+    },
+};
+
+/// Names mapped to addresses, stored as a JSON object in the `--registry`
+/// file
+pub struct NameRegistry {
+    path: PathBuf,
+    names: BTreeMap<String, String>,
+}
+
+impl NameRegistry {
+    /// Loads the registry, a missing file is an empty registry that
+    /// `--register-as` creates
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let names = match File::open(path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|err| format!("Invalid registry {}: {}", path, err))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: PathBuf::from(path),
+            names,
+        })
+    }
+
+    /// Address registered under the name
+    pub fn get(&self, name: &str) -> Result<Option<Pubkey>, Error> {
+        self.names
+            .get(name)
+            .map(|address| {
+                Pubkey::from_str(address).map_err(|_| {
+                    format!(
+                        "Name {} is registered to an invalid address {} in {}",
+                        name,
+                        address,
+                        self.path.display()
+                    )
+                    .into()
+                })
+            })
+            .transpose()
+    }
+
+    /// Registers the address under the name and writes the registry back.
+    /// Re-registering a name is only allowed for the same address.
+    pub fn register(&mut self, name: &str, address: &Pubkey) -> Result<(), Error> {
+        is_registry_name(name)?;
+        if let Some(registered_address) = self.get(name)? {
+            if registered_address == *address {
+                return Ok(());
+            }
+            return Err(format!(
+                "Name {} is already registered to {} in {}",
+                name,
+                registered_address,
+                self.path.display()
+            )
+            .into());
+        }
+
+        self.names.insert(name.to_string(), address.to_string());
+        fs::write(&self.path, serde_json::to_string_pretty(&self.names)?)?;
+        println!("Registered {} as {}", address, name);
+        Ok(())
+    }
+}
+
+/// Names are short identifiers that can never be mistaken for an address
+pub fn is_registry_name<T: AsRef<str>>(name: T) -> Result<(), String> {
+    let name = name.as_ref();
+    if Pubkey::from_str(name).is_ok() {
+        return Err(format!(
+            "{} is an address and can't be used as a name",
+            name
+        ));
+    }
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(format!(
+            "{} is not a valid name, use letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Validator for arguments taking either an address or a registered name
+pub fn is_address_or_name<T: AsRef<str>>(value: T) -> Result<(), String> {
+    let value = value.as_ref();
+    if Pubkey::from_str(value).is_ok() {
+        return Ok(());
+    }
+    is_registry_name(value).map_err(|_| format!("{} is neither an address nor a name", value))
+}
+
+/// Resolves an address or a name registered in the registry. Values that
+/// parse as an address are always taken as one.
+pub fn resolve_address(registry: Option<&NameRegistry>, value: &str) -> Result<Pubkey, Error> {
+    if let Ok(address) = Pubkey::from_str(value) {
+        return Ok(address);
+    }
+    let registry = registry
+        .ok_or_else(|| format!("{} is not an address, pass --registry to use names", value))?;
+    registry.get(value)?.ok_or_else(|| {
+        format!(
+            "{} is not an address nor a name registered in {}",
+            value,
+            registry.path.display()
+        )
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn registry(names: &[(&str, Pubkey)]) -> NameRegistry {
+        NameRegistry {
+            path: PathBuf::from("registry.json"),
+            names: names
+                .iter()
+                .map(|(name, address)| (name.to_string(), address.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn resolve_address_or_name() {
+        let usdc = Pubkey::new_unique();
+        let registry = registry(&[("usdc", usdc)]);
+
+        assert_eq!(resolve_address(Some(&registry), "usdc").unwrap(), usdc);
+
+        let address = Pubkey::new_unique();
+        assert_eq!(
+            resolve_address(Some(&registry), &address.to_string()).unwrap(),
+            address
+        );
+        assert_eq!(
+            resolve_address(None, &address.to_string()).unwrap(),
+            address
+        );
+
+        assert!(resolve_address(Some(&registry), "sol").is_err());
+        assert!(resolve_address(None, "usdc").is_err());
+    }
+
+    #[test]
+    fn address_takes_precedence_over_name() {
+        let address = Pubkey::new_unique();
+        // a registry edited by hand can hold an address as a name
+        let registry = registry(&[(&address.to_string(), Pubkey::new_unique())]);
+
+        assert_eq!(
+            resolve_address(Some(&registry), &address.to_string()).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn registry_names() {
+        assert!(is_registry_name("usdc").is_ok());
+        assert!(is_registry_name("msol-v2.main_market").is_ok());
+        assert!(is_registry_name("").is_err());
+        assert!(is_registry_name("usd coin").is_err());
+        assert!(is_registry_name(Pubkey::new_unique().to_string()).is_err());
+
+        assert!(is_address_or_name("usdc").is_ok());
+        assert!(is_address_or_name(Pubkey::new_unique().to_string()).is_ok());
+        assert!(is_address_or_name("usd/c").is_err());
+    }
+
+    #[test]
+    fn register_name() {
+        let path = std::env::temp_dir().join(format!("registry-{}.json", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+
+        let usdc = Pubkey::new_unique();
+        let mut registry = NameRegistry::load(path).unwrap();
+        registry.register("usdc", &usdc).unwrap();
+        registry.register("usdc", &usdc).unwrap();
+        assert!(registry.register("usdc", &Pubkey::new_unique()).is_err());
+        assert!(registry
+            .register(&Pubkey::new_unique().to_string(), &usdc)
+            .is_err());
+
+        let registry = NameRegistry::load(path).unwrap();
+        assert_eq!(registry.get("usdc").unwrap(), Some(usdc));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,615 @@
+//! Reserve config flags shared by the reserve commands, and the named presets
+//! they can start from
+
+use {
+    crate::{lending_error::explain_lending_error, Error},
+    clap::{Arg, ArgMatches},
+    solana_clap_utils::{input_parsers::value_of, input_validators::is_parsable},
+    solana_program::program_error::ProgramError,
+    spl_token_lending::{
+        math::WAD,
+        state::{ReserveConfig, ReserveFees},
+    },
+    std::{fmt::Debug, str::FromStr},
+};
+
+/// Names accepted by `--preset`
+pub const RESERVE_PRESETS: &[&str] = &["stablecoin", "bluechip", "volatile"];
+
+/// Reserve config of the flag defaults
+fn default_reserve_config() -> ReserveConfig {
+    ReserveConfig {
+        optimal_utilization_rate: 80,
+        loan_to_value_ratio: 50,
+        collateral_weight: 100,
+        liquidation_bonus: 5,
+        min_liquidation_bonus: 0,
+        max_liquidation_bonus: 5,
+        liquidation_threshold: 55,
+        stressed_liquidation_threshold: 0,
+        stress_utilization_rate: 0,
+        min_borrow_rate: 0,
+        optimal_borrow_rate: 4,
+        max_borrow_rate: 30,
+        max_utilization_rate: 0,
+        super_max_borrow_rate: 0,
+        max_withdraw_utilization: 100,
+        borrow_rate_rebate: 0,
+        borrow_rate_floor: 0,
+        borrow_limit: u64::MAX,
+        deposit_limit: u64::MAX,
+        max_oracle_age_slots: 0,
+        borrow_weight_wad: WAD,
+        protocol_liquidation_fee: 0,
+        liquidation_close_factor: 50,
+        liquidation_dust_threshold: 0,
+        min_deposit_amount: 0,
+        max_confidence_interval_bps: 0,
+        outflow_window_duration_slots: 0,
+        max_outflow: 0,
+        max_accrual_slots_per_refresh: 0,
+        rate_history_interval_slots: 0,
+        isolated: false,
+        borrowable_in_isolation: false,
+        fees: ReserveFees {
+            borrow_fee_wad: (0.00001 * WAD as f64) as u64,
+            flash_loan_fee_wad: (0.3 * WAD as f64) as u64,
+            host_fee_percentage: 20,
+        },
+    }
+}
+
+/// Reserve config the named preset expands to, the flag defaults for the
+/// values it doesn't set
+pub fn reserve_preset(name: &str) -> Result<ReserveConfig, Error> {
+    let default_config = default_reserve_config();
+    match name {
+        // Pegged assets, borrowed up to a high utilization with a thin margin
+        // between the loan to value ratio and the liquidation threshold
+        "stablecoin" => Ok(ReserveConfig {
+            optimal_utilization_rate: 90,
+            loan_to_value_ratio: 80,
+            liquidation_threshold: 85,
+            liquidation_bonus: 2,
+            max_liquidation_bonus: 4,
+            optimal_borrow_rate: 6,
+            max_borrow_rate: 50,
+            ..default_config
+        }),
+        // Liquid majors such as SOL, BTC or ETH
+        "bluechip" => Ok(ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 65,
+            liquidation_threshold: 75,
+            liquidation_bonus: 5,
+            max_liquidation_bonus: 8,
+            optimal_borrow_rate: 6,
+            max_borrow_rate: 80,
+            ..default_config
+        }),
+        // Long tail assets, with a wide liquidation margin, a steep borrow
+        // rate curve and borrows weighted up against borrowing power
+        "volatile" => Ok(ReserveConfig {
+            optimal_utilization_rate: 60,
+            loan_to_value_ratio: 40,
+            liquidation_threshold: 55,
+            liquidation_bonus: 10,
+            max_liquidation_bonus: 15,
+            optimal_borrow_rate: 10,
+            max_borrow_rate: 150,
+            borrow_weight_wad: WAD + WAD / 2,
+            max_confidence_interval_bps: 200,
+            ..default_config
+        }),
+        _ => Err(format!(
+            "Unknown reserve preset {}, expected one of: {}",
+            name,
+            RESERVE_PRESETS.join(", ")
+        )
+        .into()),
+    }
+}
+
+/// `--preset` and reserve config flags, in the order `add-reserve` lists them
+pub fn reserve_config_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("preset")
+            .long("preset")
+            .value_name("NAME")
+            .takes_value(true)
+            .possible_values(RESERVE_PRESETS)
+            .help("Reserve config to start from, the config flags passed explicitly override its values"),
+        Arg::with_name("optimal_utilization_rate")
+            .long("optimal-utilization-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("80")
+            .help("Optimal utilization rate: [0, 100]"),
+        Arg::with_name("loan_to_value_ratio")
+            .long("loan-to-value-ratio")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("50")
+            .help("Target ratio of the value of borrows to deposits: [0, 100)"),
+        Arg::with_name("collateral_weight")
+            .long("collateral-weight")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("100")
+            .help("Share of the deposit value counted toward borrowing power, before the loan to value ratio: [0, 100], 0 counts the full value"),
+        Arg::with_name("liquidation_bonus")
+            .long("liquidation-bonus")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("5")
+            .help("Bonus a liquidator gets when repaying part of an unhealthy obligation: [0, 100]"),
+        Arg::with_name("min_liquidation_bonus")
+            .long("min-liquidation-bonus")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Bonus a liquidator gets at the unhealthy threshold, 0 to use the liquidation bonus: [0, max liquidation bonus]"),
+        Arg::with_name("max_liquidation_bonus")
+            .long("max-liquidation-bonus")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("5")
+            .help("Bonus a liquidator gets once an obligation's borrows reach its deposits: [liquidation bonus, 100]"),
+        Arg::with_name("liquidation_threshold")
+            .long("liquidation-threshold")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("55")
+            .help("Loan to value ratio at which an obligation can be liquidated: (LTV, 100]"),
+        Arg::with_name("stressed_liquidation_threshold")
+            .long("stressed-liquidation-threshold")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Liquidation threshold at full utilization, 0 to disable: (LTV, liquidation threshold]"),
+        Arg::with_name("stress_utilization_rate")
+            .long("stress-utilization-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Utilization rate above which the liquidation threshold tightens: [0, 100)"),
+        Arg::with_name("min_borrow_rate")
+            .long("min-borrow-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Min borrow APY: min <= optimal <= max"),
+        Arg::with_name("optimal_borrow_rate")
+            .long("optimal-borrow-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("4")
+            .help("Optimal (utilization) borrow APY: min <= optimal <= max"),
+        Arg::with_name("max_borrow_rate")
+            .long("max-borrow-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("30")
+            .help("Max borrow APY: min <= optimal <= max"),
+        Arg::with_name("max_utilization_rate")
+            .long("max-utilization-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Utilization rate at which the max borrow APY is reached: [optimal, 100], 0 to reach it at full utilization"),
+        Arg::with_name("super_max_borrow_rate")
+            .long("super-max-borrow-rate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Borrow APY at full utilization with a max utilization rate: max <= super max, 0 without one"),
+        Arg::with_name("max_withdraw_utilization")
+            .long("max-withdraw-utilization")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("100")
+            .help("Utilization rate above which collateral can no longer be redeemed, 0 to disable: [0, 100]"),
+        Arg::with_name("borrow_rate_rebate")
+            .long("borrow-rate-rebate")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Borrow APY subtracted from the utilization curve, which can make the rate negative: [0, 100]"),
+        Arg::with_name("borrow_rate_floor")
+            .long("borrow-rate-floor")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Lowest negative borrow APY once the rebate is applied: [0, 100]"),
+        Arg::with_name("borrow_limit")
+            .long("borrow-limit")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("18446744073709551615")
+            .help("Maximum total borrows from the reserve, in the smallest liquidity unit, u64::MAX to disable, 0 to stop new borrows"),
+        Arg::with_name("deposit_limit")
+            .long("deposit-limit")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("18446744073709551615")
+            .help("Maximum total liquidity supplied to the reserve, in the smallest liquidity unit, u64::MAX to disable, 0 to stop new deposits"),
+        Arg::with_name("max_oracle_age_slots")
+            .long("max-oracle-age-slots")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Slots after which the oracle price is rejected as stale: [0, 9000], 0 for the oracle default"),
+        Arg::with_name("borrow_weight")
+            .long("borrow-weight")
+            .validator(is_parsable::<f64>)
+            .value_name("DECIMAL")
+            .takes_value(true)
+            .required(true)
+            .default_value("1")
+            .help("Multiplier applied to the value of borrows when checking borrowing power: [1, ...)"),
+        Arg::with_name("protocol_liquidation_fee")
+            .long("protocol-liquidation-fee")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Share of the liquidation bonus paid to the collateral fee receiver: [0, 100]"),
+        Arg::with_name("liquidation_close_factor")
+            .long("liquidation-close-factor")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("50")
+            .help("Share of an obligation's borrowed value repaid in a single liquidation: [1, 100]"),
+        Arg::with_name("liquidation_dust_threshold")
+            .long("liquidation-dust-threshold")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Remaining borrow, in the smallest liquidity unit, below which a liquidation can repay it in full, 0 to disable"),
+        Arg::with_name("min_deposit_amount")
+            .long("min-deposit-amount")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Smallest liquidity deposit, in the smallest liquidity unit, accepted by the reserve"),
+        Arg::with_name("max_confidence_interval_bps")
+            .long("max-confidence-interval-bps")
+            .validator(is_parsable::<u16>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Widest oracle confidence interval accepted, as basis points of the price: [0, 10000], 0 to disable"),
+        Arg::with_name("outflow_window_duration_slots")
+            .long("outflow-window-duration-slots")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Length in slots of the outflow rate limit window, 0 to disable the rate limit"),
+        Arg::with_name("max_outflow")
+            .long("max-outflow")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Most liquidity, in the smallest liquidity unit, redeemed, borrowed or withdrawn per outflow window"),
+        Arg::with_name("max_accrual_slots_per_refresh")
+            .long("max-accrual-slots-per-refresh")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Most slots of interest accrued by a single reserve refresh, 0 to disable the cap"),
+        Arg::with_name("rate_history_interval_slots")
+            .long("rate-history-interval-slots")
+            .validator(is_parsable::<u64>)
+            .value_name("INTEGER")
+            .takes_value(true)
+            .required(true)
+            .default_value("0")
+            .help("Slots between the utilization and borrow rate samples kept by reserve refreshes, 0 to disable the rate history"),
+        Arg::with_name("isolated")
+            .long("isolated")
+            .takes_value(false)
+            .help("Keep collateral from the reserve out of obligations holding other collateral"),
+        Arg::with_name("borrowable_in_isolation")
+            .long("borrowable-in-isolation")
+            .takes_value(false)
+            .help("Allow borrowing from the reserve against isolated collateral"),
+        Arg::with_name("borrow_fee")
+            .long("borrow-fee")
+            .validator(is_parsable::<f64>)
+            .value_name("DECIMAL_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("0.00001")
+            .help("Fee assessed on borrow, expressed as a percentage: [0, 1)"),
+        Arg::with_name("flash_loan_fee")
+            .long("flash-loan-fee")
+            .validator(is_parsable::<f64>)
+            .value_name("DECIMAL_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value(".3")
+            .help("Fee assessed for flash loans, expressed as a percentage: [0, 1)"),
+        Arg::with_name("host_fee_percentage")
+            .long("host-fee-percentage")
+            .validator(is_parsable::<u8>)
+            .value_name("INTEGER_PERCENT")
+            .takes_value(true)
+            .required(true)
+            .default_value("20")
+            .help("Amount of fee going to host account: [0, 100]"),
+    ]
+}
+
+/// Reserve config flags of a command, the flags passed explicitly override
+/// the values of the starting config
+struct ReserveConfigFlags<'a, 'b> {
+    matches: &'a ArgMatches<'b>,
+    /// Config the flags start from, the flag defaults apply without one
+    base: Option<ReserveConfig>,
+}
+
+impl ReserveConfigFlags<'_, '_> {
+    fn is_explicit(&self, name: &str) -> bool {
+        self.matches.occurrences_of(name) > 0
+    }
+
+    fn value<T>(&self, name: &str, base_value: impl FnOnce(&ReserveConfig) -> T) -> T
+    where
+        T: FromStr,
+        <T as FromStr>::Err: Debug,
+    {
+        match &self.base {
+            Some(base) if !self.is_explicit(name) => base_value(base),
+            _ => value_of(self.matches, name).unwrap(),
+        }
+    }
+
+    fn wad_value(&self, name: &str, base_value: impl FnOnce(&ReserveConfig) -> u64) -> u64 {
+        match &self.base {
+            Some(base) if !self.is_explicit(name) => base_value(base),
+            _ => (value_of::<f64>(self.matches, name).unwrap() * WAD as f64) as u64,
+        }
+    }
+
+    fn is_present(&self, name: &str, base_value: impl FnOnce(&ReserveConfig) -> bool) -> bool {
+        match &self.base {
+            Some(base) if !self.is_explicit(name) => base_value(base),
+            _ => self.matches.is_present(name),
+        }
+    }
+}
+
+/// Reserve config of the `reserve_config_args` flags, starting from the
+/// `--preset` when given, or else from the `base` config. Without either the
+/// flag defaults apply. The config must pass the program's validation.
+pub fn reserve_config_of(
+    matches: &ArgMatches<'_>,
+    base: Option<ReserveConfig>,
+) -> Result<ReserveConfig, Error> {
+    let base = match matches.value_of("preset") {
+        Some(name) => Some(reserve_preset(name)?),
+        None => base,
+    };
+    let flags = ReserveConfigFlags { matches, base };
+
+    let reserve_config = ReserveConfig {
+        optimal_utilization_rate: flags.value("optimal_utilization_rate", |base| {
+            base.optimal_utilization_rate
+        }),
+        loan_to_value_ratio: flags.value("loan_to_value_ratio", |base| base.loan_to_value_ratio),
+        collateral_weight: flags.value("collateral_weight", |base| base.collateral_weight),
+        liquidation_bonus: flags.value("liquidation_bonus", |base| base.liquidation_bonus),
+        min_liquidation_bonus: flags
+            .value("min_liquidation_bonus", |base| base.min_liquidation_bonus),
+        max_liquidation_bonus: flags
+            .value("max_liquidation_bonus", |base| base.max_liquidation_bonus),
+        liquidation_threshold: flags
+            .value("liquidation_threshold", |base| base.liquidation_threshold),
+        stressed_liquidation_threshold: flags.value("stressed_liquidation_threshold", |base| {
+            base.stressed_liquidation_threshold
+        }),
+        stress_utilization_rate: flags.value("stress_utilization_rate", |base| {
+            base.stress_utilization_rate
+        }),
+        min_borrow_rate: flags.value("min_borrow_rate", |base| base.min_borrow_rate),
+        optimal_borrow_rate: flags.value("optimal_borrow_rate", |base| base.optimal_borrow_rate),
+        max_borrow_rate: flags.value("max_borrow_rate", |base| base.max_borrow_rate),
+        max_utilization_rate: flags.value("max_utilization_rate", |base| base.max_utilization_rate),
+        super_max_borrow_rate: flags
+            .value("super_max_borrow_rate", |base| base.super_max_borrow_rate),
+        max_withdraw_utilization: flags.value("max_withdraw_utilization", |base| {
+            base.max_withdraw_utilization
+        }),
+        borrow_rate_rebate: flags.value("borrow_rate_rebate", |base| base.borrow_rate_rebate),
+        borrow_rate_floor: flags.value("borrow_rate_floor", |base| base.borrow_rate_floor),
+        borrow_limit: flags.value("borrow_limit", |base| base.borrow_limit),
+        deposit_limit: flags.value("deposit_limit", |base| base.deposit_limit),
+        max_oracle_age_slots: flags.value("max_oracle_age_slots", |base| base.max_oracle_age_slots),
+        borrow_weight_wad: flags.wad_value("borrow_weight", |base| base.borrow_weight_wad),
+        protocol_liquidation_fee: flags.value("protocol_liquidation_fee", |base| {
+            base.protocol_liquidation_fee
+        }),
+        liquidation_close_factor: flags.value("liquidation_close_factor", |base| {
+            base.liquidation_close_factor
+        }),
+        liquidation_dust_threshold: flags.value("liquidation_dust_threshold", |base| {
+            base.liquidation_dust_threshold
+        }),
+        min_deposit_amount: flags.value("min_deposit_amount", |base| base.min_deposit_amount),
+        max_confidence_interval_bps: flags.value("max_confidence_interval_bps", |base| {
+            base.max_confidence_interval_bps
+        }),
+        outflow_window_duration_slots: flags.value("outflow_window_duration_slots", |base| {
+            base.outflow_window_duration_slots
+        }),
+        max_outflow: flags.value("max_outflow", |base| base.max_outflow),
+        max_accrual_slots_per_refresh: flags.value("max_accrual_slots_per_refresh", |base| {
+            base.max_accrual_slots_per_refresh
+        }),
+        rate_history_interval_slots: flags.value("rate_history_interval_slots", |base| {
+            base.rate_history_interval_slots
+        }),
+        isolated: flags.is_present("isolated", |base| base.isolated),
+        borrowable_in_isolation: flags.is_present("borrowable_in_isolation", |base| {
+            base.borrowable_in_isolation
+        }),
+        fees: ReserveFees {
+            borrow_fee_wad: flags.wad_value("borrow_fee", |base| base.fees.borrow_fee_wad),
+            flash_loan_fee_wad: flags
+                .wad_value("flash_loan_fee", |base| base.fees.flash_loan_fee_wad),
+            host_fee_percentage: flags
+                .value("host_fee_percentage", |base| base.fees.host_fee_percentage),
+        },
+    };
+
+    reserve_config.validate().map_err(|err| match err {
+        ProgramError::Custom(code) => explain_lending_error(code),
+        err => err.to_string(),
+    })?;
+    Ok(reserve_config)
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, clap::App};
+
+    fn reserve_config_of_args(
+        args: &[&str],
+        base: Option<ReserveConfig>,
+    ) -> Result<ReserveConfig, Error> {
+        let matches = App::new("test")
+            .args(&reserve_config_args())
+            .get_matches_from(std::iter::once("test").chain(args.iter().copied()));
+        reserve_config_of(&matches, base)
+    }
+
+    #[test]
+    fn valid_presets() {
+        for &name in RESERVE_PRESETS {
+            let reserve_config = reserve_preset(name).unwrap();
+            assert!(reserve_config.loan_to_value_ratio < reserve_config.liquidation_threshold);
+            assert_eq!(reserve_config.validate(), Ok(()));
+            assert_eq!(
+                reserve_config_of_args(&["--preset", name], None).unwrap(),
+                reserve_config
+            );
+        }
+        assert!(reserve_preset("memecoin").is_err());
+    }
+
+    #[test]
+    fn flag_defaults() {
+        assert_eq!(
+            reserve_config_of_args(&[], None).unwrap(),
+            default_reserve_config()
+        );
+    }
+
+    #[test]
+    fn flags_override_preset() {
+        assert_eq!(
+            reserve_config_of_args(
+                &[
+                    "--preset",
+                    "stablecoin",
+                    "--loan-to-value-ratio",
+                    "70",
+                    "--borrow-weight",
+                    "2",
+                    "--isolated",
+                ],
+                None
+            )
+            .unwrap(),
+            ReserveConfig {
+                loan_to_value_ratio: 70,
+                borrow_weight_wad: 2 * WAD,
+                isolated: true,
+                ..reserve_preset("stablecoin").unwrap()
+            }
+        );
+
+        // the preset replaces the base config
+        let base = reserve_preset("volatile").unwrap();
+        assert_eq!(
+            reserve_config_of_args(&["--preset", "bluechip"], Some(base)).unwrap(),
+            reserve_preset("bluechip").unwrap()
+        );
+    }
+
+    #[test]
+    fn flags_override_base() {
+        let base = reserve_preset("volatile").unwrap();
+        assert_eq!(
+            reserve_config_of_args(&["--max-borrow-rate", "200"], Some(base)).unwrap(),
+            ReserveConfig {
+                max_borrow_rate: 200,
+                ..base
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_override() {
+        // stablecoin loan to value ratio of 80
+        assert!(reserve_config_of_args(
+            &["--preset", "stablecoin", "--liquidation-threshold", "80"],
+            None
+        )
+        .is_err());
+    }
+}
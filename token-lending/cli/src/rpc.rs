@@ -0,0 +1,178 @@
+//! RPC client used by the CLI commands
+
+use {
+    solana_client::{
+        client_error::{reqwest::StatusCode, ClientError, ClientErrorKind, Result as ClientResult},
+        rpc_client::RpcClient,
+        rpc_config::{RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+        rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        rpc_request::{RpcError, MAX_MULTIPLE_ACCOUNTS},
+        rpc_response::{Response, RpcSimulateTransactionResult},
+    },
+    solana_sdk::{
+        account::Account, clock::Slot, hash::Hash, message::Message, pubkey::Pubkey,
+        signature::Signature, transaction::Transaction,
+    },
+    std::{
+        cell::Cell,
+        thread::sleep,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Number of times a rate limited request is retried before giving up
+const MAX_RETRIES: u32 = 5;
+
+/// Delay before the first retry, doubled on each following retry
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Wraps `RpcClient`, throttling requests to an optional maximum rate and
+/// retrying rate limited requests with exponential backoff and jitter
+pub struct CliRpcClient {
+    rpc_client: RpcClient,
+    min_request_interval: Option<Duration>,
+    last_request_at: Cell<Option<Instant>>,
+    request_count: Cell<usize>,
+}
+
+impl CliRpcClient {
+    pub fn new(rpc_client: RpcClient, max_rps: Option<usize>) -> Self {
+        Self {
+            rpc_client,
+            min_request_interval: max_rps
+                .map(|max_rps| Duration::from_secs_f64(1.0 / max_rps as f64)),
+            last_request_at: Cell::new(None),
+            request_count: Cell::new(0),
+        }
+    }
+
+    /// Number of RPC requests sent, including retries
+    pub fn request_count(&self) -> usize {
+        self.request_count.get()
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.send(|rpc_client| rpc_client.get_account(pubkey))
+    }
+
+    /// Fetches the accounts in chunks of `MAX_MULTIPLE_ACCOUNTS`, keeping the
+    /// order of the given pubkeys
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(self.send(|rpc_client| rpc_client.get_multiple_accounts(chunk))?);
+        }
+        Ok(accounts)
+    }
+
+    pub fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.send(|rpc_client| {
+            rpc_client.get_program_accounts_with_config(program_id, config.clone())
+        })
+    }
+
+    pub fn get_slot(&self) -> ClientResult<Slot> {
+        self.send(|rpc_client| rpc_client.get_slot())
+    }
+
+    pub fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.send(|rpc_client| rpc_client.get_balance(pubkey))
+    }
+
+    pub fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        self.send(|rpc_client| rpc_client.get_minimum_balance_for_rent_exemption(data_len))
+    }
+
+    pub fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.send(|rpc_client| rpc_client.get_latest_blockhash())
+    }
+
+    pub fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.send(|rpc_client| rpc_client.get_fee_for_message(message))
+    }
+
+    pub fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Response<RpcSimulateTransactionResult>> {
+        self.send(|rpc_client| rpc_client.simulate_transaction(transaction))
+    }
+
+    /// Simulates at the client commitment unless the config sets one, like
+    /// `simulate_transaction`
+    pub fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> ClientResult<Response<RpcSimulateTransactionResult>> {
+        let config = RpcSimulateTransactionConfig {
+            commitment: config.commitment.or(Some(self.rpc_client.commitment())),
+            ..config
+        };
+        self.send(|rpc_client| {
+            rpc_client.simulate_transaction_with_config(transaction, config.clone())
+        })
+    }
+
+    pub fn send_and_confirm_transaction_with_spinner(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature> {
+        self.send(|rpc_client| rpc_client.send_and_confirm_transaction_with_spinner(transaction))
+    }
+
+    fn send<T>(&self, request: impl Fn(&RpcClient) -> ClientResult<T>) -> ClientResult<T> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retries = 0;
+        loop {
+            self.throttle();
+            self.request_count.set(self.request_count.get() + 1);
+            match request(&self.rpc_client) {
+                Err(err) if retries < MAX_RETRIES && is_rate_limited(&err) => {
+                    sleep(backoff + jitter(backoff));
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn throttle(&self) {
+        if let Some(min_request_interval) = self.min_request_interval {
+            if let Some(last_request_at) = self.last_request_at.get() {
+                let elapsed = last_request_at.elapsed();
+                if elapsed < min_request_interval {
+                    sleep(min_request_interval - elapsed);
+                }
+            }
+            self.last_request_at.set(Some(Instant::now()));
+        }
+    }
+}
+
+/// Public RPC nodes respond with either HTTP 429 or a -32005 JSON RPC error
+/// when the request rate is too high
+fn is_rate_limited(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Reqwest(err) => err.status() == Some(StatusCode::TOO_MANY_REQUESTS),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+/// Pseudo random delay of up to half the backoff, so that clients which got
+/// rate limited together don't retry in lockstep
+fn jitter(backoff: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    backoff.mul_f64(nanos as f64 / 2_000_000_000.0)
+}
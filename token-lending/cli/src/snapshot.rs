@@ -0,0 +1,638 @@
+//! Decoded lending market state used by `dump-state` and `diff-state`
+
+use {
+    serde_derive::{Deserialize, Serialize},
+    serde_json::Value,
+    solana_program::{hash::hash, pubkey::Pubkey},
+    spl_token_lending::{
+        math::Decimal,
+        state::{
+            LendingMarket, Obligation, ObligationCollateral, ObligationLiquidity, Reserve,
+            ReserveCollateral, ReserveConfig, ReserveLiquidity, ReserveOutflow, RewardPool,
+        },
+    },
+    std::collections::BTreeMap,
+};
+
+/// Version of the snapshot layout written by `dump-state`. Snapshots of older
+/// versions are read with the fields they lack defaulted.
+pub const SNAPSHOT_SCHEMA_VERSION: u8 = 1;
+
+/// Lending market and all of its reserves, and optionally its obligations,
+/// keyed by address
+#[derive(Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    #[serde(default)]
+    pub schema_version: u8,
+    pub slot: u64,
+    /// Hash of the snapshot with an empty checksum, empty in snapshots written
+    /// before checksums
+    #[serde(default)]
+    pub checksum: String,
+    pub lending_market: LendingMarketState,
+    pub reserves: BTreeMap<String, ReserveState>,
+    /// Only captured with `--include-obligations`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obligations: Option<BTreeMap<String, ObligationState>>,
+}
+
+impl MarketSnapshot {
+    pub fn new(
+        slot: u64,
+        lending_market: LendingMarketState,
+        reserves: BTreeMap<String, ReserveState>,
+        obligations: Option<BTreeMap<String, ObligationState>>,
+    ) -> Self {
+        let mut snapshot = Self {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            slot,
+            checksum: String::new(),
+            lending_market,
+            reserves,
+            obligations,
+        };
+        snapshot.checksum = snapshot.compute_checksum();
+        snapshot
+    }
+
+    /// Hash of the compact JSON of the snapshot, with sorted keys and an
+    /// empty checksum, so that it doesn't depend on how the file is formatted
+    pub fn compute_checksum(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap();
+        value["checksum"] = Value::String(String::new());
+        hash(&serde_json::to_vec(&value).unwrap()).to_string()
+    }
+
+    /// Checks that the snapshot wasn't modified since it was written. Older
+    /// snapshots, which have no checksum or lack fields added since, are
+    /// accepted as is.
+    pub fn verify_checksum(&self) -> Result<(), String> {
+        if self.checksum.is_empty() || self.schema_version != SNAPSHOT_SCHEMA_VERSION {
+            return Ok(());
+        }
+        let checksum = self.compute_checksum();
+        if checksum != self.checksum {
+            return Err(format!(
+                "Snapshot checksum {} does not match its contents, expected {}",
+                self.checksum, checksum
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LendingMarketState {
+    pub address: String,
+    pub version: u8,
+    pub bump_seed: u8,
+    pub owner: String,
+    pub quote_currency: String,
+    pub token_program_id: String,
+    pub oracle_program_id: String,
+    pub pending_owner: Option<String>,
+    #[serde(default)]
+    pub emergency_authority: Option<String>,
+    #[serde(default)]
+    pub frozen: bool,
+    #[serde(default)]
+    pub permissioned_borrowing: bool,
+    #[serde(default)]
+    pub min_borrow_value: String,
+    #[serde(default)]
+    pub dust_liquidation_threshold: String,
+    #[serde(default)]
+    pub risk_authority: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReserveState {
+    pub version: u8,
+    pub last_update_slot: u64,
+    pub last_update_stale: bool,
+    pub lending_market: String,
+    pub liquidity: ReserveLiquidityState,
+    pub collateral: ReserveCollateralState,
+    pub config: ReserveConfigState,
+    #[serde(default)]
+    pub pause_flags: u8,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub outflow: ReserveOutflowState,
+    #[serde(default)]
+    pub unaccrued_slots: u64,
+    #[serde(default)]
+    pub reward_pool: RewardPoolState,
+    #[serde(default)]
+    pub price_override: bool,
+}
+
+/// Decimal values are stored as full precision strings
+#[derive(Serialize, Deserialize)]
+pub struct ReserveLiquidityState {
+    pub mint_pubkey: String,
+    pub mint_decimals: u8,
+    pub supply_pubkey: String,
+    pub fee_receiver: String,
+    #[serde(default)]
+    pub flash_loan_fee_receiver: String,
+    #[serde(default)]
+    pub oracle_kind: String,
+    pub oracle_pubkey: String,
+    pub available_amount: u64,
+    pub borrowed_amount: String,
+    pub cumulative_borrow_rate: String,
+    pub market_price: String,
+    #[serde(default)]
+    pub market_price_slot: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReserveCollateralState {
+    pub mint_pubkey: String,
+    pub mint_total_supply: u64,
+    pub supply_pubkey: String,
+    #[serde(default)]
+    pub fee_receiver: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ReserveOutflowState {
+    pub window_start_slot: u64,
+    pub window_amount: u64,
+    pub previous_window_amount: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RewardPoolState {
+    pub vault: String,
+    pub rewards_per_slot: u64,
+    pub cumulative_rewards_per_collateral: String,
+}
+
+/// Fees are stored as full precision fractions rather than wads
+#[derive(Serialize, Deserialize)]
+pub struct ReserveConfigState {
+    pub optimal_utilization_rate: u8,
+    pub loan_to_value_ratio: u8,
+    pub collateral_weight: u8,
+    pub liquidation_bonus: u8,
+    #[serde(default)]
+    pub min_liquidation_bonus: u8,
+    pub max_liquidation_bonus: u8,
+    pub liquidation_threshold: u8,
+    #[serde(default)]
+    pub stressed_liquidation_threshold: u8,
+    #[serde(default)]
+    pub stress_utilization_rate: u8,
+    pub min_borrow_rate: u8,
+    pub optimal_borrow_rate: u8,
+    pub max_borrow_rate: u8,
+    #[serde(default)]
+    pub max_utilization_rate: u8,
+    #[serde(default)]
+    pub super_max_borrow_rate: u8,
+    pub max_withdraw_utilization: u8,
+    pub borrow_rate_rebate: u8,
+    pub borrow_rate_floor: u8,
+    pub borrow_limit: u64,
+    pub deposit_limit: u64,
+    #[serde(default)]
+    pub max_oracle_age_slots: u64,
+    #[serde(default)]
+    pub borrow_weight: String,
+    #[serde(default)]
+    pub protocol_liquidation_fee: u8,
+    #[serde(default)]
+    pub liquidation_close_factor: u8,
+    #[serde(default)]
+    pub liquidation_dust_threshold: u64,
+    #[serde(default)]
+    pub min_deposit_amount: u64,
+    #[serde(default)]
+    pub max_confidence_interval_bps: u16,
+    #[serde(default)]
+    pub outflow_window_duration_slots: u64,
+    #[serde(default)]
+    pub max_outflow: u64,
+    #[serde(default)]
+    pub max_accrual_slots_per_refresh: u64,
+    #[serde(default)]
+    pub rate_history_interval_slots: u64,
+    #[serde(default)]
+    pub isolated: bool,
+    #[serde(default)]
+    pub borrowable_in_isolation: bool,
+    pub borrow_fee: String,
+    pub flash_loan_fee: String,
+    pub host_fee_percentage: u8,
+}
+
+/// Decimal values are stored as full precision strings
+#[derive(Serialize, Deserialize)]
+pub struct ObligationState {
+    pub version: u8,
+    pub last_update_slot: u64,
+    pub last_update_stale: bool,
+    pub lending_market: String,
+    pub owner: String,
+    pub deposits: Vec<ObligationCollateralState>,
+    pub borrows: Vec<ObligationLiquidityState>,
+    pub deposited_value: String,
+    pub borrowed_value: String,
+    pub allowed_borrow_value: String,
+    pub unhealthy_borrow_value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ObligationCollateralState {
+    pub deposit_reserve: String,
+    pub deposited_amount: u64,
+    pub market_value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ObligationLiquidityState {
+    pub borrow_reserve: String,
+    pub cumulative_borrow_rate: String,
+    pub borrowed_amount: String,
+    pub market_value: String,
+}
+
+impl LendingMarketState {
+    pub fn new(address: &Pubkey, lending_market: &LendingMarket) -> Self {
+        // Quote currencies are either short symbols padded with zeros or mint
+        // addresses
+        let symbol = std::str::from_utf8(&lending_market.quote_currency)
+            .ok()
+            .map(|symbol| symbol.trim_end_matches('\0'))
+            .filter(|symbol| symbol.chars().all(|c| c.is_ascii_graphic()));
+        let quote_currency = match symbol {
+            Some(symbol) => symbol.to_string(),
+            None => Pubkey::new_from_array(lending_market.quote_currency).to_string(),
+        };
+        Self {
+            address: address.to_string(),
+            version: lending_market.version,
+            bump_seed: lending_market.bump_seed,
+            owner: lending_market.owner.to_string(),
+            quote_currency,
+            token_program_id: lending_market.token_program_id.to_string(),
+            oracle_program_id: lending_market.oracle_program_id.to_string(),
+            pending_owner: (lending_market.pending_owner != Pubkey::default())
+                .then(|| lending_market.pending_owner.to_string()),
+            emergency_authority: (lending_market.emergency_authority != Pubkey::default())
+                .then(|| lending_market.emergency_authority.to_string()),
+            frozen: lending_market.frozen,
+            permissioned_borrowing: lending_market.permissioned_borrowing,
+            min_borrow_value: lending_market.min_borrow_value.to_string(),
+            dust_liquidation_threshold: lending_market.dust_liquidation_threshold.to_string(),
+            risk_authority: (lending_market.risk_authority != Pubkey::default())
+                .then(|| lending_market.risk_authority.to_string()),
+        }
+    }
+}
+
+impl From<&Reserve> for ReserveState {
+    fn from(reserve: &Reserve) -> Self {
+        Self {
+            version: reserve.version,
+            last_update_slot: reserve.last_update.slot,
+            last_update_stale: reserve.last_update.stale,
+            lending_market: reserve.lending_market.to_string(),
+            liquidity: (&reserve.liquidity).into(),
+            collateral: (&reserve.collateral).into(),
+            config: (&reserve.config).into(),
+            pause_flags: reserve.pause_flags,
+            status: format!("{:?}", reserve.status),
+            outflow: (&reserve.outflow).into(),
+            unaccrued_slots: reserve.unaccrued_slots,
+            reward_pool: (&reserve.reward_pool).into(),
+            price_override: reserve.price_override,
+        }
+    }
+}
+
+impl From<&RewardPool> for RewardPoolState {
+    fn from(reward_pool: &RewardPool) -> Self {
+        Self {
+            vault: reward_pool.vault.to_string(),
+            rewards_per_slot: reward_pool.rewards_per_slot,
+            cumulative_rewards_per_collateral: reward_pool
+                .cumulative_rewards_per_collateral
+                .to_string(),
+        }
+    }
+}
+
+impl From<&ReserveOutflow> for ReserveOutflowState {
+    fn from(outflow: &ReserveOutflow) -> Self {
+        Self {
+            window_start_slot: outflow.window_start_slot,
+            window_amount: outflow.window_amount,
+            previous_window_amount: outflow.previous_window_amount,
+        }
+    }
+}
+
+impl From<&ReserveLiquidity> for ReserveLiquidityState {
+    fn from(liquidity: &ReserveLiquidity) -> Self {
+        Self {
+            mint_pubkey: liquidity.mint_pubkey.to_string(),
+            mint_decimals: liquidity.mint_decimals,
+            supply_pubkey: liquidity.supply_pubkey.to_string(),
+            fee_receiver: liquidity.fee_receiver.to_string(),
+            flash_loan_fee_receiver: liquidity.flash_loan_fee_receiver.to_string(),
+            oracle_kind: format!("{:?}", liquidity.oracle_kind),
+            oracle_pubkey: liquidity.oracle_pubkey.to_string(),
+            available_amount: liquidity.available_amount,
+            borrowed_amount: liquidity.borrowed_amount_wads.to_string(),
+            cumulative_borrow_rate: liquidity.cumulative_borrow_rate_wads.to_string(),
+            market_price: liquidity.market_price.to_string(),
+            market_price_slot: liquidity.market_price_slot,
+        }
+    }
+}
+
+impl From<&ReserveCollateral> for ReserveCollateralState {
+    fn from(collateral: &ReserveCollateral) -> Self {
+        Self {
+            mint_pubkey: collateral.mint_pubkey.to_string(),
+            mint_total_supply: collateral.mint_total_supply,
+            supply_pubkey: collateral.supply_pubkey.to_string(),
+            fee_receiver: collateral.fee_receiver.to_string(),
+        }
+    }
+}
+
+impl From<&ReserveConfig> for ReserveConfigState {
+    fn from(config: &ReserveConfig) -> Self {
+        Self {
+            optimal_utilization_rate: config.optimal_utilization_rate,
+            loan_to_value_ratio: config.loan_to_value_ratio,
+            collateral_weight: config.collateral_weight,
+            liquidation_bonus: config.liquidation_bonus,
+            min_liquidation_bonus: config.min_liquidation_bonus,
+            max_liquidation_bonus: config.max_liquidation_bonus,
+            liquidation_threshold: config.liquidation_threshold,
+            stressed_liquidation_threshold: config.stressed_liquidation_threshold,
+            stress_utilization_rate: config.stress_utilization_rate,
+            min_borrow_rate: config.min_borrow_rate,
+            optimal_borrow_rate: config.optimal_borrow_rate,
+            max_borrow_rate: config.max_borrow_rate,
+            max_utilization_rate: config.max_utilization_rate,
+            super_max_borrow_rate: config.super_max_borrow_rate,
+            max_withdraw_utilization: config.max_withdraw_utilization,
+            borrow_rate_rebate: config.borrow_rate_rebate,
+            borrow_rate_floor: config.borrow_rate_floor,
+            borrow_limit: config.borrow_limit,
+            deposit_limit: config.deposit_limit,
+            max_oracle_age_slots: config.max_oracle_age_slots,
+            borrow_weight: config.borrow_weight().to_string(),
+            protocol_liquidation_fee: config.protocol_liquidation_fee,
+            liquidation_close_factor: config.liquidation_close_factor,
+            liquidation_dust_threshold: config.liquidation_dust_threshold,
+            min_deposit_amount: config.min_deposit_amount,
+            max_confidence_interval_bps: config.max_confidence_interval_bps,
+            outflow_window_duration_slots: config.outflow_window_duration_slots,
+            max_outflow: config.max_outflow,
+            max_accrual_slots_per_refresh: config.max_accrual_slots_per_refresh,
+            rate_history_interval_slots: config.rate_history_interval_slots,
+            isolated: config.isolated,
+            borrowable_in_isolation: config.borrowable_in_isolation,
+            borrow_fee: Decimal::from_scaled_val(config.fees.borrow_fee_wad as u128).to_string(),
+            flash_loan_fee: Decimal::from_scaled_val(config.fees.flash_loan_fee_wad as u128)
+                .to_string(),
+            host_fee_percentage: config.fees.host_fee_percentage,
+        }
+    }
+}
+
+impl From<&Obligation> for ObligationState {
+    fn from(obligation: &Obligation) -> Self {
+        Self {
+            version: obligation.version,
+            last_update_slot: obligation.last_update.slot,
+            last_update_stale: obligation.last_update.stale,
+            lending_market: obligation.lending_market.to_string(),
+            owner: obligation.owner.to_string(),
+            deposits: obligation.deposits.iter().map(Into::into).collect(),
+            borrows: obligation.borrows.iter().map(Into::into).collect(),
+            deposited_value: obligation.deposited_value.to_string(),
+            borrowed_value: obligation.borrowed_value.to_string(),
+            allowed_borrow_value: obligation.allowed_borrow_value.to_string(),
+            unhealthy_borrow_value: obligation.unhealthy_borrow_value.to_string(),
+        }
+    }
+}
+
+impl From<&ObligationCollateral> for ObligationCollateralState {
+    fn from(collateral: &ObligationCollateral) -> Self {
+        Self {
+            deposit_reserve: collateral.deposit_reserve.to_string(),
+            deposited_amount: collateral.deposited_amount,
+            market_value: collateral.market_value.to_string(),
+        }
+    }
+}
+
+impl From<&ObligationLiquidity> for ObligationLiquidityState {
+    fn from(liquidity: &ObligationLiquidity) -> Self {
+        Self {
+            borrow_reserve: liquidity.borrow_reserve.to_string(),
+            cumulative_borrow_rate: liquidity.cumulative_borrow_rate_wads.to_string(),
+            borrowed_amount: liquidity.borrowed_amount_wads.to_string(),
+            market_value: liquidity.market_value.to_string(),
+        }
+    }
+}
+
+/// Field level differences between two snapshots, e.g.
+/// `reserve X: config.loan_to_value_ratio 80 -> 75`. Obligations are only
+/// compared when both snapshots include them.
+pub fn diff_snapshots(before: &MarketSnapshot, after: &MarketSnapshot) -> Vec<String> {
+    let mut diffs = vec![];
+
+    diff_values(
+        "market",
+        "",
+        &serde_json::to_value(&before.lending_market).unwrap(),
+        &serde_json::to_value(&after.lending_market).unwrap(),
+        &mut diffs,
+    );
+
+    diff_accounts("reserve", &before.reserves, &after.reserves, &mut diffs);
+    if let (Some(before_obligations), Some(after_obligations)) =
+        (&before.obligations, &after.obligations)
+    {
+        diff_accounts(
+            "obligation",
+            before_obligations,
+            after_obligations,
+            &mut diffs,
+        );
+    }
+
+    diffs
+}
+
+fn diff_accounts<T: serde::Serialize>(
+    kind: &str,
+    before: &BTreeMap<String, T>,
+    after: &BTreeMap<String, T>,
+    diffs: &mut Vec<String>,
+) {
+    for (address, before_account) in before {
+        let subject = format!("{} {}", kind, address);
+        match after.get(address) {
+            Some(after_account) => diff_values(
+                &subject,
+                "",
+                &serde_json::to_value(before_account).unwrap(),
+                &serde_json::to_value(after_account).unwrap(),
+                diffs,
+            ),
+            None => diffs.push(format!("{}: removed", subject)),
+        }
+    }
+    for address in after.keys() {
+        if !before.contains_key(address) {
+            diffs.push(format!("{} {}: added", kind, address));
+        }
+    }
+}
+
+fn diff_values(subject: &str, path: &str, before: &Value, after: &Value, diffs: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, before_value) in before {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let after_value = after.get(key).unwrap_or(&Value::Null);
+                diff_values(subject, &path, before_value, after_value, diffs);
+            }
+        }
+        _ if before != after => diffs.push(format!(
+            "{}: {} {} -> {}",
+            subject,
+            path,
+            display_value(before),
+            display_value(after)
+        )),
+        _ => {}
+    }
+}
+
+/// Trims the trailing zeros of full precision decimals
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(value) if value.contains('.') && value.parse::<f64>().is_ok() => value
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, spl_token_lending::state::PROGRAM_VERSION};
+
+    fn snapshot(include_obligations: bool) -> MarketSnapshot {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            owner: Pubkey::new_unique(),
+            ..LendingMarket::default()
+        };
+        let mut reserve = Reserve {
+            version: PROGRAM_VERSION,
+            ..Reserve::default()
+        };
+        // more precision than an f64 holds
+        reserve.liquidity.borrowed_amount_wads =
+            Decimal::from_scaled_val(123_456_789_123_456_789_123_456_789);
+        reserve.liquidity.cumulative_borrow_rate_wads = Decimal::one();
+        let obligation = Obligation {
+            version: PROGRAM_VERSION,
+            borrows: vec![ObligationLiquidity::new(
+                Pubkey::new_unique(),
+                reserve.liquidity.cumulative_borrow_rate_wads,
+            )],
+            ..Obligation::default()
+        };
+
+        MarketSnapshot::new(
+            200_000_000,
+            LendingMarketState::new(&Pubkey::new_unique(), &lending_market),
+            [(Pubkey::new_unique().to_string(), (&reserve).into())].into(),
+            include_obligations
+                .then(|| [(Pubkey::new_unique().to_string(), (&obligation).into())].into()),
+        )
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        for include_obligations in [false, true] {
+            let snapshot = snapshot(include_obligations);
+            let json = serde_json::to_string_pretty(&snapshot).unwrap();
+            let read: MarketSnapshot = serde_json::from_str(&json).unwrap();
+            assert_eq!(read.verify_checksum(), Ok(()));
+            assert_eq!(read.obligations.is_some(), include_obligations);
+            assert!(diff_snapshots(&snapshot, &read).is_empty());
+            assert_eq!(serde_json::to_string_pretty(&read).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn snapshot_decimals_keep_full_precision() {
+        let snapshot = snapshot(false);
+        let reserve = snapshot.reserves.values().next().unwrap();
+        assert_eq!(
+            reserve.liquidity.borrowed_amount,
+            "123456789.123456789123456789"
+        );
+    }
+
+    #[test]
+    fn snapshot_checksum_detects_changes() {
+        let mut snapshot = snapshot(true);
+        let checksum = snapshot.checksum.clone();
+        assert_eq!(snapshot.compute_checksum(), checksum);
+
+        snapshot.slot += 1;
+        assert!(snapshot.verify_checksum().is_err());
+
+        // snapshots written before checksums are not checked
+        snapshot.checksum = String::new();
+        assert_eq!(snapshot.verify_checksum(), Ok(()));
+    }
+
+    #[test]
+    fn diff_snapshot_obligations() {
+        let before = snapshot(true);
+        let mut after: MarketSnapshot =
+            serde_json::from_str(&serde_json::to_string(&before).unwrap()).unwrap();
+        let obligation = after
+            .obligations
+            .as_mut()
+            .unwrap()
+            .values_mut()
+            .next()
+            .unwrap();
+        obligation.borrowed_value = "1.000000000000000000".to_string();
+        let address = after.obligations.as_ref().unwrap().keys().next().unwrap();
+        assert_eq!(
+            diff_snapshots(&before, &after),
+            vec![format!("obligation {}: borrowed_value 0 -> 1", address)]
+        );
+
+        // not compared unless both snapshots include obligations
+        after.obligations = None;
+        assert!(diff_snapshots(&before, &after).is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Lending market consistency checks used by `verify-market`
+
+use {
+    serde_derive::Serialize,
+    solana_program::pubkey::Pubkey,
+    spl_token::state::{Account as Token, Mint},
+    spl_token_lending::{
+        state::{LendingMarket, OracleKind, Reserve},
+        switchboard,
+    },
+    std::fmt,
+};
+
+/// Outcome of every check run against a lending market and its reserves
+#[derive(Serialize)]
+pub struct MarketVerification {
+    pub lending_market: String,
+    pub slot: u64,
+    pub checks: Vec<CheckResult>,
+}
+
+impl MarketVerification {
+    pub fn failed_count(&self) -> usize {
+        self.checks.iter().filter(|check| !check.passed).count()
+    }
+}
+
+/// A single check against one reserve, e.g. `collateral_mint_supply`
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub reserve: String,
+    pub check: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} reserve {}: {} ({})",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.reserve,
+            self.check,
+            self.detail
+        )
+    }
+}
+
+/// Accounts referenced by a reserve, `None` when the account doesn't exist or
+/// can't be unpacked
+pub struct ReserveAccounts {
+    pub collateral_mint: Option<Mint>,
+    pub liquidity_supply: Option<Token>,
+    pub oracle_owner: Option<Pubkey>,
+}
+
+pub fn verify_reserve(
+    lending_market: &LendingMarket,
+    reserve_pubkey: &Pubkey,
+    reserve: &Reserve,
+    accounts: &ReserveAccounts,
+) -> Vec<CheckResult> {
+    let check = |check: &'static str, passed: bool, detail: String| CheckResult {
+        reserve: reserve_pubkey.to_string(),
+        check,
+        passed,
+        detail,
+    };
+
+    let collateral_mint_supply = match &accounts.collateral_mint {
+        Some(mint) => check(
+            "collateral_mint_supply",
+            mint.supply == reserve.collateral.mint_total_supply,
+            format!(
+                "mint supply {}, reserve {}",
+                mint.supply, reserve.collateral.mint_total_supply
+            ),
+        ),
+        None => check(
+            "collateral_mint_supply",
+            false,
+            format!(
+                "collateral mint {} not found",
+                reserve.collateral.mint_pubkey
+            ),
+        ),
+    };
+
+    let liquidity_supply_balance = match &accounts.liquidity_supply {
+        Some(token) => check(
+            "liquidity_supply_balance",
+            token.amount == reserve.liquidity.available_amount,
+            format!(
+                "supply balance {}, reserve available {}",
+                token.amount, reserve.liquidity.available_amount
+            ),
+        ),
+        None => check(
+            "liquidity_supply_balance",
+            false,
+            format!(
+                "liquidity supply {} not found",
+                reserve.liquidity.supply_pubkey
+            ),
+        ),
+    };
+
+    let loan_to_value_ratio = check(
+        "loan_to_value_ratio",
+        reserve.config.loan_to_value_ratio < reserve.config.liquidation_threshold
+            || reserve.config.is_deposit_only(),
+        format!(
+            "loan-to-value {}%, liquidation threshold {}%",
+            reserve.config.loan_to_value_ratio, reserve.config.liquidation_threshold
+        ),
+    );
+
+    let oracle_owner = match accounts.oracle_owner {
+        Some(owner) => {
+            let passed = match reserve.liquidity.oracle_kind {
+                OracleKind::Pyth => owner == lending_market.oracle_program_id,
+                OracleKind::Switchboard => {
+                    switchboard::program::check_id(&owner)
+                        || switchboard::devnet_program::check_id(&owner)
+                }
+            };
+            check(
+                "oracle_owner",
+                passed,
+                format!(
+                    "{:?} oracle {} owned by {}",
+                    reserve.liquidity.oracle_kind, reserve.liquidity.oracle_pubkey, owner
+                ),
+            )
+        }
+        None => check(
+            "oracle_owner",
+            false,
+            format!("oracle {} not found", reserve.liquidity.oracle_pubkey),
+        ),
+    };
+
+    vec![
+        collateral_mint_supply,
+        liquidity_supply_balance,
+        loan_to_value_ratio,
+        oracle_owner,
+    ]
+}
@@ -0,0 +1,193 @@
+//! Helpers for flash loan receiver programs invoked by the `FlashLoan`
+//! instruction
+
+use {
+    crate::{error::LendingError, instruction::LendingInstruction},
+    solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+        msg,
+        program::invoke_signed,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+    std::convert::TryInto,
+};
+
+/// Tag of the `ReceiveFlashLoan` instruction the `FlashLoan` instruction
+/// invokes on the flash loan receiver program
+// @FIXME: don't use 0 to indicate a flash loan receiver instruction https://git.io/JGzz9
+pub const RECEIVE_FLASH_LOAN_INSTRUCTION_TAG: u8 = 0;
+
+/// Accounts the `FlashLoan` instruction passes to the `ReceiveFlashLoan`
+/// instruction, in order
+pub struct FlashLoanReceiverAccounts<'a, 'b> {
+    /// Token account holding the loaned liquidity, the `FlashLoan`
+    /// destination liquidity
+    pub source_liquidity_info: &'a AccountInfo<'b>,
+    /// Reserve liquidity supply to repay, the `FlashLoan` source liquidity
+    pub destination_liquidity_info: &'a AccountInfo<'b>,
+    /// Token program id
+    pub token_program_info: &'a AccountInfo<'b>,
+    /// Additional accounts provided to the `FlashLoan` instruction
+    pub remaining_account_infos: &'a [AccountInfo<'b>],
+}
+
+impl<'a, 'b> FlashLoanReceiverAccounts<'a, 'b> {
+    /// Splits the accounts of the `ReceiveFlashLoan` instruction
+    pub fn from_account_infos(account_infos: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        if account_infos.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (account_infos, remaining_account_infos) = account_infos.split_at(3);
+        Ok(Self {
+            source_liquidity_info: &account_infos[0],
+            destination_liquidity_info: &account_infos[1],
+            token_program_info: &account_infos[2],
+            remaining_account_infos,
+        })
+    }
+}
+
+/// Unpacks the amount to repay, including the flash loan fee, from the
+/// `ReceiveFlashLoan` instruction data
+pub fn unpack_receive_flash_loan(input: &[u8]) -> Result<u64, ProgramError> {
+    input
+        .split_first()
+        .filter(|(tag, _)| **tag == RECEIVE_FLASH_LOAN_INSTRUCTION_TAG)
+        .and_then(|(_, amount)| amount.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| {
+            msg!("Instruction data is not a ReceiveFlashLoan instruction");
+            LendingError::InstructionUnpackError.into()
+        })
+}
+
+/// Repays `amount` from the source liquidity to the reserve liquidity supply.
+/// `authority_info` must own the source liquidity, signing with
+/// `authority_signer_seeds` when it is a PDA of the receiver program.
+pub fn repay_flash_loan<'a>(
+    accounts: &FlashLoanReceiverAccounts<'_, 'a>,
+    authority_info: &AccountInfo<'a>,
+    amount: u64,
+    authority_signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let transfer_ix = spl_token::instruction::transfer(
+        accounts.token_program_info.key,
+        accounts.source_liquidity_info.key,
+        accounts.destination_liquidity_info.key,
+        authority_info.key,
+        &[],
+        amount,
+    )?;
+    invoke_signed(
+        &transfer_ix,
+        &[
+            accounts.source_liquidity_info.clone(),
+            accounts.destination_liquidity_info.clone(),
+            authority_info.clone(),
+            accounts.token_program_info.clone(),
+        ],
+        authority_signer_seeds,
+    )
+}
+
+/// Asserts the receiver program is invoked by the `FlashLoan` instruction of
+/// `lending_program_id`, loaning `liquidity_amount` from the `reserve_pubkey`
+/// liquidity supply into the source liquidity.
+///
+/// The instructions sysvar must be one of the additional accounts provided to
+/// the `FlashLoan` instruction.
+pub fn assert_flash_loan_invocation(
+    lending_program_id: &Pubkey,
+    accounts: &FlashLoanReceiverAccounts,
+    instructions_sysvar_info: &AccountInfo,
+    reserve_pubkey: &Pubkey,
+    liquidity_amount: u64,
+) -> ProgramResult {
+    // The FlashLoan instruction is top level and invokes the receiver directly
+    let current_index = load_current_index_checked(instructions_sysvar_info)? as usize;
+    let flash_loan_instruction =
+        load_instruction_at_checked(current_index, instructions_sysvar_info)?;
+    if &flash_loan_instruction.program_id != lending_program_id
+        || get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT + 1
+    {
+        msg!("Flash loan receiver must be invoked by the lending program");
+        return Err(LendingError::InvalidFlashLoanInvocation.into());
+    }
+
+    if LendingInstruction::unpack(&flash_loan_instruction.data)
+        != Ok(LendingInstruction::FlashLoan {
+            amount: liquidity_amount,
+        })
+    {
+        msg!("Flash loan amount does not match the expected liquidity amount");
+        return Err(LendingError::InvalidFlashLoanInvocation.into());
+    }
+
+    match flash_loan_instruction.accounts.as_slice() {
+        [source_liquidity, destination_liquidity, reserve, ..]
+            if &source_liquidity.pubkey == accounts.destination_liquidity_info.key
+                && &destination_liquidity.pubkey == accounts.source_liquidity_info.key
+                && &reserve.pubkey == reserve_pubkey =>
+        {
+            Ok(())
+        }
+        _ => {
+            msg!("Flash loan reserve or liquidity accounts do not match the expected accounts");
+            Err(LendingError::InvalidFlashLoanInvocation.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unpack_receive_flash_loan_amount() {
+        let mut data = vec![RECEIVE_FLASH_LOAN_INSTRUCTION_TAG];
+        data.extend_from_slice(&1_003u64.to_le_bytes());
+        assert_eq!(unpack_receive_flash_loan(&data), Ok(1_003));
+
+        assert_eq!(
+            unpack_receive_flash_loan(&data[..8]),
+            Err(LendingError::InstructionUnpackError.into())
+        );
+
+        data[0] = 1;
+        assert_eq!(
+            unpack_receive_flash_loan(&data),
+            Err(LendingError::InstructionUnpackError.into())
+        );
+    }
+
+    #[test]
+    fn flash_loan_receiver_accounts() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = [0; 4];
+        let mut data = vec![vec![]; 4];
+        let account_infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, true, lamports, data, key, false, 0)
+            })
+            .collect();
+
+        let accounts = FlashLoanReceiverAccounts::from_account_infos(&account_infos).unwrap();
+        assert_eq!(accounts.source_liquidity_info.key, &keys[0]);
+        assert_eq!(accounts.destination_liquidity_info.key, &keys[1]);
+        assert_eq!(accounts.token_program_info.key, &keys[2]);
+        assert_eq!(accounts.remaining_account_infos.len(), 1);
+        assert_eq!(accounts.remaining_account_infos[0].key, &keys[3]);
+
+        assert_eq!(
+            FlashLoanReceiverAccounts::from_account_infos(&account_infos[..2]).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+}
@@ -99,7 +99,7 @@ pub enum LendingError {
 
     // 25
     /// Borrow amount too small
-    #[error("Borrow amount too small to receive liquidity after fees")]
+    #[error("Borrow amount too small after fees or below the minimum borrow value")]
     BorrowTooSmall,
     /// Borrow amount too large
     #[error("Borrow amount too large for deposited collateral")]
@@ -168,6 +168,103 @@ pub enum LendingError {
     /// Lending instruction exceeds desired slippage limit
     #[error("Amount smaller than desired slippage limit")]
     ExceededSlippage,
+    /// Exchange rate moved and the received amount is too small
+    #[error("Amount received at the current exchange rate is smaller than the minimum")]
+    ExchangeRateSlippageExceeded,
+    /// Borrow would exceed the reserve borrow limit
+    #[error("Reserve borrows cannot exceed the borrow limit")]
+    BorrowLimitExceeded,
+    /// Deposit would exceed the reserve deposit limit
+    #[error("Reserve liquidity cannot exceed the deposit limit")]
+    DepositLimitExceeded,
+    /// Action is paused on the reserve
+    #[error("Action is paused on the reserve")]
+    ActionPaused,
+
+    // 50
+    /// Reserve still holds liquidity, borrows, collateral or fees
+    #[error("Reserve must be empty to be closed")]
+    ReserveNotEmpty,
+    /// Token set authority failed
+    #[error("Token set authority failed")]
+    TokenSetAuthorityFailed,
+    /// Deposit is below the reserve minimum or too small to seed the reserve
+    #[error("Deposit amount is too small")]
+    DepositTooSmall,
+    /// Lending market is frozen by its owner or emergency authority
+    #[error("Lending market is frozen")]
+    MarketFrozen,
+    /// Oracle confidence interval exceeds the reserve maximum
+    #[error("Oracle price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    /// Reserve is deprecated or obsolete and no longer allows the action
+    #[error("Reserve is deprecated")]
+    ReserveDeprecated,
+    /// Liquidity leaving the reserve exceeds the outflow rate limit
+    #[error("Reserve outflow rate limit exceeded")]
+    OutflowRateLimitExceeded,
+    /// Obligation owner is not on the borrow allowlist of a market with
+    /// permissioned borrowing
+    #[error("Borrower is not on the market borrow allowlist")]
+    BorrowerNotAllowed,
+    /// Loan to value ratio is out of range
+    #[error("Invalid loan to value ratio")]
+    InvalidLtvRatio,
+    /// Liquidation threshold is not above the loan to value ratio or is over
+    /// 100
+    #[error("Invalid liquidation threshold")]
+    InvalidLiquidationThreshold,
+    /// Liquidation bonus or max liquidation bonus is out of range
+    #[error("Invalid liquidation bonus")]
+    InvalidLiquidationBonus,
+    /// Borrow rates or utilization rates don't form an increasing curve
+    #[error("Invalid borrow rate curve")]
+    InvalidBorrowRateCurve,
+    /// Borrow fee, flash loan fee or host fee percentage is out of range
+    #[error("Invalid fee config")]
+    InvalidFeeConfig,
+    /// Isolated collateral cannot be combined with other collateral or
+    /// borrowed against from the reserve
+    #[error("Isolated collateral cannot be combined or borrowed against")]
+    IsolatedCollateral,
+    /// Market metadata name or description URI exceeds its maximum length
+    #[error("Market metadata is too long")]
+    MarketMetadataTooLong,
+    /// Flash borrow has no matching flash repay later in the transaction
+    #[error("No matching flash repay found")]
+    NoFlashRepayFound,
+    /// Flash repay doesn't match the flash borrow it points to, or a flash
+    /// borrow has more than one flash repay
+    #[error("Flash repay does not match the flash borrow")]
+    InvalidFlashRepay,
+    /// Flash borrows and repays must be top level instructions
+    #[error("Flash borrow and repay cannot be invoked via CPI")]
+    FlashLoanInvokedViaCpi,
+    /// Obligation is already at the current program version
+    #[error("Obligation is already migrated")]
+    ObligationAlreadyMigrated,
+    /// Lending market or reserve is already in the current account layout
+    #[error("Account is already migrated")]
+    AccountAlreadyMigrated,
+    /// Flash loan receiver was not invoked by the expected flash loan
+    #[error("Flash loan receiver was not invoked by the expected flash loan")]
+    InvalidFlashLoanInvocation,
+    /// Lending market or reserve is still in the V1 account layout, which has
+    /// no room for the requested configuration
+    #[error("Account must be migrated to the current layout")]
+    AccountNotMigrated,
+    /// Lending market risk authority attempted a change which doesn't only
+    /// reduce risk
+    #[error("Risk authority cannot loosen reserve parameters")]
+    RiskAuthorityCannotLoosen,
+    /// Reserve was refreshed with a market price older than its max oracle
+    /// age allows for the current slot
+    #[error("Reserve market price is too old")]
+    ReservePriceStale,
+    /// Collateral redeemed right away while redemptions are queued, which
+    /// must be fulfilled first
+    #[error("Reserve has queued redemptions")]
+    RedemptionsQueued,
 }
 
 impl From<LendingError> for ProgramError {
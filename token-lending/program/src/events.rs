@@ -0,0 +1,293 @@
+//! Events logged by the lending program for indexers
+
+use {
+    base64::{engine::general_purpose, Engine as _},
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_program::{log::sol_log_data, pubkey::Pubkey},
+};
+
+/// Prefix of the transaction log lines written by `sol_log_data`
+const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Liquidity deposited into a reserve in exchange for collateral
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct DepositEvent {
+    /// Reserve address
+    pub reserve: Pubkey,
+    /// User transfer authority of the deposited liquidity
+    pub user: Pubkey,
+    /// Amount of liquidity received by the reserve
+    pub liquidity_amount: u64,
+    /// Amount of collateral minted to the user
+    pub collateral_amount: u64,
+}
+
+/// Liquidity borrowed from a reserve against an obligation
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct BorrowEvent {
+    /// Borrow reserve address
+    pub reserve: Pubkey,
+    /// Obligation address
+    pub obligation: Pubkey,
+    /// Obligation owner
+    pub user: Pubkey,
+    /// Amount of liquidity received by the borrower
+    pub liquidity_amount: u64,
+    /// Loan origination fee, including the host fee
+    pub borrow_fee: u64,
+}
+
+/// Liquidity repaid to a reserve on behalf of an obligation
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct RepayEvent {
+    /// Repay reserve address
+    pub reserve: Pubkey,
+    /// Obligation address
+    pub obligation: Pubkey,
+    /// User transfer authority of the repaid liquidity
+    pub user: Pubkey,
+    /// Amount of liquidity received by the reserve
+    pub liquidity_amount: u64,
+}
+
+/// Obligation borrow repaid by a liquidator in exchange for collateral
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct LiquidateEvent {
+    /// Obligation address
+    pub obligation: Pubkey,
+    /// Repay reserve address
+    pub repay_reserve: Pubkey,
+    /// Withdraw reserve address
+    pub withdraw_reserve: Pubkey,
+    /// Amount of liquidity received by the repay reserve
+    pub repay_amount: u64,
+    /// Amount of collateral withdrawn from the obligation, including the
+    /// protocol liquidation fee
+    pub seized_collateral: u64,
+    /// Liquidation bonus portion of the seized collateral
+    pub bonus: u64,
+}
+
+/// Event logged by the lending program
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum LendingEvent {
+    /// Liquidity deposited into a reserve
+    Deposit(DepositEvent),
+    /// Liquidity borrowed from a reserve
+    Borrow(BorrowEvent),
+    /// Liquidity repaid to a reserve
+    Repay(RepayEvent),
+    /// Obligation liquidated
+    Liquidate(LiquidateEvent),
+}
+
+impl LendingEvent {
+    /// Log the borsh serialized event with `sol_log_data`
+    pub fn emit(&self) {
+        let data = borsh::to_vec(self).expect("Lending event cannot be serialized");
+        sol_log_data(&[&data]);
+    }
+}
+
+/// Parse the events logged by the lending program from the log messages of a
+/// transaction. Data logged by other programs, including programs invoked by
+/// the lending program, is skipped.
+pub fn parse_lending_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<LendingEvent> {
+    let program_id = program_id.to_string();
+    // whether each program on the invocation stack is the lending program
+    let mut invocations = vec![];
+    let mut events = vec![];
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_LOG_PREFIX) {
+            if invocations.last() != Some(&true) {
+                continue;
+            }
+            let event = data
+                .split(' ')
+                .next()
+                .and_then(|data| general_purpose::STANDARD.decode(data).ok())
+                .and_then(|data| borsh::from_slice::<LendingEvent>(&data).ok());
+            events.extend(event);
+            continue;
+        }
+
+        let mut words = log.split(' ');
+        if words.next() != Some("Program") {
+            continue;
+        }
+        match (words.next(), words.next()) {
+            (Some(invoked_program_id), Some("invoke")) => {
+                invocations.push(invoked_program_id == program_id);
+            }
+            (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                invocations.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LENDING_PROGRAM_ID: &str = "6TvznH3B2e3p2mbhufNBpgSrLx6UkgvxtVQvopEZ2kuH";
+    const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn pubkey(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    fn fixture(lines: &[&str]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| {
+                line.replace("{lending}", LENDING_PROGRAM_ID)
+                    .replace("{token}", TOKEN_PROGRAM_ID)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_deposit_event() {
+        let logs = fixture(&[
+            "Program {lending} invoke [1]",
+            "Program log: Instruction: Deposit Reserve Liquidity",
+            "Program {token} invoke [2]",
+            "Program log: Instruction: Transfer",
+            "Program {token} consumed 4645 of 178562 compute units",
+            "Program {token} success",
+            "Program {token} invoke [2]",
+            "Program log: Instruction: MintTo",
+            "Program {token} consumed 4492 of 170884 compute units",
+            "Program {token} success",
+            "Program data: AAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgLoAwAAAAAAAIgTAAAAAAAA",
+            "Program {lending} consumed 35687 of 200000 compute units",
+            "Program {lending} success",
+        ]);
+        assert_eq!(
+            parse_lending_events(&crate::id(), &logs),
+            vec![LendingEvent::Deposit(DepositEvent {
+                reserve: pubkey(1),
+                user: pubkey(2),
+                liquidity_amount: 1000,
+                collateral_amount: 5000,
+            })]
+        );
+    }
+
+    #[test]
+    fn parse_events_of_several_instructions() {
+        let logs = fixture(&[
+            "Program {lending} invoke [1]",
+            "Program log: Instruction: Refresh Obligation",
+            "Program {lending} consumed 8210 of 400000 compute units",
+            "Program {lending} success",
+            "Program {lending} invoke [1]",
+            "Program log: Instruction: Borrow Obligation Liquidity",
+            "Program {token} invoke [2]",
+            "Program log: Instruction: Transfer",
+            "Program {token} consumed 4645 of 360213 compute units",
+            "Program {token} success",
+            "Program data: AQMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBWQAAAAAAAAAAQAAAAAAAAA=",
+            "Program {lending} consumed 41874 of 391790 compute units",
+            "Program {lending} success",
+            "Program {lending} invoke [1]",
+            "Program log: Instruction: Repay Obligation Liquidity",
+            "Program {token} invoke [2]",
+            "Program log: Instruction: Transfer",
+            "Program {token} consumed 4645 of 323112 compute units",
+            "Program {token} success",
+            "Program data: AgMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBTIAAAAAAAAA",
+            "Program {lending} consumed 30118 of 349916 compute units",
+            "Program {lending} success",
+        ]);
+        assert_eq!(
+            parse_lending_events(&crate::id(), &logs),
+            vec![
+                LendingEvent::Borrow(BorrowEvent {
+                    reserve: pubkey(3),
+                    obligation: pubkey(4),
+                    user: pubkey(5),
+                    liquidity_amount: 100,
+                    borrow_fee: 1,
+                }),
+                LendingEvent::Repay(RepayEvent {
+                    reserve: pubkey(3),
+                    obligation: pubkey(4),
+                    user: pubkey(5),
+                    liquidity_amount: 50,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_skips_data_of_other_programs() {
+        // the same event data logged by the token program and by a program
+        // invoking the lending program is not a lending event
+        let event_data = "Program data: AwQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBhQAAAAAAAAAbgAAAAAAAAAKAAAAAAAAAA==";
+        let logs = fixture(&[
+            "Program 11111111111111111111111111111111 invoke [1]",
+            event_data,
+            "Program {lending} invoke [2]",
+            "Program log: Instruction: Liquidate Obligation",
+            "Program {token} invoke [3]",
+            "Program log: Instruction: Transfer",
+            event_data,
+            "Program {token} consumed 4645 of 150312 compute units",
+            "Program {token} success",
+            event_data,
+            "Program data: bm90IGFuIGV2ZW50",
+            "Program {lending} consumed 52004 of 190288 compute units",
+            "Program {lending} success",
+            event_data,
+            "Program 11111111111111111111111111111111 success",
+        ]);
+        assert_eq!(
+            parse_lending_events(&crate::id(), &logs),
+            vec![LendingEvent::Liquidate(LiquidateEvent {
+                obligation: pubkey(4),
+                repay_reserve: pubkey(3),
+                withdraw_reserve: pubkey(6),
+                repay_amount: 20,
+                seized_collateral: 110,
+                bonus: 10,
+            })]
+        );
+    }
+
+    #[test]
+    fn parse_failed_invocation() {
+        let logs = fixture(&[
+            "Program {lending} invoke [1]",
+            "Program log: Instruction: Liquidate Obligation",
+            "Program log: Obligation is healthy and cannot be liquidated",
+            "Program {lending} consumed 21420 of 200000 compute units",
+            "Program {lending} failed: custom program error: 0x19",
+            "Program data: AAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgLoAwAAAAAAAIgTAAAAAAAA",
+        ]);
+        assert!(parse_lending_events(&crate::id(), &logs).is_empty());
+    }
+
+    #[test]
+    fn emitted_event_roundtrip() {
+        let event = LendingEvent::Deposit(DepositEvent {
+            reserve: pubkey(1),
+            user: pubkey(2),
+            liquidity_amount: 1000,
+            collateral_amount: 5000,
+        });
+        let data = general_purpose::STANDARD.encode(borsh::to_vec(&event).unwrap());
+        let logs = vec![
+            format!("Program {} invoke [1]", LENDING_PROGRAM_ID),
+            format!("{}{}", PROGRAM_DATA_LOG_PREFIX, data),
+            format!("Program {} success", LENDING_PROGRAM_ID),
+        ];
+        assert_eq!(parse_lending_events(&crate::id(), &logs), vec![event]);
+    }
+}
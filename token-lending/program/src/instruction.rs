@@ -3,15 +3,22 @@
 use {
     crate::{
         error::LendingError,
-        state::{ReserveConfig, ReserveFees},
+        math::Decimal,
+        state::{
+            find_borrow_allowlist_entry_address, find_redeem_request_address,
+            find_reward_authority_address, find_reward_checkpoint_address,
+            get_market_metadata_address, get_obligation_address, ReserveConfig, ReserveFees,
+            ReserveStatus,
+        },
     },
     solana_program::{
         instruction::{AccountMeta, Instruction},
         msg,
         program_error::ProgramError,
         pubkey::{Pubkey, PUBKEY_BYTES},
-        sysvar,
+        system_program, sysvar,
     },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
     std::{convert::TryInto, mem::size_of},
 };
 
@@ -38,7 +45,9 @@ pub enum LendingInstruction {
     },
 
     // 1
-    /// Sets the new owner of a lending market.
+    /// Sets the new owner of a lending market immediately and clears any
+    /// pending owner. Prefer ProposeLendingMarketOwner, which requires the new
+    /// owner to accept.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -58,15 +67,18 @@ pub enum LendingInstruction {
     ///      transfer $liquidity_amount.
     ///   1. `[writable]` Destination collateral token account - uninitialized.
     ///   2. `[writable]` Reserve account - uninitialized.
-    ///   3. `[]` Reserve liquidity SPL Token mint.
+    ///   3. `[]` Reserve liquidity SPL Token mint. Token-2022 mints are
+    ///      rejected.
     ///   4. `[writable]` Reserve liquidity supply SPL Token account -
     ///      uninitialized.
-    ///   5. `[writable]` Reserve liquidity fee receiver - uninitialized.
+    ///   5. `[writable]` Reserve liquidity fee receiver - uninitialized. The
+    ///      lending market authority's associated token account when
+    ///      $derive_fee_receiver is set, created if missing.
     ///   6. `[writable]` Reserve collateral SPL Token mint - uninitialized.
     ///   7. `[writable]` Reserve collateral token supply - uninitialized.
-    ///   8. `[]` Pyth product account.
-    ///   9. `[]` Pyth price account. This will be used as the reserve liquidity
-    ///      oracle account.
+    ///   8. `[]` Pyth product account, or the Switchboard aggregator account.
+    ///   9. `[]` Pyth price account, or the Switchboard aggregator account.
+    ///      This will be used as the reserve liquidity oracle account.
     ///   10 `[]` Lending market account.
     ///   11 `[]` Derived lending market authority.
     ///   12 `[signer]` Lending market owner.
@@ -74,15 +86,26 @@ pub enum LendingInstruction {
     ///   14 `[]` Clock sysvar.
     ///   15 `[]` Rent sysvar.
     ///   16 `[]` Token program id.
+    ///
+    ///
+    ///   Required when $derive_fee_receiver is set:
+    ///   17 `[writable, signer]` Payer of the fee receiver rent.
+    ///   18 `[]` System program id.
+    ///   19 `[]` Associated token program id.
     InitReserve {
         /// Initial amount of liquidity to deposit into the new reserve
         liquidity_amount: u64,
         /// Reserve configuration values
         config: ReserveConfig,
+        /// Use the lending market authority's associated token account for the
+        /// liquidity mint as the reserve liquidity fee receiver
+        derive_fee_receiver: bool,
     },
 
     // 3
     /// Accrue interest and update market price of liquidity on a reserve.
+    /// Reserves with a max accrual slots per refresh accrue at most that many
+    /// slots of interest, catching up over the next refreshes.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -112,6 +135,8 @@ pub enum LendingInstruction {
     DepositReserveLiquidity {
         /// Amount of liquidity to deposit in exchange for collateral tokens
         liquidity_amount: u64,
+        /// Minimum amount of collateral tokens to receive, 0 to disable
+        min_collateral_amount: u64,
     },
 
     // 5
@@ -133,6 +158,8 @@ pub enum LendingInstruction {
     RedeemReserveCollateral {
         /// Amount of collateral tokens to redeem in exchange for liquidity
         collateral_amount: u64,
+        /// Minimum amount of liquidity to receive, 0 to disable
+        min_liquidity_amount: u64,
     },
 
     // 6
@@ -152,7 +179,8 @@ pub enum LendingInstruction {
     /// Refresh an obligation's accrued interest and collateral and liquidity
     /// prices. Requires refreshed reserves, as all obligation collateral
     /// deposit reserves in order, followed by all liquidity borrow reserves
-    /// in order.
+    /// in order. Fails if a reserve price was published longer ago than the
+    /// reserve max oracle age, even if the reserve was refreshed.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -180,6 +208,9 @@ pub enum LendingInstruction {
     ///   6. `[signer]` User transfer authority ($authority).
     ///   7. `[]` Clock sysvar.
     ///   8. `[]` Token program id.
+    ///   .. `[]` Collateral deposit reserve accounts of the obligation, all, in
+    ///      order. Isolated collateral cannot be combined with collateral from
+    ///      other reserves.
     DepositObligationCollateral {
         /// Amount of collateral tokens to deposit
         collateral_amount: u64,
@@ -195,7 +226,9 @@ pub enum LendingInstruction {
     ///      account.
     ///   1. `[writable]` Destination collateral token account. Minted by
     ///      withdraw reserve collateral mint.
-    ///   2. `[]` Withdraw reserve account - refreshed.
+    ///   2. `[writable]` Withdraw reserve account - refreshed. Writable so the
+    ///      withdrawn liquidity counts against its outflow rate limit; it was
+    ///      read-only before the limit was added.
     ///   3. `[writable]` Obligation account - refreshed.
     ///   4. `[]` Lending market account.
     ///   5. `[]` Derived lending market authority.
@@ -227,7 +260,12 @@ pub enum LendingInstruction {
     ///   7. `[signer]` Obligation owner.
     ///   8. `[]` Clock sysvar.
     ///   9. `[]` Token program id.
-    ///   10 `[optional, writable]` Host fee receiver account.
+    ///   .. `[]` Collateral deposit reserve accounts of the obligation, all, in
+    ///      order. Only reserves borrowable in isolation can be borrowed from
+    ///      against isolated collateral.
+    ///   .. `[]` Borrow allowlist entry of the obligation owner. Optional,
+    ///      required when the lending market has permissioned borrowing.
+    ///   .. `[optional, writable]` Host fee receiver account.
     BorrowObligationLiquidity {
         /// Amount of liquidity to borrow - u64::MAX for 100% of borrowing power
         liquidity_amount: u64,
@@ -252,6 +290,12 @@ pub enum LendingInstruction {
     ///   5. `[signer]` User transfer authority ($authority).
     ///   6. `[]` Clock sysvar.
     ///   7. `[]` Token program id.
+    ///
+    /// The obligation owner doesn't sign: anyone whose $authority can
+    /// transfer from the source liquidity account can repay any obligation,
+    /// e.g. liquidation protection bots or smart wallets. Repaying only
+    /// reduces the borrow and never moves collateral or liquidity out of the
+    /// obligation.
     RepayObligationLiquidity {
         /// Amount of liquidity to repay - u64::MAX for 100% of borrowed amount
         liquidity_amount: u64,
@@ -278,6 +322,8 @@ pub enum LendingInstruction {
     ///   9. `[signer]` User transfer authority ($authority).
     ///   10 `[]` Clock sysvar.
     ///   11 `[]` Token program id.
+    ///   12 `[writable]` Withdraw reserve collateral fee receiver account.
+    ///      Optional, required when a protocol liquidation fee is taken.
     LiquidateObligation {
         /// Amount of liquidity to repay - u64::MAX for up to 100% of borrowed
         /// amount
@@ -295,7 +341,8 @@ pub enum LendingInstruction {
     ///      liquidity mint.
     ///   2. `[writable]` Reserve account.
     ///   3. `[writable]` Flash loan fee receiver account. Must match the
-    ///      reserve liquidity fee receiver.
+    ///      reserve liquidity flash loan fee receiver, or the reserve liquidity
+    ///      fee receiver if no flash loan fee receiver is set.
     ///   4. `[writable]` Host fee receiver.
     ///   5. `[]` Lending market account.
     ///   6. `[]` Derived lending market authority.
@@ -324,6 +371,8 @@ pub enum LendingInstruction {
     ///       // Amount that must be repaid by the receiver program
     ///       amount: u64
     ///   }
+    ///
+    ///   The `cpi` module has helpers to implement the receiver program.
     FlashLoan {
         /// The amount that is to be borrowed - u64::MAX for up to 100% of
         /// available liquidity
@@ -332,771 +381,3769 @@ pub enum LendingInstruction {
 
     // 14
     /// Modify the ReserveConfig parameters of an already initialized Reserve
-    /// account
+    /// account. The lending market risk authority can only make changes
+    /// which reduce risk, see `ReserveConfig::assert_tightens`.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   0. `[writable]` Reserve account
     ///   1. `[]` Lending market account
-    ///   2. `[signer]` Lending market owner
+    ///   2. `[signer]` Lending market owner or risk authority
     ModifyReserveConfig {
         /// Reserve configuration updated values
         new_config: ReserveConfig,
     },
-}
 
-impl LendingInstruction {
-    /// Unpacks a byte buffer into a
-    /// [LendingInstruction](enum.LendingInstruction.html).
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (&tag, rest) = input
-            .split_first()
-            .ok_or(LendingError::InstructionUnpackError)?;
-        Ok(match tag {
-            0 => {
-                let (owner, rest) = Self::unpack_pubkey(rest)?;
-                let (quote_currency, _rest) = Self::unpack_bytes32(rest)?;
-                Self::InitLendingMarket {
-                    owner,
-                    quote_currency: *quote_currency,
-                }
-            }
-            1 => {
-                let (new_owner, _rest) = Self::unpack_pubkey(rest)?;
-                Self::SetLendingMarketOwner { new_owner }
-            }
-            2 => {
-                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
-                let config = Self::unpack_reserve_config(rest)?;
-                Self::InitReserve {
-                    liquidity_amount,
-                    config,
-                }
-            }
-            3 => Self::RefreshReserve,
-            4 => {
-                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::DepositReserveLiquidity { liquidity_amount }
-            }
-            5 => {
-                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::RedeemReserveCollateral { collateral_amount }
-            }
-            6 => Self::InitObligation,
-            7 => Self::RefreshObligation,
-            8 => {
-                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::DepositObligationCollateral { collateral_amount }
-            }
-            9 => {
-                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::WithdrawObligationCollateral { collateral_amount }
-            }
-            10 => {
-                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
-                let (slippage_limit, _rest) = Self::unpack_u64(rest).unwrap_or((0, &[]));
-                Self::BorrowObligationLiquidity {
-                    liquidity_amount,
-                    slippage_limit,
-                }
-            }
-            11 => {
-                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::RepayObligationLiquidity { liquidity_amount }
-            }
-            12 => {
-                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
-                Self::LiquidateObligation { liquidity_amount }
-            }
-            13 => {
-                let (amount, _rest) = Self::unpack_u64(rest)?;
-                Self::FlashLoan { amount }
-            }
-            14 => {
-                let new_config = Self::unpack_reserve_config(rest)?;
-                Self::ModifyReserveConfig { new_config }
-            }
-            _ => {
-                msg!("Instruction cannot be unpacked");
-                return Err(LendingError::InstructionUnpackError.into());
-            }
-        })
-    }
+    // 15
+    /// Deposit collateral to an obligation and borrow liquidity against it in
+    /// a single instruction. Requires a refreshed obligation and reserves, the
+    /// obligation values are updated for the new collateral without another
+    /// refresh.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source collateral token account. Minted by deposit
+    ///      reserve collateral mint. $authority can transfer
+    ///      $collateral_amount.
+    ///   1. `[writable]` Destination deposit reserve collateral supply SPL
+    ///      Token account.
+    ///   2. `[]` Deposit reserve account - refreshed.
+    ///   3. `[writable]` Source borrow reserve liquidity supply SPL Token
+    ///      account.
+    ///   4. `[writable]` Destination liquidity token account. Minted by borrow
+    ///      reserve liquidity mint.
+    ///   5. `[writable]` Borrow reserve account - refreshed.
+    ///   6. `[writable]` Borrow reserve liquidity fee receiver account. Must be
+    ///      the fee account specified at InitReserve.
+    ///   7. `[writable]` Obligation account - refreshed.
+    ///   8. `[]` Lending market account.
+    ///   9. `[]` Derived lending market authority.
+    ///   10 `[signer]` Obligation owner.
+    ///   11 `[signer]` User transfer authority ($authority).
+    ///   12 `[]` Clock sysvar.
+    ///   13 `[]` Token program id.
+    ///   .. `[]` Collateral deposit reserve accounts of the obligation before
+    ///      the deposit, all, in order.
+    ///   .. `[]` Borrow allowlist entry of the obligation owner. Optional,
+    ///      required when the lending market has permissioned borrowing.
+    ///   .. `[optional, writable]` Host fee receiver account.
+    DepositObligationCollateralAndBorrow {
+        /// Amount of collateral tokens to deposit
+        collateral_amount: u64,
+        /// Amount of liquidity to borrow - u64::MAX for 100% of borrowing power
+        liquidity_amount: u64,
+        /// Minimum amount of liquidity to receive, if borrowing 100% of
+        /// borrowing power
+        slippage_limit: u64,
+    },
 
-    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
-        if input.len() < 8 {
-            msg!("u64 cannot be unpacked");
-            return Err(LendingError::InstructionUnpackError.into());
-        }
-        let (bytes, rest) = input.split_at(8);
-        let value = bytes
-            .get(..8)
-            .and_then(|slice| slice.try_into().ok())
-            .map(u64::from_le_bytes)
-            .ok_or(LendingError::InstructionUnpackError)?;
-        Ok((value, rest))
-    }
+    // 16
+    /// Proposes a new owner of a lending market, who becomes the owner once
+    /// they accept with AcceptLendingMarketOwner. Replaces any pending owner,
+    /// the default pubkey cancels a pending transfer.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Current owner.
+    ProposeLendingMarketOwner {
+        /// The proposed owner
+        new_owner: Pubkey,
+    },
 
-    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
-        if input.is_empty() {
-            msg!("u8 cannot be unpacked");
-            return Err(LendingError::InstructionUnpackError.into());
-        }
-        let (bytes, rest) = input.split_at(1);
-        let value = bytes
-            .get(..1)
-            .and_then(|slice| slice.try_into().ok())
-            .map(u8::from_le_bytes)
-            .ok_or(LendingError::InstructionUnpackError)?;
-        Ok((value, rest))
-    }
+    // 17
+    /// Accepts the ownership of a lending market proposed with
+    /// ProposeLendingMarketOwner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Pending owner.
+    AcceptLendingMarketOwner,
 
-    fn unpack_bytes32(input: &[u8]) -> Result<(&[u8; 32], &[u8]), ProgramError> {
-        if input.len() < 32 {
-            msg!("32 bytes cannot be unpacked");
-            return Err(LendingError::InstructionUnpackError.into());
-        }
-        let (bytes, rest) = input.split_at(32);
-        Ok((
-            bytes
-                .try_into()
-                .map_err(|_| LendingError::InstructionUnpackError)?,
-            rest,
-        ))
-    }
+    // 18
+    /// Sets the actions paused on a reserve. Repayments cannot be paused.
+    /// The lending market risk authority can only set flags, not clear them.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account
+    ///   1. `[]` Lending market account
+    ///   2. `[signer]` Lending market owner or risk authority
+    SetReservePauseFlags {
+        /// Bitmask of the RESERVE_PAUSE_* flags, replacing the current flags
+        pause_flags: u8,
+    },
 
-    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
-        if input.len() < PUBKEY_BYTES {
-            msg!("Pubkey cannot be unpacked");
-            return Err(LendingError::InstructionUnpackError.into());
-        }
-        let (key, rest) = input.split_at(PUBKEY_BYTES);
-        let pk = Pubkey::try_from(key).map_err(|_| LendingError::InstructionUnpackError)?;
-        Ok((pk, rest))
-    }
+    // 19
+    /// Replaces the oracle a reserve reads its liquidity market price from.
+    /// The oracle kind is determined by the owner of the oracle price account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Pyth product account, or the Switchboard aggregator account.
+    ///   2. `[]` Pyth price account, or the Switchboard aggregator account.
+    ///      This will be used as the reserve liquidity oracle account.
+    ///   3. `[]` Lending market account.
+    ///   4. `[signer]` Lending market owner.
+    ///   5. `[]` Clock sysvar.
+    SetReserveOracle,
 
-    fn unpack_reserve_config(input: &[u8]) -> Result<ReserveConfig, ProgramError> {
-        let (optimal_utilization_rate, rest) = Self::unpack_u8(input)?;
-        let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
-        let (liquidation_bonus, rest) = Self::unpack_u8(rest)?;
-        let (liquidation_threshold, rest) = Self::unpack_u8(rest)?;
-        let (min_borrow_rate, rest) = Self::unpack_u8(rest)?;
-        let (optimal_borrow_rate, rest) = Self::unpack_u8(rest)?;
-        let (max_borrow_rate, rest) = Self::unpack_u8(rest)?;
-        let (borrow_fee_wad, rest) = Self::unpack_u64(rest)?;
-        let (flash_loan_fee_wad, rest) = Self::unpack_u64(rest)?;
-        let (host_fee_percentage, _rest) = Self::unpack_u8(rest)?;
-
-        Ok(ReserveConfig {
-            optimal_utilization_rate,
-            loan_to_value_ratio,
-            liquidation_bonus,
-            liquidation_threshold,
-            min_borrow_rate,
-            optimal_borrow_rate,
-            max_borrow_rate,
-            fees: ReserveFees {
-                borrow_fee_wad,
-                flash_loan_fee_wad,
-                host_fee_percentage,
-            },
-        })
-    }
+    // 20
+    /// Sets the token account a reserve pays flash loan fees to, separately
+    /// from the reserve liquidity fee receiver.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Flash loan fee receiver token account. Minted by reserve
+    ///      liquidity mint.
+    ///   2. `[]` Lending market account.
+    ///   3. `[signer]` Lending market owner.
+    SetReserveFlashLoanFeeReceiver,
 
-    /// Packs a [LendingInstruction](enum.LendingInstruction.html) into a byte
-    /// buffer.
-    pub fn pack(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(size_of::<Self>());
-        match *self {
-            Self::InitLendingMarket {
-                owner,
-                quote_currency,
-            } => {
-                buf.push(0);
-                buf.extend_from_slice(owner.as_ref());
-                buf.extend_from_slice(quote_currency.as_ref());
-            }
-            Self::SetLendingMarketOwner { new_owner } => {
-                buf.push(1);
-                buf.extend_from_slice(new_owner.as_ref());
-            }
-            Self::InitReserve {
-                liquidity_amount,
-                config,
-            } => {
-                buf.push(2);
-                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-                Self::extend_buffer_from_reserve_config(&mut buf, &config);
-            }
-            Self::RefreshReserve => {
-                buf.push(3);
-            }
-            Self::DepositReserveLiquidity { liquidity_amount } => {
-                buf.push(4);
-                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-            }
-            Self::RedeemReserveCollateral { collateral_amount } => {
-                buf.push(5);
-                buf.extend_from_slice(&collateral_amount.to_le_bytes());
-            }
-            Self::InitObligation => {
-                buf.push(6);
-            }
-            Self::RefreshObligation => {
-                buf.push(7);
-            }
-            Self::DepositObligationCollateral { collateral_amount } => {
-                buf.push(8);
-                buf.extend_from_slice(&collateral_amount.to_le_bytes());
-            }
-            Self::WithdrawObligationCollateral { collateral_amount } => {
-                buf.push(9);
-                buf.extend_from_slice(&collateral_amount.to_le_bytes());
-            }
-            Self::BorrowObligationLiquidity {
-                liquidity_amount,
-                slippage_limit,
-            } => {
-                buf.push(10);
-                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-                buf.extend_from_slice(&slippage_limit.to_le_bytes());
-            }
-            Self::RepayObligationLiquidity { liquidity_amount } => {
-                buf.push(11);
-                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-            }
-            Self::LiquidateObligation { liquidity_amount } => {
-                buf.push(12);
-                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-            }
-            Self::FlashLoan { amount } => {
-                buf.push(13);
-                buf.extend_from_slice(&amount.to_le_bytes());
-            }
-            Self::ModifyReserveConfig { new_config } => {
-                buf.push(14);
-                Self::extend_buffer_from_reserve_config(&mut buf, &new_config);
+    // 21
+    /// Sets the token account a reserve pays protocol liquidation fees to, in
+    /// reserve collateral.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Collateral fee receiver token account. Minted by reserve
+    ///      collateral mint.
+    ///   2. `[]` Lending market account.
+    ///   3. `[signer]` Lending market owner.
+    SetReserveCollateralFeeReceiver,
+
+    // 22
+    /// Move collateral from one reserve to another reserve of the same
+    /// liquidity mint without leaving the obligation. Source collateral is
+    /// redeemed, the liquidity deposited into the destination reserve, and the
+    /// destination collateral credited to the obligation. Requires a refreshed
+    /// obligation and reserves.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source reserve account - refreshed.
+    ///   1. `[writable]` Source reserve collateral supply SPL Token account.
+    ///   2. `[writable]` Source reserve collateral SPL Token mint.
+    ///   3. `[writable]` Source reserve liquidity supply SPL Token account.
+    ///   4. `[writable]` Destination reserve account - refreshed.
+    ///   5. `[writable]` Destination reserve liquidity supply SPL Token
+    ///      account.
+    ///   6. `[writable]` Destination reserve collateral SPL Token mint.
+    ///   7. `[writable]` Destination reserve collateral supply SPL Token
+    ///      account.
+    ///   8. `[writable]` Obligation account - refreshed.
+    ///   9. `[]` Lending market account.
+    ///   10 `[]` Derived lending market authority.
+    ///   11 `[signer]` Obligation owner.
+    ///   12 `[]` Clock sysvar.
+    ///   13 `[]` Token program id.
+    MigrateObligationCollateral {
+        /// Amount of source collateral tokens to migrate - u64::MAX for 100% of
+        /// deposited amount
+        collateral_amount: u64,
+    },
+
+    // 23
+    /// Close an empty reserve, returning its rent to a destination account
+    /// and removing the reserve collateral mint authority. The reserve must
+    /// have no available liquidity, no borrows, no collateral in circulation
+    /// and an empty liquidity fee receiver.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[writable]` Reserve collateral SPL Token mint.
+    ///   2. `[]` Reserve liquidity fee receiver account.
+    ///   3. `[writable]` Destination account for the reserve rent lamports.
+    ///   4. `[]` Lending market account.
+    ///   5. `[]` Derived lending market authority.
+    ///   6. `[signer]` Lending market owner.
+    ///   7. `[]` Token program id.
+    CloseReserve,
+
+    // 24
+    /// Withdraw accrued protocol fees from a reserve liquidity fee receiver,
+    /// which is owned by the derived lending market authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` Reserve account.
+    ///   1. `[writable]` Reserve liquidity fee receiver account.
+    ///   2. `[writable]` Destination liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///   3. `[]` Lending market account.
+    ///   4. `[]` Derived lending market authority.
+    ///   5. `[signer]` Lending market owner.
+    ///   6. `[]` Token program id.
+    WithdrawProtocolFees {
+        /// Amount of liquidity to withdraw - u64::MAX for the full fee
+        /// receiver balance
+        amount: u64,
+    },
+
+    // 25
+    /// Sets the emergency authority of a lending market, which can freeze the
+    /// market with SetMarketFreeze. The default pubkey removes the emergency
+    /// authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetLendingMarketEmergencyAuthority {
+        /// The new emergency authority
+        emergency_authority: Pubkey,
+    },
+
+    // 26
+    /// Freezes or unfreezes a lending market. While frozen, deposits, borrows,
+    /// collateral withdrawals and flash loans fail in every reserve of the
+    /// market. The market can be frozen by its owner or emergency authority,
+    /// but only unfrozen by its owner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner or emergency authority.
+    SetMarketFreeze {
+        /// Whether the market is frozen
+        frozen: bool,
+    },
+
+    // 27
+    /// Transfers an obligation to a new owner, along with its deposits and
+    /// borrows. The new owner must sign so liabilities can't be moved onto an
+    /// unwilling key. The obligation must be refreshed before its next use.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[signer]` Obligation owner.
+    ///   2. `[signer]` New obligation owner.
+    SetObligationOwner,
+
+    // 28
+    /// Grows an obligation account so it can hold more deposits and borrows
+    /// combined than MAX_OBLIGATION_RESERVES. Each additional reserve slot can
+    /// hold either a deposit or a borrow. The payer funds the rent for the
+    /// larger account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[signer]` Obligation owner.
+    ///   2. `[writable, signer]` Payer.
+    ///   3. `[]` Rent sysvar.
+    ///   4. `[]` System program.
+    ResizeObligation {
+        /// Number of deposit or borrow slots to add
+        additional_reserves: u8,
+    },
+
+    // 29
+    /// Sets the lifecycle status of a reserve. Deprecated reserves reject new
+    /// deposits and borrows, obsolete reserves reject redemptions as well.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    SetReserveStatus {
+        /// New reserve status
+        status: ReserveStatus,
+    },
+
+    // 30
+    /// Donates liquidity to a reserve without minting collateral, raising the
+    /// exchange rate for existing collateral holders. Used to make suppliers
+    /// whole after a shortfall, such as bad debt left by an underwater
+    /// liquidation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account. $authority can
+    ///      transfer $liquidity_amount.
+    ///   1. `[writable]` Reserve account - refreshed.
+    ///   2. `[writable]` Reserve liquidity supply SPL Token account.
+    ///   3. `[]` Lending market account.
+    ///   4. `[signer]` User transfer authority ($authority).
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Token program id.
+    DonateLiquidity {
+        /// Amount of liquidity to donate
+        liquidity_amount: u64,
+    },
+
+    // 31
+    /// Enables or disables permissioned borrowing in a lending market. While
+    /// enabled, only obligation owners on the market borrow allowlist can
+    /// borrow. Deposits, redemptions, repays and liquidations stay
+    /// permissionless.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetPermissionedBorrowing {
+        /// Whether borrowing is limited to the borrow allowlist
+        permissioned_borrowing: bool,
+    },
+
+    // 32
+    /// Adds an obligation owner to the borrow allowlist of a lending market,
+    /// creating their allowlist entry at the address derived from the
+    /// lending market and the borrower.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Borrow allowlist entry account - uninitialized.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[writable, signer]` Payer of the entry rent.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program.
+    AddToBorrowAllowlist {
+        /// Obligation owner allowed to borrow
+        borrower: Pubkey,
+    },
+
+    // 33
+    /// Removes an obligation owner from the borrow allowlist of a lending
+    /// market, closing their allowlist entry. Existing borrows can still be
+    /// repaid and liquidated.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Borrow allowlist entry account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[writable]` Destination of the entry rent.
+    RemoveFromBorrowAllowlist,
+
+    // 34
+    /// Sets the rate and vault of the incentive rewards paid to the collateral
+    /// holders of a reserve. Rewards at the previous rate are accrued up to
+    /// the current slot first, and a zero rate stops the rewards.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account - refreshed.
+    ///   1. `[]` Reward vault - token account owned by the reserve reward
+    ///                          authority, derived from the reserve.
+    ///   2. `[]` Lending market account.
+    ///   3. `[signer]` Lending market owner.
+    ///   4. `[]` Clock sysvar.
+    SetReserveRewards {
+        /// Rewards distributed per slot across the reserve collateral supply
+        rewards_per_slot: u64,
+    },
+
+    // 35
+    /// Claims the incentive rewards accrued by the collateral an obligation
+    /// has deposited from a reserve. The first claim creates the reward
+    /// checkpoint of the deposit and pays nothing, rewards accrue from then.
+    /// Claim before changing the deposit, rewards accrue on the lesser of the
+    /// deposits at two claims.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reward checkpoint account, derived from the
+    ///                     obligation and the reserve.
+    ///   1. `[]` Obligation account.
+    ///   2. `[]` Reserve account - refreshed.
+    ///   3. `[writable]` Reward vault of the reserve.
+    ///   4. `[writable]` Destination reward token account.
+    ///   5. `[]` Lending market account.
+    ///   6. `[]` Reserve reward authority.
+    ///   7. `[signer]` Obligation owner.
+    ///   8. `[writable, signer]` Payer of the checkpoint rent.
+    ///   9. `[]` Clock sysvar.
+    ///   10 `[]` Rent sysvar.
+    ///   11 `[]` System program.
+    ///   12 `[]` Token program id.
+    ClaimReserveRewards,
+
+    // 36
+    /// Deposit collateral to an obligation on behalf of its owner, who does
+    /// not sign. Only the owner can withdraw the collateral. Requires a
+    /// refreshed reserve.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source collateral token account. Minted by deposit
+    ///      reserve collateral mint. $authority can transfer
+    ///      $collateral_amount.
+    ///   1. `[writable]` Destination deposit reserve collateral supply SPL
+    ///      Token account.
+    ///   2. `[]` Deposit reserve account - refreshed.
+    ///   3. `[writable]` Obligation account.
+    ///   4. `[]` Lending market account.
+    ///   5. `[signer]` User transfer authority ($authority).
+    ///   6. `[]` Clock sysvar.
+    ///   7. `[]` Token program id.
+    ///   .. `[]` Collateral deposit reserve accounts of the obligation, all, in
+    ///      order. Isolated collateral cannot be combined with collateral from
+    ///      other reserves.
+    DepositObligationCollateralOnBehalf {
+        /// Amount of collateral tokens to deposit
+        collateral_amount: u64,
+    },
+
+    // 37
+    /// Sets or clears an emergency price override for a reserve, used by
+    /// refreshes in place of the oracle price until cleared. The reserve must
+    /// be refreshed after either.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    SetPriceOverride {
+        /// Market price of the reserve liquidity, None to use the oracle again
+        price_override: Option<Decimal>,
+    },
+
+    // 38
+    /// Creates or updates the display metadata of a lending market at the
+    /// address derived from the lending market, resizing it to fit the new
+    /// name and description URI.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Market metadata account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[writable, signer]` Payer of the metadata rent.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program.
+    SetMarketMetadata {
+        /// Display name of the market, at most 64 bytes
+        name: String,
+        /// URI of the market description, at most 200 bytes
+        description_uri: String,
+        /// Flags reserved for future use
+        flags: u8,
+    },
+
+    // 39
+    /// Borrows liquidity from a reserve to be repaid later in the same
+    /// transaction by a matching `FlashRepayReserveLiquidity` instruction.
+    ///
+    /// The transaction must contain exactly one `FlashRepayReserveLiquidity`
+    /// after this instruction for the same reserve and amount which points
+    /// back to this instruction. Both instructions must be top level
+    /// instructions of the transaction and can't be invoked via CPI.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account. Must match the
+    ///      reserve liquidity supply.
+    ///   1. `[writable]` Destination liquidity token account. Minted by
+    ///      reserve liquidity mint.
+    ///   2. `[writable]` Reserve account.
+    ///   3. `[]` Lending market account.
+    ///   4. `[]` Derived lending market authority.
+    ///   5. `[]` Instructions sysvar.
+    ///   6. `[]` Token program id.
+    FlashBorrowReserveLiquidity {
+        /// Amount of liquidity to flash borrow
+        liquidity_amount: u64,
+    },
+
+    // 40
+    /// Repays liquidity flash borrowed by a `FlashBorrowReserveLiquidity`
+    /// instruction earlier in the same transaction, along with the flash loan
+    /// fees.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account. $authority can
+    ///      transfer $liquidity_amount plus the flash loan fees.
+    ///   1. `[writable]` Destination liquidity token account. Must match the
+    ///      reserve liquidity supply.
+    ///   2. `[writable]` Flash loan fee receiver account. Must match the
+    ///      reserve liquidity flash loan fee receiver, or the reserve liquidity
+    ///      fee receiver if no flash loan fee receiver is set.
+    ///   3. `[writable]` Host fee receiver.
+    ///   4. `[writable]` Reserve account.
+    ///   5. `[]` Lending market account.
+    ///   6. `[signer]` User transfer authority ($authority).
+    ///   7. `[]` Instructions sysvar.
+    ///   8. `[]` Token program id.
+    FlashRepayReserveLiquidity {
+        /// Amount of liquidity flash borrowed, excluding the fees
+        liquidity_amount: u64,
+        /// Index of the matching `FlashBorrowReserveLiquidity` instruction in
+        /// the transaction
+        borrow_instruction_index: u8,
+    },
+
+    // 41
+    /// Sets the minimum market value of an obligation's borrows after a
+    /// borrow. Repays can still leave borrows below it.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetMinBorrowValue {
+        /// Minimum borrow value in the quote currency, zero to disable
+        min_borrow_value: Decimal,
+    },
+
+    // 42
+    /// Migrates an obligation of an older program version to the current
+    /// version, preserving its deposits and borrows. The obligation is left
+    /// stale and must be refreshed before it is used. Anyone can migrate an
+    /// obligation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[]` Lending market account.
+    MigrateObligation,
+
+    // 43
+    /// Sets the borrowed value below which an obligation's borrows can be
+    /// liquidated in full, ignoring the close factor.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetDustLiquidationThreshold {
+        /// Dust liquidation threshold in the quote currency, zero to disable
+        dust_liquidation_threshold: Decimal,
+    },
+
+    // 44
+    /// Migrates a lending market or reserve account in the V1 layout to the
+    /// V2 layout, resizing it and bumping its version. Fields added in the V2
+    /// layout start at their defaults. Anyone can migrate an account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market or reserve account.
+    ///   1. `[signer, writable]` Payer of the rent for the resized account.
+    ///   2. `[]` Rent sysvar.
+    ///   3. `[]` System program.
+    MigrateAccount,
+
+    // 45
+    /// Refreshes several reserves in one instruction, as a `RefreshReserve`
+    /// of each reserve in order. Fails if any of the reserves can't be
+    /// refreshed. Anyone can refresh reserves.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` Clock sysvar.
+    ///   .. `[writable]` Reserve account, followed by its liquidity oracle
+    ///      account, for each of the $reserves reserves.
+    RefreshReserves {
+        /// Number of reserve and oracle account pairs
+        reserves: u8,
+    },
+
+    // 46
+    /// Sets the risk authority of a lending market, which can tighten but not
+    /// loosen reserve configs with ModifyReserveConfig and pause reserves
+    /// with SetReservePauseFlags. The default pubkey removes the risk
+    /// authority. Requires the lending market in the current layout.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetLendingMarketRiskAuthority {
+        /// The new risk authority
+        risk_authority: Pubkey,
+    },
+
+    // 47
+    /// Initializes a new lending market obligation at the address derived
+    /// from the lending market and the obligation owner, so there's at most
+    /// one such obligation per owner and market. It's otherwise the same as
+    /// an obligation created with InitObligation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - uninitialized.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[writable, signer]` Payer of the obligation rent.
+    ///   4. `[]` Clock sysvar.
+    ///   5. `[]` Rent sysvar.
+    ///   6. `[]` System program.
+    InitDerivedObligation,
+
+    // 48
+    /// Redeem collateral from a reserve in exchange for liquidity, or queue
+    /// the redemption if the reserve doesn't have the liquidity for it. While
+    /// redemptions are queued, new ones are queued after them.
+    ///
+    /// A queued redemption moves the collateral to the reserve collateral
+    /// supply and records it on a redeem request at the address derived from
+    /// the reserve and the next ticket of its redeem queue. FulfillRedeem
+    /// redeems it once liquidity returns. The request belongs to the owner of
+    /// the source collateral, who can cancel or reroute it with CancelRedeem.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source collateral token account. $authority can
+    ///      transfer $collateral_amount.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///   2. `[writable]` Reserve account.
+    ///   3. `[writable]` Reserve collateral SPL Token mint.
+    ///   4. `[writable]` Reserve liquidity supply SPL Token account.
+    ///   5. `[writable]` Reserve collateral supply SPL Token account.
+    ///   6. `[writable]` Redeem request account - uninitialized, only
+    ///      created when the redemption is queued.
+    ///   7. `[]` Lending market account.
+    ///   8. `[]` Derived lending market authority.
+    ///   9. `[signer]` User transfer authority ($authority).
+    ///   10 `[writable, signer]` Payer of the redeem request rent. The rent
+    ///      is refunded to the request owner once the request is closed.
+    ///   11 `[]` Clock sysvar.
+    ///   12 `[]` Rent sysvar.
+    ///   13 `[]` System program.
+    ///   14 `[]` Token program id.
+    RequestRedeem {
+        /// Amount of collateral tokens to redeem in exchange for liquidity
+        collateral_amount: u64,
+    },
+
+    // 49
+    /// Fulfill the redeem request at the head of a reserve redeem queue with
+    /// as much liquidity as the reserve has available. The request is closed
+    /// once all of its collateral is redeemed, and the next request moves to
+    /// the head of the queue. Anyone can fulfill requests.
+    ///
+    /// A head request that was cancelled, or whose destination was closed,
+    /// frozen or no longer holds the reserve liquidity, is skipped instead.
+    /// The collateral of a skipped request stays in the reserve collateral
+    /// supply until its owner cancels it.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Redeem request account.
+    ///   1. `[writable]` Destination liquidity token account of the request.
+    ///   2. `[writable]` Reserve account - refreshed.
+    ///   3. `[writable]` Reserve collateral SPL Token mint.
+    ///   4. `[writable]` Reserve liquidity supply SPL Token account.
+    ///   5. `[writable]` Reserve collateral supply SPL Token account.
+    ///   6. `[]` Lending market account.
+    ///   7. `[]` Derived lending market authority.
+    ///   8. `[writable]` Redeem request owner, refunded the request rent.
+    ///   9. `[]` Clock sysvar.
+    ///   10 `[]` Token program id.
+    FulfillRedeem,
+
+    // 50
+    /// Cancel a redeem request, returning its queued collateral to the
+    /// request owner and closing it, or reroute the liquidity of a request
+    /// still in the queue to another account without losing its place.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Redeem request account.
+    ///   1. `[writable]` Destination collateral token account when
+    ///      cancelling, the new destination liquidity token account when
+    ///      rerouting.
+    ///   2. `[writable]` Reserve account.
+    ///   3. `[writable]` Reserve collateral supply SPL Token account.
+    ///   4. `[]` Lending market account.
+    ///   5. `[]` Derived lending market authority.
+    ///   6. `[writable, signer]` Redeem request owner, refunded the request
+    ///      rent.
+    ///   7. `[]` Token program id.
+    CancelRedeem {
+        /// Reroute the request to the destination instead of cancelling it
+        reroute: bool,
+    },
+}
+
+impl LendingInstruction {
+    /// Unpacks a byte buffer into a
+    /// [LendingInstruction](enum.LendingInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input
+            .split_first()
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok(match tag {
+            0 => {
+                let (owner, rest) = Self::unpack_pubkey(rest)?;
+                let (quote_currency, _rest) = Self::unpack_bytes32(rest)?;
+                Self::InitLendingMarket {
+                    owner,
+                    quote_currency: *quote_currency,
+                }
+            }
+            1 => {
+                let (new_owner, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetLendingMarketOwner { new_owner }
+            }
+            2 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (config, rest) = Self::unpack_reserve_config(rest)?;
+                let (derive_fee_receiver, _rest) = Self::unpack_bool(rest)?;
+                Self::InitReserve {
+                    liquidity_amount,
+                    config,
+                    derive_fee_receiver,
+                }
             }
+            3 => Self::RefreshReserve,
+            4 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (min_collateral_amount, _rest) = Self::unpack_u64(rest).unwrap_or((0, &[]));
+                Self::DepositReserveLiquidity {
+                    liquidity_amount,
+                    min_collateral_amount,
+                }
+            }
+            5 => {
+                let (collateral_amount, rest) = Self::unpack_u64(rest)?;
+                let (min_liquidity_amount, _rest) = Self::unpack_u64(rest).unwrap_or((0, &[]));
+                Self::RedeemReserveCollateral {
+                    collateral_amount,
+                    min_liquidity_amount,
+                }
+            }
+            6 => Self::InitObligation,
+            7 => Self::RefreshObligation,
+            8 => {
+                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::DepositObligationCollateral { collateral_amount }
+            }
+            9 => {
+                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::WithdrawObligationCollateral { collateral_amount }
+            }
+            10 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (slippage_limit, _rest) = Self::unpack_u64(rest).unwrap_or((0, &[]));
+                Self::BorrowObligationLiquidity {
+                    liquidity_amount,
+                    slippage_limit,
+                }
+            }
+            11 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::RepayObligationLiquidity { liquidity_amount }
+            }
+            12 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::LiquidateObligation { liquidity_amount }
+            }
+            13 => {
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::FlashLoan { amount }
+            }
+            14 => {
+                let (new_config, _rest) = Self::unpack_reserve_config(rest)?;
+                Self::ModifyReserveConfig { new_config }
+            }
+            15 => {
+                let (collateral_amount, rest) = Self::unpack_u64(rest)?;
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (slippage_limit, _rest) = Self::unpack_u64(rest)?;
+                Self::DepositObligationCollateralAndBorrow {
+                    collateral_amount,
+                    liquidity_amount,
+                    slippage_limit,
+                }
+            }
+            16 => {
+                let (new_owner, _rest) = Self::unpack_pubkey(rest)?;
+                Self::ProposeLendingMarketOwner { new_owner }
+            }
+            17 => Self::AcceptLendingMarketOwner,
+            18 => {
+                let (pause_flags, _rest) = Self::unpack_u8(rest)?;
+                Self::SetReservePauseFlags { pause_flags }
+            }
+            19 => Self::SetReserveOracle,
+            20 => Self::SetReserveFlashLoanFeeReceiver,
+            21 => Self::SetReserveCollateralFeeReceiver,
+            22 => {
+                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::MigrateObligationCollateral { collateral_amount }
+            }
+            23 => Self::CloseReserve,
+            24 => {
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::WithdrawProtocolFees { amount }
+            }
+            25 => {
+                let (emergency_authority, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetLendingMarketEmergencyAuthority {
+                    emergency_authority,
+                }
+            }
+            26 => {
+                let (frozen, _rest) = Self::unpack_bool(rest)?;
+                Self::SetMarketFreeze { frozen }
+            }
+            27 => Self::SetObligationOwner,
+            28 => {
+                let (additional_reserves, _rest) = Self::unpack_u8(rest)?;
+                Self::ResizeObligation {
+                    additional_reserves,
+                }
+            }
+            29 => {
+                let (status, _rest) = Self::unpack_u8(rest)?;
+                Self::SetReserveStatus {
+                    status: ReserveStatus::try_from(status)?,
+                }
+            }
+            30 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::DonateLiquidity { liquidity_amount }
+            }
+            31 => {
+                let (permissioned_borrowing, _rest) = Self::unpack_bool(rest)?;
+                Self::SetPermissionedBorrowing {
+                    permissioned_borrowing,
+                }
+            }
+            32 => {
+                let (borrower, _rest) = Self::unpack_pubkey(rest)?;
+                Self::AddToBorrowAllowlist { borrower }
+            }
+            33 => Self::RemoveFromBorrowAllowlist,
+            34 => {
+                let (rewards_per_slot, _rest) = Self::unpack_u64(rest)?;
+                Self::SetReserveRewards { rewards_per_slot }
+            }
+            35 => Self::ClaimReserveRewards,
+            36 => {
+                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::DepositObligationCollateralOnBehalf { collateral_amount }
+            }
+            37 => {
+                let (has_price_override, rest) = Self::unpack_bool(rest)?;
+                let price_override = if has_price_override {
+                    let (scaled_price, _rest) = Self::unpack_u128(rest)?;
+                    Some(Decimal::from_scaled_val(scaled_price))
+                } else {
+                    None
+                };
+                Self::SetPriceOverride { price_override }
+            }
+            38 => {
+                let (name, rest) = Self::unpack_string(rest)?;
+                let (description_uri, rest) = Self::unpack_string(rest)?;
+                let (flags, _rest) = Self::unpack_u8(rest)?;
+                Self::SetMarketMetadata {
+                    name,
+                    description_uri,
+                    flags,
+                }
+            }
+            39 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::FlashBorrowReserveLiquidity { liquidity_amount }
+            }
+            40 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (borrow_instruction_index, _rest) = Self::unpack_u8(rest)?;
+                Self::FlashRepayReserveLiquidity {
+                    liquidity_amount,
+                    borrow_instruction_index,
+                }
+            }
+            41 => {
+                let (scaled_value, _rest) = Self::unpack_u128(rest)?;
+                Self::SetMinBorrowValue {
+                    min_borrow_value: Decimal::from_scaled_val(scaled_value),
+                }
+            }
+            42 => Self::MigrateObligation,
+            43 => {
+                let (scaled_value, _rest) = Self::unpack_u128(rest)?;
+                Self::SetDustLiquidationThreshold {
+                    dust_liquidation_threshold: Decimal::from_scaled_val(scaled_value),
+                }
+            }
+            44 => Self::MigrateAccount,
+            45 => {
+                let (reserves, _rest) = Self::unpack_u8(rest)?;
+                Self::RefreshReserves { reserves }
+            }
+            46 => {
+                let (risk_authority, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetLendingMarketRiskAuthority { risk_authority }
+            }
+            47 => Self::InitDerivedObligation,
+            48 => {
+                let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::RequestRedeem { collateral_amount }
+            }
+            49 => Self::FulfillRedeem,
+            50 => {
+                let (reroute, _rest) = Self::unpack_bool(rest)?;
+                Self::CancelRedeem { reroute }
+            }
+            _ => {
+                msg!("Instruction cannot be unpacked");
+                return Err(LendingError::InstructionUnpackError.into());
+            }
+        })
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        if input.len() < 8 {
+            msg!("u64 cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(8);
+        let value = bytes
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_u128(input: &[u8]) -> Result<(u128, &[u8]), ProgramError> {
+        if input.len() < 16 {
+            msg!("u128 cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(16);
+        let value = bytes
+            .get(..16)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u128::from_le_bytes)
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            msg!("u16 cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(2);
+        let value = bytes
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
+        if input.is_empty() {
+            msg!("u8 cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(1);
+        let value = bytes
+            .get(..1)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u8::from_le_bytes)
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
+        let (value, rest) = Self::unpack_u8(input)?;
+        match value {
+            0 => Ok((false, rest)),
+            1 => Ok((true, rest)),
+            _ => {
+                msg!("Boolean cannot be unpacked");
+                Err(LendingError::InstructionUnpackError.into())
+            }
+        }
+    }
+
+    fn unpack_bytes32(input: &[u8]) -> Result<(&[u8; 32], &[u8]), ProgramError> {
+        if input.len() < 32 {
+            msg!("32 bytes cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(32);
+        Ok((
+            bytes
+                .try_into()
+                .map_err(|_| LendingError::InstructionUnpackError)?,
+            rest,
+        ))
+    }
+
+    fn unpack_string(input: &[u8]) -> Result<(String, &[u8]), ProgramError> {
+        let (len, rest) = Self::unpack_u16(input)?;
+        if rest.len() < len as usize {
+            msg!("String cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = rest.split_at(len as usize);
+        let value =
+            String::from_utf8(bytes.to_vec()).map_err(|_| LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+        if input.len() < PUBKEY_BYTES {
+            msg!("Pubkey cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (key, rest) = input.split_at(PUBKEY_BYTES);
+        let pk = Pubkey::try_from(key).map_err(|_| LendingError::InstructionUnpackError)?;
+        Ok((pk, rest))
+    }
+
+    fn unpack_reserve_config(input: &[u8]) -> Result<(ReserveConfig, &[u8]), ProgramError> {
+        let (optimal_utilization_rate, rest) = Self::unpack_u8(input)?;
+        let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
+        let (liquidation_bonus, rest) = Self::unpack_u8(rest)?;
+        let (liquidation_threshold, rest) = Self::unpack_u8(rest)?;
+        let (min_borrow_rate, rest) = Self::unpack_u8(rest)?;
+        let (optimal_borrow_rate, rest) = Self::unpack_u8(rest)?;
+        let (max_borrow_rate, rest) = Self::unpack_u8(rest)?;
+        let (borrow_fee_wad, rest) = Self::unpack_u64(rest)?;
+        let (flash_loan_fee_wad, rest) = Self::unpack_u64(rest)?;
+        let (host_fee_percentage, rest) = Self::unpack_u8(rest)?;
+        let (max_liquidation_bonus, rest) = Self::unpack_u8(rest)?;
+        let (max_withdraw_utilization, rest) = Self::unpack_u8(rest)?;
+        let (borrow_rate_rebate, rest) = Self::unpack_u8(rest)?;
+        let (borrow_rate_floor, rest) = Self::unpack_u8(rest)?;
+        let (borrow_limit, rest) = Self::unpack_u64(rest)?;
+        let (deposit_limit, rest) = Self::unpack_u64(rest)?;
+        let (collateral_weight, rest) = Self::unpack_u8(rest)?;
+        let (max_oracle_age_slots, rest) = Self::unpack_u64(rest)?;
+        let (borrow_weight_wad, rest) = Self::unpack_u64(rest)?;
+        let (protocol_liquidation_fee, rest) = Self::unpack_u8(rest)?;
+        let (liquidation_close_factor, rest) = Self::unpack_u8(rest)?;
+        let (liquidation_dust_threshold, rest) = Self::unpack_u64(rest)?;
+        let (min_deposit_amount, rest) = Self::unpack_u64(rest)?;
+        let (max_confidence_interval_bps, rest) = Self::unpack_u16(rest)?;
+        let (max_utilization_rate, rest) = Self::unpack_u8(rest)?;
+        let (super_max_borrow_rate, rest) = Self::unpack_u8(rest)?;
+        let (outflow_window_duration_slots, rest) = Self::unpack_u64(rest)?;
+        let (max_outflow, rest) = Self::unpack_u64(rest)?;
+        let (max_accrual_slots_per_refresh, rest) = Self::unpack_u64(rest)?;
+        let (isolated, rest) = Self::unpack_bool(rest)?;
+        let (borrowable_in_isolation, rest) = Self::unpack_bool(rest)?;
+        let (min_liquidation_bonus, rest) = Self::unpack_u8(rest)?;
+        let (stressed_liquidation_threshold, rest) = Self::unpack_u8(rest)?;
+        let (stress_utilization_rate, rest) = Self::unpack_u8(rest)?;
+        let (rate_history_interval_slots, rest) = Self::unpack_u64(rest)?;
+
+        Ok((
+            ReserveConfig {
+                optimal_utilization_rate,
+                loan_to_value_ratio,
+                collateral_weight,
+                liquidation_bonus,
+                min_liquidation_bonus,
+                max_liquidation_bonus,
+                liquidation_threshold,
+                stressed_liquidation_threshold,
+                stress_utilization_rate,
+                min_borrow_rate,
+                optimal_borrow_rate,
+                max_borrow_rate,
+                max_utilization_rate,
+                super_max_borrow_rate,
+                max_withdraw_utilization,
+                borrow_rate_rebate,
+                borrow_rate_floor,
+                borrow_limit,
+                deposit_limit,
+                max_oracle_age_slots,
+                borrow_weight_wad,
+                protocol_liquidation_fee,
+                liquidation_close_factor,
+                liquidation_dust_threshold,
+                min_deposit_amount,
+                max_confidence_interval_bps,
+                outflow_window_duration_slots,
+                max_outflow,
+                max_accrual_slots_per_refresh,
+                rate_history_interval_slots,
+                isolated,
+                borrowable_in_isolation,
+                fees: ReserveFees {
+                    borrow_fee_wad,
+                    flash_loan_fee_wad,
+                    host_fee_percentage,
+                },
+            },
+            rest,
+        ))
+    }
+
+    /// Packs a [LendingInstruction](enum.LendingInstruction.html) into a byte
+    /// buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match *self {
+            Self::InitLendingMarket {
+                owner,
+                quote_currency,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(owner.as_ref());
+                buf.extend_from_slice(quote_currency.as_ref());
+            }
+            Self::SetLendingMarketOwner { new_owner } => {
+                buf.push(1);
+                buf.extend_from_slice(new_owner.as_ref());
+            }
+            Self::InitReserve {
+                liquidity_amount,
+                config,
+                derive_fee_receiver,
+            } => {
+                buf.push(2);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                Self::extend_buffer_from_reserve_config(&mut buf, &config);
+                buf.push(derive_fee_receiver as u8);
+            }
+            Self::RefreshReserve => {
+                buf.push(3);
+            }
+            Self::DepositReserveLiquidity {
+                liquidity_amount,
+                min_collateral_amount,
+            } => {
+                buf.push(4);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&min_collateral_amount.to_le_bytes());
+            }
+            Self::RedeemReserveCollateral {
+                collateral_amount,
+                min_liquidity_amount,
+            } => {
+                buf.push(5);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+                buf.extend_from_slice(&min_liquidity_amount.to_le_bytes());
+            }
+            Self::InitObligation => {
+                buf.push(6);
+            }
+            Self::RefreshObligation => {
+                buf.push(7);
+            }
+            Self::DepositObligationCollateral { collateral_amount } => {
+                buf.push(8);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+            }
+            Self::WithdrawObligationCollateral { collateral_amount } => {
+                buf.push(9);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+            }
+            Self::BorrowObligationLiquidity {
+                liquidity_amount,
+                slippage_limit,
+            } => {
+                buf.push(10);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&slippage_limit.to_le_bytes());
+            }
+            Self::RepayObligationLiquidity { liquidity_amount } => {
+                buf.push(11);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::LiquidateObligation { liquidity_amount } => {
+                buf.push(12);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::FlashLoan { amount } => {
+                buf.push(13);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::ModifyReserveConfig { new_config } => {
+                buf.push(14);
+                Self::extend_buffer_from_reserve_config(&mut buf, &new_config);
+            }
+            Self::DepositObligationCollateralAndBorrow {
+                collateral_amount,
+                liquidity_amount,
+                slippage_limit,
+            } => {
+                buf.push(15);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&slippage_limit.to_le_bytes());
+            }
+            Self::ProposeLendingMarketOwner { new_owner } => {
+                buf.push(16);
+                buf.extend_from_slice(new_owner.as_ref());
+            }
+            Self::AcceptLendingMarketOwner => {
+                buf.push(17);
+            }
+            Self::SetReservePauseFlags { pause_flags } => {
+                buf.push(18);
+                buf.extend_from_slice(&pause_flags.to_le_bytes());
+            }
+            Self::SetReserveOracle => {
+                buf.push(19);
+            }
+            Self::SetReserveFlashLoanFeeReceiver => {
+                buf.push(20);
+            }
+            Self::SetReserveCollateralFeeReceiver => {
+                buf.push(21);
+            }
+            Self::MigrateObligationCollateral { collateral_amount } => {
+                buf.push(22);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+            }
+            Self::CloseReserve => {
+                buf.push(23);
+            }
+            Self::WithdrawProtocolFees { amount } => {
+                buf.push(24);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::SetLendingMarketEmergencyAuthority {
+                emergency_authority,
+            } => {
+                buf.push(25);
+                buf.extend_from_slice(emergency_authority.as_ref());
+            }
+            Self::SetMarketFreeze { frozen } => {
+                buf.push(26);
+                buf.push(frozen as u8);
+            }
+            Self::SetObligationOwner => {
+                buf.push(27);
+            }
+            Self::ResizeObligation {
+                additional_reserves,
+            } => {
+                buf.push(28);
+                buf.push(additional_reserves);
+            }
+            Self::SetReserveStatus { status } => {
+                buf.push(29);
+                buf.push(status as u8);
+            }
+            Self::DonateLiquidity { liquidity_amount } => {
+                buf.push(30);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::SetPermissionedBorrowing {
+                permissioned_borrowing,
+            } => {
+                buf.push(31);
+                buf.push(permissioned_borrowing as u8);
+            }
+            Self::AddToBorrowAllowlist { borrower } => {
+                buf.push(32);
+                buf.extend_from_slice(borrower.as_ref());
+            }
+            Self::RemoveFromBorrowAllowlist => {
+                buf.push(33);
+            }
+            Self::SetReserveRewards { rewards_per_slot } => {
+                buf.push(34);
+                buf.extend_from_slice(&rewards_per_slot.to_le_bytes());
+            }
+            Self::ClaimReserveRewards => {
+                buf.push(35);
+            }
+            Self::DepositObligationCollateralOnBehalf { collateral_amount } => {
+                buf.push(36);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+            }
+            Self::SetPriceOverride { price_override } => {
+                buf.push(37);
+                buf.push(price_override.is_some() as u8);
+                if let Some(price_override) = price_override {
+                    let scaled_price = price_override
+                        .to_scaled_val()
+                        .expect("Decimal cannot be packed");
+                    buf.extend_from_slice(&scaled_price.to_le_bytes());
+                }
+            }
+            Self::SetMarketMetadata {
+                ref name,
+                ref description_uri,
+                flags,
+            } => {
+                buf.push(38);
+                for string in [name, description_uri] {
+                    buf.extend_from_slice(&(string.len() as u16).to_le_bytes());
+                    buf.extend_from_slice(string.as_bytes());
+                }
+                buf.extend_from_slice(&flags.to_le_bytes());
+            }
+            Self::FlashBorrowReserveLiquidity { liquidity_amount } => {
+                buf.push(39);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::FlashRepayReserveLiquidity {
+                liquidity_amount,
+                borrow_instruction_index,
+            } => {
+                buf.push(40);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&borrow_instruction_index.to_le_bytes());
+            }
+            Self::SetMinBorrowValue { min_borrow_value } => {
+                buf.push(41);
+                let scaled_value = min_borrow_value
+                    .to_scaled_val()
+                    .expect("Decimal cannot be packed");
+                buf.extend_from_slice(&scaled_value.to_le_bytes());
+            }
+            Self::MigrateObligation => {
+                buf.push(42);
+            }
+            Self::SetDustLiquidationThreshold {
+                dust_liquidation_threshold,
+            } => {
+                buf.push(43);
+                let scaled_value = dust_liquidation_threshold
+                    .to_scaled_val()
+                    .expect("Decimal cannot be packed");
+                buf.extend_from_slice(&scaled_value.to_le_bytes());
+            }
+            Self::MigrateAccount => {
+                buf.push(44);
+            }
+            Self::RefreshReserves { reserves } => {
+                buf.push(45);
+                buf.extend_from_slice(&reserves.to_le_bytes());
+            }
+            Self::SetLendingMarketRiskAuthority { risk_authority } => {
+                buf.push(46);
+                buf.extend_from_slice(risk_authority.as_ref());
+            }
+            Self::InitDerivedObligation => {
+                buf.push(47);
+            }
+            Self::RequestRedeem { collateral_amount } => {
+                buf.push(48);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+            }
+            Self::FulfillRedeem => {
+                buf.push(49);
+            }
+            Self::CancelRedeem { reroute } => {
+                buf.push(50);
+                buf.push(reroute as u8);
+            }
+        }
+        buf
+    }
+
+    // Helper function to pack a ReserveConfig into a Vec<u8> buffer
+    fn extend_buffer_from_reserve_config(buf: &mut Vec<u8>, config: &ReserveConfig) {
+        buf.extend_from_slice(&config.optimal_utilization_rate.to_le_bytes());
+        buf.extend_from_slice(&config.loan_to_value_ratio.to_le_bytes());
+        buf.extend_from_slice(&config.liquidation_bonus.to_le_bytes());
+        buf.extend_from_slice(&config.liquidation_threshold.to_le_bytes());
+        buf.extend_from_slice(&config.min_borrow_rate.to_le_bytes());
+        buf.extend_from_slice(&config.optimal_borrow_rate.to_le_bytes());
+        buf.extend_from_slice(&config.max_borrow_rate.to_le_bytes());
+        buf.extend_from_slice(&config.fees.borrow_fee_wad.to_le_bytes());
+        buf.extend_from_slice(&config.fees.flash_loan_fee_wad.to_le_bytes());
+        buf.extend_from_slice(&config.fees.host_fee_percentage.to_le_bytes());
+        buf.extend_from_slice(&config.max_liquidation_bonus.to_le_bytes());
+        buf.extend_from_slice(&config.max_withdraw_utilization.to_le_bytes());
+        buf.extend_from_slice(&config.borrow_rate_rebate.to_le_bytes());
+        buf.extend_from_slice(&config.borrow_rate_floor.to_le_bytes());
+        buf.extend_from_slice(&config.borrow_limit.to_le_bytes());
+        buf.extend_from_slice(&config.deposit_limit.to_le_bytes());
+        buf.extend_from_slice(&config.collateral_weight.to_le_bytes());
+        buf.extend_from_slice(&config.max_oracle_age_slots.to_le_bytes());
+        buf.extend_from_slice(&config.borrow_weight_wad.to_le_bytes());
+        buf.extend_from_slice(&config.protocol_liquidation_fee.to_le_bytes());
+        buf.extend_from_slice(&config.liquidation_close_factor.to_le_bytes());
+        buf.extend_from_slice(&config.liquidation_dust_threshold.to_le_bytes());
+        buf.extend_from_slice(&config.min_deposit_amount.to_le_bytes());
+        buf.extend_from_slice(&config.max_confidence_interval_bps.to_le_bytes());
+        buf.extend_from_slice(&config.max_utilization_rate.to_le_bytes());
+        buf.extend_from_slice(&config.super_max_borrow_rate.to_le_bytes());
+        buf.extend_from_slice(&config.outflow_window_duration_slots.to_le_bytes());
+        buf.extend_from_slice(&config.max_outflow.to_le_bytes());
+        buf.extend_from_slice(&config.max_accrual_slots_per_refresh.to_le_bytes());
+        buf.push(config.isolated as u8);
+        buf.push(config.borrowable_in_isolation as u8);
+        buf.extend_from_slice(&config.min_liquidation_bonus.to_le_bytes());
+        buf.extend_from_slice(&config.stressed_liquidation_threshold.to_le_bytes());
+        buf.extend_from_slice(&config.stress_utilization_rate.to_le_bytes());
+        buf.extend_from_slice(&config.rate_history_interval_slots.to_le_bytes());
+    }
+}
+
+/// Creates an 'InitLendingMarket' instruction.
+pub fn init_lending_market(
+    program_id: Pubkey,
+    owner: Pubkey,
+    quote_currency: [u8; 32],
+    lending_market_pubkey: Pubkey,
+    oracle_program_id: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(oracle_program_id, false),
+        ],
+        data: LendingInstruction::InitLendingMarket {
+            owner,
+            quote_currency,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'SetLendingMarketOwner' instruction.
+pub fn set_lending_market_owner(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    new_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetLendingMarketOwner { new_owner }.pack(),
+    }
+}
+
+/// Creates an 'InitReserve' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn init_reserve(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    config: ReserveConfig,
+    source_liquidity_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_mint_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_collateral_supply_pubkey: Pubkey,
+    pyth_product_pubkey: Pubkey,
+    pyth_price_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new_readonly(reserve_liquidity_mint_pubkey, false),
+        AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(reserve_collateral_mint_pubkey, false),
+        AccountMeta::new(reserve_collateral_supply_pubkey, false),
+        AccountMeta::new_readonly(pyth_product_pubkey, false),
+        AccountMeta::new_readonly(pyth_price_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::InitReserve {
+            liquidity_amount,
+            config,
+            derive_fee_receiver: false,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `InitReserve` instruction whose liquidity fee receiver is the
+/// lending market authority's associated token account for the liquidity mint
+#[allow(clippy::too_many_arguments)]
+pub fn init_reserve_with_derived_fee_receiver(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    config: ReserveConfig,
+    source_liquidity_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_mint_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_collateral_supply_pubkey: Pubkey,
+    pyth_product_pubkey: Pubkey,
+    pyth_price_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut instruction = init_reserve(
+        program_id,
+        liquidity_amount,
+        config,
+        source_liquidity_pubkey,
+        destination_collateral_pubkey,
+        reserve_pubkey,
+        reserve_liquidity_mint_pubkey,
+        reserve_liquidity_supply_pubkey,
+        get_associated_token_address_with_program_id(
+            &lending_market_authority_pubkey,
+            &reserve_liquidity_mint_pubkey,
+            &spl_token::id(),
+        ),
+        reserve_collateral_mint_pubkey,
+        reserve_collateral_supply_pubkey,
+        pyth_product_pubkey,
+        pyth_price_pubkey,
+        lending_market_pubkey,
+        lending_market_owner_pubkey,
+        user_transfer_authority_pubkey,
+    );
+    instruction.accounts.extend([
+        AccountMeta::new(payer_pubkey, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ]);
+    instruction.data = LendingInstruction::InitReserve {
+        liquidity_amount,
+        config,
+        derive_fee_receiver: true,
+    }
+    .pack();
+    instruction
+}
+
+/// Creates a `RefreshReserve` instruction
+pub fn refresh_reserve(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_oracle_pubkey: Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new_readonly(reserve_liquidity_oracle_pubkey, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::RefreshReserve.pack(),
+    }
+}
+
+/// Creates a `RefreshReserves` instruction refreshing the reserves of the
+/// `(reserve, oracle)` pairs
+pub fn refresh_reserves(program_id: Pubkey, reserves: &[(Pubkey, Pubkey)]) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(sysvar::clock::id(), false)];
+    for (reserve_pubkey, reserve_liquidity_oracle_pubkey) in reserves {
+        accounts.push(AccountMeta::new(*reserve_pubkey, false));
+        accounts.push(AccountMeta::new_readonly(
+            *reserve_liquidity_oracle_pubkey,
+            false,
+        ));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::RefreshReserves {
+            reserves: u8::try_from(reserves.len()).expect("Too many reserves"),
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'DepositReserveLiquidity' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    min_collateral_amount: Option<u64>,
+    source_liquidity_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_collateral_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(reserve_collateral_mint_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::DepositReserveLiquidity {
+            liquidity_amount,
+            min_collateral_amount: min_collateral_amount.unwrap_or(0),
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'RedeemReserveCollateral' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn redeem_reserve_collateral(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    min_liquidity_amount: Option<u64>,
+    source_collateral_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_collateral_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::RedeemReserveCollateral {
+            collateral_amount,
+            min_liquidity_amount: min_liquidity_amount.unwrap_or(0),
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'RequestRedeem' instruction. The redeem request is derived from
+/// `ticket`, the next ticket of the reserve redeem queue.
+#[allow(clippy::too_many_arguments)]
+pub fn request_redeem(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    ticket: u64,
+    source_collateral_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    reserve_collateral_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    payer: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let (redeem_request_pubkey, _bump_seed) =
+        find_redeem_request_address(&program_id, &reserve_pubkey, ticket);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_collateral_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(reserve_collateral_supply_pubkey, false),
+            AccountMeta::new(redeem_request_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::RequestRedeem { collateral_amount }.pack(),
+    }
+}
+
+/// Creates a 'FulfillRedeem' instruction for the redeem request holding
+/// `ticket`.
+#[allow(clippy::too_many_arguments)]
+pub fn fulfill_redeem(
+    program_id: Pubkey,
+    ticket: u64,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    reserve_collateral_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    redeem_request_owner: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let (redeem_request_pubkey, _bump_seed) =
+        find_redeem_request_address(&program_id, &reserve_pubkey, ticket);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(redeem_request_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(reserve_collateral_supply_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new(redeem_request_owner, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FulfillRedeem.pack(),
+    }
+}
+
+/// Creates a 'CancelRedeem' instruction for the redeem request holding
+/// `ticket`. `destination_pubkey` receives the queued collateral, or the
+/// redeemed liquidity from now on when `reroute` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_redeem(
+    program_id: Pubkey,
+    reroute: bool,
+    ticket: u64,
+    destination_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_collateral_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    redeem_request_owner: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let (redeem_request_pubkey, _bump_seed) =
+        find_redeem_request_address(&program_id, &reserve_pubkey, ticket);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(redeem_request_pubkey, false),
+            AccountMeta::new(destination_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_collateral_supply_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new(redeem_request_owner, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::CancelRedeem { reroute }.pack(),
+    }
+}
+
+/// Creates an 'InitObligation' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn init_obligation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::InitObligation.pack(),
+    }
+}
+
+/// Creates an 'InitDerivedObligation' instruction.
+pub fn init_derived_obligation(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    payer: Pubkey,
+) -> Instruction {
+    let obligation_pubkey = get_obligation_address(
+        &program_id,
+        &lending_market_pubkey,
+        &obligation_owner_pubkey,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::InitDerivedObligation.pack(),
+    }
+}
+
+/// Creates a 'RefreshObligation' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn refresh_obligation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    reserve_pubkeys: Vec<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    accounts.extend(
+        reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::RefreshObligation.pack(),
+    }
+}
+
+/// Creates a 'DepositObligationCollateral' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_obligation_collateral(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    source_collateral_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    deposit_reserve_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    obligation_deposit_reserve_pubkeys: Vec<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(source_collateral_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new_readonly(deposit_reserve_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        obligation_deposit_reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::DepositObligationCollateral { collateral_amount }.pack(),
+    }
+}
+
+/// Creates a 'DepositObligationCollateralOnBehalf' instruction, topping up the
+/// collateral of an obligation without its owner's signature.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_obligation_collateral_on_behalf(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    source_collateral_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    deposit_reserve_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    obligation_deposit_reserve_pubkeys: Vec<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(source_collateral_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new_readonly(deposit_reserve_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        obligation_deposit_reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::DepositObligationCollateralOnBehalf { collateral_amount }.pack(),
+    }
+}
+
+/// Creates a 'WithdrawObligationCollateral' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_obligation_collateral(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    source_collateral_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    withdraw_reserve_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_collateral_pubkey, false),
+            AccountMeta::new(destination_collateral_pubkey, false),
+            AccountMeta::new(withdraw_reserve_pubkey, false),
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::WithdrawObligationCollateral { collateral_amount }.pack(),
+    }
+}
+
+/// Creates a 'BorrowObligationLiquidity' instruction. Pass the borrow
+/// allowlist entry of the obligation owner when the lending market has
+/// permissioned borrowing.
+#[allow(clippy::too_many_arguments)]
+pub fn borrow_obligation_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    slippage_limit: Option<u64>,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    obligation_deposit_reserve_pubkeys: Vec<Pubkey>,
+    borrow_allowlist_entry_pubkey: Option<Pubkey>,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_liquidity_pubkey, false),
+        AccountMeta::new(borrow_reserve_pubkey, false),
+        AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        obligation_deposit_reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    if let Some(borrow_allowlist_entry_pubkey) = borrow_allowlist_entry_pubkey {
+        accounts.push(AccountMeta::new_readonly(
+            borrow_allowlist_entry_pubkey,
+            false,
+        ));
+    }
+    if let Some(host_fee_receiver_pubkey) = host_fee_receiver_pubkey {
+        accounts.push(AccountMeta::new(host_fee_receiver_pubkey, false));
+    }
+    let slippage_limit = slippage_limit.unwrap_or(0);
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::BorrowObligationLiquidity {
+            liquidity_amount,
+            slippage_limit,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `RepayObligationLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn repay_obligation_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(repay_reserve_pubkey, false),
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::RepayObligationLiquidity { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `RepayObligationLiquidity` instruction repaying an obligation
+/// owned by someone else, with liquidity the payer transfer authority can
+/// transfer.
+#[allow(clippy::too_many_arguments)]
+pub fn repay_obligation_liquidity_on_behalf(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    payer_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    payer_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    repay_obligation_liquidity(
+        program_id,
+        liquidity_amount,
+        payer_liquidity_pubkey,
+        destination_liquidity_pubkey,
+        repay_reserve_pubkey,
+        obligation_pubkey,
+        lending_market_pubkey,
+        payer_transfer_authority_pubkey,
+    )
+}
+
+/// Creates a `LiquidateObligation` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn liquidate_obligation(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
+    repay_reserve_liquidity_supply_pubkey: Pubkey,
+    withdraw_reserve_pubkey: Pubkey,
+    withdraw_reserve_collateral_supply_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    withdraw_reserve_collateral_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new(repay_reserve_pubkey, false),
+        AccountMeta::new(repay_reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new_readonly(withdraw_reserve_pubkey, false),
+        AccountMeta::new(withdraw_reserve_collateral_supply_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(collateral_fee_receiver_pubkey) = withdraw_reserve_collateral_fee_receiver_pubkey {
+        accounts.push(AccountMeta::new(collateral_fee_receiver_pubkey, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::LiquidateObligation { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `FlashLoan` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_loan(
+    program_id: Pubkey,
+    amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    flash_loan_receiver_program_id: Pubkey,
+    flash_loan_receiver_program_accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_liquidity_pubkey, false),
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(host_fee_receiver_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(flash_loan_receiver_program_id, false),
+    ];
+    accounts.extend(flash_loan_receiver_program_accounts);
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::FlashLoan { amount }.pack(),
+    }
+}
+
+/// Creates a `FlashBorrowReserveLiquidity` instruction.
+pub fn flash_borrow_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `FlashRepayReserveLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_repay_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(host_fee_receiver_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'ModifyReserveConfig` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn modify_reserve_config(
+    program_id: Pubkey,
+    config: ReserveConfig,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new(lending_market_pubkey, false),
+        AccountMeta::new(lending_market_owner_pubkey, true),
+    ];
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::ModifyReserveConfig { new_config: config }.pack(),
+    }
+}
+
+/// Creates a `DepositObligationCollateralAndBorrow` instruction. Pass the
+/// borrow allowlist entry of the obligation owner when the lending market has
+/// permissioned borrowing.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_obligation_collateral_and_borrow(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    liquidity_amount: u64,
+    slippage_limit: Option<u64>,
+    source_collateral_pubkey: Pubkey,
+    destination_collateral_pubkey: Pubkey,
+    deposit_reserve_pubkey: Pubkey,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    obligation_deposit_reserve_pubkeys: Vec<Pubkey>,
+    borrow_allowlist_entry_pubkey: Option<Pubkey>,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(source_collateral_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new_readonly(deposit_reserve_pubkey, false),
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_liquidity_pubkey, false),
+        AccountMeta::new(borrow_reserve_pubkey, false),
+        AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        obligation_deposit_reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    if let Some(borrow_allowlist_entry_pubkey) = borrow_allowlist_entry_pubkey {
+        accounts.push(AccountMeta::new_readonly(
+            borrow_allowlist_entry_pubkey,
+            false,
+        ));
+    }
+    if let Some(host_fee_receiver_pubkey) = host_fee_receiver_pubkey {
+        accounts.push(AccountMeta::new(host_fee_receiver_pubkey, false));
+    }
+    let slippage_limit = slippage_limit.unwrap_or(0);
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::DepositObligationCollateralAndBorrow {
+            collateral_amount,
+            liquidity_amount,
+            slippage_limit,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'ProposeLendingMarketOwner' instruction.
+pub fn propose_lending_market_owner(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    new_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::ProposeLendingMarketOwner { new_owner }.pack(),
+    }
+}
+
+/// Creates an 'AcceptLendingMarketOwner' instruction.
+pub fn accept_lending_market_owner(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    pending_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(pending_owner, true),
+        ],
+        data: LendingInstruction::AcceptLendingMarketOwner.pack(),
+    }
+}
+
+/// Creates a 'SetReservePauseFlags' instruction.
+pub fn set_reserve_pause_flags(
+    program_id: Pubkey,
+    pause_flags: u8,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetReservePauseFlags { pause_flags }.pack(),
+    }
+}
+
+/// Creates a 'SetReserveOracle' instruction.
+pub fn set_reserve_oracle(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    oracle_product_pubkey: Pubkey,
+    oracle_price_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(oracle_product_pubkey, false),
+            AccountMeta::new_readonly(oracle_price_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::SetReserveOracle.pack(),
+    }
+}
+
+/// Creates a 'SetReserveFlashLoanFeeReceiver' instruction.
+pub fn set_reserve_flash_loan_fee_receiver(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    flash_loan_fee_receiver_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(flash_loan_fee_receiver_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetReserveFlashLoanFeeReceiver.pack(),
+    }
+}
+
+/// Creates a 'SetReserveCollateralFeeReceiver' instruction.
+pub fn set_reserve_collateral_fee_receiver(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    collateral_fee_receiver_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(collateral_fee_receiver_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetReserveCollateralFeeReceiver.pack(),
+    }
+}
+
+/// Creates a 'MigrateObligationCollateral' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn migrate_obligation_collateral(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    source_reserve_pubkey: Pubkey,
+    source_reserve_collateral_supply_pubkey: Pubkey,
+    source_reserve_collateral_mint_pubkey: Pubkey,
+    source_reserve_liquidity_supply_pubkey: Pubkey,
+    destination_reserve_pubkey: Pubkey,
+    destination_reserve_liquidity_supply_pubkey: Pubkey,
+    destination_reserve_collateral_mint_pubkey: Pubkey,
+    destination_reserve_collateral_supply_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_reserve_pubkey, false),
+            AccountMeta::new(source_reserve_collateral_supply_pubkey, false),
+            AccountMeta::new(source_reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(source_reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(destination_reserve_pubkey, false),
+            AccountMeta::new(destination_reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(destination_reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(destination_reserve_collateral_supply_pubkey, false),
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::MigrateObligationCollateral { collateral_amount }.pack(),
+    }
+}
+
+/// Creates a 'CloseReserve' instruction.
+pub fn close_reserve(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_collateral_mint_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    destination_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_collateral_mint_pubkey, false),
+            AccountMeta::new_readonly(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(destination_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::CloseReserve.pack(),
+    }
+}
+
+/// Creates a 'WithdrawProtocolFees' instruction.
+pub fn withdraw_protocol_fees(
+    program_id: Pubkey,
+    amount: u64,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(reserve_pubkey, false),
+            AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::WithdrawProtocolFees { amount }.pack(),
+    }
+}
+
+/// Creates a 'SetLendingMarketEmergencyAuthority' instruction.
+pub fn set_lending_market_emergency_authority(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    emergency_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetLendingMarketEmergencyAuthority {
+            emergency_authority,
         }
-        buf
+        .pack(),
     }
+}
 
-    // Helper function to pack a ReserveConfig into a Vec<u8> buffer
-    fn extend_buffer_from_reserve_config(buf: &mut Vec<u8>, config: &ReserveConfig) {
-        buf.extend_from_slice(&config.optimal_utilization_rate.to_le_bytes());
-        buf.extend_from_slice(&config.loan_to_value_ratio.to_le_bytes());
-        buf.extend_from_slice(&config.liquidation_bonus.to_le_bytes());
-        buf.extend_from_slice(&config.liquidation_threshold.to_le_bytes());
-        buf.extend_from_slice(&config.min_borrow_rate.to_le_bytes());
-        buf.extend_from_slice(&config.optimal_borrow_rate.to_le_bytes());
-        buf.extend_from_slice(&config.max_borrow_rate.to_le_bytes());
-        buf.extend_from_slice(&config.fees.borrow_fee_wad.to_le_bytes());
-        buf.extend_from_slice(&config.fees.flash_loan_fee_wad.to_le_bytes());
-        buf.extend_from_slice(&config.fees.host_fee_percentage.to_le_bytes());
+/// Creates a 'SetLendingMarketRiskAuthority' instruction.
+pub fn set_lending_market_risk_authority(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    risk_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetLendingMarketRiskAuthority { risk_authority }.pack(),
+    }
+}
+
+/// Creates a 'SetMarketFreeze' instruction.
+pub fn set_market_freeze(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    authority: Pubkey,
+    frozen: bool,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data: LendingInstruction::SetMarketFreeze { frozen }.pack(),
+    }
+}
+
+/// Creates a 'SetObligationOwner' instruction.
+pub fn set_obligation_owner(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    new_obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(new_obligation_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetObligationOwner.pack(),
+    }
+}
+
+/// Creates a 'ResizeObligation' instruction.
+pub fn resize_obligation(
+    program_id: Pubkey,
+    additional_reserves: u8,
+    obligation_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ResizeObligation {
+            additional_reserves,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'MigrateObligation' instruction.
+pub fn migrate_obligation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+        ],
+        data: LendingInstruction::MigrateObligation.pack(),
+    }
+}
+
+/// Creates a 'SetReserveStatus' instruction.
+pub fn set_reserve_status(
+    program_id: Pubkey,
+    status: ReserveStatus,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetReserveStatus { status }.pack(),
+    }
+}
+
+/// Creates a 'SetPriceOverride' instruction.
+pub fn set_price_override(
+    program_id: Pubkey,
+    price_override: Option<Decimal>,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetPriceOverride { price_override }.pack(),
+    }
+}
+
+/// Creates a 'SetMarketMetadata' instruction.
+pub fn set_market_metadata(
+    program_id: Pubkey,
+    name: String,
+    description_uri: String,
+    flags: u8,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    let market_metadata_pubkey = get_market_metadata_address(&program_id, &lending_market_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(market_metadata_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::SetMarketMetadata {
+            name,
+            description_uri,
+            flags,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `DonateLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn donate_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::DonateLiquidity { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a 'SetPermissionedBorrowing' instruction.
+pub fn set_permissioned_borrowing(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    permissioned_borrowing: bool,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetPermissionedBorrowing {
+            permissioned_borrowing,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'SetMinBorrowValue' instruction.
+pub fn set_min_borrow_value(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    min_borrow_value: Decimal,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetMinBorrowValue { min_borrow_value }.pack(),
     }
 }
 
-/// Creates an 'InitLendingMarket' instruction.
-pub fn init_lending_market(
+/// Creates a 'SetDustLiquidationThreshold' instruction.
+pub fn set_dust_liquidation_threshold(
     program_id: Pubkey,
-    owner: Pubkey,
-    quote_currency: [u8; 32],
     lending_market_pubkey: Pubkey,
-    oracle_program_id: Pubkey,
+    lending_market_owner: Pubkey,
+    dust_liquidation_threshold: Decimal,
 ) -> Instruction {
     Instruction {
         program_id,
         accounts: vec![
             AccountMeta::new(lending_market_pubkey, false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(oracle_program_id, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
         ],
-        data: LendingInstruction::InitLendingMarket {
-            owner,
-            quote_currency,
+        data: LendingInstruction::SetDustLiquidationThreshold {
+            dust_liquidation_threshold,
         }
         .pack(),
     }
 }
 
-/// Creates a 'SetLendingMarketOwner' instruction.
-pub fn set_lending_market_owner(
+/// Creates a 'MigrateAccount' instruction.
+pub fn migrate_account(
+    program_id: Pubkey,
+    account_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(account_pubkey, false),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::MigrateAccount.pack(),
+    }
+}
+
+/// Creates an 'AddToBorrowAllowlist' instruction.
+pub fn add_to_borrow_allowlist(
     program_id: Pubkey,
+    borrower: Pubkey,
     lending_market_pubkey: Pubkey,
     lending_market_owner: Pubkey,
-    new_owner: Pubkey,
+    payer_pubkey: Pubkey,
 ) -> Instruction {
+    let (borrow_allowlist_entry_pubkey, _bump_seed) =
+        find_borrow_allowlist_entry_address(&program_id, &lending_market_pubkey, &borrower);
     Instruction {
         program_id,
         accounts: vec![
-            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new(borrow_allowlist_entry_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
             AccountMeta::new_readonly(lending_market_owner, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
-        data: LendingInstruction::SetLendingMarketOwner { new_owner }.pack(),
+        data: LendingInstruction::AddToBorrowAllowlist { borrower }.pack(),
+    }
+}
+
+/// Creates a 'RemoveFromBorrowAllowlist' instruction.
+pub fn remove_from_borrow_allowlist(
+    program_id: Pubkey,
+    borrower: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    destination_pubkey: Pubkey,
+) -> Instruction {
+    let (borrow_allowlist_entry_pubkey, _bump_seed) =
+        find_borrow_allowlist_entry_address(&program_id, &lending_market_pubkey, &borrower);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(borrow_allowlist_entry_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+            AccountMeta::new(destination_pubkey, false),
+        ],
+        data: LendingInstruction::RemoveFromBorrowAllowlist.pack(),
+    }
+}
+
+/// Creates a 'SetReserveRewards' instruction.
+pub fn set_reserve_rewards(
+    program_id: Pubkey,
+    rewards_per_slot: u64,
+    reserve_pubkey: Pubkey,
+    reward_vault_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(reward_vault_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::SetReserveRewards { rewards_per_slot }.pack(),
+    }
+}
+
+/// Creates a 'ClaimReserveRewards' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_reserve_rewards(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    reward_vault_pubkey: Pubkey,
+    destination_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    let (reward_checkpoint_pubkey, _bump_seed) =
+        find_reward_checkpoint_address(&program_id, &obligation_pubkey, &reserve_pubkey);
+    let (reward_authority_pubkey, _bump_seed) =
+        find_reward_authority_address(&program_id, &reserve_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reward_checkpoint_pubkey, false),
+            AccountMeta::new_readonly(obligation_pubkey, false),
+            AccountMeta::new_readonly(reserve_pubkey, false),
+            AccountMeta::new(reward_vault_pubkey, false),
+            AccountMeta::new(destination_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(reward_authority_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::ClaimReserveRewards.pack(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::state::{RESERVE_PAUSE_DEPOSITS, RESERVE_PAUSE_LIQUIDATIONS},
+    };
+
+    #[test]
+    fn test_init_lending_market() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let oracle_program_id = Pubkey::new_unique();
+        let currency = "USD";
+        let mut quote_currency = [0u8; 32];
+        quote_currency[0..currency.len()].clone_from_slice(currency.as_bytes());
+        let instruction = init_lending_market(
+            program_id,
+            owner,
+            quote_currency,
+            lending_market_pubkey,
+            oracle_program_id,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::InitLendingMarket {
+                owner,
+                quote_currency,
+            }
+            .pack()
+        );
+    }
+
+    #[test]
+    fn test_set_lending_market_owner() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+        let instruction = set_lending_market_owner(
+            program_id,
+            lending_market_pubkey,
+            lending_market_owner,
+            new_owner,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::SetLendingMarketOwner { new_owner }.pack()
+        );
+    }
+
+    #[test]
+    fn test_init_reserve() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let config = ReserveConfig {
+            optimal_utilization_rate: 50,
+            loan_to_value_ratio: 1,
+            collateral_weight: 100,
+            liquidation_bonus: 10,
+            min_liquidation_bonus: 0,
+            max_liquidation_bonus: 20,
+            liquidation_threshold: 5,
+            stressed_liquidation_threshold: 0,
+            stress_utilization_rate: 0,
+            min_borrow_rate: 2,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 10,
+            max_utilization_rate: 0,
+            super_max_borrow_rate: 0,
+            max_withdraw_utilization: 90,
+            borrow_rate_rebate: 0,
+            borrow_rate_floor: 0,
+            borrow_limit: u64::MAX,
+            deposit_limit: u64::MAX,
+            max_oracle_age_slots: 0,
+            borrow_weight_wad: 0,
+            protocol_liquidation_fee: 0,
+            liquidation_close_factor: 50,
+            liquidation_dust_threshold: 0,
+            min_deposit_amount: 0,
+            max_confidence_interval_bps: 0,
+            outflow_window_duration_slots: 0,
+            max_outflow: 0,
+            max_accrual_slots_per_refresh: 0,
+            rate_history_interval_slots: 0,
+            isolated: false,
+            borrowable_in_isolation: false,
+            fees: ReserveFees {
+                borrow_fee_wad: 1,
+                flash_loan_fee_wad: 3,
+                host_fee_percentage: 1,
+            },
+        };
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_mint_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let reserve_collateral_supply_pubkey = Pubkey::new_unique();
+        let pyth_product_pubkey = Pubkey::new_unique();
+        let pyth_price_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = init_reserve(
+            program_id,
+            liquidity_amount,
+            config,
+            source_liquidity_pubkey,
+            destination_collateral_pubkey,
+            reserve_pubkey,
+            reserve_liquidity_mint_pubkey,
+            reserve_liquidity_supply_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            reserve_collateral_mint_pubkey,
+            reserve_collateral_supply_pubkey,
+            pyth_product_pubkey,
+            pyth_price_pubkey,
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+            user_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 17);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::InitReserve {
+                liquidity_amount,
+                config,
+                derive_fee_receiver: false,
+            }
+            .pack()
+        );
+    }
+
+    #[test]
+    fn test_init_reserve_with_derived_fee_receiver() {
+        let program_id = Pubkey::new_unique();
+        let reserve_liquidity_mint_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let payer_pubkey = Pubkey::new_unique();
+        let instruction = init_reserve_with_derived_fee_receiver(
+            program_id,
+            1,
+            ReserveConfig::default(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            reserve_liquidity_mint_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            lending_market_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            payer_pubkey,
+        );
+        assert_eq!(instruction.accounts.len(), 20);
+
+        let (lending_market_authority_pubkey, _bump_seed) =
+            Pubkey::find_program_address(&[lending_market_pubkey.as_ref()], &program_id);
+        assert_eq!(
+            instruction.accounts[5].pubkey,
+            get_associated_token_address_with_program_id(
+                &lending_market_authority_pubkey,
+                &reserve_liquidity_mint_pubkey,
+                &spl_token::id(),
+            )
+        );
+        assert_eq!(
+            instruction.accounts[17],
+            AccountMeta::new(payer_pubkey, true)
+        );
+        assert_eq!(
+            instruction.accounts[19].pubkey,
+            spl_associated_token_account::id()
+        );
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::InitReserve {
+                liquidity_amount: 1,
+                config: ReserveConfig::default(),
+                derive_fee_receiver: true,
+            }
+            .pack()
+        );
+    }
+
+    #[test]
+    fn test_refresh_reserve() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_oracle_pubkey = Pubkey::new_unique();
+        let instruction =
+            refresh_reserve(program_id, reserve_pubkey, reserve_liquidity_oracle_pubkey);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(instruction.data, LendingInstruction::RefreshReserve.pack());
+    }
+
+    #[test]
+    fn test_deposit_reserve_liquidity() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = deposit_reserve_liquidity(
+            program_id,
+            liquidity_amount,
+            Some(1),
+            source_liquidity_pubkey,
+            destination_collateral_pubkey,
+            reserve_pubkey,
+            reserve_liquidity_supply_pubkey,
+            reserve_collateral_mint_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::DepositReserveLiquidity {
+                liquidity_amount,
+                min_collateral_amount: 1
+            }
+            .pack()
+        );
+
+        // instructions packed without a minimum amount don't enforce one
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data[..9]),
+            Ok(LendingInstruction::DepositReserveLiquidity {
+                liquidity_amount,
+                min_collateral_amount: 0
+            })
+        );
     }
-}
 
-/// Creates an 'InitReserve' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn init_reserve(
-    program_id: Pubkey,
-    liquidity_amount: u64,
-    config: ReserveConfig,
-    source_liquidity_pubkey: Pubkey,
-    destination_collateral_pubkey: Pubkey,
-    reserve_pubkey: Pubkey,
-    reserve_liquidity_mint_pubkey: Pubkey,
-    reserve_liquidity_supply_pubkey: Pubkey,
-    reserve_liquidity_fee_receiver_pubkey: Pubkey,
-    reserve_collateral_mint_pubkey: Pubkey,
-    reserve_collateral_supply_pubkey: Pubkey,
-    pyth_product_pubkey: Pubkey,
-    pyth_price_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    lending_market_owner_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    let accounts = vec![
-        AccountMeta::new(source_liquidity_pubkey, false),
-        AccountMeta::new(destination_collateral_pubkey, false),
-        AccountMeta::new(reserve_pubkey, false),
-        AccountMeta::new_readonly(reserve_liquidity_mint_pubkey, false),
-        AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-        AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
-        AccountMeta::new(reserve_collateral_mint_pubkey, false),
-        AccountMeta::new(reserve_collateral_supply_pubkey, false),
-        AccountMeta::new_readonly(pyth_product_pubkey, false),
-        AccountMeta::new_readonly(pyth_price_pubkey, false),
-        AccountMeta::new_readonly(lending_market_pubkey, false),
-        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-        AccountMeta::new_readonly(lending_market_owner_pubkey, true),
-        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(sysvar::rent::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::InitReserve {
-            liquidity_amount,
-            config,
-        }
-        .pack(),
+    #[test]
+    fn test_redeem_reserve_collateral() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = u64::MAX;
+        let source_collateral_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = redeem_reserve_collateral(
+            program_id,
+            collateral_amount,
+            None,
+            source_collateral_pubkey,
+            destination_liquidity_pubkey,
+            reserve_pubkey,
+            reserve_collateral_mint_pubkey,
+            reserve_liquidity_supply_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::RedeemReserveCollateral {
+                collateral_amount,
+                min_liquidity_amount: 0
+            }
+            .pack()
+        );
     }
-}
 
-/// Creates a `RefreshReserve` instruction
-pub fn refresh_reserve(
-    program_id: Pubkey,
-    reserve_pubkey: Pubkey,
-    reserve_liquidity_oracle_pubkey: Pubkey,
-) -> Instruction {
-    let accounts = vec![
-        AccountMeta::new(reserve_pubkey, false),
-        AccountMeta::new_readonly(reserve_liquidity_oracle_pubkey, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-    ];
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::RefreshReserve.pack(),
+    #[test]
+    fn test_redeem_queue() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = u64::MAX;
+        let reserve_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let (redeem_request_pubkey, _bump_seed) =
+            find_redeem_request_address(&program_id, &reserve_pubkey, 7);
+
+        let instruction = request_redeem(
+            program_id,
+            collateral_amount,
+            7,
+            Pubkey::new_unique(),
+            destination_liquidity_pubkey,
+            reserve_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(instruction.accounts.len(), 15);
+        assert_eq!(instruction.accounts[6].pubkey, redeem_request_pubkey);
+        assert!(instruction.accounts[10].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::RequestRedeem { collateral_amount })
+        );
+
+        let instruction = fulfill_redeem(
+            program_id,
+            7,
+            destination_liquidity_pubkey,
+            reserve_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(instruction.accounts.len(), 11);
+        assert_eq!(instruction.accounts[0].pubkey, redeem_request_pubkey);
+        assert!(instruction
+            .accounts
+            .iter()
+            .all(|account| !account.is_signer));
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::FulfillRedeem)
+        );
+
+        let instruction = cancel_redeem(
+            program_id,
+            true,
+            7,
+            destination_liquidity_pubkey,
+            reserve_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(instruction.accounts.len(), 8);
+        assert_eq!(instruction.accounts[0].pubkey, redeem_request_pubkey);
+        assert!(instruction.accounts[6].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::CancelRedeem { reroute: true })
+        );
     }
-}
 
-/// Creates a 'DepositReserveLiquidity' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn deposit_reserve_liquidity(
-    program_id: Pubkey,
-    liquidity_amount: u64,
-    source_liquidity_pubkey: Pubkey,
-    destination_collateral_pubkey: Pubkey,
-    reserve_pubkey: Pubkey,
-    reserve_liquidity_supply_pubkey: Pubkey,
-    reserve_collateral_mint_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_liquidity_pubkey, false),
-            AccountMeta::new(destination_collateral_pubkey, false),
-            AccountMeta::new(reserve_pubkey, false),
-            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new(reserve_collateral_mint_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::DepositReserveLiquidity { liquidity_amount }.pack(),
+    #[test]
+    fn test_init_obligation() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let instruction = init_obligation(
+            program_id,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(instruction.data, LendingInstruction::InitObligation.pack());
     }
-}
 
-/// Creates a 'RedeemReserveCollateral' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn redeem_reserve_collateral(
-    program_id: Pubkey,
-    collateral_amount: u64,
-    source_collateral_pubkey: Pubkey,
-    destination_liquidity_pubkey: Pubkey,
-    reserve_pubkey: Pubkey,
-    reserve_collateral_mint_pubkey: Pubkey,
-    reserve_liquidity_supply_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_collateral_pubkey, false),
-            AccountMeta::new(destination_liquidity_pubkey, false),
-            AccountMeta::new(reserve_pubkey, false),
-            AccountMeta::new(reserve_collateral_mint_pubkey, false),
-            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::RedeemReserveCollateral { collateral_amount }.pack(),
+    #[test]
+    fn test_init_derived_obligation() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let instruction = init_derived_obligation(
+            program_id,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            payer,
+        );
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            get_obligation_address(
+                &program_id,
+                &lending_market_pubkey,
+                &obligation_owner_pubkey
+            )
+        );
+        assert!(instruction.accounts[2].is_signer);
+        assert!(instruction.accounts[3].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::InitDerivedObligation)
+        );
     }
-}
 
-/// Creates an 'InitObligation' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn init_obligation(
-    program_id: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    obligation_owner_pubkey: Pubkey,
-) -> Instruction {
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(obligation_owner_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::InitObligation.pack(),
+    #[test]
+    fn test_refresh_obligation() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let reserve_pubkeys = vec![Pubkey::new_unique()];
+        let instruction = refresh_obligation(program_id, obligation_pubkey, reserve_pubkeys);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::RefreshObligation.pack()
+        );
     }
-}
 
-/// Creates a 'RefreshObligation' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn refresh_obligation(
-    program_id: Pubkey,
-    obligation_pubkey: Pubkey,
-    reserve_pubkeys: Vec<Pubkey>,
-) -> Instruction {
-    let mut accounts = vec![
-        AccountMeta::new(obligation_pubkey, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-    ];
-    accounts.extend(
-        reserve_pubkeys
-            .into_iter()
-            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
-    );
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::RefreshObligation.pack(),
+    #[test]
+    fn test_deposit_obligation_collateral() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = u64::MAX;
+        let source_collateral_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let deposit_reserve_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = deposit_obligation_collateral(
+            program_id,
+            collateral_amount,
+            source_collateral_pubkey,
+            destination_collateral_pubkey,
+            deposit_reserve_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            user_transfer_authority_pubkey,
+            vec![deposit_reserve_pubkey],
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::DepositObligationCollateral { collateral_amount }.pack()
+        );
     }
-}
 
-/// Creates a 'DepositObligationCollateral' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn deposit_obligation_collateral(
-    program_id: Pubkey,
-    collateral_amount: u64,
-    source_collateral_pubkey: Pubkey,
-    destination_collateral_pubkey: Pubkey,
-    deposit_reserve_pubkey: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    obligation_owner_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_collateral_pubkey, false),
-            AccountMeta::new(destination_collateral_pubkey, false),
-            AccountMeta::new_readonly(deposit_reserve_pubkey, false),
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(obligation_owner_pubkey, true),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::DepositObligationCollateral { collateral_amount }.pack(),
+    #[test]
+    fn test_deposit_obligation_collateral_on_behalf() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = 1_000;
+        let deposit_reserve_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = deposit_obligation_collateral_on_behalf(
+            program_id,
+            collateral_amount,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            deposit_reserve_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            user_transfer_authority_pubkey,
+            vec![deposit_reserve_pubkey],
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 9);
+        // the transfer authority is the only signer
+        assert_eq!(
+            instruction
+                .accounts
+                .iter()
+                .filter(|account| account.is_signer)
+                .map(|account| account.pubkey)
+                .collect::<Vec<_>>(),
+            vec![user_transfer_authority_pubkey]
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::DepositObligationCollateralOnBehalf { collateral_amount })
+        );
     }
-}
 
-/// Creates a 'WithdrawObligationCollateral' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn withdraw_obligation_collateral(
-    program_id: Pubkey,
-    collateral_amount: u64,
-    source_collateral_pubkey: Pubkey,
-    destination_collateral_pubkey: Pubkey,
-    withdraw_reserve_pubkey: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    obligation_owner_pubkey: Pubkey,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_collateral_pubkey, false),
-            AccountMeta::new(destination_collateral_pubkey, false),
-            AccountMeta::new_readonly(withdraw_reserve_pubkey, false),
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new_readonly(obligation_owner_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::WithdrawObligationCollateral { collateral_amount }.pack(),
+    #[test]
+    fn test_withdraw_obligation_collateral() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = u64::MAX;
+        let source_collateral_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let withdraw_reserve_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let instruction = withdraw_obligation_collateral(
+            program_id,
+            collateral_amount,
+            source_collateral_pubkey,
+            destination_collateral_pubkey,
+            withdraw_reserve_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 9);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::WithdrawObligationCollateral { collateral_amount }.pack()
+        );
     }
-}
 
-/// Creates a 'BorrowObligationLiquidity' instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn borrow_obligation_liquidity(
-    program_id: Pubkey,
-    liquidity_amount: u64,
-    slippage_limit: Option<u64>,
-    source_liquidity_pubkey: Pubkey,
-    destination_liquidity_pubkey: Pubkey,
-    borrow_reserve_pubkey: Pubkey,
-    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    obligation_owner_pubkey: Pubkey,
-    host_fee_receiver_pubkey: Option<Pubkey>,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    let mut accounts = vec![
-        AccountMeta::new(source_liquidity_pubkey, false),
-        AccountMeta::new(destination_liquidity_pubkey, false),
-        AccountMeta::new(borrow_reserve_pubkey, false),
-        AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
-        AccountMeta::new(obligation_pubkey, false),
-        AccountMeta::new_readonly(lending_market_pubkey, false),
-        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-        AccountMeta::new_readonly(obligation_owner_pubkey, true),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    if let Some(host_fee_receiver_pubkey) = host_fee_receiver_pubkey {
-        accounts.push(AccountMeta::new(host_fee_receiver_pubkey, false));
-    }
-    let slippage_limit = slippage_limit.unwrap_or(0);
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::BorrowObligationLiquidity {
+    #[test]
+    fn test_borrow_obligation_liquidity() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let borrow_reserve_pubkey = Pubkey::new_unique();
+        let borrow_reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let host_fee_receiver_pubkey = Some(Pubkey::new_unique());
+        let instruction = borrow_obligation_liquidity(
+            program_id,
             liquidity_amount,
-            slippage_limit,
-        }
-        .pack(),
+            None,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            borrow_reserve_pubkey,
+            borrow_reserve_liquidity_fee_receiver_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            vec![Pubkey::new_unique()],
+            None,
+            host_fee_receiver_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 12);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::BorrowObligationLiquidity {
+                liquidity_amount,
+                slippage_limit: 0
+            }
+            .pack()
+        );
     }
-}
 
-/// Creates a `RepayObligationLiquidity` instruction
-#[allow(clippy::too_many_arguments)]
-pub fn repay_obligation_liquidity(
-    program_id: Pubkey,
-    liquidity_amount: u64,
-    source_liquidity_pubkey: Pubkey,
-    destination_liquidity_pubkey: Pubkey,
-    repay_reserve_pubkey: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_liquidity_pubkey, false),
-            AccountMeta::new(destination_liquidity_pubkey, false),
-            AccountMeta::new(repay_reserve_pubkey, false),
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::RepayObligationLiquidity { liquidity_amount }.pack(),
+    #[test]
+    fn test_repay_obligation_liquidity() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let repay_reserve_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = repay_obligation_liquidity(
+            program_id,
+            liquidity_amount,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            repay_reserve_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 8);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::RepayObligationLiquidity { liquidity_amount }.pack()
+        );
     }
-}
 
-/// Creates a `LiquidateObligation` instruction
-#[allow(clippy::too_many_arguments)]
-pub fn liquidate_obligation(
-    program_id: Pubkey,
-    liquidity_amount: u64,
-    source_liquidity_pubkey: Pubkey,
-    destination_collateral_pubkey: Pubkey,
-    repay_reserve_pubkey: Pubkey,
-    repay_reserve_liquidity_supply_pubkey: Pubkey,
-    withdraw_reserve_pubkey: Pubkey,
-    withdraw_reserve_collateral_supply_pubkey: Pubkey,
-    obligation_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    user_transfer_authority_pubkey: Pubkey,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(source_liquidity_pubkey, false),
-            AccountMeta::new(destination_collateral_pubkey, false),
-            AccountMeta::new(repay_reserve_pubkey, false),
-            AccountMeta::new(repay_reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new_readonly(withdraw_reserve_pubkey, false),
-            AccountMeta::new(withdraw_reserve_collateral_supply_pubkey, false),
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: LendingInstruction::LiquidateObligation { liquidity_amount }.pack(),
+    #[test]
+    fn test_repay_obligation_liquidity_on_behalf() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let obligation_pubkey = Pubkey::new_unique();
+        let payer_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = repay_obligation_liquidity_on_behalf(
+            program_id,
+            liquidity_amount,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            obligation_pubkey,
+            Pubkey::new_unique(),
+            payer_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 8);
+        // only the payer transfer authority signs, not the obligation owner
+        let signers: Vec<_> = instruction
+            .accounts
+            .iter()
+            .filter(|account| account.is_signer)
+            .map(|account| account.pubkey)
+            .collect();
+        assert_eq!(signers, vec![payer_transfer_authority_pubkey]);
+        assert_eq!(instruction.accounts[3].pubkey, obligation_pubkey);
+        assert!(instruction.accounts[3].is_writable);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::RepayObligationLiquidity { liquidity_amount }.pack()
+        );
     }
-}
 
-/// Creates a `FlashLoan` instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn flash_loan(
-    program_id: Pubkey,
-    amount: u64,
-    source_liquidity_pubkey: Pubkey,
-    destination_liquidity_pubkey: Pubkey,
-    reserve_pubkey: Pubkey,
-    reserve_liquidity_fee_receiver_pubkey: Pubkey,
-    host_fee_receiver_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    flash_loan_receiver_program_id: Pubkey,
-    flash_loan_receiver_program_accounts: Vec<AccountMeta>,
-) -> Instruction {
-    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
-        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
-        &program_id,
-    );
-    let mut accounts = vec![
-        AccountMeta::new(source_liquidity_pubkey, false),
-        AccountMeta::new(destination_liquidity_pubkey, false),
-        AccountMeta::new(reserve_pubkey, false),
-        AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
-        AccountMeta::new(host_fee_receiver_pubkey, false),
-        AccountMeta::new_readonly(lending_market_pubkey, false),
-        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(flash_loan_receiver_program_id, false),
-    ];
-    accounts.extend(flash_loan_receiver_program_accounts);
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::FlashLoan { amount }.pack(),
-    }
-}
+    #[test]
+    fn test_liquidate_obligation() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = u64::MAX;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let repay_reserve_pubkey = Pubkey::new_unique();
+        let repay_reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let withdraw_reserve_pubkey = Pubkey::new_unique();
+        let withdraw_reserve_collateral_supply_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = liquidate_obligation(
+            program_id,
+            liquidity_amount,
+            source_liquidity_pubkey,
+            destination_collateral_pubkey,
+            repay_reserve_pubkey,
+            repay_reserve_liquidity_supply_pubkey,
+            withdraw_reserve_pubkey,
+            withdraw_reserve_collateral_supply_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+            None,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 12);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::LiquidateObligation { liquidity_amount }.pack()
+        );
 
-/// Creates a 'ModifyReserveConfig` instruction.
-#[allow(clippy::too_many_arguments)]
-pub fn modify_reserve_config(
-    program_id: Pubkey,
-    config: ReserveConfig,
-    reserve_pubkey: Pubkey,
-    lending_market_pubkey: Pubkey,
-    lending_market_owner_pubkey: Pubkey,
-) -> Instruction {
-    let accounts = vec![
-        AccountMeta::new(reserve_pubkey, false),
-        AccountMeta::new(lending_market_pubkey, false),
-        AccountMeta::new(lending_market_owner_pubkey, true),
-    ];
-    Instruction {
-        program_id,
-        accounts,
-        data: LendingInstruction::ModifyReserveConfig { new_config: config }.pack(),
+        let collateral_fee_receiver_pubkey = Pubkey::new_unique();
+        let instruction = liquidate_obligation(
+            program_id,
+            liquidity_amount,
+            source_liquidity_pubkey,
+            destination_collateral_pubkey,
+            repay_reserve_pubkey,
+            repay_reserve_liquidity_supply_pubkey,
+            withdraw_reserve_pubkey,
+            withdraw_reserve_collateral_supply_pubkey,
+            obligation_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+            Some(collateral_fee_receiver_pubkey),
+        );
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_eq!(
+            instruction.accounts[12].pubkey,
+            collateral_fee_receiver_pubkey
+        );
+        assert!(instruction.accounts[12].is_writable);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_flash_loan() {
+        let program_id = Pubkey::new_unique();
+        let amount = u64::MAX;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let repay_reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let host_fee_receiver_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let flash_loan_receiver_program_id = Pubkey::new_unique();
+        let account_meta = AccountMeta {
+            pubkey: Pubkey::new_unique(),
+            is_signer: true,
+            is_writable: false,
+        };
+        let flash_loan_receiver_program_accounts = vec![account_meta];
+        let instruction = flash_loan(
+            program_id,
+            amount,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            repay_reserve_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            host_fee_receiver_pubkey,
+            lending_market_pubkey,
+            flash_loan_receiver_program_id,
+            flash_loan_receiver_program_accounts,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::FlashLoan { amount }.pack()
+        );
+    }
 
     #[test]
-    fn test_init_lending_market() {
+    fn test_modify_reserve_config() {
         let program_id = Pubkey::new_unique();
-        let owner = Pubkey::new_unique();
+        let config = ReserveConfig {
+            optimal_utilization_rate: 60,
+            loan_to_value_ratio: 1,
+            collateral_weight: 100,
+            liquidation_bonus: 10,
+            min_liquidation_bonus: 0,
+            max_liquidation_bonus: 20,
+            liquidation_threshold: 5,
+            stressed_liquidation_threshold: 0,
+            stress_utilization_rate: 0,
+            min_borrow_rate: 2,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 10,
+            max_utilization_rate: 95,
+            super_max_borrow_rate: 150,
+            max_withdraw_utilization: 90,
+            borrow_rate_rebate: 0,
+            borrow_rate_floor: 0,
+            borrow_limit: 1_000_000,
+            deposit_limit: 2_000_000,
+            max_oracle_age_slots: 100,
+            borrow_weight_wad: 1_500_000_000_000_000_000,
+            protocol_liquidation_fee: 20,
+            liquidation_close_factor: 20,
+            liquidation_dust_threshold: 1_000,
+            min_deposit_amount: 100,
+            max_confidence_interval_bps: 200,
+            outflow_window_duration_slots: 9_000,
+            max_outflow: 1_000_000,
+            max_accrual_slots_per_refresh: 216_000,
+            rate_history_interval_slots: 9_000,
+            isolated: true,
+            borrowable_in_isolation: true,
+            fees: ReserveFees {
+                borrow_fee_wad: 1,
+                flash_loan_fee_wad: 3,
+                host_fee_percentage: 1,
+            },
+        };
+        let reserve_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let oracle_program_id = Pubkey::new_unique();
-        let currency = "USD";
-        let mut quote_currency = [0u8; 32];
-        quote_currency[0..currency.len()].clone_from_slice(currency.as_bytes());
-        let instruction = init_lending_market(
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let instruction = modify_reserve_config(
+            program_id,
+            config,
+            reserve_pubkey,
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::ModifyReserveConfig { new_config: config }.pack()
+        );
+    }
+
+    #[test]
+    fn test_deposit_obligation_collateral_and_borrow() {
+        let program_id = Pubkey::new_unique();
+        let collateral_amount = 1_000;
+        let liquidity_amount = u64::MAX;
+        let slippage_limit = Some(500);
+        let source_collateral_pubkey = Pubkey::new_unique();
+        let destination_collateral_pubkey = Pubkey::new_unique();
+        let deposit_reserve_pubkey = Pubkey::new_unique();
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let borrow_reserve_pubkey = Pubkey::new_unique();
+        let borrow_reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = deposit_obligation_collateral_and_borrow(
             program_id,
-            owner,
-            quote_currency,
+            collateral_amount,
+            liquidity_amount,
+            slippage_limit,
+            source_collateral_pubkey,
+            destination_collateral_pubkey,
+            deposit_reserve_pubkey,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            borrow_reserve_pubkey,
+            borrow_reserve_liquidity_fee_receiver_pubkey,
+            obligation_pubkey,
             lending_market_pubkey,
-            oracle_program_id,
+            obligation_owner_pubkey,
+            user_transfer_authority_pubkey,
+            vec![],
+            None,
+            None,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(instruction.accounts.len(), 14);
         assert_eq!(
             instruction.data,
-            LendingInstruction::InitLendingMarket {
-                owner,
-                quote_currency,
+            LendingInstruction::DepositObligationCollateralAndBorrow {
+                collateral_amount,
+                liquidity_amount,
+                slippage_limit: 500,
             }
             .pack()
         );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::DepositObligationCollateralAndBorrow {
+                collateral_amount,
+                liquidity_amount,
+                slippage_limit: 500,
+            }
+        );
     }
 
     #[test]
-    fn test_set_lending_market_owner() {
+    fn test_propose_lending_market_owner() {
         let program_id = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
         let lending_market_owner = Pubkey::new_unique();
         let new_owner = Pubkey::new_unique();
-        let instruction = set_lending_market_owner(
+        let instruction = propose_lending_market_owner(
             program_id,
             lending_market_pubkey,
             lending_market_owner,
@@ -1106,400 +4153,713 @@ mod tests {
         assert_eq!(instruction.accounts.len(), 2);
         assert_eq!(
             instruction.data,
-            LendingInstruction::SetLendingMarketOwner { new_owner }.pack()
+            LendingInstruction::ProposeLendingMarketOwner { new_owner }.pack()
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::ProposeLendingMarketOwner { new_owner })
         );
     }
 
     #[test]
-    fn test_init_reserve() {
+    fn test_accept_lending_market_owner() {
         let program_id = Pubkey::new_unique();
-        let liquidity_amount = u64::MAX;
-        let config = ReserveConfig {
-            optimal_utilization_rate: 50,
-            loan_to_value_ratio: 1,
-            liquidation_bonus: 10,
-            liquidation_threshold: 5,
-            min_borrow_rate: 2,
-            optimal_borrow_rate: 4,
-            max_borrow_rate: 10,
-            fees: ReserveFees {
-                borrow_fee_wad: 1,
-                flash_loan_fee_wad: 3,
-                host_fee_percentage: 1,
-            },
-        };
-        let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_collateral_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let pending_owner = Pubkey::new_unique();
+        let instruction =
+            accept_lending_market_owner(program_id, lending_market_pubkey, pending_owner);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::AcceptLendingMarketOwner.pack()
+        );
+    }
+
+    #[test]
+    fn test_set_reserve_pause_flags() {
+        let program_id = Pubkey::new_unique();
+        let pause_flags = RESERVE_PAUSE_DEPOSITS | RESERVE_PAUSE_LIQUIDATIONS;
         let reserve_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_mint_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
-        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
-        let reserve_collateral_supply_pubkey = Pubkey::new_unique();
-        let pyth_product_pubkey = Pubkey::new_unique();
-        let pyth_price_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let lending_market_owner_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = init_reserve(
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = set_reserve_pause_flags(
             program_id,
-            liquidity_amount,
-            config,
-            source_liquidity_pubkey,
-            destination_collateral_pubkey,
+            pause_flags,
             reserve_pubkey,
-            reserve_liquidity_mint_pubkey,
-            reserve_liquidity_supply_pubkey,
-            reserve_liquidity_fee_receiver_pubkey,
-            reserve_collateral_mint_pubkey,
-            reserve_collateral_supply_pubkey,
-            pyth_product_pubkey,
-            pyth_price_pubkey,
             lending_market_pubkey,
-            lending_market_owner_pubkey,
-            user_transfer_authority_pubkey,
+            lending_market_owner,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 17);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert!(instruction.accounts[2].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::InitReserve {
-                liquidity_amount,
-                config,
-            }
-            .pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReservePauseFlags { pause_flags })
         );
     }
 
     #[test]
-    fn test_refresh_reserve() {
+    fn test_set_reserve_oracle() {
         let program_id = Pubkey::new_unique();
         let reserve_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_oracle_pubkey = Pubkey::new_unique();
-        let instruction =
-            refresh_reserve(program_id, reserve_pubkey, reserve_liquidity_oracle_pubkey);
+        let oracle_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = set_reserve_oracle(
+            program_id,
+            reserve_pubkey,
+            oracle_pubkey,
+            oracle_pubkey,
+            lending_market_pubkey,
+            lending_market_owner,
+        );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 3);
-        assert_eq!(instruction.data, LendingInstruction::RefreshReserve.pack());
+        assert_eq!(instruction.accounts.len(), 6);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[4].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReserveOracle)
+        );
     }
 
     #[test]
-    fn test_deposit_reserve_liquidity() {
+    fn test_set_reserve_flash_loan_fee_receiver() {
         let program_id = Pubkey::new_unique();
-        let liquidity_amount = u64::MAX;
-        let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_collateral_pubkey = Pubkey::new_unique();
         let reserve_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
-        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let flash_loan_fee_receiver_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = deposit_reserve_liquidity(
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = set_reserve_flash_loan_fee_receiver(
             program_id,
-            liquidity_amount,
-            source_liquidity_pubkey,
-            destination_collateral_pubkey,
             reserve_pubkey,
-            reserve_liquidity_supply_pubkey,
-            reserve_collateral_mint_pubkey,
+            flash_loan_fee_receiver_pubkey,
             lending_market_pubkey,
-            user_transfer_authority_pubkey,
+            lending_market_owner,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[3].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::DepositReserveLiquidity { liquidity_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReserveFlashLoanFeeReceiver)
         );
     }
 
     #[test]
-    fn test_redeem_reserve_collateral() {
+    fn test_set_reserve_collateral_fee_receiver() {
         let program_id = Pubkey::new_unique();
-        let collateral_amount = u64::MAX;
-        let source_collateral_pubkey = Pubkey::new_unique();
-        let destination_liquidity_pubkey = Pubkey::new_unique();
         let reserve_pubkey = Pubkey::new_unique();
-        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let collateral_fee_receiver_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = redeem_reserve_collateral(
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = set_reserve_collateral_fee_receiver(
             program_id,
-            collateral_amount,
-            source_collateral_pubkey,
-            destination_liquidity_pubkey,
             reserve_pubkey,
-            reserve_collateral_mint_pubkey,
-            reserve_liquidity_supply_pubkey,
+            collateral_fee_receiver_pubkey,
             lending_market_pubkey,
-            user_transfer_authority_pubkey,
+            lending_market_owner,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[3].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::RedeemReserveCollateral { collateral_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReserveCollateralFeeReceiver)
         );
     }
 
     #[test]
-    fn test_init_obligation() {
+    fn test_migrate_obligation_collateral() {
         let program_id = Pubkey::new_unique();
+        let collateral_amount = u64::MAX;
+        let source_reserve_pubkey = Pubkey::new_unique();
+        let source_reserve_collateral_supply_pubkey = Pubkey::new_unique();
+        let source_reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let source_reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let destination_reserve_pubkey = Pubkey::new_unique();
+        let destination_reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let destination_reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let destination_reserve_collateral_supply_pubkey = Pubkey::new_unique();
         let obligation_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
         let obligation_owner_pubkey = Pubkey::new_unique();
-        let instruction = init_obligation(
+        let instruction = migrate_obligation_collateral(
             program_id,
+            collateral_amount,
+            source_reserve_pubkey,
+            source_reserve_collateral_supply_pubkey,
+            source_reserve_collateral_mint_pubkey,
+            source_reserve_liquidity_supply_pubkey,
+            destination_reserve_pubkey,
+            destination_reserve_liquidity_supply_pubkey,
+            destination_reserve_collateral_mint_pubkey,
+            destination_reserve_collateral_supply_pubkey,
             obligation_pubkey,
             lending_market_pubkey,
             obligation_owner_pubkey,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 6);
-        assert_eq!(instruction.data, LendingInstruction::InitObligation.pack());
+        assert_eq!(instruction.accounts.len(), 14);
+        assert!(instruction.accounts[11].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::MigrateObligationCollateral { collateral_amount })
+        );
     }
 
     #[test]
-    fn test_refresh_obligation() {
+    fn test_close_reserve() {
         let program_id = Pubkey::new_unique();
-        let obligation_pubkey = Pubkey::new_unique();
-        let reserve_pubkeys = vec![Pubkey::new_unique()];
-        let instruction = refresh_obligation(program_id, obligation_pubkey, reserve_pubkeys);
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_collateral_mint_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let destination_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = close_reserve(
+            program_id,
+            reserve_pubkey,
+            reserve_collateral_mint_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            destination_pubkey,
+            lending_market_pubkey,
+            lending_market_owner,
+        );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(instruction.accounts.len(), 8);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[3].is_writable);
+        assert!(instruction.accounts[6].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::RefreshObligation.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::CloseReserve)
         );
     }
 
     #[test]
-    fn test_deposit_obligation_collateral() {
+    fn test_withdraw_protocol_fees() {
         let program_id = Pubkey::new_unique();
-        let collateral_amount = u64::MAX;
-        let source_collateral_pubkey = Pubkey::new_unique();
-        let destination_collateral_pubkey = Pubkey::new_unique();
-        let deposit_reserve_pubkey = Pubkey::new_unique();
-        let obligation_pubkey = Pubkey::new_unique();
+        let amount = u64::MAX;
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let obligation_owner_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = deposit_obligation_collateral(
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = withdraw_protocol_fees(
             program_id,
-            collateral_amount,
-            source_collateral_pubkey,
-            destination_collateral_pubkey,
-            deposit_reserve_pubkey,
-            obligation_pubkey,
+            amount,
+            reserve_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            destination_liquidity_pubkey,
             lending_market_pubkey,
-            obligation_owner_pubkey,
-            user_transfer_authority_pubkey,
+            lending_market_owner,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 9);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert!(instruction.accounts[1].is_writable);
+        assert!(instruction.accounts[2].is_writable);
+        assert!(instruction.accounts[5].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::WithdrawProtocolFees { amount })
+        );
+    }
+
+    #[test]
+    fn test_set_lending_market_emergency_authority() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let emergency_authority = Pubkey::new_unique();
+        let instruction = set_lending_market_emergency_authority(
+            program_id,
+            lending_market_pubkey,
+            lending_market_owner,
+            emergency_authority,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetLendingMarketEmergencyAuthority {
+                emergency_authority
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_lending_market_risk_authority() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let risk_authority = Pubkey::new_unique();
+        let instruction = set_lending_market_risk_authority(
+            program_id,
+            lending_market_pubkey,
+            lending_market_owner,
+            risk_authority,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetLendingMarketRiskAuthority { risk_authority })
+        );
+    }
+
+    #[test]
+    fn test_set_market_freeze() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        for frozen in [true, false] {
+            let instruction =
+                set_market_freeze(program_id, lending_market_pubkey, authority, frozen);
+            assert_eq!(instruction.program_id, program_id);
+            assert_eq!(instruction.accounts.len(), 2);
+            assert!(instruction.accounts[1].is_signer);
+            assert_eq!(
+                LendingInstruction::unpack(&instruction.data),
+                Ok(LendingInstruction::SetMarketFreeze { frozen })
+            );
+        }
+
+        // only 0 and 1 are valid flags
+        assert_eq!(
+            LendingInstruction::unpack(&[26, 2]),
+            Err(LendingError::InstructionUnpackError.into())
+        );
+    }
+
+    #[test]
+    fn test_set_obligation_owner() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let obligation_owner = Pubkey::new_unique();
+        let new_obligation_owner = Pubkey::new_unique();
+        let instruction = set_obligation_owner(
+            program_id,
+            obligation_pubkey,
+            obligation_owner,
+            new_obligation_owner,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[1].is_signer);
+        assert!(instruction.accounts[2].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::DepositObligationCollateral { collateral_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetObligationOwner)
         );
     }
 
     #[test]
-    fn test_withdraw_obligation_collateral() {
+    fn test_resize_obligation() {
         let program_id = Pubkey::new_unique();
-        let collateral_amount = u64::MAX;
-        let source_collateral_pubkey = Pubkey::new_unique();
-        let destination_collateral_pubkey = Pubkey::new_unique();
-        let withdraw_reserve_pubkey = Pubkey::new_unique();
+        let additional_reserves = 5;
         let obligation_pubkey = Pubkey::new_unique();
-        let lending_market_pubkey = Pubkey::new_unique();
-        let obligation_owner_pubkey = Pubkey::new_unique();
-        let instruction = withdraw_obligation_collateral(
+        let obligation_owner = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let instruction = resize_obligation(
             program_id,
-            collateral_amount,
-            source_collateral_pubkey,
-            destination_collateral_pubkey,
-            withdraw_reserve_pubkey,
+            additional_reserves,
             obligation_pubkey,
-            lending_market_pubkey,
-            obligation_owner_pubkey,
+            obligation_owner,
+            payer,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 9);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[1].is_signer);
+        assert!(instruction.accounts[2].is_writable);
+        assert!(instruction.accounts[2].is_signer);
+        assert_eq!(instruction.accounts[4].pubkey, system_program::id());
         assert_eq!(
-            instruction.data,
-            LendingInstruction::WithdrawObligationCollateral { collateral_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::ResizeObligation {
+                additional_reserves
+            })
         );
     }
 
     #[test]
-    fn test_borrow_obligation_liquidity() {
+    fn test_migrate_obligation() {
         let program_id = Pubkey::new_unique();
-        let liquidity_amount = u64::MAX;
-        let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_liquidity_pubkey = Pubkey::new_unique();
-        let borrow_reserve_pubkey = Pubkey::new_unique();
-        let borrow_reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
         let obligation_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let obligation_owner_pubkey = Pubkey::new_unique();
-        let host_fee_receiver_pubkey = Some(Pubkey::new_unique());
-        let instruction = borrow_obligation_liquidity(
+        let instruction = migrate_obligation(program_id, obligation_pubkey, lending_market_pubkey);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(!instruction.accounts[1].is_writable);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::MigrateObligation)
+        );
+    }
+
+    #[test]
+    fn test_set_reserve_status() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner = Pubkey::new_unique();
+        let instruction = set_reserve_status(
             program_id,
-            liquidity_amount,
-            None,
-            source_liquidity_pubkey,
-            destination_liquidity_pubkey,
-            borrow_reserve_pubkey,
-            borrow_reserve_liquidity_fee_receiver_pubkey,
-            obligation_pubkey,
+            ReserveStatus::Obsolete,
+            reserve_pubkey,
             lending_market_pubkey,
-            obligation_owner_pubkey,
-            host_fee_receiver_pubkey,
+            lending_market_owner,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 11);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[2].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::BorrowObligationLiquidity {
-                liquidity_amount,
-                slippage_limit: 0
-            }
-            .pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReserveStatus {
+                status: ReserveStatus::Obsolete
+            })
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&[29, 3]),
+            Err(LendingError::InvalidConfig.into())
         );
     }
 
     #[test]
-    fn test_repay_obligation_liquidity() {
+    fn test_donate_liquidity() {
         let program_id = Pubkey::new_unique();
-        let liquidity_amount = u64::MAX;
+        let liquidity_amount = 1_000;
         let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_liquidity_pubkey = Pubkey::new_unique();
-        let repay_reserve_pubkey = Pubkey::new_unique();
-        let obligation_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = repay_obligation_liquidity(
+        let user_transfer_authority = Pubkey::new_unique();
+        let instruction = donate_liquidity(
             program_id,
             liquidity_amount,
             source_liquidity_pubkey,
-            destination_liquidity_pubkey,
-            repay_reserve_pubkey,
-            obligation_pubkey,
+            reserve_pubkey,
+            reserve_liquidity_supply_pubkey,
             lending_market_pubkey,
-            user_transfer_authority_pubkey,
+            user_transfer_authority,
         );
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 8);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert!(instruction.accounts[1].is_writable);
+        assert!(instruction.accounts[4].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::RepayObligationLiquidity { liquidity_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::DonateLiquidity { liquidity_amount })
         );
     }
 
     #[test]
-    fn test_liquidate_obligation() {
+    fn test_borrow_allowlist() {
         let program_id = Pubkey::new_unique();
-        let liquidity_amount = u64::MAX;
-        let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_collateral_pubkey = Pubkey::new_unique();
-        let repay_reserve_pubkey = Pubkey::new_unique();
-        let repay_reserve_liquidity_supply_pubkey = Pubkey::new_unique();
-        let withdraw_reserve_pubkey = Pubkey::new_unique();
-        let withdraw_reserve_collateral_supply_pubkey = Pubkey::new_unique();
-        let obligation_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let user_transfer_authority_pubkey = Pubkey::new_unique();
-        let instruction = liquidate_obligation(
+        let lending_market_owner = Pubkey::new_unique();
+        let borrower = Pubkey::new_unique();
+        let (borrow_allowlist_entry_pubkey, _bump_seed) =
+            find_borrow_allowlist_entry_address(&program_id, &lending_market_pubkey, &borrower);
+
+        let instruction = set_permissioned_borrowing(
             program_id,
-            liquidity_amount,
-            source_liquidity_pubkey,
-            destination_collateral_pubkey,
-            repay_reserve_pubkey,
-            repay_reserve_liquidity_supply_pubkey,
-            withdraw_reserve_pubkey,
-            withdraw_reserve_collateral_supply_pubkey,
-            obligation_pubkey,
             lending_market_pubkey,
-            user_transfer_authority_pubkey,
+            lending_market_owner,
+            true,
         );
-        assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 12);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[1].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::LiquidateObligation { liquidity_amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetPermissionedBorrowing {
+                permissioned_borrowing: true
+            })
+        );
+
+        let instruction = add_to_borrow_allowlist(
+            program_id,
+            borrower,
+            lending_market_pubkey,
+            lending_market_owner,
+            lending_market_owner,
+        );
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            borrow_allowlist_entry_pubkey
+        );
+        assert!(instruction.accounts[3].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::AddToBorrowAllowlist { borrower })
+        );
+
+        let instruction = remove_from_borrow_allowlist(
+            program_id,
+            borrower,
+            lending_market_pubkey,
+            lending_market_owner,
+            lending_market_owner,
+        );
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            borrow_allowlist_entry_pubkey
+        );
+        assert!(instruction.accounts[3].is_writable);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::RemoveFromBorrowAllowlist)
         );
     }
 
     #[test]
-    fn test_flash_loan() {
+    fn test_set_price_override() {
         let program_id = Pubkey::new_unique();
-        let amount = u64::MAX;
-        let source_liquidity_pubkey = Pubkey::new_unique();
-        let destination_liquidity_pubkey = Pubkey::new_unique();
-        let repay_reserve_pubkey = Pubkey::new_unique();
-        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
-        let host_fee_receiver_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let flash_loan_receiver_program_id = Pubkey::new_unique();
-        let account_meta = AccountMeta {
-            pubkey: Pubkey::new_unique(),
-            is_signer: true,
-            is_writable: false,
-        };
-        let flash_loan_receiver_program_accounts = vec![account_meta];
-        let instruction = flash_loan(
+        let lending_market_owner = Pubkey::new_unique();
+
+        for price_override in [Some(Decimal::from_scaled_val(1_234_567_890)), None] {
+            let instruction = set_price_override(
+                program_id,
+                price_override,
+                reserve_pubkey,
+                lending_market_pubkey,
+                lending_market_owner,
+            );
+            assert_eq!(instruction.accounts.len(), 3);
+            assert!(instruction.accounts[0].is_writable);
+            assert!(instruction.accounts[2].is_signer);
+            assert_eq!(
+                LendingInstruction::unpack(&instruction.data),
+                Ok(LendingInstruction::SetPriceOverride { price_override })
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_market_metadata() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let instruction = set_market_metadata(
             program_id,
-            amount,
-            source_liquidity_pubkey,
-            destination_liquidity_pubkey,
-            repay_reserve_pubkey,
-            reserve_liquidity_fee_receiver_pubkey,
-            host_fee_receiver_pubkey,
+            "Main market".to_string(),
+            "https://example.com/main.json".to_string(),
+            0,
             lending_market_pubkey,
-            flash_loan_receiver_program_id,
-            flash_loan_receiver_program_accounts,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            get_market_metadata_address(&program_id, &lending_market_pubkey)
+        );
+        assert!(instruction.accounts[2].is_signer);
+        assert!(instruction.accounts[3].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetMarketMetadata {
+                name: "Main market".to_string(),
+                description_uri: "https://example.com/main.json".to_string(),
+                flags: 0,
+            })
+        );
+
+        // a truncated string fails to unpack
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data[..10]),
+            Err(LendingError::InstructionUnpackError.into())
+        );
+    }
+
+    #[test]
+    fn test_flash_borrow_and_repay() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+
+        let instruction = flash_borrow_reserve_liquidity(
+            program_id,
+            1_000,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            reserve_pubkey,
+            Pubkey::new_unique(),
+        );
+        assert_eq!(instruction.accounts[2].pubkey, reserve_pubkey);
+        assert_eq!(instruction.accounts[5].pubkey, sysvar::instructions::id());
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::FlashBorrowReserveLiquidity {
+                liquidity_amount: 1_000
+            })
+        );
+
+        let instruction = flash_repay_reserve_liquidity(
+            program_id,
+            1_000,
+            2,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            reserve_pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(instruction.accounts[4].pubkey, reserve_pubkey);
+        assert!(instruction.accounts[6].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::FlashRepayReserveLiquidity {
+                liquidity_amount: 1_000,
+                borrow_instruction_index: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_min_borrow_value() {
+        let min_borrow_value = Decimal::from_scaled_val(10_500_000_000_000_000_000);
+        let instruction = set_min_borrow_value(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            min_borrow_value,
+        );
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetMinBorrowValue { min_borrow_value })
         );
+    }
+
+    #[test]
+    fn test_set_dust_liquidation_threshold() {
+        let dust_liquidation_threshold = Decimal::from_scaled_val(500_000_000_000_000_000);
+        let instruction = set_dust_liquidation_threshold(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            dust_liquidation_threshold,
+        );
+        assert_eq!(instruction.accounts.len(), 2);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetDustLiquidationThreshold {
+                dust_liquidation_threshold
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate_account() {
+        let program_id = Pubkey::new_unique();
+        let account_pubkey = Pubkey::new_unique();
+        let payer_pubkey = Pubkey::new_unique();
+        let instruction = migrate_account(program_id, account_pubkey, payer_pubkey);
         assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 10);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(instruction.accounts[0].pubkey, account_pubkey);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(!instruction.accounts[0].is_signer);
+        assert!(instruction.accounts[1].is_writable);
+        assert!(instruction.accounts[1].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::FlashLoan { amount }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::MigrateAccount)
         );
     }
 
     #[test]
-    fn test_modify_reserve_config() {
+    fn test_refresh_reserves() {
+        let program_id = Pubkey::new_unique();
+        let reserves: Vec<(Pubkey, Pubkey)> = (0..3)
+            .map(|_| (Pubkey::new_unique(), Pubkey::new_unique()))
+            .collect();
+        let instruction = refresh_reserves(program_id, &reserves);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_eq!(instruction.accounts[0].pubkey, sysvar::clock::id());
+        for (index, (reserve_pubkey, oracle_pubkey)) in reserves.iter().enumerate() {
+            let reserve_account = &instruction.accounts[1 + 2 * index];
+            let oracle_account = &instruction.accounts[2 + 2 * index];
+            assert_eq!(reserve_account.pubkey, *reserve_pubkey);
+            assert!(reserve_account.is_writable);
+            assert_eq!(oracle_account.pubkey, *oracle_pubkey);
+            assert!(!oracle_account.is_writable);
+        }
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::RefreshReserves { reserves: 3 })
+        );
+    }
+
+    #[test]
+    fn test_reserve_rewards() {
         let program_id = Pubkey::new_unique();
-        let config = ReserveConfig {
-            optimal_utilization_rate: 60,
-            loan_to_value_ratio: 1,
-            liquidation_bonus: 10,
-            liquidation_threshold: 5,
-            min_borrow_rate: 2,
-            optimal_borrow_rate: 4,
-            max_borrow_rate: 10,
-            fees: ReserveFees {
-                borrow_fee_wad: 1,
-                flash_loan_fee_wad: 3,
-                host_fee_percentage: 1,
-            },
-        };
         let reserve_pubkey = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let reward_vault_pubkey = Pubkey::new_unique();
         let lending_market_pubkey = Pubkey::new_unique();
-        let lending_market_owner_pubkey = Pubkey::new_unique();
-        let instruction = modify_reserve_config(
+        let owner = Pubkey::new_unique();
+        let rewards_per_slot = 1_000;
+
+        let instruction = set_reserve_rewards(
             program_id,
-            config,
+            rewards_per_slot,
             reserve_pubkey,
+            reward_vault_pubkey,
             lending_market_pubkey,
-            lending_market_owner_pubkey,
+            owner,
         );
-        assert_eq!(instruction.program_id, program_id);
-        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert!(instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[3].is_signer);
         assert_eq!(
-            instruction.data,
-            LendingInstruction::ModifyReserveConfig { new_config: config }.pack()
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::SetReserveRewards { rewards_per_slot })
+        );
+
+        let instruction = claim_reserve_rewards(
+            program_id,
+            obligation_pubkey,
+            reserve_pubkey,
+            reward_vault_pubkey,
+            Pubkey::new_unique(),
+            lending_market_pubkey,
+            owner,
+            owner,
+        );
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            find_reward_checkpoint_address(&program_id, &obligation_pubkey, &reserve_pubkey).0
+        );
+        assert_eq!(
+            instruction.accounts[6].pubkey,
+            find_reward_authority_address(&program_id, &reserve_pubkey).0
+        );
+        assert!(instruction.accounts[7].is_signer);
+        assert!(instruction.accounts[8].is_signer);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data),
+            Ok(LendingInstruction::ClaimReserveRewards)
         );
     }
 }
@@ -3,13 +3,17 @@
 
 //! A lending program for the Solana blockchain.
 
+pub mod cpi;
 pub mod entrypoint;
 pub mod error;
+pub mod events;
 pub mod instruction;
+pub mod liquidation;
 pub mod math;
 pub mod processor;
 pub mod pyth;
 pub mod state;
+pub mod switchboard;
 
 // Export current sdk types for downstream users building with a different sdk
 // version
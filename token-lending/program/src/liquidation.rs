@@ -0,0 +1,345 @@
+//! Client-side helpers composing liquidation instructions
+
+use {
+    crate::{
+        error::LendingError,
+        instruction::{
+            liquidate_obligation, redeem_reserve_collateral, refresh_obligation, refresh_reserve,
+        },
+        state::{Obligation, Reserve},
+    },
+    solana_program::{instruction::Instruction, msg, program_error::ProgramError, pubkey::Pubkey},
+};
+
+/// Accounts of the liquidator taking part in a liquidation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidatorAccounts {
+    /// Token account holding the repay reserve liquidity
+    pub source_liquidity_pubkey: Pubkey,
+    /// Token account receiving the withdraw reserve collateral
+    pub destination_collateral_pubkey: Pubkey,
+    /// Authority delegated to transfer from the liquidator's token accounts
+    pub user_transfer_authority_pubkey: Pubkey,
+}
+
+/// Redemption of the collateral seized by a liquidation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedeemSeizedCollateral {
+    /// Amount of seized collateral to redeem
+    pub collateral_amount: u64,
+    /// Minimum amount of liquidity to receive, if any
+    pub min_liquidity_amount: Option<u64>,
+    /// Token account receiving the withdraw reserve liquidity
+    pub destination_liquidity_pubkey: Pubkey,
+}
+
+/// Composes the instructions to liquidate an obligation in one transaction,
+/// optionally redeeming the seized collateral back into liquidity.
+///
+/// Refreshes every reserve of the obligation and the obligation itself, then
+/// liquidates. When redeeming, the withdraw reserve is refreshed again since
+/// liquidation leaves it stale when it is also the repay reserve.
+///
+/// `reserves` must hold the state of every reserve of the obligation.
+#[allow(clippy::too_many_arguments)]
+pub fn liquidate_obligation_instructions(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    obligation_pubkey: Pubkey,
+    obligation: &Obligation,
+    reserves: &[(Pubkey, Reserve)],
+    repay_reserve_pubkey: Pubkey,
+    withdraw_reserve_pubkey: Pubkey,
+    liquidator: LiquidatorAccounts,
+    redeem: Option<RedeemSeizedCollateral>,
+) -> Result<Vec<Instruction>, ProgramError> {
+    let find_reserve = |reserve_pubkey: &Pubkey| {
+        reserves
+            .iter()
+            .find(|(pubkey, _)| pubkey == reserve_pubkey)
+            .map(|(_, reserve)| reserve)
+            .ok_or_else(|| {
+                msg!(
+                    "Reserve {} of the liquidation was not provided",
+                    reserve_pubkey
+                );
+                ProgramError::from(LendingError::InvalidAccountInput)
+            })
+    };
+
+    let obligation_reserve_pubkeys: Vec<Pubkey> = obligation
+        .deposits
+        .iter()
+        .map(|collateral| collateral.deposit_reserve)
+        .chain(
+            obligation
+                .borrows
+                .iter()
+                .map(|liquidity| liquidity.borrow_reserve),
+        )
+        .collect();
+
+    let mut instructions = vec![];
+    let mut refreshed_reserve_pubkeys: Vec<Pubkey> = vec![];
+    for reserve_pubkey in &obligation_reserve_pubkeys {
+        if refreshed_reserve_pubkeys.contains(reserve_pubkey) {
+            continue;
+        }
+        let reserve = find_reserve(reserve_pubkey)?;
+        instructions.push(refresh_reserve(
+            program_id,
+            *reserve_pubkey,
+            reserve.liquidity.oracle_pubkey,
+        ));
+        refreshed_reserve_pubkeys.push(*reserve_pubkey);
+    }
+    instructions.push(refresh_obligation(
+        program_id,
+        obligation_pubkey,
+        obligation_reserve_pubkeys,
+    ));
+
+    let repay_reserve = find_reserve(&repay_reserve_pubkey)?;
+    let withdraw_reserve = find_reserve(&withdraw_reserve_pubkey)?;
+    let collateral_fee_receiver_pubkey =
+        if withdraw_reserve.collateral.fee_receiver != Pubkey::default() {
+            Some(withdraw_reserve.collateral.fee_receiver)
+        } else {
+            None
+        };
+    instructions.push(liquidate_obligation(
+        program_id,
+        liquidity_amount,
+        liquidator.source_liquidity_pubkey,
+        liquidator.destination_collateral_pubkey,
+        repay_reserve_pubkey,
+        repay_reserve.liquidity.supply_pubkey,
+        withdraw_reserve_pubkey,
+        withdraw_reserve.collateral.supply_pubkey,
+        obligation_pubkey,
+        obligation.lending_market,
+        liquidator.user_transfer_authority_pubkey,
+        collateral_fee_receiver_pubkey,
+    ));
+
+    if let Some(redeem) = redeem {
+        instructions.push(refresh_reserve(
+            program_id,
+            withdraw_reserve_pubkey,
+            withdraw_reserve.liquidity.oracle_pubkey,
+        ));
+        instructions.push(redeem_reserve_collateral(
+            program_id,
+            redeem.collateral_amount,
+            redeem.min_liquidity_amount,
+            liquidator.destination_collateral_pubkey,
+            redeem.destination_liquidity_pubkey,
+            withdraw_reserve_pubkey,
+            withdraw_reserve.collateral.mint_pubkey,
+            withdraw_reserve.liquidity.supply_pubkey,
+            obligation.lending_market,
+            liquidator.user_transfer_authority_pubkey,
+        ));
+    }
+
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            instruction::LendingInstruction,
+            math::Decimal,
+            state::{ObligationCollateral, ObligationLiquidity},
+        },
+    };
+
+    struct LiquidationAccounts {
+        obligation_pubkey: Pubkey,
+        obligation: Obligation,
+        reserves: Vec<(Pubkey, Reserve)>,
+        liquidator: LiquidatorAccounts,
+    }
+
+    // An obligation borrowing from the second reserve against the first
+    fn liquidation_accounts() -> LiquidationAccounts {
+        let mut reserves = vec![];
+        for _ in 0..2 {
+            let mut reserve = Reserve::default();
+            reserve.liquidity.supply_pubkey = Pubkey::new_unique();
+            reserve.liquidity.oracle_pubkey = Pubkey::new_unique();
+            reserve.collateral.mint_pubkey = Pubkey::new_unique();
+            reserve.collateral.supply_pubkey = Pubkey::new_unique();
+            reserves.push((Pubkey::new_unique(), reserve));
+        }
+        let obligation = Obligation {
+            lending_market: Pubkey::new_unique(),
+            deposits: vec![ObligationCollateral::new(reserves[0].0)],
+            borrows: vec![ObligationLiquidity::new(reserves[1].0, Decimal::one())],
+            ..Obligation::default()
+        };
+        LiquidationAccounts {
+            obligation_pubkey: Pubkey::new_unique(),
+            obligation,
+            reserves,
+            liquidator: LiquidatorAccounts {
+                source_liquidity_pubkey: Pubkey::new_unique(),
+                destination_collateral_pubkey: Pubkey::new_unique(),
+                user_transfer_authority_pubkey: Pubkey::new_unique(),
+            },
+        }
+    }
+
+    #[test]
+    fn liquidate_without_redeem() {
+        let program_id = Pubkey::new_unique();
+        let accounts = liquidation_accounts();
+        let (withdraw_reserve_pubkey, withdraw_reserve) = &accounts.reserves[0];
+        let (repay_reserve_pubkey, repay_reserve) = &accounts.reserves[1];
+
+        let instructions = liquidate_obligation_instructions(
+            program_id,
+            100,
+            accounts.obligation_pubkey,
+            &accounts.obligation,
+            &accounts.reserves,
+            *repay_reserve_pubkey,
+            *withdraw_reserve_pubkey,
+            accounts.liquidator,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(
+            instructions[0],
+            refresh_reserve(
+                program_id,
+                *withdraw_reserve_pubkey,
+                withdraw_reserve.liquidity.oracle_pubkey
+            )
+        );
+        assert_eq!(
+            instructions[1],
+            refresh_reserve(
+                program_id,
+                *repay_reserve_pubkey,
+                repay_reserve.liquidity.oracle_pubkey
+            )
+        );
+        assert_eq!(
+            instructions[2],
+            refresh_obligation(
+                program_id,
+                accounts.obligation_pubkey,
+                vec![*withdraw_reserve_pubkey, *repay_reserve_pubkey]
+            )
+        );
+        assert_eq!(
+            instructions[3],
+            liquidate_obligation(
+                program_id,
+                100,
+                accounts.liquidator.source_liquidity_pubkey,
+                accounts.liquidator.destination_collateral_pubkey,
+                *repay_reserve_pubkey,
+                repay_reserve.liquidity.supply_pubkey,
+                *withdraw_reserve_pubkey,
+                withdraw_reserve.collateral.supply_pubkey,
+                accounts.obligation_pubkey,
+                accounts.obligation.lending_market,
+                accounts.liquidator.user_transfer_authority_pubkey,
+                None,
+            )
+        );
+    }
+
+    #[test]
+    fn liquidate_and_redeem() {
+        let program_id = Pubkey::new_unique();
+        let mut accounts = liquidation_accounts();
+        let collateral_fee_receiver = Pubkey::new_unique();
+        accounts.reserves[0].1.collateral.fee_receiver = collateral_fee_receiver;
+        let (withdraw_reserve_pubkey, withdraw_reserve) = &accounts.reserves[0];
+        let (repay_reserve_pubkey, _repay_reserve) = &accounts.reserves[1];
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+
+        let instructions = liquidate_obligation_instructions(
+            program_id,
+            100,
+            accounts.obligation_pubkey,
+            &accounts.obligation,
+            &accounts.reserves,
+            *repay_reserve_pubkey,
+            *withdraw_reserve_pubkey,
+            accounts.liquidator,
+            Some(RedeemSeizedCollateral {
+                collateral_amount: 50,
+                min_liquidity_amount: Some(40),
+                destination_liquidity_pubkey,
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(instructions.len(), 6);
+        // the liquidation passes the withdraw reserve collateral fee receiver
+        assert_eq!(
+            instructions[3].accounts.last().unwrap().pubkey,
+            collateral_fee_receiver
+        );
+        assert_eq!(
+            instructions[4],
+            refresh_reserve(
+                program_id,
+                *withdraw_reserve_pubkey,
+                withdraw_reserve.liquidity.oracle_pubkey
+            )
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instructions[5].data),
+            Ok(LendingInstruction::RedeemReserveCollateral {
+                collateral_amount: 50,
+                min_liquidity_amount: 40,
+            })
+        );
+        assert_eq!(
+            instructions[5],
+            redeem_reserve_collateral(
+                program_id,
+                50,
+                Some(40),
+                accounts.liquidator.destination_collateral_pubkey,
+                destination_liquidity_pubkey,
+                *withdraw_reserve_pubkey,
+                withdraw_reserve.collateral.mint_pubkey,
+                withdraw_reserve.liquidity.supply_pubkey,
+                accounts.obligation.lending_market,
+                accounts.liquidator.user_transfer_authority_pubkey,
+            )
+        );
+    }
+
+    #[test]
+    fn liquidate_missing_reserve() {
+        let accounts = liquidation_accounts();
+        let (withdraw_reserve_pubkey, _withdraw_reserve) = &accounts.reserves[0];
+        let (repay_reserve_pubkey, _repay_reserve) = &accounts.reserves[1];
+
+        assert_eq!(
+            liquidate_obligation_instructions(
+                Pubkey::new_unique(),
+                100,
+                accounts.obligation_pubkey,
+                &accounts.obligation,
+                &accounts.reserves[..1],
+                *repay_reserve_pubkey,
+                *withdraw_reserve_pubkey,
+                accounts.liquidator,
+                None,
+            ),
+            Err(LendingError::InvalidAccountInput.into())
+        );
+    }
+}
@@ -3,5 +3,6 @@
 mod common;
 mod decimal;
 mod rate;
+mod signed_decimal;
 
-pub use {common::*, decimal::*, rate::*};
+pub use {common::*, decimal::*, rate::*, signed_decimal::*};
@@ -89,6 +89,31 @@ impl Rate {
 
         Ok(ret)
     }
+
+    /// Calculates base^exp, saturating at max instead of failing when the
+    /// result or an intermediate square overflows
+    pub fn saturating_pow(&self, mut exp: u64, max: Rate) -> Rate {
+        let mut base = (*self).min(max);
+        let mut ret = Self::one().min(max);
+
+        while exp > 0 {
+            if exp % 2 != 0 {
+                ret = ret.saturating_mul(base).min(max);
+            }
+            exp /= 2;
+
+            // Squaring past the last exponent bit could overflow needlessly
+            if exp > 0 {
+                base = base.saturating_mul(base).min(max);
+            }
+        }
+
+        ret
+    }
+
+    fn saturating_mul(self, rhs: Self) -> Self {
+        Self(self.0.saturating_mul(rhs.0) / Self::wad())
+    }
 }
 
 impl fmt::Display for Rate {
@@ -183,4 +208,37 @@ mod test {
     fn checked_pow() {
         assert_eq!(Rate::one(), Rate::one().try_pow(u64::MAX).unwrap());
     }
+
+    #[test]
+    fn saturating_pow() {
+        let max = Rate::from_scaled_val(16 * WAD);
+        assert_eq!(Rate::one(), Rate::one().saturating_pow(u64::MAX, max));
+        assert_eq!(Rate::one(), Rate::from_percent(50).saturating_pow(0, max));
+
+        let rate = Rate::one().try_add(Rate::from_percent(1)).unwrap();
+        for exp in [1, 2, 7, 100, 255] {
+            assert_eq!(
+                rate.try_pow(exp).unwrap(),
+                rate.saturating_pow(exp, max),
+                "exp {}",
+                exp
+            );
+        }
+
+        // results and squares past the max saturate
+        let two = Rate::from_scaled_val(2 * WAD);
+        assert_eq!(two.saturating_pow(4, max), max);
+        assert_eq!(two.saturating_pow(5, max), max);
+        assert_eq!(two.saturating_pow(u64::MAX, max), max);
+        assert_eq!(Rate::from_scaled_val(18 * WAD).saturating_pow(3, max), max);
+
+        assert_eq!(
+            Rate::from_percent(50).saturating_pow(2, max),
+            Rate::from_percent(25)
+        );
+        assert_eq!(
+            Rate::from_percent(50).saturating_pow(u64::MAX, max),
+            Rate::zero()
+        );
+    }
 }
@@ -0,0 +1,205 @@
+//! Math for values that can go below zero, such as borrow rates once rebates
+//! are applied.
+//!
+//! Signed decimals keep a sign flag next to an unsigned [Decimal] magnitude,
+//! so they share its precision and range. Zero is never negative.
+
+use {
+    crate::{
+        error::LendingError,
+        math::{common::*, Decimal, Rate},
+    },
+    solana_program::program_error::ProgramError,
+    std::{cmp::Ordering, convert::TryFrom, fmt, ops::Neg},
+};
+
+/// Signed decimal values, precise to 18 digits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SignedDecimal {
+    negative: bool,
+    magnitude: Decimal,
+}
+
+impl SignedDecimal {
+    /// Zero
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Create signed decimal from a sign and magnitude
+    pub fn new(negative: bool, magnitude: Decimal) -> Self {
+        Self {
+            negative: negative && magnitude != Decimal::zero(),
+            magnitude,
+        }
+    }
+
+    /// Whether the value is below zero
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Absolute value
+    pub fn abs(&self) -> Decimal {
+        self.magnitude
+    }
+}
+
+impl fmt::Display for SignedDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        fmt::Display::fmt(&self.magnitude, f)
+    }
+}
+
+impl Ord for SignedDecimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for SignedDecimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for SignedDecimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(!self.negative, self.magnitude)
+    }
+}
+
+impl From<Decimal> for SignedDecimal {
+    fn from(val: Decimal) -> Self {
+        Self::new(false, val)
+    }
+}
+
+impl From<Rate> for SignedDecimal {
+    fn from(val: Rate) -> Self {
+        Self::from(Decimal::from(val))
+    }
+}
+
+impl TryFrom<SignedDecimal> for Decimal {
+    type Error = ProgramError;
+
+    fn try_from(val: SignedDecimal) -> Result<Self, Self::Error> {
+        if val.negative {
+            return Err(LendingError::MathOverflow.into());
+        }
+        Ok(val.magnitude)
+    }
+}
+
+impl TryAdd for SignedDecimal {
+    fn try_add(self, rhs: Self) -> Result<Self, ProgramError> {
+        if self.negative == rhs.negative {
+            Ok(Self::new(
+                self.negative,
+                self.magnitude.try_add(rhs.magnitude)?,
+            ))
+        } else if self.magnitude >= rhs.magnitude {
+            Ok(Self::new(
+                self.negative,
+                self.magnitude.try_sub(rhs.magnitude)?,
+            ))
+        } else {
+            Ok(Self::new(
+                rhs.negative,
+                rhs.magnitude.try_sub(self.magnitude)?,
+            ))
+        }
+    }
+}
+
+impl TrySub for SignedDecimal {
+    fn try_sub(self, rhs: Self) -> Result<Self, ProgramError> {
+        self.try_add(-rhs)
+    }
+}
+
+impl TryMul<SignedDecimal> for SignedDecimal {
+    fn try_mul(self, rhs: Self) -> Result<Self, ProgramError> {
+        Ok(Self::new(
+            self.negative != rhs.negative,
+            self.magnitude.try_mul(rhs.magnitude)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::math::U192};
+
+    fn signed(val: i64) -> SignedDecimal {
+        SignedDecimal::new(val < 0, Decimal::from(val.unsigned_abs()))
+    }
+
+    #[test]
+    fn sign_crossover() {
+        assert_eq!(signed(1).try_sub(signed(3)), Ok(signed(-2)));
+        assert_eq!(signed(-2).try_add(signed(5)), Ok(signed(3)));
+        assert_eq!(signed(-2).try_sub(signed(-5)), Ok(signed(3)));
+        assert_eq!(signed(3).try_add(signed(-5)), Ok(signed(-2)));
+        assert_eq!(signed(-2).try_add(signed(-5)), Ok(signed(-7)));
+
+        let zero = signed(-2).try_add(signed(2)).unwrap();
+        assert_eq!(zero, SignedDecimal::zero());
+        assert!(!zero.is_negative());
+        assert!(!(-SignedDecimal::zero()).is_negative());
+    }
+
+    #[test]
+    fn multiplication_sign() {
+        assert_eq!(signed(-2).try_mul(signed(3)), Ok(signed(-6)));
+        assert_eq!(signed(2).try_mul(signed(-3)), Ok(signed(-6)));
+        assert_eq!(signed(-2).try_mul(signed(-3)), Ok(signed(6)));
+        assert_eq!(
+            signed(-2).try_mul(SignedDecimal::zero()),
+            Ok(SignedDecimal::zero())
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(signed(-3) < signed(-2));
+        assert!(signed(-2) < SignedDecimal::zero());
+        assert!(SignedDecimal::zero() < signed(1));
+        assert_eq!(signed(-3).max(signed(-5)), signed(-3));
+    }
+
+    #[test]
+    fn overflow() {
+        let max = SignedDecimal::from(Decimal(U192::MAX));
+        assert_eq!(
+            max.try_add(signed(1)),
+            Err(LendingError::MathOverflow.into())
+        );
+        assert_eq!(
+            (-max).try_sub(signed(1)),
+            Err(LendingError::MathOverflow.into())
+        );
+        assert_eq!(
+            Decimal::try_from(signed(-1)),
+            Err(LendingError::MathOverflow.into())
+        );
+        assert_eq!(Decimal::try_from(signed(1)), Ok(Decimal::one()));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(signed(-2).to_string(), "-2.000000000000000000");
+        assert_eq!(signed(2).to_string(), "2.000000000000000000");
+    }
+}
@@ -2,35 +2,60 @@
 
 use {
     crate::{
+        cpi::RECEIVE_FLASH_LOAN_INSTRUCTION_TAG,
         error::LendingError,
+        events::{BorrowEvent, DepositEvent, LendingEvent, LiquidateEvent, RepayEvent},
         instruction::LendingInstruction,
-        math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
+        math::{Decimal, Rate, TryAdd, TryDiv, TryMul, TrySub, SCALE},
         pyth,
         state::{
+            find_borrow_allowlist_entry_address, find_market_metadata_address,
+            find_obligation_address, find_redeem_request_address, find_reward_authority_address,
+            find_reward_checkpoint_address, get_market_metadata_address, BorrowAllowlistEntry,
             CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
-            InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-            NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, Reserve,
-            ReserveCollateral, ReserveConfig, ReserveLiquidity,
+            InitLendingMarketParams, InitMarketMetadataParams, InitObligationParams,
+            InitRedeemRequestParams, InitReserveParams, InitRewardCheckpointParams, LendingMarket,
+            MarketMetadata, NewReserveCollateralParams, NewReserveLiquidityParams, Obligation,
+            OracleKind, RedeemRequest, Reserve, ReserveCollateral, ReserveConfig, ReserveLiquidity,
+            ReserveStatus, RewardCheckpoint, BORROW_ALLOWLIST_SEED, LENDING_MARKET_V1_LEN,
+            MARKET_METADATA_SEED, MAX_MARKET_DESCRIPTION_URI_LEN, MAX_MARKET_NAME_LEN,
+            MAX_RESIZED_OBLIGATION_RESERVES, MIN_INITIAL_COLLATERAL_AMOUNT, OBLIGATION_SEED,
+            PROGRAM_VERSION, REDEEM_REQUEST_SEED, RESERVE_PAUSE_ALL, RESERVE_PAUSE_BORROWS,
+            RESERVE_PAUSE_DEPOSITS, RESERVE_PAUSE_LIQUIDATIONS, RESERVE_PAUSE_WITHDRAWALS,
+            RESERVE_V1_LEN, REWARD_AUTHORITY_SEED, REWARD_CHECKPOINT_SEED,
         },
+        switchboard,
     },
     num_traits::FromPrimitive,
     solana_program::{
         account_info::{next_account_info, AccountInfo},
+        clock::Slot,
         decode_error::DecodeError,
         entrypoint::ProgramResult,
-        instruction::Instruction,
+        instruction::{get_stack_height, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT},
         msg,
         program::{invoke, invoke_signed},
         program_error::{PrintProgramError, ProgramError},
         program_pack::{IsInitialized, Pack},
         pubkey::Pubkey,
-        sysvar::{clock::Clock, rent::Rent, Sysvar},
+        system_instruction,
+        sysvar::{
+            clock::Clock,
+            instructions::{load_current_index_checked, load_instruction_at_checked},
+            rent::Rent,
+            Sysvar,
+        },
+    },
+    spl_associated_token_account::{
+        get_associated_token_address_with_program_id,
+        instruction::create_associated_token_account_idempotent,
     },
     spl_token::{
+        instruction::AuthorityType,
         solana_program::instruction::AccountMeta,
         state::{Account, Mint},
     },
-    std::convert::TryInto,
+    std::{convert::TryInto, mem::size_of},
 };
 
 /// Processes an instruction
@@ -55,33 +80,72 @@ pub fn process_instruction(
         LendingInstruction::InitReserve {
             liquidity_amount,
             config,
+            derive_fee_receiver,
         } => {
             msg!("Instruction: Init Reserve");
-            process_init_reserve(program_id, liquidity_amount, config, accounts)
+            process_init_reserve(
+                program_id,
+                liquidity_amount,
+                config,
+                derive_fee_receiver,
+                accounts,
+            )
         }
         LendingInstruction::RefreshReserve => {
             msg!("Instruction: Refresh Reserve");
             process_refresh_reserve(program_id, accounts)
         }
-        LendingInstruction::DepositReserveLiquidity { liquidity_amount } => {
+        LendingInstruction::DepositReserveLiquidity {
+            liquidity_amount,
+            min_collateral_amount,
+        } => {
             msg!("Instruction: Deposit Reserve Liquidity");
-            process_deposit_reserve_liquidity(program_id, liquidity_amount, accounts)
+            process_deposit_reserve_liquidity(
+                program_id,
+                liquidity_amount,
+                min_collateral_amount,
+                accounts,
+            )
         }
-        LendingInstruction::RedeemReserveCollateral { collateral_amount } => {
+        LendingInstruction::RedeemReserveCollateral {
+            collateral_amount,
+            min_liquidity_amount,
+        } => {
             msg!("Instruction: Redeem Reserve Collateral");
-            process_redeem_reserve_collateral(program_id, collateral_amount, accounts)
+            process_redeem_reserve_collateral(
+                program_id,
+                collateral_amount,
+                min_liquidity_amount,
+                accounts,
+            )
         }
         LendingInstruction::InitObligation => {
             msg!("Instruction: Init Obligation");
             process_init_obligation(program_id, accounts)
         }
+        LendingInstruction::InitDerivedObligation => {
+            msg!("Instruction: Init Derived Obligation");
+            process_init_derived_obligation(program_id, accounts)
+        }
+        LendingInstruction::RequestRedeem { collateral_amount } => {
+            msg!("Instruction: Request Redeem");
+            process_request_redeem(program_id, collateral_amount, accounts)
+        }
+        LendingInstruction::FulfillRedeem => {
+            msg!("Instruction: Fulfill Redeem");
+            process_fulfill_redeem(program_id, accounts)
+        }
+        LendingInstruction::CancelRedeem { reroute } => {
+            msg!("Instruction: Cancel Redeem");
+            process_cancel_redeem(program_id, reroute, accounts)
+        }
         LendingInstruction::RefreshObligation => {
             msg!("Instruction: Refresh Obligation");
             process_refresh_obligation(program_id, accounts)
         }
         LendingInstruction::DepositObligationCollateral { collateral_amount } => {
             msg!("Instruction: Deposit Obligation Collateral");
-            process_deposit_obligation_collateral(program_id, collateral_amount, accounts)
+            process_deposit_obligation_collateral(program_id, collateral_amount, accounts, false)
         }
         LendingInstruction::WithdrawObligationCollateral { collateral_amount } => {
             msg!("Instruction: Withdraw Obligation Collateral");
@@ -115,6 +179,168 @@ pub fn process_instruction(
             msg!("Instruction: Modify Reserve Config");
             process_modify_reserve_config(program_id, new_config, accounts)
         }
+        LendingInstruction::DepositObligationCollateralAndBorrow {
+            collateral_amount,
+            liquidity_amount,
+            slippage_limit,
+        } => {
+            msg!("Instruction: Deposit Obligation Collateral And Borrow");
+            process_deposit_obligation_collateral_and_borrow(
+                program_id,
+                collateral_amount,
+                liquidity_amount,
+                slippage_limit,
+                accounts,
+            )
+        }
+        LendingInstruction::ProposeLendingMarketOwner { new_owner } => {
+            msg!("Instruction: Propose Lending Market Owner");
+            process_propose_lending_market_owner(program_id, new_owner, accounts)
+        }
+        LendingInstruction::AcceptLendingMarketOwner => {
+            msg!("Instruction: Accept Lending Market Owner");
+            process_accept_lending_market_owner(program_id, accounts)
+        }
+        LendingInstruction::SetReservePauseFlags { pause_flags } => {
+            msg!("Instruction: Set Reserve Pause Flags");
+            process_set_reserve_pause_flags(program_id, pause_flags, accounts)
+        }
+        LendingInstruction::SetReserveOracle => {
+            msg!("Instruction: Set Reserve Oracle");
+            process_set_reserve_oracle(program_id, accounts)
+        }
+        LendingInstruction::SetReserveFlashLoanFeeReceiver => {
+            msg!("Instruction: Set Reserve Flash Loan Fee Receiver");
+            process_set_reserve_flash_loan_fee_receiver(program_id, accounts)
+        }
+        LendingInstruction::SetReserveCollateralFeeReceiver => {
+            msg!("Instruction: Set Reserve Collateral Fee Receiver");
+            process_set_reserve_collateral_fee_receiver(program_id, accounts)
+        }
+        LendingInstruction::MigrateObligationCollateral { collateral_amount } => {
+            msg!("Instruction: Migrate Obligation Collateral");
+            process_migrate_obligation_collateral(program_id, collateral_amount, accounts)
+        }
+        LendingInstruction::CloseReserve => {
+            msg!("Instruction: Close Reserve");
+            process_close_reserve(program_id, accounts)
+        }
+        LendingInstruction::WithdrawProtocolFees { amount } => {
+            msg!("Instruction: Withdraw Protocol Fees");
+            process_withdraw_protocol_fees(program_id, amount, accounts)
+        }
+        LendingInstruction::SetLendingMarketEmergencyAuthority {
+            emergency_authority,
+        } => {
+            msg!("Instruction: Set Lending Market Emergency Authority");
+            process_set_lending_market_emergency_authority(
+                program_id,
+                emergency_authority,
+                accounts,
+            )
+        }
+        LendingInstruction::SetMarketFreeze { frozen } => {
+            msg!("Instruction: Set Market Freeze");
+            process_set_market_freeze(program_id, frozen, accounts)
+        }
+        LendingInstruction::SetObligationOwner => {
+            msg!("Instruction: Set Obligation Owner");
+            process_set_obligation_owner(program_id, accounts)
+        }
+        LendingInstruction::ResizeObligation {
+            additional_reserves,
+        } => {
+            msg!("Instruction: Resize Obligation");
+            process_resize_obligation(program_id, additional_reserves, accounts)
+        }
+        LendingInstruction::MigrateObligation => {
+            msg!("Instruction: Migrate Obligation");
+            process_migrate_obligation(program_id, accounts)
+        }
+        LendingInstruction::SetReserveStatus { status } => {
+            msg!("Instruction: Set Reserve Status");
+            process_set_reserve_status(program_id, status, accounts)
+        }
+        LendingInstruction::DonateLiquidity { liquidity_amount } => {
+            msg!("Instruction: Donate Liquidity");
+            process_donate_liquidity(program_id, liquidity_amount, accounts)
+        }
+        LendingInstruction::SetPermissionedBorrowing {
+            permissioned_borrowing,
+        } => {
+            msg!("Instruction: Set Permissioned Borrowing");
+            process_set_permissioned_borrowing(program_id, permissioned_borrowing, accounts)
+        }
+        LendingInstruction::SetMinBorrowValue { min_borrow_value } => {
+            msg!("Instruction: Set Min Borrow Value");
+            process_set_min_borrow_value(program_id, min_borrow_value, accounts)
+        }
+        LendingInstruction::SetDustLiquidationThreshold {
+            dust_liquidation_threshold,
+        } => {
+            msg!("Instruction: Set Dust Liquidation Threshold");
+            process_set_dust_liquidation_threshold(program_id, dust_liquidation_threshold, accounts)
+        }
+        LendingInstruction::MigrateAccount => {
+            msg!("Instruction: Migrate Account");
+            process_migrate_account(program_id, accounts)
+        }
+        LendingInstruction::RefreshReserves { reserves } => {
+            msg!("Instruction: Refresh Reserves");
+            process_refresh_reserves(program_id, reserves, accounts)
+        }
+        LendingInstruction::SetLendingMarketRiskAuthority { risk_authority } => {
+            msg!("Instruction: Set Lending Market Risk Authority");
+            process_set_lending_market_risk_authority(program_id, risk_authority, accounts)
+        }
+        LendingInstruction::AddToBorrowAllowlist { borrower } => {
+            msg!("Instruction: Add To Borrow Allowlist");
+            process_add_to_borrow_allowlist(program_id, borrower, accounts)
+        }
+        LendingInstruction::RemoveFromBorrowAllowlist => {
+            msg!("Instruction: Remove From Borrow Allowlist");
+            process_remove_from_borrow_allowlist(program_id, accounts)
+        }
+        LendingInstruction::DepositObligationCollateralOnBehalf { collateral_amount } => {
+            msg!("Instruction: Deposit Obligation Collateral On Behalf");
+            process_deposit_obligation_collateral(program_id, collateral_amount, accounts, true)
+        }
+        LendingInstruction::SetReserveRewards { rewards_per_slot } => {
+            msg!("Instruction: Set Reserve Rewards");
+            process_set_reserve_rewards(program_id, rewards_per_slot, accounts)
+        }
+        LendingInstruction::ClaimReserveRewards => {
+            msg!("Instruction: Claim Reserve Rewards");
+            process_claim_reserve_rewards(program_id, accounts)
+        }
+        LendingInstruction::SetPriceOverride { price_override } => {
+            msg!("Instruction: Set Price Override");
+            process_set_price_override(program_id, price_override, accounts)
+        }
+        LendingInstruction::SetMarketMetadata {
+            name,
+            description_uri,
+            flags,
+        } => {
+            msg!("Instruction: Set Market Metadata");
+            process_set_market_metadata(program_id, name, description_uri, flags, accounts)
+        }
+        LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount } => {
+            msg!("Instruction: Flash Borrow Reserve Liquidity");
+            process_flash_borrow_reserve_liquidity(program_id, liquidity_amount, accounts)
+        }
+        LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        } => {
+            msg!("Instruction: Flash Repay Reserve Liquidity");
+            process_flash_repay_reserve_liquidity(
+                program_id,
+                liquidity_amount,
+                borrow_instruction_index,
+                accounts,
+            )
+        }
     }
 }
 
@@ -144,7 +370,7 @@ fn process_init_lending_market(
         token_program_id: *token_program_id.key,
         oracle_program_id: *oracle_program_id.key,
     });
-    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
     Ok(())
 }
@@ -159,7 +385,7 @@ fn process_set_lending_market_owner(
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_owner_info = next_account_info(account_info_iter)?;
 
-    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -174,65 +400,91 @@ fn process_set_lending_market_owner(
     }
 
     lending_market.owner = new_owner;
-    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+    lending_market.pending_owner = Pubkey::default();
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
     Ok(())
 }
 
-fn process_init_reserve(
+#[inline(never)] // avoid stack frame limit
+fn process_propose_lending_market_owner(
     program_id: &Pubkey,
-    liquidity_amount: u64,
-    config: ReserveConfig,
+    new_owner: Pubkey,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    if liquidity_amount == 0 {
-        msg!("Reserve must be initialized with liquidity");
-        return Err(LendingError::InvalidAmount.into());
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    config.validate()?;
+    lending_market.pending_owner = new_owner;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
-    let account_info_iter = &mut accounts.iter().peekable();
-    let source_liquidity_info = next_account_info(account_info_iter)?;
-    let destination_collateral_info = next_account_info(account_info_iter)?;
-    let reserve_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_mint_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
-    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
-    let reserve_collateral_supply_info = next_account_info(account_info_iter)?;
-    let pyth_product_info = next_account_info(account_info_iter)?;
-    let pyth_price_info = next_account_info(account_info_iter)?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_accept_lending_market_owner(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
     let lending_market_info = next_account_info(account_info_iter)?;
-    let lending_market_authority_info = next_account_info(account_info_iter)?;
-    let lending_market_owner_info = next_account_info(account_info_iter)?;
-    let user_transfer_authority_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-    let rent_info = next_account_info(account_info_iter)?;
-    let rent = &Rent::from_account_info(rent_info)?;
-    let token_program_id = next_account_info(account_info_iter)?;
+    let pending_owner_info = next_account_info(account_info_iter)?;
 
-    assert_rent_exempt(rent, reserve_info)?;
-    let mut reserve = assert_uninitialized::<Reserve>(reserve_info)?;
-    if reserve_info.owner != program_id {
-        msg!("Reserve provided is not owned by the lending program");
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-
-    if reserve_liquidity_supply_info.key == source_liquidity_info.key {
-        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
-        return Err(LendingError::InvalidAccountInput.into());
+    if lending_market.pending_owner == Pubkey::default() {
+        msg!("Lending market has no pending owner");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if &lending_market.pending_owner != pending_owner_info.key {
+        msg!("Lending market pending owner does not match the pending owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !pending_owner_info.is_signer {
+        msg!("Lending market pending owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    lending_market.owner = lending_market.pending_owner;
+    lending_market.pending_owner = Pubkey::default();
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_emergency_authority(
+    program_id: &Pubkey,
+    emergency_authority: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &lending_market.token_program_id != token_program_id.key {
-        msg!("Lending market token program does not match the token program provided");
-        return Err(LendingError::InvalidTokenProgram.into());
-    }
     if &lending_market.owner != lending_market_owner_info.key {
         msg!("Lending market owner does not match the lending market owner provided");
         return Err(LendingError::InvalidMarketOwner.into());
@@ -242,176 +494,2981 @@ fn process_init_reserve(
         return Err(LendingError::InvalidSigner.into());
     }
 
-    if &lending_market.oracle_program_id != pyth_product_info.owner {
-        msg!("Pyth product account provided is not owned by the lending market oracle program");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-    if &lending_market.oracle_program_id != pyth_price_info.owner {
-        msg!("Pyth price account provided is not owned by the lending market oracle program");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
+    // markets created before the emergency authority existed are upgraded, or
+    // the authority would be dropped when unpacked
+    lending_market.version = PROGRAM_VERSION;
+    lending_market.emergency_authority = emergency_authority;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
-    let pyth_product_data = pyth_product_info.try_borrow_data()?;
-    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-    if pyth_product.magic != pyth::MAGIC {
-        msg!("Pyth product account provided is not a valid Pyth account");
-        return Err(LendingError::InvalidOracleConfig.into());
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_risk_authority(
+    program_id: &Pubkey,
+    risk_authority: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
-    if pyth_product.ver != pyth::VERSION_2 {
-        msg!("Pyth product account provided has a different version than expected");
-        return Err(LendingError::InvalidOracleConfig.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
     }
-    if pyth_product.atype != pyth::AccountType::Product as u32 {
-        msg!("Pyth product account provided is not a valid Pyth product account");
-        return Err(LendingError::InvalidOracleConfig.into());
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    let pyth_price_pubkey_bytes: &[u8; 32] = pyth_price_info
-        .key
-        .as_ref()
-        .try_into()
-        .map_err(|_| LendingError::InvalidAccountInput)?;
-    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
-        msg!("Pyth product price account does not match the Pyth price provided");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
+    lending_market.risk_authority = risk_authority;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
-    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
-    if lending_market.quote_currency != quote_currency {
-        msg!("Lending market quote currency does not match the oracle quote currency");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
+    Ok(())
+}
 
-    let market_price = get_pyth_price(pyth_price_info, clock)?;
+#[inline(never)] // avoid stack frame limit
+fn process_set_market_freeze(
+    program_id: &Pubkey,
+    frozen: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
 
-    let authority_signer_seeds = &[
-        lending_market_info.key.as_ref(),
-        &[lending_market.bump_seed],
-    ];
-    let lending_market_authority_pubkey =
-        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
-    if &lending_market_authority_pubkey != lending_market_authority_info.key {
-        msg!(
-            "Derived lending market authority does not match the lending market authority provided"
-        );
-        return Err(LendingError::InvalidMarketAuthority.into());
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
-
-    let reserve_liquidity_mint = unpack_mint(&reserve_liquidity_mint_info.data.borrow())?;
-    if reserve_liquidity_mint_info.owner != token_program_id.key {
-        msg!("Reserve liquidity mint is not owned by the token program provided");
-        return Err(LendingError::InvalidTokenOwner.into());
+    // the emergency authority is a hot key, so it can freeze the market but only
+    // the owner can unfreeze it
+    let is_owner = &lending_market.owner == authority_info.key;
+    let is_emergency_authority = lending_market.emergency_authority != Pubkey::default()
+        && &lending_market.emergency_authority == authority_info.key;
+    if !(is_owner || frozen && is_emergency_authority) {
+        if frozen {
+            msg!("Only the lending market owner or emergency authority can freeze the market");
+        } else {
+            msg!("Only the lending market owner can unfreeze the market");
+        }
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !authority_info.is_signer {
+        msg!("Lending market authority provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    reserve.init(InitReserveParams {
-        current_slot: clock.slot,
-        lending_market: *lending_market_info.key,
-        liquidity: ReserveLiquidity::new(NewReserveLiquidityParams {
-            mint_pubkey: *reserve_liquidity_mint_info.key,
-            mint_decimals: reserve_liquidity_mint.decimals,
-            supply_pubkey: *reserve_liquidity_supply_info.key,
-            fee_receiver: *reserve_liquidity_fee_receiver_info.key,
-            oracle_pubkey: *pyth_price_info.key,
-            market_price,
-        }),
-        collateral: ReserveCollateral::new(NewReserveCollateralParams {
-            mint_pubkey: *reserve_collateral_mint_info.key,
-            supply_pubkey: *reserve_collateral_supply_info.key,
-        }),
-        config,
-    });
+    // markets created before the frozen flag existed are upgraded, or the flag
+    // would be dropped when unpacked
+    lending_market.version = PROGRAM_VERSION;
+    lending_market.frozen = frozen;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
-    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+    Ok(())
+}
 
-    spl_token_init_account(TokenInitializeAccountParams {
-        account: reserve_liquidity_supply_info.clone(),
-        mint: reserve_liquidity_mint_info.clone(),
+fn process_set_permissioned_borrowing(
+    program_id: &Pubkey,
+    permissioned_borrowing: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    // markets created before the flag existed are upgraded, or the flag would
+    // be dropped when unpacked
+    lending_market.version = PROGRAM_VERSION;
+    lending_market.permissioned_borrowing = permissioned_borrowing;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_min_borrow_value(
+    program_id: &Pubkey,
+    min_borrow_value: Decimal,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    // markets created before the min borrow value existed are upgraded, or it
+    // would be dropped when unpacked
+    lending_market.version = PROGRAM_VERSION;
+    lending_market.min_borrow_value = min_borrow_value;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_dust_liquidation_threshold(
+    program_id: &Pubkey,
+    dust_liquidation_threshold: Decimal,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    // markets created before the threshold existed are upgraded, or it would
+    // be dropped when unpacked
+    lending_market.version = PROGRAM_VERSION;
+    lending_market.dust_liquidation_threshold = dust_liquidation_threshold;
+    LendingMarket::pack_account(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_add_to_borrow_allowlist(
+    program_id: &Pubkey,
+    borrower: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let borrow_allowlist_entry_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let (borrow_allowlist_entry_pubkey, bump_seed) =
+        find_borrow_allowlist_entry_address(program_id, lending_market_info.key, &borrower);
+    if &borrow_allowlist_entry_pubkey != borrow_allowlist_entry_info.key {
+        msg!("Borrow allowlist entry provided is not derived from the lending market and borrower");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if borrow_allowlist_entry_info.owner == program_id {
+        msg!("Borrower is already on the borrow allowlist");
+        return Err(LendingError::AlreadyInitialized.into());
+    }
+
+    // funding, allocating and assigning the entry instead of creating it keeps
+    // the borrower listable if lamports were sent to the entry address
+    let required_lamports = rent
+        .minimum_balance(BorrowAllowlistEntry::LEN)
+        .saturating_sub(borrow_allowlist_entry_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                borrow_allowlist_entry_info.key,
+                required_lamports,
+            ),
+            &[
+                payer_info.clone(),
+                borrow_allowlist_entry_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    let entry_signer_seeds: &[&[u8]] = &[
+        BORROW_ALLOWLIST_SEED,
+        lending_market_info.key.as_ref(),
+        borrower.as_ref(),
+        &[bump_seed],
+    ];
+    invoke_signed(
+        &system_instruction::allocate(
+            borrow_allowlist_entry_info.key,
+            BorrowAllowlistEntry::LEN as u64,
+        ),
+        &[
+            borrow_allowlist_entry_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[entry_signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(borrow_allowlist_entry_info.key, program_id),
+        &[
+            borrow_allowlist_entry_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[entry_signer_seeds],
+    )?;
+
+    BorrowAllowlistEntry::pack(
+        BorrowAllowlistEntry::new(*lending_market_info.key, borrower, bump_seed),
+        &mut borrow_allowlist_entry_info.data.borrow_mut(),
+    )?;
+
+    Ok(())
+}
+
+fn process_remove_from_borrow_allowlist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let borrow_allowlist_entry_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let borrow_allowlist_entry =
+        BorrowAllowlistEntry::unpack(&borrow_allowlist_entry_info.data.borrow())?;
+    if borrow_allowlist_entry_info.owner != program_id {
+        msg!("Borrow allowlist entry provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &borrow_allowlist_entry.lending_market != lending_market_info.key {
+        msg!("Borrow allowlist entry lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if destination_info.key == borrow_allowlist_entry_info.key {
+        msg!("Borrow allowlist entry cannot be used as the destination provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let destination_lamports = destination_info
+        .lamports()
+        .checked_add(borrow_allowlist_entry_info.lamports())
+        .ok_or(LendingError::MathOverflow)?;
+    **destination_info.lamports.borrow_mut() = destination_lamports;
+    **borrow_allowlist_entry_info.lamports.borrow_mut() = 0;
+    borrow_allowlist_entry_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+fn process_init_reserve(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    config: ReserveConfig,
+    derive_fee_receiver: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Reserve must be initialized with liquidity");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    config.validate()?;
+
+    if liquidity_amount < config.min_deposit_amount {
+        msg!(
+            "Liquidity amount {} is below the reserve minimum deposit of {}",
+            liquidity_amount,
+            config.min_deposit_amount
+        );
+        return Err(LendingError::DepositTooSmall.into());
+    }
+
+    let account_info_iter = &mut accounts.iter().peekable();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let oracle_product_info = next_account_info(account_info_iter)?;
+    let oracle_price_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(rent_info)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    assert_rent_exempt(rent, reserve_info)?;
+    let mut reserve = assert_uninitialized::<Reserve>(reserve_info)?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    if reserve_liquidity_supply_info.key == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let (oracle_kind, market_price, market_price_slot) = get_reserve_oracle_price(
+        &lending_market,
+        oracle_product_info,
+        oracle_price_info,
+        clock,
+        &config,
+    )?;
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    // Liquidity only moves with the lending market token program, so a
+    // Token-2022 mint is rejected with a clearer error than the owner check
+    if reserve_liquidity_mint_info.owner == &spl_token_2022::id() {
+        msg!("Reserve liquidity cannot be a Token-2022 mint");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    let reserve_liquidity_mint = unpack_mint(&reserve_liquidity_mint_info.data.borrow())?;
+    if reserve_liquidity_mint_info.owner != token_program_id.key {
+        msg!("Reserve liquidity mint is not owned by the token program provided");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let reserve_liquidity_mint_decimals = reserve_liquidity_mint.decimals;
+
+    reserve.init(InitReserveParams {
+        current_slot: clock.slot,
+        lending_market: *lending_market_info.key,
+        liquidity: ReserveLiquidity::new(NewReserveLiquidityParams {
+            mint_pubkey: *reserve_liquidity_mint_info.key,
+            mint_decimals: reserve_liquidity_mint_decimals,
+            supply_pubkey: *reserve_liquidity_supply_info.key,
+            fee_receiver: *reserve_liquidity_fee_receiver_info.key,
+            oracle_kind,
+            oracle_pubkey: *oracle_price_info.key,
+            market_price,
+            market_price_slot,
+        }),
+        collateral: ReserveCollateral::new(NewReserveCollateralParams {
+            mint_pubkey: *reserve_collateral_mint_info.key,
+            supply_pubkey: *reserve_collateral_supply_info.key,
+        }),
+        config,
+    });
+
+    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+    if collateral_amount < MIN_INITIAL_COLLATERAL_AMOUNT {
+        msg!(
+            "Reserve must be initialized with enough liquidity to mint {} collateral",
+            MIN_INITIAL_COLLATERAL_AMOUNT
+        );
+        return Err(LendingError::DepositTooSmall.into());
+    }
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_init_account(TokenInitializeAccountParams {
+        account: reserve_liquidity_supply_info.clone(),
+        mint: reserve_liquidity_mint_info.clone(),
         owner: lending_market_authority_info.clone(),
         rent: rent_info.clone(),
         token_program: token_program_id.clone(),
     })?;
 
-    spl_token_init_account(TokenInitializeAccountParams {
-        account: reserve_liquidity_fee_receiver_info.clone(),
-        mint: reserve_liquidity_mint_info.clone(),
-        owner: lending_market_authority_info.clone(),
-        rent: rent_info.clone(),
-        token_program: token_program_id.clone(),
-    })?;
+    if derive_fee_receiver {
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+        if associated_token_program_info.key != &spl_associated_token_account::id() {
+            msg!("Associated token program does not match the associated token program provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        let reserve_liquidity_fee_receiver_pubkey = get_associated_token_address_with_program_id(
+            lending_market_authority_info.key,
+            reserve_liquidity_mint_info.key,
+            token_program_id.key,
+        );
+        if &reserve_liquidity_fee_receiver_pubkey != reserve_liquidity_fee_receiver_info.key {
+            msg!(
+                "Reserve liquidity fee receiver is not the lending market authority's associated token account"
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        invoke(
+            &create_associated_token_account_idempotent(
+                payer_info.key,
+                lending_market_authority_info.key,
+                reserve_liquidity_mint_info.key,
+                token_program_id.key,
+            ),
+            &[
+                payer_info.clone(),
+                reserve_liquidity_fee_receiver_info.clone(),
+                lending_market_authority_info.clone(),
+                reserve_liquidity_mint_info.clone(),
+                system_program_info.clone(),
+                token_program_id.clone(),
+                associated_token_program_info.clone(),
+            ],
+        )?;
+    } else {
+        spl_token_init_account(TokenInitializeAccountParams {
+            account: reserve_liquidity_fee_receiver_info.clone(),
+            mint: reserve_liquidity_mint_info.clone(),
+            owner: lending_market_authority_info.clone(),
+            rent: rent_info.clone(),
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    spl_token_init_mint(TokenInitializeMintParams {
+        mint: reserve_collateral_mint_info.clone(),
+        authority: lending_market_authority_info.key,
+        rent: rent_info.clone(),
+        decimals: reserve_liquidity_mint_decimals,
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_init_account(TokenInitializeAccountParams {
+        account: reserve_collateral_supply_info.clone(),
+        mint: reserve_collateral_mint_info.clone(),
+        owner: lending_market_authority_info.clone(),
+        rent: rent_info.clone(),
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_init_account(TokenInitializeAccountParams {
+        account: destination_collateral_info.clone(),
+        mint: reserve_collateral_mint_info.clone(),
+        owner: user_transfer_authority_info.clone(),
+        rent: rent_info.clone(),
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: reserve_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_mint_to(TokenMintToParams {
+        mint: reserve_collateral_mint_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: collateral_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter().peekable();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_oracle_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    refresh_reserve_account(
+        program_id,
+        reserve_info,
+        reserve_liquidity_oracle_info,
+        clock,
+    )
+}
+
+fn process_refresh_reserves(
+    program_id: &Pubkey,
+    reserves: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    if reserves == 0 {
+        msg!("At least one reserve must be provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if account_info_iter.len() != 2 * reserves as usize {
+        msg!(
+            "Expected a reserve and oracle account for each of the {} reserves",
+            reserves
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    for _ in 0..reserves {
+        let reserve_info = next_account_info(account_info_iter)?;
+        let reserve_liquidity_oracle_info = next_account_info(account_info_iter)?;
+        refresh_reserve_account(
+            program_id,
+            reserve_info,
+            reserve_liquidity_oracle_info,
+            clock,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Accrues interest and updates the market price of a reserve, as of the
+/// clock slot
+fn refresh_reserve_account(
+    program_id: &Pubkey,
+    reserve_info: &AccountInfo,
+    reserve_liquidity_oracle_info: &AccountInfo,
+    clock: &Clock,
+) -> ProgramResult {
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.liquidity.oracle_pubkey != reserve_liquidity_oracle_info.key {
+        msg!("Reserve liquidity oracle does not match the reserve liquidity oracle provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if reserve.price_override {
+        reserve.liquidity.market_price_slot = clock.slot;
+    } else {
+        (
+            reserve.liquidity.market_price,
+            reserve.liquidity.market_price_slot,
+        ) = match reserve.liquidity.oracle_kind {
+            OracleKind::Pyth => {
+                get_pyth_price(reserve_liquidity_oracle_info, clock, &reserve.config)?
+            }
+            OracleKind::Switchboard => {
+                get_switchboard_price(reserve_liquidity_oracle_info, clock, &reserve.config)?
+            }
+        };
+    }
+
+    reserve.accrue_rewards(clock.slot)?;
+    reserve.accrue_interest(clock.slot)?;
+    reserve.record_rate_sample(clock.slot)?;
+    reserve.last_update.update_slot(clock.slot);
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_deposit_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    min_collateral_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey == destination_collateral_info.key {
+        msg!("Reserve collateral supply cannot be used as the destination collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if reserve.is_paused(RESERVE_PAUSE_DEPOSITS) {
+        msg!("Reserve deposits are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !reserve.is_active() {
+        msg!("Reserve is deprecated and no longer accepts deposits");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    if liquidity_amount < reserve.config.min_deposit_amount {
+        msg!(
+            "Liquidity amount {} is below the reserve minimum deposit of {}",
+            liquidity_amount,
+            reserve.config.min_deposit_amount
+        );
+        return Err(LendingError::DepositTooSmall.into());
+    }
+
+    let reserve_liquidity_amount = reserve
+        .liquidity
+        .total_supply()?
+        .try_add(Decimal::from(liquidity_amount))?;
+    if reserve_liquidity_amount > Decimal::from(reserve.config.deposit_limit) {
+        msg!("Reserve liquidity cannot exceed the reserve deposit limit");
+        return Err(LendingError::DepositLimitExceeded.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority {} does not match the lending market authority provided {}",
+            &lending_market_authority_pubkey.to_string(),
+            &lending_market_authority_info.key.to_string(),
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    // once every collateral token has been redeemed the next deposit sets the
+    // exchange rate again, so it has to seed the reserve like init does
+    let is_first_deposit = reserve.collateral.mint_total_supply == 0;
+    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+    if is_first_deposit && collateral_amount < MIN_INITIAL_COLLATERAL_AMOUNT {
+        msg!(
+            "First deposit into the reserve must mint at least {} collateral",
+            MIN_INITIAL_COLLATERAL_AMOUNT
+        );
+        return Err(LendingError::DepositTooSmall.into());
+    }
+    if collateral_amount < min_collateral_amount {
+        msg!(
+            "Deposit would mint {} collateral, less than the minimum of {}",
+            collateral_amount,
+            min_collateral_amount
+        );
+        return Err(LendingError::ExchangeRateSlippageExceeded.into());
+    }
+    reserve.last_update.mark_stale();
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: reserve_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_mint_to(TokenMintToParams {
+        mint: reserve_collateral_mint_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: collateral_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::Deposit(DepositEvent {
+        reserve: *reserve_info.key,
+        user: *user_transfer_authority_info.key,
+        liquidity_amount,
+        collateral_amount,
+    })
+    .emit();
+
+    Ok(())
+}
+
+fn process_redeem_reserve_collateral(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    min_liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey == source_collateral_info.key {
+        msg!("Reserve collateral supply cannot be used as the source collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if reserve.is_paused(RESERVE_PAUSE_WITHDRAWALS) {
+        msg!("Reserve withdrawals are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if reserve.status == ReserveStatus::Obsolete {
+        msg!("Reserve is obsolete and no longer allows redemptions");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    if liquidity_amount < min_liquidity_amount {
+        msg!(
+            "Redemption would return {} liquidity, less than the minimum of {}",
+            liquidity_amount,
+            min_liquidity_amount
+        );
+        return Err(LendingError::ExchangeRateSlippageExceeded.into());
+    }
+    reserve.record_outflow(liquidity_amount, clock.slot)?;
+    reserve.last_update.mark_stale();
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_burn(TokenBurnParams {
+        mint: reserve_collateral_mint_info.clone(),
+        source: source_collateral_info.clone(),
+        amount: collateral_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: reserve_liquidity_supply_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_request_redeem(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let redeem_request_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey != reserve_collateral_supply_info.key {
+        msg!("Reserve collateral supply does not match the reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey == source_collateral_info.key {
+        msg!("Reserve collateral supply cannot be used as the source collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if reserve.is_paused(RESERVE_PAUSE_WITHDRAWALS) {
+        msg!("Reserve withdrawals are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if reserve.status == ReserveStatus::Obsolete {
+        msg!("Reserve is obsolete and no longer allows redemptions");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    // a queued request can only be fulfilled to a liquidity account that
+    // still exists, so the destination is checked up front
+    if destination_liquidity_info.owner != token_program_id.key {
+        msg!("Destination liquidity provided is not owned by the token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let destination_liquidity = unpack_token_account(&destination_liquidity_info.data.borrow())?;
+    if destination_liquidity.mint != reserve.liquidity.mint_pubkey {
+        msg!("Destination liquidity mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidTokenMint.into());
+    }
+    // the request belongs to the owner of the collateral, who may not be the
+    // payer or the transfer authority
+    if source_collateral_info.owner != token_program_id.key {
+        msg!("Source collateral provided is not owned by the token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let source_collateral = unpack_token_account(&source_collateral_info.data.borrow())?;
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    if reserve.can_redeem_now(collateral_amount)? {
+        let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+        reserve.record_outflow(liquidity_amount, clock.slot)?;
+        reserve.last_update.mark_stale();
+        Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+        spl_token_burn(TokenBurnParams {
+            mint: reserve_collateral_mint_info.clone(),
+            source: source_collateral_info.clone(),
+            amount: collateral_amount,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
+
+        spl_token_transfer(TokenTransferParams {
+            source: reserve_liquidity_supply_info.clone(),
+            destination: destination_liquidity_info.clone(),
+            amount: liquidity_amount,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+
+        return Ok(());
+    }
+
+    let ticket = reserve.queue_redemption(collateral_amount)?;
+    let (redeem_request_pubkey, bump_seed) =
+        find_redeem_request_address(program_id, reserve_info.key, ticket);
+    if &redeem_request_pubkey != redeem_request_info.key {
+        msg!("Redeem request provided is not derived from the reserve and its next ticket");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let required_lamports = rent
+        .minimum_balance(RedeemRequest::LEN)
+        .saturating_sub(redeem_request_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                redeem_request_info.key,
+                required_lamports,
+            ),
+            &[
+                payer_info.clone(),
+                redeem_request_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    let ticket_bytes = ticket.to_le_bytes();
+    let redeem_request_signer_seeds: &[&[u8]] = &[
+        REDEEM_REQUEST_SEED,
+        reserve_info.key.as_ref(),
+        &ticket_bytes,
+        &[bump_seed],
+    ];
+    invoke_signed(
+        &system_instruction::allocate(redeem_request_info.key, RedeemRequest::LEN as u64),
+        &[redeem_request_info.clone(), system_program_info.clone()],
+        &[redeem_request_signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(redeem_request_info.key, program_id),
+        &[redeem_request_info.clone(), system_program_info.clone()],
+        &[redeem_request_signer_seeds],
+    )?;
+
+    let redeem_request = RedeemRequest::new(InitRedeemRequestParams {
+        bump_seed,
+        reserve: *reserve_info.key,
+        ticket,
+        owner: source_collateral.owner,
+        destination_liquidity: *destination_liquidity_info.key,
+        collateral_amount,
+    });
+    RedeemRequest::pack(redeem_request, &mut redeem_request_info.data.borrow_mut())?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_collateral_info.clone(),
+        destination: reserve_collateral_supply_info.clone(),
+        amount: collateral_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    msg!("Redemption queued with ticket {}", ticket);
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_fulfill_redeem(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let redeem_request_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let redeem_request_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey != reserve_collateral_supply_info.key {
+        msg!("Reserve collateral supply does not match the reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    // a request cancelled behind the head is closed by then, the head moves
+    // past its ticket
+    if redeem_request_info.owner != program_id || redeem_request_info.lamports() == 0 {
+        let (head_request_pubkey, _bump_seed) = find_redeem_request_address(
+            program_id,
+            reserve_info.key,
+            reserve.redeem_queue.head_ticket,
+        );
+        if &head_request_pubkey != redeem_request_info.key {
+            msg!(
+                "Redeem request provided is not derived from the reserve and the head of its queue"
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        msg!(
+            "Redeem request ticket {} was cancelled, skipping it",
+            reserve.redeem_queue.head_ticket
+        );
+        reserve.skip_redemption(0)?;
+        Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    let mut redeem_request = RedeemRequest::unpack(&redeem_request_info.data.borrow())?;
+    if &redeem_request.reserve != reserve_info.key {
+        msg!("Redeem request reserve does not match the reserve provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if redeem_request.ticket != reserve.redeem_queue.head_ticket {
+        msg!(
+            "Redeem request ticket {} is not at the head of the queue, ticket {} is",
+            redeem_request.ticket,
+            reserve.redeem_queue.head_ticket
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &redeem_request.destination_liquidity != destination_liquidity_info.key {
+        msg!("Redeem request destination does not match the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &redeem_request.owner != redeem_request_owner_info.key {
+        msg!("Redeem request owner does not match the redeem request owner provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    // a destination that was closed, frozen or reassigned since the request
+    // was queued would block every request behind it, so it's skipped and its
+    // owner cancels or reroutes it instead
+    let destination_payable = destination_liquidity_info.owner == token_program_id.key
+        && match unpack_token_account(&destination_liquidity_info.data.borrow()) {
+            Ok(destination_liquidity) => {
+                destination_liquidity.mint == reserve.liquidity.mint_pubkey
+                    && !destination_liquidity.is_frozen()
+            }
+            Err(_) => false,
+        };
+    if !destination_payable {
+        msg!(
+            "Redeem request ticket {} destination can't be paid, skipping it",
+            redeem_request.ticket
+        );
+        reserve.skip_redemption(redeem_request.collateral_amount)?;
+        Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let (collateral_amount, liquidity_amount) =
+        reserve.fulfill_redemption(redeem_request.collateral_amount)?;
+    reserve.record_outflow(liquidity_amount, clock.slot)?;
+    reserve.last_update.mark_stale();
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_burn(TokenBurnParams {
+        mint: reserve_collateral_mint_info.clone(),
+        source: reserve_collateral_supply_info.clone(),
+        amount: collateral_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: reserve_liquidity_supply_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    redeem_request.collateral_amount = redeem_request
+        .collateral_amount
+        .checked_sub(collateral_amount)
+        .ok_or(LendingError::MathOverflow)?;
+    if redeem_request.collateral_amount > 0 {
+        RedeemRequest::pack(redeem_request, &mut redeem_request_info.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    let owner_lamports = redeem_request_owner_info
+        .lamports()
+        .checked_add(redeem_request_info.lamports())
+        .ok_or(LendingError::MathOverflow)?;
+    **redeem_request_owner_info.lamports.borrow_mut() = owner_lamports;
+    **redeem_request_info.lamports.borrow_mut() = 0;
+    redeem_request_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_cancel_redeem(
+    program_id: &Pubkey,
+    reroute: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let redeem_request_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let redeem_request_owner_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.collateral.supply_pubkey != reserve_collateral_supply_info.key {
+        msg!("Reserve collateral supply does not match the reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let mut redeem_request = RedeemRequest::unpack(&redeem_request_info.data.borrow())?;
+    if redeem_request_info.owner != program_id {
+        msg!("Redeem request provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &redeem_request.reserve != reserve_info.key {
+        msg!("Redeem request reserve does not match the reserve provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &redeem_request.owner != redeem_request_owner_info.key {
+        msg!("Redeem request owner does not match the redeem request owner provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if !redeem_request_owner_info.is_signer {
+        msg!("Redeem request owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    if reroute {
+        if redeem_request.ticket < reserve.redeem_queue.head_ticket {
+            msg!("Redeem request was skipped and is no longer queued, cancel it instead");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        if &reserve.liquidity.supply_pubkey == destination_info.key {
+            msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        if destination_info.owner != token_program_id.key {
+            msg!("Destination liquidity provided is not owned by the token program");
+            return Err(LendingError::InvalidTokenOwner.into());
+        }
+        let destination_liquidity = unpack_token_account(&destination_info.data.borrow())?;
+        if destination_liquidity.mint != reserve.liquidity.mint_pubkey {
+            msg!("Destination liquidity mint does not match the reserve liquidity mint");
+            return Err(LendingError::InvalidTokenMint.into());
+        }
+
+        redeem_request.destination_liquidity = *destination_info.key;
+        RedeemRequest::pack(redeem_request, &mut redeem_request_info.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    if &reserve.collateral.supply_pubkey == destination_info.key {
+        msg!("Reserve collateral supply cannot be used as the destination collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    reserve.cancel_redemption(redeem_request.ticket, redeem_request.collateral_amount)?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: reserve_collateral_supply_info.clone(),
+        destination: destination_info.clone(),
+        amount: redeem_request.collateral_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    let owner_lamports = redeem_request_owner_info
+        .lamports()
+        .checked_add(redeem_request_info.lamports())
+        .ok_or(LendingError::MathOverflow)?;
+    **redeem_request_owner_info.lamports.borrow_mut() = owner_lamports;
+    **redeem_request_info.lamports.borrow_mut() = 0;
+    redeem_request_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_init_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    assert_rent_exempt(rent, obligation_info)?;
+    let mut obligation = assert_uninitialized::<Obligation>(obligation_info)?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    obligation.init(InitObligationParams {
+        current_slot: clock.slot,
+        lending_market: *lending_market_info.key,
+        owner: *obligation_owner_info.key,
+        deposits: vec![],
+        borrows: vec![],
+    });
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_init_derived_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let (obligation_pubkey, bump_seed) = find_obligation_address(
+        program_id,
+        lending_market_info.key,
+        obligation_owner_info.key,
+    );
+    if &obligation_pubkey != obligation_info.key {
+        msg!("Obligation provided is not derived from the lending market and obligation owner");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if obligation_info.owner == program_id {
+        msg!("Obligation is already initialized for the obligation owner");
+        return Err(LendingError::AlreadyInitialized.into());
+    }
+
+    // funding, allocating and assigning the obligation instead of creating it
+    // keeps the address usable if lamports were sent to it
+    let required_lamports = rent
+        .minimum_balance(Obligation::LEN)
+        .saturating_sub(obligation_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, obligation_info.key, required_lamports),
+            &[
+                payer_info.clone(),
+                obligation_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    let obligation_signer_seeds: &[&[u8]] = &[
+        OBLIGATION_SEED,
+        lending_market_info.key.as_ref(),
+        obligation_owner_info.key.as_ref(),
+        &[bump_seed],
+    ];
+    invoke_signed(
+        &system_instruction::allocate(obligation_info.key, Obligation::LEN as u64),
+        &[obligation_info.clone(), system_program_info.clone()],
+        &[obligation_signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(obligation_info.key, program_id),
+        &[obligation_info.clone(), system_program_info.clone()],
+        &[obligation_signer_seeds],
+    )?;
+
+    let obligation = Obligation::new(InitObligationParams {
+        current_slot: clock.slot,
+        lending_market: *lending_market_info.key,
+        owner: *obligation_owner_info.key,
+        deposits: vec![],
+        borrows: vec![],
+    });
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_obligation_owner(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let new_obligation_owner_info = next_account_info(account_info_iter)?;
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if !new_obligation_owner_info.is_signer {
+        msg!("New obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    obligation.owner = *new_obligation_owner_info.key;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_resize_obligation(
+    program_id: &Pubkey,
+    additional_reserves: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if additional_reserves == 0 {
+        msg!("Additional reserves provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let max_reserves =
+        Obligation::max_reserves(obligation_info.data_len()) + additional_reserves as usize;
+    if max_reserves > MAX_RESIZED_OBLIGATION_RESERVES {
+        msg!(
+            "Obligation cannot be resized to more than {} deposits and borrows combined",
+            MAX_RESIZED_OBLIGATION_RESERVES
+        );
+        return Err(LendingError::ObligationReserveLimit.into());
+    }
+
+    // the payer funds the rent for the new slots, existing deposits and borrows
+    // are left in place
+    let new_len = Obligation::account_len(max_reserves);
+    let required_lamports = rent.minimum_balance(new_len);
+    if obligation_info.lamports() < required_lamports {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                obligation_info.key,
+                required_lamports - obligation_info.lamports(),
+            ),
+            &[
+                payer_info.clone(),
+                obligation_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    obligation_info.realloc(new_len, true)?;
+
+    Ok(())
+}
+
+fn process_migrate_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    let mut obligation = Obligation::unpack_legacy_account(&obligation_info.data.borrow())?;
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    obligation.migrate();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if account_info.owner != program_id {
+        msg!("Account provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    // lending markets and reserves are told apart by their size
+    let data_len = account_info.data_len();
+    if data_len == LENDING_MARKET_V1_LEN || data_len == LendingMarket::LEN {
+        // market metadata is sized to its strings, so it can share the V1
+        // lending market size, but it lives at the metadata address
+        if let Ok(market_metadata) = MarketMetadata::unpack_account(&account_info.data.borrow()) {
+            if &get_market_metadata_address(program_id, &market_metadata.lending_market)
+                == account_info.key
+            {
+                msg!("Account provided is market metadata, not a lending market");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+        }
+
+        let mut lending_market = LendingMarket::unpack_legacy_account(&account_info.data.borrow())?;
+        lending_market.migrate();
+        realloc_with_rent(
+            account_info,
+            LendingMarket::LEN,
+            payer_info,
+            rent,
+            system_program_info,
+        )?;
+        LendingMarket::pack_account(lending_market, &mut account_info.data.borrow_mut())?;
+    } else if data_len == RESERVE_V1_LEN || data_len == Reserve::LEN {
+        let mut reserve = Reserve::unpack_legacy_account(&account_info.data.borrow())?;
+        reserve.migrate();
+        realloc_with_rent(
+            account_info,
+            Reserve::LEN,
+            payer_info,
+            rent,
+            system_program_info,
+        )?;
+        Reserve::pack_account(reserve, &mut account_info.data.borrow_mut())?;
+    } else {
+        msg!("Account provided is not a lending market or reserve");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    Ok(())
+}
+
+fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter().peekable();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let mut deposited_value = Decimal::zero();
+    let mut borrowed_value = Decimal::zero();
+    let mut allowed_borrow_value = Decimal::zero();
+    let mut unhealthy_borrow_value = Decimal::zero();
+    let has_combined_collateral = obligation.deposits.len() > 1;
+
+    for (index, collateral) in obligation.deposits.iter_mut().enumerate() {
+        let deposit_reserve_info = next_account_info(account_info_iter)?;
+        if deposit_reserve_info.owner != program_id {
+            msg!(
+                "Deposit reserve provided for collateral {} is not owned by the lending program",
+                index
+            );
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if collateral.deposit_reserve != *deposit_reserve_info.key {
+            msg!(
+                "Deposit reserve of collateral {} does not match the deposit reserve provided",
+                index
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let deposit_reserve = Reserve::unpack_account(&deposit_reserve_info.data.borrow())?;
+        if deposit_reserve.last_update.is_stale(clock.slot)? {
+            msg!(
+                "Deposit reserve {} provided for collateral {} is stale and must be refreshed in the current slot",
+                deposit_reserve_info.key,
+                index
+            );
+            return Err(LendingError::ReserveStale.into());
+        }
+        if deposit_reserve.is_price_stale(clock.slot)? {
+            msg!(
+                "Deposit reserve {} provided for collateral {} has a market price older than its max oracle age",
+                deposit_reserve_info.key,
+                index
+            );
+            return Err(LendingError::ReservePriceStale.into());
+        }
+
+        // @TODO: add lookup table https://git.io/JOCYq
+        let decimals = 10u64
+            .checked_pow(deposit_reserve.liquidity.mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let market_value = deposit_reserve
+            .collateral_exchange_rate()?
+            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
+            .try_mul(deposit_reserve.liquidity.market_price)?
+            .try_div(decimals)?;
+        collateral.market_value = market_value;
+
+        // isolated collateral combined with other collateral, before its
+        // reserve was isolated, still counts toward the liquidation threshold
+        // but no longer backs new borrows
+        let borrow_power_rate = if deposit_reserve.config.isolated && has_combined_collateral {
+            Rate::zero()
+        } else {
+            deposit_reserve.config.borrow_power_rate()?
+        };
+        let liquidation_threshold_rate = deposit_reserve.liquidation_threshold_rate()?;
+
+        deposited_value = deposited_value.try_add(market_value)?;
+        allowed_borrow_value =
+            allowed_borrow_value.try_add(market_value.try_mul(borrow_power_rate)?)?;
+        unhealthy_borrow_value =
+            unhealthy_borrow_value.try_add(market_value.try_mul(liquidation_threshold_rate)?)?;
+    }
+
+    for (index, liquidity) in obligation.borrows.iter_mut().enumerate() {
+        let borrow_reserve_info = next_account_info(account_info_iter)?;
+        if borrow_reserve_info.owner != program_id {
+            msg!(
+                "Borrow reserve provided for liquidity {} is not owned by the lending program",
+                index
+            );
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if liquidity.borrow_reserve != *borrow_reserve_info.key {
+            msg!(
+                "Borrow reserve of liquidity {} does not match the borrow reserve provided",
+                index
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let borrow_reserve = Reserve::unpack_account(&borrow_reserve_info.data.borrow())?;
+        if borrow_reserve.last_update.is_stale(clock.slot)? {
+            msg!(
+                "Borrow reserve {} provided for liquidity {} is stale and must be refreshed in the current slot",
+                borrow_reserve_info.key,
+                index
+            );
+            return Err(LendingError::ReserveStale.into());
+        }
+        if borrow_reserve.is_price_stale(clock.slot)? {
+            msg!(
+                "Borrow reserve {} provided for liquidity {} has a market price older than its max oracle age",
+                borrow_reserve_info.key,
+                index
+            );
+            return Err(LendingError::ReservePriceStale.into());
+        }
+
+        liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+
+        // @TODO: add lookup table https://git.io/JOCYq
+        let decimals = 10u64
+            .checked_pow(borrow_reserve.liquidity.mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let market_value = liquidity
+            .borrowed_amount_wads
+            .try_mul(borrow_reserve.liquidity.market_price)?
+            .try_div(decimals)?;
+        liquidity.market_value = market_value;
+
+        let borrow_weight = borrow_reserve.config.borrow_weight();
+
+        borrowed_value = borrowed_value.try_add(market_value.try_mul(borrow_weight)?)?;
+    }
+
+    if account_info_iter.peek().is_some() {
+        msg!("Too many obligation deposit or borrow reserves provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    obligation.deposited_value = deposited_value;
+    obligation.borrowed_value = borrowed_value;
+    obligation.allowed_borrow_value = allowed_borrow_value;
+    obligation.unhealthy_borrow_value = unhealthy_borrow_value;
+
+    obligation.last_update.update_slot(clock.slot);
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_deposit_obligation_collateral(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    accounts: &[AccountInfo],
+    on_behalf: bool,
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    // anyone may add collateral to an obligation, only withdrawals need the
+    // owner
+    let obligation_owner_info = if on_behalf {
+        None
+    } else {
+        Some(next_account_info(account_info_iter)?)
+    };
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
+
+    let deposit_reserve = Reserve::unpack_account(&deposit_reserve_info.data.borrow())?;
+    if deposit_reserve_info.owner != program_id {
+        msg!("Deposit reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &deposit_reserve.lending_market != lending_market_info.key {
+        msg!("Deposit reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &deposit_reserve.collateral.supply_pubkey == source_collateral_info.key {
+        msg!("Deposit reserve collateral supply cannot be used as the source collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &deposit_reserve.collateral.supply_pubkey != destination_collateral_info.key {
+        msg!(
+            "Deposit reserve collateral supply must be used as the destination collateral provided"
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if deposit_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Deposit reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if deposit_reserve.is_paused(RESERVE_PAUSE_DEPOSITS) {
+        msg!("Deposit reserve deposits are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !deposit_reserve.is_active() {
+        msg!("Deposit reserve is deprecated and no longer accepts deposits");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+    if deposit_reserve.config.loan_to_value_ratio == 0 {
+        msg!("Deposit reserve has collateral disabled for borrowing");
+        return Err(LendingError::ReserveCollateralDisabled.into());
+    }
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if let Some(obligation_owner_info) = obligation_owner_info {
+        if &obligation.owner != obligation_owner_info.key {
+            msg!("Obligation owner does not match the obligation owner provided");
+            return Err(LendingError::InvalidObligationOwner.into());
+        }
+        if !obligation_owner_info.is_signer {
+            msg!("Obligation owner provided must be a signer");
+            return Err(LendingError::InvalidSigner.into());
+        }
+    }
+
+    let isolated_deposit_reserves =
+        next_isolated_deposit_reserves(program_id, &obligation, account_info_iter)?;
+    if !obligation.can_combine_collateral(
+        *deposit_reserve_info.key,
+        deposit_reserve.config.isolated,
+        &isolated_deposit_reserves,
+    ) {
+        msg!("Isolated collateral cannot be combined with collateral from other reserves");
+        return Err(LendingError::IsolatedCollateral.into());
+    }
+
+    obligation
+        .find_or_add_collateral_to_deposits(*deposit_reserve_info.key)?
+        .deposit(collateral_amount)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_collateral_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: collateral_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_withdraw_obligation_collateral(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
+
+    let mut withdraw_reserve = Reserve::unpack_account(&withdraw_reserve_info.data.borrow())?;
+    if withdraw_reserve_info.owner != program_id {
+        msg!("Withdraw reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &withdraw_reserve.lending_market != lending_market_info.key {
+        msg!("Withdraw reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.collateral.supply_pubkey != source_collateral_info.key {
+        msg!("Withdraw reserve collateral supply must be used as the source collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.collateral.supply_pubkey == destination_collateral_info.key {
+        msg!("Withdraw reserve collateral supply cannot be used as the destination collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if withdraw_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Withdraw reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if withdraw_reserve.is_price_stale(clock.slot)? {
+        msg!("Withdraw reserve market price is older than its max oracle age");
+        return Err(LendingError::ReservePriceStale.into());
+    }
+    if withdraw_reserve.is_paused(RESERVE_PAUSE_WITHDRAWALS) {
+        msg!("Withdraw reserve withdrawals are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+
+    let (collateral, collateral_index) =
+        obligation.find_collateral_in_deposits(*withdraw_reserve_info.key)?;
+    if collateral.deposited_amount == 0 {
+        msg!("Collateral deposited amount is zero");
+        return Err(LendingError::ObligationCollateralEmpty.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    // deposit-only collateral never backed the borrows, so it can be
+    // withdrawn as if there were none
+    let backs_borrows =
+        !obligation.borrows.is_empty() && !withdraw_reserve.config.is_deposit_only();
+    let withdraw_amount = if !backs_borrows {
+        if collateral_amount == u64::MAX {
+            collateral.deposited_amount
+        } else {
+            collateral.deposited_amount.min(collateral_amount)
+        }
+    } else if obligation.deposited_value == Decimal::zero() {
+        msg!("Obligation deposited value is zero");
+        return Err(LendingError::ObligationDepositsZero.into());
+    } else {
+        let max_withdraw_value =
+            obligation.max_withdraw_value(withdraw_reserve.config.borrow_power_rate()?)?;
+        if max_withdraw_value == Decimal::zero() {
+            msg!("Maximum withdraw value is zero");
+            return Err(LendingError::WithdrawTooLarge.into());
+        }
+
+        let withdraw_amount = if collateral_amount == u64::MAX {
+            let withdraw_value = max_withdraw_value.min(collateral.market_value);
+            let withdraw_pct = withdraw_value.try_div(collateral.market_value)?;
+            withdraw_pct
+                .try_mul(collateral.deposited_amount)?
+                .try_floor_u64()?
+                .min(collateral.deposited_amount)
+        } else {
+            let withdraw_amount = collateral_amount.min(collateral.deposited_amount);
+            let withdraw_pct =
+                Decimal::from(withdraw_amount).try_div(collateral.deposited_amount)?;
+            let withdraw_value = collateral.market_value.try_mul(withdraw_pct)?;
+            if withdraw_value > max_withdraw_value {
+                msg!("Withdraw value cannot exceed maximum withdraw value");
+                return Err(LendingError::WithdrawTooLarge.into());
+            }
+            withdraw_amount
+        };
+        if withdraw_amount == 0 {
+            msg!("Withdraw amount is too small to transfer collateral");
+            return Err(LendingError::WithdrawTooSmall.into());
+        }
+        withdraw_amount
+    };
+
+    let withdraw_liquidity_amount = withdraw_reserve
+        .collateral_exchange_rate()?
+        .collateral_to_liquidity(withdraw_amount)?;
+    withdraw_reserve.record_outflow(withdraw_liquidity_amount, clock.slot)?;
+    Reserve::pack_account(
+        withdraw_reserve,
+        &mut withdraw_reserve_info.data.borrow_mut(),
+    )?;
+
+    obligation.withdraw(withdraw_amount, collateral_index)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_collateral_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: withdraw_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_borrow_obligation_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    slippage_limit: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
+
+    let mut borrow_reserve = Reserve::unpack_account(&borrow_reserve_info.data.borrow())?;
+    if borrow_reserve_info.owner != program_id {
+        msg!("Borrow reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &borrow_reserve.lending_market != lending_market_info.key {
+        msg!("Borrow reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.liquidity.supply_pubkey != source_liquidity_info.key {
+        msg!("Borrow reserve liquidity supply must be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!(
+            "Borrow reserve liquidity supply cannot be used as the destination liquidity provided"
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.liquidity.fee_receiver != borrow_reserve_liquidity_fee_receiver_info.key {
+        msg!("Borrow reserve liquidity fee receiver does not match the borrow reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if borrow_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Borrow reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if borrow_reserve.is_price_stale(clock.slot)? {
+        msg!("Borrow reserve market price is older than its max oracle age");
+        return Err(LendingError::ReservePriceStale.into());
+    }
+    if borrow_reserve.is_paused(RESERVE_PAUSE_BORROWS) {
+        msg!("Borrow reserve borrows are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !borrow_reserve.is_active() {
+        msg!("Borrow reserve is deprecated and no longer accepts borrows");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    let isolated_deposit_reserves =
+        next_isolated_deposit_reserves(program_id, &obligation, account_info_iter)?;
+    if !isolated_deposit_reserves.is_empty() && !borrow_reserve.config.borrowable_in_isolation {
+        msg!("Borrow reserve cannot be borrowed from against isolated collateral");
+        return Err(LendingError::IsolatedCollateral.into());
+    }
+    // the allowlist entry comes before the optional host fee receiver
+    if lending_market.permissioned_borrowing {
+        assert_borrower_allowed(
+            program_id,
+            lending_market_info.key,
+            &obligation.owner,
+            next_account_info(account_info_iter).ok(),
+        )?;
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+    if obligation.deposits.is_empty() {
+        msg!("Obligation has no deposits to borrow against");
+        return Err(LendingError::ObligationDepositsEmpty.into());
+    }
+    if obligation.deposited_value == Decimal::zero() {
+        msg!("Obligation deposits have zero value");
+        return Err(LendingError::ObligationDepositsZero.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let remaining_borrow_value = obligation.remaining_borrow_value()?;
+    if remaining_borrow_value == Decimal::zero() {
+        msg!("Remaining borrow value is zero");
+        return Err(LendingError::BorrowTooLarge.into());
+    }
+
+    let CalculateBorrowResult {
+        borrow_amount,
+        receive_amount,
+        borrow_fee,
+        host_fee,
+    } = borrow_reserve.calculate_borrow(liquidity_amount, remaining_borrow_value)?;
+
+    if receive_amount == 0 {
+        msg!("Borrow amount is too small to receive liquidity after fees");
+        return Err(LendingError::BorrowTooSmall.into());
+    }
+    assert_min_borrow_value(&lending_market, &obligation, &borrow_reserve, borrow_amount)?;
+
+    if liquidity_amount == u64::MAX && receive_amount < slippage_limit {
+        msg!("Received liquidity would be smaller than the desired slippage limit");
+        return Err(LendingError::ExceededSlippage.into());
+    }
+
+    let reserve_borrowed_amount = borrow_reserve
+        .liquidity
+        .borrowed_amount_wads
+        .try_add(borrow_amount)?;
+    if reserve_borrowed_amount > Decimal::from(borrow_reserve.config.borrow_limit) {
+        msg!("Reserve borrowed amount cannot exceed the reserve borrow limit");
+        return Err(LendingError::BorrowLimitExceeded.into());
+    }
+
+    let outflow_amount = receive_amount
+        .checked_add(borrow_fee)
+        .ok_or(LendingError::MathOverflow)?;
+    borrow_reserve.record_outflow(outflow_amount, clock.slot)?;
+    borrow_reserve.liquidity.borrow(borrow_amount)?;
+    borrow_reserve.last_update.mark_stale();
+    let cumulative_borrow_rate_wads = borrow_reserve.liquidity.cumulative_borrow_rate_wads;
+    Reserve::pack_account(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
+
+    obligation
+        .find_or_add_liquidity_to_borrows(*borrow_reserve_info.key, cumulative_borrow_rate_wads)?
+        .borrow(borrow_amount)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    let mut owner_fee = borrow_fee;
+    if let Ok(host_fee_receiver_info) = next_account_info(account_info_iter) {
+        if host_fee > 0 {
+            owner_fee = owner_fee
+                .checked_sub(host_fee)
+                .ok_or(LendingError::MathOverflow)?;
+
+            spl_token_transfer(TokenTransferParams {
+                source: source_liquidity_info.clone(),
+                destination: host_fee_receiver_info.clone(),
+                amount: host_fee,
+                authority: lending_market_authority_info.clone(),
+                authority_signer_seeds,
+                token_program: token_program_id.clone(),
+            })?;
+        }
+    }
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: borrow_reserve_liquidity_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: receive_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::Borrow(BorrowEvent {
+        reserve: *borrow_reserve_info.key,
+        obligation: *obligation_info.key,
+        user: *obligation_owner_info.key,
+        liquidity_amount: receive_amount,
+        borrow_fee,
+    })
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_repay_obligation_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let repay_reserve_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut repay_reserve = Reserve::unpack_account(&repay_reserve_info.data.borrow())?;
+    if repay_reserve_info.owner != program_id {
+        msg!("Repay reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &repay_reserve.lending_market != lending_market_info.key {
+        msg!("Repay reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey != destination_liquidity_info.key {
+        msg!("Repay reserve liquidity supply must be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if repay_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Repay reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    // The obligation owner isn't required, anyone can repay with liquidity the
+    // user transfer authority can transfer
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+
+    let (liquidity, liquidity_index) =
+        obligation.find_liquidity_in_borrows(*repay_reserve_info.key)?;
+    if liquidity.borrowed_amount_wads == Decimal::zero() {
+        msg!("Liquidity borrowed amount is zero");
+        return Err(LendingError::ObligationLiquidityEmpty.into());
+    }
+
+    let CalculateRepayResult {
+        settle_amount,
+        repay_amount,
+    } = repay_reserve.calculate_repay(liquidity_amount, liquidity.borrowed_amount_wads)?;
+
+    if repay_amount == 0 {
+        msg!("Repay amount is too small to transfer liquidity");
+        return Err(LendingError::RepayTooSmall.into());
+    }
+
+    // Partial repays may leave borrows below the market minimum borrow value,
+    // which is allowed, but a remainder that small may never be worth
+    // liquidating
+    if settle_amount < liquidity.borrowed_amount_wads
+        && lending_market.min_borrow_value > Decimal::zero()
+    {
+        let remaining_borrow_value = obligation
+            .borrowed_market_value()?
+            .try_sub(repay_reserve.market_value(settle_amount)?)?;
+        if remaining_borrow_value < lending_market.min_borrow_value {
+            msg!(
+                "Warning: remaining borrow value {} is below the market minimum borrow value {}, repay in full to avoid leaving dust",
+                remaining_borrow_value,
+                lending_market.min_borrow_value
+            );
+        }
+    }
+
+    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
+    repay_reserve.last_update.mark_stale();
+    Reserve::pack_account(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
+
+    obligation.repay(settle_amount, liquidity_index)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: repay_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::Repay(RepayEvent {
+        reserve: *repay_reserve_info.key,
+        obligation: *obligation_info.key,
+        user: *user_transfer_authority_info.key,
+        liquidity_amount: repay_amount,
+    })
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_liquidate_obligation(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let repay_reserve_info = next_account_info(account_info_iter)?;
+    let repay_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut repay_reserve = Reserve::unpack_account(&repay_reserve_info.data.borrow())?;
+    if repay_reserve_info.owner != program_id {
+        msg!("Repay reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &repay_reserve.lending_market != lending_market_info.key {
+        msg!("Repay reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey != repay_reserve_liquidity_supply_info.key {
+        msg!("Repay reserve liquidity supply does not match the repay reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.collateral.supply_pubkey == destination_collateral_info.key {
+        msg!(
+            "Repay reserve collateral supply cannot be used as the destination collateral provided"
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if repay_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Repay reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if repay_reserve.is_paused(RESERVE_PAUSE_LIQUIDATIONS) {
+        msg!("Repay reserve liquidations are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+
+    let withdraw_reserve = Reserve::unpack_account(&withdraw_reserve_info.data.borrow())?;
+    if withdraw_reserve_info.owner != program_id {
+        msg!("Withdraw reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &withdraw_reserve.lending_market != lending_market_info.key {
+        msg!("Withdraw reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.collateral.supply_pubkey != withdraw_reserve_collateral_supply_info.key {
+        msg!("Withdraw reserve collateral supply does not match the withdraw reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Withdraw reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.collateral.supply_pubkey == destination_collateral_info.key {
+        msg!("Withdraw reserve collateral supply cannot be used as the destination collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if withdraw_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Withdraw reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if withdraw_reserve.is_paused(RESERVE_PAUSE_LIQUIDATIONS) {
+        msg!("Withdraw reserve liquidations are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+    if obligation.deposited_value == Decimal::zero() {
+        msg!("Obligation deposited value is zero");
+        return Err(LendingError::ObligationDepositsZero.into());
+    }
+    if obligation.borrowed_value == Decimal::zero() {
+        msg!("Obligation borrowed value is zero");
+        return Err(LendingError::ObligationBorrowsZero.into());
+    }
+    if obligation.borrowed_value < obligation.unhealthy_borrow_value {
+        msg!("Obligation is healthy and cannot be liquidated");
+        return Err(LendingError::ObligationHealthy.into());
+    }
+
+    let (liquidity, liquidity_index) =
+        obligation.find_liquidity_in_borrows(*repay_reserve_info.key)?;
+    if liquidity.market_value == Decimal::zero() {
+        msg!("Obligation borrow value is zero");
+        return Err(LendingError::ObligationLiquidityEmpty.into());
+    }
 
-    spl_token_init_mint(TokenInitializeMintParams {
-        mint: reserve_collateral_mint_info.clone(),
-        authority: lending_market_authority_info.key,
-        rent: rent_info.clone(),
-        decimals: reserve_liquidity_mint.decimals,
-        token_program: token_program_id.clone(),
-    })?;
+    let (collateral, collateral_index) =
+        obligation.find_collateral_in_deposits(*withdraw_reserve_info.key)?;
+    if collateral.market_value == Decimal::zero() {
+        msg!("Obligation deposit value is zero");
+        return Err(LendingError::ObligationCollateralEmpty.into());
+    }
 
-    spl_token_init_account(TokenInitializeAccountParams {
-        account: reserve_collateral_supply_info.clone(),
-        mint: reserve_collateral_mint_info.clone(),
-        owner: lending_market_authority_info.clone(),
-        rent: rent_info.clone(),
-        token_program: token_program_id.clone(),
-    })?;
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
 
-    spl_token_init_account(TokenInitializeAccountParams {
-        account: destination_collateral_info.clone(),
-        mint: reserve_collateral_mint_info.clone(),
-        owner: user_transfer_authority_info.clone(),
-        rent: rent_info.clone(),
-        token_program: token_program_id.clone(),
-    })?;
+    let CalculateLiquidationResult {
+        settle_amount,
+        repay_amount,
+        withdraw_amount,
+        bonus_amount,
+        protocol_fee,
+    } = withdraw_reserve.calculate_liquidation(
+        liquidity_amount,
+        &obligation,
+        liquidity,
+        collateral,
+        &repay_reserve.config,
+        lending_market.dust_liquidation_threshold,
+    )?;
+
+    if repay_amount == 0 {
+        msg!("Liquidation is too small to transfer liquidity");
+        return Err(LendingError::LiquidationTooSmall.into());
+    }
+    if withdraw_amount == 0 {
+        msg!("Liquidation is too small to receive collateral");
+        return Err(LendingError::LiquidationTooSmall.into());
+    }
+
+    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
+    repay_reserve.last_update.mark_stale();
+    Reserve::pack_account(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
+
+    obligation.repay(settle_amount, liquidity_index)?;
+    obligation.withdraw(withdraw_amount, collateral_index)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
 
     spl_token_transfer(TokenTransferParams {
         source: source_liquidity_info.clone(),
-        destination: reserve_liquidity_supply_info.clone(),
-        amount: liquidity_amount,
+        destination: repay_reserve_liquidity_supply_info.clone(),
+        amount: repay_amount,
         authority: user_transfer_authority_info.clone(),
         authority_signer_seeds: &[],
         token_program: token_program_id.clone(),
     })?;
 
-    spl_token_mint_to(TokenMintToParams {
-        mint: reserve_collateral_mint_info.clone(),
+    let liquidator_amount = withdraw_amount
+        .checked_sub(protocol_fee)
+        .ok_or(LendingError::MathOverflow)?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: withdraw_reserve_collateral_supply_info.clone(),
         destination: destination_collateral_info.clone(),
-        amount: collateral_amount,
+        amount: liquidator_amount,
         authority: lending_market_authority_info.clone(),
         authority_signer_seeds,
         token_program: token_program_id.clone(),
     })?;
 
+    if protocol_fee > 0 {
+        let collateral_fee_receiver_info = next_account_info(account_info_iter)?;
+        if &withdraw_reserve.collateral.fee_receiver != collateral_fee_receiver_info.key {
+            msg!("Withdraw reserve collateral fee receiver does not match the collateral fee receiver provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        spl_token_transfer(TokenTransferParams {
+            source: withdraw_reserve_collateral_supply_info.clone(),
+            destination: collateral_fee_receiver_info.clone(),
+            amount: protocol_fee,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    LendingEvent::Liquidate(LiquidateEvent {
+        obligation: *obligation_info.key,
+        repay_reserve: *repay_reserve_info.key,
+        withdraw_reserve: *withdraw_reserve_info.key,
+        repay_amount,
+        seized_collateral: withdraw_amount,
+        bonus: bonus_amount,
+    })
+    .emit();
+
     Ok(())
 }
 
-fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter().peekable();
+#[inline(never)] // avoid stack frame limit
+fn process_flash_loan(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
     let reserve_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_oracle_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let flash_loan_fee_receiver_info = next_account_info(account_info_iter)?;
+    let host_fee_receiver_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+    let flash_loan_receiver_program_id = next_account_info(account_info_iter)?;
+
+    if program_id == flash_loan_receiver_program_id.key {
+        msg!("Lending program cannot be used as the flash loan receiver program provided");
+        return Err(LendingError::InvalidFlashLoanReceiverProgram.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
 
-    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
     if reserve_info.owner != program_id {
         msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &reserve.liquidity.oracle_pubkey != reserve_liquidity_oracle_info.key {
-        msg!("Reserve liquidity oracle does not match the reserve liquidity oracle provided");
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Invalid reserve lending market account");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != source_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.liquidity.flash_loan_fee_destination() != flash_loan_fee_receiver_info.key {
+        msg!("Reserve flash loan fee receiver does not match the flash loan fee receiver provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    if reserve.is_paused(RESERVE_PAUSE_BORROWS) {
+        msg!("Reserve borrows are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !reserve.is_active() {
+        msg!("Reserve is deprecated and no longer accepts borrows");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    // @FIXME: if u64::MAX is flash loaned, fees should be inclusive as with
+    // ordinary borrows
+    let flash_loan_amount = if liquidity_amount == u64::MAX {
+        reserve.liquidity.available_amount
+    } else {
+        liquidity_amount
+    };
+
+    let flash_loan_amount_decimal = Decimal::from(flash_loan_amount);
+    let (origination_fee, host_fee) = reserve
+        .config
+        .fees
+        .calculate_flash_loan_fees(flash_loan_amount_decimal)?;
+
+    let balance_before_flash_loan = Account::unpack(&source_liquidity_info.data.borrow())?.amount;
+    let expected_balance_after_flash_loan = balance_before_flash_loan
+        .checked_add(origination_fee)
+        .ok_or(LendingError::MathOverflow)?;
+    let returned_amount_required = flash_loan_amount
+        .checked_add(origination_fee)
+        .ok_or(LendingError::MathOverflow)?;
+
+    let mut flash_loan_instruction_accounts = vec![
+        AccountMeta::new(*destination_liquidity_info.key, false),
+        AccountMeta::new(*source_liquidity_info.key, false),
+        AccountMeta::new_readonly(*token_program_id.key, false),
+    ];
+    let mut flash_loan_instruction_account_infos = vec![
+        destination_liquidity_info.clone(),
+        flash_loan_receiver_program_id.clone(),
+        source_liquidity_info.clone(),
+        token_program_id.clone(),
+    ];
+    for account_info in account_info_iter {
+        flash_loan_instruction_accounts.push(AccountMeta {
+            pubkey: *account_info.key,
+            is_signer: account_info.is_signer,
+            is_writable: account_info.is_writable,
+        });
+        flash_loan_instruction_account_infos.push(account_info.clone());
+    }
+
+    reserve.liquidity.borrow(flash_loan_amount_decimal)?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: flash_loan_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    const RECEIVE_FLASH_LOAN_INSTRUCTION_DATA_SIZE: usize = 9;
+
+    let mut data = Vec::with_capacity(RECEIVE_FLASH_LOAN_INSTRUCTION_DATA_SIZE);
+    data.push(RECEIVE_FLASH_LOAN_INSTRUCTION_TAG);
+    data.extend_from_slice(&returned_amount_required.to_le_bytes());
+
+    invoke(
+        &Instruction {
+            program_id: *flash_loan_receiver_program_id.key,
+            accounts: flash_loan_instruction_accounts,
+            data,
+        },
+        &flash_loan_instruction_account_infos[..],
+    )?;
+
+    reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    reserve
+        .liquidity
+        .repay(flash_loan_amount, flash_loan_amount_decimal)?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    let actual_balance_after_flash_loan =
+        Account::unpack(&source_liquidity_info.data.borrow())?.amount;
+    if actual_balance_after_flash_loan < expected_balance_after_flash_loan {
+        msg!("Insufficient reserve liquidity after flash loan");
+        return Err(LendingError::NotEnoughLiquidityAfterFlashLoan.into());
+    }
+
+    let mut owner_fee = origination_fee;
+    if host_fee > 0 {
+        owner_fee = owner_fee
+            .checked_sub(host_fee)
+            .ok_or(LendingError::MathOverflow)?;
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: host_fee_receiver_info.clone(),
+            amount: host_fee,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
 
-    reserve.liquidity.market_price = get_pyth_price(reserve_liquidity_oracle_info, clock)?;
-
-    reserve.accrue_interest(clock.slot)?;
-    reserve.last_update.update_slot(clock.slot);
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: flash_loan_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
 
     Ok(())
 }
 
-fn process_deposit_reserve_liquidity(
+#[inline(never)] // avoid stack frame limit
+fn process_flash_borrow_reserve_liquidity(
     program_id: &Pubkey,
     liquidity_amount: u64,
     accounts: &[AccountInfo],
@@ -423,17 +3480,14 @@ fn process_deposit_reserve_liquidity(
 
     let account_info_iter = &mut accounts.iter();
     let source_liquidity_info = next_account_info(account_info_iter)?;
-    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
     let reserve_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
-    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
-    let user_transfer_authority_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let instructions_sysvar_info = next_account_info(account_info_iter)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -442,8 +3496,25 @@ fn process_deposit_reserve_liquidity(
         msg!("Lending market token program does not match the token program provided");
         return Err(LendingError::InvalidTokenProgram.into());
     }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
 
-    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
     if reserve_info.owner != program_id {
         msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -452,59 +3523,68 @@ fn process_deposit_reserve_liquidity(
         msg!("Reserve lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
-        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
-        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
-        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
+    if &reserve.liquidity.supply_pubkey != source_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the source liquidity provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.collateral.supply_pubkey == destination_collateral_info.key {
-        msg!("Reserve collateral supply cannot be used as the destination collateral provided");
-        return Err(LendingError::InvalidAccountInput.into());
+    if reserve.is_paused(RESERVE_PAUSE_BORROWS) {
+        msg!("Reserve borrows are paused");
+        return Err(LendingError::ActionPaused.into());
     }
-    if reserve.last_update.is_stale(clock.slot)? {
-        msg!("Reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
+    if !reserve.is_active() {
+        msg!("Reserve is deprecated and no longer accepts borrows");
+        return Err(LendingError::ReserveDeprecated.into());
     }
 
-    let authority_signer_seeds = &[
-        lending_market_info.key.as_ref(),
-        &[lending_market.bump_seed],
-    ];
-    let lending_market_authority_pubkey =
-        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
-    if &lending_market_authority_pubkey != lending_market_authority_info.key {
-        msg!(
-            "Derived lending market authority {} does not match the lending market authority provided {}",
-            &lending_market_authority_pubkey.to_string(),
-            &lending_market_authority_info.key.to_string(),
-        );
-        return Err(LendingError::InvalidMarketAuthority.into());
+    // The repay is found by scanning the rest of the transaction, so it must
+    // be exactly one top level repay pointing back at this borrow
+    let borrow_instruction_index =
+        load_flash_loan_instruction_index(program_id, instructions_sysvar_info)?;
+    let mut repay_found = false;
+    let mut instruction_index = borrow_instruction_index.saturating_add(1);
+    while let Ok(instruction) =
+        load_instruction_at_checked(instruction_index, instructions_sysvar_info)
+    {
+        instruction_index = instruction_index.saturating_add(1);
+        if &instruction.program_id != program_id {
+            continue;
+        }
+        if let Ok(LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount: repay_liquidity_amount,
+            borrow_instruction_index: repay_borrow_instruction_index,
+        }) = LendingInstruction::unpack(&instruction.data)
+        {
+            if repay_borrow_instruction_index as usize != borrow_instruction_index {
+                continue;
+            }
+            if repay_found {
+                msg!("Flash borrow cannot have more than one flash repay");
+                return Err(LendingError::InvalidFlashRepay.into());
+            }
+            if repay_liquidity_amount != liquidity_amount
+                || instruction.accounts.get(4).map(|account| &account.pubkey)
+                    != Some(reserve_info.key)
+            {
+                msg!("Flash repay must be for the flash borrow reserve and amount");
+                return Err(LendingError::InvalidFlashRepay.into());
+            }
+            repay_found = true;
+        }
+    }
+    if !repay_found {
+        msg!("Flash borrow has no matching flash repay later in the transaction");
+        return Err(LendingError::NoFlashRepayFound.into());
     }
 
-    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
-    reserve.last_update.mark_stale();
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+    // The liquidity is recorded as borrowed until the repay, so it can't be
+    // borrowed again or withdrawn in between
+    reserve.liquidity.borrow(Decimal::from(liquidity_amount))?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
     spl_token_transfer(TokenTransferParams {
         source: source_liquidity_info.clone(),
-        destination: reserve_liquidity_supply_info.clone(),
+        destination: destination_liquidity_info.clone(),
         amount: liquidity_amount,
-        authority: user_transfer_authority_info.clone(),
-        authority_signer_seeds: &[],
-        token_program: token_program_id.clone(),
-    })?;
-
-    spl_token_mint_to(TokenMintToParams {
-        mint: reserve_collateral_mint_info.clone(),
-        destination: destination_collateral_info.clone(),
-        amount: collateral_amount,
         authority: lending_market_authority_info.clone(),
         authority_signer_seeds,
         token_program: token_program_id.clone(),
@@ -513,29 +3593,30 @@ fn process_deposit_reserve_liquidity(
     Ok(())
 }
 
-fn process_redeem_reserve_collateral(
+#[inline(never)] // avoid stack frame limit
+fn process_flash_repay_reserve_liquidity(
     program_id: &Pubkey,
-    collateral_amount: u64,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    if collateral_amount == 0 {
-        msg!("Collateral amount provided cannot be zero");
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
         return Err(LendingError::InvalidAmount.into());
     }
 
     let account_info_iter = &mut accounts.iter();
-    let source_collateral_info = next_account_info(account_info_iter)?;
+    let source_liquidity_info = next_account_info(account_info_iter)?;
     let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let flash_loan_fee_receiver_info = next_account_info(account_info_iter)?;
+    let host_fee_receiver_info = next_account_info(account_info_iter)?;
     let reserve_info = next_account_info(account_info_iter)?;
-    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
-    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
-    let lending_market_authority_info = next_account_info(account_info_iter)?;
     let user_transfer_authority_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let instructions_sysvar_info = next_account_info(account_info_iter)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -545,7 +3626,7 @@ fn process_redeem_reserve_collateral(
         return Err(LendingError::InvalidTokenProgram.into());
     }
 
-    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
     if reserve_info.owner != program_id {
         msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -554,255 +3635,429 @@ fn process_redeem_reserve_collateral(
         msg!("Reserve lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
-        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+    if &reserve.liquidity.supply_pubkey != destination_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the destination liquidity provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.collateral.supply_pubkey == source_collateral_info.key {
-        msg!("Reserve collateral supply cannot be used as the source collateral provided");
+    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
-        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+    if reserve.liquidity.flash_loan_fee_destination() != flash_loan_fee_receiver_info.key {
+        msg!("Reserve flash loan fee receiver does not match the flash loan fee receiver provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
-        msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
-        return Err(LendingError::InvalidAccountInput.into());
+
+    let repay_instruction_index =
+        load_flash_loan_instruction_index(program_id, instructions_sysvar_info)?;
+    if borrow_instruction_index as usize >= repay_instruction_index {
+        msg!("Flash repay must come after the flash borrow");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+    let borrow_instruction =
+        load_instruction_at_checked(borrow_instruction_index as usize, instructions_sysvar_info)?;
+    let is_matching_borrow = &borrow_instruction.program_id == program_id
+        && LendingInstruction::unpack(&borrow_instruction.data)
+            == Ok(LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount })
+        && borrow_instruction
+            .accounts
+            .get(2)
+            .map(|account| &account.pubkey)
+            == Some(reserve_info.key);
+    if !is_matching_borrow {
+        msg!("Flash repay does not point to a flash borrow of the same reserve and amount");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+
+    let flash_loan_amount_decimal = Decimal::from(liquidity_amount);
+    let (origination_fee, host_fee) = reserve
+        .config
+        .fees
+        .calculate_flash_loan_fees(flash_loan_amount_decimal)?;
+
+    reserve
+        .liquidity
+        .repay(liquidity_amount, flash_loan_amount_decimal)?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    let mut owner_fee = origination_fee;
+    if host_fee > 0 {
+        owner_fee = owner_fee
+            .checked_sub(host_fee)
+            .ok_or(LendingError::MathOverflow)?;
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: host_fee_receiver_info.clone(),
+            amount: host_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
     }
-    if reserve.last_update.is_stale(clock.slot)? {
-        msg!("Reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
+
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: flash_loan_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
     }
 
-    let authority_signer_seeds = &[
-        lending_market_info.key.as_ref(),
-        &[lending_market.bump_seed],
-    ];
-    let lending_market_authority_pubkey =
-        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
-    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+    Ok(())
+}
+
+/// Index of the flash borrow or repay being processed in the transaction.
+/// Fails if it's invoked via CPI, where the top level instruction belongs to
+/// another program which could wrap the borrow and skip the repay.
+fn load_flash_loan_instruction_index(
+    program_id: &Pubkey,
+    instructions_sysvar_info: &AccountInfo,
+) -> Result<usize, ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar_info)? as usize;
+    let current_instruction = load_instruction_at_checked(current_index, instructions_sysvar_info)?;
+    if &current_instruction.program_id != program_id
+        || get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT
+    {
+        msg!("Flash borrow and repay must be top level instructions");
+        return Err(LendingError::FlashLoanInvokedViaCpi.into());
+    }
+    Ok(current_index)
+}
+
+fn process_modify_reserve_config(
+    program_id: &Pubkey,
+    new_config: ReserveConfig,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    new_config.validate()?;
+
+    let account_info_iter = &mut accounts.iter().peekable();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    let is_owner = &lending_market.owner == authority_info.key;
+    if !is_owner && !lending_market.is_risk_authority(authority_info.key) {
+        msg!("Only the lending market owner or risk authority can modify the reserve config");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !authority_info.is_signer {
+        msg!("Lending market authority provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow_mut())?;
+    // Validate that the reserve account corresponds to the correct lending market,
+    // after validating above that the lending market and lending market owner
+    // correspond, to prevent one compromised lending market owner from changing
+    // configs on other lending markets
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if !is_owner {
+        new_config.assert_tightens(&reserve.config)?;
+    }
+    // a 0 limit in the V1 padding is read as no limit at all
+    if reserve_info.data_len() == RESERVE_V1_LEN
+        && (new_config.borrow_limit == 0 || new_config.deposit_limit == 0)
+    {
         msg!(
-            "Derived lending market authority does not match the lending market authority provided"
+            "Reserve must be migrated to the current layout to set a borrow or deposit limit of 0"
         );
-        return Err(LendingError::InvalidMarketAuthority.into());
+        return Err(LendingError::AccountNotMigrated.into());
     }
 
-    let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
-    reserve.last_update.mark_stale();
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+    reserve.config = new_config;
 
-    spl_token_burn(TokenBurnParams {
-        mint: reserve_collateral_mint_info.clone(),
-        source: source_collateral_info.clone(),
-        amount: collateral_amount,
-        authority: user_transfer_authority_info.clone(),
-        authority_signer_seeds: &[],
-        token_program: token_program_id.clone(),
-    })?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_reserve_pause_flags(
+    program_id: &Pubkey,
+    pause_flags: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if pause_flags & !RESERVE_PAUSE_ALL != 0 {
+        msg!("Pause flags provided contain unknown flags");
+        return Err(LendingError::InvalidConfig.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    let is_owner = &lending_market.owner == authority_info.key;
+    if !is_owner && !lending_market.is_risk_authority(authority_info.key) {
+        msg!("Only the lending market owner or risk authority can set the reserve pause flags");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !authority_info.is_signer {
+        msg!("Lending market authority provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    // the risk authority can pause more actions but not unpause them
+    if !is_owner && pause_flags & reserve.pause_flags != reserve.pause_flags {
+        msg!("Risk authority cannot clear reserve pause flags");
+        return Err(LendingError::RiskAuthorityCannotLoosen.into());
+    }
 
-    spl_token_transfer(TokenTransferParams {
-        source: reserve_liquidity_supply_info.clone(),
-        destination: destination_liquidity_info.clone(),
-        amount: liquidity_amount,
-        authority: lending_market_authority_info.clone(),
-        authority_signer_seeds,
-        token_program: token_program_id.clone(),
-    })?;
+    reserve.pause_flags = pause_flags;
+
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
     Ok(())
 }
 
-#[inline(never)] // avoid stack frame limit
-fn process_init_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_set_reserve_status(
+    program_id: &Pubkey,
+    status: ReserveStatus,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let obligation_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
-    let obligation_owner_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
-    let token_program_id = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
 
-    assert_rent_exempt(rent, obligation_info)?;
-    let mut obligation = assert_uninitialized::<Obligation>(obligation_info)?;
-    if obligation_info.owner != program_id {
-        msg!("Obligation provided is not owned by the lending program");
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &lending_market.token_program_id != token_program_id.key {
-        msg!("Lending market token program does not match the token program provided");
-        return Err(LendingError::InvalidTokenProgram.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
     }
-
-    if !obligation_owner_info.is_signer {
-        msg!("Obligation owner provided must be a signer");
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
         return Err(LendingError::InvalidSigner.into());
     }
 
-    obligation.init(InitObligationParams {
-        current_slot: clock.slot,
-        lending_market: *lending_market_info.key,
-        owner: *obligation_owner_info.key,
-        deposits: vec![],
-        borrows: vec![],
-    });
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    reserve.status = status;
+
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
     Ok(())
 }
 
-fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter().peekable();
-    let obligation_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
-    if obligation_info.owner != program_id {
-        msg!("Obligation provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
+fn process_set_price_override(
+    program_id: &Pubkey,
+    price_override: Option<Decimal>,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if price_override == Some(Decimal::zero()) {
+        msg!("Price override provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
     }
 
-    let mut deposited_value = Decimal::zero();
-    let mut borrowed_value = Decimal::zero();
-    let mut allowed_borrow_value = Decimal::zero();
-    let mut unhealthy_borrow_value = Decimal::zero();
-
-    for (index, collateral) in obligation.deposits.iter_mut().enumerate() {
-        let deposit_reserve_info = next_account_info(account_info_iter)?;
-        if deposit_reserve_info.owner != program_id {
-            msg!(
-                "Deposit reserve provided for collateral {} is not owned by the lending program",
-                index
-            );
-            return Err(LendingError::InvalidAccountOwner.into());
-        }
-        if collateral.deposit_reserve != *deposit_reserve_info.key {
-            msg!(
-                "Deposit reserve of collateral {} does not match the deposit reserve provided",
-                index
-            );
-            return Err(LendingError::InvalidAccountInput.into());
-        }
-
-        let deposit_reserve = Reserve::unpack(&deposit_reserve_info.data.borrow())?;
-        if deposit_reserve.last_update.is_stale(clock.slot)? {
-            msg!(
-                "Deposit reserve provided for collateral {} is stale and must be refreshed in the current slot",
-                index
-            );
-            return Err(LendingError::ReserveStale.into());
-        }
-
-        // @TODO: add lookup table https://git.io/JOCYq
-        let decimals = 10u64
-            .checked_pow(deposit_reserve.liquidity.mint_decimals as u32)
-            .ok_or(LendingError::MathOverflow)?;
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
 
-        let market_value = deposit_reserve
-            .collateral_exchange_rate()?
-            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
-            .try_mul(deposit_reserve.liquidity.market_price)?
-            .try_div(decimals)?;
-        collateral.market_value = market_value;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
 
-        let loan_to_value_rate = Rate::from_percent(deposit_reserve.config.loan_to_value_ratio);
-        let liquidation_threshold_rate =
-            Rate::from_percent(deposit_reserve.config.liquidation_threshold);
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
 
-        deposited_value = deposited_value.try_add(market_value)?;
-        allowed_borrow_value =
-            allowed_borrow_value.try_add(market_value.try_mul(loan_to_value_rate)?)?;
-        unhealthy_borrow_value =
-            unhealthy_borrow_value.try_add(market_value.try_mul(liquidation_threshold_rate)?)?;
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
     }
 
-    for (index, liquidity) in obligation.borrows.iter_mut().enumerate() {
-        let borrow_reserve_info = next_account_info(account_info_iter)?;
-        if borrow_reserve_info.owner != program_id {
-            msg!(
-                "Borrow reserve provided for liquidity {} is not owned by the lending program",
-                index
-            );
-            return Err(LendingError::InvalidAccountOwner.into());
-        }
-        if liquidity.borrow_reserve != *borrow_reserve_info.key {
-            msg!(
-                "Borrow reserve of liquidity {} does not match the borrow reserve provided",
-                index
-            );
-            return Err(LendingError::InvalidAccountInput.into());
-        }
+    reserve.set_emergency_price_override(price_override);
 
-        let borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
-        if borrow_reserve.last_update.is_stale(clock.slot)? {
-            msg!(
-                "Borrow reserve provided for liquidity {} is stale and must be refreshed in the current slot",
-                index
-            );
-            return Err(LendingError::ReserveStale.into());
-        }
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
-        liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+    Ok(())
+}
 
-        // @TODO: add lookup table https://git.io/JOCYq
-        let decimals = 10u64
-            .checked_pow(borrow_reserve.liquidity.mint_decimals as u32)
-            .ok_or(LendingError::MathOverflow)?;
+fn process_set_market_metadata(
+    program_id: &Pubkey,
+    name: String,
+    description_uri: String,
+    flags: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if !MarketMetadata::is_valid_len(&name, &description_uri) {
+        msg!(
+            "Market name cannot be longer than {} bytes and description URI cannot be longer than {} bytes",
+            MAX_MARKET_NAME_LEN,
+            MAX_MARKET_DESCRIPTION_URI_LEN
+        );
+        return Err(LendingError::MarketMetadataTooLong.into());
+    }
 
-        let market_value = liquidity
-            .borrowed_amount_wads
-            .try_mul(borrow_reserve.liquidity.market_price)?
-            .try_div(decimals)?;
-        liquidity.market_value = market_value;
+    let account_info_iter = &mut accounts.iter();
+    let market_metadata_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
 
-        borrowed_value = borrowed_value.try_add(market_value)?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    if account_info_iter.peek().is_some() {
-        msg!("Too many obligation deposit or borrow reserves provided");
+    let (market_metadata_pubkey, bump_seed) =
+        find_market_metadata_address(program_id, lending_market_info.key);
+    if &market_metadata_pubkey != market_metadata_info.key {
+        msg!("Market metadata provided is not derived from the lending market");
         return Err(LendingError::InvalidAccountInput.into());
     }
 
-    obligation.deposited_value = deposited_value;
-    obligation.borrowed_value = borrowed_value;
-    obligation.allowed_borrow_value = allowed_borrow_value;
-    obligation.unhealthy_borrow_value = unhealthy_borrow_value;
-
-    obligation.last_update.update_slot(clock.slot);
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    let market_metadata = MarketMetadata::new(InitMarketMetadataParams {
+        bump_seed,
+        lending_market: *lending_market_info.key,
+        flags,
+        name,
+        description_uri,
+    });
+    let account_len = market_metadata.account_len();
+
+    // the payer funds the rent of the longer strings, the rent of shorter ones
+    // is left in the account
+    let required_lamports = rent
+        .minimum_balance(account_len)
+        .saturating_sub(market_metadata_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                market_metadata_info.key,
+                required_lamports,
+            ),
+            &[
+                payer_info.clone(),
+                market_metadata_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    if market_metadata_info.owner == program_id {
+        market_metadata_info.realloc(account_len, false)?;
+    } else {
+        let metadata_signer_seeds: &[&[u8]] = &[
+            MARKET_METADATA_SEED,
+            lending_market_info.key.as_ref(),
+            &[bump_seed],
+        ];
+        invoke_signed(
+            &system_instruction::allocate(market_metadata_info.key, account_len as u64),
+            &[market_metadata_info.clone(), system_program_info.clone()],
+            &[metadata_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(market_metadata_info.key, program_id),
+            &[market_metadata_info.clone(), system_program_info.clone()],
+            &[metadata_signer_seeds],
+        )?;
+    }
+
+    MarketMetadata::pack_account(market_metadata, &mut market_metadata_info.data.borrow_mut())?;
 
     Ok(())
 }
 
-#[inline(never)] // avoid stack frame limit
-fn process_deposit_obligation_collateral(
+fn process_donate_liquidity(
     program_id: &Pubkey,
-    collateral_amount: u64,
+    liquidity_amount: u64,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    if collateral_amount == 0 {
-        msg!("Collateral amount provided cannot be zero");
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
         return Err(LendingError::InvalidAmount.into());
     }
 
     let account_info_iter = &mut accounts.iter();
-    let source_collateral_info = next_account_info(account_info_iter)?;
-    let destination_collateral_info = next_account_info(account_info_iter)?;
-    let deposit_reserve_info = next_account_info(account_info_iter)?;
-    let obligation_info = next_account_info(account_info_iter)?;
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
-    let obligation_owner_info = next_account_info(account_info_iter)?;
     let user_transfer_authority_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -812,62 +4067,37 @@ fn process_deposit_obligation_collateral(
         return Err(LendingError::InvalidTokenProgram.into());
     }
 
-    let deposit_reserve = Reserve::unpack(&deposit_reserve_info.data.borrow())?;
-    if deposit_reserve_info.owner != program_id {
-        msg!("Deposit reserve provided is not owned by the lending program");
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &deposit_reserve.lending_market != lending_market_info.key {
-        msg!("Deposit reserve lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &deposit_reserve.collateral.supply_pubkey == source_collateral_info.key {
-        msg!("Deposit reserve collateral supply cannot be used as the source collateral provided");
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &deposit_reserve.collateral.supply_pubkey != destination_collateral_info.key {
-        msg!(
-            "Deposit reserve collateral supply must be used as the destination collateral provided"
-        );
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if deposit_reserve.last_update.is_stale(clock.slot)? {
-        msg!("Deposit reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
-    }
-    if deposit_reserve.config.loan_to_value_ratio == 0 {
-        msg!("Deposit reserve has collateral disabled for borrowing");
-        return Err(LendingError::ReserveCollateralDisabled.into());
-    }
-
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
-    if obligation_info.owner != program_id {
-        msg!("Obligation provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
-    }
-    if &obligation.lending_market != lending_market_info.key {
-        msg!("Obligation lending market does not match the lending market provided");
+    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &obligation.owner != obligation_owner_info.key {
-        msg!("Obligation owner does not match the obligation owner provided");
-        return Err(LendingError::InvalidObligationOwner.into());
-    }
-    if !obligation_owner_info.is_signer {
-        msg!("Obligation owner provided must be a signer");
-        return Err(LendingError::InvalidSigner.into());
+    // the donation is measured against an exchange rate with interest accrued
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
     }
 
-    obligation
-        .find_or_add_collateral_to_deposits(*deposit_reserve_info.key)?
-        .deposit(collateral_amount)?;
-    obligation.last_update.mark_stale();
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    reserve.donate_liquidity(liquidity_amount)?;
+    reserve.last_update.mark_stale();
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
     spl_token_transfer(TokenTransferParams {
-        source: source_collateral_info.clone(),
-        destination: destination_collateral_info.clone(),
-        amount: collateral_amount,
+        source: source_liquidity_info.clone(),
+        destination: reserve_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
         authority: user_transfer_authority_info.clone(),
         authority_signer_seeds: &[],
         token_program: token_program_id.clone(),
@@ -876,174 +4106,190 @@ fn process_deposit_obligation_collateral(
     Ok(())
 }
 
-#[inline(never)] // avoid stack frame limit
-fn process_withdraw_obligation_collateral(
-    program_id: &Pubkey,
-    collateral_amount: u64,
-    accounts: &[AccountInfo],
-) -> ProgramResult {
-    if collateral_amount == 0 {
-        msg!("Collateral amount provided cannot be zero");
-        return Err(LendingError::InvalidAmount.into());
-    }
-
+fn process_set_reserve_oracle(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let source_collateral_info = next_account_info(account_info_iter)?;
-    let destination_collateral_info = next_account_info(account_info_iter)?;
-    let withdraw_reserve_info = next_account_info(account_info_iter)?;
-    let obligation_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let oracle_product_info = next_account_info(account_info_iter)?;
+    let oracle_price_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
-    let lending_market_authority_info = next_account_info(account_info_iter)?;
-    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-    let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &lending_market.token_program_id != token_program_id.key {
-        msg!("Lending market token program does not match the token program provided");
-        return Err(LendingError::InvalidTokenProgram.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
 
-    let withdraw_reserve = Reserve::unpack(&withdraw_reserve_info.data.borrow())?;
-    if withdraw_reserve_info.owner != program_id {
-        msg!("Withdraw reserve provided is not owned by the lending program");
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let (oracle_kind, market_price, market_price_slot) = get_reserve_oracle_price(
+        &lending_market,
+        oracle_product_info,
+        oracle_price_info,
+        clock,
+        &reserve.config,
+    )?;
+
+    reserve.liquidity.oracle_kind = oracle_kind;
+    reserve.liquidity.oracle_pubkey = *oracle_price_info.key;
+    reserve.liquidity.market_price = market_price;
+    reserve.liquidity.market_price_slot = market_price_slot;
+    reserve.last_update.mark_stale();
+
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_reserve_flash_loan_fee_receiver(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let flash_loan_fee_receiver_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &withdraw_reserve.lending_market != lending_market_info.key {
-        msg!("Withdraw reserve lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &withdraw_reserve.collateral.supply_pubkey != source_collateral_info.key {
-        msg!("Withdraw reserve collateral supply must be used as the source collateral provided");
-        return Err(LendingError::InvalidAccountInput.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
     }
-    if &withdraw_reserve.collateral.supply_pubkey == destination_collateral_info.key {
-        msg!("Withdraw reserve collateral supply cannot be used as the destination collateral provided");
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if withdraw_reserve.last_update.is_stale(clock.slot)? {
-        msg!("Withdraw reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
+
+    if flash_loan_fee_receiver_info.owner != &lending_market.token_program_id {
+        msg!("Flash loan fee receiver is not owned by the lending market token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let flash_loan_fee_receiver =
+        unpack_token_account(&flash_loan_fee_receiver_info.data.borrow())?;
+    if flash_loan_fee_receiver.mint != reserve.liquidity.mint_pubkey {
+        msg!("Flash loan fee receiver mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidTokenMint.into());
     }
 
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
-    if obligation_info.owner != program_id {
-        msg!("Obligation provided is not owned by the lending program");
+    reserve.liquidity.flash_loan_fee_receiver = *flash_loan_fee_receiver_info.key;
+
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_set_reserve_collateral_fee_receiver(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let collateral_fee_receiver_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &obligation.lending_market != lending_market_info.key {
-        msg!("Obligation lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &obligation.owner != obligation_owner_info.key {
-        msg!("Obligation owner does not match the obligation owner provided");
-        return Err(LendingError::InvalidObligationOwner.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
     }
-    if !obligation_owner_info.is_signer {
-        msg!("Obligation owner provided must be a signer");
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
         return Err(LendingError::InvalidSigner.into());
     }
-    if obligation.last_update.is_stale(clock.slot)? {
-        msg!("Obligation is stale and must be refreshed in the current slot");
-        return Err(LendingError::ObligationStale.into());
-    }
 
-    let (collateral, collateral_index) =
-        obligation.find_collateral_in_deposits(*withdraw_reserve_info.key)?;
-    if collateral.deposited_amount == 0 {
-        msg!("Collateral deposited amount is zero");
-        return Err(LendingError::ObligationCollateralEmpty.into());
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve account does not match the lending market");
+        return Err(LendingError::InvalidAccountInput.into());
     }
 
-    let authority_signer_seeds = &[
-        lending_market_info.key.as_ref(),
-        &[lending_market.bump_seed],
-    ];
-    let lending_market_authority_pubkey =
-        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
-    if &lending_market_authority_pubkey != lending_market_authority_info.key {
-        msg!(
-            "Derived lending market authority does not match the lending market authority provided"
-        );
-        return Err(LendingError::InvalidMarketAuthority.into());
+    if collateral_fee_receiver_info.owner != &lending_market.token_program_id {
+        msg!("Collateral fee receiver is not owned by the lending market token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let collateral_fee_receiver =
+        unpack_token_account(&collateral_fee_receiver_info.data.borrow())?;
+    if collateral_fee_receiver.mint != reserve.collateral.mint_pubkey {
+        msg!("Collateral fee receiver mint does not match the reserve collateral mint");
+        return Err(LendingError::InvalidTokenMint.into());
+    }
+    if &reserve.collateral.supply_pubkey == collateral_fee_receiver_info.key {
+        msg!("Reserve collateral supply cannot be used as the collateral fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
 
-    let withdraw_amount = if obligation.borrows.is_empty() {
-        if collateral_amount == u64::MAX {
-            collateral.deposited_amount
-        } else {
-            collateral.deposited_amount.min(collateral_amount)
-        }
-    } else if obligation.deposited_value == Decimal::zero() {
-        msg!("Obligation deposited value is zero");
-        return Err(LendingError::ObligationDepositsZero.into());
-    } else {
-        let max_withdraw_value = obligation.max_withdraw_value(Rate::from_percent(
-            withdraw_reserve.config.loan_to_value_ratio,
-        ))?;
-        if max_withdraw_value == Decimal::zero() {
-            msg!("Maximum withdraw value is zero");
-            return Err(LendingError::WithdrawTooLarge.into());
-        }
-
-        let withdraw_amount = if collateral_amount == u64::MAX {
-            let withdraw_value = max_withdraw_value.min(collateral.market_value);
-            let withdraw_pct = withdraw_value.try_div(collateral.market_value)?;
-            withdraw_pct
-                .try_mul(collateral.deposited_amount)?
-                .try_floor_u64()?
-                .min(collateral.deposited_amount)
-        } else {
-            let withdraw_amount = collateral_amount.min(collateral.deposited_amount);
-            let withdraw_pct =
-                Decimal::from(withdraw_amount).try_div(collateral.deposited_amount)?;
-            let withdraw_value = collateral.market_value.try_mul(withdraw_pct)?;
-            if withdraw_value > max_withdraw_value {
-                msg!("Withdraw value cannot exceed maximum withdraw value");
-                return Err(LendingError::WithdrawTooLarge.into());
-            }
-            withdraw_amount
-        };
-        if withdraw_amount == 0 {
-            msg!("Withdraw amount is too small to transfer collateral");
-            return Err(LendingError::WithdrawTooSmall.into());
-        }
-        withdraw_amount
-    };
-
-    obligation.withdraw(withdraw_amount, collateral_index)?;
-    obligation.last_update.mark_stale();
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    reserve.collateral.fee_receiver = *collateral_fee_receiver_info.key;
 
-    spl_token_transfer(TokenTransferParams {
-        source: source_collateral_info.clone(),
-        destination: destination_collateral_info.clone(),
-        amount: withdraw_amount,
-        authority: lending_market_authority_info.clone(),
-        authority_signer_seeds,
-        token_program: token_program_id.clone(),
-    })?;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
 
     Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
-fn process_borrow_obligation_liquidity(
+fn process_deposit_obligation_collateral_and_borrow(
     program_id: &Pubkey,
+    collateral_amount: u64,
     liquidity_amount: u64,
     slippage_limit: u64,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
     if liquidity_amount == 0 {
         msg!("Liquidity amount provided cannot be zero");
         return Err(LendingError::InvalidAmount.into());
     }
 
     let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_info = next_account_info(account_info_iter)?;
     let source_liquidity_info = next_account_info(account_info_iter)?;
     let destination_liquidity_info = next_account_info(account_info_iter)?;
     let borrow_reserve_info = next_account_info(account_info_iter)?;
@@ -1052,10 +4298,11 @@ fn process_borrow_obligation_liquidity(
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
     let obligation_owner_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1064,8 +4311,48 @@ fn process_borrow_obligation_liquidity(
         msg!("Lending market token program does not match the token program provided");
         return Err(LendingError::InvalidTokenProgram.into());
     }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
 
-    let mut borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+    let deposit_reserve = Reserve::unpack_account(&deposit_reserve_info.data.borrow())?;
+    if deposit_reserve_info.owner != program_id {
+        msg!("Deposit reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &deposit_reserve.lending_market != lending_market_info.key {
+        msg!("Deposit reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &deposit_reserve.collateral.supply_pubkey == source_collateral_info.key {
+        msg!("Deposit reserve collateral supply cannot be used as the source collateral provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &deposit_reserve.collateral.supply_pubkey != destination_collateral_info.key {
+        msg!(
+            "Deposit reserve collateral supply must be used as the destination collateral provided"
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if deposit_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Deposit reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if deposit_reserve.is_paused(RESERVE_PAUSE_DEPOSITS) {
+        msg!("Deposit reserve deposits are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !deposit_reserve.is_active() {
+        msg!("Deposit reserve is deprecated and no longer accepts deposits");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+    if deposit_reserve.config.loan_to_value_ratio == 0 {
+        msg!("Deposit reserve has collateral disabled for borrowing");
+        return Err(LendingError::ReserveCollateralDisabled.into());
+    }
+
+    let mut borrow_reserve = Reserve::unpack_account(&borrow_reserve_info.data.borrow())?;
     if borrow_reserve_info.owner != program_id {
         msg!("Borrow reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1092,8 +4379,20 @@ fn process_borrow_obligation_liquidity(
         msg!("Borrow reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
     }
+    if borrow_reserve.is_price_stale(clock.slot)? {
+        msg!("Borrow reserve market price is older than its max oracle age");
+        return Err(LendingError::ReservePriceStale.into());
+    }
+    if borrow_reserve.is_paused(RESERVE_PAUSE_BORROWS) {
+        msg!("Borrow reserve borrows are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !borrow_reserve.is_active() {
+        msg!("Borrow reserve is deprecated and no longer accepts borrows");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
 
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
         msg!("Obligation provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1110,18 +4409,35 @@ fn process_borrow_obligation_liquidity(
         msg!("Obligation owner provided must be a signer");
         return Err(LendingError::InvalidSigner.into());
     }
+    let isolated_deposit_reserves =
+        next_isolated_deposit_reserves(program_id, &obligation, account_info_iter)?;
+    if !obligation.can_combine_collateral(
+        *deposit_reserve_info.key,
+        deposit_reserve.config.isolated,
+        &isolated_deposit_reserves,
+    ) {
+        msg!("Isolated collateral cannot be combined with collateral from other reserves");
+        return Err(LendingError::IsolatedCollateral.into());
+    }
+    if (deposit_reserve.config.isolated || !isolated_deposit_reserves.is_empty())
+        && !borrow_reserve.config.borrowable_in_isolation
+    {
+        msg!("Borrow reserve cannot be borrowed from against isolated collateral");
+        return Err(LendingError::IsolatedCollateral.into());
+    }
+    // the allowlist entry comes before the optional host fee receiver
+    if lending_market.permissioned_borrowing {
+        assert_borrower_allowed(
+            program_id,
+            lending_market_info.key,
+            &obligation.owner,
+            next_account_info(account_info_iter).ok(),
+        )?;
+    }
     if obligation.last_update.is_stale(clock.slot)? {
         msg!("Obligation is stale and must be refreshed in the current slot");
         return Err(LendingError::ObligationStale.into());
     }
-    if obligation.deposits.is_empty() {
-        msg!("Obligation has no deposits to borrow against");
-        return Err(LendingError::ObligationDepositsEmpty.into());
-    }
-    if obligation.deposited_value == Decimal::zero() {
-        msg!("Obligation deposits have zero value");
-        return Err(LendingError::ObligationDepositsZero.into());
-    }
 
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
@@ -1136,6 +4452,40 @@ fn process_borrow_obligation_liquidity(
         return Err(LendingError::InvalidMarketAuthority.into());
     }
 
+    // The obligation was refreshed against the same reserve state in this slot,
+    // so its values can be updated for the new collateral in place, exactly as
+    // a refresh would
+    // @TODO: add lookup table https://git.io/JOCYq
+    let deposit_decimals = 10u64
+        .checked_pow(deposit_reserve.liquidity.mint_decimals as u32)
+        .ok_or(LendingError::MathOverflow)?;
+
+    let collateral = obligation.find_or_add_collateral_to_deposits(*deposit_reserve_info.key)?;
+    collateral.deposit(collateral_amount)?;
+    let market_value = deposit_reserve
+        .collateral_exchange_rate()?
+        .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
+        .try_mul(deposit_reserve.liquidity.market_price)?
+        .try_div(deposit_decimals)?;
+    let deposit_value = market_value.try_sub(collateral.market_value)?;
+    collateral.market_value = market_value;
+
+    let borrow_power_rate = deposit_reserve.config.borrow_power_rate()?;
+    let liquidation_threshold_rate = deposit_reserve.liquidation_threshold_rate()?;
+
+    obligation.deposited_value = obligation.deposited_value.try_add(deposit_value)?;
+    obligation.allowed_borrow_value = obligation
+        .allowed_borrow_value
+        .try_add(deposit_value.try_mul(borrow_power_rate)?)?;
+    obligation.unhealthy_borrow_value = obligation
+        .unhealthy_borrow_value
+        .try_add(deposit_value.try_mul(liquidation_threshold_rate)?)?;
+
+    if obligation.deposited_value == Decimal::zero() {
+        msg!("Obligation deposits have zero value");
+        return Err(LendingError::ObligationDepositsZero.into());
+    }
+
     let remaining_borrow_value = obligation.remaining_borrow_value()?;
     if remaining_borrow_value == Decimal::zero() {
         msg!("Remaining borrow value is zero");
@@ -1153,21 +4503,59 @@ fn process_borrow_obligation_liquidity(
         msg!("Borrow amount is too small to receive liquidity after fees");
         return Err(LendingError::BorrowTooSmall.into());
     }
+    assert_min_borrow_value(&lending_market, &obligation, &borrow_reserve, borrow_amount)?;
 
     if liquidity_amount == u64::MAX && receive_amount < slippage_limit {
         msg!("Received liquidity would be smaller than the desired slippage limit");
         return Err(LendingError::ExceededSlippage.into());
     }
 
+    let reserve_borrowed_amount = borrow_reserve
+        .liquidity
+        .borrowed_amount_wads
+        .try_add(borrow_amount)?;
+    if reserve_borrowed_amount > Decimal::from(borrow_reserve.config.borrow_limit) {
+        msg!("Reserve borrowed amount cannot exceed the reserve borrow limit");
+        return Err(LendingError::BorrowLimitExceeded.into());
+    }
+
+    // @TODO: add lookup table https://git.io/JOCYq
+    let borrow_decimals = 10u64
+        .checked_pow(borrow_reserve.liquidity.mint_decimals as u32)
+        .ok_or(LendingError::MathOverflow)?;
+    let borrowed_value = obligation.borrowed_value.try_add(
+        borrow_amount
+            .try_mul(borrow_reserve.liquidity.market_price)?
+            .try_div(borrow_decimals)?,
+    )?;
+    if borrowed_value > obligation.allowed_borrow_value {
+        msg!("Obligation borrowed value cannot exceed its allowed borrow value after the deposit");
+        return Err(LendingError::BorrowTooLarge.into());
+    }
+
+    let outflow_amount = receive_amount
+        .checked_add(borrow_fee)
+        .ok_or(LendingError::MathOverflow)?;
+    borrow_reserve.record_outflow(outflow_amount, clock.slot)?;
     borrow_reserve.liquidity.borrow(borrow_amount)?;
     borrow_reserve.last_update.mark_stale();
-    Reserve::pack(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
+    let cumulative_borrow_rate_wads = borrow_reserve.liquidity.cumulative_borrow_rate_wads;
+    Reserve::pack_account(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
 
     obligation
-        .find_or_add_liquidity_to_borrows(*borrow_reserve_info.key)?
+        .find_or_add_liquidity_to_borrows(*borrow_reserve_info.key, cumulative_borrow_rate_wads)?
         .borrow(borrow_amount)?;
     obligation.last_update.mark_stale();
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_collateral_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: collateral_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
 
     let mut owner_fee = borrow_fee;
     if let Ok(host_fee_receiver_info) = next_account_info(account_info_iter) {
@@ -1206,31 +4594,46 @@ fn process_borrow_obligation_liquidity(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::Borrow(BorrowEvent {
+        reserve: *borrow_reserve_info.key,
+        obligation: *obligation_info.key,
+        user: *obligation_owner_info.key,
+        liquidity_amount: receive_amount,
+        borrow_fee,
+    })
+    .emit();
+
     Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
-fn process_repay_obligation_liquidity(
+fn process_migrate_obligation_collateral(
     program_id: &Pubkey,
-    liquidity_amount: u64,
+    collateral_amount: u64,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    if liquidity_amount == 0 {
-        msg!("Liquidity amount provided cannot be zero");
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
         return Err(LendingError::InvalidAmount.into());
     }
 
     let account_info_iter = &mut accounts.iter();
-    let source_liquidity_info = next_account_info(account_info_iter)?;
-    let destination_liquidity_info = next_account_info(account_info_iter)?;
-    let repay_reserve_info = next_account_info(account_info_iter)?;
+    let source_reserve_info = next_account_info(account_info_iter)?;
+    let source_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let source_reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let source_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let destination_reserve_info = next_account_info(account_info_iter)?;
+    let destination_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let destination_reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let destination_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
     let obligation_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
-    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1239,30 +4642,96 @@ fn process_repay_obligation_liquidity(
         msg!("Lending market token program does not match the token program provided");
         return Err(LendingError::InvalidTokenProgram.into());
     }
+    if lending_market.frozen {
+        msg!("Lending market is frozen");
+        return Err(LendingError::MarketFrozen.into());
+    }
 
-    let mut repay_reserve = Reserve::unpack(&repay_reserve_info.data.borrow())?;
-    if repay_reserve_info.owner != program_id {
-        msg!("Repay reserve provided is not owned by the lending program");
+    if source_reserve_info.key == destination_reserve_info.key {
+        msg!("Source reserve cannot be used as the destination reserve");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let mut source_reserve = Reserve::unpack_account(&source_reserve_info.data.borrow())?;
+    if source_reserve_info.owner != program_id {
+        msg!("Source reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &repay_reserve.lending_market != lending_market_info.key {
-        msg!("Repay reserve lending market does not match the lending market provided");
+    if &source_reserve.lending_market != lending_market_info.key {
+        msg!("Source reserve lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &repay_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
-        msg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
+    if &source_reserve.collateral.supply_pubkey != source_reserve_collateral_supply_info.key {
+        msg!("Source reserve collateral supply does not match the source reserve collateral supply provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &repay_reserve.liquidity.supply_pubkey != destination_liquidity_info.key {
-        msg!("Repay reserve liquidity supply must be used as the destination liquidity provided");
+    if &source_reserve.collateral.mint_pubkey != source_reserve_collateral_mint_info.key {
+        msg!("Source reserve collateral mint does not match the source reserve collateral mint provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if repay_reserve.last_update.is_stale(clock.slot)? {
-        msg!("Repay reserve is stale and must be refreshed in the current slot");
+    if &source_reserve.liquidity.supply_pubkey != source_reserve_liquidity_supply_info.key {
+        msg!("Source reserve liquidity supply does not match the source reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if source_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Source reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if source_reserve.is_paused(RESERVE_PAUSE_WITHDRAWALS) {
+        msg!("Source reserve withdrawals are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if source_reserve.status == ReserveStatus::Obsolete {
+        msg!("Source reserve is obsolete and no longer allows redemptions");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+
+    let mut destination_reserve = Reserve::unpack_account(&destination_reserve_info.data.borrow())?;
+    if destination_reserve_info.owner != program_id {
+        msg!("Destination reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &destination_reserve.lending_market != lending_market_info.key {
+        msg!("Destination reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if destination_reserve.liquidity.mint_pubkey != source_reserve.liquidity.mint_pubkey {
+        msg!("Destination reserve liquidity mint does not match the source reserve liquidity mint");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &destination_reserve.liquidity.supply_pubkey != destination_reserve_liquidity_supply_info.key
+    {
+        msg!("Destination reserve liquidity supply does not match the destination reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &destination_reserve.collateral.mint_pubkey != destination_reserve_collateral_mint_info.key {
+        msg!("Destination reserve collateral mint does not match the destination reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &destination_reserve.collateral.supply_pubkey
+        != destination_reserve_collateral_supply_info.key
+    {
+        msg!("Destination reserve collateral supply does not match the destination reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if destination_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Destination reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
     }
+    if destination_reserve.is_paused(RESERVE_PAUSE_DEPOSITS) {
+        msg!("Destination reserve deposits are paused");
+        return Err(LendingError::ActionPaused.into());
+    }
+    if !destination_reserve.is_active() {
+        msg!("Destination reserve is deprecated and no longer accepts deposits");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+    if destination_reserve.config.loan_to_value_ratio == 0 {
+        msg!("Destination reserve has collateral disabled for borrowing");
+        return Err(LendingError::ReserveCollateralDisabled.into());
+    }
 
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    let mut obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
         msg!("Obligation provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1271,175 +4740,227 @@ fn process_repay_obligation_liquidity(
         msg!("Obligation lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
     if obligation.last_update.is_stale(clock.slot)? {
         msg!("Obligation is stale and must be refreshed in the current slot");
         return Err(LendingError::ObligationStale.into());
     }
 
-    let (liquidity, liquidity_index) =
-        obligation.find_liquidity_in_borrows(*repay_reserve_info.key)?;
-    if liquidity.borrowed_amount_wads == Decimal::zero() {
-        msg!("Liquidity borrowed amount is zero");
-        return Err(LendingError::ObligationLiquidityEmpty.into());
+    let (collateral, collateral_index) =
+        obligation.find_collateral_in_deposits(*source_reserve_info.key)?;
+    if collateral.deposited_amount == 0 {
+        msg!("Collateral deposited amount is zero");
+        return Err(LendingError::ObligationCollateralEmpty.into());
     }
 
-    let CalculateRepayResult {
-        settle_amount,
-        repay_amount,
-    } = repay_reserve.calculate_repay(liquidity_amount, liquidity.borrowed_amount_wads)?;
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
 
-    if repay_amount == 0 {
-        msg!("Repay amount is too small to transfer liquidity");
-        return Err(LendingError::RepayTooSmall.into());
+    let migrate_amount = if collateral_amount == u64::MAX {
+        collateral.deposited_amount
+    } else {
+        collateral.deposited_amount.min(collateral_amount)
+    };
+    let migrate_pct = Decimal::from(migrate_amount).try_div(collateral.deposited_amount)?;
+    let source_value = collateral.market_value.try_mul(migrate_pct)?;
+    let source_remaining_value = collateral.market_value.try_sub(source_value)?;
+    // the liquidation thresholds at the utilization the obligation was
+    // refreshed at, before the migration moves liquidity between reserves
+    let source_liquidation_threshold_rate = source_reserve.liquidation_threshold_rate()?;
+    let destination_liquidation_threshold_rate =
+        destination_reserve.liquidation_threshold_rate()?;
+
+    let liquidity_amount = source_reserve.redeem_collateral(migrate_amount)?;
+    if liquidity_amount == 0 {
+        msg!("Migrate amount is too small to redeem liquidity");
+        return Err(LendingError::WithdrawTooSmall.into());
     }
+    source_reserve.record_outflow(liquidity_amount, clock.slot)?;
 
-    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
-    repay_reserve.last_update.mark_stale();
-    Reserve::pack(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
+    let destination_liquidity_amount = destination_reserve
+        .liquidity
+        .total_supply()?
+        .try_add(Decimal::from(liquidity_amount))?;
+    if destination_liquidity_amount > Decimal::from(destination_reserve.config.deposit_limit) {
+        msg!("Destination reserve liquidity cannot exceed the reserve deposit limit");
+        return Err(LendingError::DepositLimitExceeded.into());
+    }
+    if liquidity_amount < destination_reserve.config.min_deposit_amount {
+        msg!("Migrated liquidity is below the destination reserve minimum deposit");
+        return Err(LendingError::DepositTooSmall.into());
+    }
+
+    let is_first_deposit = destination_reserve.collateral.mint_total_supply == 0;
+    let destination_collateral_amount = destination_reserve.deposit_liquidity(liquidity_amount)?;
+    if is_first_deposit && destination_collateral_amount < MIN_INITIAL_COLLATERAL_AMOUNT {
+        msg!(
+            "First deposit into the destination reserve must mint at least {} collateral",
+            MIN_INITIAL_COLLATERAL_AMOUNT
+        );
+        return Err(LendingError::DepositTooSmall.into());
+    }
+    if destination_collateral_amount == 0 {
+        msg!("Migrate amount is too small to mint destination collateral");
+        return Err(LendingError::WithdrawTooSmall.into());
+    }
+
+    // @TODO: add lookup table https://git.io/JOCYq
+    let decimals = 10u64
+        .checked_pow(destination_reserve.liquidity.mint_decimals as u32)
+        .ok_or(LendingError::MathOverflow)?;
+    let destination_value = destination_reserve
+        .collateral_exchange_rate()?
+        .decimal_collateral_to_liquidity(destination_collateral_amount.into())?
+        .try_mul(destination_reserve.liquidity.market_price)?
+        .try_div(decimals)?;
+
+    obligation.deposits[collateral_index].market_value = source_remaining_value;
+    obligation.withdraw(migrate_amount, collateral_index)?;
+    let destination_collateral =
+        obligation.find_or_add_collateral_to_deposits(*destination_reserve_info.key)?;
+    destination_collateral.deposit(destination_collateral_amount)?;
+    destination_collateral.market_value = destination_collateral
+        .market_value
+        .try_add(destination_value)?;
+    if destination_reserve.config.isolated
+        && obligation
+            .deposits
+            .iter()
+            .any(|collateral| collateral.deposit_reserve != *destination_reserve_info.key)
+    {
+        msg!("Isolated collateral cannot be combined with collateral from other reserves");
+        return Err(LendingError::IsolatedCollateral.into());
+    }
+
+    // Swap the migrated value over to the destination reserve rates rather
+    // than refreshing every deposit and borrow of the obligation
+    obligation.deposited_value = obligation
+        .deposited_value
+        .try_add(destination_value)?
+        .try_sub(source_value)?;
+    obligation.allowed_borrow_value = obligation
+        .allowed_borrow_value
+        .try_add(destination_value.try_mul(destination_reserve.config.borrow_power_rate()?)?)?
+        .try_sub(source_value.try_mul(source_reserve.config.borrow_power_rate()?)?)?;
+    obligation.unhealthy_borrow_value = obligation
+        .unhealthy_borrow_value
+        .try_add(destination_value.try_mul(destination_liquidation_threshold_rate)?)?
+        .try_sub(source_value.try_mul(source_liquidation_threshold_rate)?)?;
+    if !obligation.borrows.is_empty() && obligation.borrowed_value > obligation.allowed_borrow_value
+    {
+        msg!("Migration would leave the obligation borrowed value above its allowed borrow value");
+        return Err(LendingError::WithdrawTooLarge.into());
+    }
 
-    obligation.repay(settle_amount, liquidity_index)?;
     obligation.last_update.mark_stale();
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+    Obligation::pack_account(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    source_reserve.last_update.mark_stale();
+    Reserve::pack_account(source_reserve, &mut source_reserve_info.data.borrow_mut())?;
+
+    destination_reserve.last_update.mark_stale();
+    Reserve::pack_account(
+        destination_reserve,
+        &mut destination_reserve_info.data.borrow_mut(),
+    )?;
+
+    spl_token_burn(TokenBurnParams {
+        mint: source_reserve_collateral_mint_info.clone(),
+        source: source_reserve_collateral_supply_info.clone(),
+        amount: migrate_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
 
     spl_token_transfer(TokenTransferParams {
-        source: source_liquidity_info.clone(),
-        destination: destination_liquidity_info.clone(),
-        amount: repay_amount,
-        authority: user_transfer_authority_info.clone(),
-        authority_signer_seeds: &[],
+        source: source_reserve_liquidity_supply_info.clone(),
+        destination: destination_reserve_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_mint_to(TokenMintToParams {
+        mint: destination_reserve_collateral_mint_info.clone(),
+        destination: destination_reserve_collateral_supply_info.clone(),
+        amount: destination_collateral_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
         token_program: token_program_id.clone(),
     })?;
 
     Ok(())
 }
 
-#[inline(never)] // avoid stack frame limit
-fn process_liquidate_obligation(
-    program_id: &Pubkey,
-    liquidity_amount: u64,
-    accounts: &[AccountInfo],
-) -> ProgramResult {
-    if liquidity_amount == 0 {
-        msg!("Liquidity amount provided cannot be zero");
-        return Err(LendingError::InvalidAmount.into());
-    }
-
+fn process_close_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
-    let source_liquidity_info = next_account_info(account_info_iter)?;
-    let destination_collateral_info = next_account_info(account_info_iter)?;
-    let repay_reserve_info = next_account_info(account_info_iter)?;
-    let repay_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
-    let withdraw_reserve_info = next_account_info(account_info_iter)?;
-    let withdraw_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
-    let obligation_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
-    let user_transfer_authority_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
-    if lending_market_info.owner != program_id {
-        msg!("Lending market provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
-    }
-    if &lending_market.token_program_id != token_program_id.key {
-        msg!("Lending market token program does not match the token program provided");
-        return Err(LendingError::InvalidTokenProgram.into());
-    }
-
-    let mut repay_reserve = Reserve::unpack(&repay_reserve_info.data.borrow())?;
-    if repay_reserve_info.owner != program_id {
-        msg!("Repay reserve provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
-    }
-    if &repay_reserve.lending_market != lending_market_info.key {
-        msg!("Repay reserve lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &repay_reserve.liquidity.supply_pubkey != repay_reserve_liquidity_supply_info.key {
-        msg!("Repay reserve liquidity supply does not match the repay reserve liquidity supply provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &repay_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
-        msg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &repay_reserve.collateral.supply_pubkey == destination_collateral_info.key {
-        msg!(
-            "Repay reserve collateral supply cannot be used as the destination collateral provided"
-        );
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if repay_reserve.last_update.is_stale(clock.slot)? {
-        msg!("Repay reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
-    }
-
-    let withdraw_reserve = Reserve::unpack(&withdraw_reserve_info.data.borrow())?;
-    if withdraw_reserve_info.owner != program_id {
-        msg!("Withdraw reserve provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
-    }
-    if &withdraw_reserve.lending_market != lending_market_info.key {
-        msg!("Withdraw reserve lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &withdraw_reserve.collateral.supply_pubkey != withdraw_reserve_collateral_supply_info.key {
-        msg!("Withdraw reserve collateral supply does not match the withdraw reserve collateral supply provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &withdraw_reserve.liquidity.supply_pubkey == source_liquidity_info.key {
-        msg!("Withdraw reserve liquidity supply cannot be used as the source liquidity provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &withdraw_reserve.collateral.supply_pubkey == destination_collateral_info.key {
-        msg!("Withdraw reserve collateral supply cannot be used as the destination collateral provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if withdraw_reserve.last_update.is_stale(clock.slot)? {
-        msg!("Withdraw reserve is stale and must be refreshed in the current slot");
-        return Err(LendingError::ReserveStale.into());
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
 
-    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
-    if obligation_info.owner != program_id {
-        msg!("Obligation provided is not owned by the lending program");
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
-    if &obligation.lending_market != lending_market_info.key {
-        msg!("Obligation lending market does not match the lending market provided");
-        return Err(LendingError::InvalidAccountInput.into());
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
     }
-    if obligation.last_update.is_stale(clock.slot)? {
-        msg!("Obligation is stale and must be refreshed in the current slot");
-        return Err(LendingError::ObligationStale.into());
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
     }
-    if obligation.deposited_value == Decimal::zero() {
-        msg!("Obligation deposited value is zero");
-        return Err(LendingError::ObligationDepositsZero.into());
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
-    if obligation.borrowed_value == Decimal::zero() {
-        msg!("Obligation borrowed value is zero");
-        return Err(LendingError::ObligationBorrowsZero.into());
+
+    let reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
-    if obligation.borrowed_value < obligation.unhealthy_borrow_value {
-        msg!("Obligation is healthy and cannot be liquidated");
-        return Err(LendingError::ObligationHealthy.into());
+    if &reserve.collateral.mint_pubkey != reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
-
-    let (liquidity, liquidity_index) =
-        obligation.find_liquidity_in_borrows(*repay_reserve_info.key)?;
-    if liquidity.market_value == Decimal::zero() {
-        msg!("Obligation borrow value is zero");
-        return Err(LendingError::ObligationLiquidityEmpty.into());
+    if &reserve.liquidity.fee_receiver != reserve_liquidity_fee_receiver_info.key {
+        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
-
-    let (collateral, collateral_index) =
-        obligation.find_collateral_in_deposits(*withdraw_reserve_info.key)?;
-    if collateral.market_value == Decimal::zero() {
-        msg!("Obligation deposit value is zero");
-        return Err(LendingError::ObligationCollateralEmpty.into());
+    if reserve_info.key == destination_info.key {
+        msg!("Reserve cannot be used as the destination provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
 
     let authority_signer_seeds = &[
@@ -1455,91 +4976,112 @@ fn process_liquidate_obligation(
         return Err(LendingError::InvalidMarketAuthority.into());
     }
 
-    let CalculateLiquidationResult {
-        settle_amount,
-        repay_amount,
-        withdraw_amount,
-    } = withdraw_reserve.calculate_liquidation(
-        liquidity_amount,
-        &obligation,
-        liquidity,
-        collateral,
-    )?;
-
-    if repay_amount == 0 {
-        msg!("Liquidation is too small to transfer liquidity");
-        return Err(LendingError::LiquidationTooSmall.into());
+    if reserve.liquidity.available_amount != 0 {
+        msg!("Reserve available liquidity must be zero");
+        return Err(LendingError::ReserveNotEmpty.into());
     }
-    if withdraw_amount == 0 {
-        msg!("Liquidation is too small to receive collateral");
-        return Err(LendingError::LiquidationTooSmall.into());
+    if reserve.liquidity.borrowed_amount_wads != Decimal::zero() {
+        msg!("Reserve borrowed liquidity must be zero");
+        return Err(LendingError::ReserveNotEmpty.into());
+    }
+    // Obligation deposits are held in the reserve collateral supply, and a
+    // deposit is removed from the obligation once fully withdrawn, so with no
+    // collateral in circulation no obligation can still reference the reserve
+    // as collateral. Obligation borrows are ruled out by the borrowed amount.
+    let reserve_collateral_mint = unpack_mint(&reserve_collateral_mint_info.data.borrow())?;
+    if reserve.collateral.mint_total_supply != 0 || reserve_collateral_mint.supply != 0 {
+        msg!("Reserve collateral mint supply must be zero");
+        return Err(LendingError::ReserveNotEmpty.into());
+    }
+    let reserve_liquidity_fee_receiver =
+        unpack_token_account(&reserve_liquidity_fee_receiver_info.data.borrow())?;
+    if reserve_liquidity_fee_receiver.amount != 0 {
+        msg!("Reserve liquidity fee receiver must be empty");
+        return Err(LendingError::ReserveNotEmpty.into());
     }
 
-    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
-    repay_reserve.last_update.mark_stale();
-    Reserve::pack(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
-
-    obligation.repay(settle_amount, liquidity_index)?;
-    obligation.withdraw(withdraw_amount, collateral_index)?;
-    obligation.last_update.mark_stale();
-    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
-
-    spl_token_transfer(TokenTransferParams {
-        source: source_liquidity_info.clone(),
-        destination: repay_reserve_liquidity_supply_info.clone(),
-        amount: repay_amount,
-        authority: user_transfer_authority_info.clone(),
-        authority_signer_seeds: &[],
-        token_program: token_program_id.clone(),
-    })?;
-
-    spl_token_transfer(TokenTransferParams {
-        source: withdraw_reserve_collateral_supply_info.clone(),
-        destination: destination_collateral_info.clone(),
-        amount: withdraw_amount,
+    spl_token_set_authority(TokenSetAuthorityParams {
+        account: reserve_collateral_mint_info.clone(),
+        new_authority: None,
+        authority_type: AuthorityType::MintTokens,
         authority: lending_market_authority_info.clone(),
         authority_signer_seeds,
         token_program: token_program_id.clone(),
     })?;
 
+    let destination_lamports = destination_info
+        .lamports()
+        .checked_add(reserve_info.lamports())
+        .ok_or(LendingError::MathOverflow)?;
+    **destination_info.lamports.borrow_mut() = destination_lamports;
+    **reserve_info.lamports.borrow_mut() = 0;
+    reserve_info.data.borrow_mut().fill(0);
+
     Ok(())
 }
 
-#[inline(never)] // avoid stack frame limit
-fn process_flash_loan(
+fn process_withdraw_protocol_fees(
     program_id: &Pubkey,
-    liquidity_amount: u64,
+    amount: u64,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    if liquidity_amount == 0 {
-        msg!("Liquidity amount provided cannot be zero");
-        return Err(LendingError::InvalidAmount.into());
-    }
-
     let account_info_iter = &mut accounts.iter();
-    let source_liquidity_info = next_account_info(account_info_iter)?;
-    let destination_liquidity_info = next_account_info(account_info_iter)?;
     let reserve_info = next_account_info(account_info_iter)?;
     let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
-    let host_fee_receiver_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
     let token_program_id = next_account_info(account_info_iter)?;
-    let flash_loan_receiver_program_id = next_account_info(account_info_iter)?;
 
-    if program_id == flash_loan_receiver_program_id.key {
-        msg!("Lending program cannot be used as the flash loan receiver program provided");
-        return Err(LendingError::InvalidFlashLoanReceiverProgram.into());
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
     }
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
     if &lending_market.token_program_id != token_program_id.key {
         msg!("Lending market token program does not match the token program provided");
         return Err(LendingError::InvalidTokenProgram.into());
     }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    // only the reserve's own fee receiver may be drained, the lending market
+    // authority owns the supply accounts of every reserve in the market too
+    if &reserve.liquidity.fee_receiver != reserve_liquidity_fee_receiver_info.key {
+        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve_liquidity_fee_receiver_info.key == destination_liquidity_info.key {
+        msg!("Reserve liquidity fee receiver cannot be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if destination_liquidity_info.owner != token_program_id.key {
+        msg!("Destination liquidity is not owned by the lending market token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let destination_liquidity = unpack_token_account(&destination_liquidity_info.data.borrow())?;
+    if destination_liquidity.mint != reserve.liquidity.mint_pubkey {
+        msg!("Destination liquidity mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidTokenMint.into());
+    }
 
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
@@ -1554,155 +5096,56 @@ fn process_flash_loan(
         return Err(LendingError::InvalidMarketAuthority.into());
     }
 
-    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
-    if reserve_info.owner != program_id {
-        msg!("Reserve provided is not owned by the lending program");
-        return Err(LendingError::InvalidAccountOwner.into());
-    }
-    if &reserve.lending_market != lending_market_info.key {
-        msg!("Invalid reserve lending market account");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &reserve.liquidity.supply_pubkey != source_liquidity_info.key {
-        msg!("Reserve liquidity supply must be used as the source liquidity provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-    if &reserve.liquidity.fee_receiver != reserve_liquidity_fee_receiver_info.key {
-        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
-        return Err(LendingError::InvalidAccountInput.into());
-    }
-
-    // @FIXME: if u64::MAX is flash loaned, fees should be inclusive as with
-    // ordinary borrows
-    let flash_loan_amount = if liquidity_amount == u64::MAX {
-        reserve.liquidity.available_amount
+    let reserve_liquidity_fee_receiver =
+        unpack_token_account(&reserve_liquidity_fee_receiver_info.data.borrow())?;
+    let withdraw_amount = if amount == u64::MAX {
+        reserve_liquidity_fee_receiver.amount
     } else {
-        liquidity_amount
+        amount
     };
-
-    let flash_loan_amount_decimal = Decimal::from(flash_loan_amount);
-    let (origination_fee, host_fee) = reserve
-        .config
-        .fees
-        .calculate_flash_loan_fees(flash_loan_amount_decimal)?;
-
-    let balance_before_flash_loan = Account::unpack(&source_liquidity_info.data.borrow())?.amount;
-    let expected_balance_after_flash_loan = balance_before_flash_loan
-        .checked_add(origination_fee)
-        .ok_or(LendingError::MathOverflow)?;
-    let returned_amount_required = flash_loan_amount
-        .checked_add(origination_fee)
-        .ok_or(LendingError::MathOverflow)?;
-
-    let mut flash_loan_instruction_accounts = vec![
-        AccountMeta::new(*destination_liquidity_info.key, false),
-        AccountMeta::new(*source_liquidity_info.key, false),
-        AccountMeta::new_readonly(*token_program_id.key, false),
-    ];
-    let mut flash_loan_instruction_account_infos = vec![
-        destination_liquidity_info.clone(),
-        flash_loan_receiver_program_id.clone(),
-        source_liquidity_info.clone(),
-        token_program_id.clone(),
-    ];
-    for account_info in account_info_iter {
-        flash_loan_instruction_accounts.push(AccountMeta {
-            pubkey: *account_info.key,
-            is_signer: account_info.is_signer,
-            is_writable: account_info.is_writable,
-        });
-        flash_loan_instruction_account_infos.push(account_info.clone());
+    if withdraw_amount == 0 {
+        msg!("Protocol fee withdraw amount cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+    if withdraw_amount > reserve_liquidity_fee_receiver.amount {
+        msg!(
+            "Protocol fee withdraw amount {} exceeds the fee receiver balance of {}",
+            withdraw_amount,
+            reserve_liquidity_fee_receiver.amount
+        );
+        return Err(LendingError::InsufficientLiquidity.into());
     }
-
-    reserve.liquidity.borrow(flash_loan_amount_decimal)?;
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
     spl_token_transfer(TokenTransferParams {
-        source: source_liquidity_info.clone(),
+        source: reserve_liquidity_fee_receiver_info.clone(),
         destination: destination_liquidity_info.clone(),
-        amount: flash_loan_amount,
+        amount: withdraw_amount,
         authority: lending_market_authority_info.clone(),
         authority_signer_seeds,
         token_program: token_program_id.clone(),
-    })?;
-
-    const RECEIVE_FLASH_LOAN_INSTRUCTION_DATA_SIZE: usize = 9;
-    // @FIXME: don't use 0 to indicate a flash loan receiver instruction https://git.io/JGzz9
-    const RECEIVE_FLASH_LOAN_INSTRUCTION_TAG: u8 = 0u8;
-
-    let mut data = Vec::with_capacity(RECEIVE_FLASH_LOAN_INSTRUCTION_DATA_SIZE);
-    data.push(RECEIVE_FLASH_LOAN_INSTRUCTION_TAG);
-    data.extend_from_slice(&returned_amount_required.to_le_bytes());
-
-    invoke(
-        &Instruction {
-            program_id: *flash_loan_receiver_program_id.key,
-            accounts: flash_loan_instruction_accounts,
-            data,
-        },
-        &flash_loan_instruction_account_infos[..],
-    )?;
-
-    reserve = Reserve::unpack(&reserve_info.data.borrow())?;
-    reserve
-        .liquidity
-        .repay(flash_loan_amount, flash_loan_amount_decimal)?;
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
-
-    let actual_balance_after_flash_loan =
-        Account::unpack(&source_liquidity_info.data.borrow())?.amount;
-    if actual_balance_after_flash_loan < expected_balance_after_flash_loan {
-        msg!("Insufficient reserve liquidity after flash loan");
-        return Err(LendingError::NotEnoughLiquidityAfterFlashLoan.into());
-    }
-
-    let mut owner_fee = origination_fee;
-    if host_fee > 0 {
-        owner_fee = owner_fee
-            .checked_sub(host_fee)
-            .ok_or(LendingError::MathOverflow)?;
-        spl_token_transfer(TokenTransferParams {
-            source: source_liquidity_info.clone(),
-            destination: host_fee_receiver_info.clone(),
-            amount: host_fee,
-            authority: lending_market_authority_info.clone(),
-            authority_signer_seeds,
-            token_program: token_program_id.clone(),
-        })?;
-    }
-
-    if owner_fee > 0 {
-        spl_token_transfer(TokenTransferParams {
-            source: source_liquidity_info.clone(),
-            destination: reserve_liquidity_fee_receiver_info.clone(),
-            amount: owner_fee,
-            authority: lending_market_authority_info.clone(),
-            authority_signer_seeds,
-            token_program: token_program_id.clone(),
-        })?;
-    }
+    })?;
 
     Ok(())
 }
 
-fn process_modify_reserve_config(
+fn process_set_reserve_rewards(
     program_id: &Pubkey,
-    new_config: ReserveConfig,
+    rewards_per_slot: u64,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
-    new_config.validate()?;
-
-    let account_info_iter = &mut accounts.iter().peekable();
+    let account_info_iter = &mut accounts.iter();
     let reserve_info = next_account_info(account_info_iter)?;
+    let reward_vault_info = next_account_info(account_info_iter)?;
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
 
     if reserve_info.owner != program_id {
         msg!("Reserve provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1716,23 +5159,295 @@ fn process_modify_reserve_config(
         return Err(LendingError::InvalidSigner.into());
     }
 
-    let mut reserve = Reserve::unpack(&reserve_info.data.borrow_mut())?;
-    // Validate that the reserve account corresponds to the correct lending market,
-    // after validating above that the lending market and lending market owner
-    // correspond, to prevent one compromised lending market owner from changing
-    // configs on other lending markets
-    if reserve.lending_market != *lending_market_info.key {
-        msg!("Reserve account does not match the lending market");
+    let mut reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    // rewards at the previous rate are accrued by the refresh
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
 
-    reserve.config = new_config;
+    // the vault must belong to the reward authority of this reserve, which
+    // owns no other token accounts of the market
+    let (reward_authority_pubkey, authority_bump_seed) =
+        find_reward_authority_address(program_id, reserve_info.key);
+    if reward_vault_info.owner != &lending_market.token_program_id {
+        msg!("Reward vault is not owned by the lending market token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+    let reward_vault = unpack_token_account(&reward_vault_info.data.borrow())?;
+    if reward_vault.owner != reward_authority_pubkey {
+        msg!("Reward vault is not owned by the reserve reward authority");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+
+    reserve.reward_pool.vault = *reward_vault_info.key;
+    reserve.reward_pool.authority_bump_seed = authority_bump_seed;
+    reserve.reward_pool.rewards_per_slot = rewards_per_slot;
+    Reserve::pack_account(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn process_claim_reserve_rewards(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reward_checkpoint_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reward_vault_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let reward_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack_account(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let reserve = Reserve::unpack_account(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    if &reserve.reward_pool.vault != reward_vault_info.key {
+        msg!("Reserve reward vault does not match the reward vault provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reward_vault_info.key == destination_info.key {
+        msg!("Reserve reward vault cannot be used as the destination provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
 
-    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+    let obligation = Obligation::unpack_account(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    let deposited_amount = obligation
+        .deposits
+        .iter()
+        .find(|collateral| &collateral.deposit_reserve == reserve_info.key)
+        .map_or(0, |collateral| collateral.deposited_amount);
+
+    let (reward_checkpoint_pubkey, bump_seed) =
+        find_reward_checkpoint_address(program_id, obligation_info.key, reserve_info.key);
+    if &reward_checkpoint_pubkey != reward_checkpoint_info.key {
+        msg!("Reward checkpoint provided is not derived from the obligation and reserve");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if reward_checkpoint_info.owner != program_id {
+        let required_lamports = rent
+            .minimum_balance(RewardCheckpoint::LEN)
+            .saturating_sub(reward_checkpoint_info.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    reward_checkpoint_info.key,
+                    required_lamports,
+                ),
+                &[
+                    payer_info.clone(),
+                    reward_checkpoint_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+        let checkpoint_signer_seeds: &[&[u8]] = &[
+            REWARD_CHECKPOINT_SEED,
+            obligation_info.key.as_ref(),
+            reserve_info.key.as_ref(),
+            &[bump_seed],
+        ];
+        invoke_signed(
+            &system_instruction::allocate(reward_checkpoint_info.key, RewardCheckpoint::LEN as u64),
+            &[reward_checkpoint_info.clone(), system_program_info.clone()],
+            &[checkpoint_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(reward_checkpoint_info.key, program_id),
+            &[reward_checkpoint_info.clone(), system_program_info.clone()],
+            &[checkpoint_signer_seeds],
+        )?;
+
+        // rewards accrue from the first claim, earlier deposits are unknown
+        RewardCheckpoint::pack(
+            RewardCheckpoint::new(InitRewardCheckpointParams {
+                bump_seed,
+                obligation: *obligation_info.key,
+                reserve: *reserve_info.key,
+                cumulative_rewards_per_collateral: reserve
+                    .reward_pool
+                    .cumulative_rewards_per_collateral,
+                deposited_amount,
+            }),
+            &mut reward_checkpoint_info.data.borrow_mut(),
+        )?;
+        return Ok(());
+    }
+
+    let mut reward_checkpoint = RewardCheckpoint::unpack(&reward_checkpoint_info.data.borrow())?;
+    reward_checkpoint.accrue(
+        reserve.reward_pool.cumulative_rewards_per_collateral,
+        deposited_amount,
+    )?;
+
+    // rewards the vault cannot cover yet stay claimable
+    let claim_amount = if reward_checkpoint.unclaimed_amount > 0 {
+        let reward_vault = unpack_token_account(&reward_vault_info.data.borrow())?;
+        reward_checkpoint.unclaimed_amount.min(reward_vault.amount)
+    } else {
+        0
+    };
+    if claim_amount > 0 {
+        let authority_signer_seeds: &[&[u8]] = &[
+            REWARD_AUTHORITY_SEED,
+            reserve_info.key.as_ref(),
+            &[reserve.reward_pool.authority_bump_seed],
+        ];
+        let reward_authority_pubkey =
+            Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+        if &reward_authority_pubkey != reward_authority_info.key {
+            msg!("Derived reward authority does not match the reward authority provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        spl_token_transfer(TokenTransferParams {
+            source: reward_vault_info.clone(),
+            destination: destination_info.clone(),
+            amount: claim_amount,
+            authority: reward_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+        reward_checkpoint.unclaimed_amount -= claim_amount;
+    }
+
+    RewardCheckpoint::pack(
+        reward_checkpoint,
+        &mut reward_checkpoint_info.data.borrow_mut(),
+    )?;
+
+    Ok(())
+}
+
+/// Checks the obligation owner is on the borrow allowlist of a market with
+/// permissioned borrowing
+fn assert_borrower_allowed(
+    program_id: &Pubkey,
+    lending_market_pubkey: &Pubkey,
+    borrower: &Pubkey,
+    borrow_allowlist_entry_info: Option<&AccountInfo>,
+) -> ProgramResult {
+    let Some(borrow_allowlist_entry_info) = borrow_allowlist_entry_info else {
+        msg!("Borrow allowlist entry must be provided when the market has permissioned borrowing");
+        return Err(LendingError::BorrowerNotAllowed.into());
+    };
+    if borrow_allowlist_entry_info.owner != program_id {
+        msg!("Borrower is not on the borrow allowlist");
+        return Err(LendingError::BorrowerNotAllowed.into());
+    }
+    let borrow_allowlist_entry =
+        BorrowAllowlistEntry::unpack(&borrow_allowlist_entry_info.data.borrow())
+            .map_err(|_| LendingError::BorrowerNotAllowed)?;
+    if &borrow_allowlist_entry.lending_market != lending_market_pubkey
+        || &borrow_allowlist_entry.borrower != borrower
+    {
+        msg!("Borrow allowlist entry does not belong to the obligation owner");
+        return Err(LendingError::BorrowerNotAllowed.into());
+    }
+    Ok(())
+}
 
+/// Checks the obligation's borrows would be worth at least the market minimum
+/// borrow value after borrowing `borrow_amount` from the borrow reserve
+fn assert_min_borrow_value(
+    lending_market: &LendingMarket,
+    obligation: &Obligation,
+    borrow_reserve: &Reserve,
+    borrow_amount: Decimal,
+) -> ProgramResult {
+    let borrowed_value = obligation
+        .borrowed_market_value()?
+        .try_add(borrow_reserve.market_value(borrow_amount)?)?;
+    if borrowed_value < lending_market.min_borrow_value {
+        msg!(
+            "Obligation borrowed value {} would be below the market minimum borrow value {}",
+            borrowed_value,
+            lending_market.min_borrow_value
+        );
+        return Err(LendingError::BorrowTooSmall.into());
+    }
     Ok(())
 }
 
+/// Reads the deposit reserves of the obligation's collateral, all in order,
+/// and returns the ones holding isolated collateral
+fn next_isolated_deposit_reserves<'a, 'b: 'a, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    program_id: &Pubkey,
+    obligation: &Obligation,
+    account_info_iter: &mut I,
+) -> Result<Vec<Pubkey>, ProgramError> {
+    let mut isolated_deposit_reserves = vec![];
+    for (index, collateral) in obligation.deposits.iter().enumerate() {
+        let deposit_reserve_info = next_account_info(account_info_iter)?;
+        if deposit_reserve_info.owner != program_id {
+            msg!(
+                "Deposit reserve provided for collateral {} is not owned by the lending program",
+                index
+            );
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if collateral.deposit_reserve != *deposit_reserve_info.key {
+            msg!(
+                "Deposit reserve of collateral {} does not match the deposit reserve provided",
+                index
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        let deposit_reserve = Reserve::unpack_account(&deposit_reserve_info.data.borrow())?;
+        if deposit_reserve.config.isolated {
+            isolated_deposit_reserves.push(collateral.deposit_reserve);
+        }
+    }
+    Ok(isolated_deposit_reserves)
+}
+
 fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
     if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
         msg!(&rent.minimum_balance(account_info.data_len()).to_string());
@@ -1742,6 +5457,33 @@ fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult
     }
 }
 
+/// Resizes a program owned account, with the payer funding the rent of the new
+/// size. New bytes are zeroed.
+fn realloc_with_rent<'a>(
+    account_info: &AccountInfo<'a>,
+    new_len: usize,
+    payer_info: &AccountInfo<'a>,
+    rent: &Rent,
+    system_program_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    let required_lamports = rent.minimum_balance(new_len);
+    if account_info.lamports() < required_lamports {
+        invoke(
+            &system_instruction::transfer(
+                payer_info.key,
+                account_info.key,
+                required_lamports - account_info.lamports(),
+            ),
+            &[
+                payer_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    account_info.realloc(new_len, true)
+}
+
 fn assert_uninitialized<T: Pack + IsInitialized>(
     account_info: &AccountInfo,
 ) -> Result<T, ProgramError> {
@@ -1758,6 +5500,11 @@ fn unpack_mint(data: &[u8]) -> Result<Mint, LendingError> {
     Mint::unpack(data).map_err(|_| LendingError::InvalidTokenMint)
 }
 
+/// Unpacks a spl_token `Account`.
+fn unpack_token_account(data: &[u8]) -> Result<Account, LendingError> {
+    Account::unpack(data).map_err(|_| LendingError::InvalidTokenAccount)
+}
+
 fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[u8; 32], ProgramError> {
     const LEN: usize = 14;
     const KEY: &[u8; LEN] = b"quote_currency";
@@ -1800,9 +5547,79 @@ fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[u8;
     Err(LendingError::InvalidOracleConfig.into())
 }
 
-fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decimal, ProgramError> {
-    const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
+/// Validates the oracle accounts provided for a reserve and returns the oracle
+/// kind along with its current price and the slot it was published at. Pyth
+/// oracles pass the product and price accounts, while Switchboard oracles pass
+/// the aggregator account for both.
+fn get_reserve_oracle_price(
+    lending_market: &LendingMarket,
+    oracle_product_info: &AccountInfo,
+    oracle_price_info: &AccountInfo,
+    clock: &Clock,
+    config: &ReserveConfig,
+) -> Result<(OracleKind, Decimal, Slot), ProgramError> {
+    if switchboard::program::check_id(oracle_price_info.owner)
+        || switchboard::devnet_program::check_id(oracle_price_info.owner)
+    {
+        if oracle_product_info.key != oracle_price_info.key {
+            msg!("Switchboard aggregator must be provided as both oracle accounts");
+            return Err(LendingError::InvalidOracleConfig.into());
+        }
+        let (market_price, market_price_slot) =
+            get_switchboard_price(oracle_price_info, clock, config)?;
+        return Ok((OracleKind::Switchboard, market_price, market_price_slot));
+    }
+
+    if &lending_market.oracle_program_id != oracle_product_info.owner {
+        msg!("Pyth product account provided is not owned by the lending market oracle program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if &lending_market.oracle_program_id != oracle_price_info.owner {
+        msg!("Pyth price account provided is not owned by the lending market oracle program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let pyth_product_data = oracle_product_info.try_borrow_data()?;
+    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if pyth_product.magic != pyth::MAGIC {
+        msg!("Pyth product account provided is not a valid Pyth account");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.ver != pyth::VERSION_2 {
+        msg!("Pyth product account provided has a different version than expected");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.atype != pyth::AccountType::Product as u32 {
+        msg!("Pyth product account provided is not a valid Pyth product account");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let pyth_price_pubkey_bytes: &[u8; 32] = oracle_price_info
+        .key
+        .as_ref()
+        .try_into()
+        .map_err(|_| LendingError::InvalidAccountInput)?;
+    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
+        msg!("Pyth product price account does not match the Pyth price provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
+    if lending_market.quote_currency != quote_currency {
+        msg!("Lending market quote currency does not match the oracle quote currency");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let (market_price, market_price_slot) = get_pyth_price(oracle_price_info, clock, config)?;
+    Ok((OracleKind::Pyth, market_price, market_price_slot))
+}
 
+fn get_pyth_price(
+    pyth_price_info: &AccountInfo,
+    clock: &Clock,
+    config: &ReserveConfig,
+) -> Result<(Decimal, Slot), ProgramError> {
     let pyth_price_data = pyth_price_info.try_borrow_data()?;
     let pyth_price = pyth::load::<pyth::Price>(&pyth_price_data)
         .map_err(|_| ProgramError::InvalidAccountData)?;
@@ -1821,7 +5638,7 @@ fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decima
         .slot
         .checked_sub(pyth_price.valid_slot)
         .ok_or(LendingError::MathOverflow)?;
-    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
+    if slots_elapsed >= config.max_oracle_age_slots(OracleKind::Pyth) {
         msg!("Oracle price is stale");
         return Err(LendingError::InvalidOracleConfig.into());
     }
@@ -1830,6 +5647,16 @@ fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decima
         msg!("Oracle price cannot be negative");
         LendingError::InvalidOracleConfig
     })?;
+    if price == 0 {
+        msg!("Oracle price cannot be zero");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    // the confidence interval shares the exponent of the price
+    validate_price_confidence(
+        price.into(),
+        pyth_price.agg.conf.into(),
+        config.max_confidence_interval_bps,
+    )?;
 
     let market_price = if pyth_price.expo >= 0 {
         let exponent = pyth_price
@@ -1853,7 +5680,117 @@ fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decima
         Decimal::from(price).try_div(decimals)?
     };
 
-    Ok(market_price)
+    Ok((market_price, pyth_price.valid_slot))
+}
+
+fn get_switchboard_price(
+    switchboard_aggregator_info: &AccountInfo,
+    clock: &Clock,
+    config: &ReserveConfig,
+) -> Result<(Decimal, Slot), ProgramError> {
+    let aggregator_data = switchboard_aggregator_info.try_borrow_data()?;
+    if aggregator_data.len() < size_of::<switchboard::Aggregator>() {
+        msg!("Switchboard aggregator account provided is too small");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let aggregator = switchboard::load::<switchboard::Aggregator>(&aggregator_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    if aggregator.discriminator != switchboard::AGGREGATOR_DISCRIMINATOR {
+        msg!("Switchboard aggregator account provided is not a valid Switchboard aggregator");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let round = aggregator.latest_confirmed_round;
+    if round.num_success == 0 {
+        msg!("Switchboard aggregator has no confirmed round result");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let slots_elapsed = clock
+        .slot
+        .checked_sub(round.round_open_slot)
+        .ok_or(LendingError::MathOverflow)?;
+    if slots_elapsed >= config.max_oracle_age_slots(OracleKind::Switchboard) {
+        msg!("Oracle price is stale");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let result = round.result;
+    let mantissa: u128 = result.mantissa.try_into().map_err(|_| {
+        msg!("Oracle price cannot be negative");
+        LendingError::InvalidOracleConfig
+    })?;
+    if mantissa == 0 {
+        msg!("Oracle price cannot be zero");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let scaled_val = get_switchboard_scaled_val(mantissa, result.scale)?;
+    // the standard deviation of the oracle responses is the confidence interval
+    let std_deviation = round.std_deviation;
+    let std_deviation_scaled_val =
+        get_switchboard_scaled_val(std_deviation.mantissa.unsigned_abs(), std_deviation.scale)?;
+    validate_price_confidence(
+        scaled_val,
+        std_deviation_scaled_val,
+        config.max_confidence_interval_bps,
+    )?;
+
+    Ok((Decimal::from_scaled_val(scaled_val), round.round_open_slot))
+}
+
+/// Switchboard decimals are `mantissa * 10^-scale`, rescale to WAD precision
+fn get_switchboard_scaled_val(mantissa: u128, scale: u32) -> Result<u128, ProgramError> {
+    let scale = scale as usize;
+    let scaled_val = if scale <= SCALE {
+        let exponent = (SCALE - scale)
+            .try_into()
+            .map_err(|_| LendingError::MathOverflow)?;
+        10u128
+            .checked_pow(exponent)
+            .and_then(|zeros| mantissa.checked_mul(zeros))
+    } else {
+        let exponent = (scale - SCALE)
+            .try_into()
+            .map_err(|_| LendingError::MathOverflow)?;
+        10u128
+            .checked_pow(exponent)
+            .and_then(|decimals| mantissa.checked_div(decimals))
+    }
+    .ok_or(LendingError::MathOverflow)?;
+
+    Ok(scaled_val)
+}
+
+/// Rejects oracle prices whose confidence interval is wider than the given
+/// basis points of the price. Both values must share the same scale.
+fn validate_price_confidence(
+    price: u128,
+    confidence: u128,
+    max_confidence_interval_bps: u16,
+) -> ProgramResult {
+    const BPS_SCALER: u128 = 10_000;
+
+    if max_confidence_interval_bps == 0 {
+        return Ok(());
+    }
+    let max_confidence = price
+        .checked_mul(max_confidence_interval_bps.into())
+        .ok_or(LendingError::MathOverflow)?;
+    let confidence = confidence
+        .checked_mul(BPS_SCALER)
+        .ok_or(LendingError::MathOverflow)?;
+    if confidence > max_confidence {
+        msg!(
+            "Oracle price confidence interval of {} bps exceeds the max confidence interval of {} bps",
+            confidence.div_ceil(price),
+            max_confidence_interval_bps
+        );
+        return Err(LendingError::PriceConfidenceTooWide.into());
+    }
+
+    Ok(())
 }
 
 /// Issue a spl_token `InitializeAccount` instruction.
@@ -1988,6 +5925,32 @@ fn spl_token_burn(params: TokenBurnParams<'_, '_>) -> ProgramResult {
     result.map_err(|_| LendingError::TokenBurnFailed.into())
 }
 
+/// Issue a spl_token `SetAuthority` instruction.
+#[inline(always)]
+fn spl_token_set_authority(params: TokenSetAuthorityParams<'_, '_>) -> ProgramResult {
+    let TokenSetAuthorityParams {
+        account,
+        new_authority,
+        authority_type,
+        authority,
+        token_program,
+        authority_signer_seeds,
+    } = params;
+    let result = invoke_optionally_signed(
+        &spl_token::instruction::set_authority(
+            token_program.key,
+            account.key,
+            new_authority,
+            authority_type,
+            authority.key,
+            &[],
+        )?,
+        &[account, authority, token_program],
+        authority_signer_seeds,
+    );
+    result.map_err(|_| LendingError::TokenSetAuthorityFailed.into())
+}
+
 struct TokenInitializeMintParams<'a: 'b, 'b> {
     mint: AccountInfo<'a>,
     rent: AccountInfo<'a>,
@@ -2031,6 +5994,15 @@ struct TokenBurnParams<'a: 'b, 'b> {
     token_program: AccountInfo<'a>,
 }
 
+struct TokenSetAuthorityParams<'a: 'b, 'b> {
+    account: AccountInfo<'a>,
+    new_authority: Option<&'b Pubkey>,
+    authority_type: AuthorityType,
+    authority: AccountInfo<'a>,
+    authority_signer_seeds: &'b [&'b [u8]],
+    token_program: AccountInfo<'a>,
+}
+
 impl PrintProgramError for LendingError {
     fn print<E>(&self)
     where
@@ -14,6 +14,10 @@ pub const PROD_ACCT_SIZE: usize = 512;
 pub const PROD_HDR_SIZE: usize = 48;
 pub const PROD_ATTR_SIZE: usize = PROD_ACCT_SIZE - PROD_HDR_SIZE;
 
+/// Slots after which a price is stale, unless the reserve sets
+/// `max_oracle_age_slots`
+pub const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct AccKey {
@@ -0,0 +1,100 @@
+use {
+    super::*,
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        msg,
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::{Pubkey, PUBKEY_BYTES},
+    },
+};
+
+/// Seed prefix of borrow allowlist entry addresses
+pub const BORROW_ALLOWLIST_SEED: &[u8] = b"borrow_allowlist";
+
+/// Borrow allowlist entry state, allowing an obligation owner to borrow from a
+/// lending market with permissioned borrowing. The entry exists while the
+/// owner is listed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BorrowAllowlistEntry {
+    /// Version of the struct
+    pub version: u8,
+    /// Bump seed for the entry address
+    pub bump_seed: u8,
+    /// Lending market address
+    pub lending_market: Pubkey,
+    /// Obligation owner allowed to borrow
+    pub borrower: Pubkey,
+}
+
+impl BorrowAllowlistEntry {
+    /// Create a new borrow allowlist entry
+    pub fn new(lending_market: Pubkey, borrower: Pubkey, bump_seed: u8) -> Self {
+        Self {
+            version: PROGRAM_VERSION,
+            bump_seed,
+            lending_market,
+            borrower,
+        }
+    }
+}
+
+/// Derives the address of the borrow allowlist entry of a borrower
+pub fn find_borrow_allowlist_entry_address(
+    program_id: &Pubkey,
+    lending_market: &Pubkey,
+    borrower: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            BORROW_ALLOWLIST_SEED,
+            lending_market.as_ref(),
+            borrower.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+impl Sealed for BorrowAllowlistEntry {}
+impl IsInitialized for BorrowAllowlistEntry {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const BORROW_ALLOWLIST_ENTRY_LEN: usize = 130; // 1 + 1 + 32 + 32 + 64
+impl Pack for BorrowAllowlistEntry {
+    const LEN: usize = BORROW_ALLOWLIST_ENTRY_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, BORROW_ALLOWLIST_ENTRY_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, bump_seed, lending_market, borrower, _padding) =
+            mut_array_refs![output, 1, 1, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+
+        *version = self.version.to_le_bytes();
+        *bump_seed = self.bump_seed.to_le_bytes();
+        lending_market.copy_from_slice(self.lending_market.as_ref());
+        borrower.copy_from_slice(self.borrower.as_ref());
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, BORROW_ALLOWLIST_ENTRY_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, bump_seed, lending_market, borrower, _padding) =
+            array_refs![input, 1, 1, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Borrow allowlist entry version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            lending_market: Pubkey::new_from_array(*lending_market),
+            borrower: Pubkey::new_from_array(*borrower),
+        })
+    }
+}
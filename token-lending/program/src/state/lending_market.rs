@@ -1,7 +1,9 @@
 use {
     super::*,
+    crate::{error::LendingError, math::Decimal},
     arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
     solana_program::{
+        entrypoint::ProgramResult,
         msg,
         program_error::ProgramError,
         program_pack::{IsInitialized, Pack, Sealed},
@@ -27,6 +29,28 @@ pub struct LendingMarket {
     pub token_program_id: Pubkey,
     /// Oracle (Pyth) program id
     pub oracle_program_id: Pubkey,
+    /// Owner proposed by the current owner, who must accept to become the
+    /// owner. Default pubkey if no transfer is pending
+    pub pending_owner: Pubkey,
+    /// Risk authority which can freeze, but not unfreeze, the market. Default
+    /// pubkey if not set
+    pub emergency_authority: Pubkey,
+    /// Halts deposits, borrows, collateral withdrawals and flash loans across
+    /// every reserve of the market, while repays and liquidations still work
+    pub frozen: bool,
+    /// Only obligation owners on the market borrow allowlist can borrow,
+    /// deposits and redemptions stay permissionless
+    pub permissioned_borrowing: bool,
+    /// Minimum market value of an obligation's borrows after a borrow, in the
+    /// quote currency. Zero disables the minimum.
+    pub min_borrow_value: Decimal,
+    /// Obligations with a borrowed value below this, in the quote currency,
+    /// can have a whole borrow liquidated at once regardless of the close
+    /// factor. Zero disables full-close liquidations.
+    pub dust_liquidation_threshold: Decimal,
+    /// Risk authority which can tighten, but not loosen, reserve configs and
+    /// pause flags. Default pubkey if not set
+    pub risk_authority: Pubkey,
 }
 
 impl LendingMarket {
@@ -45,6 +69,84 @@ impl LendingMarket {
         self.quote_currency = params.quote_currency;
         self.token_program_id = params.token_program_id;
         self.oracle_program_id = params.oracle_program_id;
+        self.pending_owner = Pubkey::default();
+        self.emergency_authority = Pubkey::default();
+        self.frozen = false;
+        self.permissioned_borrowing = false;
+        self.min_borrow_value = Decimal::zero();
+        self.dust_liquidation_threshold = Decimal::zero();
+        self.risk_authority = Pubkey::default();
+    }
+
+    /// Check if the key is the market's risk authority, when one is set
+    pub fn is_risk_authority(&self, key: &Pubkey) -> bool {
+        self.risk_authority != Pubkey::default() && &self.risk_authority == key
+    }
+
+    /// Unpacks a lending market account in either the V1 or the V2 layout,
+    /// told apart by the version. V1 markets are migrated in memory, with
+    /// defaults for the fields added since.
+    pub fn unpack_account(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != LENDING_MARKET_V1_LEN && src.len() != LENDING_MARKET_LEN {
+            msg!("Lending market account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let lending_market = Self::unpack_from_slice(src)?;
+        if !lending_market.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if src.len() != Self::account_len(lending_market.version) {
+            msg!("Lending market account size does not match its version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(lending_market)
+    }
+
+    /// Packs a lending market into its account. A V1 account is written in the
+    /// V1 layout at the last V1 version, fields added since need the account
+    /// migrated first.
+    pub fn pack_account(mut src: Self, dst: &mut [u8]) -> ProgramResult {
+        match dst.len() {
+            LENDING_MARKET_V1_LEN => {
+                if src.risk_authority != Pubkey::default() {
+                    msg!("Lending market must be migrated to the current layout to set a risk authority");
+                    return Err(LendingError::AccountNotMigrated.into());
+                }
+                src.version = src.version.min(LENDING_MARKET_V2_VERSION - 1);
+            }
+            LENDING_MARKET_LEN => {}
+            _ => {
+                msg!("Lending market account size is invalid");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
+    /// Unpacks a lending market account still in the V1 layout, to be migrated
+    /// to the V2 layout. Fails if the account is already in the V2 layout.
+    pub fn unpack_legacy_account(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == LENDING_MARKET_LEN {
+            msg!("Lending market is already in the current layout");
+            return Err(LendingError::AccountAlreadyMigrated.into());
+        }
+        Self::unpack_account(src)
+    }
+
+    /// Migrate to the current version, once the account is resized to
+    /// `LendingMarket::LEN`
+    pub fn migrate(&mut self) {
+        self.version = PROGRAM_VERSION;
+    }
+
+    /// Size of a lending market account at the given version
+    pub fn account_len(version: u8) -> usize {
+        if version < LENDING_MARKET_V2_VERSION {
+            LENDING_MARKET_V1_LEN
+        } else {
+            LENDING_MARKET_LEN
+        }
     }
 }
 
@@ -72,12 +174,29 @@ impl IsInitialized for LendingMarket {
     }
 }
 
-const LENDING_MARKET_LEN: usize = 258; // 1 + 1 + 32 + 32 + 32 + 32 + 128
+/// First lending market version storing the emergency authority and frozen flag
+const LENDING_MARKET_FREEZE_VERSION: u8 = 3;
+
+/// First lending market version storing the permissioned borrowing flag
+const LENDING_MARKET_PERMISSIONED_BORROWING_VERSION: u8 = 4;
+
+/// First lending market version in the V2 layout, which extends the V1 layout
+/// with room for fields that no longer fit its padding
+const LENDING_MARKET_V2_VERSION: u8 = 5;
+
+/// Size of a lending market account in the V1 layout
+pub const LENDING_MARKET_V1_LEN: usize = 258; // 1 + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 1 + 16 + 16 + 30
+const LENDING_MARKET_LEN: usize = 386; // LENDING_MARKET_V1_LEN + 128
+/// Size of the fields the V2 layout adds after the V1 layout
+const LENDING_MARKET_V2_EXTENSION_LEN: usize = 128; // 32 + 96
 impl Pack for LendingMarket {
     const LEN: usize = LENDING_MARKET_LEN;
 
+    /// Packs into `output`, writing the V2 extension only when the slice is
+    /// in the V2 layout
     fn pack_into_slice(&self, output: &mut [u8]) {
-        let output = array_mut_ref![output, 0, LENDING_MARKET_LEN];
+        let (output, extension) = output.split_at_mut(LENDING_MARKET_V1_LEN);
+        let output = array_mut_ref![output, 0, LENDING_MARKET_V1_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -86,6 +205,12 @@ impl Pack for LendingMarket {
             quote_currency,
             token_program_id,
             oracle_program_id,
+            pending_owner,
+            emergency_authority,
+            frozen,
+            permissioned_borrowing,
+            min_borrow_value,
+            dust_liquidation_threshold,
             _padding,
         ) = mut_array_refs![
             output,
@@ -95,7 +220,13 @@ impl Pack for LendingMarket {
             32,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            16,
+            16,
+            30
         ];
 
         *version = self.version.to_le_bytes();
@@ -104,12 +235,27 @@ impl Pack for LendingMarket {
         quote_currency.copy_from_slice(self.quote_currency.as_ref());
         token_program_id.copy_from_slice(self.token_program_id.as_ref());
         oracle_program_id.copy_from_slice(self.oracle_program_id.as_ref());
+        pending_owner.copy_from_slice(self.pending_owner.as_ref());
+        emergency_authority.copy_from_slice(self.emergency_authority.as_ref());
+        pack_bool(self.frozen, frozen);
+        pack_bool(self.permissioned_borrowing, permissioned_borrowing);
+        pack_decimal(self.min_borrow_value, min_borrow_value);
+        pack_decimal(self.dust_liquidation_threshold, dust_liquidation_threshold);
+
+        if extension.is_empty() {
+            return;
+        }
+        let extension = array_mut_ref![extension, 0, LENDING_MARKET_V2_EXTENSION_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (risk_authority, _padding) = mut_array_refs![extension, PUBKEY_BYTES, 96];
+        risk_authority.copy_from_slice(self.risk_authority.as_ref());
     }
 
     /// Unpacks a byte buffer into a
     /// [LendingMarketInfo](struct.LendingMarketInfo.html)
     fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![input, 0, LENDING_MARKET_LEN];
+        let (input, extension) = input.split_at(LENDING_MARKET_V1_LEN);
+        let input = array_ref![input, 0, LENDING_MARKET_V1_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -118,6 +264,12 @@ impl Pack for LendingMarket {
             quote_currency,
             token_program_id,
             oracle_program_id,
+            pending_owner,
+            emergency_authority,
+            frozen,
+            permissioned_borrowing,
+            min_borrow_value,
+            dust_liquidation_threshold,
             _padding,
         ) = array_refs![
             input,
@@ -127,7 +279,13 @@ impl Pack for LendingMarket {
             32,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            16,
+            16,
+            30
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -136,6 +294,37 @@ impl Pack for LendingMarket {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // older markets didn't store these fields, so their padding is ignored
+        let (emergency_authority, frozen) = if version < LENDING_MARKET_FREEZE_VERSION {
+            (Pubkey::default(), false)
+        } else {
+            (
+                Pubkey::new_from_array(*emergency_authority),
+                unpack_bool(frozen)?,
+            )
+        };
+        // the min borrow value and dust liquidation threshold are stored
+        // alongside the permissioned borrowing flag, in padding no earlier
+        // version wrote
+        let (permissioned_borrowing, min_borrow_value, dust_liquidation_threshold) =
+            if version < LENDING_MARKET_PERMISSIONED_BORROWING_VERSION {
+                (false, Decimal::zero(), Decimal::zero())
+            } else {
+                (
+                    unpack_bool(permissioned_borrowing)?,
+                    unpack_decimal(min_borrow_value),
+                    unpack_decimal(dust_liquidation_threshold),
+                )
+            };
+
+        let mut risk_authority = Pubkey::default();
+        if !extension.is_empty() {
+            let extension = array_ref![extension, 0, LENDING_MARKET_V2_EXTENSION_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (risk_authority_bytes, _padding) = array_refs![extension, PUBKEY_BYTES, 96];
+            risk_authority = Pubkey::new_from_array(*risk_authority_bytes);
+        }
+
         Ok(Self {
             version,
             bump_seed: u8::from_le_bytes(*bump_seed),
@@ -143,6 +332,203 @@ impl Pack for LendingMarket {
             quote_currency: *quote_currency,
             token_program_id: Pubkey::new_from_array(*token_program_id),
             oracle_program_id: Pubkey::new_from_array(*oracle_program_id),
+            pending_owner: Pubkey::new_from_array(*pending_owner),
+            emergency_authority,
+            frozen,
+            permissioned_borrowing,
+            min_borrow_value,
+            dust_liquidation_threshold,
+            risk_authority,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_and_unpack_frozen_lending_market() {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            bump_seed: 255,
+            owner: Pubkey::new_unique(),
+            pending_owner: Pubkey::new_unique(),
+            emergency_authority: Pubkey::new_unique(),
+            frozen: true,
+            ..LendingMarket::default()
+        };
+
+        let mut packed = [0u8; LENDING_MARKET_LEN];
+        LendingMarket::pack(lending_market.clone(), &mut packed).unwrap();
+        assert_eq!(LendingMarket::unpack(&packed).unwrap(), lending_market);
+    }
+
+    #[test]
+    fn unpack_lending_market_before_freeze_version() {
+        let lending_market = LendingMarket {
+            version: LENDING_MARKET_FREEZE_VERSION - 1,
+            owner: Pubkey::new_unique(),
+            emergency_authority: Pubkey::new_unique(),
+            frozen: true,
+            ..LendingMarket::default()
+        };
+
+        // older markets may hold arbitrary bytes where the new fields are stored
+        let mut packed = [0u8; LENDING_MARKET_LEN];
+        LendingMarket::pack(lending_market.clone(), &mut packed).unwrap();
+        packed[LENDING_MARKET_V1_LEN - 64] = 2;
+
+        let unpacked = LendingMarket::unpack(&packed).unwrap();
+        assert_eq!(unpacked.owner, lending_market.owner);
+        assert_eq!(unpacked.emergency_authority, Pubkey::default());
+        assert!(!unpacked.frozen);
+    }
+
+    #[test]
+    fn pack_and_unpack_permissioned_lending_market() {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            owner: Pubkey::new_unique(),
+            permissioned_borrowing: true,
+            ..LendingMarket::default()
+        };
+
+        let mut packed = [0u8; LENDING_MARKET_LEN];
+        LendingMarket::pack(lending_market.clone(), &mut packed).unwrap();
+        assert_eq!(LendingMarket::unpack(&packed).unwrap(), lending_market);
+
+        // markets upgraded from the freeze version may hold arbitrary bytes
+        // where the flag is stored
+        let lending_market = LendingMarket {
+            version: LENDING_MARKET_PERMISSIONED_BORROWING_VERSION - 1,
+            frozen: true,
+            ..lending_market
+        };
+        LendingMarket::pack(lending_market, &mut packed).unwrap();
+        packed[LENDING_MARKET_V1_LEN - 63] = 2;
+
+        let unpacked = LendingMarket::unpack(&packed).unwrap();
+        assert!(unpacked.frozen);
+        assert!(!unpacked.permissioned_borrowing);
+    }
+
+    #[test]
+    fn pack_and_unpack_min_borrow_value() {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            owner: Pubkey::new_unique(),
+            min_borrow_value: Decimal::from(10u64),
+            dust_liquidation_threshold: Decimal::from(1u64),
+            ..LendingMarket::default()
+        };
+
+        let mut packed = [0u8; LENDING_MARKET_LEN];
+        LendingMarket::pack(lending_market.clone(), &mut packed).unwrap();
+        assert_eq!(LendingMarket::unpack(&packed).unwrap(), lending_market);
+
+        // markets from before the permissioned borrowing version ignore it
+        let lending_market = LendingMarket {
+            version: LENDING_MARKET_PERMISSIONED_BORROWING_VERSION - 1,
+            ..lending_market
+        };
+        LendingMarket::pack(lending_market, &mut packed).unwrap();
+        let unpacked = LendingMarket::unpack(&packed).unwrap();
+        assert_eq!(unpacked.min_borrow_value, Decimal::zero());
+        assert_eq!(unpacked.dust_liquidation_threshold, Decimal::zero());
+    }
+
+    #[test]
+    fn pack_and_unpack_v1_lending_market_account() {
+        let lending_market = LendingMarket {
+            version: LENDING_MARKET_V2_VERSION - 1,
+            owner: Pubkey::new_unique(),
+            min_borrow_value: Decimal::from(10u64),
+            ..LendingMarket::default()
+        };
+
+        let mut packed = vec![0u8; LENDING_MARKET_V1_LEN];
+        LendingMarket::pack_account(lending_market.clone(), &mut packed).unwrap();
+        assert_eq!(
+            LendingMarket::unpack_account(&packed).unwrap(),
+            lending_market
+        );
+
+        // writes to a V1 account keep the last V1 version until it's migrated
+        let upgraded = LendingMarket {
+            version: PROGRAM_VERSION,
+            ..lending_market.clone()
+        };
+        LendingMarket::pack_account(upgraded, &mut packed).unwrap();
+        assert_eq!(
+            LendingMarket::unpack_account(&packed).unwrap(),
+            lending_market
+        );
+
+        let mut migrated = LendingMarket::unpack_legacy_account(&packed).unwrap();
+        migrated.migrate();
+        let mut resized = packed.clone();
+        resized.resize(LendingMarket::LEN, 0);
+        LendingMarket::pack_account(migrated.clone(), &mut resized).unwrap();
+        assert_eq!(LendingMarket::unpack_account(&resized).unwrap(), migrated);
+        assert_eq!(migrated.version, PROGRAM_VERSION);
+        assert_eq!(migrated.min_borrow_value, lending_market.min_borrow_value);
+
+        assert_eq!(
+            LendingMarket::unpack_legacy_account(&resized),
+            Err(LendingError::AccountAlreadyMigrated.into())
+        );
+    }
+
+    #[test]
+    fn pack_and_unpack_risk_authority() {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            owner: Pubkey::new_unique(),
+            risk_authority: Pubkey::new_unique(),
+            ..LendingMarket::default()
+        };
+
+        let mut packed = vec![0u8; LendingMarket::LEN];
+        LendingMarket::pack_account(lending_market.clone(), &mut packed).unwrap();
+        assert_eq!(
+            LendingMarket::unpack_account(&packed).unwrap(),
+            lending_market
+        );
+        assert!(lending_market.is_risk_authority(&lending_market.risk_authority));
+        assert!(!LendingMarket::default().is_risk_authority(&Pubkey::default()));
+
+        // V1 accounts have no room for the risk authority
+        let mut packed = vec![0u8; LENDING_MARKET_V1_LEN];
+        assert_eq!(
+            LendingMarket::pack_account(lending_market, &mut packed),
+            Err(LendingError::AccountNotMigrated.into())
+        );
+    }
+
+    #[test]
+    fn unpack_lending_market_account_of_wrong_size() {
+        let lending_market = LendingMarket {
+            version: PROGRAM_VERSION,
+            ..LendingMarket::default()
+        };
+
+        // a V2 version can't be read from a V1 sized account
+        let mut packed = vec![0u8; LENDING_MARKET_V1_LEN];
+        lending_market.pack_into_slice(&mut packed);
+        assert_eq!(
+            LendingMarket::unpack_account(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut packed = vec![0u8; LENDING_MARKET_V1_LEN + 1];
+        assert_eq!(
+            LendingMarket::pack_account(lending_market, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            LendingMarket::unpack_account(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use {
+    super::*,
+    crate::error::LendingError,
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        entrypoint::ProgramResult,
+        msg,
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::{Pubkey, PUBKEY_BYTES},
+    },
+};
+
+/// Seed prefix of market metadata addresses
+pub const MARKET_METADATA_SEED: &[u8] = b"metadata";
+
+/// Maximum length of a market name in bytes
+pub const MAX_MARKET_NAME_LEN: usize = 64;
+
+/// Maximum length of a market description URI in bytes
+pub const MAX_MARKET_DESCRIPTION_URI_LEN: usize = 200;
+
+/// Lending market display metadata for front ends. The account is sized to
+/// its strings and resized whenever the market owner updates them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarketMetadata {
+    /// Version of the struct
+    pub version: u8,
+    /// Bump seed for the metadata address
+    pub bump_seed: u8,
+    /// Lending market address
+    pub lending_market: Pubkey,
+    /// Flags reserved for future use
+    pub flags: u8,
+    /// Display name of the market, UTF-8 of at most MAX_MARKET_NAME_LEN bytes
+    pub name: String,
+    /// URI of the market description, UTF-8 of at most
+    /// MAX_MARKET_DESCRIPTION_URI_LEN bytes
+    pub description_uri: String,
+}
+
+impl MarketMetadata {
+    /// Create new market metadata
+    pub fn new(params: InitMarketMetadataParams) -> Self {
+        Self {
+            version: PROGRAM_VERSION,
+            bump_seed: params.bump_seed,
+            lending_market: params.lending_market,
+            flags: params.flags,
+            name: params.name,
+            description_uri: params.description_uri,
+        }
+    }
+
+    /// Whether the name and description URI are within their maximum lengths
+    pub fn is_valid_len(name: &str, description_uri: &str) -> bool {
+        name.len() <= MAX_MARKET_NAME_LEN && description_uri.len() <= MAX_MARKET_DESCRIPTION_URI_LEN
+    }
+
+    /// Size of the metadata account holding the name and description URI
+    pub fn account_len(&self) -> usize {
+        MARKET_METADATA_HEADER_LEN + self.name.len() + self.description_uri.len()
+    }
+
+    /// Unpacks a metadata account, which is longer than `MarketMetadata::LEN`
+    /// by its strings
+    pub fn unpack_account(src: &[u8]) -> Result<Self, ProgramError> {
+        let metadata = Self::unpack_from_slice(src)?;
+        if !metadata.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if metadata.account_len() != src.len() {
+            msg!("Market metadata account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(metadata)
+    }
+
+    /// Packs metadata into its account, which must be sized to the strings
+    pub fn pack_account(src: Self, dst: &mut [u8]) -> ProgramResult {
+        if !Self::is_valid_len(&src.name, &src.description_uri) {
+            msg!("Market metadata name or description URI is too long");
+            return Err(LendingError::MarketMetadataTooLong.into());
+        }
+        if src.account_len() != dst.len() {
+            msg!("Market metadata account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+}
+
+/// Initialize market metadata
+pub struct InitMarketMetadataParams {
+    /// Bump seed for the metadata address
+    pub bump_seed: u8,
+    /// Lending market address
+    pub lending_market: Pubkey,
+    /// Flags reserved for future use
+    pub flags: u8,
+    /// Display name of the market
+    pub name: String,
+    /// URI of the market description
+    pub description_uri: String,
+}
+
+/// Derives the address of the metadata of a lending market
+pub fn find_market_metadata_address(program_id: &Pubkey, lending_market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MARKET_METADATA_SEED, lending_market.as_ref()], program_id)
+}
+
+/// Address of the metadata of a lending market
+pub fn get_market_metadata_address(program_id: &Pubkey, lending_market: &Pubkey) -> Pubkey {
+    find_market_metadata_address(program_id, lending_market).0
+}
+
+impl Sealed for MarketMetadata {}
+impl IsInitialized for MarketMetadata {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const MARKET_METADATA_HEADER_LEN: usize = 37; // 1 + 1 + 32 + 1 + 1 + 1
+impl Pack for MarketMetadata {
+    const LEN: usize = MARKET_METADATA_HEADER_LEN;
+
+    /// Packs into `dst`, writing the strings past `MarketMetadata::LEN`
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let (output, data_flat) = dst.split_at_mut(MARKET_METADATA_HEADER_LEN);
+        let output = array_mut_ref![output, 0, MARKET_METADATA_HEADER_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, bump_seed, lending_market, flags, name_len, description_uri_len) =
+            mut_array_refs![output, 1, 1, PUBKEY_BYTES, 1, 1, 1];
+
+        *version = self.version.to_le_bytes();
+        *bump_seed = self.bump_seed.to_le_bytes();
+        lending_market.copy_from_slice(self.lending_market.as_ref());
+        *flags = self.flags.to_le_bytes();
+        *name_len = (self.name.len() as u8).to_le_bytes();
+        *description_uri_len = (self.description_uri.len() as u8).to_le_bytes();
+
+        let (name, data_flat) = data_flat.split_at_mut(self.name.len());
+        name.copy_from_slice(self.name.as_bytes());
+        data_flat[..self.description_uri.len()].copy_from_slice(self.description_uri.as_bytes());
+    }
+
+    /// Unpacks from `src`, reading the strings past `MarketMetadata::LEN`
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < MARKET_METADATA_HEADER_LEN {
+            msg!("Market metadata account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (input, data_flat) = src.split_at(MARKET_METADATA_HEADER_LEN);
+        let input = array_ref![input, 0, MARKET_METADATA_HEADER_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, bump_seed, lending_market, flags, name_len, description_uri_len) =
+            array_refs![input, 1, 1, PUBKEY_BYTES, 1, 1, 1];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Market metadata version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let name_len = u8::from_le_bytes(*name_len) as usize;
+        let description_uri_len = u8::from_le_bytes(*description_uri_len) as usize;
+        if data_flat.len() < name_len + description_uri_len {
+            msg!("Market metadata account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (name, data_flat) = data_flat.split_at(name_len);
+        let unpack_string = |bytes: &[u8]| {
+            String::from_utf8(bytes.to_vec()).map_err(|_| {
+                msg!("Market metadata strings must be UTF-8");
+                ProgramError::InvalidAccountData
+            })
+        };
+
+        Ok(Self {
+            version,
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            lending_market: Pubkey::new_from_array(*lending_market),
+            flags: u8::from_le_bytes(*flags),
+            name: unpack_string(name)?,
+            description_uri: unpack_string(&data_flat[..description_uri_len])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(name: &str, description_uri: &str) -> MarketMetadata {
+        MarketMetadata::new(InitMarketMetadataParams {
+            bump_seed: 255,
+            lending_market: Pubkey::new_unique(),
+            flags: 1,
+            name: name.to_string(),
+            description_uri: description_uri.to_string(),
+        })
+    }
+
+    #[test]
+    fn pack_account_roundtrip() {
+        let metadata = metadata("Main market", "https://example.com/main.json");
+        let mut packed = vec![0u8; metadata.account_len()];
+        MarketMetadata::pack_account(metadata.clone(), &mut packed).unwrap();
+        assert_eq!(MarketMetadata::unpack_account(&packed).unwrap(), metadata);
+
+        // the account must be resized to the strings
+        let mut packed = vec![0u8; metadata.account_len() + 1];
+        assert_eq!(
+            MarketMetadata::pack_account(metadata, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn pack_account_max_len() {
+        let metadata = metadata(
+            &"n".repeat(MAX_MARKET_NAME_LEN),
+            &"u".repeat(MAX_MARKET_DESCRIPTION_URI_LEN),
+        );
+        let mut packed = vec![0u8; metadata.account_len()];
+        MarketMetadata::pack_account(metadata.clone(), &mut packed).unwrap();
+        assert_eq!(MarketMetadata::unpack_account(&packed).unwrap(), metadata);
+
+        let metadata = MarketMetadata {
+            name: "n".repeat(MAX_MARKET_NAME_LEN + 1),
+            ..metadata
+        };
+        let mut packed = vec![0u8; metadata.account_len()];
+        assert_eq!(
+            MarketMetadata::pack_account(metadata, &mut packed),
+            Err(LendingError::MarketMetadataTooLong.into())
+        );
+    }
+
+    #[test]
+    fn unpack_account_uninitialized() {
+        let packed = [0u8; MarketMetadata::LEN];
+        assert_eq!(
+            MarketMetadata::unpack_account(&packed),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+}
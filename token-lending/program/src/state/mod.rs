@@ -1,9 +1,13 @@
 //! State types
 
+mod borrow_allowlist;
 mod last_update;
 mod lending_market;
+mod market_metadata;
 mod obligation;
+mod redeem_request;
 mod reserve;
+mod reward_checkpoint;
 
 use {
     crate::math::{Decimal, WAD},
@@ -13,7 +17,10 @@ use {
         program_error::ProgramError,
     },
 };
-pub use {last_update::*, lending_market::*, obligation::*, reserve::*};
+pub use {
+    borrow_allowlist::*, last_update::*, lending_market::*, market_metadata::*, obligation::*,
+    redeem_request::*, reserve::*, reward_checkpoint::*,
+};
 
 /// Collateral tokens are initially valued at a ratio of 5:1
 /// (collateral:liquidity)
@@ -22,7 +29,7 @@ pub const INITIAL_COLLATERAL_RATIO: u64 = 1;
 const INITIAL_COLLATERAL_RATE: u64 = INITIAL_COLLATERAL_RATIO * WAD;
 
 /// Current version of the program and all new accounts created
-pub const PROGRAM_VERSION: u8 = 1;
+pub const PROGRAM_VERSION: u8 = 5;
 
 /// Accounts are created with data zeroed out, so uninitialized state instances
 /// will have the version set to 0.
@@ -13,16 +13,43 @@ use {
         program_pack::{IsInitialized, Pack, Sealed},
         pubkey::{Pubkey, PUBKEY_BYTES},
     },
-    std::{
-        cmp::Ordering,
-        convert::{TryFrom, TryInto},
-    },
+    std::convert::{TryFrom, TryInto},
 };
 
 /// Max number of collateral and liquidity reserve accounts combined for an
-/// obligation
+/// obligation account of `Obligation::LEN`, before it is grown with
+/// ResizeObligation
 pub const MAX_OBLIGATION_RESERVES: usize = 10;
 
+/// Max number of collateral and liquidity reserve accounts combined for a
+/// resized obligation, as deposits and borrows are each counted in a byte
+pub const MAX_RESIZED_OBLIGATION_RESERVES: usize = u8::MAX as usize;
+
+/// Seed prefix of derived obligation addresses
+pub const OBLIGATION_SEED: &[u8] = b"obligation";
+
+/// Derives the address of the obligation of an owner created with
+/// InitDerivedObligation
+pub fn find_obligation_address(
+    program_id: &Pubkey,
+    lending_market: &Pubkey,
+    owner: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[OBLIGATION_SEED, lending_market.as_ref(), owner.as_ref()],
+        program_id,
+    )
+}
+
+/// Address of the obligation of an owner created with InitDerivedObligation
+pub fn get_obligation_address(
+    program_id: &Pubkey,
+    lending_market: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    find_obligation_address(program_id, lending_market, owner).0
+}
+
 /// Lending market obligation state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Obligation {
@@ -41,7 +68,8 @@ pub struct Obligation {
     pub borrows: Vec<ObligationLiquidity>,
     /// Market value of deposits
     pub deposited_value: Decimal,
-    /// Market value of borrows
+    /// Market value of borrows, weighted by the borrow weight of each borrow
+    /// reserve
     pub borrowed_value: Decimal,
     /// The maximum borrow value at the weighted average loan to value ratio
     pub allowed_borrow_value: Decimal,
@@ -110,22 +138,76 @@ impl Obligation {
             .try_div(withdraw_collateral_ltv)
     }
 
+    /// Calculate the market value of the borrows, without borrow weights
+    pub fn borrowed_market_value(&self) -> Result<Decimal, ProgramError> {
+        self.borrows
+            .iter()
+            .try_fold(Decimal::zero(), |value, liquidity| {
+                value.try_add(liquidity.market_value)
+            })
+    }
+
     /// Calculate the maximum liquidity value that can be borrowed
     pub fn remaining_borrow_value(&self) -> Result<Decimal, ProgramError> {
         self.allowed_borrow_value.try_sub(self.borrowed_value)
     }
 
-    /// Calculate the maximum liquidation amount for a given liquidity
+    /// Calculate the maximum liquidation amount for a given liquidity, using
+    /// the close factor of the reserve it is borrowed from. The whole borrow
+    /// can be liquidated when the rest would be left below the reserve's
+    /// liquidation dust threshold.
     pub fn max_liquidation_amount(
         &self,
         liquidity: &ObligationLiquidity,
+        borrow_reserve_config: &ReserveConfig,
     ) -> Result<Decimal, ProgramError> {
         let max_liquidation_value = self
             .borrowed_value
-            .try_mul(Rate::from_percent(LIQUIDATION_CLOSE_FACTOR))?
+            .try_mul(borrow_reserve_config.liquidation_close_factor_rate())?
             .min(liquidity.market_value);
         let max_liquidation_pct = max_liquidation_value.try_div(liquidity.market_value)?;
-        liquidity.borrowed_amount_wads.try_mul(max_liquidation_pct)
+        let max_liquidation_amount = liquidity
+            .borrowed_amount_wads
+            .try_mul(max_liquidation_pct)?;
+
+        let remaining_amount = liquidity
+            .borrowed_amount_wads
+            .try_sub(max_liquidation_amount)?;
+        if remaining_amount < Decimal::from(borrow_reserve_config.liquidation_dust_threshold) {
+            return Ok(liquidity.borrowed_amount_wads);
+        }
+        Ok(max_liquidation_amount)
+    }
+
+    /// Reserves which must be refreshed before the obligation can be refreshed
+    /// in the given slot. `reserves` are expected in the order they are passed
+    /// to refresh obligation, deposit reserves followed by borrow reserves.
+    pub fn stale_reserves(
+        &self,
+        reserves: &[Reserve],
+        slot: Slot,
+    ) -> Result<Vec<Pubkey>, ProgramError> {
+        if reserves.len() != self.deposits.len() + self.borrows.len() {
+            msg!("Number of reserves provided does not match the obligation deposits and borrows");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        let reserve_pubkeys = self
+            .deposits
+            .iter()
+            .map(|collateral| collateral.deposit_reserve)
+            .chain(
+                self.borrows
+                    .iter()
+                    .map(|liquidity| liquidity.borrow_reserve),
+            );
+
+        let mut stale_reserves = vec![];
+        for (reserve_pubkey, reserve) in reserve_pubkeys.zip(reserves) {
+            if reserve.last_update.is_stale(slot)? && !stale_reserves.contains(&reserve_pubkey) {
+                stale_reserves.push(reserve_pubkey);
+            }
+        }
+        Ok(stale_reserves)
     }
 
     /// Find collateral by deposit reserve
@@ -151,18 +233,32 @@ impl Obligation {
         if let Some(collateral_index) = self._find_collateral_index_in_deposits(deposit_reserve) {
             return Ok(&mut self.deposits[collateral_index]);
         }
-        if self.deposits.len() + self.borrows.len() >= MAX_OBLIGATION_RESERVES {
-            msg!(
-                "Obligation cannot have more than {} deposits and borrows combined",
-                MAX_OBLIGATION_RESERVES
-            );
-            return Err(LendingError::ObligationReserveLimit.into());
-        }
         let collateral = ObligationCollateral::new(deposit_reserve);
         self.deposits.push(collateral);
         Ok(self.deposits.last_mut().unwrap())
     }
 
+    /// Check if collateral deposited to the given reserve can be combined with
+    /// the obligation's collateral. Isolated collateral must be the only
+    /// collateral of an obligation. `isolated_deposit_reserves` are the
+    /// deposit reserves of the obligation's collateral which are isolated.
+    pub fn can_combine_collateral(
+        &self,
+        deposit_reserve: Pubkey,
+        deposit_reserve_isolated: bool,
+        isolated_deposit_reserves: &[Pubkey],
+    ) -> bool {
+        let has_other_collateral = self
+            .deposits
+            .iter()
+            .any(|collateral| collateral.deposit_reserve != deposit_reserve);
+        !has_other_collateral
+            || (!deposit_reserve_isolated
+                && isolated_deposit_reserves
+                    .iter()
+                    .all(|isolated_reserve| *isolated_reserve == deposit_reserve))
+    }
+
     fn _find_collateral_index_in_deposits(&self, deposit_reserve: Pubkey) -> Option<usize> {
         self.deposits
             .iter()
@@ -184,22 +280,19 @@ impl Obligation {
         Ok((&self.borrows[liquidity_index], liquidity_index))
     }
 
-    /// Find or add liquidity by borrow reserve
+    /// Find or add liquidity by borrow reserve, accruing interest up to the
+    /// reserve's `cumulative_borrow_rate_wads`
     pub fn find_or_add_liquidity_to_borrows(
         &mut self,
         borrow_reserve: Pubkey,
+        cumulative_borrow_rate_wads: Decimal,
     ) -> Result<&mut ObligationLiquidity, ProgramError> {
         if let Some(liquidity_index) = self._find_liquidity_index_in_borrows(borrow_reserve) {
-            return Ok(&mut self.borrows[liquidity_index]);
+            let liquidity = &mut self.borrows[liquidity_index];
+            liquidity.accrue_interest(cumulative_borrow_rate_wads)?;
+            return Ok(liquidity);
         }
-        if self.deposits.len() + self.borrows.len() >= MAX_OBLIGATION_RESERVES {
-            msg!(
-                "Obligation cannot have more than {} deposits and borrows combined",
-                MAX_OBLIGATION_RESERVES
-            );
-            return Err(LendingError::ObligationReserveLimit.into());
-        }
-        let liquidity = ObligationLiquidity::new(borrow_reserve);
+        let liquidity = ObligationLiquidity::new(borrow_reserve, cumulative_borrow_rate_wads);
         self.borrows.push(liquidity);
         Ok(self.borrows.last_mut().unwrap())
     }
@@ -209,6 +302,87 @@ impl Obligation {
             .iter()
             .position(|liquidity| liquidity.borrow_reserve == borrow_reserve)
     }
+
+    /// Size of an obligation account which can hold the given number of
+    /// deposits and borrows combined. Every reserve past
+    /// MAX_OBLIGATION_RESERVES takes a slot large enough for either.
+    pub fn account_len(max_reserves: usize) -> usize {
+        OBLIGATION_LEN
+            + OBLIGATION_LIQUIDITY_LEN * max_reserves.saturating_sub(MAX_OBLIGATION_RESERVES)
+    }
+
+    /// Number of deposits and borrows combined an obligation account of the
+    /// given size can hold
+    pub fn max_reserves(account_len: usize) -> usize {
+        MAX_OBLIGATION_RESERVES
+            + account_len.saturating_sub(OBLIGATION_LEN) / OBLIGATION_LIQUIDITY_LEN
+    }
+
+    /// Unpacks an obligation account, which can be larger than
+    /// `Obligation::LEN` once resized
+    pub fn unpack_account(src: &[u8]) -> Result<Self, ProgramError> {
+        if !Self::is_valid_account_len(src.len()) {
+            msg!("Obligation account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let obligation = Self::unpack_from_slice(src)?;
+        if !obligation.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(obligation)
+    }
+
+    /// Packs an obligation into its account, which can be larger than
+    /// `Obligation::LEN` once resized. Fails if the deposits and borrows don't
+    /// fit in the account.
+    pub fn pack_account(src: Self, dst: &mut [u8]) -> ProgramResult {
+        if !Self::is_valid_account_len(dst.len()) {
+            msg!("Obligation account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let max_reserves = Self::max_reserves(dst.len());
+        if src.deposits.len() + src.borrows.len() > max_reserves
+            || packed_len(src.deposits.len(), src.borrows.len()) > dst.len()
+        {
+            msg!(
+                "Obligation cannot have more than {} deposits and borrows combined",
+                max_reserves
+            );
+            return Err(LendingError::ObligationReserveLimit.into());
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
+    /// Unpacks an obligation account of a version older than
+    /// `PROGRAM_VERSION`, to be migrated to the current version. Fails if the
+    /// obligation is already at the current version.
+    pub fn unpack_legacy_account(src: &[u8]) -> Result<Self, ProgramError> {
+        let version = src.first().copied().unwrap_or(UNINITIALIZED_VERSION);
+        match version {
+            UNINITIALIZED_VERSION => Err(ProgramError::UninitializedAccount),
+            // every version so far shares the current layout, a layout change
+            // converts older versions here
+            version if version < PROGRAM_VERSION => Self::unpack_account(src),
+            _ => {
+                msg!("Obligation is already at the current version");
+                Err(LendingError::ObligationAlreadyMigrated.into())
+            }
+        }
+    }
+
+    /// Migrate to the current version. Values computed by older versions are
+    /// recomputed on the next refresh.
+    pub fn migrate(&mut self) {
+        self.version = PROGRAM_VERSION;
+        self.last_update.mark_stale();
+    }
+
+    fn is_valid_account_len(account_len: usize) -> bool {
+        account_len >= OBLIGATION_LEN
+            && (account_len - OBLIGATION_LEN) % OBLIGATION_LIQUIDITY_LEN == 0
+            && Self::max_reserves(account_len) <= MAX_RESIZED_OBLIGATION_RESERVES
+    }
 }
 
 /// Initialize an obligation
@@ -278,7 +452,10 @@ impl ObligationCollateral {
 pub struct ObligationLiquidity {
     /// Reserve liquidity is borrowed from
     pub borrow_reserve: Pubkey,
-    /// Borrow rate used for calculating interest
+    /// Cumulative borrow rate of the reserve when the borrowed amount was last
+    /// updated, by a refresh or a borrow. Repays and liquidations need the
+    /// obligation refreshed in the same slot, so it is also the reserve's
+    /// rate at the time of those.
     pub cumulative_borrow_rate_wads: Decimal,
     /// Amount of liquidity borrowed plus interest
     pub borrowed_amount_wads: Decimal,
@@ -287,11 +464,12 @@ pub struct ObligationLiquidity {
 }
 
 impl ObligationLiquidity {
-    /// Create new obligation liquidity
-    pub fn new(borrow_reserve: Pubkey) -> Self {
+    /// Create new obligation liquidity, accruing interest from the reserve's
+    /// current `cumulative_borrow_rate_wads`
+    pub fn new(borrow_reserve: Pubkey, cumulative_borrow_rate_wads: Decimal) -> Self {
         Self {
             borrow_reserve,
-            cumulative_borrow_rate_wads: Decimal::one(),
+            cumulative_borrow_rate_wads,
             borrowed_amount_wads: Decimal::zero(),
             market_value: Decimal::zero(),
         }
@@ -309,39 +487,66 @@ impl ObligationLiquidity {
         Ok(())
     }
 
-    /// Accrue interest
+    /// Accrue interest. A lower cumulative borrow rate, from a negative borrow
+    /// rate, shrinks the borrowed amount.
     pub fn accrue_interest(&mut self, cumulative_borrow_rate_wads: Decimal) -> ProgramResult {
-        match cumulative_borrow_rate_wads.cmp(&self.cumulative_borrow_rate_wads) {
-            Ordering::Less => {
-                msg!("Interest rate cannot be negative");
-                return Err(LendingError::NegativeInterestRate.into());
-            }
-            Ordering::Equal => {}
-            Ordering::Greater => {
-                let compounded_interest_rate: Rate = cumulative_borrow_rate_wads
-                    .try_div(self.cumulative_borrow_rate_wads)?
-                    .try_into()?;
-
-                self.borrowed_amount_wads = self
-                    .borrowed_amount_wads
-                    .try_mul(compounded_interest_rate)?;
-                self.cumulative_borrow_rate_wads = cumulative_borrow_rate_wads;
-            }
+        self.borrowed_amount_wads = self.borrowed_amount_at(cumulative_borrow_rate_wads)?;
+        self.cumulative_borrow_rate_wads = cumulative_borrow_rate_wads;
+        Ok(())
+    }
+
+    /// Interest accrued on the borrowed amount since the cumulative borrow
+    /// rate snapshot, up to the reserve's `current_cumulative_rate`, exactly
+    /// as `accrue_interest` would add it. Zero when a negative borrow rate
+    /// lowered the cumulative borrow rate, shrinking the borrowed amount
+    /// instead. Leaves the liquidity unchanged, for off-chain use.
+    pub fn accrued_interest(
+        &self,
+        current_cumulative_rate: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        let borrowed_amount_wads = self.borrowed_amount_at(current_cumulative_rate)?;
+        if borrowed_amount_wads > self.borrowed_amount_wads {
+            borrowed_amount_wads.try_sub(self.borrowed_amount_wads)
+        } else {
+            Ok(Decimal::zero())
         }
+    }
 
-        Ok(())
+    fn borrowed_amount_at(
+        &self,
+        cumulative_borrow_rate_wads: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        if cumulative_borrow_rate_wads == self.cumulative_borrow_rate_wads {
+            return Ok(self.borrowed_amount_wads);
+        }
+        let compounded_interest_rate: Rate = cumulative_borrow_rate_wads
+            .try_div(self.cumulative_borrow_rate_wads)?
+            .try_into()?;
+        self.borrowed_amount_wads.try_mul(compounded_interest_rate)
     }
 }
 
 const OBLIGATION_COLLATERAL_LEN: usize = 56; // 32 + 8 + 16
 const OBLIGATION_LIQUIDITY_LEN: usize = 80; // 32 + 16 + 16 + 16
-const OBLIGATION_LEN: usize = 916; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + (56 * 1) + (80 * 9)
+const OBLIGATION_HEADER_LEN: usize = 140; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1
+const OBLIGATION_LEN: usize = 916; // 140 + (56 * 1) + (80 * 9)
                                    // @TODO: break this up by obligation / collateral / liquidity https://git.io/JOCca
+
+/// Number of bytes the obligation header, deposits and borrows are packed into
+fn packed_len(deposits_len: usize, borrows_len: usize) -> usize {
+    OBLIGATION_HEADER_LEN
+        + OBLIGATION_COLLATERAL_LEN * deposits_len
+        + OBLIGATION_LIQUIDITY_LEN * borrows_len
+}
+
 impl Pack for Obligation {
     const LEN: usize = OBLIGATION_LEN;
 
+    /// Packs into `dst`, writing deposits and borrows past `Obligation::LEN`
+    /// when the slice is larger
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let output = array_mut_ref![dst, 0, OBLIGATION_LEN];
+        let (output, data_flat) = dst.split_at_mut(OBLIGATION_HEADER_LEN);
+        let output = array_mut_ref![output, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -355,7 +560,6 @@ impl Pack for Obligation {
             unhealthy_borrow_value,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = mut_array_refs![
             output,
             1,
@@ -368,8 +572,7 @@ impl Pack for Obligation {
             16,
             16,
             1,
-            1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            1
         ];
 
         // obligation
@@ -417,9 +620,11 @@ impl Pack for Obligation {
     }
 
     /// Unpacks a byte buffer into an
-    /// [ObligationInfo](struct.ObligationInfo.html).
+    /// [ObligationInfo](struct.ObligationInfo.html), reading deposits and
+    /// borrows past `Obligation::LEN` when the buffer is larger.
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![src, 0, OBLIGATION_LEN];
+        let (input, data_flat) = src.split_at(OBLIGATION_HEADER_LEN);
+        let input = array_ref![input, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -433,7 +638,6 @@ impl Pack for Obligation {
             unhealthy_borrow_value,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = array_refs![
             input,
             1,
@@ -446,8 +650,7 @@ impl Pack for Obligation {
             16,
             16,
             1,
-            1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            1
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -458,6 +661,10 @@ impl Pack for Obligation {
 
         let deposits_len = u8::from_le_bytes(*deposits_len);
         let borrows_len = u8::from_le_bytes(*borrows_len);
+        if packed_len(deposits_len as usize, borrows_len as usize) > src.len() {
+            msg!("Obligation deposits and borrows exceed the account size");
+            return Err(ProgramError::InvalidAccountData);
+        }
         let mut deposits = Vec::with_capacity(deposits_len as usize + 1);
         let mut borrows = Vec::with_capacity(borrows_len as usize + 1);
 
@@ -523,15 +730,6 @@ mod test {
             Err(LendingError::MathOverflow.into())
         );
 
-        assert_eq!(
-            ObligationLiquidity {
-                cumulative_borrow_rate_wads: Decimal::from(2u64),
-                ..ObligationLiquidity::default()
-            }
-            .accrue_interest(Decimal::one()),
-            Err(LendingError::NegativeInterestRate.into())
-        );
-
         assert_eq!(
             ObligationLiquidity {
                 cumulative_borrow_rate_wads: Decimal::one(),
@@ -543,6 +741,84 @@ mod test {
         );
     }
 
+    #[test]
+    fn obligation_accrue_negative_interest() {
+        let mut liquidity = ObligationLiquidity {
+            cumulative_borrow_rate_wads: Decimal::from(2u64),
+            borrowed_amount_wads: Decimal::from(100u64),
+            ..ObligationLiquidity::default()
+        };
+        liquidity.accrue_interest(Decimal::one()).unwrap();
+        assert_eq!(liquidity.borrowed_amount_wads, Decimal::from(50u64));
+        assert_eq!(liquidity.cumulative_borrow_rate_wads, Decimal::one());
+    }
+
+    #[test]
+    fn accrued_interest_matches_refreshes() {
+        // the reserve's cumulative borrow rate when the liquidity is borrowed,
+        // then after each refresh, including one at a negative borrow rate
+        let cumulative_rates: Vec<Decimal> = [
+            1_050_000_000_000_000_000u128,
+            1_102_500_000_000_000_000, // x1.05
+            1_080_450_000_000_000_000, // x0.98
+            1_188_495_000_000_000_000, // x1.1
+        ]
+        .iter()
+        .map(|rate| Decimal::from_scaled_val(*rate))
+        .collect();
+
+        let mut obligation = Obligation::default();
+        let borrow_reserve = Pubkey::new_unique();
+        obligation
+            .find_or_add_liquidity_to_borrows(borrow_reserve, cumulative_rates[0])
+            .unwrap()
+            .borrow(Decimal::from(1_000u64))
+            .unwrap();
+        let snapshot = obligation.borrows[0].clone();
+        assert_eq!(snapshot.cumulative_borrow_rate_wads, cumulative_rates[0]);
+        assert_eq!(
+            snapshot.accrued_interest(cumulative_rates[0]).unwrap(),
+            Decimal::zero()
+        );
+
+        let liquidity = &mut obligation.borrows[0];
+        for cumulative_rate in &cumulative_rates[1..] {
+            liquidity.accrue_interest(*cumulative_rate).unwrap();
+        }
+        let accrued_interest = snapshot.accrued_interest(cumulative_rates[3]).unwrap();
+        assert_eq!(
+            accrued_interest,
+            Decimal::from_scaled_val(131_900_000_000_000_000_000)
+        );
+        assert_eq!(
+            liquidity.borrowed_amount_wads,
+            snapshot
+                .borrowed_amount_wads
+                .try_add(accrued_interest)
+                .unwrap()
+        );
+
+        // the negative borrow rate shrinks the borrow without any interest
+        assert_eq!(
+            liquidity.accrued_interest(cumulative_rates[2]).unwrap(),
+            Decimal::zero()
+        );
+
+        // borrowing more accrues interest up to the reserve's rate first
+        let next_rate = Decimal::from_scaled_val(1_307_344_500_000_000_000); // x1.1
+        obligation
+            .find_or_add_liquidity_to_borrows(borrow_reserve, next_rate)
+            .unwrap()
+            .borrow(Decimal::from(100u64))
+            .unwrap();
+        let liquidity = &obligation.borrows[0];
+        assert_eq!(liquidity.cumulative_borrow_rate_wads, next_rate);
+        assert_eq!(
+            liquidity.borrowed_amount_wads,
+            Decimal::from_scaled_val(1_345_090_000_000_000_000_000)
+        );
+    }
+
     // Creates rates (r1, r2) where 0 < r1 <= r2 <= 100*r1
     prop_compose! {
         fn cumulative_rates()(rate in 1..=u128::MAX)(
@@ -635,4 +911,204 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn stale_reserves() {
+        let deposit_reserve = Pubkey::new_unique();
+        let borrow_reserve = Pubkey::new_unique();
+        let obligation = Obligation {
+            deposits: vec![ObligationCollateral::new(deposit_reserve)],
+            borrows: vec![ObligationLiquidity::new(borrow_reserve, Decimal::one())],
+            ..Obligation::default()
+        };
+
+        let mut fresh_reserve = Reserve::default();
+        fresh_reserve.last_update.update_slot(10);
+        let mut stale_reserve = Reserve::default();
+        stale_reserve.last_update.update_slot(9);
+
+        assert_eq!(
+            obligation.stale_reserves(&[fresh_reserve.clone(), fresh_reserve.clone()], 10),
+            Ok(vec![])
+        );
+        assert_eq!(
+            obligation.stale_reserves(&[fresh_reserve.clone(), stale_reserve.clone()], 10),
+            Ok(vec![borrow_reserve])
+        );
+        assert_eq!(
+            obligation.stale_reserves(&[stale_reserve.clone(), stale_reserve], 10),
+            Ok(vec![deposit_reserve, borrow_reserve])
+        );
+        assert_eq!(
+            obligation.stale_reserves(&[fresh_reserve], 10),
+            Err(LendingError::InvalidAccountInput.into())
+        );
+    }
+
+    #[test]
+    fn combine_isolated_collateral() {
+        let isolated_reserve = Pubkey::new_unique();
+        let other_reserve = Pubkey::new_unique();
+        let mut obligation = Obligation::default();
+
+        // any collateral can be the first collateral of an obligation
+        assert!(obligation.can_combine_collateral(isolated_reserve, true, &[]));
+        assert!(obligation.can_combine_collateral(other_reserve, false, &[]));
+
+        // isolated collateral can only be topped up
+        obligation.deposits = vec![ObligationCollateral::new(isolated_reserve)];
+        assert!(obligation.can_combine_collateral(isolated_reserve, true, &[isolated_reserve]));
+        assert!(!obligation.can_combine_collateral(other_reserve, false, &[isolated_reserve]));
+
+        // and cannot join other collateral
+        obligation.deposits = vec![ObligationCollateral::new(other_reserve)];
+        assert!(!obligation.can_combine_collateral(isolated_reserve, true, &[]));
+        assert!(obligation.can_combine_collateral(other_reserve, false, &[]));
+
+        // a reserve flagged isolated after the collateral was combined blocks
+        // further deposits to either reserve
+        obligation.deposits = vec![
+            ObligationCollateral::new(isolated_reserve),
+            ObligationCollateral::new(other_reserve),
+        ];
+        assert!(!obligation.can_combine_collateral(isolated_reserve, true, &[isolated_reserve]));
+        assert!(!obligation.can_combine_collateral(other_reserve, false, &[isolated_reserve]));
+        assert!(obligation.can_combine_collateral(other_reserve, false, &[]));
+    }
+
+    // A borrow of 1000 tokens worth $100, the obligation's whole borrowed value
+    fn max_liquidation_amount(close_factor: u8, dust_threshold: u64) -> Decimal {
+        let obligation = Obligation {
+            borrowed_value: Decimal::from(100u64),
+            ..Obligation::default()
+        };
+        let liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(1000u64),
+            market_value: Decimal::from(100u64),
+            ..ObligationLiquidity::default()
+        };
+        let config = ReserveConfig {
+            liquidation_close_factor: close_factor,
+            liquidation_dust_threshold: dust_threshold,
+            ..ReserveConfig::default()
+        };
+        obligation
+            .max_liquidation_amount(&liquidity, &config)
+            .unwrap()
+    }
+
+    #[test]
+    fn max_liquidation_amount_close_factor() {
+        assert_eq!(max_liquidation_amount(20, 0), Decimal::from(200u64));
+        assert_eq!(max_liquidation_amount(50, 0), Decimal::from(500u64));
+        assert_eq!(max_liquidation_amount(100, 0), Decimal::from(1000u64));
+
+        // reserves packed without a close factor use the default
+        assert_eq!(max_liquidation_amount(0, 0), Decimal::from(500u64));
+    }
+
+    #[test]
+    fn max_liquidation_amount_dust_full_close() {
+        // 500 tokens would be left, below the dust threshold
+        assert_eq!(max_liquidation_amount(50, 501), Decimal::from(1000u64));
+        assert_eq!(max_liquidation_amount(20, 801), Decimal::from(1000u64));
+
+        // at or above the threshold the close factor applies
+        assert_eq!(max_liquidation_amount(50, 500), Decimal::from(500u64));
+    }
+
+    fn obligation_with_reserves(deposits_len: usize, borrows_len: usize) -> Obligation {
+        Obligation {
+            version: PROGRAM_VERSION,
+            deposits: (0..deposits_len)
+                .map(|_| ObligationCollateral::new(Pubkey::new_unique()))
+                .collect(),
+            borrows: (0..borrows_len)
+                .map(|_| ObligationLiquidity::new(Pubkey::new_unique(), Decimal::one()))
+                .collect(),
+            ..Obligation::default()
+        }
+    }
+
+    #[test]
+    fn resized_account_len() {
+        assert_eq!(
+            Obligation::account_len(MAX_OBLIGATION_RESERVES),
+            Obligation::LEN
+        );
+        assert_eq!(
+            Obligation::max_reserves(Obligation::LEN),
+            MAX_OBLIGATION_RESERVES
+        );
+        assert_eq!(Obligation::account_len(12), Obligation::LEN + 160);
+        assert_eq!(Obligation::max_reserves(Obligation::LEN + 160), 12);
+    }
+
+    #[test]
+    fn pack_resized_account() {
+        let obligation = obligation_with_reserves(6, 6);
+        let mut packed = vec![0u8; Obligation::account_len(12)];
+        Obligation::pack_account(obligation.clone(), &mut packed).unwrap();
+        assert_eq!(Obligation::unpack_account(&packed), Ok(obligation));
+
+        // the obligation doesn't fit before the account is resized
+        let obligation = obligation_with_reserves(6, 6);
+        let mut packed = [0u8; Obligation::LEN];
+        assert_eq!(
+            Obligation::pack_account(obligation, &mut packed),
+            Err(LendingError::ObligationReserveLimit.into())
+        );
+    }
+
+    #[test]
+    fn unpack_invalid_account() {
+        let mut packed = vec![0u8; Obligation::LEN];
+        Obligation::pack_account(obligation_with_reserves(1, 1), &mut packed).unwrap();
+
+        // resized accounts grow by whole slots
+        let mut invalid_len = packed.clone();
+        invalid_len.push(0);
+        assert_eq!(
+            Obligation::unpack_account(&invalid_len),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // deposits past the end of the account
+        packed[OBLIGATION_HEADER_LEN - 2] = u8::MAX;
+        assert_eq!(
+            Obligation::unpack_account(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn unpack_legacy_account() {
+        let mut obligation = obligation_with_reserves(2, 1);
+        obligation.version = 1;
+        obligation.deposits[0].deposited_amount = 100;
+        obligation.borrows[0].borrowed_amount_wads = Decimal::from(50u64);
+        obligation.last_update.update_slot(1);
+        let mut packed = vec![0u8; Obligation::LEN];
+        Obligation::pack_account(obligation.clone(), &mut packed).unwrap();
+
+        let mut migrated = Obligation::unpack_legacy_account(&packed).unwrap();
+        assert_eq!(migrated, obligation);
+        migrated.migrate();
+        assert_eq!(migrated.version, PROGRAM_VERSION);
+        assert!(migrated.last_update.stale);
+        assert_eq!(migrated.deposits, obligation.deposits);
+        assert_eq!(migrated.borrows, obligation.borrows);
+
+        // migrating again is rejected
+        Obligation::pack_account(migrated, &mut packed).unwrap();
+        assert_eq!(
+            Obligation::unpack_legacy_account(&packed),
+            Err(LendingError::ObligationAlreadyMigrated.into())
+        );
+
+        assert_eq!(
+            Obligation::unpack_legacy_account(&[0u8; Obligation::LEN]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
 }
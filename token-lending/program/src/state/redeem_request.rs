@@ -0,0 +1,195 @@
+use {
+    super::*,
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        msg,
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::{Pubkey, PUBKEY_BYTES},
+    },
+};
+
+/// Seed prefix of redeem request addresses
+pub const REDEEM_REQUEST_SEED: &[u8] = b"redeem_request";
+
+/// Redeem request state, a collateral redemption queued on a reserve until
+/// there's liquidity to fulfill it. The request exists until all of its
+/// collateral is redeemed or its owner cancels it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RedeemRequest {
+    /// Version of the struct
+    pub version: u8,
+    /// Bump seed for the request address
+    pub bump_seed: u8,
+    /// Reserve address
+    pub reserve: Pubkey,
+    /// Position of the request in the reserve redeem queue
+    pub ticket: u64,
+    /// Owner of the queued collateral, who can cancel or reroute the request
+    /// and is refunded its rent once it's closed
+    pub owner: Pubkey,
+    /// Token account the redeemed liquidity is sent to
+    pub destination_liquidity: Pubkey,
+    /// Collateral not redeemed yet
+    pub collateral_amount: u64,
+}
+
+impl RedeemRequest {
+    /// Create a new redeem request
+    pub fn new(params: InitRedeemRequestParams) -> Self {
+        Self {
+            version: PROGRAM_VERSION,
+            bump_seed: params.bump_seed,
+            reserve: params.reserve,
+            ticket: params.ticket,
+            owner: params.owner,
+            destination_liquidity: params.destination_liquidity,
+            collateral_amount: params.collateral_amount,
+        }
+    }
+}
+
+/// Initialize a redeem request
+pub struct InitRedeemRequestParams {
+    /// Bump seed for the request address
+    pub bump_seed: u8,
+    /// Reserve address
+    pub reserve: Pubkey,
+    /// Position of the request in the reserve redeem queue
+    pub ticket: u64,
+    /// Owner of the queued collateral
+    pub owner: Pubkey,
+    /// Token account the redeemed liquidity is sent to
+    pub destination_liquidity: Pubkey,
+    /// Collateral to redeem
+    pub collateral_amount: u64,
+}
+
+/// Derives the address of the redeem request holding a ticket of a reserve
+/// redeem queue
+pub fn find_redeem_request_address(
+    program_id: &Pubkey,
+    reserve: &Pubkey,
+    ticket: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[REDEEM_REQUEST_SEED, reserve.as_ref(), &ticket.to_le_bytes()],
+        program_id,
+    )
+}
+
+impl Sealed for RedeemRequest {}
+impl IsInitialized for RedeemRequest {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const REDEEM_REQUEST_LEN: usize = 178; // 1 + 1 + 32 + 8 + 32 + 32 + 8 + 64
+impl Pack for RedeemRequest {
+    const LEN: usize = REDEEM_REQUEST_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, REDEEM_REQUEST_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            bump_seed,
+            reserve,
+            ticket,
+            owner,
+            destination_liquidity,
+            collateral_amount,
+            _padding,
+        ) = mut_array_refs![
+            output,
+            1,
+            1,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            64
+        ];
+
+        *version = self.version.to_le_bytes();
+        *bump_seed = self.bump_seed.to_le_bytes();
+        reserve.copy_from_slice(self.reserve.as_ref());
+        *ticket = self.ticket.to_le_bytes();
+        owner.copy_from_slice(self.owner.as_ref());
+        destination_liquidity.copy_from_slice(self.destination_liquidity.as_ref());
+        *collateral_amount = self.collateral_amount.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, REDEEM_REQUEST_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            bump_seed,
+            reserve,
+            ticket,
+            owner,
+            destination_liquidity,
+            collateral_amount,
+            _padding,
+        ) = array_refs![
+            input,
+            1,
+            1,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            64
+        ];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Redeem request version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            reserve: Pubkey::new_from_array(*reserve),
+            ticket: u64::from_le_bytes(*ticket),
+            owner: Pubkey::new_from_array(*owner),
+            destination_liquidity: Pubkey::new_from_array(*destination_liquidity),
+            collateral_amount: u64::from_le_bytes(*collateral_amount),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_roundtrip() {
+        let request = RedeemRequest::new(InitRedeemRequestParams {
+            bump_seed: 253,
+            reserve: Pubkey::new_unique(),
+            ticket: 42,
+            owner: Pubkey::new_unique(),
+            destination_liquidity: Pubkey::new_unique(),
+            collateral_amount: 1_000,
+        });
+        let mut packed = [0u8; RedeemRequest::LEN];
+        RedeemRequest::pack(request.clone(), &mut packed).unwrap();
+        assert_eq!(RedeemRequest::unpack(&packed).unwrap(), request);
+    }
+
+    #[test]
+    fn address_per_ticket() {
+        let program_id = Pubkey::new_unique();
+        let reserve = Pubkey::new_unique();
+        assert_ne!(
+            find_redeem_request_address(&program_id, &reserve, 0),
+            find_redeem_request_address(&program_id, &reserve, 1)
+        );
+    }
+}
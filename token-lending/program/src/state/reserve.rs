@@ -2,7 +2,8 @@ use {
     super::*,
     crate::{
         error::LendingError,
-        math::{Decimal, Rate, TryAdd, TryDiv, TryMul, TrySub},
+        math::{Decimal, Rate, SignedDecimal, TryAdd, TryDiv, TryMul, TrySub},
+        pyth, switchboard,
     },
     arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
     solana_program::{
@@ -19,12 +20,50 @@ use {
     },
 };
 
-/// Percentage of an obligation that can be repaid during each liquidation call
+/// Percentage of an obligation that can be repaid during each liquidation call,
+/// for reserves without a configured liquidation close factor
 pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
 
 /// Obligation borrow amount that is small enough to close out
 pub const LIQUIDATION_CLOSE_AMOUNT: u64 = 2;
 
+/// Collateral the first deposit into a reserve must mint, so the exchange
+/// rate isn't set by a dust amount of collateral
+pub const MIN_INITIAL_COLLATERAL_AMOUNT: u64 = 1_000;
+
+/// Pauses liquidity deposits into the reserve and collateral deposits into
+/// obligations
+pub const RESERVE_PAUSE_DEPOSITS: u8 = 1 << 0;
+
+/// Pauses borrows and flash loans of the reserve liquidity
+pub const RESERVE_PAUSE_BORROWS: u8 = 1 << 1;
+
+/// Pauses collateral redemptions and withdrawals from obligations, liquidators
+/// can still seize collateral unless liquidations are paused as well
+pub const RESERVE_PAUSE_WITHDRAWALS: u8 = 1 << 2;
+
+/// Pauses liquidations repaying or seizing the reserve
+pub const RESERVE_PAUSE_LIQUIDATIONS: u8 = 1 << 3;
+
+/// All reserve pause flags, repayments can never be paused
+pub const RESERVE_PAUSE_ALL: u8 = RESERVE_PAUSE_DEPOSITS
+    | RESERVE_PAUSE_BORROWS
+    | RESERVE_PAUSE_WITHDRAWALS
+    | RESERVE_PAUSE_LIQUIDATIONS;
+
+/// Upper bound of a reserve's configured oracle price age, about an hour of
+/// slots
+pub const MAX_ORACLE_AGE_SLOTS: u64 = 9_000;
+
+/// Most interest compounded by a single accrual, as a multiple of the
+/// borrows. Reserves left unrefreshed for longer, over a year at the highest
+/// borrow rate, accrue no more interest for the gap, so they can always be
+/// refreshed.
+pub const MAX_COMPOUNDED_INTEREST_RATE: u64 = 16;
+
+/// Rate samples kept in a reserve's rate history, a day of hourly samples
+pub const RATE_HISTORY_LEN: usize = 24;
+
 /// Lending market reserve state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Reserve {
@@ -40,6 +79,25 @@ pub struct Reserve {
     pub collateral: ReserveCollateral,
     /// Reserve configuration values
     pub config: ReserveConfig,
+    /// Actions currently paused on the reserve, see the RESERVE_PAUSE_* flags
+    pub pause_flags: u8,
+    /// Lifecycle status of the reserve, set by the market owner to wind it down
+    pub status: ReserveStatus,
+    /// Liquidity that left the reserve, tracked for the outflow rate limit
+    pub outflow: ReserveOutflow,
+    /// Slots before the last update that interest has not been accrued for
+    /// yet, left over by the accrual cap of the previous refreshes
+    pub unaccrued_slots: u64,
+    /// Incentive rewards distributed to the reserve collateral holders
+    pub reward_pool: RewardPool,
+    /// The market price is an emergency override set by the lending market
+    /// owner, and refreshes leave it in place of the oracle price
+    pub price_override: bool,
+    /// Rates sampled by refreshes, at most once per configured rate history
+    /// interval
+    pub rate_samples: RateHistory,
+    /// Collateral redemptions waiting for liquidity, in the order requested
+    pub redeem_queue: RedeemQueue,
 }
 
 impl Reserve {
@@ -60,6 +118,95 @@ impl Reserve {
         self.config = params.config;
     }
 
+    /// Check if any of the given pause flags are set on the reserve
+    pub fn is_paused(&self, pause_flags: u8) -> bool {
+        self.pause_flags & pause_flags != 0
+    }
+
+    /// Check if the reserve accepts new deposits and borrows
+    pub fn is_active(&self) -> bool {
+        self.status == ReserveStatus::Active
+    }
+
+    /// Check if the market price is too old to be used at the given slot. The
+    /// reserve may have been refreshed in the slot with a price the oracle
+    /// published up to the max oracle age earlier. Reserves in the V1 layout
+    /// don't store the price slot, and only have their price checked on
+    /// refresh.
+    pub fn is_price_stale(&self, slot: Slot) -> Result<bool, ProgramError> {
+        if self.version < RESERVE_V2_VERSION {
+            return Ok(false);
+        }
+        let slots_elapsed = slot
+            .checked_sub(self.liquidity.market_price_slot)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(slots_elapsed >= self.config.max_oracle_age_slots(self.liquidity.oracle_kind))
+    }
+
+    /// Emergency price override used in place of the oracle price, if any
+    pub fn emergency_price_override(&self) -> Option<Decimal> {
+        self.price_override.then_some(self.liquidity.market_price)
+    }
+
+    /// Set or clear the emergency price override. A cleared override keeps
+    /// the price until the next refresh reads the oracle.
+    pub fn set_emergency_price_override(&mut self, price_override: Option<Decimal>) {
+        if let Some(market_price) = price_override {
+            self.liquidity.market_price = market_price;
+        }
+        self.price_override = price_override.is_some();
+        self.last_update.mark_stale();
+    }
+
+    /// Record liquidity leaving the reserve against the outflow rate limit.
+    /// The limit applies to a window sliding with the current slot, where the
+    /// previous fixed window counts for the share of it still in the window.
+    pub fn record_outflow(&mut self, liquidity_amount: u64, current_slot: Slot) -> ProgramResult {
+        let window_duration_slots = self.config.outflow_window_duration_slots;
+        if window_duration_slots == 0 {
+            return Ok(());
+        }
+
+        let elapsed_slots = current_slot.saturating_sub(self.outflow.window_start_slot);
+        if elapsed_slots >= window_duration_slots {
+            let elapsed_windows = elapsed_slots
+                .checked_div(window_duration_slots)
+                .ok_or(LendingError::MathOverflow)?;
+            self.outflow.previous_window_amount = if elapsed_windows == 1 {
+                self.outflow.window_amount
+            } else {
+                0
+            };
+            self.outflow.window_amount = 0;
+            self.outflow.window_start_slot = elapsed_windows
+                .checked_mul(window_duration_slots)
+                .and_then(|elapsed_slots| elapsed_slots.checked_add(self.outflow.window_start_slot))
+                .ok_or(LendingError::MathOverflow)?;
+        }
+
+        let previous_window_slots = window_duration_slots
+            .saturating_sub(current_slot.saturating_sub(self.outflow.window_start_slot));
+        let previous_window_amount = Decimal::from(self.outflow.previous_window_amount)
+            .try_mul(previous_window_slots)?
+            .try_div(window_duration_slots)?
+            .try_ceil_u64()?;
+        let window_amount = previous_window_amount
+            .checked_add(self.outflow.window_amount)
+            .and_then(|window_amount| window_amount.checked_add(liquidity_amount))
+            .ok_or(LendingError::MathOverflow)?;
+        if window_amount > self.config.max_outflow {
+            msg!("Reserve outflow cannot exceed the max outflow of the rate limit window");
+            return Err(LendingError::OutflowRateLimitExceeded.into());
+        }
+
+        self.outflow.window_amount = self
+            .outflow
+            .window_amount
+            .checked_add(liquidity_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(())
+    }
+
     /// Record deposited liquidity and return amount of collateral tokens to
     /// mint
     pub fn deposit_liquidity(&mut self, liquidity_amount: u64) -> Result<u64, ProgramError> {
@@ -73,52 +220,235 @@ impl Reserve {
         Ok(collateral_amount)
     }
 
+    /// Record liquidity donated to the reserve. No collateral is minted, so the
+    /// donation raises the exchange rate for existing collateral holders.
+    pub fn donate_liquidity(&mut self, liquidity_amount: u64) -> ProgramResult {
+        if self.collateral.mint_total_supply == 0 {
+            msg!("Reserve has no collateral holders to donate liquidity to");
+            return Err(LendingError::InvalidAmount.into());
+        }
+
+        self.liquidity.deposit(liquidity_amount)?;
+        // the exchange rate must still be computable for later deposits and
+        // redemptions
+        self.collateral_exchange_rate()?;
+
+        Ok(())
+    }
+
     /// Record redeemed collateral and return amount of liquidity to withdraw
     pub fn redeem_collateral(&mut self, collateral_amount: u64) -> Result<u64, ProgramError> {
         let collateral_exchange_rate = self.collateral_exchange_rate()?;
         let liquidity_amount =
             collateral_exchange_rate.collateral_to_liquidity(collateral_amount)?;
 
+        if self.config.max_withdraw_utilization > 0 {
+            let max_liquidity_amount = self
+                .liquidity
+                .max_withdraw_amount(Rate::from_percent(self.config.max_withdraw_utilization))?;
+            if liquidity_amount > max_liquidity_amount {
+                msg!(
+                    "Redemption would push utilization above {}%, at most {} collateral ({} liquidity) can be redeemed",
+                    self.config.max_withdraw_utilization,
+                    collateral_exchange_rate.liquidity_to_collateral(max_liquidity_amount)?,
+                    max_liquidity_amount
+                );
+                return Err(LendingError::WithdrawTooLarge.into());
+            }
+        }
+
+        if !self.redeem_queue.is_empty() {
+            msg!(
+                "Reserve has queued redemptions, which are fulfilled first, request a redemption to queue after them"
+            );
+            return Err(LendingError::RedemptionsQueued.into());
+        }
+
         self.collateral.burn(collateral_amount)?;
         self.liquidity.withdraw(liquidity_amount)?;
 
         Ok(liquidity_amount)
     }
 
-    /// Calculate the current borrow rate
-    pub fn current_borrow_rate(&self) -> Result<Rate, ProgramError> {
-        let utilization_rate = self.liquidity.utilization_rate()?;
-        let optimal_utilization_rate = Rate::from_percent(self.config.optimal_utilization_rate);
-        let low_utilization = utilization_rate < optimal_utilization_rate;
-        if low_utilization || self.config.optimal_utilization_rate == 100 {
-            let normalized_rate = utilization_rate.try_div(optimal_utilization_rate)?;
-            let min_rate = Rate::from_percent(self.config.min_borrow_rate);
-            let rate_range = Rate::from_percent(
-                self.config
-                    .optimal_borrow_rate
-                    .checked_sub(self.config.min_borrow_rate)
-                    .ok_or(LendingError::MathOverflow)?,
-            );
+    /// Check if collateral can be redeemed right away. Nothing can while
+    /// redemptions are queued, so that they're fulfilled first.
+    pub fn can_redeem_now(&self, collateral_amount: u64) -> Result<bool, ProgramError> {
+        if !self.redeem_queue.is_empty() {
+            return Ok(false);
+        }
+        let liquidity_amount = self
+            .collateral_exchange_rate()?
+            .collateral_to_liquidity(collateral_amount)?;
+        Ok(liquidity_amount <= self.max_redeem_liquidity_amount()?)
+    }
+
+    /// Queue a redemption of collateral the reserve holds until liquidity
+    /// returns, and return the ticket of the queued request
+    pub fn queue_redemption(&mut self, collateral_amount: u64) -> Result<u64, ProgramError> {
+        let ticket = self.redeem_queue.next_ticket;
+        self.redeem_queue.next_ticket = ticket.checked_add(1).ok_or(LendingError::MathOverflow)?;
+        self.redeem_queue.collateral_amount = self
+            .redeem_queue
+            .collateral_amount
+            .checked_add(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(ticket)
+    }
+
+    /// Redeem as much of the collateral of the request at the head of the
+    /// queue as the available liquidity allows. The head moves to the next
+    /// request once all of its collateral is redeemed. Returns the collateral
+    /// redeemed and the liquidity to withdraw.
+    pub fn fulfill_redemption(
+        &mut self,
+        collateral_amount: u64,
+    ) -> Result<(u64, u64), ProgramError> {
+        if self.redeem_queue.is_empty() {
+            msg!("Reserve has no queued redemptions");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let collateral_exchange_rate = self.collateral_exchange_rate()?;
+        let max_collateral_amount = collateral_exchange_rate
+            .liquidity_to_collateral(self.max_redeem_liquidity_amount()?)?;
+        let redeem_collateral_amount = collateral_amount.min(max_collateral_amount);
+        if redeem_collateral_amount == 0 {
+            msg!("Reserve has no liquidity to fulfill the queued redemption");
+            return Err(LendingError::InsufficientLiquidity.into());
+        }
+        let liquidity_amount =
+            collateral_exchange_rate.collateral_to_liquidity(redeem_collateral_amount)?;
+
+        self.collateral.burn(redeem_collateral_amount)?;
+        self.liquidity.withdraw(liquidity_amount)?;
+        self.redeem_queue.collateral_amount = self
+            .redeem_queue
+            .collateral_amount
+            .checked_sub(redeem_collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        if redeem_collateral_amount == collateral_amount {
+            self.redeem_queue.head_ticket = self
+                .redeem_queue
+                .head_ticket
+                .checked_add(1)
+                .ok_or(LendingError::MathOverflow)?;
+        }
+
+        Ok((redeem_collateral_amount, liquidity_amount))
+    }
+
+    /// Move the head of the queue past a request that was cancelled or whose
+    /// destination can't be paid. The collateral of a request that can't be
+    /// paid stays escrowed until its owner cancels it.
+    pub fn skip_redemption(&mut self, collateral_amount: u64) -> Result<(), ProgramError> {
+        if self.redeem_queue.is_empty() {
+            msg!("Reserve has no queued redemptions");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        self.redeem_queue.collateral_amount = self
+            .redeem_queue
+            .collateral_amount
+            .checked_sub(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        self.redeem_queue.head_ticket = self
+            .redeem_queue
+            .head_ticket
+            .checked_add(1)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Take a cancelled request out of the queue. Requests behind the head
+    /// keep their ticket until the head is moved past it, and requests the
+    /// head was already moved past are no longer queued.
+    pub fn cancel_redemption(
+        &mut self,
+        ticket: u64,
+        collateral_amount: u64,
+    ) -> Result<(), ProgramError> {
+        if ticket < self.redeem_queue.head_ticket {
+            return Ok(());
+        }
+        if ticket == self.redeem_queue.head_ticket {
+            return self.skip_redemption(collateral_amount);
+        }
+
+        self.redeem_queue.collateral_amount = self
+            .redeem_queue
+            .collateral_amount
+            .checked_sub(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Liquidity owed to the queued redemptions at the current exchange rate
+    pub fn queued_redeem_liquidity_amount(&self) -> Result<u64, ProgramError> {
+        self.collateral_exchange_rate()?
+            .collateral_to_liquidity(self.redeem_queue.collateral_amount)
+    }
 
-            Ok(normalized_rate.try_mul(rate_range)?.try_add(min_rate)?)
+    /// Liquidity redemptions can withdraw, capped by the max withdraw
+    /// utilization
+    fn max_redeem_liquidity_amount(&self) -> Result<u64, ProgramError> {
+        if self.config.max_withdraw_utilization > 0 {
+            self.liquidity
+                .max_withdraw_amount(Rate::from_percent(self.config.max_withdraw_utilization))
         } else {
-            let normalized_rate = utilization_rate
-                .try_sub(optimal_utilization_rate)?
-                .try_div(Rate::from_percent(
-                    100u8
-                        .checked_sub(self.config.optimal_utilization_rate)
-                        .ok_or(LendingError::MathOverflow)?,
-                ))?;
-            let min_rate = Rate::from_percent(self.config.optimal_borrow_rate);
-            let rate_range = Rate::from_percent(
-                self.config
-                    .max_borrow_rate
-                    .checked_sub(self.config.optimal_borrow_rate)
-                    .ok_or(LendingError::MathOverflow)?,
-            );
+            Ok(self.liquidity.available_amount)
+        }
+    }
 
-            Ok(normalized_rate.try_mul(rate_range)?.try_add(min_rate)?)
+    /// Calculate the current borrow rate, which is negative when the
+    /// configured rebate exceeds the utilization curve
+    pub fn current_borrow_rate(&self) -> Result<SignedDecimal, ProgramError> {
+        let curve_rate = self.curve_borrow_rate()?;
+        if self.config.borrow_rate_rebate == 0 {
+            return Ok(curve_rate.into());
         }
+        let rebate = SignedDecimal::from(Rate::from_percent(self.config.borrow_rate_rebate));
+        let floor = -SignedDecimal::from(Rate::from_percent(self.config.borrow_rate_floor));
+        Ok(SignedDecimal::from(curve_rate).try_sub(rebate)?.max(floor))
+    }
+
+    /// Calculate the borrow rate on the utilization curve, interpolating
+    /// between the breakpoints around the current utilization
+    fn curve_borrow_rate(&self) -> Result<Rate, ProgramError> {
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let breakpoints = self.config.borrow_rate_breakpoints();
+        // Segments without width are never entered. Full utilization falls in
+        // the last segment that has any.
+        let segment = breakpoints
+            .windows(2)
+            .find(|segment| utilization_rate < Rate::from_percent(segment[1].0))
+            .or_else(|| {
+                breakpoints
+                    .windows(2)
+                    .rev()
+                    .find(|segment| segment[1].0 > segment[0].0)
+            })
+            .ok_or(LendingError::MathOverflow)?;
+        let (start_utilization_rate, start_borrow_rate) = segment[0];
+        let (end_utilization_rate, end_borrow_rate) = segment[1];
+
+        let normalized_rate = utilization_rate
+            .min(Rate::from_percent(end_utilization_rate))
+            .try_sub(Rate::from_percent(start_utilization_rate))?
+            .try_div(Rate::from_percent(
+                end_utilization_rate
+                    .checked_sub(start_utilization_rate)
+                    .ok_or(LendingError::MathOverflow)?,
+            ))?;
+        let min_rate = Rate::from_percent(start_borrow_rate);
+        let rate_range = Rate::from_percent(
+            end_borrow_rate
+                .checked_sub(start_borrow_rate)
+                .ok_or(LendingError::MathOverflow)?,
+        );
+
+        normalized_rate.try_mul(rate_range)?.try_add(min_rate)
     }
 
     /// Collateral exchange rate
@@ -127,9 +457,20 @@ impl Reserve {
         self.collateral.exchange_rate(total_liquidity)
     }
 
-    /// Update borrow rate and accrue interest
+    /// Update borrow rate and accrue interest. At most the configured max
+    /// accrual slots are accrued per call, the rest is accrued by the next
+    /// refreshes.
     pub fn accrue_interest(&mut self, current_slot: Slot) -> ProgramResult {
-        let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
+        let pending_slots = self
+            .last_update
+            .slots_elapsed(current_slot)?
+            .checked_add(self.unaccrued_slots)
+            .ok_or(LendingError::MathOverflow)?;
+        let slots_elapsed = match self.config.max_accrual_slots_per_refresh {
+            0 => pending_slots,
+            max_accrual_slots => pending_slots.min(max_accrual_slots),
+        };
+        self.unaccrued_slots = pending_slots - slots_elapsed;
         if slots_elapsed > 0 {
             let current_borrow_rate = self.current_borrow_rate()?;
             self.liquidity
@@ -138,12 +479,63 @@ impl Reserve {
         Ok(())
     }
 
-    /// Borrow liquidity up to a maximum market value
+    /// Sample the current utilization and borrow rates into the rate history,
+    /// unless the last sample is more recent than the configured interval
+    pub fn record_rate_sample(&mut self, current_slot: Slot) -> ProgramResult {
+        let interval_slots = self.config.rate_history_interval_slots;
+        if interval_slots == 0 {
+            return Ok(());
+        }
+        let next_sample_slot = self
+            .rate_samples
+            .last_sample_slot
+            .saturating_add(interval_slots);
+        if self.rate_samples.len > 0 && current_slot < next_sample_slot {
+            return Ok(());
+        }
+
+        let sample = RateSample {
+            utilization_rate_bps: rate_to_bps(Decimal::from(self.liquidity.utilization_rate()?))?
+                .try_into()
+                .map_err(|_| LendingError::MathOverflow)?,
+            borrow_rate_bps: signed_rate_to_bps(self.current_borrow_rate()?)?,
+        };
+        self.rate_samples.push(sample, current_slot);
+        Ok(())
+    }
+
+    /// Rates sampled by refreshes, oldest first
+    pub fn rate_history(&self) -> &[RateSample] {
+        &self.rate_samples.samples[..self.rate_samples.len as usize]
+    }
+
+    /// Accrue the rewards distributed since the last update to the reserve
+    /// collateral supply. Must be called before the last update slot moves.
+    pub fn accrue_rewards(&mut self, current_slot: Slot) -> ProgramResult {
+        let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
+        self.reward_pool
+            .accrue(slots_elapsed, self.collateral.mint_total_supply)
+    }
+
+    /// Market value of an amount of liquidity in the quote currency
+    pub fn market_value(&self, liquidity_amount: Decimal) -> Result<Decimal, ProgramError> {
+        // @TODO: add lookup table https://git.io/JOCYq
+        let decimals = 10u64
+            .checked_pow(self.liquidity.mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+        liquidity_amount
+            .try_mul(self.liquidity.market_price)?
+            .try_div(decimals)
+    }
+
+    /// Borrow liquidity up to a maximum market value. The maximum is in
+    /// weighted borrow value, as the obligation's remaining borrow value.
     pub fn calculate_borrow(
         &self,
         amount_to_borrow: u64,
         max_borrow_value: Decimal,
     ) -> Result<CalculateBorrowResult, ProgramError> {
+        let max_borrow_value = max_borrow_value.try_div(self.config.borrow_weight())?;
         // @TODO: add lookup table https://git.io/JOCYq
         let decimals = 10u64
             .checked_pow(self.liquidity.mint_decimals as u32)
@@ -213,15 +605,86 @@ impl Reserve {
         })
     }
 
-    /// Liquidate some or all of an unhealthy obligation
+    /// Liquidation threshold of the reserve's collateral at its current
+    /// utilization
+    ///
+    /// The threshold is the liquidation threshold up to the stress utilization
+    /// rate, and tightens linearly from there to the stressed liquidation
+    /// threshold at full utilization.
+    pub fn liquidation_threshold_rate(&self) -> Result<Rate, ProgramError> {
+        let threshold = self.config.liquidation_threshold;
+        let stressed_threshold = self.config.stressed_liquidation_threshold;
+        if stressed_threshold == 0 || stressed_threshold >= threshold {
+            return Ok(Rate::from_percent(threshold));
+        }
+
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let stress_utilization_rate = Rate::from_percent(self.config.stress_utilization_rate);
+        if utilization_rate <= stress_utilization_rate {
+            return Ok(Rate::from_percent(threshold));
+        }
+
+        let stress = utilization_rate
+            .try_sub(stress_utilization_rate)?
+            .try_div(Rate::one().try_sub(stress_utilization_rate)?)?
+            .min(Rate::one());
+        Rate::from_percent(threshold)
+            .try_sub(Rate::from_percent(threshold - stressed_threshold).try_mul(stress)?)
+    }
+
+    /// Liquidation bonus scaled by how far the obligation is underwater
+    ///
+    /// The bonus is the min liquidation bonus, or the liquidation bonus when
+    /// unset, when the borrowed value is at the unhealthy borrow value, grows
+    /// linearly as the borrowed value approaches the deposited value, and is
+    /// clamped at the max liquidation bonus from there on.
+    pub fn liquidation_bonus_rate(&self, obligation: &Obligation) -> Result<Rate, ProgramError> {
+        let base_bonus = if self.config.min_liquidation_bonus == 0 {
+            self.config.liquidation_bonus
+        } else {
+            self.config.min_liquidation_bonus
+        };
+        let max_bonus = self.config.max_liquidation_bonus.max(base_bonus);
+        if max_bonus == base_bonus || obligation.borrowed_value <= obligation.unhealthy_borrow_value
+        {
+            return Ok(Rate::from_percent(base_bonus));
+        }
+
+        let severity = if obligation.deposited_value > obligation.unhealthy_borrow_value {
+            obligation
+                .borrowed_value
+                .try_sub(obligation.unhealthy_borrow_value)?
+                .try_div(
+                    obligation
+                        .deposited_value
+                        .try_sub(obligation.unhealthy_borrow_value)?,
+                )?
+                .min(Decimal::one())
+        } else {
+            Decimal::one()
+        };
+
+        let bonus = Decimal::from_percent(max_bonus - base_bonus)
+            .try_mul(severity)?
+            .try_add(Decimal::from_percent(base_bonus))?;
+        Rate::try_from(bonus)
+    }
+
+    /// Liquidate some or all of an unhealthy obligation, repaying liquidity to
+    /// a reserve with the given config. Obligations with a borrowed value
+    /// below the market's dust liquidation threshold ignore the close factor.
     pub fn calculate_liquidation(
         &self,
         amount_to_liquidate: u64,
         obligation: &Obligation,
         liquidity: &ObligationLiquidity,
         collateral: &ObligationCollateral,
+        repay_reserve_config: &ReserveConfig,
+        dust_liquidation_threshold: Decimal,
     ) -> Result<CalculateLiquidationResult, ProgramError> {
-        let bonus_rate = Rate::from_percent(self.config.liquidation_bonus).try_add(Rate::one())?;
+        let bonus_rate = self
+            .liquidation_bonus_rate(obligation)?
+            .try_add(Rate::one())?;
 
         let max_amount = if amount_to_liquidate == u64::MAX {
             liquidity.borrowed_amount_wads
@@ -260,16 +723,27 @@ impl Reserve {
         } else {
             // calculate settle_amount and withdraw_amount, repay_amount is settle_amount
             // rounded
-            let liquidation_amount = obligation
-                .max_liquidation_amount(liquidity)?
-                .min(max_amount);
+            let is_dust = obligation.borrowed_value < dust_liquidation_threshold;
+            let liquidation_amount = if is_dust {
+                max_amount
+            } else {
+                obligation
+                    .max_liquidation_amount(liquidity, repay_reserve_config)?
+                    .min(max_amount)
+            };
             let liquidation_pct = liquidation_amount.try_div(liquidity.borrowed_amount_wads)?;
-            let liquidation_value = liquidity
-                .market_value
-                .try_mul(liquidation_pct)?
-                .try_mul(bonus_rate)?;
+            let repaid_value = liquidity.market_value.try_mul(liquidation_pct)?;
+            let liquidation_value = repaid_value.try_mul(bonus_rate)?;
 
             match liquidation_value.cmp(&collateral.market_value) {
+                // dust borrows are settled in full for all of the collateral,
+                // with a smaller bonus, as long as the collateral still covers
+                // the repaid value
+                Ordering::Greater if is_dust && repaid_value <= collateral.market_value => {
+                    settle_amount = liquidation_amount;
+                    repay_amount = settle_amount.try_ceil_u64()?;
+                    withdraw_amount = collateral.deposited_amount;
+                }
                 Ordering::Greater => {
                     let repay_pct = collateral.market_value.try_div(liquidation_value)?;
                     settle_amount = liquidation_amount.try_mul(repay_pct)?;
@@ -292,12 +766,137 @@ impl Reserve {
             }
         }
 
+        let bonus_amount =
+            calculate_liquidation_bonus(withdraw_amount, bonus_rate)?.try_floor_u64()?;
+        let protocol_fee = self.calculate_protocol_liquidation_fee(withdraw_amount, bonus_rate)?;
+
         Ok(CalculateLiquidationResult {
             settle_amount,
             repay_amount,
             withdraw_amount,
+            bonus_amount,
+            protocol_fee,
         })
     }
+
+    /// Share of the liquidation bonus in the withdraw amount paid to the
+    /// collateral fee receiver, rounded down in favor of the liquidator. No
+    /// fee is taken while the collateral fee receiver is unset.
+    fn calculate_protocol_liquidation_fee(
+        &self,
+        withdraw_amount: u64,
+        bonus_rate: Rate,
+    ) -> Result<u64, ProgramError> {
+        if self.config.protocol_liquidation_fee == 0
+            || self.collateral.fee_receiver == Pubkey::default()
+        {
+            return Ok(0);
+        }
+
+        calculate_liquidation_bonus(withdraw_amount, bonus_rate)?
+            .try_mul(Rate::from_percent(self.config.protocol_liquidation_fee))?
+            .try_floor_u64()
+    }
+
+    /// Unpacks a reserve account in either the V1 or the V2 layout, told apart
+    /// by the version. V1 reserves read the fields added to their padding
+    /// since as 0, which stands for each field's default. The borrow and
+    /// deposit limits, where 0 is a limit of its own, are read as unlimited.
+    pub fn unpack_account(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != RESERVE_V1_LEN && src.len() != RESERVE_LEN {
+            msg!("Reserve account size is invalid");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut reserve = Self::unpack_from_slice(src)?;
+        if !reserve.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if src.len() != Self::account_len(reserve.version) {
+            msg!("Reserve account size does not match its version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if src.len() == RESERVE_V1_LEN {
+            reserve.config.write_default_limits();
+        }
+        Ok(reserve)
+    }
+
+    /// Packs a reserve into its account. A V1 account is written in the V1
+    /// layout at the last V1 version, fields added since need the account
+    /// migrated first.
+    pub fn pack_account(mut src: Self, dst: &mut [u8]) -> ProgramResult {
+        match dst.len() {
+            RESERVE_V1_LEN => {
+                if src.config.rate_history_interval_slots != 0 {
+                    msg!("Reserve must be migrated to the current layout to record a rate history");
+                    return Err(LendingError::AccountNotMigrated.into());
+                }
+                if src.redeem_queue != RedeemQueue::default() {
+                    msg!("Reserve must be migrated to the current layout to queue redemptions");
+                    return Err(LendingError::AccountNotMigrated.into());
+                }
+                src.version = src.version.min(RESERVE_V2_VERSION - 1);
+            }
+            RESERVE_LEN => {}
+            _ => {
+                msg!("Reserve account size is invalid");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
+    /// Unpacks a reserve account still in the V1 layout, to be migrated to the
+    /// V2 layout. Fails if the account is already in the V2 layout.
+    pub fn unpack_legacy_account(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == RESERVE_LEN {
+            msg!("Reserve is already in the current layout");
+            return Err(LendingError::AccountAlreadyMigrated.into());
+        }
+        Self::unpack_account(src)
+    }
+
+    /// Migrate to the current version, once the account is resized to
+    /// `Reserve::LEN`. Fields added to the V1 padding that the reserve never
+    /// set are written with the defaults their 0 stands for.
+    pub fn migrate(&mut self) {
+        self.version = PROGRAM_VERSION;
+        self.config.write_defaults();
+        if self.liquidity.flash_loan_fee_receiver == Pubkey::default() {
+            self.liquidity.flash_loan_fee_receiver = self.liquidity.fee_receiver;
+        }
+    }
+
+    /// Size of a reserve account at the given version
+    pub fn account_len(version: u8) -> usize {
+        if version < RESERVE_V2_VERSION {
+            RESERVE_V1_LEN
+        } else {
+            RESERVE_LEN
+        }
+    }
+}
+
+/// Rate as basis points, rounded to the nearest one
+fn rate_to_bps(rate: Decimal) -> Result<u64, ProgramError> {
+    rate.try_mul(10_000)?.try_round_u64()
+}
+
+/// Signed rate as basis points, rounded to the nearest one
+fn signed_rate_to_bps(rate: SignedDecimal) -> Result<i16, ProgramError> {
+    let bps = i16::try_from(rate_to_bps(rate.abs())?).map_err(|_| LendingError::MathOverflow)?;
+    Ok(if rate.is_negative() { -bps } else { bps })
+}
+
+/// Liquidation bonus portion of the withdraw amount, where the bonus rate
+/// includes the repaid value
+fn calculate_liquidation_bonus(
+    withdraw_amount: u64,
+    bonus_rate: Rate,
+) -> Result<Decimal, ProgramError> {
+    let withdraw_amount = Decimal::from(withdraw_amount);
+    withdraw_amount.try_sub(withdraw_amount.try_div(bonus_rate)?)
 }
 
 /// Initialize a reserve
@@ -346,6 +945,11 @@ pub struct CalculateLiquidationResult {
     pub repay_amount: u64,
     /// Amount of collateral to withdraw in exchange for repay amount
     pub withdraw_amount: u64,
+    /// Liquidation bonus portion of the withdraw amount, rounded down
+    pub bonus_amount: u64,
+    /// Portion of the withdraw amount paid to the protocol instead of the
+    /// liquidator
+    pub protocol_fee: u64,
 }
 
 /// Reserve liquidity
@@ -359,6 +963,11 @@ pub struct ReserveLiquidity {
     pub supply_pubkey: Pubkey,
     /// Reserve liquidity fee receiver address
     pub fee_receiver: Pubkey,
+    /// Reserve liquidity flash loan fee receiver address, flash loan fees are
+    /// paid to the fee receiver while unset
+    pub flash_loan_fee_receiver: Pubkey,
+    /// Reserve liquidity oracle kind
+    pub oracle_kind: OracleKind,
     /// Reserve liquidity oracle account
     pub oracle_pubkey: Pubkey,
     /// Reserve liquidity available
@@ -369,6 +978,9 @@ pub struct ReserveLiquidity {
     pub cumulative_borrow_rate_wads: Decimal,
     /// Reserve liquidity market price in quote currency
     pub market_price: Decimal,
+    /// Slot the oracle published the market price at, or the slot of the
+    /// refresh that used a price override
+    pub market_price_slot: Slot,
 }
 
 impl ReserveLiquidity {
@@ -379,11 +991,24 @@ impl ReserveLiquidity {
             mint_decimals: params.mint_decimals,
             supply_pubkey: params.supply_pubkey,
             fee_receiver: params.fee_receiver,
+            flash_loan_fee_receiver: Pubkey::default(),
+            oracle_kind: params.oracle_kind,
             oracle_pubkey: params.oracle_pubkey,
             available_amount: 0,
             borrowed_amount_wads: Decimal::zero(),
             cumulative_borrow_rate_wads: Decimal::one(),
             market_price: params.market_price,
+            market_price_slot: params.market_price_slot,
+        }
+    }
+
+    /// Account flash loan fees are paid to, the flash loan fee receiver if set
+    /// or the fee receiver otherwise
+    pub fn flash_loan_fee_destination(&self) -> &Pubkey {
+        if self.flash_loan_fee_receiver == Pubkey::default() {
+            &self.fee_receiver
+        } else {
+            &self.flash_loan_fee_receiver
         }
     }
 
@@ -443,6 +1068,20 @@ impl ReserveLiquidity {
         Ok(())
     }
 
+    /// Calculate the maximum amount that can be withdrawn without pushing the
+    /// utilization rate above the given rate
+    pub fn max_withdraw_amount(&self, max_utilization_rate: Rate) -> Result<u64, ProgramError> {
+        let min_total_supply = self.borrowed_amount_wads.try_div(max_utilization_rate)?;
+        let total_supply = self.total_supply()?;
+        if min_total_supply >= total_supply {
+            return Ok(0);
+        }
+        Ok(total_supply
+            .try_sub(min_total_supply)?
+            .try_floor_u64()?
+            .min(self.available_amount))
+    }
+
     /// Calculate the liquidity utilization rate of the reserve
     pub fn utilization_rate(&self) -> Result<Rate, ProgramError> {
         let total_supply = self.total_supply()?;
@@ -452,16 +1091,34 @@ impl ReserveLiquidity {
         self.borrowed_amount_wads.try_div(total_supply)?.try_into()
     }
 
-    /// Compound current borrow rate over elapsed slots
+    /// Compound current borrow rate over elapsed slots. A negative rate
+    /// shrinks borrows, but never below zero.
     fn compound_interest(
         &mut self,
-        current_borrow_rate: Rate,
+        current_borrow_rate: SignedDecimal,
         slots_elapsed: u64,
     ) -> ProgramResult {
-        let slot_interest_rate = current_borrow_rate.try_div(SLOTS_PER_YEAR)?;
-        let compounded_interest_rate = Rate::one()
-            .try_add(slot_interest_rate)?
-            .try_pow(slots_elapsed)?;
+        let slot_interest_rate =
+            Rate::try_from(current_borrow_rate.abs())?.try_div(SLOTS_PER_YEAR)?;
+        let compounded_interest_rate = if current_borrow_rate.is_negative() {
+            Rate::one().try_sub(slot_interest_rate)?
+        } else {
+            Rate::one().try_add(slot_interest_rate)?
+        }
+        .saturating_pow(
+            slots_elapsed,
+            Rate::one().try_mul(MAX_COMPOUNDED_INTEREST_RATE)?,
+        );
+        // The cumulative values compound on every refresh, so they stop
+        // accruing at the largest packable value instead of failing to pack
+        let mut compounded_interest_rate = Decimal::from(compounded_interest_rate);
+        let max_packable_wads = Decimal::from_scaled_val(u128::MAX);
+        for cumulative_wads in [self.cumulative_borrow_rate_wads, self.borrowed_amount_wads] {
+            if cumulative_wads > Decimal::zero() {
+                compounded_interest_rate =
+                    compounded_interest_rate.min(max_packable_wads.try_div(cumulative_wads)?);
+            }
+        }
         self.cumulative_borrow_rate_wads = self
             .cumulative_borrow_rate_wads
             .try_mul(compounded_interest_rate)?;
@@ -482,10 +1139,170 @@ pub struct NewReserveLiquidityParams {
     pub supply_pubkey: Pubkey,
     /// Reserve liquidity fee receiver address
     pub fee_receiver: Pubkey,
+    /// Reserve liquidity oracle kind
+    pub oracle_kind: OracleKind,
     /// Reserve liquidity oracle account
     pub oracle_pubkey: Pubkey,
     /// Reserve liquidity market price in quote currency
     pub market_price: Decimal,
+    /// Slot the oracle published the market price at
+    pub market_price_slot: Slot,
+}
+
+/// Oracle program a reserve reads its liquidity market price from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OracleKind {
+    /// Pyth price account
+    #[default]
+    Pyth = 0,
+    /// Switchboard V2 aggregator account
+    Switchboard = 1,
+}
+
+impl TryFrom<u8> for OracleKind {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(OracleKind::Pyth),
+            1 => Ok(OracleKind::Switchboard),
+            _ => {
+                msg!("Oracle kind is invalid");
+                Err(LendingError::InvalidOracleConfig.into())
+            }
+        }
+    }
+}
+
+/// Lifecycle status of a reserve
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReserveStatus {
+    /// Reserve is open to every action
+    #[default]
+    Active = 0,
+    /// Reserve is being wound down: new deposits and borrows are rejected,
+    /// while repayments, redemptions and liquidations still go through
+    Deprecated = 1,
+    /// Reserve liquidity has been drained: redemptions are rejected as well
+    Obsolete = 2,
+}
+
+impl TryFrom<u8> for ReserveStatus {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ReserveStatus::Active),
+            1 => Ok(ReserveStatus::Deprecated),
+            2 => Ok(ReserveStatus::Obsolete),
+            _ => {
+                msg!("Reserve status is invalid");
+                Err(LendingError::InvalidConfig.into())
+            }
+        }
+    }
+}
+
+/// Liquidity that left a reserve through redemptions, borrows and collateral
+/// withdrawals, in fixed windows of the configured duration
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReserveOutflow {
+    /// Slot the current window started at
+    pub window_start_slot: Slot,
+    /// Liquidity that left the reserve in the current window
+    pub window_amount: u64,
+    /// Liquidity that left the reserve in the previous window
+    pub previous_window_amount: u64,
+}
+
+/// Queue of collateral redemptions waiting for the reserve liquidity to
+/// return. Requests are given increasing tickets and fulfilled in order, the
+/// queued collateral is held in the reserve collateral supply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RedeemQueue {
+    /// Ticket of the request fulfilled next
+    pub head_ticket: u64,
+    /// Ticket given to the next queued request
+    pub next_ticket: u64,
+    /// Collateral of the queued requests not redeemed yet
+    pub collateral_amount: u64,
+}
+
+impl RedeemQueue {
+    /// Check if no request is waiting to be fulfilled
+    pub fn is_empty(&self) -> bool {
+        self.head_ticket == self.next_ticket
+    }
+}
+
+/// Incentive rewards paid out of a vault to the holders of the reserve
+/// collateral deposited in obligations. Nothing accrues while the reward rate
+/// is zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RewardPool {
+    /// Reward token account the rewards are paid from, owned by the reward
+    /// authority of the reserve
+    pub vault: Pubkey,
+    /// Bump seed for the reward authority address
+    pub authority_bump_seed: u8,
+    /// Rewards distributed per slot across the reserve collateral supply
+    pub rewards_per_slot: u64,
+    /// Rewards distributed per collateral token since the pool was set up
+    pub cumulative_rewards_per_collateral: Decimal,
+}
+
+impl RewardPool {
+    /// Distribute the rewards of the elapsed slots across the collateral
+    /// supply. Rewards of slots without collateral are not distributed.
+    pub fn accrue(&mut self, slots_elapsed: Slot, collateral_supply: u64) -> ProgramResult {
+        if self.rewards_per_slot == 0 || slots_elapsed == 0 || collateral_supply == 0 {
+            return Ok(());
+        }
+        let rewards_per_collateral = Decimal::from(self.rewards_per_slot)
+            .try_mul(slots_elapsed)?
+            .try_div(collateral_supply)?;
+        self.cumulative_rewards_per_collateral = self
+            .cumulative_rewards_per_collateral
+            .try_add(rewards_per_collateral)?;
+        Ok(())
+    }
+}
+
+/// Utilization and borrow rates of a reserve at a refresh
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateSample {
+    /// Utilization rate, in basis points
+    pub utilization_rate_bps: u16,
+    /// Borrow rate, in basis points, negative when the configured rebate
+    /// exceeds the utilization curve
+    pub borrow_rate_bps: i16,
+}
+
+/// Most recent rate samples of a reserve, the oldest is dropped once
+/// RATE_HISTORY_LEN samples are recorded
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RateHistory {
+    /// Slot the most recent sample was recorded at
+    pub last_sample_slot: Slot,
+    /// Samples recorded, at most RATE_HISTORY_LEN
+    pub len: u8,
+    /// Samples, oldest first, of which the first `len` are recorded
+    pub samples: [RateSample; RATE_HISTORY_LEN],
+}
+
+impl RateHistory {
+    /// Record a sample, dropping the oldest one when the history is full
+    pub fn push(&mut self, sample: RateSample, current_slot: Slot) {
+        let len = self.len as usize;
+        if len < RATE_HISTORY_LEN {
+            self.samples[len] = sample;
+            self.len += 1;
+        } else {
+            self.samples.copy_within(1.., 0);
+            self.samples[RATE_HISTORY_LEN - 1] = sample;
+        }
+        self.last_sample_slot = current_slot;
+    }
 }
 
 /// Reserve collateral
@@ -497,6 +1314,9 @@ pub struct ReserveCollateral {
     pub mint_total_supply: u64,
     /// Reserve collateral supply address
     pub supply_pubkey: Pubkey,
+    /// Reserve collateral fee receiver address, protocol liquidation fees are
+    /// paid to it once set
+    pub fee_receiver: Pubkey,
 }
 
 impl ReserveCollateral {
@@ -506,6 +1326,7 @@ impl ReserveCollateral {
             mint_pubkey: params.mint_pubkey,
             mint_total_supply: 0,
             supply_pubkey: params.supply_pubkey,
+            fee_receiver: Pubkey::default(),
         }
     }
 
@@ -599,18 +1420,102 @@ pub struct ReserveConfig {
     /// Target ratio of the value of borrows to deposits, as a percentage
     /// 0 if use as collateral is disabled
     pub loan_to_value_ratio: u8,
+    /// Share of the deposited market value counted toward borrowing power
+    /// before the loan to value ratio is applied, as a percentage. Does not
+    /// affect the liquidation threshold. 0 uses the default weight of 100.
+    pub collateral_weight: u8,
     /// Bonus a liquidator gets when repaying part of an unhealthy obligation,
     /// as a percentage
     pub liquidation_bonus: u8,
+    /// Bonus a liquidator gets when the obligation's borrowed value is at its
+    /// unhealthy borrow value, as a percentage. Zero uses the liquidation
+    /// bonus.
+    pub min_liquidation_bonus: u8,
+    /// Bonus a liquidator gets when the obligation's borrowed value reaches
+    /// its deposited value, as a percentage. The effective bonus is
+    /// interpolated between the min liquidation bonus and this value.
+    pub max_liquidation_bonus: u8,
     /// Loan to value ratio at which an obligation can be liquidated, as a
     /// percentage
     pub liquidation_threshold: u8,
+    /// Liquidation threshold at full utilization, as a percentage. The
+    /// effective threshold is interpolated between the liquidation threshold
+    /// at the stress utilization rate and this value. 0 disables the
+    /// tightening.
+    pub stressed_liquidation_threshold: u8,
+    /// Utilization rate above which the liquidation threshold tightens toward
+    /// the stressed liquidation threshold, as a percentage
+    pub stress_utilization_rate: u8,
     /// Min borrow APY
     pub min_borrow_rate: u8,
     /// Optimal (utilization) borrow APY
     pub optimal_borrow_rate: u8,
     /// Max borrow APY
     pub max_borrow_rate: u8,
+    /// Utilization rate at which the borrow rate reaches the max borrow rate,
+    /// as a percentage. Above it the rate rises to the super max borrow rate
+    /// at full utilization. 0 disables the third segment, the max borrow rate
+    /// is then reached at full utilization.
+    pub max_utilization_rate: u8,
+    /// Borrow APY at full utilization when a max utilization rate is set
+    pub super_max_borrow_rate: u8,
+    /// Utilization rate above which collateral can no longer be redeemed, as
+    /// a percentage. 0 disables the limit.
+    pub max_withdraw_utilization: u8,
+    /// Borrow APY subtracted from the utilization curve, as a percentage. The
+    /// resulting rate can go below zero. 0 disables the rebate.
+    pub borrow_rate_rebate: u8,
+    /// Lowest borrow APY once the rebate is applied, as a negative percentage
+    pub borrow_rate_floor: u8,
+    /// Maximum total amount of liquidity that can be borrowed from the
+    /// reserve. u64::MAX disables the limit, 0 allows no new borrows.
+    pub borrow_limit: u64,
+    /// Maximum total amount of liquidity supplied to the reserve, available
+    /// and borrowed. u64::MAX disables the limit, 0 allows no new deposits.
+    pub deposit_limit: u64,
+    /// Slots after which the oracle price is rejected as stale. 0 uses the
+    /// default of the reserve's oracle kind.
+    pub max_oracle_age_slots: u64,
+    /// Multiplier applied to the market value of borrows from the reserve in
+    /// an obligation's borrowed value, scaled by WAD. Weights above 1.0 make
+    /// borrows of riskier assets consume more borrowing power. 0 uses the
+    /// default weight of 1.0.
+    pub borrow_weight_wad: u64,
+    /// Share of the liquidation bonus paid to the collateral fee receiver
+    /// instead of the liquidator, as a percentage
+    pub protocol_liquidation_fee: u8,
+    /// Share of an obligation's borrowed value that can be repaid to the
+    /// reserve in a single liquidation, as a percentage
+    pub liquidation_close_factor: u8,
+    /// Borrowed amount, in the smallest unit of the reserve liquidity, below
+    /// which the rest of a borrow left by a liquidation can be liquidated in
+    /// full. 0 disables full liquidations.
+    pub liquidation_dust_threshold: u64,
+    /// Smallest amount of liquidity that can be deposited into the reserve at
+    /// once
+    pub min_deposit_amount: u64,
+    /// Widest oracle confidence interval accepted, as basis points of the
+    /// price. 0 disables the check.
+    pub max_confidence_interval_bps: u16,
+    /// Duration of the outflow rate limit window, in slots. 0 disables the
+    /// rate limit.
+    pub outflow_window_duration_slots: u64,
+    /// Maximum amount of liquidity that can leave the reserve through
+    /// redemptions, borrows and collateral withdrawals within the outflow
+    /// window
+    pub max_outflow: u64,
+    /// Most slots of interest accrued by a single refresh, a reserve left
+    /// unrefreshed for longer catches up over several refreshes. 0 disables
+    /// the cap.
+    pub max_accrual_slots_per_refresh: u64,
+    /// Slots between the rate samples refreshes record in the rate history.
+    /// 0 disables the rate history, which needs the reserve in the V2 layout.
+    pub rate_history_interval_slots: u64,
+    /// Collateral from the reserve cannot be combined with collateral from
+    /// other reserves in an obligation
+    pub isolated: bool,
+    /// Liquidity can be borrowed against isolated collateral
+    pub borrowable_in_isolation: bool,
     /// Program owner fees assessed, separate from gains due to interest accrual
     pub fees: ReserveFees,
 }
@@ -620,46 +1525,377 @@ impl ReserveConfig {
     /// configs
     pub fn validate(&self) -> ProgramResult {
         if self.optimal_utilization_rate > 100 {
-            msg!("Optimal utilization rate must be in range [0, 100]");
-            return Err(LendingError::InvalidConfig.into());
+            msg!(
+                "Optimal utilization rate {} must be in range [0, 100]",
+                self.optimal_utilization_rate
+            );
+            return Err(LendingError::InvalidBorrowRateCurve.into());
         }
         if self.loan_to_value_ratio >= 100 {
-            msg!("Loan to value ratio must be in range [0, 100)");
-            return Err(LendingError::InvalidConfig.into());
+            msg!(
+                "Loan to value ratio {} must be in range [0, 100)",
+                self.loan_to_value_ratio
+            );
+            return Err(LendingError::InvalidLtvRatio.into());
         }
-        if self.liquidation_bonus > 100 {
-            msg!("Liquidation bonus must be in range [0, 100]");
+        if self.collateral_weight > 100 {
+            msg!(
+                "Collateral weight {} must be in range [0, 100]",
+                self.collateral_weight
+            );
             return Err(LendingError::InvalidConfig.into());
         }
-        if self.liquidation_threshold <= self.loan_to_value_ratio
+        if self.liquidation_bonus > 100 {
+            msg!(
+                "Liquidation bonus {} must be in range [0, 100]",
+                self.liquidation_bonus
+            );
+            return Err(LendingError::InvalidLiquidationBonus.into());
+        }
+        if self.max_liquidation_bonus < self.liquidation_bonus || self.max_liquidation_bonus > 100 {
+            msg!(
+                "Max liquidation bonus {} must be in range [liquidation bonus {}, 100]",
+                self.max_liquidation_bonus,
+                self.liquidation_bonus
+            );
+            return Err(LendingError::InvalidLiquidationBonus.into());
+        }
+        if self.min_liquidation_bonus > self.max_liquidation_bonus {
+            msg!(
+                "Min liquidation bonus {} must be in range [0, max liquidation bonus {}]",
+                self.min_liquidation_bonus,
+                self.max_liquidation_bonus
+            );
+            return Err(LendingError::InvalidLiquidationBonus.into());
+        }
+        // deposit-only reserves have neither a loan to value ratio nor a
+        // liquidation threshold
+        if (self.liquidation_threshold <= self.loan_to_value_ratio && !self.is_deposit_only())
             || self.liquidation_threshold > 100
         {
-            msg!("Liquidation threshold must be in range (LTV, 100]");
-            return Err(LendingError::InvalidConfig.into());
+            msg!(
+                "Liquidation threshold {} must be in range (LTV {}, 100], or 0 with a LTV of 0",
+                self.liquidation_threshold,
+                self.loan_to_value_ratio
+            );
+            return Err(LendingError::InvalidLiquidationThreshold.into());
+        }
+        if self.stressed_liquidation_threshold != 0 {
+            if self.stressed_liquidation_threshold <= self.loan_to_value_ratio
+                || self.stressed_liquidation_threshold > self.liquidation_threshold
+            {
+                msg!(
+                    "Stressed liquidation threshold {} must be in range (LTV {}, liquidation threshold {}]",
+                    self.stressed_liquidation_threshold,
+                    self.loan_to_value_ratio,
+                    self.liquidation_threshold
+                );
+                return Err(LendingError::InvalidLiquidationThreshold.into());
+            }
+            if self.stress_utilization_rate >= 100 {
+                msg!(
+                    "Stress utilization rate {} must be in range [0, 100)",
+                    self.stress_utilization_rate
+                );
+                return Err(LendingError::InvalidLiquidationThreshold.into());
+            }
         }
         if self.optimal_borrow_rate < self.min_borrow_rate {
-            msg!("Optimal borrow rate must be >= min borrow rate");
-            return Err(LendingError::InvalidConfig.into());
+            msg!(
+                "Optimal borrow rate {} must be >= min borrow rate {}",
+                self.optimal_borrow_rate,
+                self.min_borrow_rate
+            );
+            return Err(LendingError::InvalidBorrowRateCurve.into());
         }
         if self.optimal_borrow_rate > self.max_borrow_rate {
-            msg!("Optimal borrow rate must be <= max borrow rate");
+            msg!(
+                "Optimal borrow rate {} must be <= max borrow rate {}",
+                self.optimal_borrow_rate,
+                self.max_borrow_rate
+            );
+            return Err(LendingError::InvalidBorrowRateCurve.into());
+        }
+        if self.max_utilization_rate == 0 {
+            if self.super_max_borrow_rate != 0 {
+                msg!(
+                    "Super max borrow rate {} must be 0 when max utilization rate is 0",
+                    self.super_max_borrow_rate
+                );
+                return Err(LendingError::InvalidBorrowRateCurve.into());
+            }
+        } else {
+            if self.max_utilization_rate < self.optimal_utilization_rate
+                || self.max_utilization_rate > 100
+            {
+                msg!(
+                    "Max utilization rate {} must be 0 or in range [optimal utilization rate {}, 100]",
+                    self.max_utilization_rate,
+                    self.optimal_utilization_rate
+                );
+                return Err(LendingError::InvalidBorrowRateCurve.into());
+            }
+            if self.super_max_borrow_rate < self.max_borrow_rate {
+                msg!(
+                    "Super max borrow rate {} must be >= max borrow rate {}",
+                    self.super_max_borrow_rate,
+                    self.max_borrow_rate
+                );
+                return Err(LendingError::InvalidBorrowRateCurve.into());
+            }
+        }
+        if self.max_withdraw_utilization > 100 {
+            msg!(
+                "Max withdraw utilization {} must be in range [0, 100]",
+                self.max_withdraw_utilization
+            );
             return Err(LendingError::InvalidConfig.into());
         }
-        if self.fees.borrow_fee_wad >= WAD {
-            msg!("Borrow fee must be in range [0, 1_000_000_000_000_000_000)");
+        if self.borrow_rate_rebate > 100 {
+            msg!(
+                "Borrow rate rebate {} must be in range [0, 100]",
+                self.borrow_rate_rebate
+            );
             return Err(LendingError::InvalidConfig.into());
         }
-        if self.fees.flash_loan_fee_wad >= WAD {
-            msg!("Flash loan fee must be in range [0, 1_000_000_000_000_000_000)");
+        if self.borrow_rate_floor > 100 {
+            msg!(
+                "Borrow rate floor {} must be in range [0, 100]",
+                self.borrow_rate_floor
+            );
             return Err(LendingError::InvalidConfig.into());
         }
-        if self.fees.host_fee_percentage > 100 {
-            msg!("Host fee percentage must be in range [0, 100]");
+        if self.max_oracle_age_slots > MAX_ORACLE_AGE_SLOTS {
+            msg!(
+                "Max oracle age {} must be in range [0, {}] slots",
+                self.max_oracle_age_slots,
+                MAX_ORACLE_AGE_SLOTS
+            );
+            return Err(LendingError::InvalidConfig.into());
+        }
+        if self.borrow_weight_wad != 0 && self.borrow_weight_wad < WAD {
+            msg!(
+                "Borrow weight {} must be 0 or at least {}",
+                self.borrow_weight_wad,
+                WAD
+            );
+            return Err(LendingError::InvalidConfig.into());
+        }
+        if self.protocol_liquidation_fee > 100 {
+            msg!(
+                "Protocol liquidation fee {} must be in range [0, 100]",
+                self.protocol_liquidation_fee
+            );
+            return Err(LendingError::InvalidConfig.into());
+        }
+        if self.liquidation_close_factor == 0 || self.liquidation_close_factor > 100 {
+            msg!(
+                "Liquidation close factor {} must be in range [1, 100]",
+                self.liquidation_close_factor
+            );
+            return Err(LendingError::InvalidConfig.into());
+        }
+        if self.max_confidence_interval_bps > 10_000 {
+            msg!(
+                "Max confidence interval {} must be in range [0, 10_000] basis points",
+                self.max_confidence_interval_bps
+            );
             return Err(LendingError::InvalidConfig.into());
         }
+        if self.fees.borrow_fee_wad >= WAD {
+            msg!(
+                "Borrow fee {} must be in range [0, {})",
+                self.fees.borrow_fee_wad,
+                WAD
+            );
+            return Err(LendingError::InvalidFeeConfig.into());
+        }
+        if self.fees.flash_loan_fee_wad >= WAD {
+            msg!(
+                "Flash loan fee {} must be in range [0, {})",
+                self.fees.flash_loan_fee_wad,
+                WAD
+            );
+            return Err(LendingError::InvalidFeeConfig.into());
+        }
+        if self.fees.host_fee_percentage > 100 {
+            msg!(
+                "Host fee percentage {} must be in range [0, 100]",
+                self.fees.host_fee_percentage
+            );
+            return Err(LendingError::InvalidFeeConfig.into());
+        }
 
         Ok(())
     }
+
+    /// (utilization rate, borrow rate) points of the utilization curve, as
+    /// percentages, in increasing utilization. Without a max utilization rate
+    /// the last point repeats the full utilization one.
+    pub fn borrow_rate_breakpoints(&self) -> [(u8, u8); 4] {
+        let (max_utilization_rate, super_max_borrow_rate) = match self.max_utilization_rate {
+            0 => (100, self.max_borrow_rate),
+            max_utilization_rate => (max_utilization_rate, self.super_max_borrow_rate),
+        };
+        [
+            (0, self.min_borrow_rate),
+            (self.optimal_utilization_rate, self.optimal_borrow_rate),
+            (max_utilization_rate, self.max_borrow_rate),
+            (100, super_max_borrow_rate),
+        ]
+    }
+
+    /// Multiplier applied to the market value of borrows from the reserve
+    pub fn borrow_weight(&self) -> Decimal {
+        match self.borrow_weight_wad {
+            0 => Decimal::one(),
+            borrow_weight_wad => Decimal::from_scaled_val(borrow_weight_wad as u128),
+        }
+    }
+
+    /// Slots after which a price of the given oracle kind is rejected as stale
+    pub fn max_oracle_age_slots(&self, oracle_kind: OracleKind) -> u64 {
+        match (self.max_oracle_age_slots, oracle_kind) {
+            (0, OracleKind::Pyth) => pyth::STALE_AFTER_SLOTS_ELAPSED,
+            (0, OracleKind::Switchboard) => switchboard::STALE_AFTER_SLOTS_ELAPSED,
+            (max_oracle_age_slots, _) => max_oracle_age_slots,
+        }
+    }
+
+    /// Share of an obligation's borrowed value that can be repaid in a single
+    /// liquidation, the default close factor for reserves packed without one
+    pub fn liquidation_close_factor_rate(&self) -> Rate {
+        match self.liquidation_close_factor {
+            0 => Rate::from_percent(LIQUIDATION_CLOSE_FACTOR),
+            close_factor => Rate::from_percent(close_factor),
+        }
+    }
+
+    /// Replace the configs read as 0 from reserves packed before they were
+    /// added with the defaults the 0 stands for. The min deposit amount, the
+    /// max oracle age and the configs that 0 disables keep their 0.
+    pub fn write_defaults(&mut self) {
+        if self.collateral_weight == 0 {
+            self.collateral_weight = 100;
+        }
+        self.write_default_limits();
+        if self.borrow_weight_wad == 0 {
+            self.borrow_weight_wad = WAD;
+        }
+        if self.liquidation_close_factor == 0 {
+            self.liquidation_close_factor = LIQUIDATION_CLOSE_FACTOR;
+        }
+        self.max_liquidation_bonus = self.max_liquidation_bonus.max(self.liquidation_bonus);
+    }
+
+    /// Replace the borrow and deposit limits read as 0 from reserves packed
+    /// before the limits were added with u64::MAX, as those reserves were
+    /// unlimited. Any other 0 limit is enforced as is.
+    fn write_default_limits(&mut self) {
+        if self.borrow_limit == 0 {
+            self.borrow_limit = u64::MAX;
+        }
+        if self.deposit_limit == 0 {
+            self.deposit_limit = u64::MAX;
+        }
+    }
+
+    /// Share of the deposited market value counted toward borrowing power,
+    /// the full value for reserves packed without a collateral weight
+    pub fn collateral_weight_rate(&self) -> Rate {
+        match self.collateral_weight {
+            0 => Rate::one(),
+            collateral_weight => Rate::from_percent(collateral_weight),
+        }
+    }
+
+    /// Share of the deposited market value counted toward the allowed borrow
+    /// value, the loan to value ratio of the weighted collateral value
+    pub fn borrow_power_rate(&self) -> Result<Rate, ProgramError> {
+        self.collateral_weight_rate()
+            .try_mul(Rate::from_percent(self.loan_to_value_ratio))
+    }
+
+    /// Whether deposits of the reserve earn interest without ever backing
+    /// borrows, neither counting toward the allowed borrow value nor the
+    /// liquidation threshold
+    pub fn is_deposit_only(&self) -> bool {
+        self.loan_to_value_ratio == 0 && self.liquidation_threshold == 0
+    }
+
+    /// Check that replacing the `current` configs only reduces risk, as
+    /// required of the lending market risk authority. Ratios, bonuses, limits
+    /// and the borrow rate rebate and floor may only decrease. Borrow rates
+    /// and the rate increase past each utilization breakpoint may only
+    /// increase, steepening the curve. Every other config must be unchanged.
+    pub fn assert_tightens(&self, current: &ReserveConfig) -> ProgramResult {
+        let max_borrow_rate_spread = |config: &ReserveConfig| {
+            config
+                .max_borrow_rate
+                .saturating_sub(config.optimal_borrow_rate)
+        };
+        let super_max_borrow_rate_spread = |config: &ReserveConfig| {
+            config
+                .super_max_borrow_rate
+                .saturating_sub(config.max_borrow_rate)
+        };
+        let other_configs_unchanged = ReserveConfig {
+            loan_to_value_ratio: current.loan_to_value_ratio,
+            collateral_weight: current.collateral_weight,
+            liquidation_threshold: current.liquidation_threshold,
+            liquidation_bonus: current.liquidation_bonus,
+            max_liquidation_bonus: current.max_liquidation_bonus,
+            borrow_limit: current.borrow_limit,
+            deposit_limit: current.deposit_limit,
+            max_outflow: current.max_outflow,
+            borrow_rate_rebate: current.borrow_rate_rebate,
+            borrow_rate_floor: current.borrow_rate_floor,
+            min_borrow_rate: current.min_borrow_rate,
+            optimal_borrow_rate: current.optimal_borrow_rate,
+            max_borrow_rate: current.max_borrow_rate,
+            super_max_borrow_rate: current.super_max_borrow_rate,
+            ..*self
+        } == *current;
+        let loosened = if self.loan_to_value_ratio > current.loan_to_value_ratio {
+            "loan to value ratio"
+        } else if self.collateral_weight_rate() > current.collateral_weight_rate() {
+            "collateral weight"
+        } else if self.liquidation_threshold > current.liquidation_threshold {
+            "liquidation threshold"
+        } else if self.liquidation_bonus > current.liquidation_bonus {
+            "liquidation bonus"
+        } else if self.max_liquidation_bonus > current.max_liquidation_bonus {
+            "max liquidation bonus"
+        } else if self.borrow_limit > current.borrow_limit {
+            "borrow limit"
+        } else if self.deposit_limit > current.deposit_limit {
+            "deposit limit"
+        } else if self.max_outflow > current.max_outflow {
+            "max outflow"
+        } else if self.borrow_rate_rebate > current.borrow_rate_rebate {
+            "borrow rate rebate"
+        } else if self.borrow_rate_floor > current.borrow_rate_floor {
+            "borrow rate floor"
+        } else if self.min_borrow_rate < current.min_borrow_rate {
+            "min borrow rate"
+        } else if self.optimal_borrow_rate < current.optimal_borrow_rate {
+            "optimal borrow rate"
+        } else if self.max_borrow_rate < current.max_borrow_rate
+            || max_borrow_rate_spread(self) < max_borrow_rate_spread(current)
+        {
+            "max borrow rate"
+        } else if self.super_max_borrow_rate < current.super_max_borrow_rate
+            || super_max_borrow_rate_spread(self) < super_max_borrow_rate_spread(current)
+        {
+            "super max borrow rate"
+        } else if !other_configs_unchanged {
+            "configs other than ratios, bonuses, limits and borrow rates"
+        } else {
+            return Ok(());
+        };
+        msg!("Risk authority cannot loosen the {}", loosened);
+        Err(LendingError::RiskAuthorityCannotLoosen.into())
+    }
 }
 
 /// Additional fee information on a reserve
@@ -770,14 +2006,27 @@ impl IsInitialized for Reserve {
     }
 }
 
-const RESERVE_LEN: usize = 571; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 +
-                                // 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 248
+/// First reserve version in the V2 layout, which extends the V1 layout with
+/// room for fields that no longer fit its padding
+const RESERVE_V2_VERSION: u8 = 5;
+
+/// Size of a reserve account in the V1 layout
+pub const RESERVE_V1_LEN: usize = 571; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 +
+                                       // 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 1 + 1 + 8 + 32 + 8 + 32 + 1 + 1 + 8 + 8 + 2 + 1 + 1 + 1 +
+                                       // 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 32 + 1 + 8 + 16 + 1 + 1 + 1 + 1 + 3
+const RESERVE_LEN: usize = 827; // RESERVE_V1_LEN + 256
+/// Size of the fields the V2 layout adds after the V1 layout
+const RESERVE_V2_EXTENSION_LEN: usize = 256; // 8 + 8 + 1 + 96 + 8 + 8 + 8 + 8 + 111
+const RATE_SAMPLE_LEN: usize = 4; // 2 + 2
 impl Pack for Reserve {
     const LEN: usize = RESERVE_LEN;
 
     // @TODO: break this up by reserve / liquidity / collateral / config https://git.io/JOCca
+    /// Packs into `output`, writing the V2 extension only when the slice is
+    /// in the V2 layout
     fn pack_into_slice(&self, output: &mut [u8]) {
-        let output = array_mut_ref![output, 0, RESERVE_LEN];
+        let (output, extension) = output.split_at_mut(RESERVE_V1_LEN);
+        let output = array_mut_ref![output, 0, RESERVE_V1_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -806,6 +2055,44 @@ impl Pack for Reserve {
             config_fees_borrow_fee_wad,
             config_fees_flash_loan_fee_wad,
             config_fees_host_fee_percentage,
+            config_max_liquidation_bonus,
+            config_max_withdraw_utilization,
+            config_borrow_rate_rebate,
+            config_borrow_rate_floor,
+            config_borrow_limit,
+            config_deposit_limit,
+            pause_flags,
+            config_collateral_weight,
+            liquidity_oracle_kind,
+            config_max_oracle_age_slots,
+            liquidity_flash_loan_fee_receiver,
+            config_borrow_weight_wad,
+            collateral_fee_receiver,
+            config_protocol_liquidation_fee,
+            config_liquidation_close_factor,
+            config_liquidation_dust_threshold,
+            config_min_deposit_amount,
+            config_max_confidence_interval_bps,
+            config_max_utilization_rate,
+            config_super_max_borrow_rate,
+            status,
+            config_outflow_window_duration_slots,
+            config_max_outflow,
+            outflow_window_start_slot,
+            outflow_window_amount,
+            outflow_previous_window_amount,
+            config_max_accrual_slots_per_refresh,
+            unaccrued_slots,
+            config_isolated,
+            config_borrowable_in_isolation,
+            reward_pool_vault,
+            reward_pool_authority_bump_seed,
+            reward_pool_rewards_per_slot,
+            reward_pool_cumulative_rewards_per_collateral,
+            config_min_liquidation_bonus,
+            price_override,
+            config_stressed_liquidation_threshold,
+            config_stress_utilization_rate,
             _padding,
         ) = mut_array_refs![
             output,
@@ -835,7 +2122,45 @@ impl Pack for Reserve {
             8,
             8,
             1,
-            248
+            1,
+            1,
+            1,
+            1,
+            8,
+            8,
+            1,
+            1,
+            1,
+            8,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            8,
+            2,
+            1,
+            1,
+            1,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            1,
+            1,
+            PUBKEY_BYTES,
+            1,
+            8,
+            16,
+            1,
+            1,
+            1,
+            1,
+            3
         ];
 
         // reserve
@@ -877,11 +2202,114 @@ impl Pack for Reserve {
         *config_fees_borrow_fee_wad = self.config.fees.borrow_fee_wad.to_le_bytes();
         *config_fees_flash_loan_fee_wad = self.config.fees.flash_loan_fee_wad.to_le_bytes();
         *config_fees_host_fee_percentage = self.config.fees.host_fee_percentage.to_le_bytes();
+        *config_max_liquidation_bonus = self.config.max_liquidation_bonus.to_le_bytes();
+        *config_max_withdraw_utilization = self.config.max_withdraw_utilization.to_le_bytes();
+        *config_borrow_rate_rebate = self.config.borrow_rate_rebate.to_le_bytes();
+        *config_borrow_rate_floor = self.config.borrow_rate_floor.to_le_bytes();
+        *config_borrow_limit = self.config.borrow_limit.to_le_bytes();
+        *config_deposit_limit = self.config.deposit_limit.to_le_bytes();
+
+        *pause_flags = self.pause_flags.to_le_bytes();
+        *config_collateral_weight = self.config.collateral_weight.to_le_bytes();
+        *liquidity_oracle_kind = (self.liquidity.oracle_kind as u8).to_le_bytes();
+        *config_max_oracle_age_slots = self.config.max_oracle_age_slots.to_le_bytes();
+        liquidity_flash_loan_fee_receiver
+            .copy_from_slice(self.liquidity.flash_loan_fee_receiver.as_ref());
+        *config_borrow_weight_wad = self.config.borrow_weight_wad.to_le_bytes();
+        collateral_fee_receiver.copy_from_slice(self.collateral.fee_receiver.as_ref());
+        *config_protocol_liquidation_fee = self.config.protocol_liquidation_fee.to_le_bytes();
+        *config_liquidation_close_factor = self.config.liquidation_close_factor.to_le_bytes();
+        *config_liquidation_dust_threshold = self.config.liquidation_dust_threshold.to_le_bytes();
+        *config_min_deposit_amount = self.config.min_deposit_amount.to_le_bytes();
+        *config_max_confidence_interval_bps = self.config.max_confidence_interval_bps.to_le_bytes();
+        *config_max_utilization_rate = self.config.max_utilization_rate.to_le_bytes();
+        *config_super_max_borrow_rate = self.config.super_max_borrow_rate.to_le_bytes();
+        *status = (self.status as u8).to_le_bytes();
+        *config_outflow_window_duration_slots =
+            self.config.outflow_window_duration_slots.to_le_bytes();
+        *config_max_outflow = self.config.max_outflow.to_le_bytes();
+        *outflow_window_start_slot = self.outflow.window_start_slot.to_le_bytes();
+        *outflow_window_amount = self.outflow.window_amount.to_le_bytes();
+        *outflow_previous_window_amount = self.outflow.previous_window_amount.to_le_bytes();
+        *config_max_accrual_slots_per_refresh =
+            self.config.max_accrual_slots_per_refresh.to_le_bytes();
+        *unaccrued_slots = self.unaccrued_slots.to_le_bytes();
+        pack_bool(self.config.isolated, config_isolated);
+        pack_bool(
+            self.config.borrowable_in_isolation,
+            config_borrowable_in_isolation,
+        );
+
+        // reward pool
+        reward_pool_vault.copy_from_slice(self.reward_pool.vault.as_ref());
+        *reward_pool_authority_bump_seed = self.reward_pool.authority_bump_seed.to_le_bytes();
+        *reward_pool_rewards_per_slot = self.reward_pool.rewards_per_slot.to_le_bytes();
+        pack_decimal(
+            self.reward_pool.cumulative_rewards_per_collateral,
+            reward_pool_cumulative_rewards_per_collateral,
+        );
+
+        *config_min_liquidation_bonus = self.config.min_liquidation_bonus.to_le_bytes();
+        pack_bool(self.price_override, price_override);
+        *config_stressed_liquidation_threshold =
+            self.config.stressed_liquidation_threshold.to_le_bytes();
+        *config_stress_utilization_rate = self.config.stress_utilization_rate.to_le_bytes();
+
+        if extension.is_empty() {
+            return;
+        }
+        let extension = array_mut_ref![extension, 0, RESERVE_V2_EXTENSION_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            config_rate_history_interval_slots,
+            rate_samples_last_sample_slot,
+            rate_samples_len,
+            rate_samples_flat,
+            redeem_queue_head_ticket,
+            redeem_queue_next_ticket,
+            redeem_queue_collateral_amount,
+            liquidity_market_price_slot,
+            _padding,
+        ) = mut_array_refs![
+            extension,
+            8,
+            8,
+            1,
+            RATE_SAMPLE_LEN * RATE_HISTORY_LEN,
+            8,
+            8,
+            8,
+            8,
+            111
+        ];
+
+        *config_rate_history_interval_slots = self.config.rate_history_interval_slots.to_le_bytes();
+
+        // rate history
+        *rate_samples_last_sample_slot = self.rate_samples.last_sample_slot.to_le_bytes();
+        *rate_samples_len = self.rate_samples.len.to_le_bytes();
+        let mut offset = 0;
+        for sample in &self.rate_samples.samples {
+            let sample_flat = array_mut_ref![rate_samples_flat, offset, RATE_SAMPLE_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (utilization_rate_bps, borrow_rate_bps) = mut_array_refs![sample_flat, 2, 2];
+            *utilization_rate_bps = sample.utilization_rate_bps.to_le_bytes();
+            *borrow_rate_bps = sample.borrow_rate_bps.to_le_bytes();
+            offset += RATE_SAMPLE_LEN;
+        }
+
+        // redeem queue
+        *redeem_queue_head_ticket = self.redeem_queue.head_ticket.to_le_bytes();
+        *redeem_queue_next_ticket = self.redeem_queue.next_ticket.to_le_bytes();
+        *redeem_queue_collateral_amount = self.redeem_queue.collateral_amount.to_le_bytes();
+
+        *liquidity_market_price_slot = self.liquidity.market_price_slot.to_le_bytes();
     }
 
     /// Unpacks a byte buffer into a [ReserveInfo](struct.ReserveInfo.html).
     fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![input, 0, RESERVE_LEN];
+        let (input, extension) = input.split_at(RESERVE_V1_LEN);
+        let input = array_ref![input, 0, RESERVE_V1_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -910,6 +2338,44 @@ impl Pack for Reserve {
             config_fees_borrow_fee_wad,
             config_fees_flash_loan_fee_wad,
             config_fees_host_fee_percentage,
+            config_max_liquidation_bonus,
+            config_max_withdraw_utilization,
+            config_borrow_rate_rebate,
+            config_borrow_rate_floor,
+            config_borrow_limit,
+            config_deposit_limit,
+            pause_flags,
+            config_collateral_weight,
+            liquidity_oracle_kind,
+            config_max_oracle_age_slots,
+            liquidity_flash_loan_fee_receiver,
+            config_borrow_weight_wad,
+            collateral_fee_receiver,
+            config_protocol_liquidation_fee,
+            config_liquidation_close_factor,
+            config_liquidation_dust_threshold,
+            config_min_deposit_amount,
+            config_max_confidence_interval_bps,
+            config_max_utilization_rate,
+            config_super_max_borrow_rate,
+            status,
+            config_outflow_window_duration_slots,
+            config_max_outflow,
+            outflow_window_start_slot,
+            outflow_window_amount,
+            outflow_previous_window_amount,
+            config_max_accrual_slots_per_refresh,
+            unaccrued_slots,
+            config_isolated,
+            config_borrowable_in_isolation,
+            reward_pool_vault,
+            reward_pool_authority_bump_seed,
+            reward_pool_rewards_per_slot,
+            reward_pool_cumulative_rewards_per_collateral,
+            config_min_liquidation_bonus,
+            price_override,
+            config_stressed_liquidation_threshold,
+            config_stress_utilization_rate,
             _padding,
         ) = array_refs![
             input,
@@ -939,7 +2405,45 @@ impl Pack for Reserve {
             8,
             8,
             1,
-            248
+            1,
+            1,
+            1,
+            1,
+            8,
+            8,
+            1,
+            1,
+            1,
+            8,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            8,
+            2,
+            1,
+            1,
+            1,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            1,
+            1,
+            PUBKEY_BYTES,
+            1,
+            8,
+            16,
+            1,
+            1,
+            1,
+            1,
+            3
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -948,6 +2452,64 @@ impl Pack for Reserve {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let mut config_rate_history_interval_slots = 0;
+        let mut rate_samples = RateHistory::default();
+        let mut redeem_queue = RedeemQueue::default();
+        let mut market_price_slot = 0;
+        if !extension.is_empty() {
+            let extension = array_ref![extension, 0, RESERVE_V2_EXTENSION_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (
+                rate_history_interval_slots,
+                rate_samples_last_sample_slot,
+                rate_samples_len,
+                rate_samples_flat,
+                redeem_queue_head_ticket,
+                redeem_queue_next_ticket,
+                redeem_queue_collateral_amount,
+                liquidity_market_price_slot,
+                _padding,
+            ) = array_refs![
+                extension,
+                8,
+                8,
+                1,
+                RATE_SAMPLE_LEN * RATE_HISTORY_LEN,
+                8,
+                8,
+                8,
+                8,
+                111
+            ];
+
+            config_rate_history_interval_slots = u64::from_le_bytes(*rate_history_interval_slots);
+            rate_samples.last_sample_slot = u64::from_le_bytes(*rate_samples_last_sample_slot);
+            rate_samples.len = u8::from_le_bytes(*rate_samples_len);
+            if rate_samples.len as usize > RATE_HISTORY_LEN {
+                msg!("Reserve rate history length is invalid");
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let mut offset = 0;
+            for sample in rate_samples.samples.iter_mut() {
+                let sample_flat = array_ref![rate_samples_flat, offset, RATE_SAMPLE_LEN];
+                #[allow(clippy::ptr_offset_with_cast)]
+                let (utilization_rate_bps, borrow_rate_bps) = array_refs![sample_flat, 2, 2];
+                *sample = RateSample {
+                    utilization_rate_bps: u16::from_le_bytes(*utilization_rate_bps),
+                    borrow_rate_bps: i16::from_le_bytes(*borrow_rate_bps),
+                };
+                offset += RATE_SAMPLE_LEN;
+            }
+
+            redeem_queue = RedeemQueue {
+                head_ticket: u64::from_le_bytes(*redeem_queue_head_ticket),
+                next_ticket: u64::from_le_bytes(*redeem_queue_next_ticket),
+                collateral_amount: u64::from_le_bytes(*redeem_queue_collateral_amount),
+            };
+
+            market_price_slot = u64::from_le_bytes(*liquidity_market_price_slot);
+        }
+
         Ok(Self {
             version,
             last_update: LastUpdate {
@@ -960,31 +2522,87 @@ impl Pack for Reserve {
                 mint_decimals: u8::from_le_bytes(*liquidity_mint_decimals),
                 supply_pubkey: Pubkey::new_from_array(*liquidity_supply_pubkey),
                 fee_receiver: Pubkey::new_from_array(*liquidity_fee_receiver),
+                flash_loan_fee_receiver: Pubkey::new_from_array(*liquidity_flash_loan_fee_receiver),
+                oracle_kind: OracleKind::try_from(u8::from_le_bytes(*liquidity_oracle_kind))?,
                 oracle_pubkey: Pubkey::new_from_array(*liquidity_oracle_pubkey),
                 available_amount: u64::from_le_bytes(*liquidity_available_amount),
                 borrowed_amount_wads: unpack_decimal(liquidity_borrowed_amount_wads),
                 cumulative_borrow_rate_wads: unpack_decimal(liquidity_cumulative_borrow_rate_wads),
                 market_price: unpack_decimal(liquidity_market_price),
+                market_price_slot,
             },
             collateral: ReserveCollateral {
                 mint_pubkey: Pubkey::new_from_array(*collateral_mint_pubkey),
                 mint_total_supply: u64::from_le_bytes(*collateral_mint_total_supply),
                 supply_pubkey: Pubkey::new_from_array(*collateral_supply_pubkey),
+                fee_receiver: Pubkey::new_from_array(*collateral_fee_receiver),
             },
             config: ReserveConfig {
                 optimal_utilization_rate: u8::from_le_bytes(*config_optimal_utilization_rate),
                 loan_to_value_ratio: u8::from_le_bytes(*config_loan_to_value_ratio),
+                collateral_weight: u8::from_le_bytes(*config_collateral_weight),
                 liquidation_bonus: u8::from_le_bytes(*config_liquidation_bonus),
+                min_liquidation_bonus: u8::from_le_bytes(*config_min_liquidation_bonus),
+                max_liquidation_bonus: u8::from_le_bytes(*config_max_liquidation_bonus),
                 liquidation_threshold: u8::from_le_bytes(*config_liquidation_threshold),
+                stressed_liquidation_threshold: u8::from_le_bytes(
+                    *config_stressed_liquidation_threshold,
+                ),
+                stress_utilization_rate: u8::from_le_bytes(*config_stress_utilization_rate),
                 min_borrow_rate: u8::from_le_bytes(*config_min_borrow_rate),
                 optimal_borrow_rate: u8::from_le_bytes(*config_optimal_borrow_rate),
                 max_borrow_rate: u8::from_le_bytes(*config_max_borrow_rate),
+                max_withdraw_utilization: u8::from_le_bytes(*config_max_withdraw_utilization),
+                borrow_rate_rebate: u8::from_le_bytes(*config_borrow_rate_rebate),
+                borrow_rate_floor: u8::from_le_bytes(*config_borrow_rate_floor),
+                borrow_limit: u64::from_le_bytes(*config_borrow_limit),
+                deposit_limit: u64::from_le_bytes(*config_deposit_limit),
+                max_oracle_age_slots: u64::from_le_bytes(*config_max_oracle_age_slots),
+                borrow_weight_wad: u64::from_le_bytes(*config_borrow_weight_wad),
+                protocol_liquidation_fee: u8::from_le_bytes(*config_protocol_liquidation_fee),
+                liquidation_close_factor: u8::from_le_bytes(*config_liquidation_close_factor),
+                liquidation_dust_threshold: u64::from_le_bytes(*config_liquidation_dust_threshold),
+                min_deposit_amount: u64::from_le_bytes(*config_min_deposit_amount),
+                max_confidence_interval_bps: u16::from_le_bytes(
+                    *config_max_confidence_interval_bps,
+                ),
+                max_utilization_rate: u8::from_le_bytes(*config_max_utilization_rate),
+                super_max_borrow_rate: u8::from_le_bytes(*config_super_max_borrow_rate),
+                outflow_window_duration_slots: u64::from_le_bytes(
+                    *config_outflow_window_duration_slots,
+                ),
+                max_outflow: u64::from_le_bytes(*config_max_outflow),
+                max_accrual_slots_per_refresh: u64::from_le_bytes(
+                    *config_max_accrual_slots_per_refresh,
+                ),
+                rate_history_interval_slots: config_rate_history_interval_slots,
+                isolated: unpack_bool(config_isolated)?,
+                borrowable_in_isolation: unpack_bool(config_borrowable_in_isolation)?,
                 fees: ReserveFees {
                     borrow_fee_wad: u64::from_le_bytes(*config_fees_borrow_fee_wad),
                     flash_loan_fee_wad: u64::from_le_bytes(*config_fees_flash_loan_fee_wad),
                     host_fee_percentage: u8::from_le_bytes(*config_fees_host_fee_percentage),
                 },
             },
+            pause_flags: u8::from_le_bytes(*pause_flags),
+            status: ReserveStatus::try_from(u8::from_le_bytes(*status))?,
+            outflow: ReserveOutflow {
+                window_start_slot: u64::from_le_bytes(*outflow_window_start_slot),
+                window_amount: u64::from_le_bytes(*outflow_window_amount),
+                previous_window_amount: u64::from_le_bytes(*outflow_previous_window_amount),
+            },
+            unaccrued_slots: u64::from_le_bytes(*unaccrued_slots),
+            reward_pool: RewardPool {
+                vault: Pubkey::new_from_array(*reward_pool_vault),
+                authority_bump_seed: u8::from_le_bytes(*reward_pool_authority_bump_seed),
+                rewards_per_slot: u64::from_le_bytes(*reward_pool_rewards_per_slot),
+                cumulative_rewards_per_collateral: unpack_decimal(
+                    reward_pool_cumulative_rewards_per_collateral,
+                ),
+            },
+            price_override: unpack_bool(price_override)?,
+            rate_samples,
+            redeem_queue,
         })
     }
 }
@@ -1011,6 +2629,48 @@ mod test {
         }
     }
 
+    // Creates utilization rates (optimal, max) where 0 <= optimal <= 100, and
+    // max is 0 or optimal <= max <= 100
+    prop_compose! {
+        fn utilization_breakpoints()(optimal_rate in 0..=100u8)(
+            optimal_rate in Just(optimal_rate),
+            max_rate in optimal_rate..=100u8,
+            disable_max_rate in any::<bool>(),
+        ) -> (u8, u8) {
+            (optimal_rate, if disable_max_rate { 0 } else { max_rate })
+        }
+    }
+
+    // Creates rates (min, opt, max, super max) where 0 <= min <= opt <= max <=
+    // super max <= MAX
+    prop_compose! {
+        fn kinked_borrow_rates()((min_rate, optimal_rate, max_rate) in borrow_rates())(
+            min_rate in Just(min_rate),
+            optimal_rate in Just(optimal_rate),
+            max_rate in Just(max_rate),
+            super_max_rate in max_rate..=u8::MAX,
+        ) -> (u8, u8, u8, u8) {
+            (min_rate, optimal_rate, max_rate, super_max_rate)
+        }
+    }
+
+    // Reserve with the given share of its liquidity borrowed, scaled by WAD
+    fn reserve_with_utilization(config: ReserveConfig, borrowed_percent: u64) -> Reserve {
+        let total_liquidity = 1_000_000_000_000u64;
+        let borrowed_amount_wads = Decimal::from(total_liquidity)
+            .try_mul(Rate::from_scaled_val(borrowed_percent))
+            .unwrap();
+        Reserve {
+            liquidity: ReserveLiquidity {
+                borrowed_amount_wads,
+                available_amount: total_liquidity - borrowed_amount_wads.try_round_u64().unwrap(),
+                ..ReserveLiquidity::default()
+            },
+            config,
+            ..Reserve::default()
+        }
+    }
+
     // Creates rates (threshold, ltv) where 2 <= threshold <= 100 and threshold <=
     // ltv <= 1,000%
     prop_compose! {
@@ -1063,7 +2723,7 @@ mod test {
                 ..Reserve::default()
             };
 
-            let current_borrow_rate = reserve.current_borrow_rate()?;
+            let current_borrow_rate = Rate::try_from(Decimal::try_from(reserve.current_borrow_rate()?)?)?;
             assert!(current_borrow_rate >= Rate::from_percent(min_borrow_rate));
             assert!(current_borrow_rate <= Rate::from_percent(max_borrow_rate));
 
@@ -1088,6 +2748,37 @@ mod test {
             }
         }
 
+        #[test]
+        fn current_borrow_rate_monotone(
+            (optimal_utilization_rate, max_utilization_rate) in utilization_breakpoints(),
+            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate, super_max_borrow_rate) in kinked_borrow_rates(),
+            low_borrowed_percent in 0..=WAD,
+            high_borrowed_percent in 0..=WAD,
+        ) {
+            let config = ReserveConfig {
+                optimal_utilization_rate,
+                max_utilization_rate,
+                min_borrow_rate,
+                optimal_borrow_rate,
+                max_borrow_rate,
+                super_max_borrow_rate: if max_utilization_rate == 0 { 0 } else { super_max_borrow_rate },
+                liquidation_threshold: 1,
+                liquidation_close_factor: 100,
+                ..ReserveConfig::default()
+            };
+            assert_eq!(config.validate(), Ok(()));
+
+            let (low_borrowed_percent, high_borrowed_percent) = if low_borrowed_percent <= high_borrowed_percent {
+                (low_borrowed_percent, high_borrowed_percent)
+            } else {
+                (high_borrowed_percent, low_borrowed_percent)
+            };
+            let low_rate = reserve_with_utilization(config, low_borrowed_percent).current_borrow_rate()?;
+            let high_rate = reserve_with_utilization(config, high_borrowed_percent).current_borrow_rate()?;
+            assert!(low_rate <= high_rate);
+            assert!(high_rate <= SignedDecimal::from(Rate::from_percent(config.borrow_rate_breakpoints()[3].1)));
+        }
+
         #[test]
         fn current_utilization_rate(
             total_liquidity in 0..=MAX_LIQUIDITY,
@@ -1154,7 +2845,7 @@ mod test {
             borrow_rate in 0..=u8::MAX,
         ) {
             let mut reserve = Reserve::default();
-            let borrow_rate = Rate::from_percent(borrow_rate);
+            let borrow_rate = Rate::from_percent(borrow_rate).into();
 
             // Simulate running for max 1000 years, assuming that interest is
             // compounded at least once a year
@@ -1192,6 +2883,49 @@ mod test {
             }
         }
 
+        #[test]
+        fn reserve_accrue_interest_after_long_gaps(
+            gaps_slots in prop::collection::vec(0..=10 * SLOTS_PER_YEAR, 1..64),
+            borrowed_liquidity in 0..=u64::MAX,
+        ) {
+            let borrowed_amount_wads = Decimal::from(borrowed_liquidity);
+            let mut reserve = Reserve {
+                liquidity: ReserveLiquidity {
+                    borrowed_amount_wads,
+                    cumulative_borrow_rate_wads: Decimal::one(),
+                    ..ReserveLiquidity::default()
+                },
+                config: ReserveConfig {
+                    max_borrow_rate: u8::MAX,
+                    ..ReserveConfig::default()
+                },
+                ..Reserve::default()
+            };
+
+            // Fully utilized at the max borrow rate, the reserve can still be
+            // refreshed and packed after every gap
+            let mut packed = [0u8; Reserve::LEN];
+            let mut current_slot = 0;
+            for gap_slots in gaps_slots {
+                let cumulative_borrow_rate_wads = reserve.liquidity.cumulative_borrow_rate_wads;
+                let borrowed_amount_wads = reserve.liquidity.borrowed_amount_wads;
+
+                current_slot += gap_slots;
+                reserve.accrue_interest(current_slot)?;
+                reserve.last_update.update_slot(current_slot);
+                assert!(
+                    reserve.liquidity.cumulative_borrow_rate_wads
+                        <= cumulative_borrow_rate_wads.try_mul(MAX_COMPOUNDED_INTEREST_RATE)?
+                );
+                assert!(
+                    reserve.liquidity.borrowed_amount_wads
+                        <= borrowed_amount_wads.try_mul(MAX_COMPOUNDED_INTEREST_RATE)?
+                );
+
+                Reserve::pack(reserve.clone(), &mut packed)?;
+            }
+        }
+
         #[test]
         fn borrow_fee_calculation(
             borrow_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
@@ -1367,4 +3101,1776 @@ mod test {
         assert_eq!(total_fee, 10); // 1% of 1000
         assert_eq!(host_fee, 0); // 0 host fee
     }
+
+    #[test]
+    fn donate_liquidity_raises_exchange_rate() {
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 40,
+                borrowed_amount_wads: Decimal::from(10u64),
+                ..ReserveLiquidity::default()
+            },
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            ..Reserve::default()
+        };
+
+        // a shortfall of 50 leaves 50 liquidity backing 100 collateral
+        assert_eq!(
+            reserve
+                .collateral_exchange_rate()
+                .unwrap()
+                .collateral_to_liquidity(100),
+            Ok(50)
+        );
+
+        reserve.donate_liquidity(50).unwrap();
+        assert_eq!(reserve.liquidity.available_amount, 90);
+        assert_eq!(reserve.collateral.mint_total_supply, 100);
+        assert_eq!(
+            reserve
+                .collateral_exchange_rate()
+                .unwrap()
+                .collateral_to_liquidity(100),
+            Ok(100)
+        );
+    }
+
+    #[test]
+    fn donate_liquidity_invalid() {
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: u64::MAX,
+                ..ReserveLiquidity::default()
+            },
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            ..Reserve::default()
+        };
+        assert_eq!(
+            reserve.clone().donate_liquidity(1),
+            Err(LendingError::MathOverflow.into())
+        );
+
+        reserve.liquidity.available_amount = 0;
+        reserve.collateral.mint_total_supply = 0;
+        assert_eq!(
+            reserve.donate_liquidity(1),
+            Err(LendingError::InvalidAmount.into())
+        );
+    }
+
+    fn fully_utilized_reserve(max_accrual_slots_per_refresh: u64) -> Reserve {
+        Reserve {
+            liquidity: ReserveLiquidity {
+                borrowed_amount_wads: Decimal::from(1_000_000u64),
+                cumulative_borrow_rate_wads: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                max_borrow_rate: 50,
+                max_accrual_slots_per_refresh,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        }
+    }
+
+    #[test]
+    fn accrue_interest_capped_per_refresh() {
+        let max_accrual_slots = SLOTS_PER_YEAR / 8;
+        let gap_slots = 4 * max_accrual_slots;
+
+        // the first refresh after the gap only accrues the capped slots
+        let mut reserve = fully_utilized_reserve(max_accrual_slots);
+        reserve.accrue_interest(gap_slots).unwrap();
+        reserve.last_update.update_slot(gap_slots);
+        assert_eq!(reserve.unaccrued_slots, gap_slots - max_accrual_slots);
+
+        let mut uncapped_reserve = fully_utilized_reserve(0);
+        uncapped_reserve.accrue_interest(gap_slots).unwrap();
+        assert_eq!(uncapped_reserve.unaccrued_slots, 0);
+        assert!(
+            reserve.liquidity.cumulative_borrow_rate_wads
+                < uncapped_reserve.liquidity.cumulative_borrow_rate_wads
+        );
+
+        // and the next refreshes catch up, as if the reserve was refreshed
+        // every max accrual slots
+        let mut expected_reserve = fully_utilized_reserve(0);
+        expected_reserve.accrue_interest(max_accrual_slots).unwrap();
+        assert_eq!(reserve.liquidity, expected_reserve.liquidity);
+        for refresh in 2..=4 {
+            reserve.accrue_interest(gap_slots).unwrap();
+            reserve.last_update.update_slot(gap_slots);
+
+            expected_reserve
+                .last_update
+                .update_slot((refresh - 1) * max_accrual_slots);
+            expected_reserve
+                .accrue_interest(refresh * max_accrual_slots)
+                .unwrap();
+        }
+        assert_eq!(reserve.unaccrued_slots, 0);
+        assert_eq!(reserve.liquidity, expected_reserve.liquidity);
+
+        // caught up, refreshing in the same slot accrues nothing more
+        reserve.accrue_interest(gap_slots).unwrap();
+        assert_eq!(reserve.liquidity, expected_reserve.liquidity);
+    }
+
+    fn rate_sampled_reserve(rate_history_interval_slots: u64) -> Reserve {
+        Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 500_000,
+                borrowed_amount_wads: Decimal::from(500_000u64),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                optimal_utilization_rate: 80,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                rate_history_interval_slots,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        }
+    }
+
+    #[test]
+    fn rate_history_sampled_once_per_interval() {
+        // 50% utilization, 5/8 of the way to the optimal borrow rate of 4%
+        let sample = RateSample {
+            utilization_rate_bps: 5_000,
+            borrow_rate_bps: 250,
+        };
+        let mut reserve = rate_sampled_reserve(100);
+        reserve.record_rate_sample(1_000).unwrap();
+        reserve.record_rate_sample(1_000).unwrap();
+        reserve.record_rate_sample(1_099).unwrap();
+        assert_eq!(reserve.rate_history(), &[sample]);
+        assert_eq!(reserve.rate_samples.last_sample_slot, 1_000);
+
+        reserve.liquidity.available_amount = 1_000_000;
+        reserve.liquidity.borrowed_amount_wads = Decimal::zero();
+        reserve.record_rate_sample(1_100).unwrap();
+        assert_eq!(reserve.rate_history(), &[sample, RateSample::default()]);
+        assert_eq!(reserve.rate_samples.last_sample_slot, 1_100);
+
+        // without an interval nothing is sampled
+        let mut reserve = rate_sampled_reserve(0);
+        reserve.record_rate_sample(1_000).unwrap();
+        assert!(reserve.rate_history().is_empty());
+    }
+
+    #[test]
+    fn rate_history_drops_oldest_samples() {
+        let mut rate_history = RateHistory::default();
+        let samples: Vec<RateSample> = (0..RATE_HISTORY_LEN as u16 + 6)
+            .map(|utilization_rate_bps| RateSample {
+                utilization_rate_bps,
+                borrow_rate_bps: -(utilization_rate_bps as i16),
+            })
+            .collect();
+        for (slot, sample) in samples.iter().enumerate() {
+            rate_history.push(*sample, slot as Slot);
+        }
+
+        let reserve = Reserve {
+            rate_samples: rate_history,
+            ..Reserve::default()
+        };
+        assert_eq!(reserve.rate_history(), &samples[6..]);
+        assert_eq!(
+            reserve.rate_samples.last_sample_slot,
+            (samples.len() - 1) as Slot
+        );
+    }
+
+    #[test]
+    fn negative_borrow_rate_sampled() {
+        let mut reserve = rate_sampled_reserve(100);
+        reserve.liquidity.borrowed_amount_wads = Decimal::zero();
+        reserve.config.borrow_rate_rebate = 5;
+        reserve.config.borrow_rate_floor = 5;
+        reserve.record_rate_sample(0).unwrap();
+        assert_eq!(
+            reserve.rate_history(),
+            &[RateSample {
+                utilization_rate_bps: 0,
+                borrow_rate_bps: -500,
+            }]
+        );
+    }
+
+    fn outflow_limited_reserve() -> Reserve {
+        Reserve {
+            config: ReserveConfig {
+                outflow_window_duration_slots: 100,
+                max_outflow: 1_000,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        }
+    }
+
+    #[test]
+    fn record_outflow_rate_limit() {
+        let mut reserve = outflow_limited_reserve();
+        reserve.record_outflow(600, 1_000).unwrap();
+        reserve.record_outflow(400, 1_099).unwrap();
+        assert_eq!(
+            reserve.clone().record_outflow(1, 1_099),
+            Err(LendingError::OutflowRateLimitExceeded.into())
+        );
+        assert_eq!(
+            reserve.outflow,
+            ReserveOutflow {
+                window_start_slot: 1_000,
+                window_amount: 1_000,
+                previous_window_amount: 0,
+            }
+        );
+
+        // disabled once the window duration is 0
+        reserve.config.outflow_window_duration_slots = 0;
+        reserve.record_outflow(u64::MAX, 1_099).unwrap();
+        assert_eq!(reserve.outflow.window_amount, 1_000);
+    }
+
+    #[test]
+    fn record_outflow_window_rollover() {
+        let mut reserve = outflow_limited_reserve();
+        reserve.record_outflow(1_000, 1_000).unwrap();
+
+        // a quarter into the next window, three quarters of the previous
+        // window outflow still count
+        assert_eq!(
+            reserve.clone().record_outflow(251, 1_125),
+            Err(LendingError::OutflowRateLimitExceeded.into())
+        );
+        reserve.record_outflow(250, 1_125).unwrap();
+        assert_eq!(
+            reserve.outflow,
+            ReserveOutflow {
+                window_start_slot: 1_100,
+                window_amount: 250,
+                previous_window_amount: 1_000,
+            }
+        );
+
+        // the previous window outflow decays as slots advance
+        reserve.record_outflow(250, 1_150).unwrap();
+        assert_eq!(
+            reserve.clone().record_outflow(1, 1_150),
+            Err(LendingError::OutflowRateLimitExceeded.into())
+        );
+
+        // windows without outflow in between reset the limit
+        reserve.record_outflow(1_000, 1_350).unwrap();
+        assert_eq!(
+            reserve.outflow,
+            ReserveOutflow {
+                window_start_slot: 1_300,
+                window_amount: 1_000,
+                previous_window_amount: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn redeem_collateral_max_withdraw_utilization() {
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 80,
+                borrowed_amount_wads: Decimal::from(20u64),
+                ..ReserveLiquidity::default()
+            },
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            config: ReserveConfig {
+                max_withdraw_utilization: 50,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+
+        // 20 borrowed out of the 40 remaining would be 50% utilization
+        assert_eq!(
+            reserve
+                .liquidity
+                .max_withdraw_amount(Rate::from_percent(50)),
+            Ok(60)
+        );
+        assert_eq!(
+            reserve.clone().redeem_collateral(61),
+            Err(LendingError::WithdrawTooLarge.into())
+        );
+        assert_eq!(reserve.redeem_collateral(60), Ok(60));
+        assert_eq!(
+            reserve.liquidity.utilization_rate(),
+            Ok(Rate::from_percent(50))
+        );
+
+        // any further redemption would breach the limit
+        assert_eq!(
+            reserve.redeem_collateral(1),
+            Err(LendingError::WithdrawTooLarge.into())
+        );
+    }
+
+    #[test]
+    fn redeem_queue_fulfilled_in_order() {
+        // 90 of the 100 liquidity is borrowed, at a 1:1 exchange rate
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 10,
+                borrowed_amount_wads: Decimal::from(90u64),
+                ..ReserveLiquidity::default()
+            },
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            ..Reserve::default()
+        };
+
+        assert_eq!(reserve.can_redeem_now(10), Ok(true));
+        assert_eq!(reserve.can_redeem_now(30), Ok(false));
+        assert_eq!(reserve.queue_redemption(30), Ok(0));
+        assert_eq!(reserve.queue_redemption(20), Ok(1));
+        assert_eq!(reserve.queued_redeem_liquidity_amount(), Ok(50));
+
+        // the queued redemptions come first
+        assert_eq!(reserve.can_redeem_now(1), Ok(false));
+        assert_eq!(
+            reserve.clone().redeem_collateral(1),
+            Err(LendingError::RedemptionsQueued.into())
+        );
+
+        // the head request is partially fulfilled and stays at the head
+        assert_eq!(reserve.fulfill_redemption(30), Ok((10, 10)));
+        assert_eq!(reserve.redeem_queue.head_ticket, 0);
+        assert_eq!(reserve.redeem_queue.collateral_amount, 40);
+        assert_eq!(
+            reserve.fulfill_redemption(20),
+            Err(LendingError::InsufficientLiquidity.into())
+        );
+
+        // a repayment returns liquidity for the rest of the queue
+        reserve.liquidity.repay(60, Decimal::from(60u64)).unwrap();
+        assert_eq!(reserve.fulfill_redemption(20), Ok((20, 20)));
+        assert_eq!(reserve.redeem_queue.head_ticket, 1);
+        assert_eq!(reserve.fulfill_redemption(20), Ok((20, 20)));
+        assert!(reserve.redeem_queue.is_empty());
+        assert_eq!(reserve.redeem_queue.collateral_amount, 0);
+        assert_eq!(
+            reserve.fulfill_redemption(1),
+            Err(LendingError::InvalidAccountInput.into())
+        );
+
+        assert_eq!(reserve.can_redeem_now(20), Ok(true));
+        assert_eq!(reserve.redeem_collateral(20), Ok(20));
+    }
+
+    #[test]
+    fn redeem_queue_cancel_and_skip() {
+        let mut reserve = Reserve {
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            ..Reserve::default()
+        };
+        assert_eq!(reserve.queue_redemption(30), Ok(0));
+        assert_eq!(reserve.queue_redemption(20), Ok(1));
+        assert_eq!(reserve.queue_redemption(10), Ok(2));
+
+        // a request behind the head leaves the queue collateral right away,
+        // the head is moved past its ticket later
+        assert_eq!(reserve.cancel_redemption(1, 20), Ok(()));
+        assert_eq!(reserve.redeem_queue.head_ticket, 0);
+        assert_eq!(reserve.redeem_queue.collateral_amount, 40);
+
+        // a head that can't be paid is skipped, its collateral stays escrowed
+        // and cancelling it later doesn't touch the queue
+        assert_eq!(reserve.skip_redemption(30), Ok(()));
+        assert_eq!(reserve.redeem_queue.head_ticket, 1);
+        assert_eq!(reserve.redeem_queue.collateral_amount, 10);
+        assert_eq!(reserve.cancel_redemption(0, 30), Ok(()));
+        assert_eq!(reserve.redeem_queue.collateral_amount, 10);
+
+        // the cancelled request is skipped with nothing left to redeem
+        assert_eq!(reserve.skip_redemption(0), Ok(()));
+        assert_eq!(reserve.redeem_queue.head_ticket, 2);
+
+        // cancelling the head moves the head past it
+        assert_eq!(reserve.cancel_redemption(2, 10), Ok(()));
+        assert!(reserve.redeem_queue.is_empty());
+        assert_eq!(reserve.redeem_queue.collateral_amount, 0);
+        assert_eq!(
+            reserve.skip_redemption(0),
+            Err(LendingError::InvalidAccountInput.into())
+        );
+    }
+
+    #[test]
+    fn current_borrow_rate_rebate_floor() {
+        let mut reserve = Reserve {
+            config: ReserveConfig {
+                optimal_borrow_rate: 2,
+                max_borrow_rate: 2,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let percent = |negative, percent| {
+            SignedDecimal::new(negative, Decimal::from(Rate::from_percent(percent)))
+        };
+        assert_eq!(reserve.current_borrow_rate(), Ok(percent(false, 2)));
+
+        reserve.config.borrow_rate_rebate = 1;
+        assert_eq!(reserve.current_borrow_rate(), Ok(percent(false, 1)));
+
+        // rebate pushes the rate below zero
+        reserve.config.borrow_rate_rebate = 5;
+        reserve.config.borrow_rate_floor = 10;
+        assert_eq!(reserve.current_borrow_rate(), Ok(percent(true, 3)));
+
+        // clamped at the floor
+        reserve.config.borrow_rate_floor = 1;
+        assert_eq!(reserve.current_borrow_rate(), Ok(percent(true, 1)));
+        reserve.config.borrow_rate_floor = 0;
+        assert_eq!(reserve.current_borrow_rate(), Ok(SignedDecimal::zero()));
+    }
+
+    #[test]
+    fn current_borrow_rate_at_breakpoints() {
+        let config = ReserveConfig {
+            optimal_utilization_rate: 80,
+            max_utilization_rate: 95,
+            min_borrow_rate: 1,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            super_max_borrow_rate: 150,
+            ..ReserveConfig::default()
+        };
+        let borrow_rate = |borrowed_percent: u64| {
+            reserve_with_utilization(config, borrowed_percent * PERCENT_SCALER)
+                .current_borrow_rate()
+                .unwrap()
+        };
+        let percent = |percent| SignedDecimal::from(Rate::from_percent(percent));
+
+        assert_eq!(borrow_rate(0), percent(1));
+        assert_eq!(borrow_rate(80), percent(4));
+        assert_eq!(borrow_rate(95), percent(30));
+        assert_eq!(borrow_rate(97), percent(78));
+        assert_eq!(borrow_rate(100), percent(150));
+
+        // without a max utilization rate the max borrow rate is reached at
+        // full utilization
+        let config = ReserveConfig {
+            max_utilization_rate: 0,
+            super_max_borrow_rate: 0,
+            ..config
+        };
+        let borrow_rate = |borrowed_percent: u64| {
+            reserve_with_utilization(config, borrowed_percent * PERCENT_SCALER)
+                .current_borrow_rate()
+                .unwrap()
+        };
+        assert_eq!(borrow_rate(80), percent(4));
+        assert_eq!(borrow_rate(90), percent(17));
+        assert_eq!(borrow_rate(100), percent(30));
+    }
+
+    #[test]
+    fn validate_borrow_rate_breakpoints() {
+        let config = ReserveConfig {
+            optimal_utilization_rate: 80,
+            max_utilization_rate: 95,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            super_max_borrow_rate: 150,
+            liquidation_threshold: 1,
+            liquidation_close_factor: 100,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+
+        let invalid_configs = [
+            ReserveConfig {
+                max_utilization_rate: 79,
+                ..config
+            },
+            ReserveConfig {
+                max_utilization_rate: 101,
+                ..config
+            },
+            ReserveConfig {
+                super_max_borrow_rate: 29,
+                ..config
+            },
+            ReserveConfig {
+                max_utilization_rate: 0,
+                ..config
+            },
+        ];
+        for invalid_config in invalid_configs {
+            assert_eq!(
+                invalid_config.validate(),
+                Err(LendingError::InvalidBorrowRateCurve.into())
+            );
+        }
+    }
+
+    #[test]
+    fn validate_config_errors() {
+        let config = ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 50,
+            liquidation_bonus: 5,
+            max_liquidation_bonus: 10,
+            liquidation_threshold: 55,
+            min_borrow_rate: 1,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            liquidation_close_factor: 100,
+            fees: ReserveFees {
+                borrow_fee_wad: WAD / 100,
+                flash_loan_fee_wad: WAD / 1000,
+                host_fee_percentage: 20,
+            },
+            ..ReserveConfig::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+
+        let invalid_configs = [
+            (
+                ReserveConfig {
+                    loan_to_value_ratio: 100,
+                    liquidation_threshold: 100,
+                    ..config
+                },
+                LendingError::InvalidLtvRatio,
+            ),
+            (
+                ReserveConfig {
+                    liquidation_threshold: 50,
+                    ..config
+                },
+                LendingError::InvalidLiquidationThreshold,
+            ),
+            (
+                ReserveConfig {
+                    liquidation_threshold: 101,
+                    ..config
+                },
+                LendingError::InvalidLiquidationThreshold,
+            ),
+            (
+                ReserveConfig {
+                    stressed_liquidation_threshold: 50,
+                    ..config
+                },
+                LendingError::InvalidLiquidationThreshold,
+            ),
+            (
+                ReserveConfig {
+                    stressed_liquidation_threshold: 56,
+                    ..config
+                },
+                LendingError::InvalidLiquidationThreshold,
+            ),
+            (
+                ReserveConfig {
+                    stressed_liquidation_threshold: 52,
+                    stress_utilization_rate: 100,
+                    ..config
+                },
+                LendingError::InvalidLiquidationThreshold,
+            ),
+            (
+                ReserveConfig {
+                    liquidation_bonus: 101,
+                    max_liquidation_bonus: 101,
+                    ..config
+                },
+                LendingError::InvalidLiquidationBonus,
+            ),
+            (
+                ReserveConfig {
+                    max_liquidation_bonus: 4,
+                    ..config
+                },
+                LendingError::InvalidLiquidationBonus,
+            ),
+            (
+                ReserveConfig {
+                    min_liquidation_bonus: 11,
+                    ..config
+                },
+                LendingError::InvalidLiquidationBonus,
+            ),
+            (
+                ReserveConfig {
+                    optimal_utilization_rate: 101,
+                    ..config
+                },
+                LendingError::InvalidBorrowRateCurve,
+            ),
+            (
+                ReserveConfig {
+                    min_borrow_rate: 5,
+                    ..config
+                },
+                LendingError::InvalidBorrowRateCurve,
+            ),
+            (
+                ReserveConfig {
+                    max_borrow_rate: 3,
+                    ..config
+                },
+                LendingError::InvalidBorrowRateCurve,
+            ),
+            (
+                ReserveConfig {
+                    fees: ReserveFees {
+                        borrow_fee_wad: WAD,
+                        ..config.fees
+                    },
+                    ..config
+                },
+                LendingError::InvalidFeeConfig,
+            ),
+            (
+                ReserveConfig {
+                    fees: ReserveFees {
+                        flash_loan_fee_wad: WAD,
+                        ..config.fees
+                    },
+                    ..config
+                },
+                LendingError::InvalidFeeConfig,
+            ),
+            (
+                ReserveConfig {
+                    fees: ReserveFees {
+                        host_fee_percentage: 101,
+                        ..config.fees
+                    },
+                    ..config
+                },
+                LendingError::InvalidFeeConfig,
+            ),
+            (
+                ReserveConfig {
+                    liquidation_close_factor: 0,
+                    ..config
+                },
+                LendingError::InvalidConfig,
+            ),
+        ];
+        for (invalid_config, error) in invalid_configs {
+            assert_eq!(invalid_config.validate(), Err(error.into()));
+        }
+    }
+
+    #[test]
+    fn compound_interest_negative_rate() {
+        let borrowed_amount_wads = Decimal::from(100u64);
+        let mut liquidity = ReserveLiquidity {
+            borrowed_amount_wads,
+            cumulative_borrow_rate_wads: Decimal::one(),
+            ..ReserveLiquidity::default()
+        };
+        let borrow_rate = -SignedDecimal::from(Rate::one());
+
+        for _ in 0..10 {
+            liquidity
+                .compound_interest(borrow_rate, SLOTS_PER_YEAR)
+                .unwrap();
+        }
+        assert!(liquidity.borrowed_amount_wads < borrowed_amount_wads);
+        assert!(liquidity.borrowed_amount_wads > Decimal::zero());
+        assert!(liquidity.cumulative_borrow_rate_wads < Decimal::one());
+        assert!(liquidity.cumulative_borrow_rate_wads > Decimal::zero());
+    }
+
+    #[test]
+    fn limits_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                borrow_limit: 1_000_000,
+                deposit_limit: 2_000_000,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        let unpacked = Reserve::unpack(&packed).unwrap();
+        assert_eq!(unpacked, reserve);
+        assert_eq!(unpacked.config.borrow_limit, 1_000_000);
+        assert_eq!(unpacked.config.deposit_limit, 2_000_000);
+    }
+
+    #[test]
+    fn borrow_limit_zero_allows_no_borrows() {
+        let config = ReserveConfig {
+            borrow_limit: 1_000,
+            ..ReserveConfig::default()
+        };
+        // a 0 limit is the tightest there is
+        assert_eq!(
+            ReserveConfig {
+                borrow_limit: 0,
+                ..config
+            }
+            .assert_tightens(&config),
+            Ok(())
+        );
+        assert_eq!(
+            ReserveConfig {
+                borrow_limit: u64::MAX,
+                ..config
+            }
+            .assert_tightens(&config),
+            Err(LendingError::RiskAuthorityCannotLoosen.into())
+        );
+
+        // only reserves packed before the limit was added read it as unlimited
+        let mut legacy_config = ReserveConfig::default();
+        legacy_config.write_defaults();
+        assert_eq!(legacy_config.borrow_limit, u64::MAX);
+    }
+
+    #[test]
+    fn deposit_limit_zero_allows_no_deposits() {
+        let config = ReserveConfig {
+            deposit_limit: 2_000,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(
+            ReserveConfig {
+                deposit_limit: 0,
+                ..config
+            }
+            .assert_tightens(&config),
+            Ok(())
+        );
+        assert_eq!(
+            ReserveConfig {
+                deposit_limit: u64::MAX,
+                ..config
+            }
+            .assert_tightens(&config),
+            Err(LendingError::RiskAuthorityCannotLoosen.into())
+        );
+
+        let mut legacy_config = ReserveConfig::default();
+        legacy_config.write_defaults();
+        assert_eq!(legacy_config.deposit_limit, u64::MAX);
+    }
+
+    #[test]
+    fn risk_authority_config_changes() {
+        let current = ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 50,
+            liquidation_threshold: 60,
+            liquidation_bonus: 5,
+            max_liquidation_bonus: 10,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            max_utilization_rate: 90,
+            super_max_borrow_rate: 100,
+            borrow_limit: u64::MAX,
+            deposit_limit: u64::MAX,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(current.assert_tightens(&current), Ok(()));
+
+        let tightened = ReserveConfig {
+            loan_to_value_ratio: 40,
+            liquidation_threshold: 55,
+            liquidation_bonus: 4,
+            borrow_limit: 1_000,
+            optimal_borrow_rate: 5,
+            max_borrow_rate: 40,
+            super_max_borrow_rate: 150,
+            ..current
+        };
+        assert_eq!(tightened.assert_tightens(&current), Ok(()));
+
+        let loosened = [
+            ReserveConfig {
+                loan_to_value_ratio: 55,
+                ..current
+            },
+            ReserveConfig {
+                max_liquidation_bonus: 11,
+                ..current
+            },
+            ReserveConfig {
+                max_borrow_rate: 29,
+                ..current
+            },
+            // a higher optimal borrow rate flattens the curve past the kink
+            ReserveConfig {
+                optimal_borrow_rate: 5,
+                ..current
+            },
+            // and a higher max borrow rate past the max utilization rate
+            ReserveConfig {
+                max_borrow_rate: 31,
+                ..current
+            },
+            ReserveConfig {
+                optimal_utilization_rate: 70,
+                ..current
+            },
+            ReserveConfig {
+                max_oracle_age_slots: 10,
+                ..current
+            },
+        ];
+        for config in loosened {
+            assert_eq!(
+                config.assert_tightens(&current),
+                Err(LendingError::RiskAuthorityCannotLoosen.into())
+            );
+        }
+        // undoing the tightening loosens every changed config
+        assert_eq!(
+            current.assert_tightens(&tightened),
+            Err(LendingError::RiskAuthorityCannotLoosen.into())
+        );
+    }
+
+    #[test]
+    fn validate_deposit_only_config() {
+        let config = ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 0,
+            liquidation_threshold: 0,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            liquidation_close_factor: 100,
+            ..ReserveConfig::default()
+        };
+        assert!(config.is_deposit_only());
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.borrow_power_rate().unwrap(), Rate::zero());
+
+        // a zero LTV can still come with a liquidation threshold
+        let config = ReserveConfig {
+            liquidation_threshold: 50,
+            ..config
+        };
+        assert!(!config.is_deposit_only());
+        assert_eq!(config.validate(), Ok(()));
+
+        // but a liquidation threshold of zero needs a zero LTV
+        let config = ReserveConfig {
+            loan_to_value_ratio: 10,
+            liquidation_threshold: 0,
+            ..config
+        };
+        assert_eq!(
+            config.validate(),
+            Err(LendingError::InvalidLiquidationThreshold.into())
+        );
+    }
+
+    #[test]
+    fn borrow_power_rate_applies_collateral_weight() {
+        let config = ReserveConfig {
+            collateral_weight: 80,
+            loan_to_value_ratio: 50,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(config.borrow_power_rate().unwrap(), Rate::from_percent(40));
+
+        let config = ReserveConfig {
+            collateral_weight: 100,
+            ..config
+        };
+        assert_eq!(config.borrow_power_rate().unwrap(), Rate::from_percent(50));
+    }
+
+    #[test]
+    fn borrow_power_rate_defaults_to_full_collateral_weight() {
+        let config = ReserveConfig {
+            loan_to_value_ratio: 50,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(config.collateral_weight_rate(), Rate::one());
+        assert_eq!(config.borrow_power_rate().unwrap(), Rate::from_percent(50));
+    }
+
+    #[test]
+    fn borrow_weight_defaults_to_one() {
+        let config = ReserveConfig::default();
+        assert_eq!(config.borrow_weight(), Decimal::one());
+
+        let config = ReserveConfig {
+            borrow_weight_wad: 1_500_000_000_000_000_000,
+            ..config
+        };
+        assert_eq!(
+            config.borrow_weight(),
+            Decimal::from(3u64).try_div(2u64).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculate_borrow_applies_borrow_weight() {
+        let reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 1_000,
+                market_price: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                borrow_weight_wad: 1_500_000_000_000_000_000,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+
+        // 150 of remaining borrow value is consumed by a borrow of 100
+        let max_borrow = reserve
+            .calculate_borrow(u64::MAX, Decimal::from(150u64))
+            .unwrap();
+        assert_eq!(max_borrow.borrow_amount, Decimal::from(100u64));
+        assert_eq!(max_borrow.receive_amount, 100);
+
+        assert!(reserve.calculate_borrow(100, Decimal::from(150u64)).is_ok());
+        assert_eq!(
+            reserve
+                .calculate_borrow(101, Decimal::from(150u64))
+                .unwrap_err(),
+            LendingError::BorrowTooLarge.into()
+        );
+    }
+
+    #[test]
+    fn borrow_weight_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                borrow_weight_wad: 2 * WAD,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn pause_flags_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            pause_flags: RESERVE_PAUSE_BORROWS | RESERVE_PAUSE_WITHDRAWALS,
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        let unpacked = Reserve::unpack(&packed).unwrap();
+        assert_eq!(unpacked, reserve);
+        assert!(unpacked.is_paused(RESERVE_PAUSE_WITHDRAWALS));
+        assert!(!unpacked.is_paused(RESERVE_PAUSE_DEPOSITS | RESERVE_PAUSE_LIQUIDATIONS));
+    }
+
+    #[test]
+    fn status_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            status: ReserveStatus::Deprecated,
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        let unpacked = Reserve::unpack(&packed).unwrap();
+        assert_eq!(unpacked, reserve);
+        assert!(!unpacked.is_active());
+    }
+
+    #[test]
+    fn outflow_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                outflow_window_duration_slots: 9_000,
+                max_outflow: 1_000_000,
+                ..ReserveConfig::default()
+            },
+            outflow: ReserveOutflow {
+                window_start_slot: 18_000,
+                window_amount: 500_000,
+                previous_window_amount: 250_000,
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn accrual_cap_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                max_accrual_slots_per_refresh: 216_000,
+                ..ReserveConfig::default()
+            },
+            unaccrued_slots: 1_000_000,
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn rate_history_pack_roundtrip() {
+        let mut reserve = Reserve {
+            version: PROGRAM_VERSION,
+            ..rate_sampled_reserve(9_000)
+        };
+        for slot in 0..RATE_HISTORY_LEN as Slot + 2 {
+            reserve.record_rate_sample(slot * 9_000).unwrap();
+        }
+        reserve.rate_samples.samples[0].borrow_rate_bps = -100;
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn redeem_queue_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            redeem_queue: RedeemQueue {
+                head_ticket: 3,
+                next_ticket: 7,
+                collateral_amount: 1_000_000,
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+
+        // V1 reserves have no room for the queue
+        let mut packed = vec![0u8; RESERVE_V1_LEN];
+        assert_eq!(
+            Reserve::pack_account(
+                Reserve {
+                    version: RESERVE_V2_VERSION - 1,
+                    ..reserve
+                },
+                &mut packed
+            ),
+            Err(LendingError::AccountNotMigrated.into())
+        );
+    }
+
+    #[test]
+    fn price_stale_after_max_oracle_age() {
+        let mut reserve = Reserve {
+            version: PROGRAM_VERSION,
+            liquidity: ReserveLiquidity {
+                oracle_kind: OracleKind::Pyth,
+                market_price_slot: 100,
+                ..ReserveLiquidity::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+
+        // refreshing the reserve doesn't make an old price fresh
+        reserve
+            .last_update
+            .update_slot(100 + pyth::STALE_AFTER_SLOTS_ELAPSED);
+        assert!(!reserve
+            .last_update
+            .is_stale(100 + pyth::STALE_AFTER_SLOTS_ELAPSED)
+            .unwrap());
+        assert!(!reserve
+            .is_price_stale(100 + pyth::STALE_AFTER_SLOTS_ELAPSED - 1)
+            .unwrap());
+        assert!(reserve
+            .is_price_stale(100 + pyth::STALE_AFTER_SLOTS_ELAPSED)
+            .unwrap());
+
+        reserve.config.max_oracle_age_slots = 10;
+        assert!(!reserve.is_price_stale(109).unwrap());
+        assert!(reserve.is_price_stale(110).unwrap());
+        assert!(reserve.is_price_stale(99).is_err());
+
+        // V1 reserves only check the price on refresh
+        reserve.version = RESERVE_V2_VERSION - 1;
+        assert!(!reserve.is_price_stale(1_000).unwrap());
+    }
+
+    #[test]
+    fn isolation_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                isolated: true,
+                borrowable_in_isolation: true,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn reward_pool_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            reward_pool: RewardPool {
+                vault: Pubkey::new_unique(),
+                authority_bump_seed: 254,
+                rewards_per_slot: 1_000,
+                cumulative_rewards_per_collateral: Decimal::from_scaled_val(123_456_789),
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn emergency_price_override() {
+        let mut reserve = Reserve {
+            version: PROGRAM_VERSION,
+            last_update: LastUpdate::new(100),
+            liquidity: ReserveLiquidity {
+                market_price: Decimal::from(20u64),
+                ..ReserveLiquidity::default()
+            },
+            ..Reserve::default()
+        };
+        assert_eq!(reserve.emergency_price_override(), None);
+
+        reserve.set_emergency_price_override(Some(Decimal::from(15u64)));
+        assert_eq!(
+            reserve.emergency_price_override(),
+            Some(Decimal::from(15u64))
+        );
+        assert!(reserve.last_update.stale);
+
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+
+        reserve.set_emergency_price_override(None);
+        assert_eq!(reserve.emergency_price_override(), None);
+        assert_eq!(reserve.liquidity.market_price, Decimal::from(15u64));
+    }
+
+    #[test]
+    fn accrue_rewards() {
+        let mut reserve = Reserve {
+            last_update: LastUpdate::new(100),
+            collateral: ReserveCollateral {
+                mint_total_supply: 4_000,
+                ..ReserveCollateral::default()
+            },
+            ..Reserve::default()
+        };
+
+        // reserves without a reward rate accrue nothing
+        reserve.accrue_rewards(110).unwrap();
+        assert_eq!(
+            reserve.reward_pool.cumulative_rewards_per_collateral,
+            Decimal::zero()
+        );
+
+        reserve.reward_pool.rewards_per_slot = 100;
+        reserve.accrue_rewards(110).unwrap();
+        assert_eq!(
+            reserve.reward_pool.cumulative_rewards_per_collateral,
+            Decimal::from_percent(25)
+        );
+
+        // rewards of slots without collateral are not distributed
+        reserve.collateral.mint_total_supply = 0;
+        reserve.accrue_rewards(120).unwrap();
+        assert_eq!(
+            reserve.reward_pool.cumulative_rewards_per_collateral,
+            Decimal::from_percent(25)
+        );
+    }
+
+    #[test]
+    fn oracle_kind_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            liquidity: ReserveLiquidity {
+                oracle_kind: OracleKind::Switchboard,
+                ..ReserveLiquidity::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+
+        // reserves packed before the oracle kind was stored read back as Pyth
+        let oracle_kind_offset = RESERVE_V1_LEN - 226;
+        packed[oracle_kind_offset] = 0;
+        assert_eq!(
+            Reserve::unpack(&packed).unwrap().liquidity.oracle_kind,
+            OracleKind::Pyth
+        );
+
+        packed[oracle_kind_offset] = 2;
+        assert_eq!(
+            Reserve::unpack(&packed).unwrap_err(),
+            LendingError::InvalidOracleConfig.into()
+        );
+    }
+
+    #[test]
+    fn flash_loan_fee_destination() {
+        let mut liquidity = ReserveLiquidity {
+            fee_receiver: Pubkey::new_unique(),
+            ..ReserveLiquidity::default()
+        };
+        assert_eq!(
+            liquidity.flash_loan_fee_destination(),
+            &liquidity.fee_receiver
+        );
+
+        liquidity.flash_loan_fee_receiver = Pubkey::new_unique();
+        assert_eq!(
+            liquidity.flash_loan_fee_destination(),
+            &liquidity.flash_loan_fee_receiver
+        );
+
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            liquidity,
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    // Liquidates `amount` of a 100 token borrow worth $100 against 1000
+    // collateral tokens worth $200 with a 10% liquidation bonus
+    fn liquidate_with_protocol_fee(
+        protocol_liquidation_fee: u8,
+        amount: u64,
+    ) -> CalculateLiquidationResult {
+        let reserve = Reserve {
+            collateral: ReserveCollateral {
+                fee_receiver: Pubkey::new_unique(),
+                ..ReserveCollateral::default()
+            },
+            config: ReserveConfig {
+                liquidation_bonus: 10,
+                protocol_liquidation_fee,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let obligation = Obligation {
+            deposited_value: Decimal::from(200u64),
+            unhealthy_borrow_value: Decimal::from(80u64),
+            borrowed_value: Decimal::from(100u64),
+            ..Obligation::default()
+        };
+        let liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(100u64),
+            market_value: Decimal::from(100u64),
+            ..ObligationLiquidity::default()
+        };
+        let collateral = ObligationCollateral {
+            deposited_amount: 1000,
+            market_value: Decimal::from(200u64),
+            ..ObligationCollateral::default()
+        };
+        reserve
+            .calculate_liquidation(
+                amount,
+                &obligation,
+                &liquidity,
+                &collateral,
+                &reserve.config,
+                Decimal::zero(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn protocol_liquidation_fee_zero() {
+        let result = liquidate_with_protocol_fee(0, 20);
+        assert_eq!(result.withdraw_amount, 110);
+        assert_eq!(result.protocol_fee, 0);
+    }
+
+    #[test]
+    fn protocol_liquidation_fee_share_of_bonus() {
+        // seized collateral is unchanged, only the bonus of 10 is split
+        let result = liquidate_with_protocol_fee(50, 20);
+        assert_eq!(result.withdraw_amount, 110);
+        assert_eq!(result.bonus_amount, 10);
+        assert_eq!(result.protocol_fee, 5);
+
+        let result = liquidate_with_protocol_fee(100, 20);
+        assert_eq!(result.withdraw_amount, 110);
+        assert_eq!(result.protocol_fee, 10);
+    }
+
+    #[test]
+    fn protocol_liquidation_fee_dust_rounds_down() {
+        // a bonus of 5 - 5 / 1.1 = 0.45 tokens goes to the liquidator
+        let result = liquidate_with_protocol_fee(100, 1);
+        assert_eq!(result.withdraw_amount, 5);
+        assert_eq!(result.bonus_amount, 0);
+        assert_eq!(result.protocol_fee, 0);
+    }
+
+    // Liquidates a 1000 token borrow worth $10 against 1000 collateral tokens
+    // worth `collateral_value`, with a 10% liquidation bonus and a $20 dust
+    // liquidation threshold when `dust` is set
+    fn liquidate_dust(collateral_value: Decimal, dust: bool) -> CalculateLiquidationResult {
+        let reserve = Reserve {
+            config: ReserveConfig {
+                liquidation_bonus: 10,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let obligation = Obligation {
+            deposited_value: collateral_value,
+            unhealthy_borrow_value: Decimal::from(8u64),
+            borrowed_value: Decimal::from(10u64),
+            ..Obligation::default()
+        };
+        let liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(1000u64),
+            market_value: Decimal::from(10u64),
+            ..ObligationLiquidity::default()
+        };
+        let collateral = ObligationCollateral {
+            deposited_amount: 1000,
+            market_value: collateral_value,
+            ..ObligationCollateral::default()
+        };
+        let dust_liquidation_threshold = if dust {
+            Decimal::from(20u64)
+        } else {
+            Decimal::zero()
+        };
+        reserve
+            .calculate_liquidation(
+                u64::MAX,
+                &obligation,
+                &liquidity,
+                &collateral,
+                &reserve.config,
+                dust_liquidation_threshold,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn dust_liquidation_ignores_close_factor() {
+        // $20 of collateral covers the whole borrow with the bonus
+        let collateral_value = Decimal::from(20u64);
+        let result = liquidate_dust(collateral_value, false);
+        assert_eq!(result.settle_amount, Decimal::from(500u64));
+        assert_eq!(result.withdraw_amount, 275);
+
+        let result = liquidate_dust(collateral_value, true);
+        assert_eq!(result.settle_amount, Decimal::from(1000u64));
+        assert_eq!(result.repay_amount, 1000);
+        assert_eq!(result.withdraw_amount, 550);
+    }
+
+    #[test]
+    fn dust_liquidation_seizes_all_collateral() {
+        // $10.50 of collateral covers the borrow, but not the full bonus
+        let collateral_value = Decimal::from_scaled_val(10_500_000_000_000_000_000);
+        let result = liquidate_dust(collateral_value, true);
+        assert_eq!(result.settle_amount, Decimal::from(1000u64));
+        assert_eq!(result.repay_amount, 1000);
+        assert_eq!(result.withdraw_amount, 1000);
+        assert!(result.bonus_amount < result.withdraw_amount);
+    }
+
+    #[test]
+    fn dust_liquidation_underwater_scales_repay() {
+        // $5 of collateral doesn't cover the borrow, the repay is scaled down
+        // as usual so the liquidator isn't paid less than they repay
+        let result = liquidate_dust(Decimal::from(5u64), true);
+        assert_eq!(result.withdraw_amount, 1000);
+        assert_eq!(result.repay_amount, 455);
+        assert!(result.settle_amount < Decimal::from(1000u64));
+    }
+
+    #[test]
+    fn protocol_liquidation_fee_without_fee_receiver() {
+        let mut reserve = Reserve {
+            config: ReserveConfig {
+                protocol_liquidation_fee: 100,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let bonus_rate = Rate::from_percent(110);
+        assert_eq!(
+            reserve
+                .calculate_protocol_liquidation_fee(110, bonus_rate)
+                .unwrap(),
+            0
+        );
+
+        reserve.collateral.fee_receiver = Pubkey::new_unique();
+        assert_eq!(
+            reserve
+                .calculate_protocol_liquidation_fee(110, bonus_rate)
+                .unwrap(),
+            10
+        );
+    }
+
+    #[test]
+    fn protocol_liquidation_fee_pack_roundtrip() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            collateral: ReserveCollateral {
+                fee_receiver: Pubkey::new_unique(),
+                ..ReserveCollateral::default()
+            },
+            config: ReserveConfig {
+                protocol_liquidation_fee: 30,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    // A reserve with `borrowed` out of 100 liquidity borrowed, whose threshold
+    // tightens from 80% at 60% utilization to 60% at full utilization
+    fn stressed_threshold_reserve(borrowed: u64) -> Reserve {
+        Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 100 - borrowed,
+                borrowed_amount_wads: Decimal::from(borrowed),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                liquidation_threshold: 80,
+                stressed_liquidation_threshold: 60,
+                stress_utilization_rate: 60,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        }
+    }
+
+    #[test]
+    fn liquidation_threshold_at_low_utilization() {
+        let reserve = stressed_threshold_reserve(30);
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(80))
+        );
+
+        // up to the stress utilization rate
+        let reserve = stressed_threshold_reserve(60);
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(80))
+        );
+    }
+
+    #[test]
+    fn liquidation_threshold_at_high_utilization() {
+        let reserve = stressed_threshold_reserve(100);
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(60))
+        );
+    }
+
+    #[test]
+    fn liquidation_threshold_interpolated() {
+        // halfway between the stress utilization rate and full utilization
+        let reserve = stressed_threshold_reserve(80);
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(70))
+        );
+
+        let reserve = stressed_threshold_reserve(90);
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(65))
+        );
+    }
+
+    #[test]
+    fn liquidation_threshold_without_stressed_threshold() {
+        let mut reserve = stressed_threshold_reserve(100);
+        reserve.config.stressed_liquidation_threshold = 0;
+        assert_eq!(
+            reserve.liquidation_threshold_rate(),
+            Ok(Rate::from_percent(80))
+        );
+    }
+
+    fn liquidation_bonus_reserve() -> Reserve {
+        Reserve {
+            config: ReserveConfig {
+                liquidation_bonus: 5,
+                max_liquidation_bonus: 15,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        }
+    }
+
+    fn underwater_obligation(borrowed_value: u64) -> Obligation {
+        Obligation {
+            deposited_value: Decimal::from(100u64),
+            unhealthy_borrow_value: Decimal::from(80u64),
+            borrowed_value: Decimal::from(borrowed_value),
+            ..Obligation::default()
+        }
+    }
+
+    #[test]
+    fn liquidation_bonus_at_unhealthy_boundary() {
+        let reserve = liquidation_bonus_reserve();
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(80))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(5));
+    }
+
+    #[test]
+    fn liquidation_bonus_deeply_underwater() {
+        let reserve = liquidation_bonus_reserve();
+
+        // borrows equal to deposits reach the max bonus
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(100))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(15));
+
+        // and it is clamped from there on
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(500))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(15));
+    }
+
+    #[test]
+    fn liquidation_bonus_interpolation() {
+        let reserve = liquidation_bonus_reserve();
+
+        // halfway between unhealthy (80) and deposited (100) value
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(90))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(10));
+
+        // a quarter of the way
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(85))
+            .unwrap();
+        assert_eq!(bonus_rate.to_scaled_val(), 75_000_000_000_000_000); // 7.5%
+    }
+
+    #[test]
+    fn liquidation_bonus_with_min_bonus() {
+        let mut reserve = liquidation_bonus_reserve();
+        reserve.config.min_liquidation_bonus = 2;
+
+        // at the unhealthy threshold
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(80))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(2));
+
+        // halfway to fully underwater
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(90))
+            .unwrap();
+        assert_eq!(bonus_rate.to_scaled_val(), 85_000_000_000_000_000); // 8.5%
+
+        // fully underwater
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(120))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(15));
+    }
+
+    #[test]
+    fn liquidation_with_max_bonus_never_exceeds_deposit() {
+        let mut reserve = liquidation_bonus_reserve();
+        reserve.config.min_liquidation_bonus = 2;
+        reserve.config.max_liquidation_bonus = 50;
+
+        // borrows worth more than the collateral backing them
+        let obligation = Obligation {
+            deposited_value: Decimal::from(100u64),
+            unhealthy_borrow_value: Decimal::from(80u64),
+            borrowed_value: Decimal::from(150u64),
+            ..Obligation::default()
+        };
+        let liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(150_000u64),
+            market_value: Decimal::from(150u64),
+            ..ObligationLiquidity::default()
+        };
+        let collateral = ObligationCollateral {
+            deposited_amount: 100_000,
+            market_value: Decimal::from(100u64),
+            ..ObligationCollateral::default()
+        };
+        let repay_reserve_config = ReserveConfig {
+            liquidation_close_factor: 100,
+            ..ReserveConfig::default()
+        };
+
+        let result = reserve
+            .calculate_liquidation(
+                u64::MAX,
+                &obligation,
+                &liquidity,
+                &collateral,
+                &repay_reserve_config,
+                Decimal::zero(),
+            )
+            .unwrap();
+        assert_eq!(result.withdraw_amount, collateral.deposited_amount);
+        assert!(result.bonus_amount < result.withdraw_amount);
+        // the repaid value is scaled down so the bonus still applies
+        assert_eq!(result.repay_amount, 66_667);
+    }
+
+    #[test]
+    fn liquidation_bonus_without_max_uses_base() {
+        let mut reserve = liquidation_bonus_reserve();
+        reserve.config.max_liquidation_bonus = 0;
+        let bonus_rate = reserve
+            .liquidation_bonus_rate(&underwater_obligation(100))
+            .unwrap();
+        assert_eq!(bonus_rate, Rate::from_percent(5));
+    }
+
+    #[test]
+    fn pack_and_unpack_v1_reserve_account() {
+        let reserve = Reserve {
+            version: RESERVE_V2_VERSION - 1,
+            lending_market: Pubkey::new_unique(),
+            pause_flags: RESERVE_PAUSE_BORROWS,
+            ..Reserve::default()
+        };
+
+        let mut packed = vec![0u8; RESERVE_V1_LEN];
+        Reserve::pack_account(reserve.clone(), &mut packed).unwrap();
+        // the limits missing from the V1 padding are read as unlimited
+        let mut unpacked = Reserve::unpack_account(&packed).unwrap();
+        assert_eq!(unpacked.config.borrow_limit, u64::MAX);
+        assert_eq!(unpacked.config.deposit_limit, u64::MAX);
+        unpacked.config.borrow_limit = reserve.config.borrow_limit;
+        unpacked.config.deposit_limit = reserve.config.deposit_limit;
+        assert_eq!(unpacked, reserve);
+        // a V2 sized account can't be read at a V1 version
+        let mut resized = packed.clone();
+        resized.resize(Reserve::LEN, 0);
+        assert_eq!(
+            Reserve::unpack_account(&resized),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut migrated = Reserve::unpack_legacy_account(&packed).unwrap();
+        migrated.migrate();
+        Reserve::pack_account(migrated.clone(), &mut resized).unwrap();
+        assert_eq!(Reserve::unpack_account(&resized).unwrap(), migrated);
+        assert_eq!(migrated.version, PROGRAM_VERSION);
+        assert_eq!(migrated.pause_flags, reserve.pause_flags);
+        assert_eq!(migrated.config.collateral_weight, 100);
+        assert_eq!(migrated.config.borrow_limit, u64::MAX);
+        assert_eq!(migrated.config.deposit_limit, u64::MAX);
+        assert_eq!(migrated.config.borrow_weight_wad, WAD);
+        assert_eq!(
+            migrated.config.liquidation_close_factor,
+            LIQUIDATION_CLOSE_FACTOR
+        );
+
+        assert_eq!(
+            Reserve::unpack_legacy_account(&resized),
+            Err(LendingError::AccountAlreadyMigrated.into())
+        );
+    }
+
+    #[test]
+    fn pre_upgrade_reserve_account_has_full_collateral_weight() {
+        // reserves packed before the collateral weight was added read it as 0
+        let reserve = Reserve {
+            version: 1,
+            lending_market: Pubkey::new_unique(),
+            config: ReserveConfig {
+                loan_to_value_ratio: 50,
+                liquidation_threshold: 55,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let mut packed = vec![0u8; RESERVE_V1_LEN];
+        Reserve::pack_account(reserve, &mut packed).unwrap();
+
+        let unpacked = Reserve::unpack_account(&packed).unwrap();
+        assert_eq!(unpacked.config.collateral_weight, 0);
+        assert_eq!(
+            unpacked.config.borrow_power_rate().unwrap(),
+            Rate::from_percent(50)
+        );
+    }
+
+    #[test]
+    fn v1_reserve_account_has_no_rate_history() {
+        let reserve = Reserve {
+            version: RESERVE_V2_VERSION - 1,
+            ..rate_sampled_reserve(9_000)
+        };
+        let mut packed = vec![0u8; RESERVE_V1_LEN];
+        assert_eq!(
+            Reserve::pack_account(reserve, &mut packed),
+            Err(LendingError::AccountNotMigrated.into())
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use {
+    super::*,
+    crate::{
+        error::LendingError,
+        math::{Decimal, TryMul, TrySub},
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        entrypoint::ProgramResult,
+        msg,
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::{Pubkey, PUBKEY_BYTES},
+    },
+    std::cmp::min,
+};
+
+/// Seed prefix of reward checkpoint addresses
+pub const REWARD_CHECKPOINT_SEED: &[u8] = b"reward_checkpoint";
+
+/// Seed prefix of reserve reward authority addresses
+pub const REWARD_AUTHORITY_SEED: &[u8] = b"reward_authority";
+
+/// Reward checkpoint state, tracking the reserve rewards of the collateral an
+/// obligation has deposited from a reserve
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RewardCheckpoint {
+    /// Version of the struct
+    pub version: u8,
+    /// Bump seed for the checkpoint address
+    pub bump_seed: u8,
+    /// Obligation address
+    pub obligation: Pubkey,
+    /// Reserve address
+    pub reserve: Pubkey,
+    /// Reserve rewards per collateral token at the last checkpoint
+    pub cumulative_rewards_per_collateral: Decimal,
+    /// Collateral deposited by the obligation at the last checkpoint
+    pub deposited_amount: u64,
+    /// Rewards accrued but not yet paid out
+    pub unclaimed_amount: u64,
+}
+
+impl RewardCheckpoint {
+    /// Create a new reward checkpoint, accruing rewards from the current
+    /// reserve rewards per collateral token
+    pub fn new(params: InitRewardCheckpointParams) -> Self {
+        Self {
+            version: PROGRAM_VERSION,
+            bump_seed: params.bump_seed,
+            obligation: params.obligation,
+            reserve: params.reserve,
+            cumulative_rewards_per_collateral: params.cumulative_rewards_per_collateral,
+            deposited_amount: params.deposited_amount,
+            unclaimed_amount: 0,
+        }
+    }
+
+    /// Accrue the rewards since the last checkpoint and move the checkpoint.
+    /// Rewards are accrued on the lesser of the collateral deposited at the
+    /// last checkpoint and now, as deposits in between are not tracked.
+    pub fn accrue(
+        &mut self,
+        cumulative_rewards_per_collateral: Decimal,
+        deposited_amount: u64,
+    ) -> ProgramResult {
+        let rewards = cumulative_rewards_per_collateral
+            .try_sub(self.cumulative_rewards_per_collateral)?
+            .try_mul(min(self.deposited_amount, deposited_amount))?
+            .try_floor_u64()?;
+        self.unclaimed_amount = self
+            .unclaimed_amount
+            .checked_add(rewards)
+            .ok_or(LendingError::MathOverflow)?;
+        self.cumulative_rewards_per_collateral = cumulative_rewards_per_collateral;
+        self.deposited_amount = deposited_amount;
+        Ok(())
+    }
+}
+
+/// Initialize a reward checkpoint
+pub struct InitRewardCheckpointParams {
+    /// Bump seed for the checkpoint address
+    pub bump_seed: u8,
+    /// Obligation address
+    pub obligation: Pubkey,
+    /// Reserve address
+    pub reserve: Pubkey,
+    /// Current reserve rewards per collateral token
+    pub cumulative_rewards_per_collateral: Decimal,
+    /// Collateral currently deposited by the obligation
+    pub deposited_amount: u64,
+}
+
+/// Derives the address of the reward checkpoint of an obligation deposit
+pub fn find_reward_checkpoint_address(
+    program_id: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            REWARD_CHECKPOINT_SEED,
+            obligation.as_ref(),
+            reserve.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Derives the address of the authority owning the reward vault of a reserve
+pub fn find_reward_authority_address(program_id: &Pubkey, reserve: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REWARD_AUTHORITY_SEED, reserve.as_ref()], program_id)
+}
+
+impl Sealed for RewardCheckpoint {}
+impl IsInitialized for RewardCheckpoint {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const REWARD_CHECKPOINT_LEN: usize = 162; // 1 + 1 + 32 + 32 + 16 + 8 + 8 + 64
+impl Pack for RewardCheckpoint {
+    const LEN: usize = REWARD_CHECKPOINT_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, REWARD_CHECKPOINT_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            bump_seed,
+            obligation,
+            reserve,
+            cumulative_rewards_per_collateral,
+            deposited_amount,
+            unclaimed_amount,
+            _padding,
+        ) = mut_array_refs![output, 1, 1, PUBKEY_BYTES, PUBKEY_BYTES, 16, 8, 8, 64];
+
+        *version = self.version.to_le_bytes();
+        *bump_seed = self.bump_seed.to_le_bytes();
+        obligation.copy_from_slice(self.obligation.as_ref());
+        reserve.copy_from_slice(self.reserve.as_ref());
+        pack_decimal(
+            self.cumulative_rewards_per_collateral,
+            cumulative_rewards_per_collateral,
+        );
+        *deposited_amount = self.deposited_amount.to_le_bytes();
+        *unclaimed_amount = self.unclaimed_amount.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, REWARD_CHECKPOINT_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            bump_seed,
+            obligation,
+            reserve,
+            cumulative_rewards_per_collateral,
+            deposited_amount,
+            unclaimed_amount,
+            _padding,
+        ) = array_refs![input, 1, 1, PUBKEY_BYTES, PUBKEY_BYTES, 16, 8, 8, 64];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Reward checkpoint version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            obligation: Pubkey::new_from_array(*obligation),
+            reserve: Pubkey::new_from_array(*reserve),
+            cumulative_rewards_per_collateral: unpack_decimal(cumulative_rewards_per_collateral),
+            deposited_amount: u64::from_le_bytes(*deposited_amount),
+            unclaimed_amount: u64::from_le_bytes(*unclaimed_amount),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accrue_on_lesser_deposit() {
+        let mut checkpoint = RewardCheckpoint::new(InitRewardCheckpointParams {
+            bump_seed: 255,
+            obligation: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            cumulative_rewards_per_collateral: Decimal::from_percent(10),
+            deposited_amount: 1_000,
+        });
+
+        checkpoint.accrue(Decimal::from_percent(60), 2_000).unwrap();
+        assert_eq!(checkpoint.unclaimed_amount, 500);
+        assert_eq!(checkpoint.deposited_amount, 2_000);
+
+        checkpoint.accrue(Decimal::one(), 1_000).unwrap();
+        assert_eq!(checkpoint.unclaimed_amount, 900);
+        assert_eq!(checkpoint.cumulative_rewards_per_collateral, Decimal::one());
+    }
+
+    #[test]
+    fn pack_roundtrip() {
+        let checkpoint = RewardCheckpoint {
+            version: PROGRAM_VERSION,
+            bump_seed: 254,
+            obligation: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            cumulative_rewards_per_collateral: Decimal::from_scaled_val(987_654_321),
+            deposited_amount: 42,
+            unclaimed_amount: 7,
+        };
+        let mut packed = [0u8; RewardCheckpoint::LEN];
+        RewardCheckpoint::pack(checkpoint.clone(), &mut packed).unwrap();
+        assert_eq!(RewardCheckpoint::unpack(&packed).unwrap(), checkpoint);
+    }
+}
@@ -0,0 +1,93 @@
+#![allow(missing_docs)]
+/// Derived from the Switchboard V2 `AggregatorAccountData` zero-copy layout.
+/// Only the prefix up to and including the latest confirmed round is mapped.
+use bytemuck::{
+    cast_slice, cast_slice_mut, from_bytes, from_bytes_mut, try_cast_slice, try_cast_slice_mut,
+    Pod, PodCastError, Zeroable,
+};
+use std::mem::size_of;
+
+pub mod program {
+    solana_program::declare_id!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+}
+
+pub mod devnet_program {
+    solana_program::declare_id!("2TfB33aLaneQb5TNVwyDz3jSZXS6jdW2ARw1Dgf84XCG");
+}
+
+/// Anchor account discriminator of `AggregatorAccountData`
+pub const AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+pub const MAX_ORACLES: usize = 16;
+
+/// Slots after which a confirmed round is stale, unless the reserve sets
+/// `max_oracle_age_slots`
+pub const STALE_AFTER_SLOTS_ELAPSED: u64 = 240;
+
+#[derive(Copy, Clone)]
+#[repr(C, packed)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C, packed)]
+pub struct AggregatorRound {
+    pub num_success: u32,          // oracle responses accepted this round
+    pub num_error: u32,            // oracle responses rejected this round
+    pub is_closed: u8,             // whether the round has been closed
+    pub round_open_slot: u64,      // slot the round was opened
+    pub round_open_timestamp: i64, // unix timestamp the round was opened
+    pub result: SwitchboardDecimal,
+    pub std_deviation: SwitchboardDecimal,
+    pub min_response: SwitchboardDecimal,
+    pub max_response: SwitchboardDecimal,
+    pub oracle_pubkeys_data: [[u8; 32]; MAX_ORACLES],
+    pub medians_data: [SwitchboardDecimal; MAX_ORACLES],
+    pub current_payout: [i64; MAX_ORACLES],
+    pub medians_fulfilled: [u8; MAX_ORACLES],
+    pub errors_fulfilled: [u8; MAX_ORACLES],
+}
+
+#[derive(Copy, Clone)]
+#[repr(C, packed)]
+pub struct Aggregator {
+    pub discriminator: [u8; 8],
+    pub name: [u8; 32],
+    pub metadata: [u8; 128],
+    pub reserved1: [u8; 32],
+    pub queue_pubkey: [u8; 32],
+    pub oracle_request_batch_size: u32,
+    pub min_oracle_results: u32,
+    pub min_job_results: u32,
+    pub min_update_delay_seconds: u32,
+    pub start_after: i64,
+    pub variance_threshold: SwitchboardDecimal,
+    pub force_report_period: i64,
+    pub expiration: i64,
+    pub consecutive_failure_count: u64,
+    pub next_allowed_update_time: i64,
+    pub is_locked: u8,
+    pub crank_pubkey: [u8; 32],
+    pub latest_confirmed_round: AggregatorRound,
+}
+
+#[cfg(target_endian = "little")]
+unsafe impl Zeroable for Aggregator {}
+
+#[cfg(target_endian = "little")]
+unsafe impl Pod for Aggregator {}
+
+pub fn load<T: Pod>(data: &[u8]) -> Result<&T, PodCastError> {
+    let size = size_of::<T>();
+    Ok(from_bytes(cast_slice::<u8, u8>(try_cast_slice(
+        &data[0..size],
+    )?)))
+}
+
+pub fn load_mut<T: Pod>(data: &mut [u8]) -> Result<&mut T, PodCastError> {
+    let size = size_of::<T>();
+    Ok(from_bytes_mut(cast_slice_mut::<u8, u8>(
+        try_cast_slice_mut(&mut data[0..size])?,
+    )))
+}
@@ -0,0 +1,338 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            add_to_borrow_allowlist, borrow_obligation_liquidity, deposit_reserve_liquidity,
+            refresh_obligation, refresh_reserve, remove_from_borrow_allowlist,
+            set_permissioned_borrowing,
+        },
+        processor::process_instruction,
+        state::{find_borrow_allowlist_entry_address, INITIAL_COLLATERAL_RATIO},
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+// Adds a SOL reserve and a USDC reserve, with an obligation holding SOL
+// collateral, then enables permissioned borrowing in the market
+async fn setup() -> SolUsdcObligationTest {
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let mut test = start_sol_usdc_obligation(
+        test,
+        AddSolUsdcObligationArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                user_liquidity_amount: USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                liquidity_amount: 10 * USDC_BORROW_AMOUNT_FRACTIONAL,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            ..AddSolUsdcObligationArgs::default()
+        },
+    )
+    .await;
+
+    let instruction = set_permissioned_borrowing(
+        spl_token_lending::id(),
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        true,
+    );
+    assert_eq!(
+        process_owner_instruction(&mut test, instruction).await,
+        Ok(())
+    );
+
+    let lending_market_state = test.lending_market.get_state(&mut test.banks_client).await;
+    assert!(lending_market_state.permissioned_borrowing);
+
+    test
+}
+
+async fn process_owner_instruction(
+    test: &mut SolUsdcObligationTest,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&test.payer.pubkey()));
+    transaction.sign(
+        &[&test.payer, &test.lending_market.owner],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn borrow(
+    test: &mut SolUsdcObligationTest,
+    borrow_allowlist_entry_pubkey: Option<Pubkey>,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.sol_test_reserve.pubkey,
+                test.sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                vec![test.sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                test.usdc_test_reserve.liquidity_supply_pubkey,
+                test.usdc_test_reserve.user_liquidity_pubkey,
+                test.usdc_test_reserve.pubkey,
+                test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                test.test_obligation.owner,
+                vec![test.sol_test_reserve.pubkey],
+                borrow_allowlist_entry_pubkey,
+                Some(test.usdc_test_reserve.liquidity_host_pubkey),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.user_accounts_owner],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn deposit(test: &mut SolUsdcObligationTest) -> Result<(), TransactionError> {
+    let usdc_test_reserve = &test.usdc_test_reserve;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn borrower_not_allowed() -> Result<(), TransactionError> {
+    Err(TransactionError::InstructionError(
+        3,
+        InstructionError::Custom(LendingError::BorrowerNotAllowed as u32),
+    ))
+}
+
+#[tokio::test]
+async fn test_allowed_borrower() {
+    let mut test = setup().await;
+    let borrower = test.user_accounts_owner.pubkey();
+    let (borrow_allowlist_entry_pubkey, _bump_seed) = find_borrow_allowlist_entry_address(
+        &spl_token_lending::id(),
+        &test.lending_market.pubkey,
+        &borrower,
+    );
+
+    let instruction = add_to_borrow_allowlist(
+        spl_token_lending::id(),
+        borrower,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.payer.pubkey(),
+    );
+    assert_eq!(
+        process_owner_instruction(&mut test, instruction).await,
+        Ok(())
+    );
+
+    assert_eq!(
+        borrow(&mut test, Some(borrow_allowlist_entry_pubkey)).await,
+        Ok(())
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(
+        obligation.borrows[0].borrow_reserve,
+        test.usdc_test_reserve.pubkey
+    );
+}
+
+#[tokio::test]
+async fn test_borrower_not_allowed() {
+    let mut test = setup().await;
+    let borrower = test.user_accounts_owner.pubkey();
+    let (borrow_allowlist_entry_pubkey, _bump_seed) = find_borrow_allowlist_entry_address(
+        &spl_token_lending::id(),
+        &test.lending_market.pubkey,
+        &borrower,
+    );
+
+    assert_eq!(borrow(&mut test, None).await, borrower_not_allowed());
+
+    // the entry of another borrower can't be used
+    let other_borrower = Pubkey::new_unique();
+    let instruction = add_to_borrow_allowlist(
+        spl_token_lending::id(),
+        other_borrower,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.payer.pubkey(),
+    );
+    assert_eq!(
+        process_owner_instruction(&mut test, instruction).await,
+        Ok(())
+    );
+    let (other_borrow_allowlist_entry_pubkey, _bump_seed) = find_borrow_allowlist_entry_address(
+        &spl_token_lending::id(),
+        &test.lending_market.pubkey,
+        &other_borrower,
+    );
+    assert_eq!(
+        borrow(&mut test, Some(other_borrow_allowlist_entry_pubkey)).await,
+        borrower_not_allowed()
+    );
+
+    // removed borrowers can't borrow anymore, their entry no longer exists
+    let instruction = add_to_borrow_allowlist(
+        spl_token_lending::id(),
+        borrower,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.payer.pubkey(),
+    );
+    assert_eq!(
+        process_owner_instruction(&mut test, instruction).await,
+        Ok(())
+    );
+    let instruction = remove_from_borrow_allowlist(
+        spl_token_lending::id(),
+        borrower,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.lending_market.owner.pubkey(),
+    );
+    assert_eq!(
+        process_owner_instruction(&mut test, instruction).await,
+        Ok(())
+    );
+    assert!(test
+        .banks_client
+        .get_account(borrow_allowlist_entry_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        borrow(&mut test, Some(borrow_allowlist_entry_pubkey)).await,
+        borrower_not_allowed()
+    );
+
+    // deposits stay permissionless
+    assert_eq!(deposit(&mut test).await, Ok(()));
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert!(obligation.borrows.is_empty());
+}
+
+#[tokio::test]
+async fn test_borrow_allowlist_requires_market_owner() {
+    let mut test = setup().await;
+    let not_owner = Keypair::new();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[add_to_borrow_allowlist(
+            spl_token_lending::id(),
+            test.user_accounts_owner.pubkey(),
+            test.lending_market.pubkey,
+            not_owner.pubkey(),
+            test.payer.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(&[&test.payer, &not_owner], test.recent_blockhash);
+    assert_eq!(
+        test.banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
@@ -16,7 +16,7 @@ use {
         instruction::{borrow_obligation_liquidity, refresh_obligation},
         math::Decimal,
         processor::process_instruction,
-        state::{FeeCalculation, INITIAL_COLLATERAL_RATIO},
+        state::{FeeCalculation, Obligation, ReserveConfig, INITIAL_COLLATERAL_RATIO},
     },
     std::u64,
 };
@@ -113,6 +113,8 @@ async fn test_borrow_usdc_fixed_amount() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
                 Some(usdc_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -261,6 +263,8 @@ async fn test_borrow_sol_max_amount() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![usdc_test_reserve.pubkey],
+                None,
                 Some(sol_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -393,6 +397,8 @@ async fn test_borrow_too_large() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
                 Some(usdc_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -415,6 +421,117 @@ async fn test_borrow_too_large() {
     );
 }
 
+#[tokio::test]
+async fn test_borrow_stale_price() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    // refreshed this slot, with a price published a slot earlier, as old as
+    // the max oracle age
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                max_oracle_age_slots: 1,
+                ..reserve_config
+            },
+            mark_fresh: true,
+            oracle_slots_elapsed: 1,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ReservePriceStale as u32)
+        )
+    );
+}
+
 #[tokio::test]
 async fn test_borrow_max_receive_minimum() {
     let mut test = ProgramTest::new(
@@ -508,6 +625,8 @@ async fn test_borrow_max_receive_minimum() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![usdc_test_reserve.pubkey],
+                None,
                 Some(sol_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -647,6 +766,8 @@ async fn test_borrow_max_receive_less_than_slippage() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![usdc_test_reserve.pubkey],
+                None,
                 Some(sol_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -753,6 +874,8 @@ async fn test_borrow_less_than_max_with_slippage() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
                 Some(usdc_test_reserve.liquidity_host_pubkey),
             ),
         ],
@@ -764,3 +887,398 @@ async fn test_borrow_less_than_max_with_slippage() {
     // check that transaction succeeds
     banks_client.process_transaction(transaction).await.unwrap();
 }
+
+const LIMIT_RESERVE_BORROWED_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const LIMIT_BORROW_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const LIMIT_TOTAL_BORROWED_FRACTIONAL: u64 =
+    LIMIT_RESERVE_BORROWED_FRACTIONAL + LIMIT_BORROW_AMOUNT_FRACTIONAL;
+
+// the reserve already lends out 100 USDC and the obligation borrows another
+// 100 USDC without a borrow fee, from a reserve in the V1 layout if requested
+async fn borrow_against_limit(borrow_limit: u64, v1_layout: bool) -> Result<(), BanksClientError> {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.fees.borrow_fee_wad = 0;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: LIMIT_RESERVE_BORROWED_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                borrow_limit,
+                ..reserve_config
+            },
+            mark_fresh: true,
+            v1_layout,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                LIMIT_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn test_borrow_up_to_borrow_limit() {
+    assert!(borrow_against_limit(LIMIT_TOTAL_BORROWED_FRACTIONAL, false)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_borrow_from_pre_upgrade_reserve() {
+    // reserves packed before the borrow limit was added read it as 0
+    assert!(borrow_against_limit(0, true).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_borrow_limit_exceeded() {
+    assert_eq!(
+        borrow_against_limit(LIMIT_TOTAL_BORROWED_FRACTIONAL - 1, false)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::BorrowLimitExceeded as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_limit_zero() {
+    // 0 allows no new borrows rather than disabling the limit
+    assert_eq!(
+        borrow_against_limit(0, false).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::BorrowLimitExceeded as u32)
+        )
+    );
+}
+
+// 100 SOL collateral at $20 and 50% LTV allows $1,000 of borrows
+const WEIGHTED_RESERVE_BORROWED_FRACTIONAL: u64 = 600 * FRACTIONAL_TO_USDC;
+// 1.5x borrow weight
+const USDC_BORROW_WEIGHT_WAD: u64 = 1_500_000_000_000_000_000;
+
+// the obligation already borrows 600 USDC from a reserve with a 1.5x borrow
+// weight, consuming $900 of its $1,000 borrowing power
+async fn borrow_with_borrow_weight(
+    liquidity_amount: u64,
+) -> (Result<(), BanksClientError>, Obligation) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.fees.borrow_fee_wad = 0;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: WEIGHTED_RESERVE_BORROWED_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                borrow_weight_wad: USDC_BORROW_WEIGHT_WAD,
+                ..reserve_config
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, WEIGHTED_RESERVE_BORROWED_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                liquidity_amount,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+
+    (result, obligation)
+}
+
+#[tokio::test]
+async fn test_borrow_weight_consumes_borrow_power() {
+    // $100 of borrowing power left covers 66.67 USDC at a 1.5x weight
+    let (result, obligation) = borrow_with_borrow_weight(66 * FRACTIONAL_TO_USDC).await;
+    assert!(result.is_ok());
+
+    // 600 USDC borrowed counts as $900
+    let liquidity = &obligation.borrows[0];
+    assert_eq!(
+        liquidity.market_value,
+        Decimal::from(WEIGHTED_RESERVE_BORROWED_FRACTIONAL / FRACTIONAL_TO_USDC)
+    );
+    assert_eq!(obligation.borrowed_value, Decimal::from(900u64));
+    assert_eq!(obligation.allowed_borrow_value, Decimal::from(1_000u64));
+    assert_eq!(
+        liquidity.borrowed_amount_wads,
+        Decimal::from(WEIGHTED_RESERVE_BORROWED_FRACTIONAL + 66 * FRACTIONAL_TO_USDC)
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_weight_borrow_too_large() {
+    // 67 USDC at a 1.5x weight would consume $100.50
+    let (result, _) = borrow_with_borrow_weight(67 * FRACTIONAL_TO_USDC).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::BorrowTooLarge as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_against_pre_upgrade_collateral() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    // 100 SOL collateral at $20 and 50% LTV allows $1,000 of borrows
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.fees.borrow_fee_wad = 0;
+
+    // reserves packed before the collateral weight was added read it as 0
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: ReserveConfig {
+                collateral_weight: 0,
+                ..reserve_config
+            },
+            mark_fresh: true,
+            v1_layout: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.allowed_borrow_value, Decimal::from(1_000u64));
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL)
+    );
+}
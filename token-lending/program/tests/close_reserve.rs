@@ -0,0 +1,230 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey},
+    solana_program_test::*,
+    solana_sdk::{
+        account::AccountSharedData,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::state::Mint,
+    spl_token_lending::{
+        error::LendingError, instruction::close_reserve, math::Decimal,
+        processor::process_instruction, state::Reserve,
+    },
+};
+
+struct CloseTest {
+    context: ProgramTestContext,
+    lending_market: TestLendingMarket,
+    usdc_mint: TestMint,
+    usdc_test_reserve: TestReserve,
+    destination: Pubkey,
+}
+
+// Adds an empty USDC reserve, then applies `modify_reserve` to its state
+async fn setup<F>(collateral_amount: u64, modify_reserve: F) -> CloseTest
+where
+    F: FnOnce(&mut Reserve),
+{
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut context = test.start_with_context().await;
+
+    let mut reserve_account = context
+        .banks_client
+        .get_account(usdc_test_reserve.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut reserve = Reserve::unpack(&reserve_account.data).unwrap();
+    modify_reserve(&mut reserve);
+    Reserve::pack(reserve, &mut reserve_account.data).unwrap();
+    context.set_account(
+        &usdc_test_reserve.pubkey,
+        &AccountSharedData::from(reserve_account),
+    );
+
+    CloseTest {
+        context,
+        lending_market,
+        usdc_mint,
+        usdc_test_reserve,
+        destination: Pubkey::new_unique(),
+    }
+}
+
+async fn close(test: &mut CloseTest) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[close_reserve(
+            spl_token_lending::id(),
+            test.usdc_test_reserve.pubkey,
+            test.usdc_test_reserve.collateral_mint_pubkey,
+            test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            test.destination,
+            test.lending_market.pubkey,
+            test.lending_market.owner.pubkey(),
+        )],
+        Some(&test.context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.context.payer, &test.lending_market.owner],
+        test.context.last_blockhash,
+    );
+    test.context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn reserve_not_empty() -> TransactionError {
+    TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(LendingError::ReserveNotEmpty as u32),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup(0, |_| {}).await;
+
+    let reserve_lamports = test
+        .context
+        .banks_client
+        .get_balance(test.usdc_test_reserve.pubkey)
+        .await
+        .unwrap();
+
+    assert_eq!(close(&mut test).await, Ok(()));
+
+    let banks_client = &mut test.context.banks_client;
+    assert!(banks_client
+        .get_account(test.usdc_test_reserve.pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        banks_client.get_balance(test.destination).await.unwrap(),
+        reserve_lamports
+    );
+
+    let collateral_mint = banks_client
+        .get_account(test.usdc_test_reserve.collateral_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let collateral_mint = Mint::unpack(&collateral_mint.data).unwrap();
+    assert_eq!(collateral_mint.mint_authority, COption::None);
+}
+
+#[tokio::test]
+async fn test_available_liquidity() {
+    let mut test = setup(0, |reserve| {
+        reserve.liquidity.available_amount = 1;
+    })
+    .await;
+
+    assert_eq!(close(&mut test).await, Err(reserve_not_empty()));
+}
+
+#[tokio::test]
+async fn test_borrowed_liquidity() {
+    let mut test = setup(0, |reserve| {
+        reserve.liquidity.borrowed_amount_wads = Decimal::from(1u64);
+    })
+    .await;
+
+    assert_eq!(close(&mut test).await, Err(reserve_not_empty()));
+}
+
+#[tokio::test]
+async fn test_collateral_supply() {
+    // Collateral in circulation backs obligation deposits, so a reserve with
+    // any collateral supply may still be referenced by an obligation
+    let mut test = setup(1, |_| {}).await;
+
+    assert_eq!(close(&mut test).await, Err(reserve_not_empty()));
+}
+
+#[tokio::test]
+async fn test_fee_receiver_not_empty() {
+    let mut test = setup(0, |_| {}).await;
+
+    mint_to(
+        &mut test.context.banks_client,
+        test.usdc_mint.pubkey,
+        &test.context.payer,
+        test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        &test.usdc_mint.authority,
+        1,
+    )
+    .await;
+
+    assert_eq!(close(&mut test).await, Err(reserve_not_empty()));
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = setup(0, |_| {}).await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[close_reserve(
+            spl_token_lending::id(),
+            test.usdc_test_reserve.pubkey,
+            test.usdc_test_reserve.collateral_mint_pubkey,
+            test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            test.destination,
+            test.lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&test.context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.context.payer, &invalid_owner],
+        test.context.last_blockhash,
+    );
+
+    assert_eq!(
+        test.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
@@ -5,14 +5,24 @@ mod helpers;
 
 use {
     helpers::*,
+    solana_program::pubkey::Pubkey,
     solana_program_test::*,
     solana_sdk::{
+        instruction::InstructionError,
         signature::{Keypair, Signer},
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::{
+        instruction::approve,
+        state::{Account as Token, AccountState},
     },
-    spl_token::instruction::approve,
     spl_token_lending::{
-        instruction::deposit_obligation_collateral, processor::process_instruction,
+        error::LendingError,
+        instruction::{
+            deposit_obligation_collateral, deposit_obligation_collateral_on_behalf,
+            refresh_obligation, withdraw_obligation_collateral,
+        },
+        processor::process_instruction,
         state::INITIAL_COLLATERAL_RATIO,
     },
 };
@@ -90,6 +100,7 @@ async fn test_success() {
                 lending_market.pubkey,
                 test_obligation.owner,
                 user_transfer_authority.pubkey(),
+                vec![],
             ),
         ],
         Some(&payer.pubkey()),
@@ -115,3 +126,134 @@ async fn test_success() {
         initial_user_collateral_balance - SOL_DEPOSIT_AMOUNT_LAMPORTS
     );
 }
+
+#[tokio::test]
+async fn test_deposit_on_behalf() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 10 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    // a stranger tops up the obligation with their own collateral, without
+    // the owner's key
+    let stranger = Keypair::new();
+    let stranger_collateral_pubkey = Pubkey::new_unique();
+    test.add_packable_account(
+        stranger_collateral_pubkey,
+        u32::MAX as u64,
+        &Token {
+            mint: sol_test_reserve.collateral_mint_pubkey,
+            owner: stranger.pubkey(),
+            amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            state: AccountState::Initialized,
+            ..Token::default()
+        },
+        &spl_token::id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_obligation_collateral_on_behalf(
+            spl_token_lending::id(),
+            SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            stranger_collateral_pubkey,
+            sol_test_reserve.collateral_supply_pubkey,
+            sol_test_reserve.pubkey,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            stranger.pubkey(),
+            vec![],
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, stranger_collateral_pubkey).await,
+        0
+    );
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.owner, user_accounts_owner.pubkey());
+    assert_eq!(
+        obligation.deposits[0].deposit_reserve,
+        sol_test_reserve.pubkey
+    );
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+
+    // withdrawing stays with the obligation owner
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            withdraw_obligation_collateral(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                sol_test_reserve.collateral_supply_pubkey,
+                stranger_collateral_pubkey,
+                sol_test_reserve.pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                stranger.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+}
@@ -0,0 +1,341 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral,
+            deposit_obligation_collateral_and_borrow, refresh_obligation, refresh_reserve,
+        },
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const SOL_RESERVE_LIQUIDITY_LAMPORTS: u64 = 2 * 100 * LAMPORTS_TO_SOL;
+// 100 SOL * 50% LTV -> 50 SOL * 20 USDC -> 1000 USDC max, less the borrow fee
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 800 * FRACTIONAL_TO_USDC;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 4 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+#[tokio::test]
+async fn test_success_matches_deposit_then_borrow() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(120_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let two_step_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+    let composite_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+    let initial_fee_receiver_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_test_reserve.pubkey,
+                two_step_obligation.pubkey,
+                lending_market.pubkey,
+                two_step_obligation.owner,
+                user_accounts_owner.pubkey(),
+                vec![],
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                two_step_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                two_step_obligation.pubkey,
+                lending_market.pubkey,
+                two_step_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let two_step_user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+    let two_step_fee_receiver_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(spl_token_lending::id(), composite_obligation.pubkey, vec![]),
+            deposit_obligation_collateral_and_borrow(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                composite_obligation.pubkey,
+                lending_market.pubkey,
+                composite_obligation.owner,
+                user_accounts_owner.pubkey(),
+                vec![],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let composite_user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+    let composite_fee_receiver_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+
+    assert_eq!(
+        two_step_user_liquidity_balance - initial_user_liquidity_balance,
+        USDC_BORROW_AMOUNT_FRACTIONAL
+    );
+    assert_eq!(
+        composite_user_liquidity_balance - two_step_user_liquidity_balance,
+        two_step_user_liquidity_balance - initial_user_liquidity_balance
+    );
+    assert_eq!(
+        composite_fee_receiver_balance - two_step_fee_receiver_balance,
+        two_step_fee_receiver_balance - initial_fee_receiver_balance
+    );
+
+    let two_step = two_step_obligation.get_state(&mut banks_client).await;
+    let composite = composite_obligation.get_state(&mut banks_client).await;
+    assert_eq!(composite.deposits.len(), 1);
+    assert_eq!(composite.borrows.len(), 1);
+    assert_eq!(
+        composite.deposits[0].deposit_reserve,
+        sol_test_reserve.pubkey
+    );
+    assert_eq!(
+        composite.deposits[0].deposited_amount,
+        two_step.deposits[0].deposited_amount
+    );
+    assert_eq!(
+        composite.borrows[0].borrow_reserve,
+        usdc_test_reserve.pubkey
+    );
+    assert_eq!(
+        composite.borrows[0].borrowed_amount_wads,
+        two_step.borrows[0].borrowed_amount_wads
+    );
+    assert!(composite.last_update.stale);
+}
+
+#[tokio::test]
+async fn test_borrow_exceeds_deposited_collateral() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            mark_fresh: true,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // a hundredth of the collateral cannot cover the borrow
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_obligation_collateral_and_borrow(
+            spl_token_lending::id(),
+            SOL_DEPOSIT_AMOUNT_LAMPORTS / 100,
+            USDC_BORROW_AMOUNT_FRACTIONAL,
+            None,
+            sol_test_reserve.user_collateral_pubkey,
+            sol_test_reserve.collateral_supply_pubkey,
+            sol_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            test_obligation.owner,
+            user_accounts_owner.pubkey(),
+            vec![],
+            None,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::BorrowTooLarge as u32)
+        )
+    );
+}
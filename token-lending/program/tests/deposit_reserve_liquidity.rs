@@ -4,8 +4,23 @@
 mod helpers;
 
 use {
-    helpers::*, solana_program_test::*, solana_sdk::signature::Keypair,
-    spl_token_lending::processor::process_instruction,
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{deposit_reserve_liquidity, refresh_reserve},
+        processor::process_instruction,
+        state::{
+            ReserveConfig, INITIAL_COLLATERAL_RATIO, MAX_ORACLE_AGE_SLOTS,
+            MIN_INITIAL_COLLATERAL_AMOUNT,
+        },
+    },
 };
 
 #[tokio::test]
@@ -52,3 +67,446 @@ async fn test_success() {
         )
         .await;
 }
+
+const SHIFT_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10_000 * FRACTIONAL_TO_USDC;
+const SHIFT_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+#[tokio::test]
+async fn test_min_collateral_amount() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_amount: SHIFT_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_collateral_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_collateral_pubkey).await;
+
+    // the exchange rate is untouched, so exactly the quoted amount is minted
+    let min_collateral_amount = SHIFT_DEPOSIT_AMOUNT_FRACTIONAL * INITIAL_COLLATERAL_RATIO;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+                Some(min_collateral_amount),
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let collateral_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_collateral_pubkey).await;
+    assert_eq!(
+        collateral_balance - initial_collateral_balance,
+        min_collateral_amount
+    );
+}
+
+#[tokio::test]
+async fn test_min_collateral_amount_after_rate_shift() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // fixed 30% borrow rate on half of the reserve liquidity
+    const BORROW_RATE: u8 = 30;
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_amount: SHIFT_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            borrow_amount: SHIFT_RESERVE_LIQUIDITY_FRACTIONAL / 2,
+            config: ReserveConfig {
+                optimal_utilization_rate: 100,
+                min_borrow_rate: BORROW_RATE,
+                optimal_borrow_rate: BORROW_RATE,
+                max_borrow_rate: BORROW_RATE,
+                // the oracle price is from slot 0
+                max_oracle_age_slots: MAX_ORACLE_AGE_SLOTS,
+                ..TEST_RESERVE_CONFIG
+            },
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(1_001).unwrap(); // clock.slot = 1_001
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        last_blockhash: recent_blockhash,
+        ..
+    } = test_context;
+
+    // interest accrued since the quote makes each collateral token worth more
+    // liquidity, so fewer are minted than quoted
+    let min_collateral_amount = SHIFT_DEPOSIT_AMOUNT_FRACTIONAL * INITIAL_COLLATERAL_RATIO;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                SHIFT_DEPOSIT_AMOUNT_FRACTIONAL,
+                Some(min_collateral_amount),
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(LendingError::ExchangeRateSlippageExceeded as u32)
+        )
+    );
+}
+
+const LIMIT_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10_000 * FRACTIONAL_TO_USDC;
+const LIMIT_RESERVE_BORROWED_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const LIMIT_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const LIMIT_TOTAL_LIQUIDITY_FRACTIONAL: u64 =
+    LIMIT_RESERVE_LIQUIDITY_FRACTIONAL + LIMIT_DEPOSIT_AMOUNT_FRACTIONAL;
+
+// borrowed liquidity still counts towards the reserve liquidity, deposited
+// into a reserve in the V1 layout if requested
+async fn deposit_against_limit(
+    deposit_limit: u64,
+    v1_layout: bool,
+) -> Result<(), BanksClientError> {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: LIMIT_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_amount: LIMIT_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            borrow_amount: LIMIT_RESERVE_BORROWED_FRACTIONAL,
+            config: ReserveConfig {
+                deposit_limit,
+                ..TEST_RESERVE_CONFIG
+            },
+            mark_fresh: true,
+            v1_layout,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                LIMIT_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                LIMIT_DEPOSIT_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn test_deposit_up_to_deposit_limit() {
+    assert!(
+        deposit_against_limit(LIMIT_TOTAL_LIQUIDITY_FRACTIONAL, false)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_into_pre_upgrade_reserve() {
+    // reserves packed before the deposit limit was added read it as 0
+    assert!(deposit_against_limit(0, true).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_deposit_limit_exceeded() {
+    assert_eq!(
+        deposit_against_limit(LIMIT_TOTAL_LIQUIDITY_FRACTIONAL - 1, false)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::DepositLimitExceeded as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_limit_below_reserve_liquidity() {
+    assert_eq!(
+        deposit_against_limit(LIMIT_RESERVE_LIQUIDITY_FRACTIONAL / 2, false)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::DepositLimitExceeded as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_limit_zero() {
+    // 0 allows no new deposits rather than disabling the limit
+    assert_eq!(
+        deposit_against_limit(0, false).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::DepositLimitExceeded as u32)
+        )
+    );
+}
+
+const MIN_DEPOSIT_AMOUNT_FRACTIONAL: u64 = FRACTIONAL_TO_USDC;
+
+async fn deposit_into_reserve(
+    min_deposit_amount: u64,
+    reserve_liquidity_amount: u64,
+    deposit_amount: u64,
+) -> Result<(), BanksClientError> {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: deposit_amount,
+            liquidity_amount: reserve_liquidity_amount,
+            collateral_amount: reserve_liquidity_amount * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: ReserveConfig {
+                min_deposit_amount,
+                ..TEST_RESERVE_CONFIG
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                deposit_amount,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                deposit_amount,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn test_deposit_at_min_deposit_amount() {
+    assert!(deposit_into_reserve(
+        MIN_DEPOSIT_AMOUNT_FRACTIONAL,
+        10_000 * FRACTIONAL_TO_USDC,
+        MIN_DEPOSIT_AMOUNT_FRACTIONAL,
+    )
+    .await
+    .is_ok());
+}
+
+#[tokio::test]
+async fn test_deposit_below_min_deposit_amount() {
+    assert_eq!(
+        deposit_into_reserve(
+            MIN_DEPOSIT_AMOUNT_FRACTIONAL,
+            10_000 * FRACTIONAL_TO_USDC,
+            MIN_DEPOSIT_AMOUNT_FRACTIONAL - 1,
+        )
+        .await
+        .unwrap_err()
+        .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::DepositTooSmall as u32)
+        )
+    );
+}
+
+// an empty reserve has no collateral outstanding, so the next deposit sets the
+// exchange rate and must mint at least the seed amount
+#[tokio::test]
+async fn test_first_deposit_seeds_collateral() {
+    assert!(deposit_into_reserve(0, 0, MIN_INITIAL_COLLATERAL_AMOUNT)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_first_deposit_too_small() {
+    assert_eq!(
+        deposit_into_reserve(0, 0, MIN_INITIAL_COLLATERAL_AMOUNT - 1)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::DepositTooSmall as u32)
+        )
+    );
+}
@@ -0,0 +1,252 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::program_pack::Pack,
+    solana_program_test::*,
+    solana_sdk::{
+        account::AccountSharedData,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::{instruction::approve, state::Mint},
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{donate_liquidity, refresh_reserve},
+        math::Decimal,
+        processor::process_instruction,
+        state::{Reserve, INITIAL_COLLATERAL_RATIO},
+    },
+};
+
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const USDC_BAD_DEBT_FRACTIONAL: u64 = 500 * FRACTIONAL_TO_USDC;
+const COLLATERAL_AMOUNT: u64 = USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO;
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BAD_DEBT_FRACTIONAL,
+            user_liquidity_amount: USDC_BAD_DEBT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: COLLATERAL_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut context = test.start_with_context().await;
+
+    let initial_reserve = usdc_test_reserve.get_state(&mut context.banks_client).await;
+    assert_eq!(
+        initial_reserve
+            .collateral_exchange_rate()
+            .unwrap()
+            .collateral_to_liquidity(COLLATERAL_AMOUNT),
+        Ok(USDC_RESERVE_LIQUIDITY_FRACTIONAL)
+    );
+
+    // the borrow can't be recovered, so suppliers are short of it
+    let mut reserve_account = context
+        .banks_client
+        .get_account(usdc_test_reserve.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut reserve = Reserve::unpack(&reserve_account.data).unwrap();
+    reserve.liquidity.borrowed_amount_wads = Decimal::zero();
+    Reserve::pack(reserve, &mut reserve_account.data).unwrap();
+    context.set_account(
+        &usdc_test_reserve.pubkey,
+        &AccountSharedData::from(reserve_account),
+    );
+
+    let reserve = usdc_test_reserve.get_state(&mut context.banks_client).await;
+    assert_eq!(
+        reserve
+            .collateral_exchange_rate()
+            .unwrap()
+            .collateral_to_liquidity(COLLATERAL_AMOUNT),
+        Ok(USDC_RESERVE_LIQUIDITY_FRACTIONAL - USDC_BAD_DEBT_FRACTIONAL)
+    );
+    let initial_liquidity_supply_balance = get_token_balance(
+        &mut context.banks_client,
+        usdc_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                USDC_BAD_DEBT_FRACTIONAL,
+            )
+            .unwrap(),
+            donate_liquidity(
+                spl_token_lending::id(),
+                USDC_BAD_DEBT_FRACTIONAL,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &context.payer,
+            &user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        context.last_blockhash,
+    );
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the donation restores the exchange rate without minting collateral
+    let reserve = usdc_test_reserve.get_state(&mut context.banks_client).await;
+    assert_eq!(
+        reserve
+            .collateral_exchange_rate()
+            .unwrap()
+            .collateral_to_liquidity(COLLATERAL_AMOUNT),
+        Ok(USDC_RESERVE_LIQUIDITY_FRACTIONAL)
+    );
+    assert_eq!(
+        reserve.collateral.mint_total_supply,
+        initial_reserve.collateral.mint_total_supply
+    );
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        initial_reserve.liquidity.available_amount + USDC_BAD_DEBT_FRACTIONAL
+    );
+
+    let liquidity_supply_balance = get_token_balance(
+        &mut context.banks_client,
+        usdc_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+    assert_eq!(
+        liquidity_supply_balance,
+        initial_liquidity_supply_balance + USDC_BAD_DEBT_FRACTIONAL
+    );
+    let collateral_mint_account = context
+        .banks_client
+        .get_account(usdc_test_reserve.collateral_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let collateral_mint = Mint::unpack(&collateral_mint_account.data).unwrap();
+    assert_eq!(collateral_mint.supply, COLLATERAL_AMOUNT);
+}
+
+#[tokio::test]
+async fn test_reserve_stale() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: USDC_BAD_DEBT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: COLLATERAL_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                USDC_BAD_DEBT_FRACTIONAL,
+            )
+            .unwrap(),
+            donate_liquidity(
+                spl_token_lending::id(),
+                USDC_BAD_DEBT_FRACTIONAL,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ReserveStale as u32)
+        )
+    );
+}
@@ -0,0 +1,318 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program::invoke,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{flash_borrow_reserve_liquidity, flash_repay_reserve_liquidity},
+        math::Decimal,
+        processor::process_instruction,
+    },
+};
+
+const FLASH_LOAN_AMOUNT: u64 = 1_000 * FRACTIONAL_TO_USDC;
+// 0.3% flash loan fee of TEST_RESERVE_CONFIG, 20% of it to the host
+const FEE_AMOUNT: u64 = 3_000_000;
+const HOST_FEE_AMOUNT: u64 = 600_000;
+
+struct FlashBorrowTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    user_liquidity_pubkey: Pubkey,
+    proxy_program_id: Pubkey,
+}
+
+// Invokes the lending instruction in its data with the remaining accounts, to
+// flash borrow via CPI
+fn process_proxy_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (lending_program_info, account_infos) = accounts.split_first().unwrap();
+    invoke(
+        &Instruction {
+            program_id: *lending_program_info.key,
+            accounts: account_infos
+                .iter()
+                .map(|account_info| AccountMeta {
+                    pubkey: *account_info.key,
+                    is_signer: account_info.is_signer,
+                    is_writable: account_info.is_writable,
+                })
+                .collect(),
+            data: instruction_data.to_vec(),
+        },
+        account_infos,
+    )
+}
+
+// Adds a USDC reserve holding the flash loan amount, and a user USDC account
+// holding enough to pay the fees of two flash loans
+async fn setup() -> FlashBorrowTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let proxy_program_id = Pubkey::new_unique();
+    test.prefer_bpf(false);
+    test.add_program(
+        "flash_borrow_proxy",
+        proxy_program_id,
+        processor!(process_proxy_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: FLASH_LOAN_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let user_liquidity_pubkey = add_account_for_program(
+        &mut test,
+        &user_accounts_owner.pubkey(),
+        2 * FEE_AMOUNT,
+        &usdc_mint.pubkey,
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    FlashBorrowTest {
+        banks_client,
+        payer,
+        user_accounts_owner,
+        lending_market,
+        usdc_test_reserve,
+        user_liquidity_pubkey,
+        proxy_program_id,
+    }
+}
+
+fn flash_borrow(test: &FlashBorrowTest, liquidity_amount: u64) -> Instruction {
+    flash_borrow_reserve_liquidity(
+        spl_token_lending::id(),
+        liquidity_amount,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.user_liquidity_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.lending_market.pubkey,
+    )
+}
+
+fn flash_repay(
+    test: &FlashBorrowTest,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+) -> Instruction {
+    flash_repay_reserve_liquidity(
+        spl_token_lending::id(),
+        liquidity_amount,
+        borrow_instruction_index,
+        test.user_liquidity_pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        test.usdc_test_reserve.liquidity_host_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    )
+}
+
+async fn process(
+    test: &mut FlashBorrowTest,
+    instructions: &[Instruction],
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&test.payer.pubkey()));
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    // only repays need the user transfer authority to sign
+    let mut signers = vec![&test.payer];
+    if transaction
+        .message
+        .account_keys
+        .contains(&test.user_accounts_owner.pubkey())
+    {
+        signers.push(&test.user_accounts_owner);
+    }
+    transaction.sign(&signers, recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup().await;
+
+    let instructions = [
+        flash_borrow(&test, FLASH_LOAN_AMOUNT),
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 0),
+    ];
+    process(&mut test, &instructions).await.unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(usdc_reserve.liquidity.available_amount, FLASH_LOAN_AMOUNT);
+    assert_eq!(usdc_reserve.liquidity.borrowed_amount_wads, Decimal::zero());
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.liquidity_supply_pubkey
+        )
+        .await,
+        FLASH_LOAN_AMOUNT
+    );
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, test.user_liquidity_pubkey).await,
+        FEE_AMOUNT
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.liquidity_fee_receiver_pubkey
+        )
+        .await,
+        FEE_AMOUNT - HOST_FEE_AMOUNT
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.liquidity_host_pubkey
+        )
+        .await,
+        HOST_FEE_AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn test_multiple_pairs() {
+    let mut test = setup().await;
+
+    let instructions = [
+        flash_borrow(&test, FLASH_LOAN_AMOUNT),
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 0),
+        flash_borrow(&test, FLASH_LOAN_AMOUNT),
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 2),
+    ];
+    process(&mut test, &instructions).await.unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(usdc_reserve.liquidity.available_amount, FLASH_LOAN_AMOUNT);
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, test.user_liquidity_pubkey).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_missing_repay() {
+    let mut test = setup().await;
+
+    let instructions = [flash_borrow(&test, FLASH_LOAN_AMOUNT)];
+    assert_eq!(
+        process(&mut test, &instructions).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NoFlashRepayFound as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_repay_smaller_amount() {
+    let mut test = setup().await;
+
+    let instructions = [
+        flash_borrow(&test, FLASH_LOAN_AMOUNT),
+        flash_repay(&test, FLASH_LOAN_AMOUNT - 1, 0),
+    ];
+    assert_eq!(
+        process(&mut test, &instructions).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidFlashRepay as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_duplicate_repay() {
+    let mut test = setup().await;
+
+    let instructions = [
+        flash_borrow(&test, FLASH_LOAN_AMOUNT),
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 0),
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 0),
+    ];
+    assert_eq!(
+        process(&mut test, &instructions).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidFlashRepay as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_via_cpi() {
+    let mut test = setup().await;
+
+    // the proxy borrows via CPI, with a top level repay pointing at it
+    let borrow = flash_borrow(&test, FLASH_LOAN_AMOUNT);
+    let mut accounts = vec![AccountMeta::new_readonly(spl_token_lending::id(), false)];
+    accounts.extend(borrow.accounts);
+    let instructions = [
+        Instruction {
+            program_id: test.proxy_program_id,
+            accounts,
+            data: borrow.data,
+        },
+        flash_repay(&test, FLASH_LOAN_AMOUNT, 0),
+    ];
+    assert_eq!(
+        process(&mut test, &instructions).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::FlashLoanInvokedViaCpi as u32)
+        )
+    );
+}
@@ -14,7 +14,9 @@ use {
     },
     spl_token::solana_program::instruction::InstructionError,
     spl_token_lending::{
-        error::LendingError, instruction::flash_loan, processor::process_instruction,
+        error::LendingError,
+        instruction::{flash_loan, set_reserve_flash_loan_fee_receiver},
+        processor::process_instruction,
     },
 };
 
@@ -225,3 +227,196 @@ async fn test_failure() {
         )
     );
 }
+
+#[tokio::test]
+async fn test_success_zero_fee() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const FLASH_LOAN_AMOUNT: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+    let receiver_program_id = Pubkey::new_unique();
+    test.prefer_bpf(false);
+    test.add_program(
+        "flash_loan_receiver",
+        receiver_program_id,
+        processor!(helpers::flash_loan_receiver::process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.fees.flash_loan_fee_wad = 0;
+    // the borrow fee doesn't apply to flash loans
+    assert!(reserve_config.fees.borrow_fee_wad > 0);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: FLASH_LOAN_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (receiver_authority_pubkey, _) =
+        Pubkey::find_program_address(&[b"flashloan"], &receiver_program_id);
+    let program_owned_token_account =
+        add_account_for_program(&mut test, &receiver_authority_pubkey, 0, &usdc_mint.pubkey);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[flash_loan(
+            spl_token_lending::id(),
+            FLASH_LOAN_AMOUNT,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            program_owned_token_account,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            usdc_test_reserve.liquidity_host_pubkey,
+            lending_market.pubkey,
+            receiver_program_id,
+            vec![AccountMeta::new_readonly(receiver_authority_pubkey, false)],
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let liquidity_supply =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_supply_pubkey).await;
+    assert_eq!(liquidity_supply, FLASH_LOAN_AMOUNT);
+
+    let fee_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+    assert_eq!(fee_balance, 0);
+}
+
+#[tokio::test]
+async fn test_fee_receiver_updated_mid_flight() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const FLASH_LOAN_AMOUNT: u64 = 1_000 * FRACTIONAL_TO_USDC;
+    const FEE_AMOUNT: u64 = 3_000_000;
+    const HOST_FEE_AMOUNT: u64 = 600_000;
+
+    let receiver_program_id = Pubkey::new_unique();
+    test.prefer_bpf(false);
+    test.add_program(
+        "flash_loan_receiver",
+        receiver_program_id,
+        processor!(helpers::flash_loan_receiver::process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.fees.flash_loan_fee_wad = 3_000_000_000_000_000;
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: FLASH_LOAN_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (receiver_authority_pubkey, _) =
+        Pubkey::find_program_address(&[b"flashloan"], &receiver_program_id);
+    let program_owned_token_account = add_account_for_program(
+        &mut test,
+        &receiver_authority_pubkey,
+        FEE_AMOUNT,
+        &usdc_mint.pubkey,
+    );
+    let flash_loan_fee_receiver =
+        add_account_for_program(&mut test, &Pubkey::new_unique(), 0, &usdc_mint.pubkey);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let flash_loan_with_fee_receiver = |fee_receiver_pubkey| {
+        Transaction::new_signed_with_payer(
+            &[
+                set_reserve_flash_loan_fee_receiver(
+                    spl_token_lending::id(),
+                    usdc_test_reserve.pubkey,
+                    flash_loan_fee_receiver,
+                    lending_market.pubkey,
+                    lending_market.owner.pubkey(),
+                ),
+                flash_loan(
+                    spl_token_lending::id(),
+                    FLASH_LOAN_AMOUNT,
+                    usdc_test_reserve.liquidity_supply_pubkey,
+                    program_owned_token_account,
+                    usdc_test_reserve.pubkey,
+                    fee_receiver_pubkey,
+                    usdc_test_reserve.liquidity_host_pubkey,
+                    lending_market.pubkey,
+                    receiver_program_id,
+                    vec![AccountMeta::new_readonly(receiver_authority_pubkey, false)],
+                ),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer, &lending_market.owner],
+            recent_blockhash,
+        )
+    };
+
+    // the flash loan can't name the fee receiver the reserve had before
+    assert_eq!(
+        banks_client
+            .process_transaction(flash_loan_with_fee_receiver(
+                usdc_test_reserve.liquidity_fee_receiver_pubkey
+            ))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+
+    assert!(banks_client
+        .process_transaction(flash_loan_with_fee_receiver(flash_loan_fee_receiver))
+        .await
+        .is_ok());
+
+    let flash_loan_fee_balance =
+        get_token_balance(&mut banks_client, flash_loan_fee_receiver).await;
+    assert_eq!(flash_loan_fee_balance, FEE_AMOUNT - HOST_FEE_AMOUNT);
+
+    let host_fee_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_host_pubkey).await;
+    assert_eq!(host_fee_balance, HOST_FEE_AMOUNT);
+}
@@ -0,0 +1,236 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::{
+        example_flash_loan_receiver::{AUTHORITY_SEED, FLASH_LOAN_AMOUNT},
+        *,
+    },
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        sysvar,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::solana_program::instruction::InstructionError,
+    spl_token_lending::{
+        cpi::RECEIVE_FLASH_LOAN_INSTRUCTION_TAG, error::LendingError, instruction::flash_loan,
+        processor::process_instruction,
+    },
+};
+
+struct FlashLoanCpiTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    receiver_program_id: Pubkey,
+    receiver_authority_pubkey: Pubkey,
+    program_owned_token_account: Pubkey,
+}
+
+// Adds a USDC reserve holding FLASH_LOAN_AMOUNT and the example receiver
+// program, holding enough to pay the flash loan fee
+async fn setup() -> FlashLoanCpiTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let receiver_program_id = Pubkey::new_unique();
+    test.prefer_bpf(false);
+    test.add_program(
+        "example_flash_loan_receiver",
+        receiver_program_id,
+        processor!(helpers::example_flash_loan_receiver::process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.fees.flash_loan_fee_wad = 3_000_000_000_000_000;
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: FLASH_LOAN_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (receiver_authority_pubkey, _) =
+        Pubkey::find_program_address(&[AUTHORITY_SEED], &receiver_program_id);
+    let program_owned_token_account = add_account_for_program(
+        &mut test,
+        &receiver_authority_pubkey,
+        FLASH_LOAN_AMOUNT,
+        &usdc_mint.pubkey,
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    FlashLoanCpiTest {
+        banks_client,
+        payer,
+        lending_market,
+        usdc_test_reserve,
+        receiver_program_id,
+        receiver_authority_pubkey,
+        program_owned_token_account,
+    }
+}
+
+// Accounts the example receiver expects after the ReceiveFlashLoan accounts
+fn receiver_accounts(test: &FlashLoanCpiTest, reserve_pubkey: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(test.receiver_authority_pubkey, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(reserve_pubkey, false),
+    ]
+}
+
+fn flash_loan_instruction(
+    test: &FlashLoanCpiTest,
+    amount: u64,
+    reserve_pubkey: Pubkey,
+) -> Instruction {
+    flash_loan(
+        spl_token_lending::id(),
+        amount,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.program_owned_token_account,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        test.usdc_test_reserve.liquidity_host_pubkey,
+        test.lending_market.pubkey,
+        test.receiver_program_id,
+        receiver_accounts(test, reserve_pubkey),
+    )
+}
+
+async fn process(
+    test: &mut FlashLoanCpiTest,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&test.payer.pubkey()));
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn invalid_flash_loan_invocation() -> TransactionError {
+    TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(LendingError::InvalidFlashLoanInvocation as u32),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup().await;
+    let reserve_pubkey = test.usdc_test_reserve.pubkey;
+
+    let initial_liquidity_supply = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+    let initial_token_balance =
+        get_token_balance(&mut test.banks_client, test.program_owned_token_account).await;
+
+    let instruction = flash_loan_instruction(&test, FLASH_LOAN_AMOUNT, reserve_pubkey);
+    process(&mut test, instruction).await.unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(usdc_reserve.liquidity.available_amount, FLASH_LOAN_AMOUNT);
+
+    let (total_fee, _host_fee) = usdc_reserve
+        .config
+        .fees
+        .calculate_flash_loan_fees(FLASH_LOAN_AMOUNT.into())
+        .unwrap();
+    assert!(total_fee > 0);
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, test.program_owned_token_account).await,
+        initial_token_balance - total_fee
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.liquidity_supply_pubkey
+        )
+        .await,
+        initial_liquidity_supply
+    );
+}
+
+#[tokio::test]
+async fn test_unexpected_amount() {
+    let mut test = setup().await;
+    let reserve_pubkey = test.usdc_test_reserve.pubkey;
+
+    let instruction = flash_loan_instruction(&test, FLASH_LOAN_AMOUNT / 2, reserve_pubkey);
+    assert_eq!(
+        process(&mut test, instruction).await.unwrap_err(),
+        invalid_flash_loan_invocation()
+    );
+}
+
+#[tokio::test]
+async fn test_unexpected_reserve() {
+    let mut test = setup().await;
+
+    let instruction = flash_loan_instruction(&test, FLASH_LOAN_AMOUNT, Pubkey::new_unique());
+    assert_eq!(
+        process(&mut test, instruction).await.unwrap_err(),
+        invalid_flash_loan_invocation()
+    );
+}
+
+#[tokio::test]
+async fn test_not_invoked_by_lending_program() {
+    let mut test = setup().await;
+    let reserve_pubkey = test.usdc_test_reserve.pubkey;
+
+    // the receiver is invoked directly, outside of a flash loan
+    let mut accounts = vec![
+        AccountMeta::new(test.program_owned_token_account, false),
+        AccountMeta::new(test.usdc_test_reserve.liquidity_supply_pubkey, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(receiver_accounts(&test, reserve_pubkey));
+    let mut data = vec![RECEIVE_FLASH_LOAN_INSTRUCTION_TAG];
+    data.extend_from_slice(&FLASH_LOAN_AMOUNT.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: test.receiver_program_id,
+        accounts,
+        data,
+    };
+    assert_eq!(
+        process(&mut test, instruction).await.unwrap_err(),
+        invalid_flash_loan_invocation()
+    );
+}
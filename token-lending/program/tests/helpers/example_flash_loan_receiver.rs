@@ -0,0 +1,67 @@
+//! Example flash loan receiver built on the `spl_token_lending::cpi` helpers
+
+use {
+    crate::helpers::FRACTIONAL_TO_USDC,
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        pubkey::Pubkey,
+    },
+    spl_token_lending::{
+        cpi::{
+            assert_flash_loan_invocation, repay_flash_loan, unpack_receive_flash_loan,
+            FlashLoanReceiverAccounts,
+        },
+        error::LendingError,
+    },
+};
+
+/// Seed of the PDA owning the receiver's liquidity token accounts
+pub const AUTHORITY_SEED: &[u8] = b"flashloan";
+
+/// Liquidity amount the receiver expects to be loaned
+pub const FLASH_LOAN_AMOUNT: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+/// Receives a flash loan of FLASH_LOAN_AMOUNT and repays it with the fee.
+///
+/// Additional accounts expected after the `ReceiveFlashLoan` accounts:
+///
+///   0. `[]` Derived receiver authority, owner of the source liquidity.
+///   1. `[]` Instructions sysvar.
+///   2. `[]` Reserve expected to lend the liquidity.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let amount = unpack_receive_flash_loan(instruction_data)?;
+    msg!("Instruction: Receive Flash Loan");
+
+    let receiver_accounts = FlashLoanReceiverAccounts::from_account_infos(accounts)?;
+    let account_info_iter = &mut receiver_accounts.remaining_account_infos.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+
+    assert_flash_loan_invocation(
+        &spl_token_lending::id(),
+        &receiver_accounts,
+        instructions_sysvar_info,
+        reserve_info.key,
+        FLASH_LOAN_AMOUNT,
+    )?;
+
+    let (authority_pubkey, bump_seed) = Pubkey::find_program_address(&[AUTHORITY_SEED], program_id);
+    if &authority_pubkey != authority_info.key {
+        msg!("Receiver authority does not match the derived authority");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    repay_flash_loan(
+        &receiver_accounts,
+        authority_info,
+        amount,
+        &[&[AUTHORITY_SEED, &[bump_seed]]],
+    )
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+pub mod example_flash_loan_receiver;
 pub mod flash_loan_receiver;
 
 use {
@@ -8,6 +9,7 @@ use {
     solana_program_test::*,
     solana_sdk::{
         account::Account,
+        hash::Hash,
         signature::{read_keypair_file, Keypair, Signer},
         system_instruction::create_account,
         transaction::{Transaction, TransactionError},
@@ -16,21 +18,31 @@ use {
         instruction::approve,
         state::{Account as Token, AccountState, Mint},
     },
+    spl_token_2022::{
+        extension::{
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        },
+        state::Mint as Token2022Mint,
+    },
     spl_token_lending::{
         instruction::{
-            borrow_obligation_liquidity, deposit_reserve_liquidity, init_lending_market,
-            init_obligation, init_reserve, liquidate_obligation, refresh_reserve,
+            borrow_obligation_liquidity, deposit_reserve_liquidity, init_derived_obligation,
+            init_lending_market, init_obligation, init_reserve,
+            init_reserve_with_derived_fee_receiver, liquidate_obligation, refresh_reserve,
         },
         math::{Decimal, Rate, TryAdd, TryMul},
         pyth,
         state::{
-            InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-            NewReserveCollateralParams, NewReserveLiquidityParams, Obligation,
-            ObligationCollateral, ObligationLiquidity, Reserve, ReserveCollateral, ReserveConfig,
-            ReserveFees, ReserveLiquidity, INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION,
+            get_obligation_address, InitLendingMarketParams, InitObligationParams,
+            InitReserveParams, LendingMarket, NewReserveCollateralParams,
+            NewReserveLiquidityParams, Obligation, ObligationCollateral, ObligationLiquidity,
+            OracleKind, Reserve, ReserveCollateral, ReserveConfig, ReserveFees, ReserveLiquidity,
+            INITIAL_COLLATERAL_RATIO, LENDING_MARKET_V1_LEN, PROGRAM_VERSION, RESERVE_V1_LEN,
         },
+        switchboard,
     },
-    std::{convert::TryInto, str::FromStr},
+    std::{convert::TryInto, mem::size_of, str::FromStr},
 };
 
 pub const QUOTE_CURRENCY: [u8; 32] =
@@ -42,11 +54,36 @@ pub const FRACTIONAL_TO_USDC: u64 = 1_000_000;
 pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
     optimal_utilization_rate: 80,
     loan_to_value_ratio: 50,
+    collateral_weight: 100,
     liquidation_bonus: 5,
+    min_liquidation_bonus: 0,
+    max_liquidation_bonus: 5,
     liquidation_threshold: 55,
+    stressed_liquidation_threshold: 0,
+    stress_utilization_rate: 0,
     min_borrow_rate: 0,
     optimal_borrow_rate: 4,
     max_borrow_rate: 30,
+    max_utilization_rate: 0,
+    super_max_borrow_rate: 0,
+    max_withdraw_utilization: 100,
+    borrow_rate_rebate: 0,
+    borrow_rate_floor: 0,
+    borrow_limit: u64::MAX,
+    deposit_limit: u64::MAX,
+    max_oracle_age_slots: 0,
+    borrow_weight_wad: 0,
+    protocol_liquidation_fee: 0,
+    liquidation_close_factor: 50,
+    liquidation_dust_threshold: 0,
+    min_deposit_amount: 0,
+    max_confidence_interval_bps: 0,
+    outflow_window_duration_slots: 0,
+    max_outflow: 0,
+    max_accrual_slots_per_refresh: 0,
+    rate_history_interval_slots: 0,
+    isolated: false,
+    borrowable_in_isolation: false,
     fees: ReserveFees {
         // 0.00001% (Aave borrow fee)
         borrow_fee_wad: 100_000_000_000,
@@ -89,6 +126,28 @@ impl AddPacked for ProgramTest {
 }
 
 pub fn add_lending_market(test: &mut ProgramTest) -> TestLendingMarket {
+    add_lending_market_account(test, LendingMarket::LEN, QUOTE_CURRENCY)
+}
+
+/// Adds a lending market quoting prices in the given currency, a padded symbol
+/// or a mint pubkey
+pub fn add_lending_market_with_quote_currency(
+    test: &mut ProgramTest,
+    quote_currency: [u8; 32],
+) -> TestLendingMarket {
+    add_lending_market_account(test, LendingMarket::LEN, quote_currency)
+}
+
+/// Adds a lending market in the V1 account layout, to be migrated
+pub fn add_v1_lending_market(test: &mut ProgramTest) -> TestLendingMarket {
+    add_lending_market_account(test, LENDING_MARKET_V1_LEN, QUOTE_CURRENCY)
+}
+
+fn add_lending_market_account(
+    test: &mut ProgramTest,
+    account_len: usize,
+    quote_currency: [u8; 32],
+) -> TestLendingMarket {
     let lending_market_pubkey = Pubkey::new_unique();
     let (lending_market_authority, bump_seed) =
         Pubkey::find_program_address(&[lending_market_pubkey.as_ref()], &spl_token_lending::id());
@@ -99,24 +158,22 @@ pub fn add_lending_market(test: &mut ProgramTest) -> TestLendingMarket {
         .unwrap()
         .pubkey();
 
-    test.add_packable_account(
-        lending_market_pubkey,
-        u32::MAX as u64,
-        &LendingMarket::new(InitLendingMarketParams {
-            bump_seed,
-            owner: lending_market_owner.pubkey(),
-            quote_currency: QUOTE_CURRENCY,
-            token_program_id: spl_token::id(),
-            oracle_program_id,
-        }),
-        &spl_token_lending::id(),
-    );
+    let lending_market = LendingMarket::new(InitLendingMarketParams {
+        bump_seed,
+        owner: lending_market_owner.pubkey(),
+        quote_currency,
+        token_program_id: spl_token::id(),
+        oracle_program_id,
+    });
+    let mut account = Account::new(u32::MAX as u64, account_len, &spl_token_lending::id());
+    LendingMarket::pack_account(lending_market, &mut account.data).unwrap();
+    test.add_account(lending_market_pubkey, account);
 
     TestLendingMarket {
         pubkey: lending_market_pubkey,
         owner: lending_market_owner,
         authority: lending_market_authority,
-        quote_currency: QUOTE_CURRENCY,
+        quote_currency,
         oracle_program_id,
     }
 }
@@ -127,6 +184,8 @@ pub struct AddObligationArgs<'a> {
     pub borrows: &'a [(&'a TestReserve, u64)],
     pub mark_fresh: bool,
     pub slots_elapsed: u64,
+    /// Packs the obligation at an older version, to be migrated
+    pub version: Option<u8>,
 }
 
 pub fn add_obligation(
@@ -140,6 +199,7 @@ pub fn add_obligation(
         borrows,
         mark_fresh,
         slots_elapsed,
+        version,
     } = args;
 
     let obligation_keypair = Keypair::new();
@@ -167,7 +227,7 @@ pub fn add_obligation(
         .map(|(borrow_reserve, liquidity_amount)| {
             let borrowed_amount_wads = Decimal::from(*liquidity_amount);
 
-            let mut liquidity = ObligationLiquidity::new(borrow_reserve.pubkey);
+            let mut liquidity = ObligationLiquidity::new(borrow_reserve.pubkey, Decimal::one());
             liquidity.borrowed_amount_wads = borrowed_amount_wads;
 
             (
@@ -195,13 +255,19 @@ pub fn add_obligation(
     if mark_fresh {
         obligation.last_update.update_slot(current_slot);
     }
+    if let Some(version) = version {
+        obligation.version = version;
+    }
 
-    test.add_packable_account(
-        obligation_pubkey,
+    // obligations with more reserves than fit in Obligation::LEN are added
+    // already resized
+    let mut account = Account::new(
         u32::MAX as u64,
-        &obligation,
+        Obligation::account_len(deposits.len() + borrows.len()),
         &spl_token_lending::id(),
     );
+    Obligation::pack_account(obligation, &mut account.data).unwrap();
+    test.add_account(obligation_pubkey, account);
 
     TestObligation {
         pubkey: obligation_pubkey,
@@ -225,6 +291,11 @@ pub struct AddReserveArgs {
     pub collateral_amount: u64,
     pub mark_fresh: bool,
     pub slots_elapsed: u64,
+    /// Slots the oracle published the market price before the reserve was
+    /// last updated
+    pub oracle_slots_elapsed: u64,
+    /// Packs the reserve in the V1 account layout, to be migrated
+    pub v1_layout: bool,
 }
 
 pub fn add_reserve(
@@ -246,6 +317,8 @@ pub fn add_reserve(
         collateral_amount,
         mark_fresh,
         slots_elapsed,
+        oracle_slots_elapsed,
+        v1_layout,
     } = args;
 
     let is_native = if liquidity_mint_pubkey == spl_token::native_mint::id() {
@@ -311,7 +384,7 @@ pub fn add_reserve(
         u32::MAX as u64,
         &Token {
             mint: liquidity_mint_pubkey,
-            owner: lending_market.owner.pubkey(),
+            owner: lending_market.authority,
             amount: 0,
             state: AccountState::Initialized,
             ..Token::default()
@@ -343,8 +416,10 @@ pub fn add_reserve(
             mint_decimals: liquidity_mint_decimals,
             supply_pubkey: liquidity_supply_pubkey,
             fee_receiver: liquidity_fee_receiver_pubkey,
+            oracle_kind: oracle.oracle_kind,
             oracle_pubkey: oracle.price_pubkey,
             market_price: oracle.price,
+            market_price_slot: current_slot - oracle_slots_elapsed,
         }),
         collateral: ReserveCollateral::new(NewReserveCollateralParams {
             mint_pubkey: collateral_mint_pubkey,
@@ -364,12 +439,14 @@ pub fn add_reserve(
         reserve.last_update.update_slot(current_slot);
     }
 
-    test.add_packable_account(
-        reserve_pubkey,
-        u32::MAX as u64,
-        &reserve,
-        &spl_token_lending::id(),
-    );
+    let account_len = if v1_layout {
+        RESERVE_V1_LEN
+    } else {
+        Reserve::LEN
+    };
+    let mut account = Account::new(u32::MAX as u64, account_len, &spl_token_lending::id());
+    Reserve::pack_account(reserve, &mut account.data).unwrap();
+    test.add_account(reserve_pubkey, account);
 
     let amount = if let COption::Some(rent_reserve) = is_native {
         user_liquidity_amount + rent_reserve
@@ -424,6 +501,118 @@ pub fn add_reserve(
     }
 }
 
+#[derive(Default)]
+pub struct AddSolUsdcObligationArgs {
+    /// SOL reserve args, the liquidity mint is filled in and it's marked fresh
+    pub sol_reserve: AddReserveArgs,
+    /// USDC reserve args, the liquidity mint is filled in and it's marked
+    /// fresh
+    pub usdc_reserve: AddReserveArgs,
+    pub sol_deposit_amount: u64,
+    pub usdc_deposit_amount: u64,
+    pub usdc_borrow_amount: u64,
+}
+
+/// Started test with a lending market holding a SOL reserve and a USDC
+/// reserve, and an obligation using them
+pub struct SolUsdcObligationTest {
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub recent_blockhash: Hash,
+    pub user_accounts_owner: Keypair,
+    pub lending_market: TestLendingMarket,
+    pub sol_oracle: TestOracle,
+    pub sol_test_reserve: TestReserve,
+    pub usdc_oracle: TestOracle,
+    pub usdc_test_reserve: TestReserve,
+    pub test_obligation: TestObligation,
+}
+
+/// Adds a lending market with a SOL reserve and a USDC reserve, and an
+/// obligation depositing and borrowing the non-zero amounts given, then
+/// starts the test
+pub async fn start_sol_usdc_obligation(
+    mut test: ProgramTest,
+    args: AddSolUsdcObligationArgs,
+) -> SolUsdcObligationTest {
+    let AddSolUsdcObligationArgs {
+        sol_reserve,
+        usdc_reserve,
+        sol_deposit_amount,
+        usdc_deposit_amount,
+        usdc_borrow_amount,
+    } = args;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            mark_fresh: true,
+            ..sol_reserve
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            mark_fresh: true,
+            ..usdc_reserve
+        },
+    );
+
+    let mut deposits = vec![];
+    if sol_deposit_amount > 0 {
+        deposits.push((&sol_test_reserve, sol_deposit_amount));
+    }
+    if usdc_deposit_amount > 0 {
+        deposits.push((&usdc_test_reserve, usdc_deposit_amount));
+    }
+    let mut borrows = vec![];
+    if usdc_borrow_amount > 0 {
+        borrows.push((&usdc_test_reserve, usdc_borrow_amount));
+    }
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &deposits,
+            borrows: &borrows,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    SolUsdcObligationTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        lending_market,
+        sol_oracle,
+        sol_test_reserve,
+        usdc_oracle,
+        usdc_test_reserve,
+        test_obligation,
+    }
+}
+
 pub fn add_account_for_program(
     test: &mut ProgramTest,
     program_derived_account: &Pubkey,
@@ -472,6 +661,14 @@ pub struct LiquidateArgs<'a> {
 
 impl TestLendingMarket {
     pub async fn init(banks_client: &mut BanksClient, payer: &Keypair) -> Self {
+        Self::init_with_quote_currency(banks_client, payer, QUOTE_CURRENCY).await
+    }
+
+    pub async fn init_with_quote_currency(
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        quote_currency: [u8; 32],
+    ) -> Self {
         let lending_market_owner =
             read_keypair_file("tests/fixtures/lending_market_owner.json").unwrap();
         let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json")
@@ -498,7 +695,7 @@ impl TestLendingMarket {
                 init_lending_market(
                     spl_token_lending::id(),
                     lending_market_owner.pubkey(),
-                    QUOTE_CURRENCY,
+                    quote_currency,
                     lending_market_pubkey,
                     oracle_program_id,
                 ),
@@ -514,7 +711,7 @@ impl TestLendingMarket {
             owner: lending_market_owner,
             pubkey: lending_market_pubkey,
             authority: lending_market_authority,
-            quote_currency: QUOTE_CURRENCY,
+            quote_currency,
             oracle_program_id,
         }
     }
@@ -563,6 +760,7 @@ impl TestLendingMarket {
                 deposit_reserve_liquidity(
                     spl_token_lending::id(),
                     liquidity_amount,
+                    None,
                     reserve.user_liquidity_pubkey,
                     reserve.user_collateral_pubkey,
                     reserve.pubkey,
@@ -622,6 +820,7 @@ impl TestLendingMarket {
                     obligation.pubkey,
                     self.pubkey,
                     user_transfer_authority.pubkey(),
+                    None,
                 ),
             ],
             Some(&payer.pubkey()),
@@ -660,6 +859,12 @@ impl TestLendingMarket {
                 obligation.pubkey,
                 self.pubkey,
                 obligation.owner,
+                obligation
+                    .deposits
+                    .iter()
+                    .map(|collateral| collateral.deposit_reserve)
+                    .collect(),
+                None,
                 Some(borrow_reserve.liquidity_host_pubkey),
             )],
             Some(&payer.pubkey()),
@@ -677,7 +882,7 @@ impl TestLendingMarket {
             .await
             .unwrap()
             .unwrap();
-        LendingMarket::unpack(&lending_market_account.data[..]).unwrap()
+        LendingMarket::unpack_account(&lending_market_account.data[..]).unwrap()
     }
 
     pub async fn validate_state(&self, banks_client: &mut BanksClient) {
@@ -688,6 +893,16 @@ impl TestLendingMarket {
     }
 }
 
+/// Liquidity fee receiver of a reserve initialized with `TestReserve`
+pub enum TestFeeReceiver {
+    /// A new token account, initialized by the lending program
+    New,
+    /// The lending market authority's associated token account
+    Derived,
+    /// An existing token account
+    Existing(Pubkey),
+}
+
 #[derive(Debug)]
 pub struct TestReserve {
     pub name: String,
@@ -720,6 +935,36 @@ impl TestReserve {
         user_liquidity_pubkey: Pubkey,
         payer: &Keypair,
         user_accounts_owner: &Keypair,
+    ) -> Result<Self, TransactionError> {
+        Self::init_with_fee_receiver(
+            name,
+            banks_client,
+            lending_market,
+            oracle,
+            liquidity_amount,
+            config,
+            liquidity_mint_pubkey,
+            user_liquidity_pubkey,
+            payer,
+            user_accounts_owner,
+            TestFeeReceiver::New,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn init_with_fee_receiver(
+        name: String,
+        banks_client: &mut BanksClient,
+        lending_market: &TestLendingMarket,
+        oracle: &TestOracle,
+        liquidity_amount: u64,
+        config: ReserveConfig,
+        liquidity_mint_pubkey: Pubkey,
+        user_liquidity_pubkey: Pubkey,
+        payer: &Keypair,
+        user_accounts_owner: &Keypair,
+        fee_receiver: TestFeeReceiver,
     ) -> Result<Self, TransactionError> {
         let reserve_keypair = Keypair::new();
         let reserve_pubkey = reserve_keypair.pubkey();
@@ -739,67 +984,109 @@ impl TestReserve {
         let liquidity_mint = Mint::unpack(&liquidity_mint_account.data[..]).unwrap();
 
         let rent = banks_client.get_rent().await.unwrap();
-        let mut transaction = Transaction::new_with_payer(
-            &[
-                approve(
-                    &spl_token::id(),
-                    &user_liquidity_pubkey,
-                    &user_transfer_authority_keypair.pubkey(),
-                    &user_accounts_owner.pubkey(),
-                    &[],
-                    liquidity_amount,
-                )
-                .unwrap(),
-                create_account(
-                    &payer.pubkey(),
-                    &collateral_mint_keypair.pubkey(),
-                    rent.minimum_balance(Mint::LEN),
-                    Mint::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &collateral_supply_keypair.pubkey(),
-                    rent.minimum_balance(Token::LEN),
-                    Token::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &liquidity_supply_keypair.pubkey(),
-                    rent.minimum_balance(Token::LEN),
-                    Token::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &liquidity_fee_receiver_keypair.pubkey(),
-                    rent.minimum_balance(Token::LEN),
-                    Token::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &liquidity_host_keypair.pubkey(),
-                    rent.minimum_balance(Token::LEN),
-                    Token::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &user_collateral_token_keypair.pubkey(),
-                    rent.minimum_balance(Token::LEN),
-                    Token::LEN as u64,
-                    &spl_token::id(),
-                ),
-                create_account(
-                    &payer.pubkey(),
-                    &reserve_pubkey,
-                    rent.minimum_balance(Reserve::LEN),
-                    Reserve::LEN as u64,
-                    &spl_token_lending::id(),
-                ),
-                init_reserve(
+        let mut instructions = vec![
+            approve(
+                &spl_token::id(),
+                &user_liquidity_pubkey,
+                &user_transfer_authority_keypair.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                liquidity_amount,
+            )
+            .unwrap(),
+            create_account(
+                &payer.pubkey(),
+                &collateral_mint_keypair.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            create_account(
+                &payer.pubkey(),
+                &collateral_supply_keypair.pubkey(),
+                rent.minimum_balance(Token::LEN),
+                Token::LEN as u64,
+                &spl_token::id(),
+            ),
+            create_account(
+                &payer.pubkey(),
+                &liquidity_supply_keypair.pubkey(),
+                rent.minimum_balance(Token::LEN),
+                Token::LEN as u64,
+                &spl_token::id(),
+            ),
+            create_account(
+                &payer.pubkey(),
+                &liquidity_host_keypair.pubkey(),
+                rent.minimum_balance(Token::LEN),
+                Token::LEN as u64,
+                &spl_token::id(),
+            ),
+            create_account(
+                &payer.pubkey(),
+                &user_collateral_token_keypair.pubkey(),
+                rent.minimum_balance(Token::LEN),
+                Token::LEN as u64,
+                &spl_token::id(),
+            ),
+            create_account(
+                &payer.pubkey(),
+                &reserve_pubkey,
+                rent.minimum_balance(Reserve::LEN),
+                Reserve::LEN as u64,
+                &spl_token_lending::id(),
+            ),
+        ];
+        let mut signers = vec![
+            payer,
+            user_accounts_owner,
+            &reserve_keypair,
+            &lending_market.owner,
+            &collateral_mint_keypair,
+            &collateral_supply_keypair,
+            &liquidity_supply_keypair,
+            &liquidity_host_keypair,
+            &user_collateral_token_keypair,
+            &user_transfer_authority_keypair,
+        ];
+
+        let init_reserve_with_fee_receiver = |liquidity_fee_receiver_pubkey| {
+            init_reserve(
+                spl_token_lending::id(),
+                liquidity_amount,
+                config,
+                user_liquidity_pubkey,
+                user_collateral_token_keypair.pubkey(),
+                reserve_pubkey,
+                liquidity_mint_pubkey,
+                liquidity_supply_keypair.pubkey(),
+                liquidity_fee_receiver_pubkey,
+                collateral_mint_keypair.pubkey(),
+                collateral_supply_keypair.pubkey(),
+                oracle.product_pubkey,
+                oracle.price_pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+                user_transfer_authority_keypair.pubkey(),
+            )
+        };
+        let liquidity_fee_receiver_pubkey = match fee_receiver {
+            TestFeeReceiver::New => {
+                instructions.extend([
+                    create_account(
+                        &payer.pubkey(),
+                        &liquidity_fee_receiver_keypair.pubkey(),
+                        rent.minimum_balance(Token::LEN),
+                        Token::LEN as u64,
+                        &spl_token::id(),
+                    ),
+                    init_reserve_with_fee_receiver(liquidity_fee_receiver_keypair.pubkey()),
+                ]);
+                signers.push(&liquidity_fee_receiver_keypair);
+                liquidity_fee_receiver_keypair.pubkey()
+            }
+            TestFeeReceiver::Derived => {
+                let instruction = init_reserve_with_derived_fee_receiver(
                     spl_token_lending::id(),
                     liquidity_amount,
                     config,
@@ -808,7 +1095,6 @@ impl TestReserve {
                     reserve_pubkey,
                     liquidity_mint_pubkey,
                     liquidity_supply_keypair.pubkey(),
-                    liquidity_fee_receiver_keypair.pubkey(),
                     collateral_mint_keypair.pubkey(),
                     collateral_supply_keypair.pubkey(),
                     oracle.product_pubkey,
@@ -816,28 +1102,23 @@ impl TestReserve {
                     lending_market.pubkey,
                     lending_market.owner.pubkey(),
                     user_transfer_authority_keypair.pubkey(),
-                ),
-            ],
-            Some(&payer.pubkey()),
-        );
+                    payer.pubkey(),
+                );
+                let liquidity_fee_receiver_pubkey = instruction.accounts[5].pubkey;
+                instructions.push(instruction);
+                liquidity_fee_receiver_pubkey
+            }
+            TestFeeReceiver::Existing(liquidity_fee_receiver_pubkey) => {
+                instructions.push(init_reserve_with_fee_receiver(
+                    liquidity_fee_receiver_pubkey,
+                ));
+                liquidity_fee_receiver_pubkey
+            }
+        };
 
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
         let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
-        transaction.sign(
-            &vec![
-                payer,
-                user_accounts_owner,
-                &reserve_keypair,
-                &lending_market.owner,
-                &collateral_mint_keypair,
-                &collateral_supply_keypair,
-                &liquidity_supply_keypair,
-                &liquidity_fee_receiver_keypair,
-                &liquidity_host_keypair,
-                &user_collateral_token_keypair,
-                &user_transfer_authority_keypair,
-            ],
-            recent_blockhash,
-        );
+        transaction.sign(&signers, recent_blockhash);
 
         banks_client
             .process_transaction(transaction)
@@ -850,7 +1131,7 @@ impl TestReserve {
                 liquidity_mint_pubkey,
                 liquidity_mint_decimals: liquidity_mint.decimals,
                 liquidity_supply_pubkey: liquidity_supply_keypair.pubkey(),
-                liquidity_fee_receiver_pubkey: liquidity_fee_receiver_keypair.pubkey(),
+                liquidity_fee_receiver_pubkey,
                 liquidity_host_pubkey: liquidity_host_keypair.pubkey(),
                 liquidity_oracle_pubkey: oracle.price_pubkey,
                 collateral_mint_pubkey: collateral_mint_keypair.pubkey(),
@@ -868,7 +1149,7 @@ impl TestReserve {
             .await
             .unwrap()
             .unwrap();
-        Reserve::unpack(&reserve_account.data[..]).unwrap()
+        Reserve::unpack_account(&reserve_account.data[..]).unwrap()
     }
 
     pub async fn validate_state(&self, banks_client: &mut BanksClient) {
@@ -962,13 +1243,52 @@ impl TestObligation {
         Ok(obligation)
     }
 
+    pub async fn init_derived(
+        banks_client: &mut BanksClient,
+        lending_market: &TestLendingMarket,
+        user_accounts_owner: &Keypair,
+        payer: &Keypair,
+    ) -> Result<Self, TransactionError> {
+        let obligation = TestObligation {
+            pubkey: get_obligation_address(
+                &spl_token_lending::id(),
+                &lending_market.pubkey,
+                &user_accounts_owner.pubkey(),
+            ),
+            lending_market: lending_market.pubkey,
+            owner: user_accounts_owner.pubkey(),
+            deposits: vec![],
+            borrows: vec![],
+        };
+
+        let mut transaction = Transaction::new_with_payer(
+            &[init_derived_obligation(
+                spl_token_lending::id(),
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+                payer.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+
+        let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+        transaction.sign(&[payer, user_accounts_owner], recent_blockhash);
+
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())?;
+
+        Ok(obligation)
+    }
+
     pub async fn get_state(&self, banks_client: &mut BanksClient) -> Obligation {
         let obligation_account: Account = banks_client
             .get_account(self.pubkey)
             .await
             .unwrap()
             .unwrap();
-        Obligation::unpack(&obligation_account.data[..]).unwrap()
+        Obligation::unpack_account(&obligation_account.data[..]).unwrap()
     }
 
     pub async fn validate_state(&self, banks_client: &mut BanksClient) {
@@ -993,7 +1313,7 @@ impl TestObligationCollateral {
             .await
             .unwrap()
             .unwrap();
-        Obligation::unpack(&obligation_account.data[..]).unwrap()
+        Obligation::unpack_account(&obligation_account.data[..]).unwrap()
     }
 
     pub async fn validate_state(&self, banks_client: &mut BanksClient) {
@@ -1021,7 +1341,7 @@ impl TestObligationLiquidity {
             .await
             .unwrap()
             .unwrap();
-        Obligation::unpack(&obligation_account.data[..]).unwrap()
+        Obligation::unpack_account(&obligation_account.data[..]).unwrap()
     }
 
     pub async fn validate_state(&self, banks_client: &mut BanksClient) {
@@ -1063,7 +1383,55 @@ pub fn add_usdc_mint(test: &mut ProgramTest) -> TestMint {
     }
 }
 
+/// Adds a Token-2022 mint charging `transfer_fee_basis_points` on every
+/// transfer, without a maximum fee
+pub fn add_transfer_fee_mint(test: &mut ProgramTest, transfer_fee_basis_points: u16) -> TestMint {
+    let authority = Keypair::new();
+    let pubkey = Pubkey::new_unique();
+    let decimals = 6;
+
+    let mint_len = ExtensionType::try_calculate_account_len::<Token2022Mint>(&[
+        ExtensionType::TransferFeeConfig,
+    ])
+    .unwrap();
+    let mut data = vec![0; mint_len];
+    let mut state =
+        StateWithExtensionsMut::<Token2022Mint>::unpack_uninitialized(&mut data).unwrap();
+    let transfer_fee = TransferFee {
+        epoch: 0.into(),
+        maximum_fee: u64::MAX.into(),
+        transfer_fee_basis_points: transfer_fee_basis_points.into(),
+    };
+    let transfer_fee_config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+    transfer_fee_config.older_transfer_fee = transfer_fee;
+    transfer_fee_config.newer_transfer_fee = transfer_fee;
+    state.base = Token2022Mint {
+        is_initialized: true,
+        mint_authority: COption::Some(authority.pubkey()),
+        decimals,
+        ..Token2022Mint::default()
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    test.add_account(
+        pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token_2022::id(),
+            ..Account::default()
+        },
+    );
+    TestMint {
+        pubkey,
+        authority,
+        decimals,
+    }
+}
+
 pub struct TestOracle {
+    pub oracle_kind: OracleKind,
     pub product_pubkey: Pubkey,
     pub price_pubkey: Pubkey,
     pub price: Decimal,
@@ -1079,6 +1447,17 @@ pub fn add_sol_oracle(test: &mut ProgramTest) -> TestOracle {
     )
 }
 
+pub fn add_sol_oracle_with_confidence(test: &mut ProgramTest, confidence: Decimal) -> TestOracle {
+    add_oracle_with_confidence(
+        test,
+        Pubkey::from_str(SOL_PYTH_PRODUCT).unwrap(),
+        Pubkey::from_str(SOL_PYTH_PRICE).unwrap(),
+        // Set SOL price to $20
+        Decimal::from(20u64),
+        confidence,
+    )
+}
+
 pub fn add_usdc_oracle(test: &mut ProgramTest) -> TestOracle {
     add_oracle(
         test,
@@ -1095,6 +1474,16 @@ pub fn add_oracle(
     product_pubkey: Pubkey,
     price_pubkey: Pubkey,
     price: Decimal,
+) -> TestOracle {
+    add_oracle_with_confidence(test, product_pubkey, price_pubkey, price, Decimal::zero())
+}
+
+pub fn add_oracle_with_confidence(
+    test: &mut ProgramTest,
+    product_pubkey: Pubkey,
+    price_pubkey: Pubkey,
+    price: Decimal,
+    confidence: Decimal,
 ) -> TestOracle {
     let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json").unwrap();
 
@@ -1126,6 +1515,11 @@ pub fn add_oracle(
         .unwrap()
         .try_into()
         .unwrap();
+    pyth_price.agg.conf = confidence
+        .try_mul(decimals)
+        .unwrap()
+        .try_round_u64()
+        .unwrap();
 
     test.add_account(
         price_pubkey,
@@ -1139,12 +1533,70 @@ pub fn add_oracle(
     );
 
     TestOracle {
+        oracle_kind: OracleKind::Pyth,
         product_pubkey,
         price_pubkey,
         price,
     }
 }
 
+pub fn add_sol_switchboard_oracle(test: &mut ProgramTest) -> TestOracle {
+    add_switchboard_oracle(
+        test,
+        Pubkey::new_unique(),
+        // Set SOL price to $20
+        Decimal::from(20u64),
+        0,
+    )
+}
+
+pub fn add_switchboard_oracle(
+    test: &mut ProgramTest,
+    aggregator_pubkey: Pubkey,
+    price: Decimal,
+    round_open_slot: u64,
+) -> TestOracle {
+    const RESULT_SCALE: u32 = 9;
+
+    // Switchboard aggregator account with a single confirmed round
+    let mut aggregator_data = vec![0; size_of::<switchboard::Aggregator>()];
+    let aggregator =
+        switchboard::load_mut::<switchboard::Aggregator>(aggregator_data.as_mut_slice()).unwrap();
+
+    aggregator.discriminator = switchboard::AGGREGATOR_DISCRIMINATOR;
+    aggregator.latest_confirmed_round.num_success = 1;
+    aggregator.latest_confirmed_round.is_closed = 1;
+    aggregator.latest_confirmed_round.round_open_slot = round_open_slot;
+    aggregator.latest_confirmed_round.result = switchboard::SwitchboardDecimal {
+        mantissa: price
+            .try_round_u64()
+            .unwrap()
+            .checked_mul(10u64.pow(RESULT_SCALE))
+            .unwrap()
+            .into(),
+        scale: RESULT_SCALE,
+    };
+
+    test.add_account(
+        aggregator_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data: aggregator_data,
+            owner: switchboard::program::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    // Switchboard reserves pass the aggregator as both oracle accounts
+    TestOracle {
+        oracle_kind: OracleKind::Switchboard,
+        product_pubkey: aggregator_pubkey,
+        price_pubkey: aggregator_pubkey,
+        price,
+    }
+}
+
 pub async fn create_and_mint_to_token_account(
     banks_client: &mut BanksClient,
     mint_pubkey: Pubkey,
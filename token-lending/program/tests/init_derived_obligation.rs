@@ -0,0 +1,399 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{program_pack::Pack, pubkey::Pubkey, system_program},
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral, init_derived_obligation,
+            refresh_obligation, refresh_reserve, repay_obligation_liquidity,
+            withdraw_obligation_collateral,
+        },
+        processor::process_instruction,
+        state::{get_obligation_address, Obligation, INITIAL_COLLATERAL_RATIO},
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const SOL_RESERVE_LIQUIDITY_LAMPORTS: u64 = 2 * 100 * LAMPORTS_TO_SOL;
+// 100 SOL * 50% LTV -> 50 SOL * 20 USDC -> 1000 USDC max, less the borrow fee
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 800 * FRACTIONAL_TO_USDC;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 4 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+async fn process(
+    banks_client: &mut BanksClient,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    user_accounts_owner: &Keypair,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[payer, user_accounts_owner], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn lending_error(error: LendingError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+    let obligation = TestObligation::init_derived(
+        &mut banks_client,
+        &lending_market,
+        &user_accounts_owner,
+        &payer,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        obligation.pubkey,
+        get_obligation_address(
+            &spl_token_lending::id(),
+            &lending_market.pubkey,
+            &user_accounts_owner.pubkey()
+        )
+    );
+    obligation.validate_state(&mut banks_client).await;
+
+    let obligation_account = banks_client
+        .get_account(obligation.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let rent = banks_client.get_rent().await.unwrap();
+    assert_eq!(obligation_account.owner, spl_token_lending::id());
+    assert_eq!(obligation_account.data.len(), Obligation::LEN);
+    assert_eq!(
+        obligation_account.lamports,
+        rent.minimum_balance(Obligation::LEN)
+    );
+}
+
+#[tokio::test]
+async fn test_prefunded_address() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // lamports sent to the obligation address don't keep it from being
+    // initialized
+    let obligation_pubkey = get_obligation_address(
+        &spl_token_lending::id(),
+        &lending_market.pubkey,
+        &user_accounts_owner.pubkey(),
+    );
+    test.add_account(
+        obligation_pubkey,
+        Account::new(1_000_000, 0, &system_program::id()),
+    );
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+    let obligation = TestObligation::init_derived(
+        &mut banks_client,
+        &lending_market,
+        &user_accounts_owner,
+        &payer,
+    )
+    .await
+    .unwrap();
+
+    obligation.validate_state(&mut banks_client).await;
+}
+
+#[tokio::test]
+async fn test_already_initialized() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+    TestObligation::init_derived(
+        &mut banks_client,
+        &lending_market,
+        &user_accounts_owner,
+        &payer,
+    )
+    .await
+    .unwrap();
+
+    // a new blockhash keeps the second init from being deduplicated
+    let mut transaction = Transaction::new_with_payer(
+        &[init_derived_obligation(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        lending_error(LendingError::AlreadyInitialized)
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_obligation_address() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    let mut instruction = init_derived_obligation(
+        spl_token_lending::id(),
+        lending_market.pubkey,
+        user_accounts_owner.pubkey(),
+        payer.pubkey(),
+    );
+    instruction.accounts[0].pubkey = Pubkey::new_unique();
+    assert_eq!(
+        process(
+            &mut banks_client,
+            &[instruction],
+            &payer,
+            &user_accounts_owner
+        )
+        .await
+        .unwrap_err(),
+        lending_error(LendingError::InvalidAccountInput)
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_borrow_repay_withdraw() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            // covers the borrow fee when repaying
+            user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+    let obligation = TestObligation::init_derived(
+        &mut banks_client,
+        &lending_market,
+        &user_accounts_owner,
+        &payer,
+    )
+    .await
+    .unwrap();
+
+    let initial_user_collateral_balance =
+        get_token_balance(&mut banks_client, sol_test_reserve.user_collateral_pubkey).await;
+    let initial_user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+
+    process(
+        &mut banks_client,
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_test_reserve.pubkey,
+                obligation.pubkey,
+                lending_market.pubkey,
+                obligation.owner,
+                user_accounts_owner.pubkey(),
+                vec![],
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                obligation.pubkey,
+                lending_market.pubkey,
+                obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        &payer,
+        &user_accounts_owner,
+    )
+    .await
+    .unwrap();
+
+    let obligation_state = obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation_state.deposits.len(), 1);
+    assert_eq!(
+        obligation_state.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+    assert_eq!(obligation_state.borrows.len(), 1);
+    assert_eq!(
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await,
+        initial_user_liquidity_balance + USDC_BORROW_AMOUNT_FRACTIONAL
+    );
+
+    process(
+        &mut banks_client,
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.pubkey,
+                obligation.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            withdraw_obligation_collateral(
+                spl_token_lending::id(),
+                u64::MAX,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.pubkey,
+                obligation.pubkey,
+                lending_market.pubkey,
+                obligation.owner,
+            ),
+        ],
+        &payer,
+        &user_accounts_owner,
+    )
+    .await
+    .unwrap();
+
+    let obligation_state = obligation.get_state(&mut banks_client).await;
+    assert!(obligation_state.deposits.is_empty());
+    assert!(obligation_state.borrows.is_empty());
+    assert_eq!(
+        get_token_balance(&mut banks_client, sol_test_reserve.user_collateral_pubkey).await,
+        initial_user_collateral_balance
+    );
+}
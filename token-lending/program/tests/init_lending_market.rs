@@ -34,6 +34,25 @@ async fn test_success() {
     test_lending_market.validate_state(&mut banks_client).await;
 }
 
+#[tokio::test]
+async fn test_success_with_mint_quote_currency() {
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    // markets may still be quoted in a token mint rather than a symbol
+    let quote_currency = spl_token::native_mint::id().to_bytes();
+    let test_lending_market =
+        TestLendingMarket::init_with_quote_currency(&mut banks_client, &payer, quote_currency)
+            .await;
+
+    test_lending_market.validate_state(&mut banks_client).await;
+}
+
 #[tokio::test]
 async fn test_already_initialized() {
     let mut test = ProgramTest::new(
@@ -5,18 +5,31 @@ mod helpers;
 
 use {
     helpers::*,
+    solana_program::{program_pack::Pack, pubkey::Pubkey},
     solana_program_test::*,
     solana_sdk::{
+        account::Account,
         instruction::InstructionError,
         signature::{Keypair, Signer},
         transaction::{Transaction, TransactionError},
     },
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::state::{Account as Token, Mint},
     spl_token_lending::{
         error::LendingError,
-        instruction::init_reserve,
+        instruction::{
+            borrow_obligation_liquidity, deposit_reserve_liquidity, init_reserve,
+            redeem_reserve_collateral, refresh_obligation, refresh_reserve,
+        },
+        math::Decimal,
         processor::process_instruction,
-        state::{ReserveFees, INITIAL_COLLATERAL_RATIO},
+        state::{
+            FeeCalculation, OracleKind, Reserve, ReserveFees, INITIAL_COLLATERAL_RATIO,
+            MIN_INITIAL_COLLATERAL_AMOUNT,
+        },
+        switchboard,
     },
+    std::mem::size_of,
 };
 
 #[tokio::test]
@@ -36,7 +49,7 @@ async fn test_success() {
 
     let (mut banks_client, payer, _recent_blockhash) = test.start().await;
 
-    const RESERVE_AMOUNT: u64 = 42;
+    const RESERVE_AMOUNT: u64 = 1_000;
 
     let sol_user_liquidity_account = create_and_mint_to_token_account(
         &mut banks_client,
@@ -79,6 +92,163 @@ async fn test_success() {
     );
 }
 
+// Initializes a reserve of the given liquidity mint, then deposits liquidity
+// and redeems all of the minted collateral back into liquidity
+async fn init_and_redeem_reserve(
+    test: ProgramTest,
+    lending_market: &TestLendingMarket,
+    oracle: &TestOracle,
+    liquidity_mint_pubkey: Pubkey,
+    liquidity_mint_authority: Option<&Keypair>,
+    liquidity_mint_decimals: u8,
+) {
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000_000;
+    const DEPOSIT_AMOUNT: u64 = 1_000_000;
+
+    let user_accounts_owner = Keypair::new();
+    let user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        liquidity_mint_pubkey,
+        liquidity_mint_authority,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT + DEPOSIT_AMOUNT,
+    )
+    .await;
+
+    let test_reserve = TestReserve::init(
+        "reserve".to_owned(),
+        &mut banks_client,
+        lending_market,
+        oracle,
+        RESERVE_AMOUNT,
+        TEST_RESERVE_CONFIG,
+        liquidity_mint_pubkey,
+        user_liquidity_account,
+        &payer,
+        &user_accounts_owner,
+    )
+    .await
+    .unwrap();
+
+    let collateral_mint_account = banks_client
+        .get_account(test_reserve.collateral_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let collateral_mint = Mint::unpack(&collateral_mint_account.data[..]).unwrap();
+    assert_eq!(collateral_mint.decimals, liquidity_mint_decimals);
+
+    let user_collateral_account = create_token_account(
+        &mut banks_client,
+        test_reserve.collateral_mint_pubkey,
+        &payer,
+        Some(user_accounts_owner.pubkey()),
+        None,
+    )
+    .await;
+
+    let collateral_amount = DEPOSIT_AMOUNT * INITIAL_COLLATERAL_RATIO;
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test_reserve.pubkey,
+                test_reserve.liquidity_oracle_pubkey,
+            ),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                DEPOSIT_AMOUNT,
+                None,
+                user_liquidity_account,
+                user_collateral_account,
+                test_reserve.pubkey,
+                test_reserve.liquidity_supply_pubkey,
+                test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test_reserve.pubkey,
+                test_reserve.liquidity_oracle_pubkey,
+            ),
+            redeem_reserve_collateral(
+                spl_token_lending::id(),
+                collateral_amount,
+                None,
+                user_collateral_account,
+                user_liquidity_account,
+                test_reserve.pubkey,
+                test_reserve.collateral_mint_pubkey,
+                test_reserve.liquidity_supply_pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, user_liquidity_account).await,
+        DEPOSIT_AMOUNT
+    );
+    assert_eq!(
+        get_token_balance(&mut banks_client, user_collateral_account).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_collateral_mint_decimals_with_6_decimals() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+
+    init_and_redeem_reserve(
+        test,
+        &lending_market,
+        &usdc_oracle,
+        usdc_mint.pubkey,
+        Some(&usdc_mint.authority),
+        usdc_mint.decimals,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_collateral_mint_decimals_with_9_decimals() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    init_and_redeem_reserve(
+        test,
+        &lending_market,
+        &sol_oracle,
+        spl_token::native_mint::id(),
+        None,
+        spl_token::native_mint::DECIMALS,
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_already_initialized() {
     let mut test = ProgramTest::new(
@@ -161,7 +331,7 @@ async fn test_invalid_fees() {
 
     let (mut banks_client, payer, _recent_blockhash) = test.start().await;
 
-    const RESERVE_AMOUNT: u64 = 42;
+    const RESERVE_AMOUNT: u64 = 1_000;
 
     let sol_user_liquidity_account = create_and_mint_to_token_account(
         &mut banks_client,
@@ -199,7 +369,7 @@ async fn test_invalid_fees() {
             .unwrap_err(),
             TransactionError::InstructionError(
                 8,
-                InstructionError::Custom(LendingError::InvalidConfig as u32)
+                InstructionError::Custom(LendingError::InvalidFeeConfig as u32)
             )
         );
     }
@@ -230,8 +400,536 @@ async fn test_invalid_fees() {
             .unwrap_err(),
             TransactionError::InstructionError(
                 8,
-                InstructionError::Custom(LendingError::InvalidConfig as u32)
+                InstructionError::Custom(LendingError::InvalidFeeConfig as u32)
             )
         );
     }
 }
+
+#[tokio::test]
+async fn test_initial_collateral_too_small() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = MIN_INITIAL_COLLATERAL_AMOUNT / INITIAL_COLLATERAL_RATIO - 1;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    assert_eq!(
+        TestReserve::init(
+            "sol".to_owned(),
+            &mut banks_client,
+            &lending_market,
+            &sol_oracle,
+            RESERVE_AMOUNT,
+            TEST_RESERVE_CONFIG,
+            spl_token::native_mint::id(),
+            sol_user_liquidity_account,
+            &payer,
+            &user_accounts_owner,
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            8,
+            InstructionError::Custom(LendingError::DepositTooSmall as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_quote_currency_mismatch() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // the SOL Pyth product is quoted in USD rather than the market's mint
+    let user_accounts_owner = Keypair::new();
+    let lending_market =
+        add_lending_market_with_quote_currency(&mut test, Pubkey::new_unique().to_bytes());
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    assert_eq!(
+        TestReserve::init(
+            "sol".to_owned(),
+            &mut banks_client,
+            &lending_market,
+            &sol_oracle,
+            RESERVE_AMOUNT,
+            TEST_RESERVE_CONFIG,
+            spl_token::native_mint::id(),
+            sol_user_liquidity_account,
+            &payer,
+            &user_accounts_owner,
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            8,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_success_with_switchboard_oracle() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_switchboard_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    let sol_reserve = TestReserve::init(
+        "sol".to_owned(),
+        &mut banks_client,
+        &lending_market,
+        &sol_oracle,
+        RESERVE_AMOUNT,
+        TEST_RESERVE_CONFIG,
+        spl_token::native_mint::id(),
+        sol_user_liquidity_account,
+        &payer,
+        &user_accounts_owner,
+    )
+    .await
+    .unwrap();
+
+    sol_reserve.validate_state(&mut banks_client).await;
+
+    let reserve = sol_reserve.get_state(&mut banks_client).await;
+    assert_eq!(reserve.liquidity.oracle_kind, OracleKind::Switchboard);
+    assert_eq!(reserve.liquidity.market_price, Decimal::from(20u64));
+}
+
+#[tokio::test]
+async fn test_invalid_switchboard_aggregator() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // Owned by the Switchboard program but missing the aggregator discriminator
+    let aggregator_pubkey = Pubkey::new_unique();
+    test.add_account(
+        aggregator_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data: vec![0; size_of::<switchboard::Aggregator>()],
+            owner: switchboard::program::id(),
+            ..Account::default()
+        },
+    );
+    let sol_oracle = TestOracle {
+        oracle_kind: OracleKind::Switchboard,
+        product_pubkey: aggregator_pubkey,
+        price_pubkey: aggregator_pubkey,
+        price: Decimal::from(20u64),
+    };
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    assert_eq!(
+        TestReserve::init(
+            "sol".to_owned(),
+            &mut banks_client,
+            &lending_market,
+            &sol_oracle,
+            RESERVE_AMOUNT,
+            TEST_RESERVE_CONFIG,
+            spl_token::native_mint::id(),
+            sol_user_liquidity_account,
+            &payer,
+            &user_accounts_owner,
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            8,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_success_with_derived_fee_receiver() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    let sol_reserve = TestReserve::init_with_fee_receiver(
+        "sol".to_owned(),
+        &mut banks_client,
+        &lending_market,
+        &sol_oracle,
+        RESERVE_AMOUNT,
+        TEST_RESERVE_CONFIG,
+        spl_token::native_mint::id(),
+        sol_user_liquidity_account,
+        &payer,
+        &user_accounts_owner,
+        TestFeeReceiver::Derived,
+    )
+    .await
+    .unwrap();
+
+    sol_reserve.validate_state(&mut banks_client).await;
+
+    let fee_receiver_pubkey =
+        get_associated_token_address(&lending_market.authority, &spl_token::native_mint::id());
+    assert_eq!(
+        sol_reserve.liquidity_fee_receiver_pubkey,
+        fee_receiver_pubkey
+    );
+    let reserve = sol_reserve.get_state(&mut banks_client).await;
+    assert_eq!(reserve.liquidity.fee_receiver, fee_receiver_pubkey);
+
+    let fee_receiver_account = banks_client
+        .get_account(fee_receiver_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fee_receiver_account.owner, spl_token::id());
+    let fee_receiver = Token::unpack(&fee_receiver_account.data).unwrap();
+    assert_eq!(fee_receiver.owner, lending_market.authority);
+    assert_eq!(fee_receiver.mint, spl_token::native_mint::id());
+    assert_eq!(fee_receiver.amount, 0);
+}
+
+#[tokio::test]
+async fn test_derived_fee_receiver_receives_borrow_fees() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 500 * FRACTIONAL_TO_USDC;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 4 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    let usdc_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        usdc_mint.pubkey,
+        Some(&usdc_mint.authority),
+        &payer,
+        user_accounts_owner.pubkey(),
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+    )
+    .await;
+    let usdc_test_reserve = TestReserve::init_with_fee_receiver(
+        "usdc".to_owned(),
+        &mut banks_client,
+        &lending_market,
+        &usdc_oracle,
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+        TEST_RESERVE_CONFIG,
+        usdc_mint.pubkey,
+        usdc_user_liquidity_account,
+        &payer,
+        &user_accounts_owner,
+        TestFeeReceiver::Derived,
+    )
+    .await
+    .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // without a host fee receiver the whole borrow fee goes to the reserve
+    let (borrow_fee, _host_fee) = TEST_RESERVE_CONFIG
+        .fees
+        .calculate_borrow_fees(
+            USDC_BORROW_AMOUNT_FRACTIONAL.into(),
+            FeeCalculation::Exclusive,
+        )
+        .unwrap();
+    assert!(borrow_fee > 0);
+    assert_eq!(
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        get_associated_token_address(&lending_market.authority, &usdc_mint.pubkey)
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut banks_client,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey
+        )
+        .await,
+        borrow_fee
+    );
+}
+
+#[tokio::test]
+async fn test_initialized_fee_receiver() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    const RESERVE_AMOUNT: u64 = 1_000;
+
+    let sol_user_liquidity_account = create_and_mint_to_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        None,
+        &payer,
+        user_accounts_owner.pubkey(),
+        RESERVE_AMOUNT,
+    )
+    .await;
+
+    // an existing token account is rejected, even when it already belongs to
+    // the lending market authority
+    let fee_receiver_pubkey = create_token_account(
+        &mut banks_client,
+        spl_token::native_mint::id(),
+        &payer,
+        Some(lending_market.authority),
+        None,
+    )
+    .await;
+
+    assert_eq!(
+        TestReserve::init_with_fee_receiver(
+            "sol".to_owned(),
+            &mut banks_client,
+            &lending_market,
+            &sol_oracle,
+            RESERVE_AMOUNT,
+            TEST_RESERVE_CONFIG,
+            spl_token::native_mint::id(),
+            sol_user_liquidity_account,
+            &payer,
+            &user_accounts_owner,
+            TestFeeReceiver::Existing(fee_receiver_pubkey),
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            7,
+            InstructionError::Custom(LendingError::TokenInitializeAccountFailed as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_token_2022_liquidity_mint() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_transfer_authority = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let transfer_fee_mint = add_transfer_fee_mint(&mut test, 100);
+
+    let reserve_pubkey = Pubkey::new_unique();
+    test.add_account(
+        reserve_pubkey,
+        Account::new(u32::MAX as u64, Reserve::LEN, &spl_token_lending::id()),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // the token accounts are never read, the liquidity mint is rejected first
+    let mut transaction = Transaction::new_with_payer(
+        &[init_reserve(
+            spl_token_lending::id(),
+            1_000,
+            TEST_RESERVE_CONFIG,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            reserve_pubkey,
+            transfer_fee_mint.pubkey,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            usdc_oracle.product_pubkey,
+            usdc_oracle.price_pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            user_transfer_authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &lending_market.owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidTokenProgram as u32)
+        )
+    );
+}
@@ -0,0 +1,405 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral, modify_reserve_config,
+            refresh_obligation, refresh_reserve,
+        },
+        math::TryMul,
+        processor::process_instruction,
+        state::{ReserveConfig, INITIAL_COLLATERAL_RATIO},
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+struct SetupArgs {
+    sol_isolated: bool,
+    usdc_borrowable_in_isolation: bool,
+    sol_deposit: bool,
+    usdc_deposit: bool,
+}
+
+// Adds a SOL reserve and a USDC reserve, with an obligation holding the
+// requested collateral
+async fn setup(args: SetupArgs) -> SolUsdcObligationTest {
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    start_sol_usdc_obligation(
+        test,
+        AddSolUsdcObligationArgs {
+            sol_reserve: AddReserveArgs {
+                liquidity_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: ReserveConfig {
+                    isolated: args.sol_isolated,
+                    ..reserve_config
+                },
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                liquidity_amount: 2 * USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                collateral_amount: 2 * USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                config: ReserveConfig {
+                    borrowable_in_isolation: args.usdc_borrowable_in_isolation,
+                    ..reserve_config
+                },
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: if args.sol_deposit {
+                SOL_DEPOSIT_AMOUNT_LAMPORTS
+            } else {
+                0
+            },
+            usdc_deposit_amount: if args.usdc_deposit {
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL
+            } else {
+                0
+            },
+            ..AddSolUsdcObligationArgs::default()
+        },
+    )
+    .await
+}
+
+async fn deposit(
+    test: &mut SolUsdcObligationTest,
+    deposit_sol: bool,
+    collateral_amount: u64,
+) -> Result<(), TransactionError> {
+    let (test_reserve, test_oracle) = if deposit_sol {
+        (&test.sol_test_reserve, &test.sol_oracle)
+    } else {
+        (&test.usdc_test_reserve, &test.usdc_oracle)
+    };
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &test_reserve.user_collateral_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                collateral_amount,
+            )
+            .unwrap(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test_reserve.pubkey,
+                test_oracle.price_pubkey,
+            ),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                collateral_amount,
+                test_reserve.user_collateral_pubkey,
+                test_reserve.collateral_supply_pubkey,
+                test_reserve.pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                test.test_obligation.owner,
+                user_transfer_authority.pubkey(),
+                obligation
+                    .deposits
+                    .iter()
+                    .map(|collateral| collateral.deposit_reserve)
+                    .collect(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn refresh(test: &mut SolUsdcObligationTest) -> Result<(), TransactionError> {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.sol_test_reserve.pubkey,
+                test.sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                obligation
+                    .deposits
+                    .iter()
+                    .map(|collateral| collateral.deposit_reserve)
+                    .collect(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(&[&test.payer], test.recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn borrow(test: &mut SolUsdcObligationTest) -> Result<(), TransactionError> {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let deposit_reserves: Vec<_> = obligation
+        .deposits
+        .iter()
+        .map(|collateral| collateral.deposit_reserve)
+        .collect();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.sol_test_reserve.pubkey,
+                test.sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                deposit_reserves.clone(),
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                test.usdc_test_reserve.liquidity_supply_pubkey,
+                test.usdc_test_reserve.user_liquidity_pubkey,
+                test.usdc_test_reserve.pubkey,
+                test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                test.test_obligation.owner,
+                deposit_reserves,
+                None,
+                Some(test.usdc_test_reserve.liquidity_host_pubkey),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.user_accounts_owner],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn isolated_collateral(instruction_index: u8) -> Result<(), TransactionError> {
+    Err(TransactionError::InstructionError(
+        instruction_index,
+        InstructionError::Custom(LendingError::IsolatedCollateral as u32),
+    ))
+}
+
+#[tokio::test]
+async fn test_deposit_into_isolated_obligation() {
+    let mut test = setup(SetupArgs {
+        sol_isolated: true,
+        usdc_borrowable_in_isolation: false,
+        sol_deposit: true,
+        usdc_deposit: false,
+    })
+    .await;
+
+    // more of the same isolated collateral can be deposited
+    assert_eq!(
+        deposit(&mut test, true, SOL_DEPOSIT_AMOUNT_LAMPORTS).await,
+        Ok(())
+    );
+
+    // collateral from another reserve can't be combined with it
+    assert_eq!(
+        deposit(&mut test, false, USDC_DEPOSIT_AMOUNT_FRACTIONAL).await,
+        isolated_collateral(2)
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 1);
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        2 * SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_isolated_into_obligation() {
+    let mut test = setup(SetupArgs {
+        sol_isolated: true,
+        usdc_borrowable_in_isolation: false,
+        sol_deposit: false,
+        usdc_deposit: true,
+    })
+    .await;
+
+    assert_eq!(
+        deposit(&mut test, true, SOL_DEPOSIT_AMOUNT_LAMPORTS).await,
+        isolated_collateral(2)
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 1);
+    assert_eq!(
+        obligation.deposits[0].deposit_reserve,
+        test.usdc_test_reserve.pubkey
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_against_isolated_collateral() {
+    let mut test = setup(SetupArgs {
+        sol_isolated: true,
+        usdc_borrowable_in_isolation: false,
+        sol_deposit: true,
+        usdc_deposit: false,
+    })
+    .await;
+
+    assert_eq!(borrow(&mut test).await, isolated_collateral(3));
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert!(obligation.borrows.is_empty());
+}
+
+#[tokio::test]
+async fn test_borrow_borrowable_in_isolation() {
+    let mut test = setup(SetupArgs {
+        sol_isolated: true,
+        usdc_borrowable_in_isolation: true,
+        sol_deposit: true,
+        usdc_deposit: false,
+    })
+    .await;
+
+    assert_eq!(borrow(&mut test).await, Ok(()));
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(
+        obligation.borrows[0].borrow_reserve,
+        test.usdc_test_reserve.pubkey
+    );
+}
+
+#[tokio::test]
+async fn test_combined_collateral_grandfathered() {
+    let mut test = setup(SetupArgs {
+        sol_isolated: false,
+        usdc_borrowable_in_isolation: false,
+        sol_deposit: true,
+        usdc_deposit: true,
+    })
+    .await;
+
+    let mut sol_config = test.sol_test_reserve.config;
+    sol_config.isolated = true;
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            sol_config,
+            test.sol_test_reserve.pubkey,
+            test.lending_market.pubkey,
+            test.lending_market.owner.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.lending_market.owner],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the existing positions are kept and can still be refreshed
+    assert_eq!(refresh(&mut test).await, Ok(()));
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 2);
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        obligation.deposits[1].deposited_amount,
+        USDC_DEPOSIT_AMOUNT_FRACTIONAL
+    );
+    // the isolated collateral no longer counts towards borrow power
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        obligation.deposits[1]
+            .market_value
+            .try_mul(test.usdc_test_reserve.config.borrow_power_rate().unwrap())
+            .unwrap()
+    );
+
+    // new deposits into either reserve are blocked
+    assert_eq!(
+        deposit(&mut test, true, SOL_DEPOSIT_AMOUNT_LAMPORTS).await,
+        isolated_collateral(2)
+    );
+    assert_eq!(
+        deposit(&mut test, false, USDC_DEPOSIT_AMOUNT_FRACTIONAL).await,
+        isolated_collateral(2)
+    );
+
+    // new borrows against the isolated collateral are blocked
+    assert_eq!(borrow(&mut test).await, isolated_collateral(3));
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        obligation.deposits[1].deposited_amount,
+        USDC_DEPOSIT_AMOUNT_FRACTIONAL
+    );
+    assert!(obligation.borrows.is_empty());
+}
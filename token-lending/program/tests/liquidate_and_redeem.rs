@@ -0,0 +1,284 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+    spl_token_lending::{
+        liquidation::{
+            liquidate_obligation_instructions, LiquidatorAccounts, RedeemSeizedCollateral,
+        },
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+// 100 SOL collateral
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 100 SOL * 80% LTV -> 80 SOL * 20 USDC -> 1600 USDC borrow
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_600 * FRACTIONAL_TO_USDC;
+// 1600 USDC * 50% -> 800 USDC liquidation
+const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 2;
+// 800 USDC / 20 USDC per SOL -> 40 SOL + 10% bonus -> 44 SOL
+const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 44 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const SOL_REDEEM_AMOUNT_LAMPORTS: u64 = 44 * LAMPORTS_TO_SOL;
+
+const SOL_RESERVE_LIQUIDITY_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+struct LiquidateAndRedeemTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    user_accounts_owner: Keypair,
+    sol_test_reserve: TestReserve,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+}
+
+// Adds an unhealthy obligation borrowing USDC against SOL, with SOL liquidity
+// in the SOL reserve to redeem the seized collateral
+async fn setup() -> LiquidateAndRedeemTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.liquidation_threshold = 80;
+    reserve_config.liquidation_bonus = 10;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS * INITIAL_COLLATERAL_RATIO,
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    LiquidateAndRedeemTest {
+        banks_client,
+        payer,
+        user_accounts_owner,
+        sol_test_reserve,
+        usdc_test_reserve,
+        test_obligation,
+    }
+}
+
+// Composes the liquidation of the USDC borrow, with the liquidator's accounts
+// owned by the user accounts owner
+async fn liquidation_instructions(
+    test: &mut LiquidateAndRedeemTest,
+    redeem: Option<RedeemSeizedCollateral>,
+) -> Vec<Instruction> {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let reserves = vec![
+        (
+            test.sol_test_reserve.pubkey,
+            test.sol_test_reserve
+                .get_state(&mut test.banks_client)
+                .await,
+        ),
+        (
+            test.usdc_test_reserve.pubkey,
+            test.usdc_test_reserve
+                .get_state(&mut test.banks_client)
+                .await,
+        ),
+    ];
+
+    liquidate_obligation_instructions(
+        spl_token_lending::id(),
+        USDC_LIQUIDATION_AMOUNT_FRACTIONAL,
+        test.test_obligation.pubkey,
+        &obligation,
+        &reserves,
+        test.usdc_test_reserve.pubkey,
+        test.sol_test_reserve.pubkey,
+        LiquidatorAccounts {
+            source_liquidity_pubkey: test.usdc_test_reserve.user_liquidity_pubkey,
+            destination_collateral_pubkey: test.sol_test_reserve.user_collateral_pubkey,
+            user_transfer_authority_pubkey: test.user_accounts_owner.pubkey(),
+        },
+        redeem,
+    )
+    .unwrap()
+}
+
+// Simulates the composed transaction, then processes it
+async fn simulate_and_process(test: &mut LiquidateAndRedeemTest, instructions: &[Instruction]) {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&test.payer.pubkey()));
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, &test.user_accounts_owner], recent_blockhash);
+
+    let simulation = test
+        .banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    assert_eq!(simulation.result, Some(Ok(())));
+
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_liquidate() {
+    let mut test = setup().await;
+
+    let initial_user_liquidity_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+    )
+    .await;
+    let initial_user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+
+    let instructions = liquidation_instructions(&mut test, None).await;
+    simulate_and_process(&mut test, &instructions).await;
+
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.user_liquidity_pubkey
+        )
+        .await,
+        initial_user_liquidity_balance - USDC_LIQUIDATION_AMOUNT_FRACTIONAL
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.sol_test_reserve.user_collateral_pubkey
+        )
+        .await,
+        initial_user_collateral_balance + SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn test_liquidate_and_redeem() {
+    let mut test = setup().await;
+
+    let initial_user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+    let initial_user_sol_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.user_liquidity_pubkey,
+    )
+    .await;
+    let initial_sol_supply_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+
+    let redeem = RedeemSeizedCollateral {
+        collateral_amount: SOL_LIQUIDATION_AMOUNT_LAMPORTS,
+        min_liquidity_amount: Some(SOL_REDEEM_AMOUNT_LAMPORTS),
+        destination_liquidity_pubkey: test.sol_test_reserve.user_liquidity_pubkey,
+    };
+    let instructions = liquidation_instructions(&mut test, Some(redeem)).await;
+    simulate_and_process(&mut test, &instructions).await;
+
+    // the seized collateral is redeemed in the same transaction
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.sol_test_reserve.user_collateral_pubkey
+        )
+        .await,
+        initial_user_collateral_balance
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.sol_test_reserve.user_liquidity_pubkey
+        )
+        .await,
+        initial_user_sol_balance + SOL_REDEEM_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.sol_test_reserve.liquidity_supply_pubkey
+        )
+        .await,
+        initial_sol_supply_balance - SOL_REDEEM_AMOUNT_LAMPORTS
+    );
+
+    let sol_reserve = test
+        .sol_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(
+        sol_reserve.liquidity.available_amount,
+        SOL_RESERVE_LIQUIDITY_LAMPORTS - SOL_REDEEM_AMOUNT_LAMPORTS
+    );
+}
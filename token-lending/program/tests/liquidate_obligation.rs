@@ -5,19 +5,40 @@ mod helpers;
 
 use {
     helpers::*,
+    solana_program::pubkey::Pubkey,
     solana_program_test::*,
     solana_sdk::{
+        instruction::InstructionError,
         signature::{Keypair, Signer},
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
     },
     spl_token::instruction::approve,
     spl_token_lending::{
-        instruction::{liquidate_obligation, refresh_obligation},
+        error::LendingError,
+        instruction::{
+            liquidate_obligation, refresh_obligation, set_dust_liquidation_threshold,
+            set_reserve_collateral_fee_receiver,
+        },
+        math::Decimal,
         processor::process_instruction,
-        state::INITIAL_COLLATERAL_RATIO,
+        state::{ReserveConfig, INITIAL_COLLATERAL_RATIO},
     },
 };
 
+// 100 SOL collateral
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 100 SOL * 80% LTV -> 80 SOL * 20 USDC -> 1600 USDC borrow
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_600 * FRACTIONAL_TO_USDC;
+// 1600 USDC * 50% -> 800 USDC liquidation
+const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 2;
+// 800 USDC / 20 USDC per SOL -> 40 SOL + 10% bonus -> 44 SOL
+const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 44 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 10% bonus on 40 SOL -> 4 SOL
+const SOL_LIQUIDATION_BONUS_LAMPORTS: u64 = 4 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+
+const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
 #[tokio::test]
 async fn test_success() {
     let mut test = ProgramTest::new(
@@ -29,18 +50,6 @@ async fn test_success() {
     // limit to track compute unit increase
     test.set_compute_max_units(68_000);
 
-    // 100 SOL collateral
-    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
-    // 100 SOL * 80% LTV -> 80 SOL * 20 USDC -> 1600 USDC borrow
-    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_600 * FRACTIONAL_TO_USDC;
-    // 1600 USDC * 50% -> 800 USDC liquidation
-    const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 2;
-    // 800 USDC / 20 USDC per SOL -> 40 SOL + 10% bonus -> 44 SOL
-    const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 44 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
-
-    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
-    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
-
     let user_accounts_owner = Keypair::new();
     let user_transfer_authority = Keypair::new();
     let lending_market = add_lending_market(&mut test);
@@ -135,6 +144,7 @@ async fn test_success() {
                 test_obligation.pubkey,
                 lending_market.pubkey,
                 user_transfer_authority.pubkey(),
+                None,
             ),
         ],
         Some(&payer.pubkey()),
@@ -184,3 +194,329 @@ async fn test_success() {
         (USDC_BORROW_AMOUNT_FRACTIONAL - USDC_LIQUIDATION_AMOUNT_FRACTIONAL).into()
     )
 }
+
+// Adds an unhealthy obligation borrowing USDC against SOL, with the given
+// changes to the reserve config
+async fn setup<F>(modify_reserve_config: F) -> SolUsdcObligationTest
+where
+    F: FnOnce(&mut ReserveConfig),
+{
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.liquidation_threshold = 80;
+    reserve_config.liquidation_bonus = 10;
+    modify_reserve_config(&mut reserve_config);
+
+    start_sol_usdc_obligation(
+        test,
+        AddSolUsdcObligationArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            usdc_borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            ..AddSolUsdcObligationArgs::default()
+        },
+    )
+    .await
+}
+
+// Creates a token account for SOL reserve collateral and sets it as the
+// reserve collateral fee receiver
+async fn set_collateral_fee_receiver(test: &mut SolUsdcObligationTest) -> Pubkey {
+    let collateral_fee_receiver = create_token_account(
+        &mut test.banks_client,
+        test.sol_test_reserve.collateral_mint_pubkey,
+        &test.payer,
+        None,
+        None,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_collateral_fee_receiver(
+            spl_token_lending::id(),
+            test.sol_test_reserve.pubkey,
+            collateral_fee_receiver,
+            test.lending_market.pubkey,
+            test.lending_market.owner.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.lending_market.owner],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    collateral_fee_receiver
+}
+
+async fn liquidate(
+    test: &mut SolUsdcObligationTest,
+    liquidity_amount: u64,
+    collateral_fee_receiver: Option<Pubkey>,
+) -> Result<(), TransactionError> {
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &test.usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                liquidity_amount,
+            )
+            .unwrap(),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                vec![test.sol_test_reserve.pubkey, test.usdc_test_reserve.pubkey],
+            ),
+            liquidate_obligation(
+                spl_token_lending::id(),
+                liquidity_amount,
+                test.usdc_test_reserve.user_liquidity_pubkey,
+                test.sol_test_reserve.user_collateral_pubkey,
+                test.usdc_test_reserve.pubkey,
+                test.usdc_test_reserve.liquidity_supply_pubkey,
+                test.sol_test_reserve.pubkey,
+                test.sol_test_reserve.collateral_supply_pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+                collateral_fee_receiver,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success_with_protocol_liquidation_fee() {
+    let mut test = setup(|reserve_config| {
+        reserve_config.protocol_liquidation_fee = 100;
+    })
+    .await;
+    let collateral_fee_receiver = set_collateral_fee_receiver(&mut test).await;
+
+    let initial_user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+    let initial_collateral_supply_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.collateral_supply_pubkey,
+    )
+    .await;
+
+    assert_eq!(
+        liquidate(
+            &mut test,
+            USDC_LIQUIDATION_AMOUNT_FRACTIONAL,
+            Some(collateral_fee_receiver)
+        )
+        .await,
+        Ok(())
+    );
+
+    // the whole bonus goes to the protocol
+    let user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+    assert_eq!(
+        user_collateral_balance,
+        initial_user_collateral_balance + SOL_LIQUIDATION_AMOUNT_LAMPORTS
+            - SOL_LIQUIDATION_BONUS_LAMPORTS
+    );
+
+    let collateral_fee_receiver_balance =
+        get_token_balance(&mut test.banks_client, collateral_fee_receiver).await;
+    assert_eq!(
+        collateral_fee_receiver_balance,
+        SOL_LIQUIDATION_BONUS_LAMPORTS
+    );
+
+    // while the borrower loses the same collateral as without the fee
+    let collateral_supply_balance = get_token_balance(
+        &mut test.banks_client,
+        test.sol_test_reserve.collateral_supply_pubkey,
+    )
+    .await;
+    assert_eq!(
+        collateral_supply_balance,
+        initial_collateral_supply_balance - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_collateral_fee_receiver() {
+    let mut test = setup(|reserve_config| {
+        reserve_config.protocol_liquidation_fee = 100;
+    })
+    .await;
+    set_collateral_fee_receiver(&mut test).await;
+
+    let invalid_collateral_fee_receiver = create_token_account(
+        &mut test.banks_client,
+        test.sol_test_reserve.collateral_mint_pubkey,
+        &test.payer,
+        None,
+        None,
+    )
+    .await;
+
+    assert_eq!(
+        liquidate(
+            &mut test,
+            USDC_LIQUIDATION_AMOUNT_FRACTIONAL,
+            Some(invalid_collateral_fee_receiver)
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_success_with_close_factor() {
+    // 1600 USDC * 20% -> 320 USDC liquidation
+    const USDC_CLOSE_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 5;
+    // 320 USDC / 20 USDC per SOL -> 16 SOL + 10% bonus -> 17.6 SOL
+    const SOL_CLOSE_AMOUNT_LAMPORTS: u64 = 176 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO / 10;
+
+    let mut test = setup(|reserve_config| {
+        reserve_config.liquidation_close_factor = 20;
+    })
+    .await;
+
+    // repaying more than the close factor only repays up to it
+    assert_eq!(
+        liquidate(&mut test, USDC_LIQUIDATION_AMOUNT_FRACTIONAL, None).await,
+        Ok(())
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_CLOSE_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        (USDC_BORROW_AMOUNT_FRACTIONAL - USDC_CLOSE_AMOUNT_FRACTIONAL).into()
+    );
+}
+
+#[tokio::test]
+async fn test_success_full_close_below_dust_threshold() {
+    // 1600 USDC / 20 USDC per SOL -> 80 SOL + 10% bonus -> 88 SOL
+    const SOL_CLOSE_AMOUNT_LAMPORTS: u64 = 88 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+
+    // a 50% liquidation would leave 800 USDC, below the threshold
+    let mut test = setup(|reserve_config| {
+        reserve_config.liquidation_dust_threshold = 900 * FRACTIONAL_TO_USDC;
+    })
+    .await;
+
+    assert_eq!(
+        liquidate(&mut test, USDC_BORROW_AMOUNT_FRACTIONAL, None).await,
+        Ok(())
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_CLOSE_AMOUNT_LAMPORTS
+    );
+    assert!(obligation.borrows.is_empty());
+}
+
+#[tokio::test]
+async fn test_success_full_close_below_market_dust_threshold() {
+    // 1600 USDC / 20 USDC per SOL -> 80 SOL + 10% bonus -> 88 SOL
+    const SOL_CLOSE_AMOUNT_LAMPORTS: u64 = 88 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+
+    let mut test = setup(|_| {}).await;
+
+    // the obligation's 1600 USDC borrowed value is below the threshold
+    let mut transaction = Transaction::new_with_payer(
+        &[set_dust_liquidation_threshold(
+            spl_token_lending::id(),
+            test.lending_market.pubkey,
+            test.lending_market.owner.pubkey(),
+            Decimal::from(2_000u64),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.lending_market.owner],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the close factor is ignored, clearing the borrow in one liquidation
+    assert_eq!(
+        liquidate(&mut test, USDC_BORROW_AMOUNT_FRACTIONAL, None).await,
+        Ok(())
+    );
+
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        lending_market.dust_liquidation_threshold,
+        Decimal::from(2_000u64)
+    );
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_CLOSE_AMOUNT_LAMPORTS
+    );
+    assert!(obligation.borrows.is_empty());
+}
@@ -0,0 +1,493 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::solana_program::program_pack::Pack,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral, deposit_reserve_liquidity,
+            migrate_account, refresh_obligation, refresh_reserve, repay_obligation_liquidity,
+        },
+        math::{Decimal, WAD},
+        processor::process_instruction,
+        state::{
+            FeeCalculation, LendingMarket, Reserve, ReserveConfig, ReserveLiquidity,
+            INITIAL_COLLATERAL_RATIO, LENDING_MARKET_V1_LEN, LIQUIDATION_CLOSE_FACTOR,
+            PROGRAM_VERSION, RESERVE_V1_LEN,
+        },
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 100 SOL * 50% LTV -> 50 SOL * 20 USDC -> 1000 USDC borrowable
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 500 * FRACTIONAL_TO_USDC;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+struct MigrateAccountTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    sol_test_reserve: TestReserve,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+}
+
+async fn setup() -> MigrateAccountTest {
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    setup_with_reserve_config(reserve_config).await
+}
+
+// Adds a lending market and reserves in the V1 layout, with an empty
+// obligation to run a deposit and borrow cycle once they are migrated
+async fn setup_with_reserve_config(reserve_config: ReserveConfig) -> MigrateAccountTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_v1_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS / INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            v1_layout: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            v1_layout: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    MigrateAccountTest {
+        banks_client,
+        payer,
+        user_accounts_owner,
+        lending_market,
+        sol_test_reserve,
+        usdc_test_reserve,
+        test_obligation,
+    }
+}
+
+async fn account_len(banks_client: &mut BanksClient, pubkey: Pubkey) -> usize {
+    banks_client
+        .get_account(pubkey)
+        .await
+        .unwrap()
+        .unwrap()
+        .data
+        .len()
+}
+
+async fn migrate(
+    test: &mut MigrateAccountTest,
+    account_pubkeys: &[Pubkey],
+) -> Result<(), TransactionError> {
+    let instructions: Vec<_> = account_pubkeys
+        .iter()
+        .map(|account_pubkey| {
+            migrate_account(
+                spl_token_lending::id(),
+                *account_pubkey,
+                test.payer.pubkey(),
+            )
+        })
+        .collect();
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&test.payer.pubkey()));
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup().await;
+    let lending_market_pubkey = test.lending_market.pubkey;
+    let sol_reserve_pubkey = test.sol_test_reserve.pubkey;
+    let usdc_reserve_pubkey = test.usdc_test_reserve.pubkey;
+
+    assert_eq!(
+        account_len(&mut test.banks_client, lending_market_pubkey).await,
+        LENDING_MARKET_V1_LEN
+    );
+    assert_eq!(
+        account_len(&mut test.banks_client, sol_reserve_pubkey).await,
+        RESERVE_V1_LEN
+    );
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert!(lending_market.version < PROGRAM_VERSION);
+    let sol_reserve = test
+        .sol_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(sol_reserve.version < PROGRAM_VERSION);
+
+    migrate(
+        &mut test,
+        &[
+            lending_market_pubkey,
+            sol_reserve_pubkey,
+            usdc_reserve_pubkey,
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        account_len(&mut test.banks_client, lending_market_pubkey).await,
+        LendingMarket::LEN
+    );
+    assert_eq!(
+        account_len(&mut test.banks_client, sol_reserve_pubkey).await,
+        Reserve::LEN
+    );
+    assert_eq!(
+        account_len(&mut test.banks_client, usdc_reserve_pubkey).await,
+        Reserve::LEN
+    );
+
+    let migrated_lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert_eq!(migrated_lending_market.version, PROGRAM_VERSION);
+    assert_eq!(migrated_lending_market.owner, lending_market.owner);
+    assert_eq!(
+        migrated_lending_market.quote_currency,
+        lending_market.quote_currency
+    );
+    let migrated_sol_reserve = test
+        .sol_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(migrated_sol_reserve.version, PROGRAM_VERSION);
+    // the unset flash loan fee receiver and borrow weight are written with
+    // their defaults
+    assert_eq!(
+        migrated_sol_reserve.liquidity,
+        ReserveLiquidity {
+            flash_loan_fee_receiver: sol_reserve.liquidity.fee_receiver,
+            ..sol_reserve.liquidity
+        }
+    );
+    assert_eq!(migrated_sol_reserve.collateral, sol_reserve.collateral);
+    assert_eq!(
+        migrated_sol_reserve.config,
+        ReserveConfig {
+            borrow_weight_wad: WAD,
+            ..sol_reserve.config
+        }
+    );
+
+    let user_accounts_owner_pubkey = test.user_accounts_owner.pubkey();
+    let obligation_pubkey = test.test_obligation.pubkey;
+    let sol_test_reserve = &test.sol_test_reserve;
+    let usdc_test_reserve = &test.usdc_test_reserve;
+    let initial_liquidity_supply = get_token_balance(
+        &mut test.banks_client,
+        usdc_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_reserve_pubkey,
+                sol_test_reserve.liquidity_oracle_pubkey,
+            ),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_reserve_pubkey,
+                obligation_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+                user_accounts_owner_pubkey,
+                vec![],
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation_pubkey,
+                vec![sol_reserve_pubkey],
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_oracle_pubkey,
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                obligation_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+                vec![sol_reserve_pubkey],
+                None,
+                Some(usdc_test_reserve.liquidity_host_pubkey),
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_oracle_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation_pubkey,
+                vec![sol_reserve_pubkey, usdc_reserve_pubkey],
+            ),
+            // repays the borrow fee along with the borrowed amount
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_reserve_pubkey,
+                obligation_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, &test.user_accounts_owner], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 1);
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+    assert_eq!(obligation.borrows.len(), 0);
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(usdc_reserve.liquidity.borrowed_amount_wads, Decimal::zero());
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.liquidity_supply_pubkey
+        )
+        .await,
+        initial_liquidity_supply
+    );
+}
+
+#[tokio::test]
+async fn test_already_migrated() {
+    let mut test = setup().await;
+    let sol_reserve_pubkey = test.sol_test_reserve.pubkey;
+
+    // the second migration in the transaction sees the current layout
+    assert_eq!(
+        migrate(&mut test, &[sol_reserve_pubkey, sol_reserve_pubkey])
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::AccountAlreadyMigrated as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_pre_upgrade_reserves() {
+    // configs of reserves packed before any field was added to the V1 padding
+    let mut test = setup_with_reserve_config(ReserveConfig {
+        optimal_utilization_rate: 80,
+        loan_to_value_ratio: 50,
+        liquidation_bonus: 5,
+        liquidation_threshold: 55,
+        min_borrow_rate: 0,
+        optimal_borrow_rate: 4,
+        max_borrow_rate: 30,
+        fees: TEST_RESERVE_CONFIG.fees,
+        ..ReserveConfig::default()
+    })
+    .await;
+    let lending_market_pubkey = test.lending_market.pubkey;
+    let sol_reserve_pubkey = test.sol_test_reserve.pubkey;
+    let usdc_reserve_pubkey = test.usdc_test_reserve.pubkey;
+
+    migrate(
+        &mut test,
+        &[
+            lending_market_pubkey,
+            sol_reserve_pubkey,
+            usdc_reserve_pubkey,
+        ],
+    )
+    .await
+    .unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(usdc_reserve.config.collateral_weight, 100);
+    assert_eq!(usdc_reserve.config.borrow_limit, u64::MAX);
+    assert_eq!(usdc_reserve.config.deposit_limit, u64::MAX);
+    assert_eq!(usdc_reserve.config.borrow_weight_wad, WAD);
+    assert_eq!(
+        usdc_reserve.config.liquidation_close_factor,
+        LIQUIDATION_CLOSE_FACTOR
+    );
+    assert_eq!(usdc_reserve.config.max_liquidation_bonus, 5);
+    assert_eq!(usdc_reserve.config.min_deposit_amount, 0);
+    assert_eq!(
+        usdc_reserve.liquidity.flash_loan_fee_receiver,
+        usdc_reserve.liquidity.fee_receiver
+    );
+
+    let user_accounts_owner_pubkey = test.user_accounts_owner.pubkey();
+    let obligation_pubkey = test.test_obligation.pubkey;
+    let sol_test_reserve = &test.sol_test_reserve;
+    let usdc_test_reserve = &test.usdc_test_reserve;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_oracle_pubkey,
+            ),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_reserve_pubkey,
+                sol_test_reserve.liquidity_oracle_pubkey,
+            ),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                sol_test_reserve.user_collateral_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                sol_reserve_pubkey,
+                obligation_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+                user_accounts_owner_pubkey,
+                vec![],
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                obligation_pubkey,
+                vec![sol_reserve_pubkey],
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_oracle_pubkey,
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_reserve_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                obligation_pubkey,
+                lending_market_pubkey,
+                user_accounts_owner_pubkey,
+                vec![sol_reserve_pubkey],
+                None,
+                None,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, &test.user_accounts_owner], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (borrow_fee, _host_fee) = usdc_reserve
+        .config
+        .fees
+        .calculate_borrow_fees(
+            USDC_BORROW_AMOUNT_FRACTIONAL.into(),
+            FeeCalculation::Exclusive,
+        )
+        .unwrap();
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL + borrow_fee)
+    );
+}
@@ -0,0 +1,163 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::migrate_obligation,
+        math::Decimal,
+        processor::process_instruction,
+        state::{INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION},
+    },
+};
+
+const LEGACY_VERSION: u8 = 1;
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+
+async fn setup() -> (BanksClient, Keypair, TestLendingMarket, TestObligation) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: 2 * USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            mark_fresh: true,
+            version: Some(LEGACY_VERSION),
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+    (banks_client, payer, lending_market, test_obligation)
+}
+
+async fn migrate(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    lending_market: &TestLendingMarket,
+    test_obligation: &TestObligation,
+    times: usize,
+) -> Result<(), TransactionError> {
+    let instruction = migrate_obligation(
+        spl_token_lending::id(),
+        test_obligation.pubkey,
+        lending_market.pubkey,
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&vec![instruction; times], Some(&payer.pubkey()));
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (mut banks_client, payer, lending_market, test_obligation) = setup().await;
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.version, LEGACY_VERSION);
+
+    migrate(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        &test_obligation,
+        1,
+    )
+    .await
+    .unwrap();
+
+    let migrated = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(migrated.version, PROGRAM_VERSION);
+    assert!(migrated.last_update.stale);
+    assert_eq!(migrated.owner, obligation.owner);
+    assert_eq!(migrated.deposits, obligation.deposits);
+    assert_eq!(
+        migrated.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS
+    );
+    assert_eq!(migrated.borrows, obligation.borrows);
+    assert_eq!(
+        migrated.borrows[0].borrowed_amount_wads,
+        Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL)
+    );
+}
+
+#[tokio::test]
+async fn test_already_migrated() {
+    let (mut banks_client, payer, lending_market, test_obligation) = setup().await;
+
+    // the second migration in the transaction sees the current version
+    assert_eq!(
+        migrate(
+            &mut banks_client,
+            &payer,
+            &lending_market,
+            &test_obligation,
+            2
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationAlreadyMigrated as u32)
+        )
+    );
+}
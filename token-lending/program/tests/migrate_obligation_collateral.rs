@@ -0,0 +1,312 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        hash::Hash,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{migrate_obligation_collateral, refresh_obligation, refresh_reserve},
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+    std::u64,
+};
+
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+const SOL_BORROW_AMOUNT_LAMPORTS: u64 = 10 * LAMPORTS_TO_SOL;
+
+struct MigrateTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    usdc_destination_test_reserve: TestReserve,
+    sol_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+}
+
+// Adds two USDC reserves and a SOL reserve, with an obligation holding USDC
+// collateral in the first USDC reserve and borrowing SOL
+async fn setup() -> MigrateTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            collateral_amount: USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+    let usdc_destination_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 500 * FRACTIONAL_TO_USDC,
+            collateral_amount: 500 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: SOL_BORROW_AMOUNT_LAMPORTS,
+            liquidity_amount: 100 * LAMPORTS_TO_SOL,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&usdc_test_reserve, USDC_DEPOSIT_AMOUNT_FRACTIONAL)],
+            borrows: &[(&sol_test_reserve, SOL_BORROW_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    MigrateTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        lending_market,
+        usdc_test_reserve,
+        usdc_destination_test_reserve,
+        sol_test_reserve,
+        test_obligation,
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let MigrateTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        lending_market,
+        usdc_test_reserve,
+        usdc_destination_test_reserve,
+        sol_test_reserve,
+        test_obligation,
+    } = setup().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![usdc_test_reserve.pubkey, sol_test_reserve.pubkey],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let initial_obligation = test_obligation.get_state(&mut banks_client).await;
+    let initial_source_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_supply_pubkey).await;
+    let initial_destination_liquidity_balance = get_token_balance(
+        &mut banks_client,
+        usdc_destination_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![usdc_test_reserve.pubkey, sol_test_reserve.pubkey],
+            ),
+            migrate_obligation_collateral(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.collateral_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_destination_test_reserve.pubkey,
+                usdc_destination_test_reserve.liquidity_supply_pubkey,
+                usdc_destination_test_reserve.collateral_mint_pubkey,
+                usdc_destination_test_reserve.collateral_supply_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // check that liquidity moved between the reserves
+    let source_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_supply_pubkey).await;
+    assert_eq!(
+        source_liquidity_balance,
+        initial_source_liquidity_balance - USDC_DEPOSIT_AMOUNT_FRACTIONAL
+    );
+    let destination_liquidity_balance = get_token_balance(
+        &mut banks_client,
+        usdc_destination_test_reserve.liquidity_supply_pubkey,
+    )
+    .await;
+    assert_eq!(
+        destination_liquidity_balance,
+        initial_destination_liquidity_balance + USDC_DEPOSIT_AMOUNT_FRACTIONAL
+    );
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.deposits.len(), 1);
+    let collateral = &obligation.deposits[0];
+    assert_eq!(
+        collateral.deposit_reserve,
+        usdc_destination_test_reserve.pubkey
+    );
+    assert_eq!(collateral.deposited_amount, USDC_DEPOSIT_AMOUNT_FRACTIONAL);
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        initial_obligation.allowed_borrow_value
+    );
+    assert_eq!(
+        obligation.deposited_value,
+        initial_obligation.deposited_value
+    );
+
+    // a full refresh agrees with the values recomputed by the migration
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_destination_test_reserve.pubkey,
+                usdc_destination_test_reserve.liquidity_oracle_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_test_reserve.liquidity_oracle_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![
+                    usdc_destination_test_reserve.pubkey,
+                    sol_test_reserve.pubkey,
+                ],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        initial_obligation.allowed_borrow_value
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_destination_mint() {
+    let MigrateTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        lending_market,
+        usdc_test_reserve,
+        sol_test_reserve,
+        test_obligation,
+        ..
+    } = setup().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![usdc_test_reserve.pubkey, sol_test_reserve.pubkey],
+            ),
+            migrate_obligation_collateral(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.collateral_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                sol_test_reserve.pubkey,
+                sol_test_reserve.liquidity_supply_pubkey,
+                sol_test_reserve.collateral_mint_pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+}
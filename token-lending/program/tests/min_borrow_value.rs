@@ -0,0 +1,307 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, refresh_obligation, refresh_reserve,
+            repay_obligation_liquidity, set_min_borrow_value,
+        },
+        math::Decimal,
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+const MIN_BORROW_VALUE: u64 = 10;
+// $10 at the USDC price of $1
+const MIN_BORROW_AMOUNT_FRACTIONAL: u64 = MIN_BORROW_VALUE * FRACTIONAL_TO_USDC;
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+struct MinBorrowValueTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    sol_oracle: TestOracle,
+    sol_test_reserve: TestReserve,
+    usdc_oracle: TestOracle,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+}
+
+// Adds an obligation with SOL collateral and `usdc_borrow_amount` of USDC
+// borrowed, in a market with a $10 min borrow value
+async fn setup(usdc_borrow_amount: u64) -> MinBorrowValueTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // no borrow fee, so the borrowed amount is the amount received
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.fees.borrow_fee_wad = 0;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: usdc_borrow_amount,
+            user_liquidity_amount: usdc_borrow_amount,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let borrows = [(&usdc_test_reserve, usdc_borrow_amount)];
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: if usdc_borrow_amount > 0 {
+                &borrows
+            } else {
+                &[]
+            },
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_min_borrow_value(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            Decimal::from(MIN_BORROW_VALUE),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    MinBorrowValueTest {
+        banks_client,
+        payer,
+        user_accounts_owner,
+        lending_market,
+        sol_oracle,
+        sol_test_reserve,
+        usdc_oracle,
+        usdc_test_reserve,
+        test_obligation,
+    }
+}
+
+// Refreshes the reserves and the obligation ahead of the instruction
+async fn process_refreshed(
+    test: &mut MinBorrowValueTest,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let mut reserve_pubkeys = vec![test.sol_test_reserve.pubkey];
+    if !obligation.borrows.is_empty() {
+        reserve_pubkeys.push(test.usdc_test_reserve.pubkey);
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.sol_test_reserve.pubkey,
+                test.sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                reserve_pubkeys,
+            ),
+            instruction,
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, &test.user_accounts_owner], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn borrow(
+    test: &mut MinBorrowValueTest,
+    liquidity_amount: u64,
+) -> Result<(), TransactionError> {
+    let instruction = borrow_obligation_liquidity(
+        spl_token_lending::id(),
+        liquidity_amount,
+        None,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        test.test_obligation.pubkey,
+        test.lending_market.pubkey,
+        test.test_obligation.owner,
+        vec![test.sol_test_reserve.pubkey],
+        None,
+        None,
+    );
+    process_refreshed(test, instruction).await
+}
+
+async fn repay(
+    test: &mut MinBorrowValueTest,
+    liquidity_amount: u64,
+) -> Result<(), TransactionError> {
+    let instruction = repay_obligation_liquidity(
+        spl_token_lending::id(),
+        liquidity_amount,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.test_obligation.pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    );
+    process_refreshed(test, instruction).await
+}
+
+#[tokio::test]
+async fn test_borrow_at_min_borrow_value() {
+    let mut test = setup(0).await;
+
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        lending_market.min_borrow_value,
+        Decimal::from(MIN_BORROW_VALUE)
+    );
+
+    borrow(&mut test, MIN_BORROW_AMOUNT_FRACTIONAL)
+        .await
+        .unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(MIN_BORROW_AMOUNT_FRACTIONAL)
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_below_min_borrow_value() {
+    let mut test = setup(0).await;
+
+    assert_eq!(
+        borrow(&mut test, MIN_BORROW_AMOUNT_FRACTIONAL - 1)
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            3,
+            InstructionError::Custom(LendingError::BorrowTooSmall as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_counts_existing_borrows() {
+    let mut test = setup(MIN_BORROW_AMOUNT_FRACTIONAL / 2).await;
+
+    // the obligation's borrows reach the min borrow value together
+    borrow(&mut test, MIN_BORROW_AMOUNT_FRACTIONAL / 2)
+        .await
+        .unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(MIN_BORROW_AMOUNT_FRACTIONAL)
+    );
+}
+
+#[tokio::test]
+async fn test_partial_repay_below_min_borrow_value() {
+    let mut test = setup(2 * MIN_BORROW_AMOUNT_FRACTIONAL).await;
+
+    // leaves $5 borrowed, below the min borrow value
+    repay(&mut test, MIN_BORROW_AMOUNT_FRACTIONAL * 3 / 2)
+        .await
+        .unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(MIN_BORROW_AMOUNT_FRACTIONAL / 2)
+    );
+
+    // a full repay clears the remainder
+    repay(&mut test, u64::MAX).await.unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert!(obligation.borrows.is_empty());
+}
+
+#[tokio::test]
+async fn test_full_repay() {
+    let mut test = setup(2 * MIN_BORROW_AMOUNT_FRACTIONAL).await;
+
+    repay(&mut test, u64::MAX).await.unwrap();
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert!(obligation.borrows.is_empty());
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.user_liquidity_pubkey
+        )
+        .await,
+        0
+    );
+}
@@ -15,10 +15,11 @@ use {
     spl_token_lending::{
         error::LendingError,
         instruction::modify_reserve_config,
+        math::WAD,
         processor::process_instruction,
         state::{
             InitLendingMarketParams, LendingMarket, ReserveConfig, ReserveFees,
-            INITIAL_COLLATERAL_RATIO,
+            INITIAL_COLLATERAL_RATIO, MAX_ORACLE_AGE_SLOTS,
         },
     },
 };
@@ -64,11 +65,36 @@ async fn modify_reserve_config_success() {
         optimal_utilization_rate: TEST_RESERVE_CONFIG.optimal_utilization_rate
             - OPTIMAL_UTILIZATION_RATE_CHANGE,
         loan_to_value_ratio: 50,
+        collateral_weight: 100,
         liquidation_bonus: 5,
+        min_liquidation_bonus: 0,
+        max_liquidation_bonus: 5,
         liquidation_threshold: 55,
+        stressed_liquidation_threshold: 0,
+        stress_utilization_rate: 0,
         min_borrow_rate: 0,
         optimal_borrow_rate: 4,
         max_borrow_rate: 30,
+        max_utilization_rate: 0,
+        super_max_borrow_rate: 0,
+        max_withdraw_utilization: 100,
+        borrow_rate_rebate: 0,
+        borrow_rate_floor: 0,
+        borrow_limit: u64::MAX,
+        deposit_limit: u64::MAX,
+        max_oracle_age_slots: 0,
+        borrow_weight_wad: 0,
+        protocol_liquidation_fee: 0,
+        liquidation_close_factor: 50,
+        liquidation_dust_threshold: 0,
+        min_deposit_amount: 0,
+        max_confidence_interval_bps: 0,
+        outflow_window_duration_slots: 0,
+        max_outflow: 0,
+        max_accrual_slots_per_refresh: 0,
+        rate_history_interval_slots: 0,
+        isolated: false,
+        borrowable_in_isolation: false,
         fees: ReserveFees {
             borrow_fee_wad: 100_000_000_000,
             flash_loan_fee_wad: 3_000_000_000_000_000,
@@ -76,6 +102,8 @@ async fn modify_reserve_config_success() {
         },
     };
 
+    let initial_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+
     let mut transaction = Transaction::new_with_payer(
         &[modify_reserve_config(
             spl_token_lending::id(),
@@ -97,6 +125,218 @@ async fn modify_reserve_config_success() {
 
     let reserve_info = sol_test_reserve.get_state(&mut banks_client).await;
     assert_eq!(reserve_info.config, new_config);
+
+    // only the config changes, liquidity and collateral are left untouched
+    assert_eq!(reserve_info.liquidity, initial_reserve.liquidity);
+    assert_eq!(reserve_info.collateral, initial_reserve.collateral);
+    assert_eq!(
+        reserve_info.last_update.slot,
+        initial_reserve.last_update.slot
+    );
+    assert_eq!(
+        reserve_info.last_update.stale,
+        initial_reserve.last_update.stale
+    );
+}
+
+#[tokio::test]
+async fn invalid_config_cannot_be_set() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    test.set_compute_max_units(70_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 10 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // liquidation threshold must be above the loan to value ratio
+    let new_config = ReserveConfig {
+        loan_to_value_ratio: 60,
+        liquidation_threshold: 60,
+        ..TEST_RESERVE_CONFIG
+    };
+
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            sol_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidLiquidationThreshold as u32)
+        )
+    );
+
+    // oracle prices can't be accepted for longer than the upper bound
+    let new_config = ReserveConfig {
+        max_oracle_age_slots: MAX_ORACLE_AGE_SLOTS + 1,
+        ..TEST_RESERVE_CONFIG
+    };
+
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            sol_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+
+    // borrows can't be weighted below their market value
+    let new_config = ReserveConfig {
+        borrow_weight_wad: WAD - 1,
+        ..TEST_RESERVE_CONFIG
+    };
+
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            sol_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+
+    // the protocol can't take more than the whole liquidation bonus
+    let new_config = ReserveConfig {
+        protocol_liquidation_fee: 101,
+        ..TEST_RESERVE_CONFIG
+    };
+
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            sol_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+
+    // liquidations must be able to repay part of a borrow
+    let new_config = ReserveConfig {
+        liquidation_close_factor: 0,
+        ..TEST_RESERVE_CONFIG
+    };
+
+    let mut transaction = Transaction::new_with_payer(
+        &[modify_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            sol_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+
+    let reserve_info = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(reserve_info.config, TEST_RESERVE_CONFIG);
 }
 
 #[tokio::test]
@@ -145,11 +385,36 @@ async fn wrong_signer_of_lending_market_cannot_change_reserve_config() {
         optimal_utilization_rate: TEST_RESERVE_CONFIG.optimal_utilization_rate
             - OPTIMAL_UTILIZATION_RATE_CHANGE,
         loan_to_value_ratio: 50,
+        collateral_weight: 100,
         liquidation_bonus: 5,
+        min_liquidation_bonus: 0,
+        max_liquidation_bonus: 5,
         liquidation_threshold: 55,
+        stressed_liquidation_threshold: 0,
+        stress_utilization_rate: 0,
         min_borrow_rate: 0,
         optimal_borrow_rate: 4,
         max_borrow_rate: 30,
+        max_utilization_rate: 0,
+        super_max_borrow_rate: 0,
+        max_withdraw_utilization: 100,
+        borrow_rate_rebate: 0,
+        borrow_rate_floor: 0,
+        borrow_limit: u64::MAX,
+        deposit_limit: u64::MAX,
+        max_oracle_age_slots: 0,
+        borrow_weight_wad: 0,
+        protocol_liquidation_fee: 0,
+        liquidation_close_factor: 50,
+        liquidation_dust_threshold: 0,
+        min_deposit_amount: 0,
+        max_confidence_interval_bps: 0,
+        outflow_window_duration_slots: 0,
+        max_outflow: 0,
+        max_accrual_slots_per_refresh: 0,
+        rate_history_interval_slots: 0,
+        isolated: false,
+        borrowable_in_isolation: false,
         fees: ReserveFees {
             borrow_fee_wad: 100_000_000_000,
             flash_loan_fee_wad: 3_000_000_000_000_000,
@@ -259,11 +524,36 @@ async fn owner_of_different_lending_market_cannot_change_reserve_config() {
         optimal_utilization_rate: TEST_RESERVE_CONFIG.optimal_utilization_rate
             - OPTIMAL_UTILIZATION_RATE_CHANGE,
         loan_to_value_ratio: 50,
+        collateral_weight: 100,
         liquidation_bonus: 5,
+        min_liquidation_bonus: 0,
+        max_liquidation_bonus: 5,
         liquidation_threshold: 55,
+        stressed_liquidation_threshold: 0,
+        stress_utilization_rate: 0,
         min_borrow_rate: 0,
         optimal_borrow_rate: 4,
         max_borrow_rate: 30,
+        max_utilization_rate: 0,
+        super_max_borrow_rate: 0,
+        max_withdraw_utilization: 100,
+        borrow_rate_rebate: 0,
+        borrow_rate_floor: 0,
+        borrow_limit: u64::MAX,
+        deposit_limit: u64::MAX,
+        max_oracle_age_slots: 0,
+        borrow_weight_wad: 0,
+        protocol_liquidation_fee: 0,
+        liquidation_close_factor: 50,
+        liquidation_dust_threshold: 0,
+        min_deposit_amount: 0,
+        max_confidence_interval_bps: 0,
+        outflow_window_duration_slots: 0,
+        max_outflow: 0,
+        max_accrual_slots_per_refresh: 0,
+        rate_history_interval_slots: 0,
+        isolated: false,
+        borrowable_in_isolation: false,
         fees: ReserveFees {
             borrow_fee_wad: 100_000_000_000,
             flash_loan_fee_wad: 3_000_000_000_000_000,
@@ -349,11 +639,36 @@ async fn correct_owner_providing_wrong_lending_market_fails() {
         optimal_utilization_rate: TEST_RESERVE_CONFIG.optimal_utilization_rate
             - OPTIMAL_UTILIZATION_RATE_CHANGE,
         loan_to_value_ratio: 50,
+        collateral_weight: 100,
         liquidation_bonus: 5,
+        min_liquidation_bonus: 0,
+        max_liquidation_bonus: 5,
         liquidation_threshold: 55,
+        stressed_liquidation_threshold: 0,
+        stress_utilization_rate: 0,
         min_borrow_rate: 0,
         optimal_borrow_rate: 4,
         max_borrow_rate: 30,
+        max_utilization_rate: 0,
+        super_max_borrow_rate: 0,
+        max_withdraw_utilization: 100,
+        borrow_rate_rebate: 0,
+        borrow_rate_floor: 0,
+        borrow_limit: u64::MAX,
+        deposit_limit: u64::MAX,
+        max_oracle_age_slots: 0,
+        borrow_weight_wad: 0,
+        protocol_liquidation_fee: 0,
+        liquidation_close_factor: 50,
+        liquidation_dust_threshold: 0,
+        min_deposit_amount: 0,
+        max_confidence_interval_bps: 0,
+        outflow_window_duration_slots: 0,
+        max_outflow: 0,
+        max_accrual_slots_per_refresh: 0,
+        rate_history_interval_slots: 0,
+        isolated: false,
+        borrowable_in_isolation: false,
         fees: ReserveFees {
             borrow_fee_wad: 100_000_000_000,
             flash_loan_fee_wad: 3_000_000_000_000_000,
@@ -392,3 +707,67 @@ async fn correct_owner_providing_wrong_lending_market_fails() {
     let reserve_info = sol_test_reserve.get_state(&mut banks_client).await;
     assert_eq!(reserve_info.config, TEST_RESERVE_CONFIG);
 }
+
+#[tokio::test]
+async fn zero_limit_cannot_be_set_before_migration() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10 * LAMPORTS_TO_SOL,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: TEST_RESERVE_CONFIG,
+            v1_layout: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // the V1 layout reads a 0 limit as unlimited, so it would loosen the limit
+    for new_config in [
+        ReserveConfig {
+            borrow_limit: 0,
+            ..TEST_RESERVE_CONFIG
+        },
+        ReserveConfig {
+            deposit_limit: 0,
+            ..TEST_RESERVE_CONFIG
+        },
+    ] {
+        let mut transaction = Transaction::new_with_payer(
+            &[modify_reserve_config(
+                spl_token_lending::id(),
+                new_config,
+                sol_test_reserve.pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+        assert_eq!(
+            banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(LendingError::AccountNotMigrated as u32)
+            )
+        );
+    }
+}
@@ -151,6 +151,7 @@ async fn test_success() {
                 lending_market.pubkey,
                 user_accounts_owner_pubkey,
                 user_transfer_authority_pubkey,
+                vec![],
             ),
             // 5
             refresh_obligation(
@@ -176,6 +177,8 @@ async fn test_success() {
                 obligation_pubkey,
                 lending_market.pubkey,
                 user_accounts_owner_pubkey,
+                vec![sol_test_reserve.pubkey],
+                None,
                 Some(usdc_test_reserve.liquidity_host_pubkey),
             ),
             // 8
@@ -251,7 +254,7 @@ async fn test_success() {
             .await
             .unwrap()
             .unwrap();
-        Obligation::unpack(&obligation_account.data[..]).unwrap()
+        Obligation::unpack_account(&obligation_account.data[..]).unwrap()
     };
 
     let collateral_supply_balance =
@@ -0,0 +1,538 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_reserve_liquidity, migrate_obligation_collateral,
+            redeem_reserve_collateral, refresh_obligation, refresh_reserve,
+            repay_obligation_liquidity,
+        },
+        processor::process_instruction,
+        state::{ReserveConfig, INITIAL_COLLATERAL_RATIO, MAX_ORACLE_AGE_SLOTS},
+    },
+};
+
+const WINDOW_DURATION_SLOTS: u64 = 100;
+const MAX_OUTFLOW_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+fn outflow_limited_config() -> ReserveConfig {
+    ReserveConfig {
+        loan_to_value_ratio: 50,
+        outflow_window_duration_slots: WINDOW_DURATION_SLOTS,
+        max_outflow: MAX_OUTFLOW_FRACTIONAL,
+        // the oracle price is from slot 0
+        max_oracle_age_slots: MAX_ORACLE_AGE_SLOTS,
+        ..TEST_RESERVE_CONFIG
+    }
+}
+
+async fn redeem(
+    context: &mut ProgramTestContext,
+    lending_market: &TestLendingMarket,
+    usdc_test_reserve: &TestReserve,
+    usdc_oracle: &TestOracle,
+    user_accounts_owner: &Keypair,
+    liquidity_amount: u64,
+) -> Result<(), TransactionError> {
+    let collateral_amount = liquidity_amount * INITIAL_COLLATERAL_RATIO;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_collateral_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                collateral_amount,
+            )
+            .unwrap(),
+            redeem_reserve_collateral(
+                spl_token_lending::id(),
+                collateral_amount,
+                None,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &context.payer,
+            user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+fn outflow_rate_limit_exceeded(instruction_index: u8) -> TransactionError {
+    TransactionError::InstructionError(
+        instruction_index,
+        InstructionError::Custom(LendingError::OutflowRateLimitExceeded as u32),
+    )
+}
+
+#[tokio::test]
+async fn test_redeem_window_rollover() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 300 * FRACTIONAL_TO_USDC;
+    const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: outflow_limited_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(1_000).unwrap(); // clock.slot = 1_000
+
+    redeem(
+        &mut context,
+        &lending_market,
+        &usdc_test_reserve,
+        &usdc_oracle,
+        &user_accounts_owner,
+        60 * FRACTIONAL_TO_USDC,
+    )
+    .await
+    .unwrap();
+
+    // deposits never count against the limit
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &context.payer,
+            &user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        context.last_blockhash,
+    );
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut context.banks_client).await;
+    assert_eq!(usdc_reserve.outflow.window_start_slot, 1_000);
+    assert_eq!(usdc_reserve.outflow.window_amount, 60 * FRACTIONAL_TO_USDC);
+
+    assert_eq!(
+        redeem(
+            &mut context,
+            &lending_market,
+            &usdc_test_reserve,
+            &usdc_oracle,
+            &user_accounts_owner,
+            50 * FRACTIONAL_TO_USDC,
+        )
+        .await,
+        Err(outflow_rate_limit_exceeded(2))
+    );
+
+    // half of the previous window outflow still counts halfway into the next
+    // window
+    context.warp_to_slot(1_150).unwrap(); // clock.slot = 1_150
+
+    redeem(
+        &mut context,
+        &lending_market,
+        &usdc_test_reserve,
+        &usdc_oracle,
+        &user_accounts_owner,
+        50 * FRACTIONAL_TO_USDC,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        redeem(
+            &mut context,
+            &lending_market,
+            &usdc_test_reserve,
+            &usdc_oracle,
+            &user_accounts_owner,
+            30 * FRACTIONAL_TO_USDC,
+        )
+        .await,
+        Err(outflow_rate_limit_exceeded(2))
+    );
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut context.banks_client).await;
+    assert_eq!(usdc_reserve.outflow.window_start_slot, 1_100);
+    assert_eq!(usdc_reserve.outflow.window_amount, 50 * FRACTIONAL_TO_USDC);
+    assert_eq!(
+        usdc_reserve.outflow.previous_window_amount,
+        60 * FRACTIONAL_TO_USDC
+    );
+
+    // a full window without outflow resets the limit
+    context.warp_to_slot(1_400).unwrap(); // clock.slot = 1_400
+
+    redeem(
+        &mut context,
+        &lending_market,
+        &usdc_test_reserve,
+        &usdc_oracle,
+        &user_accounts_owner,
+        MAX_OUTFLOW_FRACTIONAL,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_borrow_limited_and_repay_not_counted() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 80 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: outflow_limited_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: outflow_limited_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let refresh_instructions = |obligation_reserves| {
+        vec![
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.price_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.price_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                obligation_reserves,
+            ),
+        ]
+    };
+    let borrow_instruction = |liquidity_amount| {
+        borrow_obligation_liquidity(
+            spl_token_lending::id(),
+            liquidity_amount,
+            None,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            test_obligation.owner,
+            vec![sol_test_reserve.pubkey],
+            None,
+            Some(usdc_test_reserve.liquidity_host_pubkey),
+        )
+    };
+
+    let mut instructions = refresh_instructions(vec![sol_test_reserve.pubkey]);
+    instructions.push(borrow_instruction(USDC_BORROW_AMOUNT_FRACTIONAL));
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // the borrow fee leaves the liquidity supply too
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    let borrow_outflow = usdc_reserve.outflow.window_amount;
+    assert!(borrow_outflow > USDC_BORROW_AMOUNT_FRACTIONAL);
+
+    // repayments never count against the limit
+    let user_transfer_authority = Keypair::new();
+    let mut instructions = vec![approve(
+        &spl_token::id(),
+        &usdc_test_reserve.user_liquidity_pubkey,
+        &user_transfer_authority.pubkey(),
+        &user_accounts_owner.pubkey(),
+        &[],
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+    )
+    .unwrap()];
+    instructions.extend(refresh_instructions(vec![
+        sol_test_reserve.pubkey,
+        usdc_test_reserve.pubkey,
+    ]));
+    instructions.push(repay_obligation_liquidity(
+        spl_token_lending::id(),
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        usdc_test_reserve.user_liquidity_pubkey,
+        usdc_test_reserve.liquidity_supply_pubkey,
+        usdc_test_reserve.pubkey,
+        test_obligation.pubkey,
+        lending_market.pubkey,
+        user_transfer_authority.pubkey(),
+    ));
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(usdc_reserve.outflow.window_amount, borrow_outflow);
+
+    let mut instructions =
+        refresh_instructions(vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey]);
+    instructions.push(borrow_instruction(30 * FRACTIONAL_TO_USDC));
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        outflow_rate_limit_exceeded(3)
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_collateral_limited() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 300 * FRACTIONAL_TO_USDC;
+    const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 200 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: outflow_limited_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+    let usdc_destination_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                loan_to_value_ratio: 50,
+                ..TEST_RESERVE_CONFIG
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(
+                &usdc_test_reserve,
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            )],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let migrate_instructions = |liquidity_amount: u64| {
+        vec![
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![usdc_test_reserve.pubkey],
+            ),
+            migrate_obligation_collateral(
+                spl_token_lending::id(),
+                liquidity_amount * INITIAL_COLLATERAL_RATIO,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.collateral_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_destination_test_reserve.pubkey,
+                usdc_destination_test_reserve.liquidity_supply_pubkey,
+                usdc_destination_test_reserve.collateral_mint_pubkey,
+                usdc_destination_test_reserve.collateral_supply_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+            ),
+        ]
+    };
+
+    // the redeemed liquidity leaves the source reserve, so it counts against the limit
+    let mut transaction = Transaction::new_with_payer(
+        &migrate_instructions(MAX_OUTFLOW_FRACTIONAL + FRACTIONAL_TO_USDC),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        outflow_rate_limit_exceeded(1)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &migrate_instructions(MAX_OUTFLOW_FRACTIONAL),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(usdc_reserve.outflow.window_amount, MAX_OUTFLOW_FRACTIONAL);
+}
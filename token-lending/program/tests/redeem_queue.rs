@@ -0,0 +1,576 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::program_pack::Pack,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            cancel_redeem, deposit_reserve_liquidity, fulfill_redeem, redeem_reserve_collateral,
+            refresh_reserve, request_redeem,
+        },
+        processor::process_instruction,
+        state::{
+            find_redeem_request_address, RedeemRequest, ReserveConfig, INITIAL_COLLATERAL_RATIO,
+        },
+    },
+};
+
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10 * FRACTIONAL_TO_USDC;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 8 * FRACTIONAL_TO_USDC;
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 5 * FRACTIONAL_TO_USDC;
+
+struct RedeemQueueTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_oracle: TestOracle,
+    usdc_test_reserve: TestReserve,
+}
+
+// Adds a USDC reserve with 8 of its 10 USDC borrowed, so 2 USDC can be
+// redeemed right away
+async fn setup() -> RedeemQueueTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            user_liquidity_amount: USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            // no interest accrues, so the exchange rate stays 1:1
+            config: ReserveConfig {
+                optimal_borrow_rate: 0,
+                max_borrow_rate: 0,
+                ..TEST_RESERVE_CONFIG
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    RedeemQueueTest {
+        banks_client,
+        payer,
+        user_accounts_owner,
+        lending_market,
+        usdc_oracle,
+        usdc_test_reserve,
+    }
+}
+
+async fn process(
+    test: &mut RedeemQueueTest,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let refresh_instruction = refresh_reserve(
+        spl_token_lending::id(),
+        test.usdc_test_reserve.pubkey,
+        test.usdc_oracle.price_pubkey,
+    );
+    // the user only signs instructions that ask for their signature
+    let user_signs = instruction
+        .accounts
+        .iter()
+        .any(|account| account.is_signer && account.pubkey == test.user_accounts_owner.pubkey());
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_instruction, instruction],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    if user_signs {
+        transaction.sign(&[&test.payer, &test.user_accounts_owner], recent_blockhash);
+    } else {
+        transaction.sign(&[&test.payer], recent_blockhash);
+    }
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn request(
+    test: &mut RedeemQueueTest,
+    collateral_amount: u64,
+) -> Result<u64, TransactionError> {
+    let destination_liquidity_pubkey = test.usdc_test_reserve.user_liquidity_pubkey;
+    request_to(test, collateral_amount, destination_liquidity_pubkey).await
+}
+
+async fn request_to(
+    test: &mut RedeemQueueTest,
+    collateral_amount: u64,
+    destination_liquidity_pubkey: Pubkey,
+) -> Result<u64, TransactionError> {
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    let ticket = reserve.redeem_queue.next_ticket;
+    let instruction = request_redeem(
+        spl_token_lending::id(),
+        collateral_amount,
+        ticket,
+        test.usdc_test_reserve.user_collateral_pubkey,
+        destination_liquidity_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.collateral_mint_pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.collateral_supply_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+        test.payer.pubkey(),
+    );
+    process(test, instruction).await.map(|()| ticket)
+}
+
+async fn fulfill(test: &mut RedeemQueueTest, ticket: u64) -> Result<(), TransactionError> {
+    let destination_liquidity_pubkey = test.usdc_test_reserve.user_liquidity_pubkey;
+    fulfill_to(test, ticket, destination_liquidity_pubkey).await
+}
+
+async fn fulfill_to(
+    test: &mut RedeemQueueTest,
+    ticket: u64,
+    destination_liquidity_pubkey: Pubkey,
+) -> Result<(), TransactionError> {
+    let instruction = fulfill_redeem(
+        spl_token_lending::id(),
+        ticket,
+        destination_liquidity_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.collateral_mint_pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.collateral_supply_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    );
+    process(test, instruction).await
+}
+
+async fn cancel(
+    test: &mut RedeemQueueTest,
+    reroute: bool,
+    ticket: u64,
+    destination_pubkey: Pubkey,
+) -> Result<(), TransactionError> {
+    let instruction = cancel_redeem(
+        spl_token_lending::id(),
+        reroute,
+        ticket,
+        destination_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.collateral_supply_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    );
+    process(test, instruction).await
+}
+
+async fn create_destination(test: &mut RedeemQueueTest) -> Pubkey {
+    create_token_account(
+        &mut test.banks_client,
+        test.usdc_test_reserve.liquidity_mint_pubkey,
+        &test.payer,
+        Some(test.user_accounts_owner.pubkey()),
+        None,
+    )
+    .await
+}
+
+async fn redeem_request_exists(test: &mut RedeemQueueTest, ticket: u64) -> bool {
+    let (redeem_request_pubkey, _bump_seed) = find_redeem_request_address(
+        &spl_token_lending::id(),
+        &test.usdc_test_reserve.pubkey,
+        ticket,
+    );
+    test.banks_client
+        .get_account(redeem_request_pubkey)
+        .await
+        .unwrap()
+        .is_some()
+}
+
+async fn deposit(test: &mut RedeemQueueTest, liquidity_amount: u64) {
+    let instruction = deposit_reserve_liquidity(
+        spl_token_lending::id(),
+        liquidity_amount,
+        None,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+        test.usdc_test_reserve.user_collateral_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.usdc_test_reserve.collateral_mint_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    );
+    process(test, instruction).await.unwrap();
+}
+
+fn lending_error(error: LendingError) -> TransactionError {
+    TransactionError::InstructionError(1, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn test_redeem_right_away() {
+    let mut test = setup().await;
+
+    let initial_user_liquidity_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+    )
+    .await;
+
+    let collateral_amount = FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+    let ticket = request(&mut test, collateral_amount).await.unwrap();
+
+    // nothing was queued
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(reserve.redeem_queue.is_empty());
+    assert_eq!(reserve.redeem_queue.collateral_amount, 0);
+    let (redeem_request_pubkey, _bump_seed) = find_redeem_request_address(
+        &spl_token_lending::id(),
+        &test.usdc_test_reserve.pubkey,
+        ticket,
+    );
+    assert!(test
+        .banks_client
+        .get_account(redeem_request_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.user_liquidity_pubkey
+        )
+        .await,
+        initial_user_liquidity_balance + FRACTIONAL_TO_USDC
+    );
+}
+
+#[tokio::test]
+async fn test_queue_and_fulfill() {
+    let mut test = setup().await;
+
+    let initial_user_liquidity_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+    )
+    .await;
+    let initial_collateral_supply_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.collateral_supply_pubkey,
+    )
+    .await;
+
+    // 5 USDC is more than the 2 USDC available
+    let collateral_amount = 5 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+    let ticket = request(&mut test, collateral_amount).await.unwrap();
+    assert_eq!(ticket, 0);
+
+    let (redeem_request_pubkey, _bump_seed) = find_redeem_request_address(
+        &spl_token_lending::id(),
+        &test.usdc_test_reserve.pubkey,
+        ticket,
+    );
+    let redeem_request_account = test
+        .banks_client
+        .get_account(redeem_request_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let redeem_request = RedeemRequest::unpack(&redeem_request_account.data).unwrap();
+    assert_eq!(redeem_request.collateral_amount, collateral_amount);
+    // the request belongs to the collateral owner, not the rent payer
+    assert_eq!(redeem_request.owner, test.user_accounts_owner.pubkey());
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.collateral_supply_pubkey
+        )
+        .await,
+        initial_collateral_supply_balance + collateral_amount
+    );
+
+    // later redemptions queue behind the request, even ones the reserve
+    // could cover
+    let next_ticket = request(&mut test, INITIAL_COLLATERAL_RATIO).await.unwrap();
+    assert_eq!(next_ticket, 1);
+
+    // the available 2 USDC is redeemed, the rest of the request stays at the
+    // head of the queue
+    fulfill(&mut test, ticket).await.unwrap();
+    let redeem_request_account = test
+        .banks_client
+        .get_account(redeem_request_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let redeem_request = RedeemRequest::unpack(&redeem_request_account.data).unwrap();
+    assert_eq!(
+        redeem_request.collateral_amount,
+        3 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut test.banks_client,
+            test.usdc_test_reserve.user_liquidity_pubkey
+        )
+        .await,
+        initial_user_liquidity_balance + 2 * FRACTIONAL_TO_USDC
+    );
+    assert_eq!(
+        fulfill(&mut test, next_ticket).await.unwrap_err(),
+        lending_error(LendingError::InvalidAccountInput)
+    );
+
+    // fresh liquidity fulfills the rest, and the request is closed
+    deposit(&mut test, USDC_DEPOSIT_AMOUNT_FRACTIONAL).await;
+    fulfill(&mut test, ticket).await.unwrap();
+    assert!(test
+        .banks_client
+        .get_account(redeem_request_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.redeem_queue.head_ticket, next_ticket);
+    assert_eq!(
+        reserve.redeem_queue.collateral_amount,
+        INITIAL_COLLATERAL_RATIO
+    );
+
+    fulfill(&mut test, next_ticket).await.unwrap();
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(reserve.redeem_queue.is_empty());
+}
+
+#[tokio::test]
+async fn test_redeem_while_queued() {
+    let mut test = setup().await;
+
+    request(&mut test, 5 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO)
+        .await
+        .unwrap();
+    deposit(&mut test, USDC_DEPOSIT_AMOUNT_FRACTIONAL).await;
+
+    // the reserve could cover both, but the queued request comes first
+    let instruction = redeem_reserve_collateral(
+        spl_token_lending::id(),
+        INITIAL_COLLATERAL_RATIO,
+        None,
+        test.usdc_test_reserve.user_collateral_pubkey,
+        test.usdc_test_reserve.user_liquidity_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.collateral_mint_pubkey,
+        test.usdc_test_reserve.liquidity_supply_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+    );
+    assert_eq!(
+        process(&mut test, instruction).await.unwrap_err(),
+        lending_error(LendingError::RedemptionsQueued)
+    );
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    let mut test = setup().await;
+
+    let initial_user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+
+    let collateral_amount = 5 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+    let ticket = request(&mut test, collateral_amount).await.unwrap();
+    let next_ticket = request(&mut test, INITIAL_COLLATERAL_RATIO).await.unwrap();
+
+    // only the owner can cancel
+    let instruction = cancel_redeem(
+        spl_token_lending::id(),
+        false,
+        next_ticket,
+        test.usdc_test_reserve.user_collateral_pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.usdc_test_reserve.collateral_supply_pubkey,
+        test.lending_market.pubkey,
+        test.payer.pubkey(),
+    );
+    assert_eq!(
+        process(&mut test, instruction).await.unwrap_err(),
+        lending_error(LendingError::InvalidAccountInput)
+    );
+
+    // a request behind the head leaves the queue collateral, the head stays
+    let user_collateral_pubkey = test.usdc_test_reserve.user_collateral_pubkey;
+    cancel(&mut test, false, next_ticket, user_collateral_pubkey)
+        .await
+        .unwrap();
+    assert!(!redeem_request_exists(&mut test, next_ticket).await);
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.redeem_queue.head_ticket, ticket);
+    assert_eq!(reserve.redeem_queue.collateral_amount, collateral_amount);
+
+    // cancelling the head moves the head to the closed request, which is
+    // skipped without liquidity
+    cancel(&mut test, false, ticket, user_collateral_pubkey)
+        .await
+        .unwrap();
+    assert!(!redeem_request_exists(&mut test, ticket).await);
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.redeem_queue.head_ticket, next_ticket);
+    assert_eq!(reserve.redeem_queue.collateral_amount, 0);
+
+    fulfill(&mut test, next_ticket).await.unwrap();
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(reserve.redeem_queue.is_empty());
+
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, user_collateral_pubkey).await,
+        initial_user_collateral_balance
+    );
+}
+
+#[tokio::test]
+async fn test_reroute() {
+    let mut test = setup().await;
+
+    let collateral_amount = 5 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+    let ticket = request(&mut test, collateral_amount).await.unwrap();
+
+    let destination_liquidity_pubkey = create_destination(&mut test).await;
+    cancel(&mut test, true, ticket, destination_liquidity_pubkey)
+        .await
+        .unwrap();
+    assert_eq!(
+        fulfill(&mut test, ticket).await.unwrap_err(),
+        lending_error(LendingError::InvalidAccountInput)
+    );
+
+    // the request keeps its place and is paid to the new destination
+    deposit(&mut test, USDC_DEPOSIT_AMOUNT_FRACTIONAL).await;
+    fulfill_to(&mut test, ticket, destination_liquidity_pubkey)
+        .await
+        .unwrap();
+    assert!(!redeem_request_exists(&mut test, ticket).await);
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, destination_liquidity_pubkey).await,
+        5 * FRACTIONAL_TO_USDC
+    );
+}
+
+#[tokio::test]
+async fn test_skip_unpayable_head() {
+    let mut test = setup().await;
+
+    let initial_user_collateral_balance = get_token_balance(
+        &mut test.banks_client,
+        test.usdc_test_reserve.user_collateral_pubkey,
+    )
+    .await;
+
+    let collateral_amount = 5 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+    let destination_liquidity_pubkey = create_destination(&mut test).await;
+    let ticket = request_to(&mut test, collateral_amount, destination_liquidity_pubkey)
+        .await
+        .unwrap();
+    let next_ticket = request(&mut test, INITIAL_COLLATERAL_RATIO).await.unwrap();
+
+    // the destination of the head request is closed
+    let instruction = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &destination_liquidity_pubkey,
+        &test.payer.pubkey(),
+        &test.user_accounts_owner.pubkey(),
+        &[],
+    )
+    .unwrap();
+    process(&mut test, instruction).await.unwrap();
+
+    // the head is skipped rather than blocking the request behind it
+    fulfill_to(&mut test, ticket, destination_liquidity_pubkey)
+        .await
+        .unwrap();
+    assert!(redeem_request_exists(&mut test, ticket).await);
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.redeem_queue.head_ticket, next_ticket);
+    assert_eq!(
+        reserve.redeem_queue.collateral_amount,
+        INITIAL_COLLATERAL_RATIO
+    );
+    fulfill(&mut test, next_ticket).await.unwrap();
+
+    // the skipped request can't be rerouted back into the queue, its owner
+    // cancels it to get the collateral back
+    let user_liquidity_pubkey = test.usdc_test_reserve.user_liquidity_pubkey;
+    assert_eq!(
+        cancel(&mut test, true, ticket, user_liquidity_pubkey)
+            .await
+            .unwrap_err(),
+        lending_error(LendingError::InvalidAccountInput)
+    );
+    let user_collateral_pubkey = test.usdc_test_reserve.user_collateral_pubkey;
+    cancel(&mut test, false, ticket, user_collateral_pubkey)
+        .await
+        .unwrap();
+    assert!(!redeem_request_exists(&mut test, ticket).await);
+    assert_eq!(
+        get_token_balance(&mut test.banks_client, user_collateral_pubkey).await,
+        initial_user_collateral_balance - INITIAL_COLLATERAL_RATIO
+    );
+}
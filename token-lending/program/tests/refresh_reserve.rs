@@ -7,14 +7,16 @@ use {
     helpers::*,
     solana_program_test::*,
     solana_sdk::{
+        instruction::InstructionError,
         signature::{Keypair, Signer},
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
     },
     spl_token_lending::{
+        error::LendingError,
         instruction::refresh_reserve,
         math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
         processor::process_instruction,
-        state::SLOTS_PER_YEAR,
+        state::{OracleKind, ReserveConfig, SLOTS_PER_YEAR},
     },
 };
 
@@ -140,3 +142,227 @@ async fn test_success() {
         usdc_test_reserve.market_price
     );
 }
+
+// Refreshes a SOL reserve at the given slot, with an oracle price published
+// at slot 0
+async fn refresh_sol_reserve_at_slot(
+    add_oracle: fn(&mut ProgramTest) -> TestOracle,
+    reserve_config: ReserveConfig,
+    slot: u64,
+) -> (Result<(), TransactionError>, TestReserve, BanksClient) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 100 * LAMPORTS_TO_SOL,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: reserve_config,
+            slots_elapsed: 1,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(slot).unwrap();
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        last_blockhash: recent_blockhash,
+        ..
+    } = test_context;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserve(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            sol_oracle.price_pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap());
+
+    (result, sol_test_reserve, banks_client)
+}
+
+#[tokio::test]
+async fn test_success_with_switchboard_oracle() {
+    let (result, sol_test_reserve, mut banks_client) =
+        refresh_sol_reserve_at_slot(add_sol_switchboard_oracle, TEST_RESERVE_CONFIG, 3).await;
+    assert_eq!(result, Ok(()));
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.liquidity.oracle_kind, OracleKind::Switchboard);
+    assert_eq!(sol_reserve.liquidity.market_price, Decimal::from(20u64));
+    assert_eq!(sol_reserve.last_update.slot, 3);
+}
+
+#[tokio::test]
+async fn test_stale_switchboard_round() {
+    let (result, _sol_test_reserve, _banks_client) =
+        refresh_sol_reserve_at_slot(add_sol_switchboard_oracle, TEST_RESERVE_CONFIG, 300).await;
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_success_within_max_oracle_age() {
+    let reserve_config = ReserveConfig {
+        max_oracle_age_slots: 10,
+        ..TEST_RESERVE_CONFIG
+    };
+    let (result, sol_test_reserve, mut banks_client) =
+        refresh_sol_reserve_at_slot(add_sol_oracle, reserve_config, 9).await;
+    assert_eq!(result, Ok(()));
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.last_update.slot, 9);
+}
+
+#[tokio::test]
+async fn test_oracle_price_older_than_max_oracle_age() {
+    let reserve_config = ReserveConfig {
+        max_oracle_age_slots: 10,
+        ..TEST_RESERVE_CONFIG
+    };
+    let (result, _sol_test_reserve, _banks_client) =
+        refresh_sol_reserve_at_slot(add_sol_oracle, reserve_config, 10).await;
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_success_within_max_confidence_interval() {
+    let reserve_config = ReserveConfig {
+        max_confidence_interval_bps: 100,
+        ..TEST_RESERVE_CONFIG
+    };
+    // $0.10 on a $20 price is 50 basis points
+    let (result, sol_test_reserve, mut banks_client) = refresh_sol_reserve_at_slot(
+        |test| add_sol_oracle_with_confidence(test, Decimal::from_percent(10)),
+        reserve_config,
+        3,
+    )
+    .await;
+    assert_eq!(result, Ok(()));
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.liquidity.market_price, Decimal::from(20u64));
+}
+
+#[tokio::test]
+async fn test_oracle_confidence_wider_than_max_confidence_interval() {
+    let reserve_config = ReserveConfig {
+        max_confidence_interval_bps: 100,
+        ..TEST_RESERVE_CONFIG
+    };
+    // $1 on a $20 price is 500 basis points
+    let (result, _sol_test_reserve, _banks_client) = refresh_sol_reserve_at_slot(
+        |test| add_sol_oracle_with_confidence(test, Decimal::one()),
+        reserve_config,
+        3,
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::PriceConfidenceTooWide as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_wide_confidence_without_max_confidence_interval() {
+    // 0 disables the check, for reserves configured before it existed
+    let reserve_config = ReserveConfig {
+        max_confidence_interval_bps: 0,
+        ..TEST_RESERVE_CONFIG
+    };
+    // $10 on a $20 price is 5000 basis points
+    let (result, sol_test_reserve, mut banks_client) = refresh_sol_reserve_at_slot(
+        |test| add_sol_oracle_with_confidence(test, Decimal::from(10u64)),
+        reserve_config,
+        3,
+    )
+    .await;
+    assert_eq!(result, Ok(()));
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.liquidity.market_price, Decimal::from(20u64));
+}
+
+#[tokio::test]
+async fn test_oracle_confidence_just_wider_than_max_confidence_interval() {
+    let reserve_config = ReserveConfig {
+        max_confidence_interval_bps: 100,
+        ..TEST_RESERVE_CONFIG
+    };
+    // $0.20 is exactly 100 basis points of the $20 price and is accepted
+    let (result, _sol_test_reserve, _banks_client) = refresh_sol_reserve_at_slot(
+        |test| add_sol_oracle_with_confidence(test, Decimal::from_percent(20)),
+        reserve_config,
+        3,
+    )
+    .await;
+    assert_eq!(result, Ok(()));
+
+    // $0.21 is 105 basis points
+    let (result, _sol_test_reserve, _banks_client) = refresh_sol_reserve_at_slot(
+        |test| add_sol_oracle_with_confidence(test, Decimal::from_percent(21)),
+        reserve_config,
+        3,
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::PriceConfidenceTooWide as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_rate_history_sampled() {
+    let reserve_config = ReserveConfig {
+        rate_history_interval_slots: 100,
+        ..TEST_RESERVE_CONFIG
+    };
+    let (result, sol_test_reserve, mut banks_client) =
+        refresh_sol_reserve_at_slot(add_sol_oracle, reserve_config, 3).await;
+    assert_eq!(result, Ok(()));
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.rate_samples.last_sample_slot, 3);
+    assert_eq!(sol_reserve.rate_history().len(), 1);
+    assert_eq!(sol_reserve.rate_history()[0].utilization_rate_bps, 0);
+}
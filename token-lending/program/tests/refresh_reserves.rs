@@ -0,0 +1,164 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError, instruction::refresh_reserves, math::Decimal,
+        processor::process_instruction, state::ReserveConfig,
+    },
+};
+
+const RESERVES: usize = 5;
+
+struct RefreshReservesTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    test_reserves: Vec<TestReserve>,
+}
+
+// Adds RESERVES reserves alternating between the SOL and USDC oracles, last
+// updated at slot 2. All but the last one accept oracle prices up to 10 slots
+// old, the last one uses `last_reserve_config`.
+async fn setup(last_reserve_config: ReserveConfig, slot: u64) -> RefreshReservesTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // the batch must fit the default compute budget of a single instruction
+    // transaction
+    test.set_compute_max_units(200_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+
+    let test_reserves = (0..RESERVES)
+        .map(|index| {
+            let config = if index == RESERVES - 1 {
+                last_reserve_config
+            } else {
+                ReserveConfig {
+                    max_oracle_age_slots: 10,
+                    ..TEST_RESERVE_CONFIG
+                }
+            };
+            let (oracle, liquidity_mint_pubkey, liquidity_mint_decimals) = if index % 2 == 0 {
+                (&sol_oracle, spl_token::native_mint::id(), 9)
+            } else {
+                (&usdc_oracle, usdc_mint.pubkey, usdc_mint.decimals)
+            };
+            add_reserve(
+                &mut test,
+                &lending_market,
+                oracle,
+                &user_accounts_owner,
+                AddReserveArgs {
+                    borrow_amount: 100,
+                    liquidity_amount: 1_000_000,
+                    liquidity_mint_pubkey,
+                    liquidity_mint_decimals,
+                    config,
+                    slots_elapsed: 1, // elapsed from 1; clock.slot = 2
+                    ..AddReserveArgs::default()
+                },
+            )
+        })
+        .collect();
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(slot).unwrap();
+
+    RefreshReservesTest {
+        banks_client: test_context.banks_client,
+        payer: test_context.payer,
+        test_reserves,
+    }
+}
+
+fn reserve_and_oracle_pubkeys(test: &RefreshReservesTest) -> Vec<(Pubkey, Pubkey)> {
+    test.test_reserves
+        .iter()
+        .map(|test_reserve| (test_reserve.pubkey, test_reserve.liquidity_oracle_pubkey))
+        .collect()
+}
+
+async fn process(
+    test: &mut RefreshReservesTest,
+    reserves: &[(Pubkey, Pubkey)],
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserves(spl_token_lending::id(), reserves)],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup(TEST_RESERVE_CONFIG, 3).await;
+
+    let reserves = reserve_and_oracle_pubkeys(&test);
+    process(&mut test, &reserves).await.unwrap();
+
+    for test_reserve in &test.test_reserves {
+        let reserve = test_reserve.get_state(&mut test.banks_client).await;
+        assert_eq!(reserve.last_update.slot, 3);
+        assert!(!reserve.last_update.stale);
+        assert!(reserve.liquidity.cumulative_borrow_rate_wads > Decimal::one());
+    }
+}
+
+#[tokio::test]
+async fn test_stale_oracle_fails_all_reserves() {
+    // only the last reserve's price is older than its default Pyth max age
+    let mut test = setup(TEST_RESERVE_CONFIG, 9).await;
+
+    let reserves = reserve_and_oracle_pubkeys(&test);
+    assert_eq!(
+        process(&mut test, &reserves).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+
+    for test_reserve in &test.test_reserves {
+        let reserve = test_reserve.get_state(&mut test.banks_client).await;
+        assert_eq!(reserve.last_update.slot, 2);
+    }
+}
+
+#[tokio::test]
+async fn test_mismatched_oracle() {
+    let mut test = setup(TEST_RESERVE_CONFIG, 3).await;
+
+    let mut reserves = reserve_and_oracle_pubkeys(&test);
+    reserves[1].1 = reserves[0].1;
+    assert_eq!(
+        process(&mut test, &reserves).await.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+}
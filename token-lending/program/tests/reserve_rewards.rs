@@ -0,0 +1,271 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::state::{Account as Token, AccountState},
+    spl_token_lending::{
+        instruction::{claim_reserve_rewards, refresh_reserve, set_reserve_rewards},
+        math::{Decimal, TryDiv},
+        processor::process_instruction,
+        state::{
+            find_reward_authority_address, find_reward_checkpoint_address, ReserveConfig,
+            MAX_ORACLE_AGE_SLOTS,
+        },
+    },
+};
+
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 50 * FRACTIONAL_TO_USDC;
+const REWARD_VAULT_AMOUNT: u64 = 1_000_000;
+
+struct RewardsTest {
+    context: ProgramTestContext,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_oracle: TestOracle,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+    reward_vault_pubkey: Pubkey,
+    destination_pubkey: Pubkey,
+}
+
+// Adds a USDC reserve whose collateral is half deposited in an obligation,
+// with a reward vault funded in USDC
+async fn setup() -> RewardsTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                // the oracle price is from slot 0
+                max_oracle_age_slots: MAX_ORACLE_AGE_SLOTS,
+                ..TEST_RESERVE_CONFIG
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&usdc_test_reserve, USDC_DEPOSIT_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (reward_authority_pubkey, _bump_seed) =
+        find_reward_authority_address(&spl_token_lending::id(), &usdc_test_reserve.pubkey);
+    let reward_vault_pubkey = Pubkey::new_unique();
+    test.add_packable_account(
+        reward_vault_pubkey,
+        u32::MAX as u64,
+        &Token {
+            mint: usdc_mint.pubkey,
+            owner: reward_authority_pubkey,
+            amount: REWARD_VAULT_AMOUNT,
+            state: AccountState::Initialized,
+            ..Token::default()
+        },
+        &spl_token::id(),
+    );
+    let destination_pubkey = Pubkey::new_unique();
+    test.add_packable_account(
+        destination_pubkey,
+        u32::MAX as u64,
+        &Token {
+            mint: usdc_mint.pubkey,
+            owner: user_accounts_owner.pubkey(),
+            state: AccountState::Initialized,
+            ..Token::default()
+        },
+        &spl_token::id(),
+    );
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(1_000).unwrap(); // clock.slot = 1_000
+
+    RewardsTest {
+        context,
+        user_accounts_owner,
+        lending_market,
+        usdc_oracle,
+        usdc_test_reserve,
+        test_obligation,
+        reward_vault_pubkey,
+        destination_pubkey,
+    }
+}
+
+// Refreshes the reserve ahead of the instruction
+async fn process_refreshed(
+    test: &mut RewardsTest,
+    instruction: Instruction,
+    signer: &Keypair,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+            instruction,
+        ],
+        Some(&test.context.payer.pubkey()),
+    );
+    transaction.sign(&[&test.context.payer, signer], test.context.last_blockhash);
+    test.context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+// Claims repeat the same transaction, so a fresh blockhash keeps them apart
+async fn warp_to_slot(test: &mut RewardsTest, slot: u64) {
+    test.context.warp_to_slot(slot).unwrap();
+    test.context.last_blockhash = test.context.get_new_latest_blockhash().await.unwrap();
+}
+
+async fn set_rewards(test: &mut RewardsTest, rewards_per_slot: u64) {
+    let instruction = set_reserve_rewards(
+        spl_token_lending::id(),
+        rewards_per_slot,
+        test.usdc_test_reserve.pubkey,
+        test.reward_vault_pubkey,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+    );
+    let owner = test.lending_market.owner.insecure_clone();
+    process_refreshed(test, instruction, &owner).await.unwrap();
+}
+
+async fn claim(test: &mut RewardsTest) {
+    let instruction = claim_reserve_rewards(
+        spl_token_lending::id(),
+        test.test_obligation.pubkey,
+        test.usdc_test_reserve.pubkey,
+        test.reward_vault_pubkey,
+        test.destination_pubkey,
+        test.lending_market.pubkey,
+        test.user_accounts_owner.pubkey(),
+        test.context.payer.pubkey(),
+    );
+    let owner = test.user_accounts_owner.insecure_clone();
+    process_refreshed(test, instruction, &owner).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_claim_accrued_rewards() {
+    let mut test = setup().await;
+
+    set_rewards(&mut test, 1_000).await;
+
+    // the first claim only checkpoints the deposit
+    claim(&mut test).await;
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.destination_pubkey).await,
+        0
+    );
+    let (reward_checkpoint_pubkey, _bump_seed) = find_reward_checkpoint_address(
+        &spl_token_lending::id(),
+        &test.test_obligation.pubkey,
+        &test.usdc_test_reserve.pubkey,
+    );
+    assert!(test
+        .context
+        .banks_client
+        .get_account(reward_checkpoint_pubkey)
+        .await
+        .unwrap()
+        .is_some());
+
+    // the obligation holds half of the collateral supply over 100 slots
+    warp_to_slot(&mut test, 1_100).await; // clock.slot = 1_100
+    claim(&mut test).await;
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.context.banks_client)
+        .await;
+    assert_eq!(
+        usdc_reserve.reward_pool.cumulative_rewards_per_collateral,
+        Decimal::from(100_000u64)
+            .try_div(USDC_RESERVE_LIQUIDITY_FRACTIONAL)
+            .unwrap()
+    );
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.destination_pubkey).await,
+        50_000
+    );
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.reward_vault_pubkey).await,
+        REWARD_VAULT_AMOUNT - 50_000
+    );
+
+    // claiming again in a later slot pays only the rewards since the last claim
+    warp_to_slot(&mut test, 1_150).await; // clock.slot = 1_150
+    claim(&mut test).await;
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.destination_pubkey).await,
+        75_000
+    );
+}
+
+#[tokio::test]
+async fn test_zero_rewards_accrue_nothing() {
+    let mut test = setup().await;
+
+    set_rewards(&mut test, 0).await;
+    claim(&mut test).await;
+
+    warp_to_slot(&mut test, 1_100).await; // clock.slot = 1_100
+    claim(&mut test).await;
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.context.banks_client)
+        .await;
+    assert_eq!(
+        usdc_reserve.reward_pool.cumulative_rewards_per_collateral,
+        Decimal::zero()
+    );
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.destination_pubkey).await,
+        0
+    );
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, test.reward_vault_pubkey).await,
+        REWARD_VAULT_AMOUNT
+    );
+}
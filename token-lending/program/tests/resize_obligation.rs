@@ -0,0 +1,494 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::instruction::Instruction,
+    solana_program_test::*,
+    solana_sdk::{
+        hash::Hash,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral, liquidate_obligation,
+            refresh_obligation, refresh_reserve, repay_obligation_liquidity, resize_obligation,
+        },
+        processor::process_instruction,
+        state::{Obligation, INITIAL_COLLATERAL_RATIO, MAX_OBLIGATION_RESERVES},
+    },
+};
+
+const RESERVE_COUNT: usize = MAX_OBLIGATION_RESERVES + 2;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+// $100 of collateral in each deposit reserve
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 10 * FRACTIONAL_TO_USDC;
+
+struct ResizeTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    test_reserves: Vec<TestReserve>,
+    test_obligation: TestObligation,
+}
+
+// Adds RESERVE_COUNT USDC reserves, with an obligation depositing into the
+// first `deposits` reserves and borrowing `borrow_amount` from each of the
+// next `borrows` reserves
+async fn setup(deposits: usize, borrows: usize, borrow_amount: u64) -> ResizeTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.liquidation_threshold = 80;
+    reserve_config.liquidation_bonus = 10;
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let test_reserves: Vec<TestReserve> = (0..RESERVE_COUNT)
+        .map(|index| {
+            let is_borrow_reserve = index >= deposits && index < deposits + borrows;
+            add_reserve(
+                &mut test,
+                &lending_market,
+                &usdc_oracle,
+                &user_accounts_owner,
+                AddReserveArgs {
+                    borrow_amount: if is_borrow_reserve { borrow_amount } else { 0 },
+                    user_liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+                    liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+                    collateral_amount: 2 * USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                    liquidity_mint_pubkey: usdc_mint.pubkey,
+                    liquidity_mint_decimals: usdc_mint.decimals,
+                    config: reserve_config,
+                    mark_fresh: true,
+                    ..AddReserveArgs::default()
+                },
+            )
+        })
+        .collect();
+
+    let obligation_deposits: Vec<(&TestReserve, u64)> = test_reserves[..deposits]
+        .iter()
+        .map(|reserve| (reserve, USDC_DEPOSIT_AMOUNT_FRACTIONAL))
+        .collect();
+    let obligation_borrows: Vec<(&TestReserve, u64)> = test_reserves[deposits..deposits + borrows]
+        .iter()
+        .map(|reserve| (reserve, borrow_amount))
+        .collect();
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &obligation_deposits,
+            borrows: &obligation_borrows,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    ResizeTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        lending_market,
+        test_reserves,
+        test_obligation,
+    }
+}
+
+// Refreshes the obligation with its deposit reserves followed by its borrow
+// reserves, in the order they are stored
+async fn refresh(test: &mut ResizeTest) -> Instruction {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    refresh_obligation(
+        spl_token_lending::id(),
+        test.test_obligation.pubkey,
+        obligation
+            .deposits
+            .iter()
+            .map(|collateral| collateral.deposit_reserve)
+            .chain(
+                obligation
+                    .borrows
+                    .iter()
+                    .map(|liquidity| liquidity.borrow_reserve),
+            )
+            .collect(),
+    )
+}
+
+async fn resize(
+    test: &mut ResizeTest,
+    additional_reserves: u8,
+    owner: Option<&Keypair>,
+) -> Result<(), TransactionError> {
+    let owner = owner.unwrap_or(&test.user_accounts_owner);
+    let mut transaction = Transaction::new_with_payer(
+        &[resize_obligation(
+            spl_token_lending::id(),
+            additional_reserves,
+            test.test_obligation.pubkey,
+            owner.pubkey(),
+            test.payer.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(&[&test.payer, owner], test.recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn borrow(test: &mut ResizeTest, reserve_index: usize) -> Result<(), TransactionError> {
+    let refresh_obligation = refresh(test).await;
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let borrow_reserve = &test.test_reserves[reserve_index];
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation,
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                None,
+                borrow_reserve.liquidity_supply_pubkey,
+                borrow_reserve.user_liquidity_pubkey,
+                borrow_reserve.pubkey,
+                borrow_reserve.liquidity_fee_receiver_pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                test.user_accounts_owner.pubkey(),
+                obligation
+                    .deposits
+                    .iter()
+                    .map(|collateral| collateral.deposit_reserve)
+                    .collect(),
+                None,
+                None,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.user_accounts_owner],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn deposit(test: &mut ResizeTest, reserve_index: usize) -> Result<(), TransactionError> {
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    let deposit_reserve = &test.test_reserves[reserve_index];
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &deposit_reserve.user_collateral_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_obligation_collateral(
+                spl_token_lending::id(),
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                deposit_reserve.user_collateral_pubkey,
+                deposit_reserve.collateral_supply_pubkey,
+                deposit_reserve.pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                test.user_accounts_owner.pubkey(),
+                user_transfer_authority.pubkey(),
+                obligation
+                    .deposits
+                    .iter()
+                    .map(|collateral| collateral.deposit_reserve)
+                    .collect(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    // the obligation starts with as many reserves as it can hold
+    let mut test = setup(5, 5, USDC_BORROW_AMOUNT_FRACTIONAL).await;
+    let new_borrow_index = MAX_OBLIGATION_RESERVES;
+
+    assert_eq!(
+        borrow(&mut test, new_borrow_index).await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        ))
+    );
+
+    assert_eq!(resize(&mut test, 2, None).await, Ok(()));
+    let obligation_account = test
+        .banks_client
+        .get_account(test.test_obligation.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        obligation_account.data.len(),
+        Obligation::account_len(MAX_OBLIGATION_RESERVES + 2)
+    );
+    let rent = test.banks_client.get_rent().await.unwrap();
+    assert!(rent.is_exempt(obligation_account.lamports, obligation_account.data.len()));
+
+    // the retried borrow needs a new blockhash to not be a duplicate transaction
+    test.recent_blockhash = test
+        .banks_client
+        .get_new_latest_blockhash(&test.recent_blockhash)
+        .await
+        .unwrap();
+    assert_eq!(borrow(&mut test, new_borrow_index).await, Ok(()));
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 5);
+    assert_eq!(obligation.borrows.len(), 6);
+    assert_eq!(
+        obligation.borrows[5].borrow_reserve,
+        test.test_reserves[new_borrow_index].pubkey
+    );
+
+    // repay the new borrow in full
+    let borrow_reserve = &test.test_reserves[new_borrow_index];
+    let refresh_borrow_reserve = refresh_reserve(
+        spl_token_lending::id(),
+        borrow_reserve.pubkey,
+        borrow_reserve.liquidity_oracle_pubkey,
+    );
+    let refresh_obligation = refresh(&mut test).await;
+    let borrow_reserve = &test.test_reserves[new_borrow_index];
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &borrow_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            )
+            .unwrap(),
+            refresh_borrow_reserve,
+            refresh_obligation,
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                u64::MAX,
+                borrow_reserve.user_liquidity_pubkey,
+                borrow_reserve.liquidity_supply_pubkey,
+                borrow_reserve.pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.borrows.len(), 5);
+    assert!(obligation
+        .borrows
+        .iter()
+        .all(|liquidity| liquidity.borrow_reserve != borrow_reserve.pubkey));
+}
+
+#[tokio::test]
+async fn test_deposit() {
+    // the obligation starts with as many reserves as it can hold
+    let mut test = setup(5, 5, USDC_BORROW_AMOUNT_FRACTIONAL).await;
+    let new_deposit_index = MAX_OBLIGATION_RESERVES;
+
+    assert_eq!(
+        deposit(&mut test, new_deposit_index).await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        ))
+    );
+
+    assert_eq!(resize(&mut test, 1, None).await, Ok(()));
+    assert_eq!(deposit(&mut test, new_deposit_index).await, Ok(()));
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 6);
+    assert_eq!(obligation.borrows.len(), 5);
+    assert_eq!(
+        obligation.deposits[5].deposit_reserve,
+        test.test_reserves[new_deposit_index].pubkey
+    );
+    assert_eq!(
+        obligation.deposits[5].deposited_amount,
+        USDC_DEPOSIT_AMOUNT_FRACTIONAL
+    );
+
+    // the resized obligation is full again
+    assert_eq!(
+        deposit(&mut test, new_deposit_index + 1).await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_liquidate() {
+    // $600 of collateral with an unhealthy borrow value of $480, borrowing $540
+    let mut test = setup(6, 6, 90 * FRACTIONAL_TO_USDC).await;
+    let initial_obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert!(
+        initial_obligation.deposits.len() + initial_obligation.borrows.len()
+            > MAX_OBLIGATION_RESERVES
+    );
+
+    let refresh_obligation = refresh(&mut test).await;
+    let repay_reserve = &test.test_reserves[6];
+    let withdraw_reserve = &test.test_reserves[0];
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &repay_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            refresh_obligation,
+            liquidate_obligation(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                repay_reserve.user_liquidity_pubkey,
+                withdraw_reserve.user_collateral_pubkey,
+                repay_reserve.pubkey,
+                repay_reserve.liquidity_supply_pubkey,
+                withdraw_reserve.pubkey,
+                withdraw_reserve.collateral_supply_pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+                None,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.deposits.len(), 6);
+    assert_eq!(obligation.borrows.len(), 6);
+    assert!(
+        obligation.deposits[0].deposited_amount < initial_obligation.deposits[0].deposited_amount
+    );
+    assert!(
+        obligation.borrows[0].borrowed_amount_wads
+            < initial_obligation.borrows[0].borrowed_amount_wads
+    );
+    assert!(obligation.deposits[1..]
+        .iter()
+        .all(|collateral| collateral.deposited_amount == USDC_DEPOSIT_AMOUNT_FRACTIONAL));
+}
+
+#[tokio::test]
+async fn test_invalid_obligation_owner() {
+    let mut test = setup(1, 1, USDC_BORROW_AMOUNT_FRACTIONAL).await;
+
+    assert_eq!(
+        resize(&mut test, 1, Some(&Keypair::new())).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_resize_limit() {
+    let mut test = setup(1, 1, USDC_BORROW_AMOUNT_FRACTIONAL).await;
+
+    assert_eq!(
+        resize(&mut test, 0, None).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAmount as u32)
+        ))
+    );
+
+    // deposits and borrows are each counted in a byte
+    assert_eq!(
+        resize(&mut test, u8::MAX, None).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        ))
+    );
+}
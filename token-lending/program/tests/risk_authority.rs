@@ -0,0 +1,307 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            modify_reserve_config, set_lending_market_risk_authority, set_reserve_pause_flags,
+        },
+        processor::process_instruction,
+        state::{ReserveConfig, RESERVE_PAUSE_BORROWS, RESERVE_PAUSE_DEPOSITS},
+    },
+};
+
+struct RiskAuthorityTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    risk_authority: Keypair,
+}
+
+// Adds a USDC reserve, then sets the market risk authority
+async fn setup() -> RiskAuthorityTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 1_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    let mut test = RiskAuthorityTest {
+        banks_client,
+        payer,
+        lending_market,
+        usdc_test_reserve,
+        risk_authority: Keypair::new(),
+    };
+    let instruction = set_lending_market_risk_authority(
+        spl_token_lending::id(),
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.risk_authority.pubkey(),
+    );
+    let owner = test.lending_market.owner.insecure_clone();
+    process(&mut test, instruction, &owner).await.unwrap();
+
+    test
+}
+
+async fn process(
+    test: &mut RiskAuthorityTest,
+    instruction: Instruction,
+    authority: &Keypair,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&test.payer.pubkey()));
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, authority], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn modify_config(
+    test: &mut RiskAuthorityTest,
+    config: ReserveConfig,
+    authority: &Keypair,
+) -> Result<(), TransactionError> {
+    let instruction = modify_reserve_config(
+        spl_token_lending::id(),
+        config,
+        test.usdc_test_reserve.pubkey,
+        test.lending_market.pubkey,
+        authority.pubkey(),
+    );
+    process(test, instruction, authority).await
+}
+
+async fn set_pause_flags(
+    test: &mut RiskAuthorityTest,
+    pause_flags: u8,
+    authority: &Keypair,
+) -> Result<(), TransactionError> {
+    let instruction = set_reserve_pause_flags(
+        spl_token_lending::id(),
+        pause_flags,
+        test.usdc_test_reserve.pubkey,
+        test.lending_market.pubkey,
+        authority.pubkey(),
+    );
+    process(test, instruction, authority).await
+}
+
+fn lending_error(error: LendingError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn test_tighten() {
+    let mut test = setup().await;
+    let risk_authority = test.risk_authority.insecure_clone();
+
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert_eq!(lending_market.risk_authority, risk_authority.pubkey());
+
+    let tightened_config = ReserveConfig {
+        loan_to_value_ratio: 40,
+        liquidation_threshold: 50,
+        borrow_limit: 500 * FRACTIONAL_TO_USDC,
+        max_borrow_rate: 40,
+        ..TEST_RESERVE_CONFIG
+    };
+    modify_config(&mut test, tightened_config, &risk_authority)
+        .await
+        .unwrap();
+    set_pause_flags(&mut test, RESERVE_PAUSE_BORROWS, &risk_authority)
+        .await
+        .unwrap();
+    set_pause_flags(
+        &mut test,
+        RESERVE_PAUSE_BORROWS | RESERVE_PAUSE_DEPOSITS,
+        &risk_authority,
+    )
+    .await
+    .unwrap();
+
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.config, tightened_config);
+    assert_eq!(
+        reserve.pause_flags,
+        RESERVE_PAUSE_BORROWS | RESERVE_PAUSE_DEPOSITS
+    );
+}
+
+#[tokio::test]
+async fn test_loosen_config() {
+    let mut test = setup().await;
+    let risk_authority = test.risk_authority.insecure_clone();
+
+    let loosened_configs = [
+        ReserveConfig {
+            loan_to_value_ratio: 51,
+            ..TEST_RESERVE_CONFIG
+        },
+        ReserveConfig {
+            liquidation_threshold: 60,
+            ..TEST_RESERVE_CONFIG
+        },
+        ReserveConfig {
+            liquidation_bonus: 6,
+            max_liquidation_bonus: 6,
+            ..TEST_RESERVE_CONFIG
+        },
+        // a lower max borrow rate flattens the curve past the optimal
+        // utilization rate
+        ReserveConfig {
+            max_borrow_rate: 20,
+            ..TEST_RESERVE_CONFIG
+        },
+        ReserveConfig {
+            max_oracle_age_slots: 100,
+            ..TEST_RESERVE_CONFIG
+        },
+        // a tightening doesn't cover loosening another config
+        ReserveConfig {
+            loan_to_value_ratio: 40,
+            borrow_rate_rebate: 1,
+            ..TEST_RESERVE_CONFIG
+        },
+    ];
+    for config in loosened_configs {
+        assert_eq!(
+            modify_config(&mut test, config, &risk_authority)
+                .await
+                .unwrap_err(),
+            lending_error(LendingError::RiskAuthorityCannotLoosen)
+        );
+    }
+
+    let reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert_eq!(reserve.config, TEST_RESERVE_CONFIG);
+}
+
+#[tokio::test]
+async fn test_loosen_tightened_config() {
+    let mut test = setup().await;
+    let risk_authority = test.risk_authority.insecure_clone();
+
+    let tightened_config = ReserveConfig {
+        borrow_limit: 500 * FRACTIONAL_TO_USDC,
+        ..TEST_RESERVE_CONFIG
+    };
+    modify_config(&mut test, tightened_config, &risk_authority)
+        .await
+        .unwrap();
+    assert_eq!(
+        modify_config(&mut test, TEST_RESERVE_CONFIG, &risk_authority)
+            .await
+            .unwrap_err(),
+        lending_error(LendingError::RiskAuthorityCannotLoosen)
+    );
+
+    // the owner can still loosen it
+    let owner = test.lending_market.owner.insecure_clone();
+    modify_config(&mut test, TEST_RESERVE_CONFIG, &owner)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_clear_pause_flags() {
+    let mut test = setup().await;
+    let risk_authority = test.risk_authority.insecure_clone();
+
+    set_pause_flags(
+        &mut test,
+        RESERVE_PAUSE_BORROWS | RESERVE_PAUSE_DEPOSITS,
+        &risk_authority,
+    )
+    .await
+    .unwrap();
+    for pause_flags in [0, RESERVE_PAUSE_BORROWS] {
+        assert_eq!(
+            set_pause_flags(&mut test, pause_flags, &risk_authority)
+                .await
+                .unwrap_err(),
+            lending_error(LendingError::RiskAuthorityCannotLoosen)
+        );
+    }
+
+    let owner = test.lending_market.owner.insecure_clone();
+    set_pause_flags(&mut test, 0, &owner).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_authority() {
+    let mut test = setup().await;
+    let invalid_authority = Keypair::new();
+
+    let tightened_config = ReserveConfig {
+        loan_to_value_ratio: 40,
+        ..TEST_RESERVE_CONFIG
+    };
+    assert_eq!(
+        modify_config(&mut test, tightened_config, &invalid_authority)
+            .await
+            .unwrap_err(),
+        lending_error(LendingError::InvalidMarketOwner)
+    );
+    assert_eq!(
+        set_pause_flags(&mut test, RESERVE_PAUSE_BORROWS, &invalid_authority)
+            .await
+            .unwrap_err(),
+        lending_error(LendingError::InvalidMarketOwner)
+    );
+
+    // only the owner can set the risk authority
+    let risk_authority = test.risk_authority.insecure_clone();
+    let instruction = set_lending_market_risk_authority(
+        spl_token_lending::id(),
+        test.lending_market.pubkey,
+        risk_authority.pubkey(),
+        invalid_authority.pubkey(),
+    );
+    assert_eq!(
+        process(&mut test, instruction, &risk_authority)
+            .await
+            .unwrap_err(),
+        lending_error(LendingError::InvalidMarketOwner)
+    );
+}
@@ -15,7 +15,7 @@ use {
     },
     spl_token_lending::{
         error::LendingError,
-        instruction::{set_lending_market_owner, LendingInstruction},
+        instruction::{propose_lending_market_owner, set_lending_market_owner, LendingInstruction},
         processor::process_instruction,
     },
 };
@@ -57,6 +57,44 @@ async fn test_success() {
     assert_eq!(lending_market_info.owner, new_owner);
 }
 
+#[tokio::test]
+async fn test_clears_pending_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let pending_owner = Pubkey::new_unique();
+    let new_owner = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            propose_lending_market_owner(
+                spl_token_lending::id(),
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+                pending_owner,
+            ),
+            set_lending_market_owner(
+                spl_token_lending::id(),
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+                new_owner,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let lending_market_info = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(lending_market_info.owner, new_owner);
+    assert_eq!(lending_market_info.pending_owner, Pubkey::default());
+}
+
 #[tokio::test]
 async fn test_invalid_owner() {
     let mut test = ProgramTest::new(
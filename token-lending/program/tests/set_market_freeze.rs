@@ -0,0 +1,293 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            deposit_reserve_liquidity, refresh_obligation, repay_obligation_liquidity,
+            set_lending_market_emergency_authority, set_market_freeze,
+        },
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const USDC_DEPOSIT_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+// Adds a SOL reserve and a USDC reserve, with an obligation holding SOL
+// collateral and borrowing USDC, then sets the market emergency authority
+async fn setup(emergency_authority: &Keypair) -> SolUsdcObligationTest {
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    let mut test = start_sol_usdc_obligation(
+        test,
+        AddSolUsdcObligationArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL
+                    + USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                liquidity_amount: 2 * USDC_BORROW_AMOUNT_FRACTIONAL,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            usdc_borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            ..AddSolUsdcObligationArgs::default()
+        },
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_lending_market_emergency_authority(
+            spl_token_lending::id(),
+            test.lending_market.pubkey,
+            test.lending_market.owner.pubkey(),
+            emergency_authority.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.lending_market.owner],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    test
+}
+
+async fn set_freeze(
+    test: &mut SolUsdcObligationTest,
+    authority: Option<&Keypair>,
+    frozen: bool,
+) -> Result<(), TransactionError> {
+    let authority = authority.unwrap_or(&test.lending_market.owner);
+    let mut transaction = Transaction::new_with_payer(
+        &[set_market_freeze(
+            spl_token_lending::id(),
+            test.lending_market.pubkey,
+            authority.pubkey(),
+            frozen,
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(&[&test.payer, authority], test.recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn deposit(test: &mut SolUsdcObligationTest) -> Result<(), TransactionError> {
+    let usdc_test_reserve = &test.usdc_test_reserve;
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                USDC_DEPOSIT_AMOUNT_FRACTIONAL,
+                None,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_emergency_freeze() {
+    let emergency_authority = Keypair::new();
+    let mut test = setup(&emergency_authority).await;
+
+    assert_eq!(
+        set_freeze(&mut test, Some(&emergency_authority), true).await,
+        Ok(())
+    );
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert!(lending_market.frozen);
+
+    assert_eq!(
+        deposit(&mut test).await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::MarketFrozen as u32)
+        ))
+    );
+
+    // borrowers can still repay while the market is frozen
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &test.usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &test.user_accounts_owner.pubkey(),
+                &[],
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                vec![test.sol_test_reserve.pubkey, test.usdc_test_reserve.pubkey],
+            ),
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                test.usdc_test_reserve.user_liquidity_pubkey,
+                test.usdc_test_reserve.liquidity_supply_pubkey,
+                test.usdc_test_reserve.pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[
+            &test.payer,
+            &test.user_accounts_owner,
+            &user_transfer_authority,
+        ],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.borrows.len(), 0);
+}
+
+#[tokio::test]
+async fn test_owner_unfreeze() {
+    let emergency_authority = Keypair::new();
+    let mut test = setup(&emergency_authority).await;
+
+    assert_eq!(
+        set_freeze(&mut test, Some(&emergency_authority), true).await,
+        Ok(())
+    );
+
+    // the emergency authority can't lift the freeze
+    assert_eq!(
+        set_freeze(&mut test, Some(&emergency_authority), false).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        ))
+    );
+
+    assert_eq!(set_freeze(&mut test, None, false).await, Ok(()));
+    let lending_market = test.lending_market.get_state(&mut test.banks_client).await;
+    assert!(!lending_market.frozen);
+
+    assert_eq!(deposit(&mut test).await, Ok(()));
+}
+
+#[tokio::test]
+async fn test_invalid_freeze_authority() {
+    let mut test = setup(&Keypair::new()).await;
+
+    assert_eq!(
+        set_freeze(&mut test, Some(&Keypair::new()), true).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_set_emergency_authority_invalid_owner() {
+    let SolUsdcObligationTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        ..
+    } = setup(&Keypair::new()).await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_lending_market_emergency_authority(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
@@ -0,0 +1,153 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::set_market_metadata,
+        processor::process_instruction,
+        state::{get_market_metadata_address, MarketMetadata, MAX_MARKET_NAME_LEN},
+    },
+};
+
+async fn setup() -> (BanksClient, Keypair, TestLendingMarket) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+    (banks_client, payer, lending_market)
+}
+
+async fn set_metadata(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    lending_market: &TestLendingMarket,
+    name: &str,
+    description_uri: &str,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[set_market_metadata(
+            spl_token_lending::id(),
+            name.to_string(),
+            description_uri.to_string(),
+            0,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[payer, &lending_market.owner], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_create_and_update() {
+    let (mut banks_client, payer, lending_market) = setup().await;
+    let market_metadata_pubkey =
+        get_market_metadata_address(&spl_token_lending::id(), &lending_market.pubkey);
+    let rent = banks_client.get_rent().await.unwrap();
+
+    set_metadata(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        "Main",
+        "https://example.com/m.json",
+    )
+    .await
+    .unwrap();
+
+    let account = banks_client
+        .get_account(market_metadata_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, spl_token_lending::id());
+    let market_metadata = MarketMetadata::unpack_account(&account.data).unwrap();
+    assert_eq!(market_metadata.lending_market, lending_market.pubkey);
+    assert_eq!(market_metadata.name, "Main");
+    assert_eq!(
+        market_metadata.description_uri,
+        "https://example.com/m.json"
+    );
+    assert!(rent.is_exempt(account.lamports, account.data.len()));
+
+    // longer strings grow the account, with the payer funding the extra rent
+    let description_uri = format!("https://example.com/{}.json", "d".repeat(150));
+    set_metadata(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        "Main isolated market",
+        &description_uri,
+    )
+    .await
+    .unwrap();
+
+    let account = banks_client
+        .get_account(market_metadata_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let market_metadata = MarketMetadata::unpack_account(&account.data).unwrap();
+    assert_eq!(account.data.len(), market_metadata.account_len());
+    assert_eq!(market_metadata.name, "Main isolated market");
+    assert_eq!(market_metadata.description_uri, description_uri);
+    assert!(rent.is_exempt(account.lamports, account.data.len()));
+}
+
+#[tokio::test]
+async fn test_name_too_long() {
+    let (mut banks_client, payer, lending_market) = setup().await;
+
+    assert_eq!(
+        set_metadata(
+            &mut banks_client,
+            &payer,
+            &lending_market,
+            &"n".repeat(MAX_MARKET_NAME_LEN + 1),
+            "",
+        )
+        .await
+        .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::MarketMetadataTooLong as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let (mut banks_client, payer, mut lending_market) = setup().await;
+    lending_market.owner = Keypair::new();
+
+    assert_eq!(
+        set_metadata(&mut banks_client, &payer, &lending_market, "Main", "")
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
@@ -0,0 +1,208 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{refresh_obligation, set_obligation_owner, withdraw_obligation_collateral},
+        processor::process_instruction,
+        state::INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 200 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const WITHDRAW_AMOUNT: u64 = 50 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+
+async fn setup() -> SolUsdcObligationTest {
+    let test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+
+    start_sol_usdc_obligation(
+        test,
+        AddSolUsdcObligationArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                config: reserve_config,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            usdc_borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            ..AddSolUsdcObligationArgs::default()
+        },
+    )
+    .await
+}
+
+// Withdraws collateral as the given owner, or as the original owner if none
+async fn withdraw(
+    test: &mut SolUsdcObligationTest,
+    owner: Option<&Keypair>,
+) -> Result<(), TransactionError> {
+    let owner = owner.unwrap_or(&test.user_accounts_owner);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test.test_obligation.pubkey,
+                vec![test.sol_test_reserve.pubkey, test.usdc_test_reserve.pubkey],
+            ),
+            withdraw_obligation_collateral(
+                spl_token_lending::id(),
+                WITHDRAW_AMOUNT,
+                test.sol_test_reserve.collateral_supply_pubkey,
+                test.sol_test_reserve.user_collateral_pubkey,
+                test.sol_test_reserve.pubkey,
+                test.test_obligation.pubkey,
+                test.lending_market.pubkey,
+                owner.pubkey(),
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(&[&test.payer, owner], test.recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup().await;
+    let initial_obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            test.test_obligation.pubkey,
+            test.user_accounts_owner.pubkey(),
+            new_owner.pubkey(),
+        )],
+        Some(&test.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.payer, &test.user_accounts_owner, &new_owner],
+        test.recent_blockhash,
+    );
+    assert!(test
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the position moves with the obligation
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(obligation.owner, new_owner.pubkey());
+    assert!(obligation.last_update.stale);
+    assert_eq!(obligation.deposits, initial_obligation.deposits);
+    assert_eq!(obligation.borrows, initial_obligation.borrows);
+
+    assert_eq!(
+        withdraw(&mut test, None).await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        ))
+    );
+
+    assert_eq!(withdraw(&mut test, Some(&new_owner)).await, Ok(()));
+    let obligation = test.test_obligation.get_state(&mut test.banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - WITHDRAW_AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_obligation_owner() {
+    let SolUsdcObligationTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        test_obligation,
+        ..
+    } = setup().await;
+
+    let invalid_owner = Keypair::new();
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            invalid_owner.pubkey(),
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner, &new_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_new_owner_not_signer() {
+    let SolUsdcObligationTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        user_accounts_owner,
+        test_obligation,
+        ..
+    } = setup().await;
+
+    let mut instruction = set_obligation_owner(
+        spl_token_lending::id(),
+        test_obligation.pubkey,
+        user_accounts_owner.pubkey(),
+        Keypair::new().pubkey(),
+    );
+    instruction.accounts[2].is_signer = false;
+
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidSigner as u32)
+        )
+    );
+}
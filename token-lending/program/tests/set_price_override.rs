@@ -0,0 +1,165 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{refresh_reserve, set_price_override},
+        math::Decimal,
+        processor::process_instruction,
+    },
+};
+
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+struct PriceOverrideTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    lending_market: TestLendingMarket,
+    usdc_oracle: TestOracle,
+    usdc_test_reserve: TestReserve,
+}
+
+async fn setup() -> PriceOverrideTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (banks_client, payer, _recent_blockhash) = test.start().await;
+
+    PriceOverrideTest {
+        banks_client,
+        payer,
+        lending_market,
+        usdc_oracle,
+        usdc_test_reserve,
+    }
+}
+
+// Sets the price override and refreshes the reserve in the same transaction
+async fn set_and_refresh(
+    test: &mut PriceOverrideTest,
+    price_override: Option<Decimal>,
+    owner: &Keypair,
+) -> Result<(), TransactionError> {
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_price_override(
+                spl_token_lending::id(),
+                price_override,
+                test.usdc_test_reserve.pubkey,
+                test.lending_market.pubkey,
+                owner.pubkey(),
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                test.usdc_test_reserve.pubkey,
+                test.usdc_oracle.price_pubkey,
+            ),
+        ],
+        Some(&test.payer.pubkey()),
+    );
+    let recent_blockhash = test.banks_client.get_latest_blockhash().await.unwrap();
+    transaction.sign(&[&test.payer, owner], recent_blockhash);
+    test.banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = setup().await;
+    let owner = test.lending_market.owner.insecure_clone();
+
+    let price_override = Decimal::from(2u64);
+    assert_ne!(price_override, test.usdc_oracle.price);
+    set_and_refresh(&mut test, Some(price_override), &owner)
+        .await
+        .unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(usdc_reserve.price_override);
+    assert_eq!(usdc_reserve.liquidity.market_price, price_override);
+    assert_eq!(
+        usdc_reserve.emergency_price_override(),
+        Some(price_override)
+    );
+
+    // clearing the override goes back to the oracle price on refresh
+    set_and_refresh(&mut test, None, &owner).await.unwrap();
+
+    let usdc_reserve = test
+        .usdc_test_reserve
+        .get_state(&mut test.banks_client)
+        .await;
+    assert!(!usdc_reserve.price_override);
+    assert_eq!(usdc_reserve.liquidity.market_price, test.usdc_oracle.price);
+    assert_eq!(usdc_reserve.emergency_price_override(), None);
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = setup().await;
+    let invalid_owner = Keypair::new();
+
+    assert_eq!(
+        set_and_refresh(&mut test, Some(Decimal::from(2u64)), &invalid_owner)
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_zero_price() {
+    let mut test = setup().await;
+    let owner = test.lending_market.owner.insecure_clone();
+
+    assert_eq!(
+        set_and_refresh(&mut test, Some(Decimal::zero()), &owner)
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAmount as u32)
+        )
+    );
+}
@@ -0,0 +1,188 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::{
+        hash::Hash,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError, instruction::set_reserve_collateral_fee_receiver,
+        processor::process_instruction,
+    },
+};
+
+struct FeeReceiverTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    collateral_token_account: Pubkey,
+    usdc_token_account: Pubkey,
+}
+
+// Adds a USDC reserve along with a token account for its collateral and a USDC
+// token account
+async fn setup() -> FeeReceiverTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 1_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let treasury_owner = Pubkey::new_unique();
+    let collateral_token_account = add_account_for_program(
+        &mut test,
+        &treasury_owner,
+        0,
+        &usdc_test_reserve.collateral_mint_pubkey,
+    );
+    let usdc_token_account =
+        add_account_for_program(&mut test, &treasury_owner, 0, &usdc_mint.pubkey);
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    FeeReceiverTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        collateral_token_account,
+        usdc_token_account,
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        collateral_token_account,
+        ..
+    } = setup().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_collateral_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            collateral_token_account,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        usdc_reserve.collateral.fee_receiver,
+        collateral_token_account
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        collateral_token_account,
+        ..
+    } = setup().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_collateral_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            collateral_token_account,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_mint() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        usdc_token_account,
+        ..
+    } = setup().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_collateral_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_token_account,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidTokenMint as u32)
+        )
+    );
+}
@@ -0,0 +1,240 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError, instruction::set_reserve_flash_loan_fee_receiver,
+        processor::process_instruction,
+    },
+};
+
+struct FeeReceiverTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    usdc_token_account: Pubkey,
+    sol_token_account: Pubkey,
+}
+
+// Adds a USDC reserve along with a USDC and a SOL token account
+async fn setup<F>(add_accounts: F) -> FeeReceiverTest
+where
+    F: FnOnce(&mut ProgramTest),
+{
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 1_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let treasury_owner = Pubkey::new_unique();
+    let usdc_token_account =
+        add_account_for_program(&mut test, &treasury_owner, 0, &usdc_mint.pubkey);
+    let sol_token_account =
+        add_account_for_program(&mut test, &treasury_owner, 0, &spl_token::native_mint::id());
+
+    add_accounts(&mut test);
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    FeeReceiverTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        usdc_token_account,
+        sol_token_account,
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        usdc_token_account,
+        ..
+    } = setup(|_| {}).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_flash_loan_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_token_account,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        usdc_reserve.liquidity.flash_loan_fee_receiver,
+        usdc_token_account
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.fee_receiver,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        usdc_token_account,
+        ..
+    } = setup(|_| {}).await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_flash_loan_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_token_account,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_mint() {
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        sol_token_account,
+        ..
+    } = setup(|_| {}).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_flash_loan_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            sol_token_account,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidTokenMint as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_not_a_token_account() {
+    let invalid_receiver = Pubkey::new_unique();
+    let FeeReceiverTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        usdc_test_reserve,
+        ..
+    } = setup(|test| {
+        test.add_account(
+            invalid_receiver,
+            Account {
+                lamports: u32::MAX as u64,
+                owner: Pubkey::new_unique(),
+                ..Account::default()
+            },
+        );
+    })
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_flash_loan_fee_receiver(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            invalid_receiver,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidTokenOwner as u32)
+        )
+    );
+}
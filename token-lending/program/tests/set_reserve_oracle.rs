@@ -0,0 +1,240 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::pubkey::Pubkey,
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token_lending::{
+        error::LendingError, instruction::set_reserve_oracle, math::Decimal,
+        processor::process_instruction, state::OracleKind,
+    },
+};
+
+struct OracleTest {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    lending_market: TestLendingMarket,
+    sol_test_reserve: TestReserve,
+}
+
+// Adds a SOL reserve priced by Pyth, with the given oracle accounts added
+// alongside it
+async fn setup<F>(add_oracle_accounts: F) -> OracleTest
+where
+    F: FnOnce(&mut ProgramTest),
+{
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 100 * LAMPORTS_TO_SOL,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    add_oracle_accounts(&mut test);
+
+    let (banks_client, payer, recent_blockhash) = test.start().await;
+
+    OracleTest {
+        banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        sol_test_reserve,
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let aggregator_pubkey = Pubkey::new_unique();
+    let OracleTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        sol_test_reserve,
+    } = setup(|test| {
+        // Set SOL price to $25
+        add_switchboard_oracle(test, aggregator_pubkey, Decimal::from(25u64), 0);
+    })
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_oracle(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            aggregator_pubkey,
+            aggregator_pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.liquidity.oracle_kind, OracleKind::Switchboard);
+    assert_eq!(sol_reserve.liquidity.oracle_pubkey, aggregator_pubkey);
+    assert_eq!(sol_reserve.liquidity.market_price, Decimal::from(25u64));
+    assert!(sol_reserve.last_update.stale);
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let aggregator_pubkey = Pubkey::new_unique();
+    let OracleTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        sol_test_reserve,
+    } = setup(|test| {
+        add_switchboard_oracle(test, aggregator_pubkey, Decimal::from(25u64), 0);
+    })
+    .await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_oracle(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            aggregator_pubkey,
+            aggregator_pubkey,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_oracle_program() {
+    // Owned by neither the Pyth nor the Switchboard program
+    let aggregator_pubkey = Pubkey::new_unique();
+    let OracleTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        sol_test_reserve,
+    } = setup(|test| {
+        test.add_account(
+            aggregator_pubkey,
+            Account {
+                lamports: u32::MAX as u64,
+                owner: Pubkey::new_unique(),
+                ..Account::default()
+            },
+        );
+    })
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_oracle(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            aggregator_pubkey,
+            aggregator_pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_switchboard_aggregator_mismatch() {
+    let aggregator_pubkey = Pubkey::new_unique();
+    let other_aggregator_pubkey = Pubkey::new_unique();
+    let OracleTest {
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        lending_market,
+        sol_test_reserve,
+    } = setup(|test| {
+        add_switchboard_oracle(test, aggregator_pubkey, Decimal::from(25u64), 0);
+        add_switchboard_oracle(test, other_aggregator_pubkey, Decimal::from(25u64), 0);
+    })
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_oracle(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            other_aggregator_pubkey,
+            aggregator_pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
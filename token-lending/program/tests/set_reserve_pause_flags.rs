@@ -0,0 +1,523 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_reserve_liquidity, liquidate_obligation,
+            refresh_obligation, repay_obligation_liquidity, set_reserve_pause_flags,
+            withdraw_obligation_collateral,
+        },
+        processor::process_instruction,
+        state::{
+            INITIAL_COLLATERAL_RATIO, RESERVE_PAUSE_ALL, RESERVE_PAUSE_BORROWS,
+            RESERVE_PAUSE_DEPOSITS, RESERVE_PAUSE_LIQUIDATIONS, RESERVE_PAUSE_WITHDRAWALS,
+        },
+    },
+};
+
+// 100 SOL collateral
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 100 SOL * 80% LTV -> 80 SOL * 20 USDC -> 1600 USDC borrow
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_600 * FRACTIONAL_TO_USDC;
+// 1600 USDC * 50% -> 800 USDC liquidation
+const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 2;
+
+struct PauseTest {
+    lending_market: TestLendingMarket,
+    sol_test_reserve: TestReserve,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+    user_transfer_authority: Keypair,
+}
+
+// Pauses the SOL collateral and USDC borrow reserves of an unhealthy
+// obligation, then runs the instructions in the same transaction
+async fn process_paused<F>(
+    sol_pause_flags: u8,
+    usdc_pause_flags: u8,
+    instructions: F,
+) -> Result<(), TransactionError>
+where
+    F: FnOnce(&PauseTest) -> Vec<Instruction>,
+{
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.liquidation_threshold = 80;
+    reserve_config.liquidation_bonus = 10;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let pause_test = PauseTest {
+        lending_market,
+        sol_test_reserve,
+        usdc_test_reserve,
+        test_obligation,
+        user_transfer_authority: Keypair::new(),
+    };
+
+    let mut all_instructions = vec![
+        set_reserve_pause_flags(
+            spl_token_lending::id(),
+            sol_pause_flags,
+            pause_test.sol_test_reserve.pubkey,
+            pause_test.lending_market.pubkey,
+            pause_test.lending_market.owner.pubkey(),
+        ),
+        set_reserve_pause_flags(
+            spl_token_lending::id(),
+            usdc_pause_flags,
+            pause_test.usdc_test_reserve.pubkey,
+            pause_test.lending_market.pubkey,
+            pause_test.lending_market.owner.pubkey(),
+        ),
+        approve(
+            &spl_token::id(),
+            &pause_test.usdc_test_reserve.user_liquidity_pubkey,
+            &pause_test.user_transfer_authority.pubkey(),
+            &user_accounts_owner.pubkey(),
+            &[],
+            USDC_BORROW_AMOUNT_FRACTIONAL,
+        )
+        .unwrap(),
+    ];
+    all_instructions.extend(instructions(&pause_test));
+
+    let mut transaction = Transaction::new_with_payer(&all_instructions, Some(&payer.pubkey()));
+
+    // only sign with the keys the instructions require
+    let signer_keys = transaction.message.signer_keys();
+    let signers: Vec<&Keypair> = [
+        &payer,
+        &pause_test.lending_market.owner,
+        &user_accounts_owner,
+        &pause_test.user_transfer_authority,
+    ]
+    .into_iter()
+    .filter(|keypair| signer_keys.contains(&&keypair.pubkey()))
+    .collect();
+    transaction.sign(&signers, recent_blockhash);
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+fn liquidate(pause_test: &PauseTest) -> Vec<Instruction> {
+    vec![
+        refresh_obligation(
+            spl_token_lending::id(),
+            pause_test.test_obligation.pubkey,
+            vec![
+                pause_test.sol_test_reserve.pubkey,
+                pause_test.usdc_test_reserve.pubkey,
+            ],
+        ),
+        liquidate_obligation(
+            spl_token_lending::id(),
+            USDC_LIQUIDATION_AMOUNT_FRACTIONAL,
+            pause_test.usdc_test_reserve.user_liquidity_pubkey,
+            pause_test.sol_test_reserve.user_collateral_pubkey,
+            pause_test.usdc_test_reserve.pubkey,
+            pause_test.usdc_test_reserve.liquidity_supply_pubkey,
+            pause_test.sol_test_reserve.pubkey,
+            pause_test.sol_test_reserve.collateral_supply_pubkey,
+            pause_test.test_obligation.pubkey,
+            pause_test.lending_market.pubkey,
+            pause_test.user_transfer_authority.pubkey(),
+            None,
+        ),
+    ]
+}
+
+fn action_paused(instruction_index: u8) -> TransactionError {
+    TransactionError::InstructionError(
+        instruction_index,
+        InstructionError::Custom(LendingError::ActionPaused as u32),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(initial_reserve.pause_flags, 0);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_pause_flags(
+            spl_token_lending::id(),
+            RESERVE_PAUSE_DEPOSITS | RESERVE_PAUSE_BORROWS,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        reserve.pause_flags,
+        RESERVE_PAUSE_DEPOSITS | RESERVE_PAUSE_BORROWS
+    );
+    assert_eq!(reserve.config, initial_reserve.config);
+    assert_eq!(reserve.liquidity, initial_reserve.liquidity);
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_pause_flags(
+            spl_token_lending::id(),
+            RESERVE_PAUSE_ALL,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_pause_flags() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_pause_flags(
+            spl_token_lending::id(),
+            RESERVE_PAUSE_ALL + 1,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_paused() {
+    let deposit = |pause_test: &PauseTest| {
+        vec![deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            FRACTIONAL_TO_USDC,
+            None,
+            pause_test.usdc_test_reserve.user_liquidity_pubkey,
+            pause_test.usdc_test_reserve.user_collateral_pubkey,
+            pause_test.usdc_test_reserve.pubkey,
+            pause_test.usdc_test_reserve.liquidity_supply_pubkey,
+            pause_test.usdc_test_reserve.collateral_mint_pubkey,
+            pause_test.lending_market.pubkey,
+            pause_test.user_transfer_authority.pubkey(),
+        )]
+    };
+
+    // every other flag leaves deposits enabled
+    assert_eq!(
+        process_paused(0, RESERVE_PAUSE_ALL & !RESERVE_PAUSE_DEPOSITS, deposit).await,
+        Ok(())
+    );
+    assert_eq!(
+        process_paused(0, RESERVE_PAUSE_DEPOSITS, deposit).await,
+        Err(action_paused(3))
+    );
+}
+
+#[tokio::test]
+async fn test_borrow_paused() {
+    let borrow = |pause_test: &PauseTest| {
+        vec![
+            refresh_obligation(
+                spl_token_lending::id(),
+                pause_test.test_obligation.pubkey,
+                vec![
+                    pause_test.sol_test_reserve.pubkey,
+                    pause_test.usdc_test_reserve.pubkey,
+                ],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                FRACTIONAL_TO_USDC,
+                None,
+                pause_test.usdc_test_reserve.liquidity_supply_pubkey,
+                pause_test.usdc_test_reserve.user_liquidity_pubkey,
+                pause_test.usdc_test_reserve.pubkey,
+                pause_test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                pause_test.test_obligation.pubkey,
+                pause_test.lending_market.pubkey,
+                pause_test.test_obligation.owner,
+                vec![pause_test.sol_test_reserve.pubkey],
+                None,
+                None,
+            ),
+        ]
+    };
+
+    assert_eq!(
+        process_paused(0, RESERVE_PAUSE_BORROWS, borrow).await,
+        Err(action_paused(4))
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_paused() {
+    let withdraw = |pause_test: &PauseTest| {
+        vec![
+            refresh_obligation(
+                spl_token_lending::id(),
+                pause_test.test_obligation.pubkey,
+                vec![
+                    pause_test.sol_test_reserve.pubkey,
+                    pause_test.usdc_test_reserve.pubkey,
+                ],
+            ),
+            withdraw_obligation_collateral(
+                spl_token_lending::id(),
+                LAMPORTS_TO_SOL,
+                pause_test.sol_test_reserve.collateral_supply_pubkey,
+                pause_test.sol_test_reserve.user_collateral_pubkey,
+                pause_test.sol_test_reserve.pubkey,
+                pause_test.test_obligation.pubkey,
+                pause_test.lending_market.pubkey,
+                pause_test.test_obligation.owner,
+            ),
+        ]
+    };
+
+    assert_eq!(
+        process_paused(RESERVE_PAUSE_WITHDRAWALS, 0, withdraw).await,
+        Err(action_paused(4))
+    );
+}
+
+#[tokio::test]
+async fn test_repay_cannot_be_paused() {
+    let repay = |pause_test: &PauseTest| {
+        vec![
+            refresh_obligation(
+                spl_token_lending::id(),
+                pause_test.test_obligation.pubkey,
+                vec![
+                    pause_test.sol_test_reserve.pubkey,
+                    pause_test.usdc_test_reserve.pubkey,
+                ],
+            ),
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                pause_test.usdc_test_reserve.user_liquidity_pubkey,
+                pause_test.usdc_test_reserve.liquidity_supply_pubkey,
+                pause_test.usdc_test_reserve.pubkey,
+                pause_test.test_obligation.pubkey,
+                pause_test.lending_market.pubkey,
+                pause_test.user_transfer_authority.pubkey(),
+            ),
+        ]
+    };
+
+    assert_eq!(
+        process_paused(RESERVE_PAUSE_ALL, RESERVE_PAUSE_ALL, repay).await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn test_liquidate_with_withdrawals_paused() {
+    // liquidators can still seize collateral from a reserve with withdrawals
+    // paused
+    let pause_flags = RESERVE_PAUSE_DEPOSITS | RESERVE_PAUSE_BORROWS | RESERVE_PAUSE_WITHDRAWALS;
+    assert_eq!(
+        process_paused(pause_flags, pause_flags, liquidate).await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn test_liquidate_paused() {
+    assert_eq!(
+        process_paused(RESERVE_PAUSE_LIQUIDATIONS, 0, liquidate).await,
+        Err(action_paused(4))
+    );
+    assert_eq!(
+        process_paused(0, RESERVE_PAUSE_LIQUIDATIONS, liquidate).await,
+        Err(action_paused(4))
+    );
+}
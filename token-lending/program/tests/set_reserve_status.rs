@@ -0,0 +1,463 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::instruction::approve,
+    spl_token_lending::{
+        error::LendingError,
+        instruction::{
+            borrow_obligation_liquidity, deposit_reserve_liquidity, liquidate_obligation,
+            redeem_reserve_collateral, refresh_obligation, repay_obligation_liquidity,
+            set_reserve_status,
+        },
+        processor::process_instruction,
+        state::{ReserveStatus, INITIAL_COLLATERAL_RATIO},
+    },
+};
+
+// 100 SOL collateral
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+// 100 SOL * 80% LTV -> 80 SOL * 20 USDC -> 1600 USDC borrow
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_600 * FRACTIONAL_TO_USDC;
+// 1600 USDC * 50% -> 800 USDC liquidation
+const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = USDC_BORROW_AMOUNT_FRACTIONAL / 2;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+const USDC_REDEEM_COLLATERAL_AMOUNT: u64 = FRACTIONAL_TO_USDC * INITIAL_COLLATERAL_RATIO;
+
+struct StatusTest {
+    lending_market: TestLendingMarket,
+    sol_test_reserve: TestReserve,
+    usdc_test_reserve: TestReserve,
+    test_obligation: TestObligation,
+    user_transfer_authority: Keypair,
+}
+
+// Sets the status of the SOL collateral and USDC borrow reserves of an
+// unhealthy obligation, then runs the instructions in the same transaction
+async fn process_with_status<F>(
+    sol_status: ReserveStatus,
+    usdc_status: ReserveStatus,
+    instructions: F,
+) -> Result<(), TransactionError>
+where
+    F: FnOnce(&StatusTest) -> Vec<Instruction>,
+{
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.liquidation_threshold = 80;
+    reserve_config.liquidation_bonus = 10;
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            collateral_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL * INITIAL_COLLATERAL_RATIO,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let status_test = StatusTest {
+        lending_market,
+        sol_test_reserve,
+        usdc_test_reserve,
+        test_obligation,
+        user_transfer_authority: Keypair::new(),
+    };
+
+    let mut all_instructions = vec![
+        set_reserve_status(
+            spl_token_lending::id(),
+            sol_status,
+            status_test.sol_test_reserve.pubkey,
+            status_test.lending_market.pubkey,
+            status_test.lending_market.owner.pubkey(),
+        ),
+        set_reserve_status(
+            spl_token_lending::id(),
+            usdc_status,
+            status_test.usdc_test_reserve.pubkey,
+            status_test.lending_market.pubkey,
+            status_test.lending_market.owner.pubkey(),
+        ),
+        approve(
+            &spl_token::id(),
+            &status_test.usdc_test_reserve.user_liquidity_pubkey,
+            &status_test.user_transfer_authority.pubkey(),
+            &user_accounts_owner.pubkey(),
+            &[],
+            USDC_BORROW_AMOUNT_FRACTIONAL,
+        )
+        .unwrap(),
+        approve(
+            &spl_token::id(),
+            &status_test.usdc_test_reserve.user_collateral_pubkey,
+            &status_test.user_transfer_authority.pubkey(),
+            &user_accounts_owner.pubkey(),
+            &[],
+            USDC_REDEEM_COLLATERAL_AMOUNT,
+        )
+        .unwrap(),
+    ];
+    all_instructions.extend(instructions(&status_test));
+
+    let mut transaction = Transaction::new_with_payer(&all_instructions, Some(&payer.pubkey()));
+
+    // only sign with the keys the instructions require
+    let signer_keys = transaction.message.signer_keys();
+    let signers: Vec<&Keypair> = [
+        &payer,
+        &status_test.lending_market.owner,
+        &user_accounts_owner,
+        &status_test.user_transfer_authority,
+    ]
+    .into_iter()
+    .filter(|keypair| signer_keys.contains(&&keypair.pubkey()))
+    .collect();
+    transaction.sign(&signers, recent_blockhash);
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+fn deposit(status_test: &StatusTest) -> Vec<Instruction> {
+    vec![deposit_reserve_liquidity(
+        spl_token_lending::id(),
+        FRACTIONAL_TO_USDC,
+        None,
+        status_test.usdc_test_reserve.user_liquidity_pubkey,
+        status_test.usdc_test_reserve.user_collateral_pubkey,
+        status_test.usdc_test_reserve.pubkey,
+        status_test.usdc_test_reserve.liquidity_supply_pubkey,
+        status_test.usdc_test_reserve.collateral_mint_pubkey,
+        status_test.lending_market.pubkey,
+        status_test.user_transfer_authority.pubkey(),
+    )]
+}
+
+fn borrow(status_test: &StatusTest) -> Vec<Instruction> {
+    vec![
+        refresh_obligation(
+            spl_token_lending::id(),
+            status_test.test_obligation.pubkey,
+            vec![
+                status_test.sol_test_reserve.pubkey,
+                status_test.usdc_test_reserve.pubkey,
+            ],
+        ),
+        borrow_obligation_liquidity(
+            spl_token_lending::id(),
+            FRACTIONAL_TO_USDC,
+            None,
+            status_test.usdc_test_reserve.liquidity_supply_pubkey,
+            status_test.usdc_test_reserve.user_liquidity_pubkey,
+            status_test.usdc_test_reserve.pubkey,
+            status_test.usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            status_test.test_obligation.pubkey,
+            status_test.lending_market.pubkey,
+            status_test.test_obligation.owner,
+            vec![status_test.sol_test_reserve.pubkey],
+            None,
+            None,
+        ),
+    ]
+}
+
+fn repay(status_test: &StatusTest) -> Vec<Instruction> {
+    vec![
+        refresh_obligation(
+            spl_token_lending::id(),
+            status_test.test_obligation.pubkey,
+            vec![
+                status_test.sol_test_reserve.pubkey,
+                status_test.usdc_test_reserve.pubkey,
+            ],
+        ),
+        repay_obligation_liquidity(
+            spl_token_lending::id(),
+            USDC_BORROW_AMOUNT_FRACTIONAL,
+            status_test.usdc_test_reserve.user_liquidity_pubkey,
+            status_test.usdc_test_reserve.liquidity_supply_pubkey,
+            status_test.usdc_test_reserve.pubkey,
+            status_test.test_obligation.pubkey,
+            status_test.lending_market.pubkey,
+            status_test.user_transfer_authority.pubkey(),
+        ),
+    ]
+}
+
+fn redeem(status_test: &StatusTest) -> Vec<Instruction> {
+    vec![redeem_reserve_collateral(
+        spl_token_lending::id(),
+        USDC_REDEEM_COLLATERAL_AMOUNT,
+        None,
+        status_test.usdc_test_reserve.user_collateral_pubkey,
+        status_test.usdc_test_reserve.user_liquidity_pubkey,
+        status_test.usdc_test_reserve.pubkey,
+        status_test.usdc_test_reserve.collateral_mint_pubkey,
+        status_test.usdc_test_reserve.liquidity_supply_pubkey,
+        status_test.lending_market.pubkey,
+        status_test.user_transfer_authority.pubkey(),
+    )]
+}
+
+fn liquidate(status_test: &StatusTest) -> Vec<Instruction> {
+    vec![
+        refresh_obligation(
+            spl_token_lending::id(),
+            status_test.test_obligation.pubkey,
+            vec![
+                status_test.sol_test_reserve.pubkey,
+                status_test.usdc_test_reserve.pubkey,
+            ],
+        ),
+        liquidate_obligation(
+            spl_token_lending::id(),
+            USDC_LIQUIDATION_AMOUNT_FRACTIONAL,
+            status_test.usdc_test_reserve.user_liquidity_pubkey,
+            status_test.sol_test_reserve.user_collateral_pubkey,
+            status_test.usdc_test_reserve.pubkey,
+            status_test.usdc_test_reserve.liquidity_supply_pubkey,
+            status_test.sol_test_reserve.pubkey,
+            status_test.sol_test_reserve.collateral_supply_pubkey,
+            status_test.test_obligation.pubkey,
+            status_test.lending_market.pubkey,
+            status_test.user_transfer_authority.pubkey(),
+            None,
+        ),
+    ]
+}
+
+fn reserve_deprecated(instruction_index: u8) -> TransactionError {
+    TransactionError::InstructionError(
+        instruction_index,
+        InstructionError::Custom(LendingError::ReserveDeprecated as u32),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(initial_reserve.status, ReserveStatus::Active);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_status(
+            spl_token_lending::id(),
+            ReserveStatus::Deprecated,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(reserve.status, ReserveStatus::Deprecated);
+    assert_eq!(reserve.config, initial_reserve.config);
+    assert_eq!(reserve.liquidity, initial_reserve.liquidity);
+    assert_eq!(reserve.pause_flags, initial_reserve.pause_flags);
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_status(
+            spl_token_lending::id(),
+            ReserveStatus::Deprecated,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_active() {
+    use ReserveStatus::Active;
+
+    assert_eq!(process_with_status(Active, Active, deposit).await, Ok(()));
+    assert_eq!(process_with_status(Active, Active, redeem).await, Ok(()));
+}
+
+#[tokio::test]
+async fn test_deprecated() {
+    use ReserveStatus::{Active, Deprecated};
+
+    assert_eq!(
+        process_with_status(Active, Deprecated, deposit).await,
+        Err(reserve_deprecated(4))
+    );
+    assert_eq!(
+        process_with_status(Active, Deprecated, borrow).await,
+        Err(reserve_deprecated(5))
+    );
+
+    // existing positions can still be unwound
+    assert_eq!(process_with_status(Active, Deprecated, repay).await, Ok(()));
+    assert_eq!(
+        process_with_status(Active, Deprecated, redeem).await,
+        Ok(())
+    );
+    assert_eq!(
+        process_with_status(Deprecated, Deprecated, liquidate).await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn test_obsolete() {
+    use ReserveStatus::{Active, Obsolete};
+
+    assert_eq!(
+        process_with_status(Active, Obsolete, deposit).await,
+        Err(reserve_deprecated(4))
+    );
+    assert_eq!(
+        process_with_status(Active, Obsolete, borrow).await,
+        Err(reserve_deprecated(5))
+    );
+    assert_eq!(
+        process_with_status(Active, Obsolete, redeem).await,
+        Err(reserve_deprecated(4))
+    );
+
+    assert_eq!(process_with_status(Active, Obsolete, repay).await, Ok(()));
+    assert_eq!(
+        process_with_status(Obsolete, Obsolete, liquidate).await,
+        Ok(())
+    );
+}
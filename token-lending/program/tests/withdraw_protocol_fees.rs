@@ -0,0 +1,221 @@
+#![allow(clippy::arithmetic_side_effects)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{program_pack::Pack, pubkey::Pubkey},
+    solana_program_test::*,
+    solana_sdk::{
+        account::AccountSharedData,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::state::Account as Token,
+    spl_token_lending::{
+        error::LendingError, instruction::withdraw_protocol_fees, processor::process_instruction,
+    },
+};
+
+const FEE_AMOUNT_FRACTIONAL: u64 = 10 * FRACTIONAL_TO_USDC;
+
+struct WithdrawFeesTest {
+    context: ProgramTestContext,
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    usdc_token_account: Pubkey,
+    sol_token_account: Pubkey,
+}
+
+// Adds a USDC reserve whose fee receiver holds accrued fees, along with a USDC
+// and a SOL token account
+async fn setup() -> WithdrawFeesTest {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 1_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let treasury_owner = Pubkey::new_unique();
+    let usdc_token_account =
+        add_account_for_program(&mut test, &treasury_owner, 0, &usdc_mint.pubkey);
+    let sol_token_account =
+        add_account_for_program(&mut test, &treasury_owner, 0, &spl_token::native_mint::id());
+
+    let mut context = test.start_with_context().await;
+
+    let mut fee_receiver_account = context
+        .banks_client
+        .get_account(usdc_test_reserve.liquidity_fee_receiver_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut fee_receiver = Token::unpack(&fee_receiver_account.data).unwrap();
+    fee_receiver.amount = FEE_AMOUNT_FRACTIONAL;
+    Token::pack(fee_receiver, &mut fee_receiver_account.data).unwrap();
+    context.set_account(
+        &usdc_test_reserve.liquidity_fee_receiver_pubkey,
+        &AccountSharedData::from(fee_receiver_account),
+    );
+
+    WithdrawFeesTest {
+        context,
+        lending_market,
+        usdc_test_reserve,
+        usdc_token_account,
+        sol_token_account,
+    }
+}
+
+async fn withdraw(
+    test: &mut WithdrawFeesTest,
+    amount: u64,
+    fee_receiver: Pubkey,
+    destination: Pubkey,
+    invalid_owner: Option<Keypair>,
+) -> Result<(), TransactionError> {
+    let lending_market_owner = invalid_owner.as_ref().unwrap_or(&test.lending_market.owner);
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw_protocol_fees(
+            spl_token_lending::id(),
+            amount,
+            test.usdc_test_reserve.pubkey,
+            fee_receiver,
+            destination,
+            test.lending_market.pubkey,
+            lending_market_owner.pubkey(),
+        )],
+        Some(&test.context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test.context.payer, lending_market_owner],
+        test.context.last_blockhash,
+    );
+    test.context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+#[tokio::test]
+async fn test_partial_withdraw() {
+    let mut test = setup().await;
+    let fee_receiver = test.usdc_test_reserve.liquidity_fee_receiver_pubkey;
+    let destination = test.usdc_token_account;
+
+    assert_eq!(
+        withdraw(
+            &mut test,
+            FEE_AMOUNT_FRACTIONAL / 4,
+            fee_receiver,
+            destination,
+            None,
+        )
+        .await,
+        Ok(())
+    );
+
+    let banks_client = &mut test.context.banks_client;
+    assert_eq!(
+        get_token_balance(banks_client, fee_receiver).await,
+        FEE_AMOUNT_FRACTIONAL - FEE_AMOUNT_FRACTIONAL / 4
+    );
+    assert_eq!(
+        get_token_balance(banks_client, destination).await,
+        FEE_AMOUNT_FRACTIONAL / 4
+    );
+}
+
+#[tokio::test]
+async fn test_full_withdraw() {
+    let mut test = setup().await;
+    let fee_receiver = test.usdc_test_reserve.liquidity_fee_receiver_pubkey;
+    let destination = test.usdc_token_account;
+
+    assert_eq!(
+        withdraw(&mut test, u64::MAX, fee_receiver, destination, None).await,
+        Ok(())
+    );
+
+    let banks_client = &mut test.context.banks_client;
+    assert_eq!(get_token_balance(banks_client, fee_receiver).await, 0);
+    assert_eq!(
+        get_token_balance(banks_client, destination).await,
+        FEE_AMOUNT_FRACTIONAL
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_destination_mint() {
+    let mut test = setup().await;
+    let fee_receiver = test.usdc_test_reserve.liquidity_fee_receiver_pubkey;
+    let destination = test.sol_token_account;
+
+    assert_eq!(
+        withdraw(&mut test, u64::MAX, fee_receiver, destination, None).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidTokenMint as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = setup().await;
+    let fee_receiver = test.usdc_test_reserve.liquidity_fee_receiver_pubkey;
+    let destination = test.usdc_token_account;
+
+    assert_eq!(
+        withdraw(
+            &mut test,
+            u64::MAX,
+            fee_receiver,
+            destination,
+            Some(Keypair::new()),
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_fee_receiver() {
+    let mut test = setup().await;
+    let liquidity_supply = test.usdc_test_reserve.liquidity_supply_pubkey;
+    let destination = test.usdc_token_account;
+
+    // the liquidity supply is also owned by the lending market authority
+    assert_eq!(
+        withdraw(&mut test, u64::MAX, liquidity_supply, destination, None).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        ))
+    );
+}